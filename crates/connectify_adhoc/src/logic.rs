@@ -20,6 +20,7 @@ use connectify_stripe::error::StripeError;
 #[cfg(feature = "stripe")]
 use connectify_stripe::logic::{
     create_checkout_session as stripe_create_checkout_session,
+    CheckoutMode,
     CreateCheckoutSessionRequest as StripeCreateCheckoutRequest,
     // CreateCheckoutSessionResponse as StripeCreateCheckoutResponse
 };
@@ -178,10 +179,9 @@ pub async fn initiate_adhoc_session_logic(
     }
 
     // 2. Find Price Tier
-    let price_tier = stripe_config
-        .price_tiers
-        .iter()
-        .find(|t| t.duration_minutes == request_data.duration_minutes)
+    let pricing_config = app_config.pricing.clone().unwrap_or_default();
+    let price_tier = pricing_config
+        .tier_for_duration(request_data.duration_minutes)
         .ok_or(AdhocSessionError::NoMatchingPriceTier(
             request_data.duration_minutes,
         ))?;
@@ -214,6 +214,10 @@ pub async fn initiate_adhoc_session_logic(
         fulfillment_type: "adhoc_gcal_twilio".to_string(), // New fulfillment type
         fulfillment_data,
         client_reference_id: Some("adhoc-{{CHECKOUT_SESSION_ID}}".to_string()), // Unique ref
+        mode: CheckoutMode::Payment,
+        price_id: None,
+        customer_email: None,
+        customer_name: None,
     };
 
     // 5. Create Stripe Checkout Session
@@ -229,7 +233,8 @@ pub async fn initiate_adhoc_session_logic(
 
     #[cfg(feature = "stripe")]
     let stripe_session_response =
-        stripe_create_checkout_session(&dynamic_stripe_config, stripe_request).await?;
+        stripe_create_checkout_session(&dynamic_stripe_config, &pricing_config, stripe_request)
+            .await?;
 
     #[cfg(feature = "stripe")]
     {
@@ -1,6 +1,7 @@
 // --- File: crates/connectify_adhoc/src/routes.rs ---
 use crate::handlers::{initiate_adhoc_session_handler, AdhocState};
 use axum::{routing::post, Router};
+use connectify_common::idempotency::IdempotencyLayer;
 use connectify_config::AppConfig;
 use std::sync::Arc;
 
@@ -18,7 +19,7 @@ pub fn routes(
     Router::new()
         .route(
             "/adhoc/initiate-session",
-            post(initiate_adhoc_session_handler),
+            post(initiate_adhoc_session_handler).layer(IdempotencyLayer::new()),
         )
         .with_state(adhoc_state)
 }
@@ -30,7 +31,7 @@ pub fn routes(config: Arc<AppConfig>) -> Router {
     Router::new()
         .route(
             "/adhoc/initiate-session",
-            post(initiate_adhoc_session_handler),
+            post(initiate_adhoc_session_handler).layer(IdempotencyLayer::new()),
         )
         .with_state(adhoc_state)
 }
@@ -0,0 +1,284 @@
+//! API key authentication middleware.
+//!
+//! This module provides a tower layer that validates the `X-Api-Key` header against the keys
+//! configured in the `api_keys` section of the `AppConfig`. Keys can be configured either in
+//! plain text (typically injected from the environment) or as a hex encoded SHA-256 digest,
+//! so that the configuration file does not need to contain the secret itself.
+//!
+//! On success, the caller's [`CallerIdentity`] is inserted into the request extensions,
+//! where handlers can pick it up as an extractor:
+//!
+//! ```ignore
+//! async fn handler(caller: CallerIdentity) -> String {
+//!     format!("Hello {}", caller.name)
+//! }
+//!
+//! let router = Router::new()
+//!     .route("/internal", post(handler))
+//!     .layer(ApiKeyAuthLayer::from_config(&config).expect("api_keys not configured"));
+//! ```
+
+use axum::{
+    body::Body,
+    extract::FromRequestParts,
+    http::{request::Parts, Request},
+    response::{IntoResponse, Response},
+};
+use connectify_config::{ApiKeysConfig, AppConfig};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use tracing::{debug, warn};
+
+use crate::error::ConnectifyError;
+use crate::webhook::constant_time_eq;
+
+pub use crate::rate_limit::API_KEY_HEADER;
+
+/// The identity of an authenticated caller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallerIdentity {
+    /// The configured name of the caller.
+    pub name: String,
+    /// The scopes granted to the caller.
+    pub scopes: Vec<String>,
+}
+
+impl CallerIdentity {
+    /// Check whether the caller has been granted a scope.
+    ///
+    /// Callers without any scopes are unrestricted.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.is_empty() || self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for CallerIdentity {
+    type Rejection = ConnectifyError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<CallerIdentity>()
+            .cloned()
+            .ok_or_else(|| ConnectifyError::AuthError("Caller is not authenticated".to_string()))
+    }
+}
+
+/// Hash an API key the way it is expected in `key_sha256`.
+pub fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// The set of API keys accepted by the authentication layer.
+#[derive(Debug, Default)]
+pub struct ApiKeyStore {
+    /// Pairs of SHA-256 key digest and the identity it grants.
+    keys: Vec<(String, CallerIdentity)>,
+}
+
+impl ApiKeyStore {
+    /// Build the key store from the configuration.
+    ///
+    /// Entries that have neither a `key` nor a `key_sha256` are skipped with a warning.
+    pub fn from_config(config: &ApiKeysConfig) -> Self {
+        let keys = config
+            .keys
+            .iter()
+            .filter_map(|entry| {
+                let digest = match (&entry.key_sha256, &entry.key) {
+                    (Some(digest), _) => digest.to_lowercase(),
+                    (None, Some(key)) if !key.is_empty() => hash_api_key(key),
+                    _ => {
+                        warn!(
+                            "API key '{}' has no key or key_sha256, ignoring",
+                            entry.name
+                        );
+                        return None;
+                    }
+                };
+                Some((
+                    digest,
+                    CallerIdentity {
+                        name: entry.name.clone(),
+                        scopes: entry.scopes.clone(),
+                    },
+                ))
+            })
+            .collect();
+        Self { keys }
+    }
+
+    /// Check whether the store contains any keys.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Authenticate an API key.
+    ///
+    /// # Returns
+    ///
+    /// The identity of the caller if the key is valid, `None` otherwise.
+    pub fn authenticate(&self, key: &str) -> Option<CallerIdentity> {
+        let digest = hash_api_key(key);
+        self.keys
+            .iter()
+            .find(|(expected, _)| constant_time_eq(expected.as_bytes(), digest.as_bytes()))
+            .map(|(_, identity)| identity.clone())
+    }
+}
+
+/// Tower layer that requires a valid `X-Api-Key` header.
+#[derive(Clone)]
+pub struct ApiKeyAuthLayer {
+    store: Arc<ApiKeyStore>,
+    required_scope: Option<String>,
+}
+
+impl ApiKeyAuthLayer {
+    /// Create a new layer accepting the keys in the given store.
+    pub fn new(store: ApiKeyStore) -> Self {
+        Self {
+            store: Arc::new(store),
+            required_scope: None,
+        }
+    }
+
+    /// Create a layer from the `api_keys` section of the configuration.
+    ///
+    /// Returns `None` if no API keys are configured.
+    pub fn from_config(config: &AppConfig) -> Option<Self> {
+        let store = ApiKeyStore::from_config(config.api_keys.as_ref()?);
+        if store.is_empty() {
+            return None;
+        }
+        Some(Self::new(store))
+    }
+
+    /// Additionally require the caller to have the given scope.
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.required_scope = Some(scope.into());
+        self
+    }
+}
+
+impl<S> Layer<S> for ApiKeyAuthLayer {
+    type Service = ApiKeyAuth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiKeyAuth {
+            inner,
+            store: self.store.clone(),
+            required_scope: self.required_scope.clone(),
+        }
+    }
+}
+
+/// Service created by [`ApiKeyAuthLayer`].
+#[derive(Clone)]
+pub struct ApiKeyAuth<S> {
+    inner: S,
+    store: Arc<ApiKeyStore>,
+    required_scope: Option<String>,
+}
+
+impl<S> ApiKeyAuth<S> {
+    fn authorize(&self, req: &Request<Body>) -> Result<CallerIdentity, ConnectifyError> {
+        let key = req
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| ConnectifyError::AuthError("Missing X-Api-Key header".to_string()))?;
+
+        let identity = self
+            .store
+            .authenticate(key)
+            .ok_or_else(|| ConnectifyError::AuthError("Invalid API key".to_string()))?;
+
+        if let Some(scope) = &self.required_scope {
+            if !identity.has_scope(scope) {
+                return Err(ConnectifyError::AuthError(format!(
+                    "API key '{}' is missing scope '{}'",
+                    identity.name, scope
+                )));
+            }
+        }
+        Ok(identity)
+    }
+}
+
+impl<S> Service<Request<Body>> for ApiKeyAuth<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        match self.authorize(&req) {
+            Ok(identity) => {
+                debug!("Authenticated API caller '{}'", identity.name);
+                req.extensions_mut().insert(identity);
+                Box::pin(self.inner.call(req))
+            }
+            Err(e) => {
+                warn!(
+                    "API key authentication failed for {}: {}",
+                    req.uri().path(),
+                    e
+                );
+                Box::pin(async move { Ok(e.into_response()) })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use connectify_config::ApiKeyEntry;
+
+    fn store() -> ApiKeyStore {
+        ApiKeyStore::from_config(&ApiKeysConfig {
+            keys: vec![
+                ApiKeyEntry {
+                    name: "stripe-webhook".to_string(),
+                    key: Some("plain-key".to_string()),
+                    key_sha256: None,
+                    scopes: vec!["fulfillment".to_string()],
+                },
+                ApiKeyEntry {
+                    name: "admin".to_string(),
+                    key: None,
+                    key_sha256: Some(hash_api_key("hashed-key")),
+                    scopes: vec![],
+                },
+            ],
+        })
+    }
+
+    #[test]
+    fn test_authenticate_plain_and_hashed_keys() {
+        let store = store();
+
+        let caller = store.authenticate("plain-key").unwrap();
+        assert_eq!(caller.name, "stripe-webhook");
+        assert!(caller.has_scope("fulfillment"));
+        assert!(!caller.has_scope("admin"));
+
+        let caller = store.authenticate("hashed-key").unwrap();
+        assert_eq!(caller.name, "admin");
+        assert!(caller.has_scope("admin"));
+
+        assert!(store.authenticate("wrong-key").is_none());
+    }
+}
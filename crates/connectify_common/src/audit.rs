@@ -0,0 +1,321 @@
+//! Structured audit logging for the Connectify application.
+//!
+//! Sensitive operations (cancellations, refunds, admin listings, ...) record an [`AuditEvent`]
+//! describing who did what to which resource, and whether it worked. Events are handed to all
+//! configured [`AuditSink`]s: by default they are logged with the `audit` tracing target, and
+//! the backend adds a database sink when a database is configured.
+//!
+//! ## Usage
+//!
+//! ```ignore
+//! async fn cancel_booking(actor: AuditActor, Path(event_id): Path<String>) -> ... {
+//!     let result = cancel(&event_id).await;
+//!     audit::record(
+//!         AuditEvent::new(actor, "booking.cancel", format!("gcal_event:{}", event_id))
+//!             .with_result(&result),
+//!     )
+//!     .await;
+//!     result
+//! }
+//! ```
+//!
+//! Recording never fails the request: sink errors are logged and counted in the error metrics.
+
+use axum::extract::{FromRequestParts, OptionalFromRequestParts};
+use axum::http::request::Parts;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+
+use crate::api_key::CallerIdentity;
+use crate::error::ConnectifyError;
+use crate::jwt::AuthClaims;
+use crate::request_id::current_request_id;
+use crate::services::BoxFuture;
+
+/// The actor of requests without any credentials.
+pub const ANONYMOUS_ACTOR: &str = "anonymous";
+
+/// Whether an audited operation succeeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+    /// The actor was not allowed to perform the operation.
+    Denied,
+}
+
+impl AuditOutcome {
+    /// The outcome as stored and logged, e.g. "success".
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditOutcome::Success => "success",
+            AuditOutcome::Failure => "failure",
+            AuditOutcome::Denied => "denied",
+        }
+    }
+}
+
+impl fmt::Display for AuditOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The caller of a request, as recorded in audit events.
+///
+/// Extracting it never fails: API key callers are `api_key:<name>`, users with a valid JWT
+/// are `user:<sub>`, and everyone else is [`ANONYMOUS_ACTOR`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditActor(pub String);
+
+impl fmt::Display for AuditActor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<AuditActor> for String {
+    fn from(actor: AuditActor) -> Self {
+        actor.0
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuditActor {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(identity) = parts.extensions.get::<CallerIdentity>() {
+            return Ok(AuditActor(format!("api_key:{}", identity.name)));
+        }
+        let claims =
+            <AuthClaims as OptionalFromRequestParts<S>>::from_request_parts(parts, state).await;
+        Ok(match claims {
+            Ok(Some(claims)) => AuditActor(format!("user:{}", claims.sub)),
+            _ => AuditActor(ANONYMOUS_ACTOR.to_string()),
+        })
+    }
+}
+
+/// A record of a sensitive operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    /// Unique ID of the event.
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    /// Who performed the operation, e.g. "user:42" or "api_key:frontend".
+    pub actor: String,
+    /// What was done, e.g. "booking.cancel".
+    pub action: String,
+    /// What it was done to, e.g. "gcal_event:abc123".
+    pub resource: String,
+    pub outcome: AuditOutcome,
+    /// The ID of the request that performed the operation.
+    pub request_id: Option<String>,
+    /// Additional details, e.g. query parameters or the error message.
+    #[serde(default)]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+impl AuditEvent {
+    /// Create a successful event for the current request.
+    pub fn new(
+        actor: impl Into<String>,
+        action: impl Into<String>,
+        resource: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            actor: actor.into(),
+            action: action.into(),
+            resource: resource.into(),
+            outcome: AuditOutcome::Success,
+            request_id: current_request_id(),
+            metadata: serde_json::Map::new(),
+        }
+    }
+
+    /// Set the outcome.
+    pub fn outcome(mut self, outcome: AuditOutcome) -> Self {
+        self.outcome = outcome;
+        self
+    }
+
+    /// Add a metadata entry.
+    pub fn with_metadata(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set the outcome from the result of the operation, recording the error on failure.
+    pub fn with_result<T, E: fmt::Display>(self, result: &Result<T, E>) -> Self {
+        match result {
+            Ok(_) => self.outcome(AuditOutcome::Success),
+            Err(e) => self
+                .outcome(AuditOutcome::Failure)
+                .with_metadata("error", e.to_string()),
+        }
+    }
+}
+
+/// A destination for audit events.
+pub trait AuditSink: Send + Sync {
+    /// A short name of the sink, used in logs and metrics.
+    fn name(&self) -> &'static str;
+
+    /// Store or forward an audit event.
+    fn record<'a>(&'a self, event: &'a AuditEvent) -> BoxFuture<'a, (), ConnectifyError>;
+}
+
+/// Logs audit events with the `audit` tracing target.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingAuditSink;
+
+impl AuditSink for TracingAuditSink {
+    fn name(&self) -> &'static str {
+        "tracing"
+    }
+
+    fn record<'a>(&'a self, event: &'a AuditEvent) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move {
+            info!(
+                target: "audit",
+                audit_id = %event.id,
+                actor = %event.actor,
+                action = %event.action,
+                resource = %event.resource,
+                outcome = %event.outcome,
+                request_id = event.request_id.as_deref().unwrap_or(""),
+                metadata = %serde_json::Value::Object(event.metadata.clone()),
+                "{} {} {}: {}",
+                event.actor,
+                event.action,
+                event.resource,
+                event.outcome
+            );
+            Ok(())
+        })
+    }
+}
+
+/// The globally configured audit sinks.
+static AUDIT_SINKS: Lazy<RwLock<Vec<Arc<dyn AuditSink>>>> =
+    Lazy::new(|| RwLock::new(vec![Arc::new(TracingAuditSink)]));
+
+/// Replace the audit sinks.
+pub fn configure_audit_sinks(sinks: Vec<Arc<dyn AuditSink>>) {
+    *AUDIT_SINKS.write().unwrap_or_else(|e| e.into_inner()) = sinks;
+}
+
+/// Add an audit sink to the configured ones.
+pub fn add_audit_sink(sink: Arc<dyn AuditSink>) {
+    AUDIT_SINKS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(sink);
+}
+
+/// Record an audit event in all configured sinks.
+pub async fn record(event: AuditEvent) {
+    let sinks = AUDIT_SINKS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    record_in(&sinks, &event).await;
+}
+
+async fn record_in(sinks: &[Arc<dyn AuditSink>], event: &AuditEvent) {
+    for sink in sinks {
+        if let Err(e) = sink.record(event).await {
+            warn!(
+                "Failed to record audit event {} ({}) in the {} sink: {}",
+                event.id,
+                event.action,
+                sink.name(),
+                e
+            );
+            crate::metrics::record_error("audit", sink.name());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemorySink(Mutex<Vec<AuditEvent>>);
+
+    impl AuditSink for MemorySink {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+
+        fn record<'a>(&'a self, event: &'a AuditEvent) -> BoxFuture<'a, (), ConnectifyError> {
+            Box::pin(async move {
+                self.0.lock().unwrap().push(event.clone());
+                Ok(())
+            })
+        }
+    }
+
+    struct FailingSink;
+
+    impl AuditSink for FailingSink {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        fn record<'a>(&'a self, _event: &'a AuditEvent) -> BoxFuture<'a, (), ConnectifyError> {
+            Box::pin(async { Err(ConnectifyError::InternalError("down".to_string())) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_in_all_sinks() {
+        let memory = Arc::new(MemorySink::default());
+        let sinks: Vec<Arc<dyn AuditSink>> = vec![Arc::new(FailingSink), memory.clone()];
+
+        let result: Result<(), &str> = Err("not found");
+        let event = AuditEvent::new("user:1", "booking.cancel", "gcal_event:abc")
+            .with_metadata("notify_attendees", true)
+            .with_result(&result);
+        record_in(&sinks, &event).await;
+
+        let recorded = memory.0.lock().unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].outcome, AuditOutcome::Failure);
+        assert_eq!(recorded[0].metadata["error"], "not found");
+        assert_eq!(recorded[0].metadata["notify_attendees"], true);
+    }
+
+    #[tokio::test]
+    async fn test_actor_extraction() {
+        let (mut parts, _) = Request::new(()).into_parts();
+        let actor = AuditActor::from_request_parts(&mut parts, &())
+            .await
+            .unwrap();
+        assert_eq!(actor.0, ANONYMOUS_ACTOR);
+
+        parts.extensions.insert(CallerIdentity {
+            name: "frontend".to_string(),
+            scopes: vec![],
+        });
+        let actor = AuditActor::from_request_parts(&mut parts, &())
+            .await
+            .unwrap();
+        assert_eq!(actor.0, "api_key:frontend");
+    }
+}
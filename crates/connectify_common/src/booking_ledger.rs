@@ -0,0 +1,322 @@
+//! Booking ledger for capacity-based group slots.
+//!
+//! Calendars with a capacity offer each slot to several customers, e.g. the seats of a group
+//! class. The calendar itself cannot tell how many seats of a slot are taken, so every booked
+//! seat is recorded in the ledger under the ID of its calendar event, and released again when
+//! the booking is cancelled.
+//!
+//! When several staff calendars share the bookings, the ledger also records which calendar
+//! each booking was assigned to, so the booking can be found in it later, and so the next one
+//! can go to the next or the least booked calendar.
+//!
+//! The ledger defaults to an in-memory one; the backend replaces it with the database
+//! repository via [`configure_booking_ledger`], so seats are counted across instances.
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// A seat booked in a group slot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeatBooking {
+    /// The ID of the calendar event of the booking
+    pub id: String,
+    pub calendar_id: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+}
+
+impl SeatBooking {
+    /// Whether the booked slot overlaps the time from `start` to `end`.
+    pub fn overlaps(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        self.start_time < end && start < self.end_time
+    }
+}
+
+/// The calendar a booking was assigned to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookingAssignment {
+    /// The ID of the calendar event of the booking
+    pub event_id: String,
+    pub calendar_id: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    /// When the booking was assigned
+    pub assigned_at: DateTime<Utc>,
+}
+
+/// Storage for booked seats and the calendars bookings were assigned to.
+pub trait BookingLedger: Send + Sync {
+    /// Record a booked seat.
+    fn record(&self, booking: SeatBooking) -> BoxFuture<'_, (), ConnectifyError>;
+
+    /// Look up a booked seat by the ID of its calendar event.
+    fn get<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Option<SeatBooking>, ConnectifyError>;
+
+    /// The seats booked in a calendar overlapping the time from `start` to `end`.
+    fn overlapping<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> BoxFuture<'a, Vec<SeatBooking>, ConnectifyError>;
+
+    /// Release a booked seat, e.g. when the booking is cancelled.
+    ///
+    /// # Returns
+    ///
+    /// Whether the seat was recorded.
+    fn release<'a>(&'a self, id: &'a str) -> BoxFuture<'a, bool, ConnectifyError>;
+
+    /// Record the calendar a booking was assigned to, replacing an earlier assignment.
+    fn assign(&self, assignment: BookingAssignment) -> BoxFuture<'_, (), ConnectifyError>;
+
+    /// Look up the assignment of a booking by the ID of its calendar event.
+    fn assignment<'a>(
+        &'a self,
+        event_id: &'a str,
+    ) -> BoxFuture<'a, Option<BookingAssignment>, ConnectifyError>;
+
+    /// The most recent assignment, if any.
+    fn latest_assignment(&self) -> BoxFuture<'_, Option<BookingAssignment>, ConnectifyError>;
+
+    /// The number of assigned bookings ending after `since`, by calendar.
+    fn upcoming_assignments(
+        &self,
+        since: DateTime<Utc>,
+    ) -> BoxFuture<'_, HashMap<String, usize>, ConnectifyError>;
+
+    /// Remove the assignment of a booking, e.g. when the booking is cancelled.
+    ///
+    /// # Returns
+    ///
+    /// Whether the booking was assigned.
+    fn unassign<'a>(&'a self, event_id: &'a str) -> BoxFuture<'a, bool, ConnectifyError>;
+}
+
+/// A [`BookingLedger`] keeping seats in memory, for single-instance deployments and tests.
+#[derive(Debug, Default)]
+pub struct InMemoryBookingLedger {
+    seats: Mutex<HashMap<String, SeatBooking>>,
+    assignments: Mutex<HashMap<String, BookingAssignment>>,
+}
+
+impl BookingLedger for InMemoryBookingLedger {
+    fn record(&self, booking: SeatBooking) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move {
+            self.seats
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(booking.id.clone(), booking);
+            Ok(())
+        })
+    }
+
+    fn get<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Option<SeatBooking>, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .seats
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(id)
+                .cloned())
+        })
+    }
+
+    fn overlapping<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> BoxFuture<'a, Vec<SeatBooking>, ConnectifyError> {
+        Box::pin(async move {
+            let seats = self.seats.lock().unwrap_or_else(|e| e.into_inner());
+            let mut overlapping: Vec<SeatBooking> = seats
+                .values()
+                .filter(|seat| seat.calendar_id == calendar_id && seat.overlaps(start, end))
+                .cloned()
+                .collect();
+            overlapping.sort_by_key(|seat| seat.start_time);
+            Ok(overlapping)
+        })
+    }
+
+    fn release<'a>(&'a self, id: &'a str) -> BoxFuture<'a, bool, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .seats
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(id)
+                .is_some())
+        })
+    }
+
+    fn assign(&self, assignment: BookingAssignment) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move {
+            self.assignments
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(assignment.event_id.clone(), assignment);
+            Ok(())
+        })
+    }
+
+    fn assignment<'a>(
+        &'a self,
+        event_id: &'a str,
+    ) -> BoxFuture<'a, Option<BookingAssignment>, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .assignments
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(event_id)
+                .cloned())
+        })
+    }
+
+    fn latest_assignment(&self) -> BoxFuture<'_, Option<BookingAssignment>, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .assignments
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .values()
+                .max_by_key(|assignment| assignment.assigned_at)
+                .cloned())
+        })
+    }
+
+    fn upcoming_assignments(
+        &self,
+        since: DateTime<Utc>,
+    ) -> BoxFuture<'_, HashMap<String, usize>, ConnectifyError> {
+        Box::pin(async move {
+            let mut counts = HashMap::new();
+            for assignment in self
+                .assignments
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .values()
+                .filter(|assignment| assignment.end_time > since)
+            {
+                *counts.entry(assignment.calendar_id.clone()).or_default() += 1;
+            }
+            Ok(counts)
+        })
+    }
+
+    fn unassign<'a>(&'a self, event_id: &'a str) -> BoxFuture<'a, bool, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .assignments
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(event_id)
+                .is_some())
+        })
+    }
+}
+
+/// The global ledger returned by [`booking_ledger`].
+static BOOKING_LEDGER: Lazy<RwLock<Arc<dyn BookingLedger>>> =
+    Lazy::new(|| RwLock::new(Arc::new(InMemoryBookingLedger::default())));
+
+/// Replace the ledger used for booked seats.
+pub fn configure_booking_ledger(ledger: Arc<dyn BookingLedger>) {
+    *BOOKING_LEDGER.write().unwrap_or_else(|e| e.into_inner()) = ledger;
+}
+
+/// The ledger used for booked seats.
+pub fn booking_ledger() -> Arc<dyn BookingLedger> {
+    BOOKING_LEDGER
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn seat(id: &str, hour: u32) -> SeatBooking {
+        SeatBooking {
+            id: id.to_string(),
+            calendar_id: "classes".to_string(),
+            start_time: Utc.with_ymd_and_hms(2025, 5, 15, hour, 0, 0).unwrap(),
+            end_time: Utc.with_ymd_and_hms(2025, 5, 15, hour + 1, 0, 0).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_ledger() {
+        let ledger = InMemoryBookingLedger::default();
+        ledger.record(seat("a", 10)).await.unwrap();
+        ledger.record(seat("b", 10)).await.unwrap();
+        ledger.record(seat("c", 12)).await.unwrap();
+
+        let ten = Utc.with_ymd_and_hms(2025, 5, 15, 10, 0, 0).unwrap();
+        let eleven = Utc.with_ymd_and_hms(2025, 5, 15, 11, 0, 0).unwrap();
+        let seats = ledger.overlapping("classes", ten, eleven).await.unwrap();
+        assert_eq!(seats.len(), 2);
+        assert!(ledger
+            .overlapping("other", ten, eleven)
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(ledger.get("c").await.unwrap(), Some(seat("c", 12)));
+        assert!(ledger.release("a").await.unwrap());
+        assert!(!ledger.release("a").await.unwrap());
+        assert_eq!(
+            ledger.overlapping("classes", ten, eleven).await.unwrap(),
+            vec![seat("b", 10)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_assignments() {
+        let ledger = InMemoryBookingLedger::default();
+        let assignment = |event_id: &str, calendar_id: &str, day: u32| BookingAssignment {
+            event_id: event_id.to_string(),
+            calendar_id: calendar_id.to_string(),
+            start_time: Utc.with_ymd_and_hms(2025, 5, day, 10, 0, 0).unwrap(),
+            end_time: Utc.with_ymd_and_hms(2025, 5, day, 11, 0, 0).unwrap(),
+            assigned_at: Utc.with_ymd_and_hms(2025, 5, 1, day, 0, 0).unwrap(),
+        };
+        assert_eq!(ledger.latest_assignment().await.unwrap(), None);
+
+        ledger.assign(assignment("a", "anna", 10)).await.unwrap();
+        ledger.assign(assignment("b", "ben", 12)).await.unwrap();
+        ledger.assign(assignment("c", "anna", 14)).await.unwrap();
+        assert_eq!(
+            ledger.assignment("b").await.unwrap(),
+            Some(assignment("b", "ben", 12))
+        );
+        assert_eq!(
+            ledger.latest_assignment().await.unwrap(),
+            Some(assignment("c", "anna", 14))
+        );
+
+        // Past bookings don't count
+        let since = Utc.with_ymd_and_hms(2025, 5, 11, 0, 0, 0).unwrap();
+        let counts = ledger.upcoming_assignments(since).await.unwrap();
+        assert_eq!(counts.get("anna"), Some(&1));
+        assert_eq!(counts.get("ben"), Some(&1));
+
+        assert!(ledger.unassign("c").await.unwrap());
+        assert!(!ledger.unassign("c").await.unwrap());
+        assert_eq!(ledger.assignment("c").await.unwrap(), None);
+        assert_eq!(
+            ledger.latest_assignment().await.unwrap(),
+            Some(assignment("b", "ben", 12))
+        );
+    }
+}
@@ -0,0 +1,424 @@
+//! Caching for the Connectify application.
+//!
+//! The [`Cache`] trait stores byte values with a time to live. Two backends are provided:
+//!
+//! - [`MemoryCache`], an in-process cache based on moka, used by default.
+//! - `RedisCache` (with the `redis` feature), shared between backend instances. The backend
+//!   uses it when `cache.redis_url` is configured.
+//!
+//! Cached data is an optimization: [`get_json`] and [`set_json`] log and swallow cache
+//! failures, so a broken cache never fails a request.
+//!
+//! ## Usage
+//!
+//! ```ignore
+//! let key = format!("stripe:session:{}", session_id);
+//! if let Some(session) = cache::get_json::<Session>(&*cache::cache(), &key).await {
+//!     return Ok(session);
+//! }
+//! let session = fetch_session(session_id).await?;
+//! cache::set_json(&*cache::cache(), &key, &session, Duration::from_secs(60)).await;
+//! ```
+
+use moka::Expiry;
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// Default maximum number of entries of the in-memory cache.
+pub const DEFAULT_MAX_ENTRIES: u64 = 10_000;
+
+/// A key-value cache with per-entry expiry.
+pub trait Cache: Send + Sync {
+    /// A short name of the backend, used in logs and metrics.
+    fn name(&self) -> &'static str;
+
+    /// Get a value, or `None` if it is missing or expired.
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Vec<u8>>, ConnectifyError>;
+
+    /// Store a value for the given time.
+    fn set<'a>(
+        &'a self,
+        key: &'a str,
+        value: Vec<u8>,
+        ttl: Duration,
+    ) -> BoxFuture<'a, (), ConnectifyError>;
+
+    /// The remaining time to live of a value, or `None` if it is missing or expired.
+    fn ttl<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Duration>, ConnectifyError>;
+
+    /// Remove a value.
+    fn invalidate<'a>(&'a self, key: &'a str) -> BoxFuture<'a, (), ConnectifyError>;
+
+    /// Remove all values whose key starts with `prefix`.
+    fn invalidate_prefix<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, (), ConnectifyError>;
+}
+
+#[derive(Clone)]
+struct MemoryEntry {
+    value: Vec<u8>,
+    expires_at: Instant,
+}
+
+struct MemoryExpiry;
+
+impl Expiry<String, MemoryEntry> for MemoryExpiry {
+    fn expire_after_create(
+        &self,
+        _key: &String,
+        value: &MemoryEntry,
+        created_at: Instant,
+    ) -> Option<Duration> {
+        Some(value.expires_at.saturating_duration_since(created_at))
+    }
+
+    fn expire_after_update(
+        &self,
+        _key: &String,
+        value: &MemoryEntry,
+        updated_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        Some(value.expires_at.saturating_duration_since(updated_at))
+    }
+}
+
+/// A [`Cache`] keeping values in memory, for single-instance deployments and tests.
+pub struct MemoryCache {
+    entries: moka::future::Cache<String, MemoryEntry>,
+}
+
+impl MemoryCache {
+    /// Create a cache holding at most `max_entries` values.
+    pub fn new(max_entries: u64) -> Self {
+        Self {
+            entries: moka::future::Cache::builder()
+                .max_capacity(max_entries)
+                .expire_after(MemoryExpiry)
+                .support_invalidation_closures()
+                .build(),
+        }
+    }
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ENTRIES)
+    }
+}
+
+impl Cache for MemoryCache {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Vec<u8>>, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .entries
+                .get(key)
+                .await
+                .filter(|entry| entry.expires_at > Instant::now())
+                .map(|entry| entry.value))
+        })
+    }
+
+    fn set<'a>(
+        &'a self,
+        key: &'a str,
+        value: Vec<u8>,
+        ttl: Duration,
+    ) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move {
+            let entry = MemoryEntry {
+                value,
+                expires_at: Instant::now() + ttl,
+            };
+            self.entries.insert(key.to_string(), entry).await;
+            Ok(())
+        })
+    }
+
+    fn ttl<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Duration>, ConnectifyError> {
+        Box::pin(async move {
+            let now = Instant::now();
+            Ok(self
+                .entries
+                .get(key)
+                .await
+                .filter(|entry| entry.expires_at > now)
+                .map(|entry| entry.expires_at - now))
+        })
+    }
+
+    fn invalidate<'a>(&'a self, key: &'a str) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move {
+            self.entries.invalidate(key).await;
+            Ok(())
+        })
+    }
+
+    fn invalidate_prefix<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move {
+            let prefix = prefix.to_string();
+            self.entries
+                .invalidate_entries_if(move |key, _| key.starts_with(&prefix))
+                .map_err(|e| ConnectifyError::InternalError(e.to_string()))?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use redis_cache::RedisCache;
+
+#[cfg(feature = "redis")]
+mod redis_cache {
+    use super::*;
+    use redis::aio::ConnectionManager;
+    use redis::AsyncCommands;
+
+    fn redis_error(e: redis::RedisError) -> ConnectifyError {
+        crate::error::external_service_error("redis", e)
+    }
+
+    /// A [`Cache`] backed by Redis, shared between backend instances.
+    #[derive(Clone)]
+    pub struct RedisCache {
+        connection: ConnectionManager,
+        /// Prefix added to all keys, so the Redis instance can be shared.
+        namespace: String,
+    }
+
+    impl RedisCache {
+        /// Connect to Redis, e.g. `redis://localhost:6379`.
+        pub async fn connect(url: &str) -> Result<Self, ConnectifyError> {
+            let client = redis::Client::open(url)
+                .map_err(|e| ConnectifyError::ConfigError(format!("Invalid Redis URL: {}", e)))?;
+            let connection = ConnectionManager::new(client).await.map_err(redis_error)?;
+            Ok(Self {
+                connection,
+                namespace: "connectify:".to_string(),
+            })
+        }
+
+        /// Set the prefix added to all keys (default: "connectify:").
+        pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+            self.namespace = namespace.into();
+            self
+        }
+
+        fn key(&self, key: &str) -> String {
+            format!("{}{}", self.namespace, key)
+        }
+    }
+
+    impl Cache for RedisCache {
+        fn name(&self) -> &'static str {
+            "redis"
+        }
+
+        fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Vec<u8>>, ConnectifyError> {
+            Box::pin(async move {
+                let mut connection = self.connection.clone();
+                connection.get(self.key(key)).await.map_err(redis_error)
+            })
+        }
+
+        fn set<'a>(
+            &'a self,
+            key: &'a str,
+            value: Vec<u8>,
+            ttl: Duration,
+        ) -> BoxFuture<'a, (), ConnectifyError> {
+            Box::pin(async move {
+                let mut connection = self.connection.clone();
+                // Redis rejects a zero expiry
+                let millis = ttl.as_millis().max(1) as u64;
+                connection
+                    .pset_ex(self.key(key), value, millis)
+                    .await
+                    .map_err(redis_error)
+            })
+        }
+
+        fn ttl<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Duration>, ConnectifyError> {
+            Box::pin(async move {
+                let mut connection = self.connection.clone();
+                // Negative values mean the key is missing or has no expiry
+                let millis: i64 = connection.pttl(self.key(key)).await.map_err(redis_error)?;
+                Ok((millis >= 0).then(|| Duration::from_millis(millis as u64)))
+            })
+        }
+
+        fn invalidate<'a>(&'a self, key: &'a str) -> BoxFuture<'a, (), ConnectifyError> {
+            Box::pin(async move {
+                let mut connection = self.connection.clone();
+                connection.del(self.key(key)).await.map_err(redis_error)
+            })
+        }
+
+        fn invalidate_prefix<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, (), ConnectifyError> {
+            Box::pin(async move {
+                let mut connection = self.connection.clone();
+                let pattern = format!("{}*", self.key(prefix));
+                let keys: Vec<String> = {
+                    let mut iter = connection
+                        .scan_match::<_, String>(pattern)
+                        .await
+                        .map_err(redis_error)?;
+                    let mut keys = Vec::new();
+                    while let Some(key) = iter.next_item().await {
+                        keys.push(key);
+                    }
+                    keys
+                };
+                if !keys.is_empty() {
+                    let mut connection = self.connection.clone();
+                    connection.del::<_, ()>(keys).await.map_err(redis_error)?;
+                }
+                Ok(())
+            })
+        }
+    }
+}
+
+/// The globally configured cache.
+static CACHE: Lazy<RwLock<Arc<dyn Cache>>> =
+    Lazy::new(|| RwLock::new(Arc::new(MemoryCache::default())));
+
+/// Replace the global cache, e.g. with a Redis cache shared between instances.
+pub fn configure_cache(cache: Arc<dyn Cache>) {
+    *CACHE.write().unwrap_or_else(|e| e.into_inner()) = cache;
+}
+
+/// The global cache.
+pub fn cache() -> Arc<dyn Cache> {
+    CACHE.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Get a JSON value from the cache.
+///
+/// Cache failures and undecodable values are logged and treated as a miss.
+pub async fn get_json<T: DeserializeOwned>(cache: &dyn Cache, key: &str) -> Option<T> {
+    match cache.get(key).await {
+        Ok(Some(bytes)) => match serde_json::from_slice(&bytes) {
+            Ok(value) => {
+                debug!("Cache hit for {}", key);
+                Some(value)
+            }
+            Err(e) => {
+                warn!("Ignoring undecodable cache entry {}: {}", key, e);
+                None
+            }
+        },
+        Ok(None) => None,
+        Err(e) => {
+            warn!(
+                "Failed to read {} from the {} cache: {}",
+                key,
+                cache.name(),
+                e
+            );
+            crate::metrics::record_error("cache", cache.name());
+            None
+        }
+    }
+}
+
+/// Store a value as JSON in the cache.
+///
+/// Cache failures are logged and otherwise ignored.
+pub async fn set_json<T: Serialize>(cache: &dyn Cache, key: &str, value: &T, ttl: Duration) {
+    let bytes = match serde_json::to_vec(value) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to serialize cache entry {}: {}", key, e);
+            return;
+        }
+    };
+    if let Err(e) = cache.set(key, bytes, ttl).await {
+        warn!(
+            "Failed to write {} to the {} cache: {}",
+            key,
+            cache.name(),
+            e
+        );
+        crate::metrics::record_error("cache", cache.name());
+    }
+}
+
+/// Remove all values whose key starts with `prefix`, logging failures.
+pub async fn invalidate_prefix(cache: &dyn Cache, prefix: &str) {
+    if let Err(e) = cache.invalidate_prefix(prefix).await {
+        warn!(
+            "Failed to invalidate {}* in the {} cache: {}",
+            prefix,
+            cache.name(),
+            e
+        );
+        crate::metrics::record_error("cache", cache.name());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_cache() {
+        let cache = MemoryCache::default();
+        cache
+            .set("a:1", b"one".to_vec(), Duration::from_secs(60))
+            .await
+            .unwrap();
+        cache
+            .set("a:2", b"two".to_vec(), Duration::from_secs(60))
+            .await
+            .unwrap();
+        cache
+            .set("b:1", b"three".to_vec(), Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        assert_eq!(cache.get("a:1").await.unwrap(), Some(b"one".to_vec()));
+        let ttl = cache.ttl("a:1").await.unwrap().unwrap();
+        assert!(ttl > Duration::from_secs(59) && ttl <= Duration::from_secs(60));
+
+        cache.invalidate("a:1").await.unwrap();
+        assert_eq!(cache.get("a:1").await.unwrap(), None);
+
+        cache.invalidate_prefix("a:").await.unwrap();
+        assert_eq!(cache.get("a:2").await.unwrap(), None);
+        assert_eq!(cache.get("b:1").await.unwrap(), Some(b"three".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_expiry() {
+        let cache = MemoryCache::default();
+        cache
+            .set("short", b"value".to_vec(), Duration::from_millis(10))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(cache.get("short").await.unwrap(), None);
+        assert_eq!(cache.ttl("short").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_json_helpers() {
+        let cache = MemoryCache::default();
+        set_json(&cache, "json", &vec![1, 2, 3], Duration::from_secs(60)).await;
+        assert_eq!(
+            get_json::<Vec<u32>>(&cache, "json").await,
+            Some(vec![1, 2, 3])
+        );
+        assert_eq!(get_json::<String>(&cache, "json").await, None);
+    }
+}
@@ -0,0 +1,95 @@
+//! Time source for the Connectify application.
+//!
+//! Logic that depends on the current time (available slots, webhook timestamp tolerance,
+//! token expiry) takes a [`Clock`] instead of calling `Utc::now()` directly. Production code
+//! uses [`SystemClock`]; tests use a [`MockClock`] and control "now" explicitly:
+//!
+//! ```ignore
+//! let clock = Arc::new(MockClock::new(Utc.with_ymd_and_hms(2025, 5, 5, 9, 0, 0).unwrap()));
+//! let verifier = JwtVerifier::from_config(&config)?.with_clock(clock.clone());
+//! clock.advance(Duration::hours(2)); // Tokens issued before are now expired
+//! ```
+
+use chrono::{DateTime, Duration, Utc};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// A source of the current time.
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// The current time.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// The current time as a Unix timestamp in seconds.
+    fn timestamp(&self) -> i64 {
+        self.now().timestamp()
+    }
+}
+
+/// A shared clock, as stored in handler state.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The system's wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Create a shared [`SystemClock`].
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// A clock that only moves when told to, for tests.
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl MockClock {
+    /// Create a clock standing at `now`.
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    /// Set the current time.
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = now;
+    }
+
+    /// Move the current time forward (or backward, with a negative duration).
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += duration;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_mock_clock() {
+        let start = Utc.with_ymd_and_hms(2025, 5, 5, 9, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        assert_eq!(clock.now(), start);
+        assert_eq!(clock.timestamp(), start.timestamp());
+
+        clock.advance(Duration::minutes(90));
+        assert_eq!(clock.now(), start + Duration::minutes(90));
+
+        clock.set(start);
+        assert_eq!(clock.now(), start);
+    }
+}
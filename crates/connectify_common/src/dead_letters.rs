@@ -0,0 +1,307 @@
+//! Fulfillments that could not be delivered, kept for retrying.
+//!
+//! A payment webhook triggers its fulfillment (creating the booking) through an HTTP call. When
+//! that call fails, answering the provider with an error would make it redeliver the webhook for
+//! days, each time running into the same failure. Instead the fulfillment request is stored here
+//! as a dead letter and the webhook is acknowledged; a background job retries the dead letters
+//! with backoff, and admins can inspect and requeue them.
+//!
+//! The store defaults to an in-memory one; the backend replaces it with the database
+//! repository via [`configure_dead_letter_store`], so dead letters survive restarts.
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// Whether a dead letter still needs to be delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeadLetterStatus {
+    /// Waiting for its next retry.
+    Pending,
+    /// Delivered by a retry.
+    Delivered,
+    /// Out of retries; only an admin requeue delivers it.
+    Exhausted,
+}
+
+impl DeadLetterStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeadLetterStatus::Pending => "pending",
+            DeadLetterStatus::Delivered => "delivered",
+            DeadLetterStatus::Exhausted => "exhausted",
+        }
+    }
+}
+
+impl fmt::Display for DeadLetterStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DeadLetterStatus {
+    type Err = ConnectifyError;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "pending" => Ok(DeadLetterStatus::Pending),
+            "delivered" => Ok(DeadLetterStatus::Delivered),
+            "exhausted" => Ok(DeadLetterStatus::Exhausted),
+            other => Err(ConnectifyError::ValidationError(format!(
+                "Unknown dead letter status: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A fulfillment request that failed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// The payment provider whose webhook triggered the fulfillment, e.g. "stripe".
+    pub provider: String,
+    /// The provider's payment or session ID; a payment has at most one dead letter.
+    pub payment_id: String,
+    /// The fulfillment type, e.g. "gcal_booking".
+    pub fulfillment_type: String,
+    /// The JSON body of the fulfillment request.
+    pub payload: String,
+    pub status: DeadLetterStatus,
+    /// How often delivery was attempted, including the original call.
+    pub attempts: u32,
+    /// The error of the last failed attempt.
+    pub last_error: Option<String>,
+    /// When the next retry is due.
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl DeadLetter {
+    /// A dead letter for a fulfillment whose first call failed, due for a retry at
+    /// `next_attempt_at`.
+    pub fn failed(
+        provider: impl Into<String>,
+        payment_id: impl Into<String>,
+        fulfillment_type: impl Into<String>,
+        payload: impl Into<String>,
+        error: impl Into<String>,
+        next_attempt_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            provider: provider.into(),
+            payment_id: payment_id.into(),
+            fulfillment_type: fulfillment_type.into(),
+            payload: payload.into(),
+            status: DeadLetterStatus::Pending,
+            attempts: 1,
+            last_error: Some(error.into()),
+            next_attempt_at,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Storage for dead letters.
+pub trait DeadLetterStore: Send + Sync {
+    /// Store a dead letter, replacing an earlier one of the payment.
+    fn save(&self, letter: DeadLetter) -> BoxFuture<'_, (), ConnectifyError>;
+
+    /// Get the dead letter of a payment.
+    fn get<'a>(
+        &'a self,
+        provider: &'a str,
+        payment_id: &'a str,
+    ) -> BoxFuture<'a, Option<DeadLetter>, ConnectifyError>;
+
+    /// Pending dead letters of a provider whose retry is due, oldest first.
+    fn due<'a>(
+        &'a self,
+        provider: &'a str,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<DeadLetter>, ConnectifyError>;
+
+    /// The most recent dead letters of a provider, optionally only those with a status.
+    fn list<'a>(
+        &'a self,
+        provider: &'a str,
+        status: Option<DeadLetterStatus>,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<DeadLetter>, ConnectifyError>;
+}
+
+/// A [`DeadLetterStore`] keeping dead letters in memory, for single-instance deployments and
+/// tests.
+#[derive(Debug, Default)]
+pub struct InMemoryDeadLetterStore {
+    letters: Mutex<HashMap<(String, String), DeadLetter>>,
+}
+
+impl DeadLetterStore for InMemoryDeadLetterStore {
+    fn save(&self, letter: DeadLetter) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move {
+            let key = (letter.provider.clone(), letter.payment_id.clone());
+            self.letters
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(key, letter);
+            Ok(())
+        })
+    }
+
+    fn get<'a>(
+        &'a self,
+        provider: &'a str,
+        payment_id: &'a str,
+    ) -> BoxFuture<'a, Option<DeadLetter>, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .letters
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(&(provider.to_string(), payment_id.to_string()))
+                .cloned())
+        })
+    }
+
+    fn due<'a>(
+        &'a self,
+        provider: &'a str,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<DeadLetter>, ConnectifyError> {
+        Box::pin(async move {
+            let letters = self.letters.lock().unwrap_or_else(|e| e.into_inner());
+            let mut due: Vec<DeadLetter> = letters
+                .values()
+                .filter(|letter| letter.provider == provider)
+                .filter(|letter| letter.status == DeadLetterStatus::Pending)
+                .filter(|letter| letter.next_attempt_at <= now)
+                .cloned()
+                .collect();
+            due.sort_by_key(|letter| letter.next_attempt_at);
+            due.truncate(limit);
+            Ok(due)
+        })
+    }
+
+    fn list<'a>(
+        &'a self,
+        provider: &'a str,
+        status: Option<DeadLetterStatus>,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<DeadLetter>, ConnectifyError> {
+        Box::pin(async move {
+            let letters = self.letters.lock().unwrap_or_else(|e| e.into_inner());
+            let mut listed: Vec<DeadLetter> = letters
+                .values()
+                .filter(|letter| letter.provider == provider)
+                .filter(|letter| status.is_none_or(|status| letter.status == status))
+                .cloned()
+                .collect();
+            listed.sort_by_key(|letter| std::cmp::Reverse(letter.created_at));
+            listed.truncate(limit);
+            Ok(listed)
+        })
+    }
+}
+
+/// The global store returned by [`dead_letter_store`].
+static DEAD_LETTER_STORE: Lazy<RwLock<Arc<dyn DeadLetterStore>>> =
+    Lazy::new(|| RwLock::new(Arc::new(InMemoryDeadLetterStore::default())));
+
+/// Replace the store used for dead letters.
+pub fn configure_dead_letter_store(store: Arc<dyn DeadLetterStore>) {
+    *DEAD_LETTER_STORE.write().unwrap_or_else(|e| e.into_inner()) = store;
+}
+
+/// The store used for dead letters.
+pub fn dead_letter_store() -> Arc<dyn DeadLetterStore> {
+    DEAD_LETTER_STORE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(second: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(second, 0).unwrap()
+    }
+
+    fn failed(payment_id: &str, second: i64) -> DeadLetter {
+        DeadLetter::failed(
+            "stripe",
+            payment_id,
+            "gcal_booking",
+            "{}",
+            "502 Bad Gateway",
+            at(second + 60),
+            at(second),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemoryDeadLetterStore::default();
+        store.save(failed("cs_1", 0)).await.unwrap();
+        store.save(failed("cs_2", 10)).await.unwrap();
+        let mut delivered = failed("cs_3", 20);
+        delivered.status = DeadLetterStatus::Delivered;
+        store.save(delivered.clone()).await.unwrap();
+
+        // Only pending letters whose retry is due, oldest first
+        let due = |second| store.due("stripe", at(second), 10);
+        assert!(due(59).await.unwrap().is_empty());
+        assert_eq!(due(60).await.unwrap(), vec![failed("cs_1", 0)]);
+        let payment_ids: Vec<String> = due(100)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|letter| letter.payment_id)
+            .collect();
+        assert_eq!(payment_ids, vec!["cs_1", "cs_2"]);
+        assert!(store.due("payrexx", at(100), 10).await.unwrap().is_empty());
+
+        // Saving again replaces the letter of the payment
+        let mut retried = failed("cs_1", 0);
+        retried.attempts = 2;
+        retried.next_attempt_at = at(300);
+        store.save(retried.clone()).await.unwrap();
+        assert_eq!(
+            store.get("stripe", "cs_1").await.unwrap(),
+            Some(retried.clone())
+        );
+        assert_eq!(store.get("stripe", "cs_4").await.unwrap(), None);
+
+        assert_eq!(
+            store
+                .list("stripe", Some(DeadLetterStatus::Delivered), 10)
+                .await
+                .unwrap(),
+            vec![delivered]
+        );
+        let all: Vec<String> = store
+            .list("stripe", None, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|letter| letter.payment_id)
+            .collect();
+        assert_eq!(all, vec!["cs_3", "cs_2", "cs_1"]);
+    }
+}
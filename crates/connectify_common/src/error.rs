@@ -2,10 +2,27 @@
 use std::fmt;
 use thiserror::Error;
 
+/// Details of a failed call to an external service.
+///
+/// Boxed in [`ConnectifyError::ExternalServiceError`] so that the error, and every `Result`
+/// carrying it, stays the size of a single `String`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalServiceFailure {
+    pub service_name: String,
+    pub message: String,
+}
+
+impl fmt::Display for ExternalServiceFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {}", self.service_name, self.message)
+    }
+}
+
 /// The base error type for all Connectify errors.
 ///
 /// This enum provides a common set of error variants that can be used across all crates.
 /// Each crate can extend this by implementing From<SpecificError> for ConnectifyError.
+/// Variants carry at most one `String`; larger payloads are boxed to keep the type small.
 #[derive(Error, Debug)]
 pub enum ConnectifyError {
     /// Error occurred during an HTTP request
@@ -33,11 +50,8 @@ pub enum ConnectifyError {
     DatabaseError(String),
 
     /// Error occurred during external service call
-    #[error("External service error: {service_name} - {message}")]
-    ExternalServiceError {
-        service_name: String,
-        message: String,
-    },
+    #[error("External service error: {0}")]
+    ExternalServiceError(Box<ExternalServiceFailure>),
 
     /// Error occurred due to a conflict (e.g., resource already exists)
     #[error("Conflict: {0}")]
@@ -82,7 +96,7 @@ impl HttpStatusCode for ConnectifyError {
             ConnectifyError::AuthError(_) => 401,
             ConnectifyError::ValidationError(_) => 400,
             ConnectifyError::DatabaseError(_) => 500,
-            ConnectifyError::ExternalServiceError { .. } => 502,
+            ConnectifyError::ExternalServiceError(_) => 502,
             ConnectifyError::ConflictError(_) => 409,
             ConnectifyError::NotFoundError(_) => 404,
             ConnectifyError::TimeoutError(_) => 504,
@@ -164,12 +178,30 @@ pub fn conflict<T: fmt::Display>(message: T) -> ConnectifyError {
 }
 
 pub fn external_service_error<T: fmt::Display>(service_name: &str, message: T) -> ConnectifyError {
-    ConnectifyError::ExternalServiceError {
+    ConnectifyError::ExternalServiceError(Box::new(ExternalServiceFailure {
         service_name: service_name.to_string(),
         message: message.to_string(),
-    }
+    }))
 }
 
 pub fn internal_error<T: fmt::Display>(message: T) -> ConnectifyError {
     ConnectifyError::InternalError(message.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_stays_small() {
+        // A String plus the discriminant; boxing keeps large variants from growing every Result
+        assert!(std::mem::size_of::<ConnectifyError>() <= 32);
+
+        let err = external_service_error("Stripe API", "Status: 500");
+        assert_eq!(err.status_code(), 502);
+        assert_eq!(
+            err.to_string(),
+            "External service error: Stripe API - Status: 500"
+        );
+    }
+}
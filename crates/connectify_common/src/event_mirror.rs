@@ -0,0 +1,309 @@
+//! Local mirror of calendar events.
+//!
+//! A sync job copies the confirmed and cancelled events of the calendars into the mirror,
+//! fetching only the changes since the last run with the calendar's incremental sync token.
+//! Reporting and conflict checks can then be answered from the mirror, e.g. while the calendar
+//! API is slow or down.
+//!
+//! The mirror defaults to an in-memory one; the backend replaces it with the database
+//! repository via [`configure_event_mirror`].
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// The status of a mirrored event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MirroredEventStatus {
+    Confirmed,
+    Cancelled,
+}
+
+impl MirroredEventStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MirroredEventStatus::Confirmed => "confirmed",
+            MirroredEventStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl fmt::Display for MirroredEventStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MirroredEventStatus {
+    type Err = ConnectifyError;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "confirmed" => Ok(MirroredEventStatus::Confirmed),
+            "cancelled" => Ok(MirroredEventStatus::Cancelled),
+            other => Err(ConnectifyError::ValidationError(format!(
+                "Unknown event status: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// An event of a calendar, as last seen by the sync job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MirroredEvent {
+    /// The ID of the calendar event
+    pub id: String,
+    pub calendar_id: String,
+    pub summary: Option<String>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub status: MirroredEventStatus,
+    /// When the event was last changed in the calendar
+    pub updated_at: DateTime<Utc>,
+}
+
+impl MirroredEvent {
+    /// Whether the event overlaps the time from `start` to `end`.
+    pub fn overlaps(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        self.start_time < end && start < self.end_time
+    }
+}
+
+/// Storage for mirrored events and the sync token of each calendar.
+pub trait EventMirror: Send + Sync {
+    /// Insert or replace events, matched by calendar and event ID.
+    fn upsert(&self, events: Vec<MirroredEvent>) -> BoxFuture<'_, (), ConnectifyError>;
+
+    /// Mark events as cancelled, e.g. deletions reported without their times.
+    ///
+    /// Events that aren't mirrored yet are ignored.
+    fn mark_cancelled<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        ids: Vec<String>,
+        updated_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, (), ConnectifyError>;
+
+    /// The mirrored events of a calendar overlapping the time from `start` to `end`,
+    /// confirmed and cancelled, sorted by start time.
+    fn overlapping<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> BoxFuture<'a, Vec<MirroredEvent>, ConnectifyError>;
+
+    /// The token to fetch the changes of a calendar since its last sync.
+    ///
+    /// `None` if the calendar was never synced, or its mirror was cleared.
+    fn sync_token<'a>(
+        &'a self,
+        calendar_id: &'a str,
+    ) -> BoxFuture<'a, Option<String>, ConnectifyError>;
+
+    /// Store the token returned by the last sync of a calendar.
+    fn set_sync_token<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        sync_token: String,
+    ) -> BoxFuture<'a, (), ConnectifyError>;
+
+    /// Drop the events and the sync token of a calendar, before syncing it from scratch.
+    fn clear<'a>(&'a self, calendar_id: &'a str) -> BoxFuture<'a, (), ConnectifyError>;
+}
+
+/// An [`EventMirror`] keeping events in memory, for single-instance deployments and tests.
+#[derive(Debug, Default)]
+pub struct InMemoryEventMirror {
+    events: Mutex<HashMap<(String, String), MirroredEvent>>,
+    sync_tokens: Mutex<HashMap<String, String>>,
+}
+
+impl EventMirror for InMemoryEventMirror {
+    fn upsert(&self, events: Vec<MirroredEvent>) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move {
+            let mut mirrored = self.events.lock().unwrap_or_else(|e| e.into_inner());
+            for event in events {
+                mirrored.insert((event.calendar_id.clone(), event.id.clone()), event);
+            }
+            Ok(())
+        })
+    }
+
+    fn mark_cancelled<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        ids: Vec<String>,
+        updated_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move {
+            let mut mirrored = self.events.lock().unwrap_or_else(|e| e.into_inner());
+            for id in ids {
+                if let Some(event) = mirrored.get_mut(&(calendar_id.to_string(), id)) {
+                    event.status = MirroredEventStatus::Cancelled;
+                    event.updated_at = updated_at;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn overlapping<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> BoxFuture<'a, Vec<MirroredEvent>, ConnectifyError> {
+        Box::pin(async move {
+            let mirrored = self.events.lock().unwrap_or_else(|e| e.into_inner());
+            let mut overlapping: Vec<MirroredEvent> = mirrored
+                .values()
+                .filter(|event| event.calendar_id == calendar_id && event.overlaps(start, end))
+                .cloned()
+                .collect();
+            overlapping.sort_by_key(|event| event.start_time);
+            Ok(overlapping)
+        })
+    }
+
+    fn sync_token<'a>(
+        &'a self,
+        calendar_id: &'a str,
+    ) -> BoxFuture<'a, Option<String>, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .sync_tokens
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(calendar_id)
+                .cloned())
+        })
+    }
+
+    fn set_sync_token<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        sync_token: String,
+    ) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move {
+            self.sync_tokens
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(calendar_id.to_string(), sync_token);
+            Ok(())
+        })
+    }
+
+    fn clear<'a>(&'a self, calendar_id: &'a str) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move {
+            self.events
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .retain(|(event_calendar_id, _), _| event_calendar_id != calendar_id);
+            self.sync_tokens
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(calendar_id);
+            Ok(())
+        })
+    }
+}
+
+/// The global mirror returned by [`event_mirror`].
+static EVENT_MIRROR: Lazy<RwLock<Arc<dyn EventMirror>>> =
+    Lazy::new(|| RwLock::new(Arc::new(InMemoryEventMirror::default())));
+
+/// Replace the mirror used for calendar events.
+pub fn configure_event_mirror(mirror: Arc<dyn EventMirror>) {
+    *EVENT_MIRROR.write().unwrap_or_else(|e| e.into_inner()) = mirror;
+}
+
+/// The mirror used for calendar events.
+pub fn event_mirror() -> Arc<dyn EventMirror> {
+    EVENT_MIRROR
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn event(id: &str, hour: u32) -> MirroredEvent {
+        MirroredEvent {
+            id: id.to_string(),
+            calendar_id: "primary".to_string(),
+            summary: Some("Consultation".to_string()),
+            start_time: Utc.with_ymd_and_hms(2025, 5, 15, hour, 0, 0).unwrap(),
+            end_time: Utc.with_ymd_and_hms(2025, 5, 15, hour + 1, 0, 0).unwrap(),
+            status: MirroredEventStatus::Confirmed,
+            updated_at: Utc.with_ymd_and_hms(2025, 5, 1, 0, 0, 0).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_mirror() {
+        let mirror = InMemoryEventMirror::default();
+        let day_start = Utc.with_ymd_and_hms(2025, 5, 15, 0, 0, 0).unwrap();
+        let day_end = Utc.with_ymd_and_hms(2025, 5, 16, 0, 0, 0).unwrap();
+
+        mirror
+            .upsert(vec![event("b", 14), event("a", 10)])
+            .await
+            .unwrap();
+        let mirrored = mirror
+            .overlapping("primary", day_start, day_end)
+            .await
+            .unwrap();
+        assert_eq!(mirrored, vec![event("a", 10), event("b", 14)]);
+
+        // Moved events replace the mirrored ones
+        mirror.upsert(vec![event("a", 12)]).await.unwrap();
+        let cancelled_at = Utc.with_ymd_and_hms(2025, 5, 2, 0, 0, 0).unwrap();
+        mirror
+            .mark_cancelled(
+                "primary",
+                vec!["b".to_string(), "unknown".to_string()],
+                cancelled_at,
+            )
+            .await
+            .unwrap();
+        let mirrored = mirror
+            .overlapping("primary", day_start, day_end)
+            .await
+            .unwrap();
+        assert_eq!(mirrored.len(), 2);
+        assert_eq!(mirrored[0], event("a", 12));
+        assert_eq!(mirrored[1].status, MirroredEventStatus::Cancelled);
+        assert_eq!(mirrored[1].updated_at, cancelled_at);
+
+        assert_eq!(mirror.sync_token("primary").await.unwrap(), None);
+        mirror
+            .set_sync_token("primary", "token".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            mirror.sync_token("primary").await.unwrap(),
+            Some("token".to_string())
+        );
+
+        mirror.clear("primary").await.unwrap();
+        assert_eq!(mirror.sync_token("primary").await.unwrap(), None);
+        assert!(mirror
+            .overlapping("primary", day_start, day_end)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
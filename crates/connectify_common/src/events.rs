@@ -0,0 +1,350 @@
+//! In-process event bus for the Connectify application.
+//!
+//! Crates announce what happened by publishing a [`DomainEvent`] ("booking created",
+//! "payment succeeded", ...) on the [`EVENT_BUS`]; other crates react by subscribing, without
+//! knowing about the publisher or calling it over HTTP. Delivery is asynchronous and
+//! best-effort: events published while nobody listens are dropped, and a subscriber that falls
+//! too far behind skips the oldest events (a warning is logged).
+//!
+//! ## Usage
+//!
+//! ```ignore
+//! // Publisher
+//! events::publish(DomainEvent::BookingCreated(BookingCreated { event_id, .. }));
+//!
+//! // Typed subscriber, only called for booking creations
+//! events::EVENT_BUS.on::<BookingCreated, _, _>("send_confirmation", |booking| async move {
+//!     send_confirmation(&booking).await
+//! });
+//! ```
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::error::ConnectifyError;
+
+/// Number of events buffered per subscriber before the oldest ones are skipped.
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// A calendar booking was created.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookingCreated {
+    pub event_id: String,
+    /// Start time in RFC 3339 format
+    pub start_time: String,
+    /// End time in RFC 3339 format
+    pub end_time: String,
+    pub summary: Option<String>,
+}
+
+/// A calendar booking was moved to another time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookingRescheduled {
+    pub event_id: String,
+    /// New start time in RFC 3339 format
+    pub start_time: String,
+    /// New end time in RFC 3339 format
+    pub end_time: String,
+}
+
+/// A calendar booking was cancelled or deleted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookingCancelled {
+    pub event_id: String,
+}
+
+/// A payment was completed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentSucceeded {
+    /// The payment provider, e.g. "stripe"
+    pub provider: String,
+    /// The provider's payment or session ID
+    pub payment_id: String,
+    /// Amount in the smallest currency unit
+    pub amount: Option<i64>,
+    pub currency: Option<String>,
+    /// Our reference for the payment, e.g. the client reference ID
+    pub reference: Option<String>,
+}
+
+/// A payment failed or was declined.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentFailed {
+    pub provider: String,
+    pub payment_id: String,
+    pub reason: Option<String>,
+}
+
+/// A payment was refunded in full or in part.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentRefunded {
+    /// The payment provider, e.g. "stripe"
+    pub provider: String,
+    /// The provider's payment ID
+    pub payment_id: String,
+    /// The provider's refund ID
+    pub refund_id: String,
+    /// Refunded amount in the smallest currency unit
+    pub amount: i64,
+    pub currency: String,
+    /// Why the payment was refunded, e.g. "booking_cancelled"
+    pub reason: Option<String>,
+    /// The calendar event ID of the refunded booking
+    pub booking_id: Option<String>,
+    /// Phone number of the client, notified by SMS
+    pub customer_phone: Option<String>,
+    /// User ID of the client, notified on their registered devices
+    pub customer_user_id: Option<String>,
+}
+
+/// A subscription was created, changed or ended.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubscriptionChanged {
+    /// The payment provider, e.g. "stripe"
+    pub provider: String,
+    /// The provider's subscription ID
+    pub subscription_id: String,
+    /// What happened, e.g. "created", "updated" or "deleted"
+    pub change: String,
+    /// The provider's status, e.g. "active", "past_due" or "canceled"
+    pub status: String,
+    /// The provider's customer ID
+    pub customer: Option<String>,
+    /// The provider's ID of the subscribed price
+    pub price_id: Option<String>,
+    /// End of the current billing period as a Unix timestamp
+    pub current_period_end: Option<i64>,
+    /// Our reference for the subscription, e.g. the client reference ID
+    pub reference: Option<String>,
+}
+
+/// A notification (push, SMS, email) could not be delivered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationFailed {
+    /// The channel, e.g. "firebase" or "twilio_sms"
+    pub channel: String,
+    pub recipient: String,
+    pub error: String,
+}
+
+/// Events published on the event bus.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum DomainEvent {
+    BookingCreated(BookingCreated),
+    BookingRescheduled(BookingRescheduled),
+    BookingCancelled(BookingCancelled),
+    PaymentSucceeded(PaymentSucceeded),
+    PaymentFailed(PaymentFailed),
+    PaymentRefunded(PaymentRefunded),
+    SubscriptionChanged(SubscriptionChanged),
+    NotificationFailed(NotificationFailed),
+}
+
+impl DomainEvent {
+    /// A short name of the event type, e.g. for logs and metrics.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DomainEvent::BookingCreated(_) => "booking_created",
+            DomainEvent::BookingRescheduled(_) => "booking_rescheduled",
+            DomainEvent::BookingCancelled(_) => "booking_cancelled",
+            DomainEvent::PaymentSucceeded(_) => "payment_succeeded",
+            DomainEvent::PaymentFailed(_) => "payment_failed",
+            DomainEvent::PaymentRefunded(_) => "payment_refunded",
+            DomainEvent::SubscriptionChanged(_) => "subscription_changed",
+            DomainEvent::NotificationFailed(_) => "notification_failed",
+        }
+    }
+}
+
+/// An event type that typed subscribers can listen for.
+pub trait EventType: Clone + Send + 'static {
+    /// Extract this event type from a domain event, if it matches.
+    fn from_event(event: &DomainEvent) -> Option<Self>;
+}
+
+macro_rules! impl_event_type {
+    ($($variant:ident),* $(,)?) => {
+        $(
+            impl EventType for $variant {
+                fn from_event(event: &DomainEvent) -> Option<Self> {
+                    match event {
+                        DomainEvent::$variant(inner) => Some(inner.clone()),
+                        _ => None,
+                    }
+                }
+            }
+
+            impl From<$variant> for DomainEvent {
+                fn from(event: $variant) -> Self {
+                    DomainEvent::$variant(event)
+                }
+            }
+        )*
+    };
+}
+
+impl_event_type!(
+    BookingCreated,
+    BookingRescheduled,
+    BookingCancelled,
+    PaymentSucceeded,
+    PaymentFailed,
+    PaymentRefunded,
+    SubscriptionChanged,
+    NotificationFailed,
+);
+
+impl EventType for DomainEvent {
+    fn from_event(event: &DomainEvent) -> Option<Self> {
+        Some(event.clone())
+    }
+}
+
+/// An async publish/subscribe bus for [`DomainEvent`]s.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<DomainEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl EventBus {
+    /// Create a bus buffering up to `capacity` events per subscriber.
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    /// Publish an event to all current subscribers.
+    ///
+    /// # Returns
+    ///
+    /// The number of subscribers the event was delivered to.
+    pub fn publish(&self, event: impl Into<DomainEvent>) -> usize {
+        let event = event.into();
+        debug!("Publishing {} event", event.name());
+        self.sender.send(event).unwrap_or(0)
+    }
+
+    /// Subscribe to all events, to be received from the returned channel.
+    pub fn subscribe(&self) -> broadcast::Receiver<DomainEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Run a handler for every event of type `E`, in a background task.
+    ///
+    /// Handler errors are logged and do not stop the subscription. The task ends when the
+    /// bus is dropped or the returned handle is aborted.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the subscriber, used in logs
+    /// * `handler` - Called with each matching event, one at a time
+    pub fn on<E, F, Fut>(&self, name: &'static str, handler: F) -> JoinHandle<()>
+    where
+        E: EventType,
+        F: Fn(E) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), ConnectifyError>> + Send + 'static,
+    {
+        let mut receiver = self.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        let Some(typed) = E::from_event(&event) else {
+                            continue;
+                        };
+                        if let Err(e) = handler(typed).await {
+                            warn!(
+                                "Event subscriber '{}' failed on {} event: {}",
+                                name,
+                                event.name(),
+                                e
+                            );
+                            crate::metrics::record_error("event_bus", name);
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(
+                            "Event subscriber '{}' is lagging, skipped {} events",
+                            name, skipped
+                        );
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
+
+/// The global event bus.
+pub static EVENT_BUS: Lazy<EventBus> = Lazy::new(EventBus::default);
+
+/// Publish an event on the global [`EVENT_BUS`].
+pub fn publish(event: impl Into<DomainEvent>) -> usize {
+    EVENT_BUS.publish(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_typed_subscribers_receive_matching_events() {
+        let bus = EventBus::new(16);
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let sink = received.clone();
+        bus.on::<PaymentSucceeded, _, _>("test", move |payment| {
+            let sink = sink.clone();
+            async move {
+                sink.lock().unwrap().push(payment.payment_id);
+                Ok(())
+            }
+        });
+        let mut all = bus.subscribe();
+
+        bus.publish(BookingCancelled {
+            event_id: "evt-1".to_string(),
+        });
+        bus.publish(PaymentSucceeded {
+            provider: "stripe".to_string(),
+            payment_id: "cs_1".to_string(),
+            amount: Some(1000),
+            currency: Some("chf".to_string()),
+            reference: None,
+        });
+
+        assert_eq!(all.recv().await.unwrap().name(), "booking_cancelled");
+        assert_eq!(all.recv().await.unwrap().name(), "payment_succeeded");
+        for _ in 0..100 {
+            if !received.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert_eq!(*received.lock().unwrap(), vec!["cs_1".to_string()]);
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let event = DomainEvent::from(BookingCancelled {
+            event_id: "evt-1".to_string(),
+        });
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({"type": "booking_cancelled", "data": {"event_id": "evt-1"}})
+        );
+        assert_eq!(EventBus::new(1).publish(event), 0);
+    }
+}
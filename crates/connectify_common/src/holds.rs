@@ -0,0 +1,217 @@
+//! Temporary slot holds for the Connectify application.
+//!
+//! A hold reserves a calendar slot for a short time, e.g. while the customer pays. Held
+//! slots are treated as busy when calculating availability and when booking, so a second
+//! customer cannot pay for the same slot. A hold ends when the booking is made with its id,
+//! when it is released, or when it expires.
+//!
+//! The store defaults to an in-memory one; the backend replaces it with the database
+//! repository via [`configure_slot_hold_store`], so holds are shared between instances.
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// A slot reserved until `expires_at`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlotHold {
+    pub id: String,
+    pub calendar_id: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Free-form reference of the holder, e.g. a checkout session
+    pub reference: Option<String>,
+}
+
+impl SlotHold {
+    /// Whether the hold is still in effect at `now`.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at > now
+    }
+
+    /// Whether the held slot overlaps the time from `start` to `end`.
+    pub fn overlaps(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        self.start_time < end && start < self.end_time
+    }
+}
+
+/// Storage for slot holds.
+pub trait SlotHoldStore: Send + Sync {
+    /// Store a new hold.
+    fn create(&self, hold: SlotHold) -> BoxFuture<'_, (), ConnectifyError>;
+
+    /// Look up a hold, whether active or not.
+    fn get<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Option<SlotHold>, ConnectifyError>;
+
+    /// The holds of a calendar active at `now` and overlapping the time from `start` to `end`.
+    fn overlapping<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, Vec<SlotHold>, ConnectifyError>;
+
+    /// Release a hold.
+    ///
+    /// # Returns
+    ///
+    /// Whether the hold existed.
+    fn release<'a>(&'a self, id: &'a str) -> BoxFuture<'a, bool, ConnectifyError>;
+
+    /// Remove the holds expired at `now`.
+    ///
+    /// # Returns
+    ///
+    /// The number of removed holds.
+    fn purge_expired(&self, now: DateTime<Utc>) -> BoxFuture<'_, u64, ConnectifyError>;
+}
+
+/// A [`SlotHoldStore`] keeping holds in memory, for single-instance deployments and tests.
+#[derive(Debug, Default)]
+pub struct InMemorySlotHoldStore {
+    holds: Mutex<HashMap<String, SlotHold>>,
+}
+
+impl SlotHoldStore for InMemorySlotHoldStore {
+    fn create(&self, hold: SlotHold) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move {
+            self.holds
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(hold.id.clone(), hold);
+            Ok(())
+        })
+    }
+
+    fn get<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Option<SlotHold>, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .holds
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(id)
+                .cloned())
+        })
+    }
+
+    fn overlapping<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, Vec<SlotHold>, ConnectifyError> {
+        Box::pin(async move {
+            let holds = self.holds.lock().unwrap_or_else(|e| e.into_inner());
+            let mut overlapping: Vec<SlotHold> = holds
+                .values()
+                .filter(|hold| {
+                    hold.calendar_id == calendar_id
+                        && hold.is_active(now)
+                        && hold.overlaps(start, end)
+                })
+                .cloned()
+                .collect();
+            overlapping.sort_by_key(|hold| hold.start_time);
+            Ok(overlapping)
+        })
+    }
+
+    fn release<'a>(&'a self, id: &'a str) -> BoxFuture<'a, bool, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .holds
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(id)
+                .is_some())
+        })
+    }
+
+    fn purge_expired(&self, now: DateTime<Utc>) -> BoxFuture<'_, u64, ConnectifyError> {
+        Box::pin(async move {
+            let mut holds = self.holds.lock().unwrap_or_else(|e| e.into_inner());
+            let before = holds.len();
+            holds.retain(|_, hold| hold.is_active(now));
+            Ok((before - holds.len()) as u64)
+        })
+    }
+}
+
+/// The global store returned by [`slot_hold_store`].
+static SLOT_HOLD_STORE: Lazy<RwLock<Arc<dyn SlotHoldStore>>> =
+    Lazy::new(|| RwLock::new(Arc::new(InMemorySlotHoldStore::default())));
+
+/// Replace the store used for slot holds.
+pub fn configure_slot_hold_store(store: Arc<dyn SlotHoldStore>) {
+    *SLOT_HOLD_STORE.write().unwrap_or_else(|e| e.into_inner()) = store;
+}
+
+/// The store used for slot holds.
+pub fn slot_hold_store() -> Arc<dyn SlotHoldStore> {
+    SLOT_HOLD_STORE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn hold(id: &str, hour: u32, expires_at: DateTime<Utc>) -> SlotHold {
+        SlotHold {
+            id: id.to_string(),
+            calendar_id: "primary".to_string(),
+            start_time: Utc.with_ymd_and_hms(2025, 5, 15, hour, 0, 0).unwrap(),
+            end_time: Utc.with_ymd_and_hms(2025, 5, 15, hour + 1, 0, 0).unwrap(),
+            expires_at,
+            reference: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemorySlotHoldStore::default();
+        let now = Utc.with_ymd_and_hms(2025, 5, 15, 8, 0, 0).unwrap();
+        store
+            .create(hold("active", 10, now + Duration::minutes(15)))
+            .await
+            .unwrap();
+        store.create(hold("expired", 11, now)).await.unwrap();
+
+        let day_start = Utc.with_ymd_and_hms(2025, 5, 15, 0, 0, 0).unwrap();
+        let day_end = day_start + Duration::days(1);
+        let active = store
+            .overlapping("primary", day_start, day_end, now)
+            .await
+            .unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].id, "active");
+        // Adjacent slots don't overlap
+        let at_eleven = Utc.with_ymd_and_hms(2025, 5, 15, 11, 0, 0).unwrap();
+        assert!(store
+            .overlapping("primary", at_eleven, day_end, now)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(store
+            .overlapping("other", day_start, day_end, now)
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(store.purge_expired(now).await.unwrap(), 1);
+        assert!(store.get("expired").await.unwrap().is_none());
+        assert!(store.release("active").await.unwrap());
+        assert!(!store.release("active").await.unwrap());
+    }
+}
@@ -9,7 +9,9 @@ use serde_json::json;
 use crate::error::{ConnectifyError, HttpStatusCode};
 
 // Include the client module
+pub mod circuit_breaker;
 pub mod client;
+pub mod etag;
 
 /// Extension trait for ConnectifyError to convert it to an Axum HTTP response.
 pub trait IntoHttpResponse {
@@ -23,6 +25,7 @@ impl IntoHttpResponse for ConnectifyError {
             StatusCode::from_u16(self.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
 
         let error_message = self.to_string();
+        crate::metrics::record_error("http", status_code.as_str());
 
         // Create a JSON response with the error message
         let body = Json(json!({
@@ -44,44 +47,43 @@ impl IntoResponse for ConnectifyError {
     }
 }
 
-/// A utility function to convert a Result<T, ConnectifyError> to a Result<T, Box<Response>>.
-/// This is useful for Axum handlers that return a Result.
-pub fn handle_result<T>(result: Result<T, ConnectifyError>) -> Result<T, Box<Response>>
+/// A utility function to convert a Result<T, E> into a Result<T, ConnectifyError>.
+/// Works with any domain error that implements `Into<ConnectifyError>`, so Axum handlers
+/// can return the (small) `ConnectifyError`, which renders itself as a JSON error response.
+pub fn handle_result<T, E>(result: Result<T, E>) -> Result<T, ConnectifyError>
 where
     T: IntoResponse,
+    E: Into<ConnectifyError>,
 {
-    result.map_err(|err| Box::new(err.into_response()))
+    result.map_err(Into::into)
 }
 
-/// A utility function to convert a Result<Json<T>, ConnectifyError> to a Result<Json<T>, Box<Response>>.
+/// A utility function to convert a Result<T, E> to a Result<Json<T>, ConnectifyError>.
 /// This is useful for Axum handlers that return a JSON response.
-pub fn handle_json_result<T>(result: Result<T, ConnectifyError>) -> Result<Json<T>, Box<Response>>
+pub fn handle_json_result<T, E>(result: Result<T, E>) -> Result<Json<T>, ConnectifyError>
 where
     T: serde::Serialize,
+    E: Into<ConnectifyError>,
 {
-    result
-        .map(Json)
-        .map_err(|err| Box::new(err.into_response()))
+    result.map(Json).map_err(Into::into)
 }
 
-/// A utility function to convert a Result<T, E> to a Result<T, Box<Response>> using a custom error mapper.
-/// This is useful for Axum handlers that need to convert domain-specific errors to HTTP responses.
-pub fn map_error<T, E, F>(result: Result<T, E>, f: F) -> Result<T, Box<Response>>
+/// A utility function to convert a Result<T, E> to a Result<T, ConnectifyError> using a custom error mapper.
+/// This is useful for Axum handlers that need to log or enrich domain-specific errors.
+pub fn map_error<T, E, F>(result: Result<T, E>, f: F) -> Result<T, ConnectifyError>
 where
     T: IntoResponse,
     F: FnOnce(E) -> ConnectifyError,
 {
-    result.map_err(|err| Box::new(f(err).into_response()))
+    result.map_err(f)
 }
 
-/// A utility function to convert a Result<T, E> to a Result<Json<T>, Box<Response>> using a custom error mapper.
-/// This is useful for Axum handlers that need to convert domain-specific errors to HTTP responses.
-pub fn map_json_error<T, E, F>(result: Result<T, E>, f: F) -> Result<Json<T>, Box<Response>>
+/// A utility function to convert a Result<T, E> to a Result<Json<T>, ConnectifyError> using a custom error mapper.
+/// This is useful for Axum handlers that need to log or enrich domain-specific errors.
+pub fn map_json_error<T, E, F>(result: Result<T, E>, f: F) -> Result<Json<T>, ConnectifyError>
 where
     T: serde::Serialize,
     F: FnOnce(E) -> ConnectifyError,
 {
-    result
-        .map(Json)
-        .map_err(|err| Box::new(f(err).into_response()))
+    result.map(Json).map_err(f)
 }
@@ -0,0 +1,220 @@
+// --- File: crates/connectify_common/src/http/circuit_breaker.rs ---
+//! Per-host circuit breaker for outbound HTTP calls.
+//!
+//! Every host starts with a closed circuit. After `failure_threshold` consecutive failures
+//! (transport errors or 5xx responses) the circuit opens and calls to that host fail fast
+//! for `open_seconds`. Afterwards a single half-open probe is let through: if it succeeds
+//! the circuit closes again, otherwise it stays open for another period.
+//!
+//! The thresholds are read from the `circuit_breaker` section of the `AppConfig` via
+//! [`configure_circuit_breakers`]; unconfigured hosts use the defaults.
+
+use connectify_config::{CircuitBreakerConfig, CircuitBreakerRule};
+use once_cell::sync::Lazy;
+use reqwest::{RequestBuilder, Response};
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::error::{external_service_error, ConnectifyError};
+
+/// Errors returned by requests sent through the circuit breaker.
+#[derive(Error, Debug)]
+pub enum HttpClientError {
+    /// The circuit for the host is open, the request was not sent.
+    #[error("Circuit open for host {host}, request not sent")]
+    CircuitOpen { host: String },
+
+    /// The request itself failed.
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+}
+
+impl From<HttpClientError> for ConnectifyError {
+    fn from(err: HttpClientError) -> Self {
+        match err {
+            HttpClientError::CircuitOpen { host } => {
+                external_service_error(&host, "Service temporarily unavailable (circuit open)")
+            }
+            HttpClientError::Request(e) => e.into(),
+        }
+    }
+}
+
+/// The state of the circuit for a single host.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CircuitState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { probe_started: Instant },
+}
+
+/// Registry of circuit breakers, one per host.
+#[derive(Debug, Default)]
+pub struct CircuitBreakerRegistry {
+    config: RwLock<CircuitBreakerConfig>,
+    circuits: Mutex<HashMap<String, CircuitState>>,
+}
+
+impl CircuitBreakerRegistry {
+    /// Create a registry with the given thresholds.
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Replace the thresholds, keeping the current circuit states.
+    pub fn configure(&self, config: CircuitBreakerConfig) {
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = config;
+    }
+
+    fn rule(&self, host: &str) -> CircuitBreakerRule {
+        let config = self.config.read().unwrap_or_else(|e| e.into_inner());
+        config.hosts.get(host).unwrap_or(&config.default).clone()
+    }
+
+    /// Check whether a request to the host may be sent.
+    pub fn acquire(&self, host: &str) -> Result<(), HttpClientError> {
+        self.acquire_at(host, Instant::now())
+    }
+
+    fn acquire_at(&self, host: &str, now: Instant) -> Result<(), HttpClientError> {
+        let open_duration = Duration::from_secs(self.rule(host).open_seconds);
+        let mut circuits = self.circuits.lock().unwrap_or_else(|e| e.into_inner());
+        let state = circuits
+            .entry(host.to_string())
+            .or_insert(CircuitState::Closed { failures: 0 });
+
+        match *state {
+            CircuitState::Closed { .. } => Ok(()),
+            // Let a single probe through once the open period has elapsed. A probe that never
+            // reported back (e.g. a dropped future) is replaced after another open period.
+            CircuitState::Open { until } if now >= until => {
+                info!("Circuit for {} is half-open, sending probe", host);
+                *state = CircuitState::HalfOpen { probe_started: now };
+                Ok(())
+            }
+            CircuitState::HalfOpen { probe_started }
+                if now.duration_since(probe_started) >= open_duration =>
+            {
+                *state = CircuitState::HalfOpen { probe_started: now };
+                Ok(())
+            }
+            _ => Err(HttpClientError::CircuitOpen {
+                host: host.to_string(),
+            }),
+        }
+    }
+
+    /// Record the outcome of a request to the host.
+    pub fn record(&self, host: &str, success: bool) {
+        self.record_at(host, success, Instant::now())
+    }
+
+    fn record_at(&self, host: &str, success: bool, now: Instant) {
+        let rule = self.rule(host);
+        let mut circuits = self.circuits.lock().unwrap_or_else(|e| e.into_inner());
+        let state = circuits
+            .entry(host.to_string())
+            .or_insert(CircuitState::Closed { failures: 0 });
+
+        let open = CircuitState::Open {
+            until: now + Duration::from_secs(rule.open_seconds),
+        };
+        *state = match (*state, success) {
+            (CircuitState::HalfOpen { .. }, true) => {
+                info!("Circuit for {} closed again", host);
+                CircuitState::Closed { failures: 0 }
+            }
+            (_, true) => CircuitState::Closed { failures: 0 },
+            (CircuitState::Closed { failures }, false)
+                if failures + 1 < rule.failure_threshold.max(1) =>
+            {
+                CircuitState::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (CircuitState::Open { .. }, false) => *state,
+            (_, false) => {
+                warn!(
+                    "Circuit for {} opened for {} seconds",
+                    host, rule.open_seconds
+                );
+                crate::metrics::record_error("circuit_breaker", "open");
+                open
+            }
+        };
+    }
+}
+
+/// The global circuit breaker registry used by [`send_guarded`].
+pub static CIRCUIT_BREAKERS: Lazy<CircuitBreakerRegistry> = Lazy::new(Default::default);
+
+/// Apply the thresholds from the `circuit_breaker` configuration section.
+pub fn configure_circuit_breakers(config: &CircuitBreakerConfig) {
+    CIRCUIT_BREAKERS.configure(config.clone());
+}
+
+/// Send a request through the circuit breaker of its host.
+///
+/// # Arguments
+///
+/// * `request` - The request to send
+///
+/// # Returns
+///
+/// The response, a `CircuitOpen` error if the host's circuit is open, or the request error.
+/// Only transport errors and 5xx responses count as failures.
+pub async fn send_guarded(request: RequestBuilder) -> Result<Response, HttpClientError> {
+    let (client, request) = request.build_split();
+    let request = request?;
+    let host = request.url().host_str().unwrap_or_default().to_string();
+
+    CIRCUIT_BREAKERS.acquire(&host)?;
+    let result = client.execute(request).await;
+    let success = matches!(&result, Ok(response) if !response.status().is_server_error());
+    CIRCUIT_BREAKERS.record(&host, success);
+
+    Ok(result?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_opens_and_recovers() {
+        let registry = CircuitBreakerRegistry::new(CircuitBreakerConfig {
+            default: CircuitBreakerRule {
+                failure_threshold: 2,
+                open_seconds: 10,
+            },
+            hosts: HashMap::new(),
+        });
+        let host = "api.example.com";
+        let now = Instant::now();
+
+        registry.record_at(host, false, now);
+        assert!(registry.acquire_at(host, now).is_ok());
+        registry.record_at(host, false, now);
+        assert!(registry.acquire_at(host, now).is_err());
+
+        // Half-open: exactly one probe is let through
+        let later = now + Duration::from_secs(10);
+        assert!(registry.acquire_at(host, later).is_ok());
+        assert!(registry.acquire_at(host, later).is_err());
+
+        // A failed probe opens the circuit again, a successful one closes it
+        registry.record_at(host, false, later);
+        assert!(registry.acquire_at(host, later).is_err());
+        let much_later = later + Duration::from_secs(10);
+        assert!(registry.acquire_at(host, much_later).is_ok());
+        registry.record_at(host, true, much_later);
+        assert!(registry.acquire_at(host, much_later).is_ok());
+        assert!(registry.acquire_at("other.example.com", now).is_ok());
+    }
+}
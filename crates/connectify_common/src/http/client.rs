@@ -3,11 +3,17 @@ use once_cell::sync::Lazy;
 use reqwest::{Client, Error as ReqwestError, Response};
 use std::time::Duration;
 
+use super::circuit_breaker::{send_guarded, HttpClientError};
+use crate::request_id::RequestIdExt;
+
 /// Default timeout for HTTP requests in seconds
 const DEFAULT_TIMEOUT_SECS: u64 = 30;
 
 /// A static HTTP client that can be reused across the application.
 /// This client is configured with a default timeout and follows redirects.
+/// The helper functions below forward the current `X-Request-Id` and go through the per-host
+/// circuit breaker; direct users of the client should call `RequestIdExt::with_request_id`
+/// on their request builders and send them with `send_guarded`.
 pub static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
     Client::builder()
         .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
@@ -44,9 +50,9 @@ pub fn create_client(timeout_secs: u64, follow_redirects: bool) -> Result<Client
 ///
 /// # Returns
 ///
-/// A Result containing the Response or an Error
-pub async fn get(url: &str) -> Result<Response, ReqwestError> {
-    HTTP_CLIENT.get(url).send().await
+/// A Result containing the Response or an Error. Fails fast if the host's circuit is open.
+pub async fn get(url: &str) -> Result<Response, HttpClientError> {
+    send_guarded(HTTP_CLIENT.get(url).with_request_id()).await
 }
 
 /// A utility function to make a POST request to the specified URL with the specified body.
@@ -58,9 +64,9 @@ pub async fn get(url: &str) -> Result<Response, ReqwestError> {
 ///
 /// # Returns
 ///
-/// A Result containing the Response or an Error
-pub async fn post<T: serde::Serialize>(url: &str, body: &T) -> Result<Response, ReqwestError> {
-    HTTP_CLIENT.post(url).json(body).send().await
+/// A Result containing the Response or an Error. Fails fast if the host's circuit is open.
+pub async fn post<T: serde::Serialize>(url: &str, body: &T) -> Result<Response, HttpClientError> {
+    send_guarded(HTTP_CLIENT.post(url).with_request_id().json(body)).await
 }
 
 /// A utility function to make a PUT request to the specified URL with the specified body.
@@ -72,9 +78,9 @@ pub async fn post<T: serde::Serialize>(url: &str, body: &T) -> Result<Response,
 ///
 /// # Returns
 ///
-/// A Result containing the Response or an Error
-pub async fn put<T: serde::Serialize>(url: &str, body: &T) -> Result<Response, ReqwestError> {
-    HTTP_CLIENT.put(url).json(body).send().await
+/// A Result containing the Response or an Error. Fails fast if the host's circuit is open.
+pub async fn put<T: serde::Serialize>(url: &str, body: &T) -> Result<Response, HttpClientError> {
+    send_guarded(HTTP_CLIENT.put(url).with_request_id().json(body)).await
 }
 
 /// A utility function to make a DELETE request to the specified URL.
@@ -85,9 +91,9 @@ pub async fn put<T: serde::Serialize>(url: &str, body: &T) -> Result<Response, R
 ///
 /// # Returns
 ///
-/// A Result containing the Response or an Error
-pub async fn delete(url: &str) -> Result<Response, ReqwestError> {
-    HTTP_CLIENT.delete(url).send().await
+/// A Result containing the Response or an Error. Fails fast if the host's circuit is open.
+pub async fn delete(url: &str) -> Result<Response, HttpClientError> {
+    send_guarded(HTTP_CLIENT.delete(url).with_request_id()).await
 }
 
 /// A utility function to make a PATCH request to the specified URL with the specified body.
@@ -99,7 +105,7 @@ pub async fn delete(url: &str) -> Result<Response, ReqwestError> {
 ///
 /// # Returns
 ///
-/// A Result containing the Response or an Error
-pub async fn patch<T: serde::Serialize>(url: &str, body: &T) -> Result<Response, ReqwestError> {
-    HTTP_CLIENT.patch(url).json(body).send().await
+/// A Result containing the Response or an Error. Fails fast if the host's circuit is open.
+pub async fn patch<T: serde::Serialize>(url: &str, body: &T) -> Result<Response, HttpClientError> {
+    send_guarded(HTTP_CLIENT.patch(url).with_request_id().json(body)).await
 }
@@ -0,0 +1,123 @@
+// --- File: crates/connectify_common/src/http/etag.rs ---
+//! Conditional responses with entity tags.
+//!
+//! [`json_with_etag`] serializes a response body, derives an ETag from a hash of it and
+//! answers `304 Not Modified` without a body when the request's `If-None-Match` header already
+//! names that ETag, so polling clients only download content that changed.
+//!
+//! ## Usage
+//!
+//! ```ignore
+//! async fn handler(headers: HeaderMap) -> Response {
+//!     let slots = compute_slots().await;
+//!     json_with_etag(&headers, &slots)
+//! }
+//! ```
+
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::error::ConnectifyError;
+
+/// The strong ETag of a response body: a quoted prefix of its SHA-256 hash.
+pub fn etag_for(body: &[u8]) -> String {
+    let hash = hex::encode(Sha256::digest(body));
+    format!("\"{}\"", &hash[..32])
+}
+
+/// Returns `true` if the `If-None-Match` header of a request names the given ETag.
+///
+/// Uses the weak comparison required for `If-None-Match`, so `W/"..."` matches `"..."`.
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// A JSON response with an ETag, or `304 Not Modified` if the client already has it.
+///
+/// Responses are marked `Cache-Control: no-cache`, so clients revalidate them on every use.
+pub fn json_with_etag<T: Serialize>(headers: &HeaderMap, value: &T) -> Response {
+    let body = match serde_json::to_vec(value) {
+        Ok(body) => body,
+        Err(e) => {
+            return ConnectifyError::InternalError(format!("Failed to serialize response: {}", e))
+                .into_response()
+        }
+    };
+    let etag = etag_for(&body);
+    let Ok(etag_header) = HeaderValue::from_str(&etag) else {
+        return ConnectifyError::InternalError("Invalid ETag".to_string()).into_response();
+    };
+    let cache_control = HeaderValue::from_static("no-cache");
+
+    if if_none_match(headers, &etag) {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [(ETAG, etag_header), (CACHE_CONTROL, cache_control)],
+        )
+            .into_response();
+    }
+    (
+        [
+            (CONTENT_TYPE, HeaderValue::from_static("application/json")),
+            (ETAG, etag_header),
+            (CACHE_CONTROL, cache_control),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_if_none_match() {
+        let etag = etag_for(b"[]");
+        assert_eq!(etag.len(), 34);
+
+        let mut headers = HeaderMap::new();
+        assert!(!if_none_match(&headers, &etag));
+
+        headers.insert(
+            IF_NONE_MATCH,
+            HeaderValue::from_str(&format!("\"other\", W/{}", etag)).unwrap(),
+        );
+        assert!(if_none_match(&headers, &etag));
+
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_static("*"));
+        assert!(if_none_match(&headers, &etag));
+    }
+
+    #[test]
+    fn test_json_with_etag() {
+        let value = json!({ "slots": [] });
+        let response = json_with_etag(&HeaderMap::new(), &value);
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers().get(ETAG).unwrap().clone();
+        assert_eq!(
+            etag.to_str().unwrap(),
+            etag_for(&serde_json::to_vec(&value).unwrap())
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_NONE_MATCH, etag.clone());
+        let response = json_with_etag(&headers, &value);
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get(ETAG), Some(&etag));
+
+        // Changed content gets a new ETag
+        let response = json_with_etag(&headers, &json!({ "slots": [1] }));
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,588 @@
+//! Idempotency-Key support for the Connectify application.
+//!
+//! The [`IdempotencyLayer`] makes creation endpoints (bookings, checkout sessions, payment
+//! gateways) safe to retry. A client sends an `Idempotency-Key` header with a unique value;
+//! the first response for that key is stored in an [`IdempotencyStore`] and replayed for every
+//! later request with the same key, instead of e.g. booking the slot twice.
+//!
+//! - A key reused with a different request body is rejected with 422.
+//! - A duplicate arriving while the first request is still running is rejected with 409.
+//! - Server errors (5xx) are not stored, so the client can retry with the same key.
+//! - Requests dropped before their response is stored (e.g. on a client disconnect or
+//!   timeout) release their key, so the client can retry with it as well.
+//! - Requests without the header are passed through unchanged.
+//!
+//! Keys are scoped to the request method and path. The store defaults to an in-memory one;
+//! the backend replaces it with the database repository via [`configure_idempotency_store`].
+//!
+//! ## Usage
+//!
+//! ```ignore
+//! Router::new().route("/book", post(book_slot_handler).layer(IdempotencyLayer::new()))
+//! ```
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    http::{HeaderName, HeaderValue, Method, Request, StatusCode},
+    response::{IntoResponse, Response},
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+use tracing::{debug, info, warn};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// Header carrying the idempotency key.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Header added to replayed responses.
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Maximum accepted length of an idempotency key.
+const MAX_KEY_LEN: usize = 255;
+
+/// Maximum size of request and response bodies buffered by the middleware.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// How long stored responses are kept by default.
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A response stored for replay.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredResponse {
+    pub status: u16,
+    /// Response headers, except the ones describing the transfer (e.g. `content-length`).
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// The state of an idempotency key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdempotencyRecord {
+    /// Hash of the request the key was first used with.
+    pub fingerprint: String,
+    /// The stored response, or `None` while the first request is still being handled.
+    pub response: Option<StoredResponse>,
+}
+
+/// Storage for idempotency keys and their responses.
+///
+/// Implementations must make [`reserve`](IdempotencyStore::reserve) atomic, so that two
+/// concurrent requests with the same key cannot both be handled.
+pub trait IdempotencyStore: Send + Sync {
+    /// Reserve a key for a request.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the key was unused (or expired) and is now reserved, the existing record otherwise.
+    fn reserve<'a>(
+        &'a self,
+        key: &'a str,
+        fingerprint: &'a str,
+    ) -> BoxFuture<'a, Option<IdempotencyRecord>, ConnectifyError>;
+
+    /// Store the response of the request that reserved the key.
+    fn complete<'a>(
+        &'a self,
+        key: &'a str,
+        response: StoredResponse,
+    ) -> BoxFuture<'a, (), ConnectifyError>;
+
+    /// Release a reserved key without storing a response, so the request can be retried.
+    fn release<'a>(&'a self, key: &'a str) -> BoxFuture<'a, (), ConnectifyError>;
+
+    /// Remove expired keys.
+    ///
+    /// # Returns
+    ///
+    /// The number of removed keys.
+    fn purge_expired(&self) -> BoxFuture<'_, u64, ConnectifyError>;
+}
+
+/// An [`IdempotencyStore`] keeping keys in memory, for single-instance deployments and tests.
+#[derive(Debug)]
+pub struct InMemoryIdempotencyStore {
+    ttl: Duration,
+    records: Mutex<HashMap<String, (Instant, IdempotencyRecord)>>,
+}
+
+impl InMemoryIdempotencyStore {
+    /// Create a store that forgets keys after the given time.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            records: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for InMemoryIdempotencyStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDEMPOTENCY_TTL)
+    }
+}
+
+impl IdempotencyStore for InMemoryIdempotencyStore {
+    fn reserve<'a>(
+        &'a self,
+        key: &'a str,
+        fingerprint: &'a str,
+    ) -> BoxFuture<'a, Option<IdempotencyRecord>, ConnectifyError> {
+        Box::pin(async move {
+            let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            records.retain(|_, (created, _)| now.duration_since(*created) < self.ttl);
+
+            if let Some((_, record)) = records.get(key) {
+                return Ok(Some(record.clone()));
+            }
+            records.insert(
+                key.to_string(),
+                (
+                    now,
+                    IdempotencyRecord {
+                        fingerprint: fingerprint.to_string(),
+                        response: None,
+                    },
+                ),
+            );
+            Ok(None)
+        })
+    }
+
+    fn complete<'a>(
+        &'a self,
+        key: &'a str,
+        response: StoredResponse,
+    ) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move {
+            let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some((_, record)) = records.get_mut(key) {
+                record.response = Some(response);
+            }
+            Ok(())
+        })
+    }
+
+    fn release<'a>(&'a self, key: &'a str) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move {
+            self.records
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(key);
+            Ok(())
+        })
+    }
+
+    fn purge_expired(&self) -> BoxFuture<'_, u64, ConnectifyError> {
+        Box::pin(async move {
+            let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+            let before = records.len();
+            let now = Instant::now();
+            records.retain(|_, (created, _)| now.duration_since(*created) < self.ttl);
+            Ok((before - records.len()) as u64)
+        })
+    }
+}
+
+/// The global store used by [`IdempotencyLayer::new`].
+static IDEMPOTENCY_STORE: Lazy<RwLock<Arc<dyn IdempotencyStore>>> =
+    Lazy::new(|| RwLock::new(Arc::new(InMemoryIdempotencyStore::default())));
+
+/// Replace the store used by layers created with [`IdempotencyLayer::new`].
+///
+/// Layers look the store up per request, so this also affects routers built earlier.
+pub fn configure_idempotency_store(store: Arc<dyn IdempotencyStore>) {
+    *IDEMPOTENCY_STORE.write().unwrap_or_else(|e| e.into_inner()) = store;
+}
+
+/// The store used by layers created with [`IdempotencyLayer::new`].
+pub fn idempotency_store() -> Arc<dyn IdempotencyStore> {
+    IDEMPOTENCY_STORE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Tower layer honoring the `Idempotency-Key` header.
+#[derive(Clone, Default)]
+pub struct IdempotencyLayer {
+    store: Option<Arc<dyn IdempotencyStore>>,
+}
+
+impl IdempotencyLayer {
+    /// Create a layer using the globally configured store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a layer using a specific store.
+    pub fn with_store(store: Arc<dyn IdempotencyStore>) -> Self {
+        Self { store: Some(store) }
+    }
+}
+
+impl<S> Layer<S> for IdempotencyLayer {
+    type Service = Idempotency<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Idempotency {
+            inner,
+            store: self.store.clone(),
+        }
+    }
+}
+
+/// Service created by [`IdempotencyLayer`].
+#[derive(Clone)]
+pub struct Idempotency<S> {
+    inner: S,
+    store: Option<Arc<dyn IdempotencyStore>>,
+}
+
+/// Hash the parts of a request that must match for a replay.
+fn fingerprint(method: &Method, path: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str().as_bytes());
+    hasher.update(b" ");
+    hasher.update(path.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+/// Accept a key only if it is short and printable.
+fn parse_key(value: &HeaderValue) -> Result<String, ConnectifyError> {
+    let key = value.to_str().map(str::trim).unwrap_or_default();
+    if key.is_empty() || key.len() > MAX_KEY_LEN || key.chars().any(|c| c.is_ascii_control()) {
+        return Err(ConnectifyError::ValidationError(format!(
+            "Idempotency-Key must be 1 to {} printable characters",
+            MAX_KEY_LEN
+        )));
+    }
+    Ok(key.to_string())
+}
+
+impl StoredResponse {
+    async fn from_response(response: Response) -> Result<(Self, Response), ConnectifyError> {
+        let (parts, body) = response.into_parts();
+        let body = to_bytes(body, MAX_BODY_BYTES)
+            .await
+            .map_err(|e| ConnectifyError::InternalError(format!("Response too large: {}", e)))?;
+        let stored = Self {
+            status: parts.status.as_u16(),
+            headers: parts
+                .headers
+                .iter()
+                .filter(|(name, _)| {
+                    *name != axum::http::header::CONTENT_LENGTH
+                        && *name != axum::http::header::TRANSFER_ENCODING
+                })
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            body: body.to_vec(),
+        };
+        Ok((stored, Response::from_parts(parts, Body::from(body))))
+    }
+
+    fn into_response(self) -> Response {
+        let mut response = Response::new(Body::from(self.body));
+        *response.status_mut() = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        let headers = response.headers_mut();
+        for (name, value) in self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::try_from(name.as_str()),
+                HeaderValue::try_from(value.as_str()),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        headers.insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+/// A reserved key, released when dropped before its request was settled.
+///
+/// Request futures are dropped when the client disconnects or a timeout fires; without the
+/// release, retries with the key would be rejected as in progress until the key expires.
+struct Reservation {
+    store: Arc<dyn IdempotencyStore>,
+    key: String,
+    settled: bool,
+}
+
+impl Reservation {
+    fn new(store: Arc<dyn IdempotencyStore>, key: String) -> Self {
+        Self {
+            store,
+            key,
+            settled: false,
+        }
+    }
+
+    /// Store the response of the request.
+    async fn complete(mut self, response: StoredResponse) -> Result<(), ConnectifyError> {
+        let result = self.store.complete(&self.key, response).await;
+        self.settled = result.is_ok();
+        result
+    }
+
+    /// Release the key, so the request can be retried.
+    async fn release(mut self) -> Result<(), ConnectifyError> {
+        let result = self.store.release(&self.key).await;
+        self.settled = result.is_ok();
+        result
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if self.settled {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            warn!(
+                "Cannot release idempotency key '{}' without a runtime",
+                self.key
+            );
+            return;
+        };
+        debug!(
+            "Releasing idempotency key '{}' of a dropped request",
+            self.key
+        );
+        let store = self.store.clone();
+        let key = std::mem::take(&mut self.key);
+        runtime.spawn(async move {
+            if let Err(e) = store.release(&key).await {
+                warn!("Failed to release idempotency key '{}': {}", key, e);
+            }
+        });
+    }
+}
+
+async fn handle<S>(
+    mut inner: S,
+    store: Arc<dyn IdempotencyStore>,
+    req: Request<Body>,
+    key: String,
+) -> Result<Response, ConnectifyError>
+where
+    S: Service<Request<Body>, Response = Response>,
+{
+    let (parts, body) = req.into_parts();
+    let body: Bytes = to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|e| ConnectifyError::ValidationError(format!("Request too large: {}", e)))?;
+    let scoped_key = format!("{} {} {}", parts.method, parts.uri.path(), key);
+    let fingerprint = fingerprint(&parts.method, parts.uri.path(), &body);
+
+    match store.reserve(&scoped_key, &fingerprint).await? {
+        Some(record) if record.fingerprint != fingerprint => {
+            return Ok((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Idempotency-Key was already used with a different request",
+            )
+                .into_response());
+        }
+        Some(IdempotencyRecord {
+            response: Some(stored),
+            ..
+        }) => {
+            debug!("Replaying stored response for idempotency key '{}'", key);
+            return Ok(stored.into_response());
+        }
+        Some(_) => {
+            return Err(ConnectifyError::ConflictError(
+                "A request with this Idempotency-Key is still being processed".to_string(),
+            ));
+        }
+        None => {}
+    }
+    let reservation = Reservation::new(store, scoped_key);
+
+    let response = match inner
+        .call(Request::from_parts(parts, Body::from(body)))
+        .await
+    {
+        Ok(response) => response,
+        Err(_) => {
+            reservation.release().await?;
+            return Err(ConnectifyError::InternalError(
+                "Request handling failed".to_string(),
+            ));
+        }
+    };
+
+    if response.status().is_server_error() {
+        reservation.release().await?;
+        return Ok(response);
+    }
+    match StoredResponse::from_response(response).await {
+        Ok((stored, response)) => {
+            reservation.complete(stored).await?;
+            info!("Stored response for idempotency key '{}'", key);
+            Ok(response)
+        }
+        Err(e) => {
+            reservation.release().await?;
+            Err(e)
+        }
+    }
+}
+
+impl<S> Service<Request<Body>> for Idempotency<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let Some(value) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+            return Box::pin(self.inner.call(req));
+        };
+        let key = match parse_key(value) {
+            Ok(key) => key,
+            Err(e) => return Box::pin(async move { Ok(e.into_response()) }),
+        };
+
+        // Use the service that was polled ready, leave a fresh clone in its place
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+        let store = self.store.clone().unwrap_or_else(idempotency_store);
+        Box::pin(async move {
+            Ok(handle(inner, store, req, key).await.unwrap_or_else(|e| {
+                warn!("Idempotency handling failed: {}", e);
+                e.into_response()
+            }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn app(store: Arc<dyn IdempotencyStore>, calls: Arc<AtomicU32>) -> axum::Router {
+        axum::Router::new().route(
+            "/book",
+            axum::routing::post(move |body: String| {
+                let calls = calls.clone();
+                async move {
+                    let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                    (StatusCode::CREATED, format!("booking {} for {}", n, body))
+                }
+            })
+            .layer(IdempotencyLayer::with_store(store)),
+        )
+    }
+
+    fn request(key: Option<&str>, body: &str) -> Request<Body> {
+        let mut builder = Request::post("/book");
+        if let Some(key) = key {
+            builder = builder.header(IDEMPOTENCY_KEY_HEADER, key);
+        }
+        builder.body(Body::from(body.to_string())).unwrap()
+    }
+
+    async fn send(app: &axum::Router, req: Request<Body>) -> (StatusCode, bool, String) {
+        let response = tower::ServiceExt::oneshot(app.clone(), req).await.unwrap();
+        let status = response.status();
+        let replayed = response.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER);
+        let body = to_bytes(response.into_body(), MAX_BODY_BYTES)
+            .await
+            .unwrap();
+        (status, replayed, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_replays_first_response() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let app = app(Arc::new(InMemoryIdempotencyStore::default()), calls.clone());
+
+        let first = send(&app, request(Some("key-1"), "slot-a")).await;
+        assert_eq!(
+            first,
+            (StatusCode::CREATED, false, "booking 1 for slot-a".into())
+        );
+        let second = send(&app, request(Some("key-1"), "slot-a")).await;
+        assert_eq!(
+            second,
+            (StatusCode::CREATED, true, "booking 1 for slot-a".into())
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // A different payload with the same key is rejected, requests without a key pass
+        let mismatch = send(&app, request(Some("key-1"), "slot-b")).await;
+        assert_eq!(mismatch.0, StatusCode::UNPROCESSABLE_ENTITY);
+        send(&app, request(None, "slot-a")).await;
+        send(&app, request(None, "slot-a")).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_request_releases_key() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let handler_calls = calls.clone();
+        let app = axum::Router::new().route(
+            "/book",
+            axum::routing::post(move || {
+                let calls = handler_calls.clone();
+                async move {
+                    // The first request hangs until the client gives up
+                    if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                        std::future::pending::<()>().await;
+                    }
+                    StatusCode::CREATED
+                }
+            })
+            .layer(IdempotencyLayer::with_store(Arc::new(
+                InMemoryIdempotencyStore::default(),
+            ))),
+        );
+
+        let dropped = tokio::time::timeout(
+            Duration::from_millis(50),
+            send(&app, request(Some("key-1"), "slot-a")),
+        )
+        .await;
+        assert!(dropped.is_err());
+        tokio::task::yield_now().await;
+
+        let retry = send(&app, request(Some("key-1"), "slot-a")).await;
+        assert_eq!(retry, (StatusCode::CREATED, false, String::new()));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_in_progress_key_conflicts() {
+        let store = InMemoryIdempotencyStore::default();
+        assert_eq!(store.reserve("k", "fp").await.unwrap(), None);
+        let pending = store.reserve("k", "fp").await.unwrap().unwrap();
+        assert_eq!(pending.response, None);
+
+        store.release("k").await.unwrap();
+        assert_eq!(store.reserve("k", "fp").await.unwrap(), None);
+    }
+}
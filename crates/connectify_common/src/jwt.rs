@@ -0,0 +1,437 @@
+//! JWT authentication for the Connectify application.
+//!
+//! This module verifies bearer tokens signed with HS256 (shared secret) or RS256 (static public
+//! key or keys fetched from a JWKS endpoint), as configured in the `jwt` section of the
+//! `AppConfig`. The backend installs a [`JwtVerifier`] as a request extension, so handlers
+//! can require an authenticated user with the [`AuthClaims`] extractor:
+//!
+//! ```ignore
+//! async fn my_bookings(claims: AuthClaims) -> Result<Json<Vec<Booking>>, ConnectifyError> {
+//!     claims.require_role("customer")?;
+//!     // ... load the bookings of claims.sub
+//! }
+//! ```
+//!
+//! Use `Option<AuthClaims>` for endpoints that also serve anonymous callers; a present but
+//! invalid token is still rejected.
+
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts},
+    http::{header, request::Parts},
+};
+use connectify_config::JwtConfig;
+use jsonwebtoken::{
+    decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, TokenData, Validation,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::clock::{system_clock, SharedClock};
+
+use crate::error::{external_service_error, ConnectifyError};
+use crate::http::circuit_breaker::send_guarded;
+use crate::http::client::HTTP_CLIENT;
+use crate::request_id::RequestIdExt;
+
+/// Default time fetched JWKS keys are cached.
+const DEFAULT_JWKS_CACHE: Duration = Duration::from_secs(3600);
+
+/// Minimum time between two JWKS fetches, so tokens with unknown key ids cannot flood the endpoint.
+const MIN_JWKS_REFETCH: Duration = Duration::from_secs(30);
+
+/// Default allowed clock skew in seconds.
+const DEFAULT_LEEWAY_SECONDS: u64 = 60;
+
+/// The claims of a verified JWT.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuthClaims {
+    /// The subject, i.e. the user id.
+    pub sub: String,
+    /// Expiration time as a Unix timestamp.
+    pub exp: u64,
+    #[serde(default)]
+    pub iss: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    /// Roles granted to the user, e.g. "admin".
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Space separated OAuth scopes.
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// Any other claims.
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl AuthClaims {
+    /// Check whether the user has a role.
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    /// Check whether the token grants a scope.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope
+            .as_deref()
+            .is_some_and(|scopes| scopes.split_whitespace().any(|s| s == scope))
+    }
+
+    /// Require the user to have a role.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the user has the role, an `AuthError` otherwise.
+    pub fn require_role(&self, role: &str) -> Result<(), ConnectifyError> {
+        if self.has_role(role) {
+            Ok(())
+        } else {
+            Err(ConnectifyError::AuthError(format!(
+                "User '{}' is missing role '{}'",
+                self.sub, role
+            )))
+        }
+    }
+}
+
+/// Verifies JWTs according to the `jwt` configuration.
+pub struct JwtVerifier {
+    hs256_key: Option<DecodingKey>,
+    rs256_key: Option<DecodingKey>,
+    jwks_url: Option<String>,
+    jwks_cache_duration: Duration,
+    jwks_cache: Mutex<Option<(Instant, JwkSet)>>,
+    /// Held while fetching the JWKS, so concurrent requests share one fetch.
+    jwks_refresh: tokio::sync::Mutex<()>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    leeway_seconds: u64,
+    /// Clock token expiry is checked against.
+    clock: SharedClock,
+}
+
+impl JwtVerifier {
+    /// Create a verifier from the configuration.
+    ///
+    /// # Returns
+    ///
+    /// A `ConfigError` if the RSA public key is invalid or no key source is configured.
+    pub fn from_config(config: &JwtConfig) -> Result<Self, ConnectifyError> {
+        let hs256_key = config
+            .hs256_secret
+            .as_ref()
+            .map(|secret| DecodingKey::from_secret(secret.as_bytes()));
+        let rs256_key = config
+            .rs256_public_key_pem
+            .as_ref()
+            .map(|pem| DecodingKey::from_rsa_pem(pem.as_bytes()))
+            .transpose()
+            .map_err(|e| ConnectifyError::ConfigError(format!("Invalid JWT RSA key: {}", e)))?;
+
+        if hs256_key.is_none() && rs256_key.is_none() && config.jwks_url.is_none() {
+            return Err(ConnectifyError::ConfigError(
+                "JWT config needs hs256_secret, rs256_public_key_pem or jwks_url".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            hs256_key,
+            rs256_key,
+            jwks_url: config.jwks_url.clone(),
+            jwks_cache_duration: config
+                .jwks_cache_seconds
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_JWKS_CACHE),
+            jwks_cache: Mutex::new(None),
+            jwks_refresh: tokio::sync::Mutex::new(()),
+            issuer: config.issuer.clone(),
+            audience: config.audience.clone(),
+            leeway_seconds: config.leeway_seconds.unwrap_or(DEFAULT_LEEWAY_SECONDS),
+            clock: system_clock(),
+        })
+    }
+
+    /// Use another clock for the expiry check, e.g. in tests.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Verify a token and return its claims.
+    pub async fn verify(&self, token: &str) -> Result<AuthClaims, ConnectifyError> {
+        let header = decode_header(token)
+            .map_err(|e| ConnectifyError::AuthError(format!("Malformed token: {}", e)))?;
+
+        let key = match header.alg {
+            Algorithm::HS256 => self.hs256_key.clone().ok_or_else(|| {
+                ConnectifyError::AuthError("HS256 tokens are not accepted".to_string())
+            })?,
+            Algorithm::RS256 => match (&header.kid, &self.jwks_url, &self.rs256_key) {
+                (Some(kid), Some(url), _) => self.jwks_key(url, kid).await?,
+                (_, _, Some(key)) => key.clone(),
+                _ => {
+                    return Err(ConnectifyError::AuthError(
+                        "RS256 tokens are not accepted".to_string(),
+                    ))
+                }
+            },
+            alg => {
+                return Err(ConnectifyError::AuthError(format!(
+                    "Unsupported token algorithm {:?}",
+                    alg
+                )))
+            }
+        };
+
+        let data: TokenData<AuthClaims> = decode(token, &key, &self.validation(header.alg))
+            .map_err(|e| ConnectifyError::AuthError(format!("Invalid token: {}", e)))?;
+
+        // Expiry is checked here rather than by jsonwebtoken, so it follows the clock
+        let now = self.clock.timestamp().max(0) as u64;
+        if data.claims.exp.saturating_add(self.leeway_seconds) < now {
+            return Err(ConnectifyError::AuthError(
+                "Invalid token: token expired".to_string(),
+            ));
+        }
+        Ok(data.claims)
+    }
+
+    fn validation(&self, alg: Algorithm) -> Validation {
+        let mut validation = Validation::new(alg);
+        validation.leeway = self.leeway_seconds;
+        validation.validate_exp = false;
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match &self.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+        validation
+    }
+
+    /// Look up a key from the JWKS endpoint, refetching on cache expiry or unknown `kid`.
+    ///
+    /// Concurrent lookups share one fetch, and the JWKS is fetched at most once per
+    /// `MIN_JWKS_REFETCH`.
+    async fn jwks_key(&self, url: &str, kid: &str) -> Result<DecodingKey, ConnectifyError> {
+        if let Some(key) = self.cached_jwk(kid, true)? {
+            return Ok(key);
+        }
+
+        let _refresh = self.jwks_refresh.lock().await;
+        // Another request may have fetched the JWKS while we waited
+        if let Some(key) = self.cached_jwk(kid, true)? {
+            return Ok(key);
+        }
+        if !self.jwks_fetched_within(MIN_JWKS_REFETCH) {
+            debug!("Fetching JWKS from {}", url);
+            let jwks: JwkSet = send_guarded(HTTP_CLIENT.get(url).with_request_id())
+                .await?
+                .error_for_status()
+                .map_err(|e| external_service_error("jwks", e))?
+                .json()
+                .await?;
+            *self.jwks_cache.lock().unwrap_or_else(|e| e.into_inner()) =
+                Some((Instant::now(), jwks));
+        }
+
+        self.cached_jwk(kid, false)?.ok_or_else(|| {
+            warn!("Token signed with unknown key id '{}'", kid);
+            ConnectifyError::AuthError(format!("Unknown token key id '{}'", kid))
+        })
+    }
+
+    fn jwks_fetched_within(&self, interval: Duration) -> bool {
+        self.jwks_cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .is_some_and(|(fetched_at, _)| fetched_at.elapsed() < interval)
+    }
+
+    fn cached_jwk(
+        &self,
+        kid: &str,
+        fresh_only: bool,
+    ) -> Result<Option<DecodingKey>, ConnectifyError> {
+        let cache = self.jwks_cache.lock().unwrap_or_else(|e| e.into_inner());
+        let Some((fetched_at, jwks)) = cache.as_ref() else {
+            return Ok(None);
+        };
+        if fresh_only && fetched_at.elapsed() > self.jwks_cache_duration {
+            return Ok(None);
+        }
+        jwks.find(kid)
+            .map(|jwk| {
+                DecodingKey::from_jwk(jwk)
+                    .map_err(|e| ConnectifyError::AuthError(format!("Invalid JWKS key: {}", e)))
+            })
+            .transpose()
+    }
+}
+
+/// Extract the bearer token from the `Authorization` header.
+fn bearer_token(parts: &Parts) -> Option<&str> {
+    parts
+        .headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Get the verifier installed by the backend.
+fn verifier(parts: &Parts) -> Result<Arc<JwtVerifier>, ConnectifyError> {
+    parts
+        .extensions
+        .get::<Arc<JwtVerifier>>()
+        .cloned()
+        .ok_or_else(|| {
+            ConnectifyError::ConfigError("JWT authentication is not configured".to_string())
+        })
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuthClaims {
+    type Rejection = ConnectifyError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let verifier = verifier(parts)?;
+        let token = bearer_token(parts)
+            .ok_or_else(|| ConnectifyError::AuthError("Missing bearer token".to_string()))?;
+        verifier.verify(token).await
+    }
+}
+
+impl<S: Send + Sync> OptionalFromRequestParts<S> for AuthClaims {
+    type Rejection = ConnectifyError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        let Some(token) = bearer_token(parts) else {
+            return Ok(None);
+        };
+        verifier(parts)?.verify(token).await.map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, MockClock};
+    use chrono::TimeZone;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    fn clock() -> Arc<MockClock> {
+        Arc::new(MockClock::new(
+            chrono::Utc.with_ymd_and_hms(2025, 5, 5, 9, 0, 0).unwrap(),
+        ))
+    }
+
+    fn claims(exp: u64) -> AuthClaims {
+        AuthClaims {
+            sub: "user-1".to_string(),
+            exp,
+            iss: Some("connectify".to_string()),
+            email: None,
+            roles: vec!["admin".to_string()],
+            scope: Some("bookings:read bookings:write".to_string()),
+            extra: HashMap::new(),
+        }
+    }
+
+    fn verifier(clock: Arc<MockClock>) -> JwtVerifier {
+        JwtVerifier::from_config(&JwtConfig {
+            hs256_secret: Some("secret".to_string()),
+            issuer: Some("connectify".to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_clock(clock)
+    }
+
+    fn token(claims: &AuthClaims, secret: &str) -> String {
+        encode(
+            &Header::new(Algorithm::HS256),
+            claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_verify_hs256_token() {
+        let clock = clock();
+        let exp = clock.timestamp() as u64 + 300;
+        let verified = verifier(clock)
+            .verify(&token(&claims(exp), "secret"))
+            .await
+            .unwrap();
+
+        assert_eq!(verified, claims(exp));
+        assert!(verified.require_role("admin").is_ok());
+        assert!(verified.require_role("staff").is_err());
+        assert!(verified.has_scope("bookings:write"));
+        assert!(!verified.has_scope("bookings"));
+    }
+
+    #[tokio::test]
+    async fn test_reject_invalid_tokens() {
+        let clock = clock();
+        let verifier = verifier(clock.clone());
+        let exp = clock.timestamp() as u64 + 300;
+
+        assert!(verifier
+            .verify(&token(&claims(exp), "wrong"))
+            .await
+            .is_err());
+        assert!(verifier
+            .verify(&token(&claims(1_000), "secret"))
+            .await
+            .is_err());
+        assert!(verifier.verify("not-a-token").await.is_err());
+
+        // Expires once the clock passes exp plus the leeway
+        let valid = token(&claims(exp), "secret");
+        assert!(verifier.verify(&valid).await.is_ok());
+        clock.advance(chrono::Duration::seconds(
+            300 + DEFAULT_LEEWAY_SECONDS as i64 + 1,
+        ));
+        assert!(verifier.verify(&valid).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_kid_fetches_jwks_once() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("GET"))
+            .and(wiremock::matchers::path("/jwks"))
+            .respond_with(
+                wiremock::ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "keys": [] })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let verifier = JwtVerifier::from_config(&JwtConfig {
+            jwks_url: Some(format!("{}/jwks", server.uri())),
+            ..Default::default()
+        })
+        .unwrap();
+        // Header {"alg":"RS256","kid":"rotated"}; only the header is read before the key lookup
+        let token = "eyJhbGciOiJSUzI1NiIsImtpZCI6InJvdGF0ZWQifQ.e30.c2ln";
+
+        let (first, second, third) = tokio::join!(
+            verifier.verify(token),
+            verifier.verify(token),
+            verifier.verify(token)
+        );
+        assert!(first.is_err() && second.is_err() && third.is_err());
+        // Within the minimum refetch interval the cached JWKS answers
+        assert!(verifier.verify(token).await.is_err());
+    }
+}
@@ -1,15 +1,43 @@
 // --- File: crates/connectify_common/src/lib.rs ---
 
 // Declare modules within this crate
+pub mod api_key; // API key authentication middleware
+pub mod audit; // Audit logging
+pub mod booking_ledger; // Seats booked in group slots
+pub mod cache; // Caching with in-memory and Redis backends
+pub mod clock; // Time source abstraction
+pub mod dead_letters; // Failed fulfillments kept for retrying
 pub mod error; // Error handling
+pub mod event_mirror; // Local mirror of calendar events
+pub mod events; // In-process event bus
 pub mod features;
 pub mod handlers; // HTTP request handlers
+pub mod holds; // Temporary slot holds
 pub mod http; // HTTP utilities
+pub mod idempotency; // Idempotency-Key middleware
+pub mod jwt; // JWT authentication
+pub mod lock; // Distributed locks
 pub mod logging; // Logging utilities
 pub mod logic; // Core business logic
+pub mod message_status; // Delivery statuses of sent messages
+pub mod metrics; // Prometheus metrics
 pub mod models; // Data structures and models
+pub mod oauth_tokens; // OAuth refresh token storage
+pub mod orders; // Fulfillment payloads of pending payments
+pub mod payments; // Payment provider customers
+pub mod queue; // Message queues
+pub mod rate_limit; // Rate limiting middleware
+pub mod request_id; // Request ID propagation
+pub mod retry; // Retry with backoff
 pub mod routes; // Route definitions
+pub mod runtime_flags; // Runtime kill switches
+pub mod schedule_exceptions; // One-off working hours changes
+pub mod scheduler; // Cron-style background jobs
 pub mod services; // Service abstractions // Feature flag handling
+pub mod sms_consent; // Received SMS and opt-outs
+pub mod validation; // Validated request extractors
+pub mod webhook; // Webhook signature verification
+pub mod webhook_events; // Received webhook events
 
 // Re-export the routes function to be used by the main backend service
 pub use routes::routes;
@@ -17,7 +45,7 @@ pub use routes::routes;
 // Re-export error types and utilities for easier access
 pub use error::{
     config_error, conflict, external_service_error, internal_error, not_found, validation_error,
-    ConnectifyError, Context, HttpStatusCode,
+    ConnectifyError, Context, ExternalServiceFailure, HttpStatusCode,
 };
 
 // Re-export HTTP utilities for easier access
@@ -0,0 +1,368 @@
+//! Distributed locks for the Connectify application.
+//!
+//! When several backend instances run behind a load balancer, the same webhook can be
+//! delivered to two of them, and two clients can book the same slot at the same time. A
+//! [`DistributedLock`] makes sure only one instance works on a key at a time.
+//!
+//! Locks are leases: they expire after their time to live unless renewed, so a crashed
+//! instance cannot hold a lock forever. Three backends are provided:
+//!
+//! - [`InMemoryLock`], used by default, for single-instance deployments and tests.
+//! - `RedisLock` (with the `redis` feature).
+//! - `SqlAdvisoryLock` in connectify_db, based on Postgres advisory locks.
+//!
+//! ## Usage
+//!
+//! ```ignore
+//! let lock = distributed_lock();
+//! let Some(lease) = lock.acquire("stripe:event:evt_123", Duration::from_secs(60)).await? else {
+//!     return Ok(StatusCode::CONFLICT); // Another instance is processing the event
+//! };
+//! let result = process(event).await;
+//! lock.release(&lease).await?;
+//! ```
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// How often [`acquire_with_wait`] retries a held lock.
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A held lock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockLease {
+    /// The locked key.
+    pub key: String,
+    /// Random token identifying this holder, so only it can renew or release the lock.
+    pub token: String,
+}
+
+impl LockLease {
+    /// Create a lease with a new random token.
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            token: uuid::Uuid::new_v4().to_string(),
+        }
+    }
+}
+
+/// A lock shared between backend instances.
+pub trait DistributedLock: Send + Sync {
+    /// A short name of the backend, used in logs.
+    fn name(&self) -> &'static str;
+
+    /// Try to acquire a lock for the given time.
+    ///
+    /// # Returns
+    ///
+    /// The lease, or `None` if the lock is held by someone else.
+    fn acquire<'a>(
+        &'a self,
+        key: &'a str,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Option<LockLease>, ConnectifyError>;
+
+    /// Extend a held lock to expire `ttl` from now.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the lease has expired (and the lock may have been taken by someone else).
+    fn renew<'a>(
+        &'a self,
+        lease: &'a LockLease,
+        ttl: Duration,
+    ) -> BoxFuture<'a, bool, ConnectifyError>;
+
+    /// Release a held lock. Releasing an expired lease is a no-op.
+    fn release<'a>(&'a self, lease: &'a LockLease) -> BoxFuture<'a, (), ConnectifyError>;
+}
+
+/// A [`DistributedLock`] within a single process, for single-instance deployments and tests.
+#[derive(Debug, Default)]
+pub struct InMemoryLock {
+    /// Token and expiry of the held locks, by key.
+    held: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl InMemoryLock {
+    /// Create a lock without any held keys.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DistributedLock for InMemoryLock {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn acquire<'a>(
+        &'a self,
+        key: &'a str,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Option<LockLease>, ConnectifyError> {
+        Box::pin(async move {
+            let mut held = self.held.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            if held
+                .get(key)
+                .is_some_and(|(_, expires_at)| *expires_at > now)
+            {
+                return Ok(None);
+            }
+            let lease = LockLease::new(key);
+            held.insert(key.to_string(), (lease.token.clone(), now + ttl));
+            Ok(Some(lease))
+        })
+    }
+
+    fn renew<'a>(
+        &'a self,
+        lease: &'a LockLease,
+        ttl: Duration,
+    ) -> BoxFuture<'a, bool, ConnectifyError> {
+        Box::pin(async move {
+            let mut held = self.held.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            match held.get_mut(&lease.key) {
+                Some((token, expires_at)) if *token == lease.token && *expires_at > now => {
+                    *expires_at = now + ttl;
+                    Ok(true)
+                }
+                _ => Ok(false),
+            }
+        })
+    }
+
+    fn release<'a>(&'a self, lease: &'a LockLease) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move {
+            let mut held = self.held.lock().unwrap_or_else(|e| e.into_inner());
+            if held
+                .get(&lease.key)
+                .is_some_and(|(token, _)| *token == lease.token)
+            {
+                held.remove(&lease.key);
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use redis_lock::RedisLock;
+
+#[cfg(feature = "redis")]
+mod redis_lock {
+    use super::*;
+    use redis::aio::ConnectionManager;
+
+    fn redis_error(e: redis::RedisError) -> ConnectifyError {
+        crate::error::external_service_error("redis", e)
+    }
+
+    /// Extends the expiry only if the lock is still held with the given token.
+    const RENEW_SCRIPT: &str = r#"
+        if redis.call("GET", KEYS[1]) == ARGV[1] then
+            return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+        end
+        return 0
+    "#;
+
+    /// Deletes the lock only if it is still held with the given token.
+    const RELEASE_SCRIPT: &str = r#"
+        if redis.call("GET", KEYS[1]) == ARGV[1] then
+            return redis.call("DEL", KEYS[1])
+        end
+        return 0
+    "#;
+
+    /// A [`DistributedLock`] backed by Redis keys with an expiry.
+    #[derive(Clone)]
+    pub struct RedisLock {
+        connection: ConnectionManager,
+        /// Prefix added to all keys, so the Redis instance can be shared.
+        namespace: String,
+    }
+
+    impl RedisLock {
+        /// Connect to Redis, e.g. `redis://localhost:6379`.
+        pub async fn connect(url: &str) -> Result<Self, ConnectifyError> {
+            let client = redis::Client::open(url)
+                .map_err(|e| ConnectifyError::ConfigError(format!("Invalid Redis URL: {}", e)))?;
+            let connection = ConnectionManager::new(client).await.map_err(redis_error)?;
+            Ok(Self {
+                connection,
+                namespace: "connectify:lock:".to_string(),
+            })
+        }
+
+        fn key(&self, key: &str) -> String {
+            format!("{}{}", self.namespace, key)
+        }
+    }
+
+    impl DistributedLock for RedisLock {
+        fn name(&self) -> &'static str {
+            "redis"
+        }
+
+        fn acquire<'a>(
+            &'a self,
+            key: &'a str,
+            ttl: Duration,
+        ) -> BoxFuture<'a, Option<LockLease>, ConnectifyError> {
+            Box::pin(async move {
+                let mut connection = self.connection.clone();
+                let lease = LockLease::new(key);
+                let acquired: Option<String> = redis::cmd("SET")
+                    .arg(self.key(key))
+                    .arg(&lease.token)
+                    .arg("NX")
+                    .arg("PX")
+                    .arg(ttl.as_millis().max(1) as u64)
+                    .query_async(&mut connection)
+                    .await
+                    .map_err(redis_error)?;
+                Ok(acquired.map(|_| lease))
+            })
+        }
+
+        fn renew<'a>(
+            &'a self,
+            lease: &'a LockLease,
+            ttl: Duration,
+        ) -> BoxFuture<'a, bool, ConnectifyError> {
+            Box::pin(async move {
+                let mut connection = self.connection.clone();
+                let renewed: i64 = redis::Script::new(RENEW_SCRIPT)
+                    .key(self.key(&lease.key))
+                    .arg(&lease.token)
+                    .arg(ttl.as_millis().max(1) as u64)
+                    .invoke_async(&mut connection)
+                    .await
+                    .map_err(redis_error)?;
+                Ok(renewed == 1)
+            })
+        }
+
+        fn release<'a>(&'a self, lease: &'a LockLease) -> BoxFuture<'a, (), ConnectifyError> {
+            Box::pin(async move {
+                let mut connection = self.connection.clone();
+                let _: i64 = redis::Script::new(RELEASE_SCRIPT)
+                    .key(self.key(&lease.key))
+                    .arg(&lease.token)
+                    .invoke_async(&mut connection)
+                    .await
+                    .map_err(redis_error)?;
+                Ok(())
+            })
+        }
+    }
+}
+
+/// Try to acquire a lock, waiting up to `timeout` for it to be released.
+///
+/// # Returns
+///
+/// The lease, or `None` if the lock was still held after `timeout`.
+pub async fn acquire_with_wait(
+    lock: &dyn DistributedLock,
+    key: &str,
+    ttl: Duration,
+    timeout: Duration,
+) -> Result<Option<LockLease>, ConnectifyError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(lease) = lock.acquire(key, ttl).await? {
+            return Ok(Some(lease));
+        }
+        if Instant::now() + RETRY_INTERVAL > deadline {
+            return Ok(None);
+        }
+        tokio::time::sleep(RETRY_INTERVAL).await;
+    }
+}
+
+/// Release a lock, logging failures. The lease expires on its own if this fails.
+pub async fn release_quietly(lock: &dyn DistributedLock, lease: &LockLease) {
+    if let Err(e) = lock.release(lease).await {
+        warn!(
+            "Failed to release lock {} in {}: {}",
+            lease.key,
+            lock.name(),
+            e
+        );
+        crate::metrics::record_error("lock", lock.name());
+    }
+}
+
+/// The globally configured lock.
+static DISTRIBUTED_LOCK: Lazy<RwLock<Arc<dyn DistributedLock>>> =
+    Lazy::new(|| RwLock::new(Arc::new(InMemoryLock::new())));
+
+/// Replace the global lock, e.g. with a Redis or Postgres lock shared between instances.
+pub fn configure_distributed_lock(lock: Arc<dyn DistributedLock>) {
+    *DISTRIBUTED_LOCK.write().unwrap_or_else(|e| e.into_inner()) = lock;
+}
+
+/// The global lock.
+pub fn distributed_lock() -> Arc<dyn DistributedLock> {
+    DISTRIBUTED_LOCK
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_lock() {
+        let lock = InMemoryLock::new();
+        let ttl = Duration::from_secs(60);
+
+        let lease = lock.acquire("slot", ttl).await.unwrap().unwrap();
+        assert!(lock.acquire("slot", ttl).await.unwrap().is_none());
+        assert!(lock.acquire("other", ttl).await.unwrap().is_some());
+
+        // Only the holder can renew or release the lock
+        let stranger = LockLease::new("slot");
+        assert!(!lock.renew(&stranger, ttl).await.unwrap());
+        lock.release(&stranger).await.unwrap();
+        assert!(lock.acquire("slot", ttl).await.unwrap().is_none());
+
+        assert!(lock.renew(&lease, ttl).await.unwrap());
+        lock.release(&lease).await.unwrap();
+        assert!(lock.acquire("slot", ttl).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_expired_lease() {
+        let lock = InMemoryLock::new();
+        let lease = lock
+            .acquire("slot", Duration::from_millis(10))
+            .await
+            .unwrap()
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert!(!lock.renew(&lease, Duration::from_secs(60)).await.unwrap());
+        let next = acquire_with_wait(
+            &lock,
+            "slot",
+            Duration::from_secs(60),
+            Duration::from_millis(50),
+        )
+        .await
+        .unwrap();
+        assert!(next.is_some());
+    }
+}
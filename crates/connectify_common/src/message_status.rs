@@ -0,0 +1,253 @@
+//! Delivery statuses of outgoing messages.
+//!
+//! Twilio reports the progress of each SMS (`queued`, `sent`, `delivered`, `undelivered`,
+//! `failed`, ...) to a status callback. The latest status of every message is kept here, so a
+//! client's "I didn't get the SMS" can be looked up by their number.
+//!
+//! The store defaults to an in-memory one; the backend replaces it with the database
+//! repository via [`configure_message_status_store`], so statuses survive restarts.
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// Statuses after which a message's status no longer changes.
+pub const FINAL_MESSAGE_STATUSES: [&str; 6] = [
+    "delivered",
+    "undelivered",
+    "failed",
+    "read",
+    "canceled",
+    "received",
+];
+
+/// The latest delivery status of a message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageStatusRecord {
+    /// The provider's ID of the message, e.g. a Twilio `SM…` SID.
+    pub message_sid: String,
+    /// The recipient's number.
+    pub to: String,
+    /// e.g. `queued`, `sent`, `delivered` or `failed`.
+    pub status: String,
+    /// The provider's error code of undelivered and failed messages, e.g. `30003`.
+    pub error_code: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl MessageStatusRecord {
+    /// A message the provider accepted with a status, e.g. `queued`.
+    pub fn sent(
+        message_sid: impl Into<String>,
+        to: impl Into<String>,
+        status: impl Into<String>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            message_sid: message_sid.into(),
+            to: to.into(),
+            status: status.into(),
+            error_code: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Whether the message's status no longer changes.
+    pub fn is_final(&self) -> bool {
+        FINAL_MESSAGE_STATUSES.contains(&self.status.as_str())
+    }
+
+    /// Apply a reported status. Callbacks may arrive out of order, so a final status is not
+    /// replaced by an intermediate one, e.g. `delivered` by a late `sent`.
+    ///
+    /// # Returns
+    ///
+    /// Whether the status was applied.
+    pub fn apply_status(
+        &mut self,
+        status: &str,
+        error_code: Option<String>,
+        now: DateTime<Utc>,
+    ) -> bool {
+        if self.is_final() && !FINAL_MESSAGE_STATUSES.contains(&status) {
+            return false;
+        }
+        self.status = status.to_string();
+        self.error_code = error_code;
+        self.updated_at = now;
+        true
+    }
+}
+
+/// Storage for message delivery statuses.
+pub trait MessageStatusStore: Send + Sync {
+    /// Store a message's status, replacing an earlier one of the message.
+    fn save(&self, record: MessageStatusRecord) -> BoxFuture<'_, (), ConnectifyError>;
+
+    /// Get the status of a message.
+    fn get<'a>(
+        &'a self,
+        message_sid: &'a str,
+    ) -> BoxFuture<'a, Option<MessageStatusRecord>, ConnectifyError>;
+
+    /// The most recent messages to a number, newest first.
+    fn list_for_recipient<'a>(
+        &'a self,
+        to: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<MessageStatusRecord>, ConnectifyError>;
+}
+
+/// A [`MessageStatusStore`] keeping statuses in memory, for single-instance deployments and
+/// tests.
+#[derive(Debug, Default)]
+pub struct InMemoryMessageStatusStore {
+    records: Mutex<HashMap<String, MessageStatusRecord>>,
+}
+
+impl MessageStatusStore for InMemoryMessageStatusStore {
+    fn save(&self, record: MessageStatusRecord) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move {
+            self.records
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(record.message_sid.clone(), record);
+            Ok(())
+        })
+    }
+
+    fn get<'a>(
+        &'a self,
+        message_sid: &'a str,
+    ) -> BoxFuture<'a, Option<MessageStatusRecord>, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .records
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(message_sid)
+                .cloned())
+        })
+    }
+
+    fn list_for_recipient<'a>(
+        &'a self,
+        to: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<MessageStatusRecord>, ConnectifyError> {
+        Box::pin(async move {
+            let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+            let mut listed: Vec<MessageStatusRecord> = records
+                .values()
+                .filter(|record| record.to == to)
+                .cloned()
+                .collect();
+            listed.sort_by_key(|record| std::cmp::Reverse(record.created_at));
+            listed.truncate(limit);
+            Ok(listed)
+        })
+    }
+}
+
+/// The global store returned by [`message_status_store`].
+static MESSAGE_STATUS_STORE: Lazy<RwLock<Arc<dyn MessageStatusStore>>> =
+    Lazy::new(|| RwLock::new(Arc::new(InMemoryMessageStatusStore::default())));
+
+/// Replace the store used for message statuses.
+pub fn configure_message_status_store(store: Arc<dyn MessageStatusStore>) {
+    *MESSAGE_STATUS_STORE
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = store;
+}
+
+/// The store used for message statuses.
+pub fn message_status_store() -> Arc<dyn MessageStatusStore> {
+    MESSAGE_STATUS_STORE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(second: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(second, 0).unwrap()
+    }
+
+    #[test]
+    fn test_apply_status() {
+        let mut record = MessageStatusRecord::sent("SM1", "+41791234567", "queued", at(0));
+        assert!(record.apply_status("sent", None, at(1)));
+        assert!(record.apply_status("undelivered", Some("30003".to_string()), at(2)));
+        assert_eq!(record.error_code.as_deref(), Some("30003"));
+
+        // A late intermediate status doesn't replace the final one
+        assert!(!record.apply_status("sent", None, at(3)));
+        assert_eq!(record.status, "undelivered");
+        assert_eq!(record.updated_at, at(2));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemoryMessageStatusStore::default();
+        store
+            .save(MessageStatusRecord::sent(
+                "SM1",
+                "+41791234567",
+                "queued",
+                at(0),
+            ))
+            .await
+            .unwrap();
+        store
+            .save(MessageStatusRecord::sent(
+                "SM2",
+                "+41791234567",
+                "queued",
+                at(10),
+            ))
+            .await
+            .unwrap();
+        store
+            .save(MessageStatusRecord::sent(
+                "SM3",
+                "+41797654321",
+                "queued",
+                at(20),
+            ))
+            .await
+            .unwrap();
+
+        let mut delivered = store.get("SM1").await.unwrap().unwrap();
+        delivered.apply_status("delivered", None, at(30));
+        store.save(delivered.clone()).await.unwrap();
+        assert_eq!(store.get("SM1").await.unwrap(), Some(delivered));
+        assert_eq!(store.get("SM4").await.unwrap(), None);
+
+        let sids: Vec<String> = store
+            .list_for_recipient("+41791234567", 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|record| record.message_sid)
+            .collect();
+        assert_eq!(sids, vec!["SM2", "SM1"]);
+        assert_eq!(
+            store
+                .list_for_recipient("+41791234567", 1)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! Prometheus metrics for the Connectify application.
+//!
+//! This module provides a process-wide metrics registry with counters and histograms
+//! for incoming HTTP requests, outbound calls to external APIs (Google, Stripe, Twilio, ...)
+//! and errors. The metrics are exposed in the Prometheus text format by the router
+//! returned from [`metrics_router`], which the backend merges to serve `/metrics`.
+//!
+//! ## Usage
+//!
+//! ```ignore
+//! let app = Router::new()
+//!     .nest("/api", api_router)
+//!     .merge(connectify_common::metrics::metrics_router())
+//!     .layer(axum::middleware::from_fn(connectify_common::metrics::track_http_metrics));
+//! ```
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::error;
+
+/// Label used for requests that did not match any route, to keep label cardinality bounded.
+const UNMATCHED_PATH: &str = "unmatched";
+
+/// Collection of all Prometheus metrics registered by the application.
+pub struct Metrics {
+    registry: Registry,
+    /// Total number of HTTP requests handled, by method, route and status code.
+    pub http_requests_total: IntCounterVec,
+    /// Latency of HTTP requests in seconds, by method and route.
+    pub http_request_duration_seconds: HistogramVec,
+    /// Total number of calls to external APIs, by service, operation and outcome.
+    pub external_api_calls_total: IntCounterVec,
+    /// Latency of calls to external APIs in seconds, by service and operation.
+    pub external_api_call_duration_seconds: HistogramVec,
+    /// Total number of errors, by source and kind.
+    pub errors_total: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("connectify".to_string()), None)
+            .expect("Failed to create metrics registry");
+
+        let http_requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "Total number of HTTP requests"),
+            &["method", "path", "status"],
+        )
+        .expect("Failed to create http_requests_total metric");
+        let http_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency in seconds",
+            ),
+            &["method", "path"],
+        )
+        .expect("Failed to create http_request_duration_seconds metric");
+        let external_api_calls_total = IntCounterVec::new(
+            Opts::new(
+                "external_api_calls_total",
+                "Total number of calls to external APIs",
+            ),
+            &["service", "operation", "outcome"],
+        )
+        .expect("Failed to create external_api_calls_total metric");
+        let external_api_call_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "external_api_call_duration_seconds",
+                "External API call latency in seconds",
+            ),
+            &["service", "operation"],
+        )
+        .expect("Failed to create external_api_call_duration_seconds metric");
+        let errors_total = IntCounterVec::new(
+            Opts::new("errors_total", "Total number of errors"),
+            &["source", "kind"],
+        )
+        .expect("Failed to create errors_total metric");
+
+        for collector in [
+            Box::new(http_requests_total.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_request_duration_seconds.clone()),
+            Box::new(external_api_calls_total.clone()),
+            Box::new(external_api_call_duration_seconds.clone()),
+            Box::new(errors_total.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("Failed to register metric");
+        }
+
+        Self {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            external_api_calls_total,
+            external_api_call_duration_seconds,
+            errors_total,
+        }
+    }
+
+    /// Returns the registry holding all Connectify metrics.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+}
+
+/// The global metrics instance shared across the application.
+pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+/// Records a handled HTTP request.
+///
+/// # Arguments
+///
+/// * `method` - The HTTP method of the request
+/// * `path` - The matched route template (not the raw URI, to keep cardinality low)
+/// * `status` - The HTTP status code of the response
+/// * `elapsed` - The time it took to handle the request
+pub fn record_http_request(method: &str, path: &str, status: u16, elapsed: Duration) {
+    METRICS
+        .http_requests_total
+        .with_label_values(&[method, path, &status.to_string()])
+        .inc();
+    METRICS
+        .http_request_duration_seconds
+        .with_label_values(&[method, path])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// Records a call to an external API.
+///
+/// # Arguments
+///
+/// * `service` - The external service, e.g. "stripe" or "gcal"
+/// * `operation` - The operation performed, e.g. "create_checkout_session"
+/// * `success` - Whether the call succeeded
+/// * `elapsed` - The time the call took
+pub fn record_external_call(service: &str, operation: &str, success: bool, elapsed: Duration) {
+    let outcome = if success { "success" } else { "failure" };
+    METRICS
+        .external_api_calls_total
+        .with_label_values(&[service, operation, outcome])
+        .inc();
+    METRICS
+        .external_api_call_duration_seconds
+        .with_label_values(&[service, operation])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// Records an error.
+///
+/// # Arguments
+///
+/// * `source` - Where the error originated, e.g. "http" or "stripe_webhook"
+/// * `kind` - A short, low-cardinality description of the error kind
+pub fn record_error(source: &str, kind: &str) {
+    METRICS
+        .errors_total
+        .with_label_values(&[source, kind])
+        .inc();
+}
+
+/// Runs an external API call and records its outcome and latency.
+///
+/// # Arguments
+///
+/// * `service` - The external service being called
+/// * `operation` - The operation being performed
+/// * `call` - The future performing the call
+///
+/// # Returns
+///
+/// The result of the call, unchanged
+pub async fn observe_external_call<T, E, F>(service: &str, operation: &str, call: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let start = Instant::now();
+    let result = call.await;
+    record_external_call(service, operation, result.is_ok(), start.elapsed());
+    result
+}
+
+/// Renders all registered metrics in the Prometheus text exposition format.
+pub fn render_metrics() -> String {
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&METRICS.registry.gather(), &mut buffer) {
+        error!("Failed to encode metrics: {}", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+/// Axum middleware that records the count and latency of every HTTP request.
+///
+/// The route template from [`MatchedPath`] is used as the `path` label, so this layer
+/// must be applied with `Router::layer` after the routes have been added.
+pub async fn track_http_metrics(req: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = req.method().to_string();
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| UNMATCHED_PATH.to_string());
+
+    let response = next.run(req).await;
+
+    record_http_request(&method, &path, response.status().as_u16(), start.elapsed());
+    response
+}
+
+/// Handler serving the metrics for Prometheus scraping.
+pub async fn metrics_handler() -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [(
+            header::CONTENT_TYPE,
+            TextEncoder::new().format_type().to_string(),
+        )],
+        render_metrics(),
+    )
+}
+
+/// Creates a router exposing the metrics at `/metrics`.
+///
+/// # Returns
+/// A router that can be merged into the main application router.
+pub fn metrics_router() -> Router {
+    Router::new().route("/metrics", get(metrics_handler))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorded_metrics_are_rendered() {
+        record_http_request("GET", "/api/test", 200, Duration::from_millis(5));
+        record_external_call("stripe", "test_call", false, Duration::from_millis(10));
+        record_error("test", "500");
+
+        let output = render_metrics();
+        assert!(output.contains("connectify_http_requests_total"));
+        assert!(output.contains(r#"path="/api/test""#));
+        assert!(output.contains(r#"outcome="failure""#));
+        assert!(output.contains("connectify_errors_total"));
+    }
+}
@@ -59,3 +59,136 @@ impl DeviceRegistration {
         }
     }
 }
+
+/// Default number of items per page.
+pub const DEFAULT_PAGE_SIZE: u32 = 25;
+
+/// Maximum number of items per page.
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+/// Sort order of a list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// Oldest or smallest first
+    Asc,
+    /// Newest or largest first
+    #[default]
+    Desc,
+}
+
+/// Query parameters for cursor-based pagination.
+///
+/// The cursor is opaque to clients: they pass the `next_cursor` of the previous [`Page`]
+/// to get the next one.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema, utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct CursorQuery {
+    /// Number of items per page (1-100, default 25)
+    #[serde(default)]
+    pub limit: Option<u32>,
+
+    /// Cursor returned as `next_cursor` by the previous page
+    #[serde(default)]
+    pub cursor: Option<String>,
+
+    /// Sort order, newest first by default
+    #[serde(default)]
+    pub order: Option<SortOrder>,
+}
+
+impl CursorQuery {
+    /// The requested page size, clamped to `1..=MAX_PAGE_SIZE`.
+    pub fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE) as usize
+    }
+
+    /// The requested sort order.
+    pub fn order(&self) -> SortOrder {
+        self.order.unwrap_or_default()
+    }
+}
+
+/// A page of a list, returned by list endpoints.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Page<T> {
+    /// The items of this page
+    pub items: Vec<T>,
+
+    /// Cursor for the next page, absent on the last page
+    pub next_cursor: Option<String>,
+
+    /// Whether there are more items after this page
+    pub has_more: bool,
+}
+
+impl<T> Page<T> {
+    /// Build a page from up to `limit + 1` items fetched after the cursor.
+    ///
+    /// Fetching one item more than requested tells whether there is a next page without a
+    /// separate count query. The cursor of the next page is taken from the last returned item.
+    ///
+    /// # Arguments
+    ///
+    /// * `items` - The fetched items, in the requested order
+    /// * `limit` - The requested page size
+    /// * `cursor_of` - Returns the cursor pointing after an item
+    pub fn from_items(mut items: Vec<T>, limit: usize, cursor_of: impl Fn(&T) -> String) -> Self {
+        let has_more = items.len() > limit;
+        items.truncate(limit);
+        let next_cursor = if has_more {
+            items.last().map(cursor_of)
+        } else {
+            None
+        };
+        Self {
+            items,
+            next_cursor,
+            has_more,
+        }
+    }
+
+    /// Convert the items of the page, keeping the pagination state.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            has_more: self.has_more,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_from_items() {
+        let page = Page::from_items(vec![1, 2, 3], 2, |n| n.to_string());
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(page.next_cursor.as_deref(), Some("2"));
+        assert!(page.has_more);
+
+        let last = Page::from_items(vec![3], 2, |n| n.to_string()).map(|n| n * 10);
+        assert_eq!(last.items, vec![30]);
+        assert_eq!(last.next_cursor, None);
+        assert!(!last.has_more);
+    }
+
+    #[test]
+    fn test_cursor_query_defaults() {
+        let query: CursorQuery = serde_json::from_str(r#"{"limit": 500}"#).unwrap();
+        assert_eq!(query.limit(), MAX_PAGE_SIZE as usize);
+        assert_eq!(query.order(), SortOrder::Desc);
+        assert_eq!(CursorQuery::default().limit(), DEFAULT_PAGE_SIZE as usize);
+        assert_eq!(
+            serde_json::from_str::<SortOrder>(r#""asc""#).unwrap(),
+            SortOrder::Asc
+        );
+    }
+}
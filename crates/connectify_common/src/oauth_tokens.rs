@@ -0,0 +1,129 @@
+//! Storage for OAuth refresh tokens.
+//!
+//! Integrations that act on behalf of a user, e.g. a professional who connected their own
+//! Google calendar through the consent screen, keep the refresh token they received here under
+//! the name of the provider. Access tokens are short-lived and only cached by the integration.
+//!
+//! The store defaults to an in-memory one, so tokens are lost on restart; the backend replaces
+//! it with the database repository via [`configure_oauth_token_store`].
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// The refresh token granted by a user to an OAuth provider.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OAuthRefreshToken {
+    /// The provider the token is used with, e.g. `google_calendar`
+    pub provider: String,
+    pub refresh_token: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Storage for refresh tokens, one per provider.
+pub trait OAuthTokenStore: Send + Sync {
+    /// The refresh token of a provider, if a user connected it.
+    fn get<'a>(
+        &'a self,
+        provider: &'a str,
+    ) -> BoxFuture<'a, Option<OAuthRefreshToken>, ConnectifyError>;
+
+    /// Store a refresh token, replacing the provider's previous one.
+    fn put(&self, token: OAuthRefreshToken) -> BoxFuture<'_, (), ConnectifyError>;
+
+    /// Forget the refresh token of a provider.
+    ///
+    /// # Returns
+    ///
+    /// Whether a token was stored.
+    fn delete<'a>(&'a self, provider: &'a str) -> BoxFuture<'a, bool, ConnectifyError>;
+}
+
+/// An [`OAuthTokenStore`] keeping tokens in memory, for development and tests.
+#[derive(Debug, Default)]
+pub struct InMemoryOAuthTokenStore {
+    tokens: Mutex<HashMap<String, OAuthRefreshToken>>,
+}
+
+impl OAuthTokenStore for InMemoryOAuthTokenStore {
+    fn get<'a>(
+        &'a self,
+        provider: &'a str,
+    ) -> BoxFuture<'a, Option<OAuthRefreshToken>, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .tokens
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(provider)
+                .cloned())
+        })
+    }
+
+    fn put(&self, token: OAuthRefreshToken) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move {
+            self.tokens
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(token.provider.clone(), token);
+            Ok(())
+        })
+    }
+
+    fn delete<'a>(&'a self, provider: &'a str) -> BoxFuture<'a, bool, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .tokens
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(provider)
+                .is_some())
+        })
+    }
+}
+
+/// The global store returned by [`oauth_token_store`].
+static OAUTH_TOKEN_STORE: Lazy<RwLock<Arc<dyn OAuthTokenStore>>> =
+    Lazy::new(|| RwLock::new(Arc::new(InMemoryOAuthTokenStore::default())));
+
+/// Replace the store used for refresh tokens.
+pub fn configure_oauth_token_store(store: Arc<dyn OAuthTokenStore>) {
+    *OAUTH_TOKEN_STORE.write().unwrap_or_else(|e| e.into_inner()) = store;
+}
+
+/// The store used for refresh tokens.
+pub fn oauth_token_store() -> Arc<dyn OAuthTokenStore> {
+    OAUTH_TOKEN_STORE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemoryOAuthTokenStore::default();
+        assert_eq!(store.get("google_calendar").await.unwrap(), None);
+
+        let token = |refresh_token: &str| OAuthRefreshToken {
+            provider: "google_calendar".to_string(),
+            refresh_token: refresh_token.to_string(),
+            updated_at: Utc::now(),
+        };
+        store.put(token("first")).await.unwrap();
+        let second = token("second");
+        store.put(second.clone()).await.unwrap();
+        assert_eq!(store.get("google_calendar").await.unwrap(), Some(second));
+
+        assert!(store.delete("google_calendar").await.unwrap());
+        assert!(!store.delete("google_calendar").await.unwrap());
+    }
+}
@@ -0,0 +1,134 @@
+//! Fulfillment orders of payments that are still being paid.
+//!
+//! A checkout has to carry what is fulfilled once it is paid, e.g. the booked slot. Payment
+//! providers limit their metadata (Stripe to 500 characters per value), so the fulfillment
+//! payload is stored here under an order ID and only that ID is passed to the provider; the
+//! payment webhook loads the payload again by the ID.
+//!
+//! The store defaults to an in-memory one; the backend replaces it with the database
+//! repository via [`configure_fulfillment_order_store`], so orders survive restarts and are
+//! found by any backend instance.
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// What to fulfill once a payment is made.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FulfillmentOrder {
+    /// Our ID of the order, passed to the payment provider.
+    pub order_id: String,
+    /// The fulfillment type, e.g. "gcal_booking".
+    pub fulfillment_type: String,
+    /// The JSON fulfillment data.
+    pub payload: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl FulfillmentOrder {
+    /// A new order with a random ID.
+    pub fn new(
+        fulfillment_type: impl Into<String>,
+        payload: impl Into<String>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            order_id: format!("ord_{}", uuid::Uuid::new_v4().simple()),
+            fulfillment_type: fulfillment_type.into(),
+            payload: payload.into(),
+            created_at: now,
+        }
+    }
+}
+
+/// Storage for fulfillment orders.
+pub trait FulfillmentOrderStore: Send + Sync {
+    /// Store an order, replacing an earlier one with the same ID.
+    fn save(&self, order: FulfillmentOrder) -> BoxFuture<'_, (), ConnectifyError>;
+
+    /// Get an order by its ID.
+    fn get<'a>(
+        &'a self,
+        order_id: &'a str,
+    ) -> BoxFuture<'a, Option<FulfillmentOrder>, ConnectifyError>;
+}
+
+/// A [`FulfillmentOrderStore`] keeping orders in memory, for single-instance deployments and
+/// tests.
+#[derive(Debug, Default)]
+pub struct InMemoryFulfillmentOrderStore {
+    orders: Mutex<HashMap<String, FulfillmentOrder>>,
+}
+
+impl FulfillmentOrderStore for InMemoryFulfillmentOrderStore {
+    fn save(&self, order: FulfillmentOrder) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move {
+            self.orders
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(order.order_id.clone(), order);
+            Ok(())
+        })
+    }
+
+    fn get<'a>(
+        &'a self,
+        order_id: &'a str,
+    ) -> BoxFuture<'a, Option<FulfillmentOrder>, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .orders
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(order_id)
+                .cloned())
+        })
+    }
+}
+
+/// The global store returned by [`fulfillment_order_store`].
+static FULFILLMENT_ORDER_STORE: Lazy<RwLock<Arc<dyn FulfillmentOrderStore>>> =
+    Lazy::new(|| RwLock::new(Arc::new(InMemoryFulfillmentOrderStore::default())));
+
+/// Replace the store used for fulfillment orders.
+pub fn configure_fulfillment_order_store(store: Arc<dyn FulfillmentOrderStore>) {
+    *FULFILLMENT_ORDER_STORE
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = store;
+}
+
+/// The store used for fulfillment orders.
+pub fn fulfillment_order_store() -> Arc<dyn FulfillmentOrderStore> {
+    FULFILLMENT_ORDER_STORE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemoryFulfillmentOrderStore::default();
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        // Larger than a Stripe metadata value may be
+        let payload = format!(r#"{{"summary":"{}"}}"#, "x".repeat(1000));
+        let order = FulfillmentOrder::new("gcal_booking", payload, now);
+        assert!(order.order_id.starts_with("ord_"));
+        assert_ne!(
+            order.order_id,
+            FulfillmentOrder::new("gcal_booking", "{}", now).order_id
+        );
+
+        store.save(order.clone()).await.unwrap();
+        assert_eq!(store.get(&order.order_id).await.unwrap(), Some(order));
+        assert_eq!(store.get("ord_unknown").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,271 @@
+//! Payment provider customers of our clients, and the payments they made.
+//!
+//! Providers like Stripe keep a customer object per client, which links their payments,
+//! receipts and subscriptions. This module remembers which provider customer belongs to which
+//! email address, so that a returning client's checkouts are attached to the same customer
+//! instead of creating a new one each time. Completed payments are recorded with their tax
+//! amounts, for bookkeeping.
+//!
+//! The stores default to in-memory ones; the backend replaces them with the database
+//! repository via [`configure_payment_customer_store`] and [`configure_payment_record_store`].
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// Normalize an email address for lookups.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// A provider customer linked to a client's email address.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentCustomer {
+    /// The payment provider, e.g. "stripe"
+    pub provider: String,
+    /// The client's email address, normalized with [`normalize_email`]
+    pub email: String,
+    /// The provider's customer ID, e.g. "cus_..."
+    pub customer_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Storage for the customers of payment providers.
+pub trait PaymentCustomerStore: Send + Sync {
+    /// Link a customer to an email address, replacing an earlier link of the address.
+    fn save(&self, customer: PaymentCustomer) -> BoxFuture<'_, (), ConnectifyError>;
+
+    /// The customer linked to an email address.
+    fn find_by_email<'a>(
+        &'a self,
+        provider: &'a str,
+        email: &'a str,
+    ) -> BoxFuture<'a, Option<PaymentCustomer>, ConnectifyError>;
+}
+
+/// A completed payment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentRecord {
+    /// The payment provider, e.g. "stripe"
+    pub provider: String,
+    /// The provider's payment or session ID
+    pub payment_id: String,
+    /// Our reference for the payment, e.g. the client reference ID
+    pub reference: Option<String>,
+    /// The provider's customer ID, if the payment is linked to one
+    pub customer_id: Option<String>,
+    pub currency: Option<String>,
+    /// Amount charged in the smallest currency unit, including taxes
+    pub amount_total: Option<i64>,
+    /// Amount before taxes and discounts
+    pub amount_subtotal: Option<i64>,
+    /// Taxes included in `amount_total`
+    pub amount_tax: Option<i64>,
+    /// Country of the billing address, which the taxes were calculated for
+    pub country: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Storage for completed payments.
+pub trait PaymentRecordStore: Send + Sync {
+    /// Store a payment, replacing an earlier record of it, e.g. of a redelivered webhook.
+    fn record_payment(&self, payment: PaymentRecord) -> BoxFuture<'_, (), ConnectifyError>;
+
+    /// A stored payment.
+    fn get_payment<'a>(
+        &'a self,
+        provider: &'a str,
+        payment_id: &'a str,
+    ) -> BoxFuture<'a, Option<PaymentRecord>, ConnectifyError>;
+}
+
+/// A [`PaymentCustomerStore`] keeping customers in memory, for single-instance deployments and
+/// tests.
+#[derive(Debug, Default)]
+pub struct InMemoryPaymentCustomerStore {
+    customers: Mutex<HashMap<(String, String), PaymentCustomer>>,
+}
+
+impl PaymentCustomerStore for InMemoryPaymentCustomerStore {
+    fn save(&self, customer: PaymentCustomer) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move {
+            let key = (customer.provider.clone(), normalize_email(&customer.email));
+            self.customers
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(key, customer);
+            Ok(())
+        })
+    }
+
+    fn find_by_email<'a>(
+        &'a self,
+        provider: &'a str,
+        email: &'a str,
+    ) -> BoxFuture<'a, Option<PaymentCustomer>, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .customers
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(&(provider.to_string(), normalize_email(email)))
+                .cloned())
+        })
+    }
+}
+
+/// A [`PaymentRecordStore`] keeping payments in memory, for single-instance deployments and
+/// tests.
+#[derive(Debug, Default)]
+pub struct InMemoryPaymentRecordStore {
+    payments: Mutex<HashMap<(String, String), PaymentRecord>>,
+}
+
+impl PaymentRecordStore for InMemoryPaymentRecordStore {
+    fn record_payment(&self, payment: PaymentRecord) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move {
+            let key = (payment.provider.clone(), payment.payment_id.clone());
+            self.payments
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(key, payment);
+            Ok(())
+        })
+    }
+
+    fn get_payment<'a>(
+        &'a self,
+        provider: &'a str,
+        payment_id: &'a str,
+    ) -> BoxFuture<'a, Option<PaymentRecord>, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .payments
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(&(provider.to_string(), payment_id.to_string()))
+                .cloned())
+        })
+    }
+}
+
+/// The global store returned by [`payment_customer_store`].
+static PAYMENT_CUSTOMER_STORE: Lazy<RwLock<Arc<dyn PaymentCustomerStore>>> =
+    Lazy::new(|| RwLock::new(Arc::new(InMemoryPaymentCustomerStore::default())));
+
+/// Replace the store used for payment provider customers.
+pub fn configure_payment_customer_store(store: Arc<dyn PaymentCustomerStore>) {
+    *PAYMENT_CUSTOMER_STORE
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = store;
+}
+
+/// The store used for payment provider customers.
+pub fn payment_customer_store() -> Arc<dyn PaymentCustomerStore> {
+    PAYMENT_CUSTOMER_STORE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// The global store returned by [`payment_record_store`].
+static PAYMENT_RECORD_STORE: Lazy<RwLock<Arc<dyn PaymentRecordStore>>> =
+    Lazy::new(|| RwLock::new(Arc::new(InMemoryPaymentRecordStore::default())));
+
+/// Replace the store used for completed payments.
+pub fn configure_payment_record_store(store: Arc<dyn PaymentRecordStore>) {
+    *PAYMENT_RECORD_STORE
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = store;
+}
+
+/// The store used for completed payments.
+pub fn payment_record_store() -> Arc<dyn PaymentRecordStore> {
+    PAYMENT_RECORD_STORE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn customer(email: &str, customer_id: &str) -> PaymentCustomer {
+        PaymentCustomer {
+            provider: "stripe".to_string(),
+            email: normalize_email(email),
+            customer_id: customer_id.to_string(),
+            created_at: DateTime::from_timestamp(0, 0).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemoryPaymentCustomerStore::default();
+        store
+            .save(customer("Anna@Example.com", "cus_1"))
+            .await
+            .unwrap();
+
+        // Lookups ignore case and surrounding whitespace
+        assert_eq!(
+            store
+                .find_by_email("stripe", " anna@example.COM ")
+                .await
+                .unwrap(),
+            Some(customer("anna@example.com", "cus_1"))
+        );
+        assert_eq!(
+            store
+                .find_by_email("payrexx", "anna@example.com")
+                .await
+                .unwrap(),
+            None
+        );
+
+        store
+            .save(customer("anna@example.com", "cus_2"))
+            .await
+            .unwrap();
+        assert_eq!(
+            store
+                .find_by_email("stripe", "anna@example.com")
+                .await
+                .unwrap()
+                .map(|customer| customer.customer_id),
+            Some("cus_2".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_record_store() {
+        let store = InMemoryPaymentRecordStore::default();
+        let payment = PaymentRecord {
+            provider: "stripe".to_string(),
+            payment_id: "cs_1".to_string(),
+            reference: Some("order-1".to_string()),
+            customer_id: None,
+            currency: Some("chf".to_string()),
+            amount_total: Some(10810),
+            amount_subtotal: Some(10000),
+            amount_tax: Some(810),
+            country: Some("CH".to_string()),
+            created_at: DateTime::from_timestamp(0, 0).unwrap(),
+        };
+        store.record_payment(payment.clone()).await.unwrap();
+        // Recording a payment again replaces it
+        store.record_payment(payment.clone()).await.unwrap();
+
+        assert_eq!(
+            store.get_payment("stripe", "cs_1").await.unwrap(),
+            Some(payment)
+        );
+        assert_eq!(store.get_payment("stripe", "cs_2").await.unwrap(), None);
+    }
+}
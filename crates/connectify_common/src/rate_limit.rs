@@ -0,0 +1,317 @@
+//! Rate limiting middleware for the Connectify application.
+//!
+//! This module provides a tower layer that limits the number of requests a single client
+//! can make, using a token bucket per client. Clients are identified by the configured name of
+//! their API key if they send a valid `X-Api-Key` header, otherwise by their IP address, so
+//! made-up keys cannot escape the limit.
+//!
+//! The limits are read from the `rate_limit` section of the `AppConfig`. The backend applies
+//! the global limits to all API routes, while individual route groups (e.g. the Stripe webhook)
+//! can apply stricter limits configured under `rate_limit.groups`.
+//!
+//! ## Usage
+//!
+//! ```ignore
+//! Router::new()
+//!     .route("/stripe/webhook", post(stripe_webhook_handler))
+//!     .layer(RateLimitLayer::for_group(&config, "stripe_webhook"));
+//! ```
+//!
+//! If the relevant limits are not configured, the layer passes all requests through.
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, HeaderValue, Request},
+    response::{IntoResponse, Response},
+};
+use connectify_config::{AppConfig, RateLimitRule};
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+use tracing::warn;
+
+use crate::api_key::{ApiKeyStore, CallerIdentity};
+use crate::error::ConnectifyError;
+
+/// Header used to identify API clients.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Number of tracked clients above which idle buckets are purged.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// A single token bucket.
+#[derive(Debug, Clone)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// A token bucket rate limiter keyed by client identifier.
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Create a new rate limiter from a rate limit rule.
+    ///
+    /// # Arguments
+    ///
+    /// * `rule` - The number of requests per minute and the allowed burst size
+    pub fn new(rule: &RateLimitRule) -> Self {
+        let requests_per_minute = rule.requests_per_minute.max(1);
+        Self {
+            capacity: f64::from(rule.burst.unwrap_or(requests_per_minute).max(1)),
+            refill_per_second: f64::from(requests_per_minute) / 60.0,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for the given client.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the request is allowed, or `Err(retry_after)` with the time until
+    /// the next token becomes available.
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        if buckets.len() > MAX_TRACKED_CLIENTS {
+            let capacity = self.capacity;
+            let refill = self.refill_per_second;
+            buckets.retain(|_, bucket| {
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens + elapsed * refill < capacity
+            });
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.capacity,
+            last_refill: now,
+        });
+
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let missing = 1.0 - bucket.tokens;
+            Err(Duration::from_secs_f64(missing / self.refill_per_second))
+        }
+    }
+}
+
+/// Determine the rate limit key for a request.
+///
+/// The caller authenticated by an earlier layer or by a valid `X-Api-Key` header is preferred,
+/// followed by the connection's peer address and finally the first entry of `X-Forwarded-For`.
+/// The key itself is never part of the result, as it ends up in the logs.
+fn client_key<B>(req: &Request<B>, api_keys: Option<&ApiKeyStore>) -> String {
+    let caller = req
+        .extensions()
+        .get::<CallerIdentity>()
+        .cloned()
+        .or_else(|| {
+            let api_key = req.headers().get(API_KEY_HEADER)?.to_str().ok()?;
+            api_keys?.authenticate(api_key)
+        });
+    if let Some(caller) = caller {
+        return format!("caller:{}", caller.name);
+    }
+    if let Some(ConnectInfo(addr)) = req.extensions().get::<ConnectInfo<SocketAddr>>() {
+        return format!("ip:{}", addr.ip());
+    }
+    if let Some(forwarded) = req
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+    {
+        return format!("ip:{}", forwarded.trim());
+    }
+    "unknown".to_string()
+}
+
+/// Tower layer that applies a [`RateLimiter`] to the wrapped service.
+#[derive(Clone, Default)]
+pub struct RateLimitLayer {
+    limiter: Option<Arc<RateLimiter>>,
+    /// Keys whose callers get a bucket of their own.
+    api_keys: Option<Arc<ApiKeyStore>>,
+}
+
+impl RateLimitLayer {
+    /// Create a new layer enforcing the given rule.
+    ///
+    /// Callers are only told apart by their API key if an earlier layer authenticated them.
+    pub fn new(rule: &RateLimitRule) -> Self {
+        Self {
+            limiter: Some(Arc::new(RateLimiter::new(rule))),
+            api_keys: None,
+        }
+    }
+
+    /// Give the callers of the configured API keys a bucket of their own.
+    fn with_api_keys(mut self, config: &AppConfig) -> Self {
+        self.api_keys = config
+            .api_keys
+            .as_ref()
+            .map(|api_keys| Arc::new(ApiKeyStore::from_config(api_keys)));
+        self
+    }
+
+    /// Create a layer enforcing the global limits from the configuration.
+    ///
+    /// Passes all requests through if no `rate_limit` section is configured.
+    pub fn from_config(config: &AppConfig) -> Self {
+        config
+            .rate_limit
+            .as_ref()
+            .map(|rate_limit| Self::new(&rate_limit.global).with_api_keys(config))
+            .unwrap_or_default()
+    }
+
+    /// Create a layer enforcing the limits of a named route group from the configuration.
+    ///
+    /// Passes all requests through if the group is not configured.
+    pub fn for_group(config: &AppConfig, group: &str) -> Self {
+        config
+            .rate_limit
+            .as_ref()
+            .and_then(|rate_limit| rate_limit.groups.get(group))
+            .map(|rule| Self::new(rule).with_api_keys(config))
+            .unwrap_or_default()
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            limiter: self.limiter.clone(),
+            api_keys: self.api_keys.clone(),
+        }
+    }
+}
+
+/// Service created by [`RateLimitLayer`].
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limiter: Option<Arc<RateLimiter>>,
+    api_keys: Option<Arc<ApiKeyStore>>,
+}
+
+impl<S> Service<Request<Body>> for RateLimit<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        if let Some(limiter) = &self.limiter {
+            let key = client_key(&req, self.api_keys.as_deref());
+            if let Err(retry_after) = limiter.check(&key) {
+                warn!("Rate limit exceeded for {} on {}", key, req.uri().path());
+                return Box::pin(async move { Ok(rate_limited_response(retry_after)) });
+            }
+        }
+        Box::pin(self.inner.call(req))
+    }
+}
+
+/// Build the 429 response returned when a client exceeds its limit.
+fn rate_limited_response(retry_after: Duration) -> Response {
+    let retry_after_secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    let mut response = ConnectifyError::RateLimitError(format!(
+        "Too many requests, retry after {} seconds",
+        retry_after_secs
+    ))
+    .into_response();
+    if let Ok(value) = HeaderValue::from_str(&retry_after_secs.to_string()) {
+        response.headers_mut().insert(header::RETRY_AFTER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use connectify_config::{ApiKeyEntry, ApiKeysConfig};
+
+    #[test]
+    fn test_bucket_allows_burst_then_limits() {
+        let limiter = RateLimiter::new(&RateLimitRule {
+            requests_per_minute: 60,
+            burst: Some(2),
+        });
+        let now = Instant::now();
+
+        assert!(limiter.check_at("client", now).is_ok());
+        assert!(limiter.check_at("client", now).is_ok());
+        let retry_after = limiter.check_at("client", now).unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(1));
+
+        // Other clients have their own bucket
+        assert!(limiter.check_at("other", now).is_ok());
+
+        // One token is refilled after a second
+        assert!(limiter
+            .check_at("client", now + Duration::from_secs(1))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_client_key_prefers_valid_api_key() {
+        let api_keys = ApiKeyStore::from_config(&ApiKeysConfig {
+            keys: vec![ApiKeyEntry {
+                name: "partner".to_string(),
+                key: Some("abc".to_string()),
+                key_sha256: None,
+                scopes: vec![],
+            }],
+        });
+        let request = |api_key: &str| {
+            Request::builder()
+                .header(API_KEY_HEADER, api_key)
+                .header("x-forwarded-for", "10.0.0.1, 10.0.0.2")
+                .body(())
+                .unwrap()
+        };
+        assert_eq!(
+            client_key(&request("abc"), Some(&api_keys)),
+            "caller:partner"
+        );
+
+        // Unknown keys share the bucket of their address
+        assert_eq!(
+            client_key(&request("made-up"), Some(&api_keys)),
+            "ip:10.0.0.1"
+        );
+        assert_eq!(client_key(&request("abc"), None), "ip:10.0.0.1");
+    }
+}
@@ -0,0 +1,123 @@
+//! Request ID propagation for the Connectify application.
+//!
+//! The [`propagate_request_id`] middleware assigns every incoming request an `X-Request-Id`
+//! (reusing the caller's one if it is well-formed), records it on a tracing span wrapping the
+//! request, and echoes it in the response. While the request is being handled, the ID is
+//! available through [`current_request_id`], and outgoing calls made with
+//! [`RequestIdExt::with_request_id`] forward it, so that e.g. the logs of a Stripe webhook and
+//! of the fulfillment call it triggers can be correlated.
+//!
+//! ## Usage
+//!
+//! ```ignore
+//! let app = app.layer(axum::middleware::from_fn(request_id::propagate_request_id));
+//!
+//! HTTP_CLIENT.post(url).with_request_id().json(&body).send().await?;
+//! ```
+
+use axum::{extract::Request, http::HeaderValue, middleware::Next, response::Response};
+use tracing::{info_span, Instrument};
+
+/// Header carrying the request ID.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Maximum accepted length of an incoming request ID.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: String;
+}
+
+/// The ID of a request, available as a request extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Returns the ID of the request currently being handled, if any.
+pub fn current_request_id() -> Option<String> {
+    CURRENT_REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Run a future with the given request ID as the current one.
+///
+/// Useful for background tasks spawned from a request, which do not inherit the ID.
+pub async fn with_request_id<F: std::future::Future>(id: String, future: F) -> F::Output {
+    CURRENT_REQUEST_ID.scope(id, future).await
+}
+
+/// Accept an incoming request ID only if it is short and printable, to keep logs clean.
+fn sanitize(value: &HeaderValue) -> Option<String> {
+    let id = value.to_str().ok()?.trim();
+    let valid = !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+    valid.then(|| id.to_string())
+}
+
+/// Axum middleware that assigns and propagates the `X-Request-Id`.
+pub async fn propagate_request_id(mut req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(sanitize)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    req.extensions_mut().insert(RequestId(id.clone()));
+    let span = info_span!(
+        "request",
+        request_id = %id,
+        method = %req.method(),
+        path = %req.uri().path()
+    );
+
+    let mut response = CURRENT_REQUEST_ID
+        .scope(id.clone(), next.run(req).instrument(span))
+        .await;
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Extension trait forwarding the current request ID on outgoing HTTP requests.
+pub trait RequestIdExt {
+    /// Adds the `X-Request-Id` header if a request is currently being handled.
+    fn with_request_id(self) -> Self;
+}
+
+impl RequestIdExt for reqwest::RequestBuilder {
+    fn with_request_id(self) -> Self {
+        match current_request_id() {
+            Some(id) => self.header(REQUEST_ID_HEADER, id),
+            None => self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_request_id() {
+        assert_eq!(
+            sanitize(&HeaderValue::from_static("abc-123")),
+            Some("abc-123".to_string())
+        );
+        assert_eq!(sanitize(&HeaderValue::from_static("")), None);
+        assert_eq!(sanitize(&HeaderValue::from_static("a b")), None);
+        assert_eq!(
+            sanitize(&HeaderValue::from_str(&"a".repeat(200)).unwrap()),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_current_request_id_is_scoped() {
+        assert_eq!(current_request_id(), None);
+        let id = with_request_id("req-1".to_string(), async { current_request_id() }).await;
+        assert_eq!(id, Some("req-1".to_string()));
+    }
+}
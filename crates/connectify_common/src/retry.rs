@@ -0,0 +1,481 @@
+//! Retry with exponential backoff for the Connectify application.
+//!
+//! [`retry_async`] re-runs a fallible async operation according to a [`RetryPolicy`], as long as
+//! the error is classified as transient by the [`Retryable`] trait. Delays grow exponentially
+//! and are jittered, so that many clients failing at the same time do not retry in lockstep.
+//!
+//! ## Usage
+//!
+//! ```ignore
+//! let policy = RetryPolicy::default();
+//! let busy = retry_async(&policy, || calendar.get_busy_times(&id, start, end)).await?;
+//!
+//! // HTTP requests are retried on transport errors and 429/5xx responses
+//! let response = send_with_retry(&policy, HTTP_CLIENT.get(&url)).await?;
+//!
+//! // Requests that create something only when the server surely didn't process them
+//! let response = send_non_idempotent_with_retry(&policy, HTTP_CLIENT.post(&url)).await?;
+//! ```
+//!
+//! When a server asks for a minimum delay through a `Retry-After` header, the next attempt
+//! waits at least that long. Delays beyond the policy's `max_delay` are not waited for: the
+//! error is returned right away, so callers can surface it instead of blocking a request.
+
+use chrono::{DateTime, Utc};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::collections::hash_map::RandomState;
+use std::fmt::Display;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::error::ConnectifyError;
+use crate::http::circuit_breaker::{send_guarded, HttpClientError};
+
+/// Describes how often and how fast an operation is retried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub initial_delay: Duration,
+    /// Upper bound for the delay between attempts.
+    pub max_delay: Duration,
+    /// Factor the delay grows by after each attempt.
+    pub multiplier: f64,
+    /// Whether to randomize delays between 50% and 100% of their nominal value.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn no_retry() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// The delay to wait after the given (1-based) failed attempt.
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31) as i32;
+        let nominal = self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        let capped = nominal.min(self.max_delay.as_secs_f64());
+        let factor = if self.jitter {
+            0.5 + 0.5 * random_fraction()
+        } else {
+            1.0
+        };
+        Duration::from_secs_f64(capped * factor)
+    }
+}
+
+/// Parse the value of a `Retry-After` header, either delay-seconds or an HTTP date.
+///
+/// Dates in the past yield a zero delay.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// The delay to wait before retrying, honouring a server-provided minimum delay.
+///
+/// Returns `None` if the server asks for a longer delay than the policy allows.
+fn next_delay(
+    policy: &RetryPolicy,
+    attempt: u32,
+    retry_after: Option<Duration>,
+) -> Option<Duration> {
+    let delay = policy.delay_for(attempt);
+    match retry_after {
+        Some(retry_after) if retry_after > policy.max_delay => None,
+        Some(retry_after) => Some(delay.max(retry_after)),
+        None => Some(delay),
+    }
+}
+
+/// A random number in `[0, 1)`, good enough for jitter.
+fn random_fraction() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Classifies errors as transient (worth retrying) or permanent.
+pub trait Retryable {
+    /// Returns `true` if the operation may succeed when retried.
+    fn is_retryable(&self) -> bool;
+
+    /// The minimum delay before a retry requested by the server, if any.
+    fn retry_after(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Returns `true` for HTTP statuses that indicate a transient failure.
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+        || (status.is_server_error() && status != StatusCode::NOT_IMPLEMENTED)
+}
+
+impl Retryable for reqwest::Error {
+    fn is_retryable(&self) -> bool {
+        self.is_timeout() || self.is_connect() || self.status().is_some_and(is_retryable_status)
+    }
+}
+
+impl Retryable for HttpClientError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            // Retrying against an open circuit would only fail again
+            HttpClientError::CircuitOpen { .. } => false,
+            HttpClientError::Request(e) => e.is_retryable(),
+        }
+    }
+}
+
+impl Retryable for ConnectifyError {
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ConnectifyError::HttpError(_)
+                | ConnectifyError::TimeoutError(_)
+                | ConnectifyError::RateLimitError(_)
+                | ConnectifyError::ExternalServiceError(_)
+        )
+    }
+}
+
+/// Runs an async operation, retrying it on transient errors.
+///
+/// # Arguments
+///
+/// * `policy` - How often and how fast to retry
+/// * `op` - Creates the future for each attempt
+///
+/// # Returns
+///
+/// The first successful result, the first permanent error, or the error of the last attempt.
+pub async fn retry_async<T, E, F, Fut>(policy: &RetryPolicy, op: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Retryable + Display,
+{
+    retry_async_with_retry_after(policy, E::is_retryable, E::retry_after, op).await
+}
+
+/// Like [`retry_async`], with a custom classification of retryable errors.
+///
+/// Useful for error types from other crates that cannot implement [`Retryable`].
+pub async fn retry_async_if<T, E, F, Fut, C>(
+    policy: &RetryPolicy,
+    is_retryable: C,
+    op: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Display,
+    C: Fn(&E) -> bool,
+{
+    retry_async_with_retry_after(policy, is_retryable, |_| None, op).await
+}
+
+/// Like [`retry_async_if`], waiting at least as long as `retry_after` returns for an error.
+///
+/// Useful for APIs that report a `Retry-After` delay along with rate limit errors.
+pub async fn retry_async_with_retry_after<T, E, F, Fut, C, R>(
+    policy: &RetryPolicy,
+    is_retryable: C,
+    retry_after: R,
+    mut op: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Display,
+    C: Fn(&E) -> bool,
+    R: Fn(&E) -> Option<Duration>,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(e) if attempt < policy.max_attempts && is_retryable(&e) => {
+                let Some(delay) = next_delay(policy, attempt, retry_after(&e)) else {
+                    warn!(
+                        "Attempt {}/{} failed: {}. Retry-After exceeds {:?}, giving up",
+                        attempt, policy.max_attempts, e, policy.max_delay
+                    );
+                    return Err(e);
+                };
+                warn!(
+                    "Attempt {}/{} failed: {}. Retrying in {:?}",
+                    attempt, policy.max_attempts, e, delay
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Returns `true` for HTTP statuses that show a non-idempotent request was not processed.
+///
+/// Only 429 qualifies: after a timeout or a 5xx response, the server may have processed it.
+pub fn is_retryable_non_idempotent_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Sends an HTTP request through the circuit breaker, retrying transport errors and
+/// 429/5xx responses.
+///
+/// Unlike [`retry_async`], a retryable response is returned unchanged after the last attempt,
+/// or when its `Retry-After` header asks for a longer delay than the policy allows, so callers
+/// can handle it like any other error response. Requests with streaming bodies cannot be
+/// cloned and are sent only once.
+///
+/// Only use it for idempotent requests, e.g. GET and DELETE, see
+/// [`send_non_idempotent_with_retry`].
+pub async fn send_with_retry(
+    policy: &RetryPolicy,
+    request: RequestBuilder,
+) -> Result<Response, HttpClientError> {
+    send_with_retry_when(
+        policy,
+        request,
+        HttpClientError::is_retryable,
+        is_retryable_status,
+    )
+    .await
+}
+
+/// Like [`send_with_retry`], for requests the server must not process twice, e.g. POSTs
+/// creating a payment or sending a message without an idempotency key.
+///
+/// Only connect errors and 429 responses are retried, as the server surely didn't process the
+/// request then. Timeouts and 5xx responses are returned right away, since the request may
+/// have been processed before they occurred.
+pub async fn send_non_idempotent_with_retry(
+    policy: &RetryPolicy,
+    request: RequestBuilder,
+) -> Result<Response, HttpClientError> {
+    send_with_retry_when(
+        policy,
+        request,
+        |e| matches!(e, HttpClientError::Request(e) if e.is_connect()),
+        is_retryable_non_idempotent_status,
+    )
+    .await
+}
+
+async fn send_with_retry_when(
+    policy: &RetryPolicy,
+    request: RequestBuilder,
+    retryable_error: impl Fn(&HttpClientError) -> bool,
+    retryable_status: impl Fn(StatusCode) -> bool,
+) -> Result<Response, HttpClientError> {
+    let mut attempt = 1;
+    loop {
+        let Some(builder) = request.try_clone() else {
+            return send_guarded(request).await;
+        };
+        let result = send_guarded(builder).await;
+        let retryable = match &result {
+            Ok(response) => retryable_status(response.status()),
+            Err(e) => retryable_error(e),
+        };
+        if !retryable || attempt >= policy.max_attempts {
+            return result;
+        }
+
+        let retry_after = result.as_ref().ok().and_then(|response| {
+            response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_retry_after)
+        });
+        let Some(delay) = next_delay(policy, attempt, retry_after) else {
+            return result;
+        };
+        match &result {
+            Ok(response) => warn!(
+                "Attempt {}/{} returned {} from {}. Retrying in {:?}",
+                attempt,
+                policy.max_attempts,
+                response.status(),
+                response.url(),
+                delay
+            ),
+            Err(e) => warn!(
+                "Attempt {}/{} failed: {}. Retrying in {:?}",
+                attempt, policy.max_attempts, e, delay
+            ),
+        }
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy {
+            initial_delay: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_delay_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+            jitter: false,
+            ..Default::default()
+        };
+        assert_eq!(policy.delay_for(1), Duration::from_millis(100));
+        assert_eq!(policy.delay_for(2), Duration::from_millis(200));
+        assert_eq!(policy.delay_for(3), Duration::from_millis(300));
+
+        let jittered = RetryPolicy {
+            jitter: true,
+            ..policy
+        }
+        .delay_for(2);
+        assert!(jittered >= Duration::from_millis(100) && jittered <= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_retries_only_transient_errors() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), ConnectifyError> = retry_async(&fast_policy(), || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(ConnectifyError::TimeoutError("slow".to_string()))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let calls = AtomicU32::new(0);
+        let result = retry_async(&fast_policy(), || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(ConnectifyError::HttpError("reset".to_string())),
+                _ => Ok("done"),
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), "done");
+
+        let calls = AtomicU32::new(0);
+        let result: Result<(), ConnectifyError> = retry_async(&fast_policy(), || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(ConnectifyError::ValidationError("bad".to_string()))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        for status in [
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::REQUEST_TIMEOUT,
+            StatusCode::BAD_GATEWAY,
+        ] {
+            assert!(is_retryable_status(status));
+        }
+        assert!(!is_retryable_status(StatusCode::NOT_IMPLEMENTED));
+        assert!(!is_retryable_status(StatusCode::BAD_REQUEST));
+
+        // A non-idempotent request may have been processed before a timeout or 5xx
+        assert!(is_retryable_non_idempotent_status(
+            StatusCode::TOO_MANY_REQUESTS
+        ));
+        assert!(!is_retryable_non_idempotent_status(
+            StatusCode::REQUEST_TIMEOUT
+        ));
+        assert!(!is_retryable_non_idempotent_status(
+            StatusCode::SERVICE_UNAVAILABLE
+        ));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn test_honours_retry_after() {
+        let policy = RetryPolicy {
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(50),
+            ..Default::default()
+        };
+
+        let calls = AtomicU32::new(0);
+        let started = std::time::Instant::now();
+        let result = retry_async_with_retry_after(
+            &policy,
+            |_: &ConnectifyError| true,
+            |_| Some(Duration::from_millis(20)),
+            || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(ConnectifyError::RateLimitError("slow down".to_string())),
+                    _ => Ok("done"),
+                }
+            },
+        )
+        .await;
+        assert_eq!(result.unwrap(), "done");
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        // Waiting longer than the policy allows fails fast
+        let calls = AtomicU32::new(0);
+        let result: Result<(), ConnectifyError> = retry_async_with_retry_after(
+            &policy,
+            |_| true,
+            |_| Some(Duration::from_secs(60)),
+            || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(ConnectifyError::RateLimitError("slow down".to_string()))
+            },
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,318 @@
+//! Runtime kill switches for the Connectify application.
+//!
+//! Operators can switch features off during an incident without redeploying, e.g. stop new
+//! bookings while the calendar is misbehaving or stop outgoing SMS. Flags are named (see the
+//! constants in this module) and enabled unless explicitly disabled.
+//!
+//! The flags live in a global [`RuntimeFlags`] store, which the backend refreshes periodically
+//! from its [`RuntimeFlagSource`]s: the `runtime_flags` section of the configuration (re-read
+//! from disk) and, when a database is configured, the `runtime_flags` table.
+//!
+//! ## Usage
+//!
+//! Routes are guarded with [`feature_guard`], which answers `503 Service Unavailable` while the
+//! flag is disabled:
+//!
+//! ```ignore
+//! Router::new().route("/book", post(book_slot_handler).layer(feature_guard(BOOKINGS)))
+//! ```
+//!
+//! Code paths without a route of their own check the store directly:
+//!
+//! ```ignore
+//! if !runtime_flags().is_enabled(SMS) {
+//!     return Err(...);
+//! }
+//! ```
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use once_cell::sync::Lazy;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use tracing::{info, warn};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// New calendar bookings.
+pub const BOOKINGS: &str = "bookings";
+
+/// New payment checkout sessions.
+pub const CHECKOUT: &str = "checkout";
+
+/// Outgoing push notifications.
+pub const NOTIFICATIONS: &str = "notifications";
+
+/// Outgoing SMS.
+pub const SMS: &str = "sms";
+
+/// Outgoing voice calls.
+pub const CALLS: &str = "calls";
+
+/// A store of named feature flags.
+///
+/// Flags that were never set are enabled.
+#[derive(Debug, Default)]
+pub struct RuntimeFlags {
+    flags: RwLock<HashMap<String, bool>>,
+}
+
+impl RuntimeFlags {
+    /// Create a store with all flags enabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check whether a flag is enabled.
+    pub fn is_enabled(&self, flag: &str) -> bool {
+        self.flags
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(flag)
+            .copied()
+            .unwrap_or(true)
+    }
+
+    /// Enable or disable a flag.
+    pub fn set(&self, flag: impl Into<String>, enabled: bool) {
+        let flag = flag.into();
+        let previous = self
+            .flags
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(flag.clone(), enabled);
+        log_change(&flag, previous.unwrap_or(true), enabled);
+    }
+
+    /// Replace all flags, e.g. after loading them from the sources.
+    ///
+    /// Flags missing from `flags` are enabled again.
+    pub fn replace(&self, flags: HashMap<String, bool>) {
+        let mut current = self.flags.write().unwrap_or_else(|e| e.into_inner());
+        for (flag, &enabled) in &flags {
+            log_change(flag, current.get(flag).copied().unwrap_or(true), enabled);
+        }
+        for (flag, &enabled) in current.iter() {
+            if !flags.contains_key(flag) {
+                log_change(flag, enabled, true);
+            }
+        }
+        *current = flags;
+    }
+
+    /// The explicitly set flags.
+    pub fn snapshot(&self) -> BTreeMap<String, bool> {
+        self.flags
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(flag, &enabled)| (flag.clone(), enabled))
+            .collect()
+    }
+
+    /// Reload the flags from the given sources.
+    ///
+    /// Sources are merged in order, so later sources override earlier ones. If a source fails,
+    /// the current flags are kept.
+    pub async fn refresh(
+        &self,
+        sources: &[Arc<dyn RuntimeFlagSource>],
+    ) -> Result<(), ConnectifyError> {
+        let mut flags = HashMap::new();
+        for source in sources {
+            let loaded = source.load().await.map_err(|e| {
+                warn!("Failed to load runtime flags from {}: {}", source.name(), e);
+                e
+            })?;
+            flags.extend(loaded);
+        }
+        self.replace(flags);
+        Ok(())
+    }
+}
+
+fn log_change(flag: &str, was_enabled: bool, enabled: bool) {
+    match (was_enabled, enabled) {
+        (true, false) => warn!("Runtime flag '{}' disabled", flag),
+        (false, true) => info!("Runtime flag '{}' enabled", flag),
+        _ => {}
+    }
+}
+
+/// A source of runtime flag states.
+pub trait RuntimeFlagSource: Send + Sync {
+    /// A short name of the source, used in logs.
+    fn name(&self) -> &'static str;
+
+    /// Load the flag states defined by this source.
+    fn load(&self) -> BoxFuture<'_, HashMap<String, bool>, ConnectifyError>;
+}
+
+/// Reads the flags from the `runtime_flags` section of the configuration.
+///
+/// The configuration is loaded again on every refresh, so edits to the config files (or
+/// environment) take effect without a restart.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConfigFlagSource;
+
+impl RuntimeFlagSource for ConfigFlagSource {
+    fn name(&self) -> &'static str {
+        "config"
+    }
+
+    fn load(&self) -> BoxFuture<'_, HashMap<String, bool>, ConnectifyError> {
+        Box::pin(async {
+            let config = connectify_config::load_config()
+                .map_err(|e| ConnectifyError::ConfigError(e.to_string()))?;
+            Ok(config.runtime_flags.map(|c| c.flags).unwrap_or_default())
+        })
+    }
+}
+
+/// The global runtime flags.
+static RUNTIME_FLAGS: Lazy<RuntimeFlags> = Lazy::new(RuntimeFlags::new);
+
+/// The global runtime flags, as checked by [`feature_guard`].
+pub fn runtime_flags() -> &'static RuntimeFlags {
+    &RUNTIME_FLAGS
+}
+
+/// Guard routes with a runtime flag of the global store.
+pub fn feature_guard(flag: &'static str) -> FeatureGuardLayer {
+    FeatureGuardLayer { flag }
+}
+
+/// Layer rejecting requests with 503 while a runtime flag is disabled.
+#[derive(Debug, Clone, Copy)]
+pub struct FeatureGuardLayer {
+    flag: &'static str,
+}
+
+impl<S> Layer<S> for FeatureGuardLayer {
+    type Service = FeatureGuard<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        FeatureGuard {
+            inner,
+            flag: self.flag,
+        }
+    }
+}
+
+/// Service created by [`FeatureGuardLayer`].
+#[derive(Debug, Clone)]
+pub struct FeatureGuard<S> {
+    inner: S,
+    flag: &'static str,
+}
+
+impl<S> Service<Request<Body>> for FeatureGuard<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        if runtime_flags().is_enabled(self.flag) {
+            return Box::pin(self.inner.call(req));
+        }
+        warn!(
+            "Rejected {} {}: runtime flag '{}' is disabled",
+            req.method(),
+            req.uri().path(),
+            self.flag
+        );
+        let response = disabled_response(self.flag);
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+fn disabled_response(flag: &str) -> Response {
+    let status = StatusCode::SERVICE_UNAVAILABLE;
+    crate::metrics::record_error("runtime_flag", flag);
+    let body = Json(json!({
+        "error": {
+            "message": format!("This feature is temporarily disabled ({})", flag),
+            "code": status.as_u16(),
+        }
+    }));
+    (status, body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use tower::ServiceExt;
+
+    struct StaticSource(&'static [(&'static str, bool)]);
+
+    impl RuntimeFlagSource for StaticSource {
+        fn name(&self) -> &'static str {
+            "static"
+        }
+
+        fn load(&self) -> BoxFuture<'_, HashMap<String, bool>, ConnectifyError> {
+            Box::pin(async move {
+                Ok(self
+                    .0
+                    .iter()
+                    .map(|&(flag, enabled)| (flag.to_string(), enabled))
+                    .collect())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_merges_sources() {
+        let flags = RuntimeFlags::new();
+        assert!(flags.is_enabled(BOOKINGS));
+
+        flags.set(SMS, false);
+        let sources: Vec<Arc<dyn RuntimeFlagSource>> = vec![
+            Arc::new(StaticSource(&[(BOOKINGS, false), (CHECKOUT, false)])),
+            Arc::new(StaticSource(&[(CHECKOUT, true)])),
+        ];
+        flags.refresh(&sources).await.unwrap();
+
+        assert!(!flags.is_enabled(BOOKINGS));
+        assert!(flags.is_enabled(CHECKOUT));
+        // Flags missing from the sources are enabled again
+        assert!(flags.is_enabled(SMS));
+    }
+
+    #[tokio::test]
+    async fn test_feature_guard() {
+        const FLAG: &str = "feature_guard_test";
+        let app = Router::new().route("/", get(|| async { "ok" }).layer(feature_guard(FLAG)));
+        let request = || Request::builder().uri("/").body(Body::empty()).unwrap();
+
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        runtime_flags().set(FLAG, false);
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        runtime_flags().set(FLAG, true);
+        let response = app.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
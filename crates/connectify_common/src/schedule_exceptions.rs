@@ -0,0 +1,199 @@
+//! One-off exceptions to the weekly working hours.
+//!
+//! Staff can open extra hours or close early on a specific date without deploying a new
+//! config. Exceptions are stored here and merged into the working hours of that date when
+//! availability is calculated.
+//!
+//! The store defaults to an in-memory one; the backend replaces it with the database
+//! repository via [`configure_schedule_exception_store`], so exceptions survive restarts and
+//! are shared between instances.
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// Whether an exception adds or removes working hours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduleExceptionKind {
+    /// Extra working hours, e.g. a Saturday morning.
+    Open,
+    /// No working hours, e.g. closing early.
+    Closed,
+}
+
+impl ScheduleExceptionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScheduleExceptionKind::Open => "open",
+            ScheduleExceptionKind::Closed => "closed",
+        }
+    }
+}
+
+impl fmt::Display for ScheduleExceptionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ScheduleExceptionKind {
+    type Err = ConnectifyError;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "open" => Ok(ScheduleExceptionKind::Open),
+            "closed" => Ok(ScheduleExceptionKind::Closed),
+            other => Err(ConnectifyError::ValidationError(format!(
+                "Unknown schedule exception kind: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A change of the working hours on one date, in the calendar's time zone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleException {
+    pub id: String,
+    pub date: NaiveDate,
+    pub kind: ScheduleExceptionKind,
+    /// Start of the changed hours; closing from the start of the day if not set
+    pub start_time: Option<NaiveTime>,
+    /// End of the changed hours; closing until the end of the day if not set
+    pub end_time: Option<NaiveTime>,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Storage for schedule exceptions.
+pub trait ScheduleExceptionStore: Send + Sync {
+    /// Store a new exception.
+    fn create(&self, exception: ScheduleException) -> BoxFuture<'_, (), ConnectifyError>;
+
+    /// The exceptions from `start` to `end` (inclusive), sorted by date and start time.
+    fn between(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> BoxFuture<'_, Vec<ScheduleException>, ConnectifyError>;
+
+    /// Remove an exception.
+    ///
+    /// # Returns
+    ///
+    /// Whether the exception existed.
+    fn delete<'a>(&'a self, id: &'a str) -> BoxFuture<'a, bool, ConnectifyError>;
+}
+
+/// A [`ScheduleExceptionStore`] keeping exceptions in memory, for single-instance deployments
+/// and tests.
+#[derive(Debug, Default)]
+pub struct InMemoryScheduleExceptionStore {
+    exceptions: Mutex<HashMap<String, ScheduleException>>,
+}
+
+impl ScheduleExceptionStore for InMemoryScheduleExceptionStore {
+    fn create(&self, exception: ScheduleException) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move {
+            self.exceptions
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(exception.id.clone(), exception);
+            Ok(())
+        })
+    }
+
+    fn between(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> BoxFuture<'_, Vec<ScheduleException>, ConnectifyError> {
+        Box::pin(async move {
+            let exceptions = self.exceptions.lock().unwrap_or_else(|e| e.into_inner());
+            let mut between: Vec<ScheduleException> = exceptions
+                .values()
+                .filter(|exception| start <= exception.date && exception.date <= end)
+                .cloned()
+                .collect();
+            between.sort_by_key(|exception| (exception.date, exception.start_time));
+            Ok(between)
+        })
+    }
+
+    fn delete<'a>(&'a self, id: &'a str) -> BoxFuture<'a, bool, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .exceptions
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(id)
+                .is_some())
+        })
+    }
+}
+
+/// The global store returned by [`schedule_exception_store`].
+static SCHEDULE_EXCEPTION_STORE: Lazy<RwLock<Arc<dyn ScheduleExceptionStore>>> =
+    Lazy::new(|| RwLock::new(Arc::new(InMemoryScheduleExceptionStore::default())));
+
+/// Replace the store used for schedule exceptions.
+pub fn configure_schedule_exception_store(store: Arc<dyn ScheduleExceptionStore>) {
+    *SCHEDULE_EXCEPTION_STORE
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = store;
+}
+
+/// The store used for schedule exceptions.
+pub fn schedule_exception_store() -> Arc<dyn ScheduleExceptionStore> {
+    SCHEDULE_EXCEPTION_STORE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exception(id: &str, day: u32, start_hour: u32) -> ScheduleException {
+        ScheduleException {
+            id: id.to_string(),
+            date: NaiveDate::from_ymd_opt(2025, 5, day).unwrap(),
+            kind: ScheduleExceptionKind::Open,
+            start_time: NaiveTime::from_hms_opt(start_hour, 0, 0),
+            end_time: NaiveTime::from_hms_opt(start_hour + 2, 0, 0),
+            reason: None,
+            created_at: DateTime::from_timestamp(0, 0).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemoryScheduleExceptionStore::default();
+        store.create(exception("b", 17, 14)).await.unwrap();
+        store.create(exception("a", 17, 9)).await.unwrap();
+        store.create(exception("c", 20, 9)).await.unwrap();
+
+        let date = |day| NaiveDate::from_ymd_opt(2025, 5, day).unwrap();
+        assert_eq!(
+            store.between(date(15), date(17)).await.unwrap(),
+            vec![exception("a", 17, 9), exception("b", 17, 14)]
+        );
+        assert_eq!(store.between(date(15), date(20)).await.unwrap().len(), 3);
+
+        assert!(store.delete("a").await.unwrap());
+        assert!(!store.delete("a").await.unwrap());
+        assert_eq!(
+            store.between(date(17), date(17)).await.unwrap(),
+            vec![exception("b", 17, 14)]
+        );
+    }
+}
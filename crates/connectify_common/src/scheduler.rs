@@ -0,0 +1,398 @@
+//! Cron-style background job scheduler for the Connectify application.
+//!
+//! Periodic tasks (pruning expired tokens and idempotency keys, cleaning up sessions, warming
+//! caches) are registered on a [`Scheduler`] with a standard 5-field cron expression and run
+//! in background tasks once the scheduler is started:
+//!
+//! ```ignore
+//! let handle = Scheduler::new()
+//!     .every("0 3 * * *", "idempotency_cleanup", || async { purge().await })?
+//!     .start();
+//! ```
+//!
+//! Cron fields are `minute hour day-of-month month day-of-week` and support `*`, numbers,
+//! lists (`1,15`), ranges (`1-5`) and steps (`*/15`, `0-30/10`). Day of week 0 and 7 are
+//! Sunday. As in classic cron, if both day fields are restricted, a day matching either runs.
+//!
+//! A failing run is logged and counted in the error metrics; the job keeps its schedule.
+//! Runs of the same job never overlap.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use connectify_config::SchedulerConfig;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// How far ahead the next run of a schedule is searched for.
+const MAX_LOOKAHEAD_DAYS: i64 = 5 * 366;
+
+/// A parsed 5-field cron expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    day_of_month_restricted: bool,
+    day_of_week_restricted: bool,
+}
+
+/// Parse one cron field into a bitmask of allowed values.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("invalid step '{}'", step))?;
+                if step == 0 {
+                    return Err("step must not be 0".to_string());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            let start = start
+                .parse()
+                .map_err(|_| format!("invalid value '{}'", start))?;
+            let end = end
+                .parse()
+                .map_err(|_| format!("invalid value '{}'", end))?;
+            (start, end)
+        } else {
+            let value = range
+                .parse()
+                .map_err(|_| format!("invalid value '{}'", range))?;
+            // "5/10" means "from 5 to the end, every 10"
+            (value, if part.contains('/') { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            return Err(format!(
+                "'{}' is outside the allowed range {}-{}",
+                part, min, max
+            ));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+impl FromStr for CronSchedule {
+    type Err = ConnectifyError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| {
+            ConnectifyError::ValidationError(format!(
+                "Invalid cron expression '{}': {}",
+                expression, reason
+            ))
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            return Err(invalid(format!("expected 5 fields, got {}", fields.len())));
+        };
+
+        let mut days_of_week = parse_field(day_of_week, 0, 7).map_err(invalid)?;
+        // 7 is an alias for Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            expression: expression.to_string(),
+            minutes: parse_field(minute, 0, 59).map_err(invalid)?,
+            hours: parse_field(hour, 0, 23).map_err(invalid)?,
+            days_of_month: parse_field(day_of_month, 1, 31).map_err(invalid)?,
+            months: parse_field(month, 1, 12).map_err(invalid)?,
+            days_of_week,
+            day_of_month_restricted: day_of_month != "*",
+            day_of_week_restricted: day_of_week != "*",
+        })
+    }
+}
+
+impl CronSchedule {
+    /// The expression the schedule was parsed from.
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day_of_month = self.days_of_month & (1 << date.day()) != 0;
+        let day_of_week = self.days_of_week & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.day_of_month_restricted, self.day_of_week_restricted) {
+            (true, true) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+
+    /// The first time strictly after `after` matching the schedule, in the given time zone.
+    ///
+    /// Local times skipped by a DST transition are not run; repeated local times run once.
+    pub fn next_after(&self, after: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = after.timezone();
+        let start = after.naive_local() + Duration::minutes(1);
+        let start = start.with_second(0)?.with_nanosecond(0)?;
+
+        let mut date = start.date();
+        for _ in 0..MAX_LOOKAHEAD_DAYS {
+            if self.matches_day(date) {
+                let first_minute = if date == start.date() {
+                    start.hour() * 60 + start.minute()
+                } else {
+                    0
+                };
+                for minute_of_day in first_minute..24 * 60 {
+                    let (hour, minute) = (minute_of_day / 60, minute_of_day % 60);
+                    if self.hours & (1 << hour) == 0 || self.minutes & (1 << minute) == 0 {
+                        continue;
+                    }
+                    let local =
+                        NaiveDateTime::new(date, chrono::NaiveTime::from_hms_opt(hour, minute, 0)?);
+                    if let Some(time) = tz.from_local_datetime(&local).earliest() {
+                        if time > after {
+                            return Some(time);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+}
+
+type JobFn = Arc<dyn Fn() -> BoxFuture<'static, (), ConnectifyError> + Send + Sync>;
+
+struct Job {
+    name: String,
+    schedule: CronSchedule,
+    run: JobFn,
+}
+
+/// Runs registered jobs on their cron schedules.
+pub struct Scheduler {
+    timezone: Tz,
+    config: SchedulerConfig,
+    jobs: Vec<Job>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scheduler {
+    /// Create a scheduler evaluating cron expressions in UTC.
+    pub fn new() -> Self {
+        Self {
+            timezone: Tz::UTC,
+            config: SchedulerConfig::default(),
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Create a scheduler from the `scheduler` section of the configuration.
+    ///
+    /// The configured time zone is used, and jobs registered later can be rescheduled or
+    /// disabled by name.
+    pub fn from_config(config: &SchedulerConfig) -> Result<Self, ConnectifyError> {
+        let timezone = match config.timezone.as_deref() {
+            Some(name) => name.parse::<Tz>().map_err(|_| {
+                ConnectifyError::ConfigError(format!("Invalid scheduler timezone '{}'", name))
+            })?,
+            None => Tz::UTC,
+        };
+        Ok(Self {
+            timezone,
+            config: config.clone(),
+            jobs: Vec::new(),
+        })
+    }
+
+    /// Register a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `cron` - Default cron expression, e.g. "0 3 * * *" for daily at 03:00
+    /// * `name` - Unique job name, used in logs and to override the schedule in the config
+    /// * `job` - Creates the future for each run
+    ///
+    /// # Returns
+    ///
+    /// A `ValidationError` if the (possibly overridden) cron expression is invalid.
+    pub fn every<F, Fut>(
+        mut self,
+        cron: &str,
+        name: impl Into<String>,
+        job: F,
+    ) -> Result<Self, ConnectifyError>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), ConnectifyError>> + Send + 'static,
+    {
+        let name = name.into();
+        let job_config = self.config.jobs.get(&name);
+        if job_config.is_some_and(|c| c.disabled) {
+            info!("Background job '{}' is disabled", name);
+            return Ok(self);
+        }
+        let cron = job_config.and_then(|c| c.cron.as_deref()).unwrap_or(cron);
+
+        self.jobs.push(Job {
+            schedule: cron.parse()?,
+            name,
+            run: Arc::new(move || Box::pin(job())),
+        });
+        Ok(self)
+    }
+
+    /// Names of the registered jobs.
+    pub fn job_names(&self) -> Vec<&str> {
+        self.jobs.iter().map(|job| job.name.as_str()).collect()
+    }
+
+    /// Start running the jobs in background tasks.
+    pub fn start(self) -> SchedulerHandle {
+        let timezone = self.timezone;
+        let tasks = self
+            .jobs
+            .into_iter()
+            .map(|job| {
+                info!(
+                    "Scheduling background job '{}' at '{}' ({})",
+                    job.name,
+                    job.schedule.expression(),
+                    timezone
+                );
+                tokio::spawn(run_job(job, timezone))
+            })
+            .collect();
+        SchedulerHandle { tasks }
+    }
+}
+
+async fn run_job(job: Job, timezone: Tz) {
+    loop {
+        let now = Utc::now().with_timezone(&timezone);
+        let Some(next) = job.schedule.next_after(now) else {
+            warn!("Background job '{}' will never run again", job.name);
+            return;
+        };
+        debug!("Background job '{}' next runs at {}", job.name, next);
+        let wait = (next.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default();
+        tokio::time::sleep(wait).await;
+
+        info!("Running background job '{}'", job.name);
+        match (job.run)().await {
+            Ok(()) => debug!("Background job '{}' finished", job.name),
+            Err(e) => {
+                error!("Background job '{}' failed: {}", job.name, e);
+                crate::metrics::record_error("scheduler", &job.name);
+            }
+        }
+    }
+}
+
+/// Handle to the tasks of a started [`Scheduler`].
+pub struct SchedulerHandle {
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl SchedulerHandle {
+    /// Stop all jobs. Runs in progress are cancelled.
+    pub fn shutdown(self) {
+        for task in self.tasks {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(tz: Tz, s: &str) -> DateTime<Tz> {
+        tz.from_local_datetime(&NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_next_after() {
+        let daily: CronSchedule = "0 3 * * *".parse().unwrap();
+        assert_eq!(
+            daily.next_after(at(Tz::UTC, "2025-01-01 03:00")),
+            Some(at(Tz::UTC, "2025-01-02 03:00"))
+        );
+
+        let quarter_hours: CronSchedule = "*/15 9-17 * * 1-5".parse().unwrap();
+        // Friday evening -> Monday morning
+        assert_eq!(
+            quarter_hours.next_after(at(Tz::UTC, "2025-01-03 17:50")),
+            Some(at(Tz::UTC, "2025-01-06 09:00"))
+        );
+
+        let sundays: CronSchedule = "30 2 * * 7".parse().unwrap();
+        // 02:30 does not exist in Zurich on 2025-03-30 (DST), the next Sunday runs
+        assert_eq!(
+            sundays.next_after(at(Tz::Europe__Zurich, "2025-03-29 12:00")),
+            Some(at(Tz::Europe__Zurich, "2025-04-06 02:30"))
+        );
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        for expression in [
+            "0 3 * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "a * * * *",
+        ] {
+            assert!(
+                expression.parse::<CronSchedule>().is_err(),
+                "{}",
+                expression
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_config_overrides_jobs() {
+        let mut config = SchedulerConfig::default();
+        config.jobs.insert(
+            "disabled".to_string(),
+            connectify_config::ScheduledJobConfig {
+                cron: None,
+                disabled: true,
+            },
+        );
+        let scheduler = Scheduler::from_config(&config)
+            .unwrap()
+            .every("0 3 * * *", "disabled", || async { Ok(()) })
+            .unwrap()
+            .every("0 4 * * *", "enabled", || async { Ok(()) })
+            .unwrap();
+        assert_eq!(scheduler.job_names(), vec!["enabled"]);
+    }
+}
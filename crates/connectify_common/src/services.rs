@@ -8,11 +8,13 @@
 use chrono::DateTime;
 use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
 use std::error::Error as StdError;
 use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 /// Type alias for a boxed future that returns a Result
 pub type BoxFuture<'a, T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'a>>;
@@ -63,6 +65,16 @@ pub trait CalendarService: Send + Sync {
         event: CalendarEvent,
     ) -> BoxFuture<'_, CalendarEventResult, Self::Error>;
 
+    /// Update fields of an existing calendar event, e.g. to reschedule it.
+    ///
+    /// Fields that are `None` in the patch are left unchanged.
+    fn update_event(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+        patch: CalendarEventPatch,
+    ) -> BoxFuture<'_, CalendarEventResult, Self::Error>;
+
     /// Delete a calendar event.
     fn delete_event(
         &self,
@@ -149,19 +161,361 @@ pub trait NotificationService: Send + Sync {
     fn send_sms(&self, to: &str, body: &str) -> BoxFuture<'_, NotificationResult, Self::Error>;
 }
 
-/// A factory for creating service instances.
+/// A trait for email service operations.
+///
+/// This trait defines the operations that can be performed on an email provider,
+/// such as sending booking or payment confirmations rendered from a template.
+pub trait EmailService: Send + Sync {
+    /// Error type returned by email service operations.
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Send an email rendered from a template.
+    fn send_templated_email(
+        &self,
+        email: TemplatedEmail,
+    ) -> BoxFuture<'_, EmailResult, Self::Error>;
+}
+
+/// A calendar service with boxed errors, as stored in a [`ServiceRegistry`].
+pub type DynCalendarService = dyn CalendarService<Error = BoxedError>;
+
+/// A payment service with boxed errors, as stored in a [`ServiceRegistry`].
+pub type DynPaymentService = dyn PaymentService<Error = BoxedError>;
+
+/// A notification service with boxed errors, as stored in a [`ServiceRegistry`].
+pub type DynNotificationService = dyn NotificationService<Error = BoxedError>;
+
+/// An email service with boxed errors, as stored in a [`ServiceRegistry`].
+pub type DynEmailService = dyn EmailService<Error = BoxedError>;
+
+/// A registered service together with its name and priority.
+struct Registration {
+    name: String,
+    priority: i32,
+    /// An `Arc<S>` for the capability `S` the service was registered under.
+    service: Box<dyn Any + Send + Sync>,
+}
+
+/// A registry of services, keyed by capability and name.
+///
+/// A capability is the (usually unsized) type a service is registered under, such as
+/// [`DynPaymentService`]. Several providers can register for the same capability; lookups
+/// without a name return the one with the highest priority, and the first registered one
+/// among equal priorities.
+///
+/// Services can be registered at any time, also after the registry has been shared.
+///
+/// # Example
+///
+/// ```ignore
+/// let registry = ServiceRegistry::new();
+/// registry.register::<DynPaymentService>("stripe", Arc::new(BoxErrors(stripe_service)));
+/// registry.register_with_priority::<DynPaymentService>("payrexx", 10, Arc::new(payrexx));
+///
+/// let preferred = registry.get::<DynPaymentService>(); // payrexx
+/// let stripe = registry.get_named::<DynPaymentService>("stripe");
+/// ```
+#[derive(Default)]
+pub struct ServiceRegistry {
+    services: RwLock<HashMap<TypeId, Vec<Registration>>>,
+}
+
+impl fmt::Debug for ServiceRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let services = self.services.read().unwrap_or_else(|e| e.into_inner());
+        let names: Vec<&str> = services
+            .values()
+            .flatten()
+            .map(|registration| registration.name.as_str())
+            .collect();
+        f.debug_struct("ServiceRegistry")
+            .field("services", &names)
+            .finish()
+    }
+}
+
+impl ServiceRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a service for capability `S` with priority 0.
+    ///
+    /// A service registered under an existing name replaces the previous one.
+    pub fn register<S>(&self, name: &str, service: Arc<S>)
+    where
+        S: ?Sized + Send + Sync + 'static,
+    {
+        self.register_with_priority(name, 0, service);
+    }
+
+    /// Register a service for capability `S` with the given priority (higher wins).
+    pub fn register_with_priority<S>(&self, name: &str, priority: i32, service: Arc<S>)
+    where
+        S: ?Sized + Send + Sync + 'static,
+    {
+        let mut services = self.services.write().unwrap_or_else(|e| e.into_inner());
+        let registrations = services.entry(TypeId::of::<S>()).or_default();
+        registrations.retain(|registration| registration.name != name);
+        registrations.push(Registration {
+            name: name.to_string(),
+            priority,
+            service: Box::new(service),
+        });
+        // Stable sort, so the first registration wins among equal priorities
+        registrations.sort_by_key(|registration| std::cmp::Reverse(registration.priority));
+    }
+
+    /// Remove the service registered for capability `S` under the given name.
+    ///
+    /// # Returns
+    ///
+    /// `true` if a service was removed.
+    pub fn unregister<S>(&self, name: &str) -> bool
+    where
+        S: ?Sized + 'static,
+    {
+        let mut services = self.services.write().unwrap_or_else(|e| e.into_inner());
+        let Some(registrations) = services.get_mut(&TypeId::of::<S>()) else {
+            return false;
+        };
+        let before = registrations.len();
+        registrations.retain(|registration| registration.name != name);
+        registrations.len() != before
+    }
+
+    /// Get the highest-priority service for capability `S`.
+    pub fn get<S>(&self) -> Option<Arc<S>>
+    where
+        S: ?Sized + Send + Sync + 'static,
+    {
+        let services = self.services.read().unwrap_or_else(|e| e.into_inner());
+        services
+            .get(&TypeId::of::<S>())?
+            .first()
+            .and_then(|registration| registration.service.downcast_ref::<Arc<S>>())
+            .cloned()
+    }
+
+    /// Get the service registered for capability `S` under the given name.
+    pub fn get_named<S>(&self, name: &str) -> Option<Arc<S>>
+    where
+        S: ?Sized + Send + Sync + 'static,
+    {
+        let services = self.services.read().unwrap_or_else(|e| e.into_inner());
+        services
+            .get(&TypeId::of::<S>())?
+            .iter()
+            .find(|registration| registration.name == name)
+            .and_then(|registration| registration.service.downcast_ref::<Arc<S>>())
+            .cloned()
+    }
+
+    /// The names of the services registered for capability `S`, highest priority first.
+    pub fn names<S>(&self) -> Vec<String>
+    where
+        S: ?Sized + 'static,
+    {
+        let services = self.services.read().unwrap_or_else(|e| e.into_inner());
+        services
+            .get(&TypeId::of::<S>())
+            .map(|registrations| {
+                registrations
+                    .iter()
+                    .map(|registration| registration.name.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// A factory for accessing service instances.
 ///
-/// This trait provides methods for creating instances of various services.
-/// It's used by the application to get access to the services it needs.
+/// Services are looked up in the factory's [`ServiceRegistry`]; the accessors return the
+/// highest-priority provider registered for each capability. Implementors only need to
+/// provide the registry.
 pub trait ServiceFactory: Send + Sync {
+    /// The registry holding the services.
+    fn registry(&self) -> &ServiceRegistry;
+
     /// Get a calendar service instance.
-    fn calendar_service(&self) -> Option<Arc<dyn CalendarService<Error = BoxedError>>>;
+    fn calendar_service(&self) -> Option<Arc<DynCalendarService>> {
+        self.registry().get::<DynCalendarService>()
+    }
 
     /// Get a payment service instance.
-    fn payment_service(&self) -> Option<Arc<dyn PaymentService<Error = BoxedError>>>;
+    fn payment_service(&self) -> Option<Arc<DynPaymentService>> {
+        self.registry().get::<DynPaymentService>()
+    }
 
     /// Get a notification service instance.
-    fn notification_service(&self) -> Option<Arc<dyn NotificationService<Error = BoxedError>>>;
+    fn notification_service(&self) -> Option<Arc<DynNotificationService>> {
+        self.registry().get::<DynNotificationService>()
+    }
+
+    /// Get an email service instance.
+    fn email_service(&self) -> Option<Arc<DynEmailService>> {
+        self.registry().get::<DynEmailService>()
+    }
+}
+
+impl ServiceFactory for ServiceRegistry {
+    fn registry(&self) -> &ServiceRegistry {
+        self
+    }
+}
+
+/// Adapts a service to return [`BoxedError`]s, so it can be registered as e.g.
+/// [`DynCalendarService`] regardless of its own error type.
+#[derive(Debug, Clone)]
+pub struct BoxErrors<S>(pub S);
+
+fn box_error<E: StdError + Send + Sync + 'static>(err: E) -> BoxedError {
+    BoxedError(Box::new(err))
+}
+
+impl<S: CalendarService> CalendarService for BoxErrors<S> {
+    type Error = BoxedError;
+
+    fn get_busy_times(
+        &self,
+        calendar_id: &str,
+        start_time: DateTime<Tz>,
+        end_time: DateTime<Tz>,
+    ) -> BoxFuture<'_, Vec<(DateTime<Tz>, DateTime<Tz>)>, Self::Error> {
+        let future = self.0.get_busy_times(calendar_id, start_time, end_time);
+        Box::pin(async move { future.await.map_err(box_error) })
+    }
+
+    fn create_event(
+        &self,
+        calendar_id: &str,
+        event: CalendarEvent,
+    ) -> BoxFuture<'_, CalendarEventResult, Self::Error> {
+        let future = self.0.create_event(calendar_id, event);
+        Box::pin(async move { future.await.map_err(box_error) })
+    }
+
+    fn update_event(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+        patch: CalendarEventPatch,
+    ) -> BoxFuture<'_, CalendarEventResult, Self::Error> {
+        let future = self.0.update_event(calendar_id, event_id, patch);
+        Box::pin(async move { future.await.map_err(box_error) })
+    }
+
+    fn delete_event(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+        notify_attendees: bool,
+    ) -> BoxFuture<'_, (), Self::Error> {
+        let future = self.0.delete_event(calendar_id, event_id, notify_attendees);
+        Box::pin(async move { future.await.map_err(box_error) })
+    }
+
+    fn mark_event_cancelled(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+        notify_attendees: bool,
+    ) -> BoxFuture<'_, CalendarEventResult, Self::Error> {
+        let future = self
+            .0
+            .mark_event_cancelled(calendar_id, event_id, notify_attendees);
+        Box::pin(async move { future.await.map_err(box_error) })
+    }
+
+    fn get_booked_events(
+        &self,
+        calendar_id: &str,
+        start_time: DateTime<Tz>,
+        end_time: DateTime<Tz>,
+        include_cancelled: bool,
+    ) -> BoxFuture<'_, Vec<BookedEvent>, Self::Error> {
+        let future = self
+            .0
+            .get_booked_events(calendar_id, start_time, end_time, include_cancelled);
+        Box::pin(async move { future.await.map_err(box_error) })
+    }
+}
+
+impl<S: PaymentService> PaymentService for BoxErrors<S> {
+    type Error = BoxedError;
+
+    fn create_payment_intent(
+        &self,
+        amount: i64,
+        currency: &str,
+        description: Option<&str>,
+        metadata: Option<serde_json::Value>,
+    ) -> BoxFuture<'_, PaymentIntentResult, Self::Error> {
+        let future = self
+            .0
+            .create_payment_intent(amount, currency, description, metadata);
+        Box::pin(async move { future.await.map_err(box_error) })
+    }
+
+    fn confirm_payment_intent(
+        &self,
+        payment_intent_id: &str,
+    ) -> BoxFuture<'_, PaymentIntentResult, Self::Error> {
+        let future = self.0.confirm_payment_intent(payment_intent_id);
+        Box::pin(async move { future.await.map_err(box_error) })
+    }
+
+    fn cancel_payment_intent(
+        &self,
+        payment_intent_id: &str,
+    ) -> BoxFuture<'_, PaymentIntentResult, Self::Error> {
+        let future = self.0.cancel_payment_intent(payment_intent_id);
+        Box::pin(async move { future.await.map_err(box_error) })
+    }
+
+    fn create_refund(
+        &self,
+        payment_intent_id: &str,
+        amount: Option<i64>,
+        reason: Option<&str>,
+    ) -> BoxFuture<'_, RefundResult, Self::Error> {
+        let future = self.0.create_refund(payment_intent_id, amount, reason);
+        Box::pin(async move { future.await.map_err(box_error) })
+    }
+}
+
+impl<S: NotificationService> NotificationService for BoxErrors<S> {
+    type Error = BoxedError;
+
+    fn send_email(
+        &self,
+        to: &str,
+        subject: &str,
+        body: &str,
+        is_html: bool,
+    ) -> BoxFuture<'_, NotificationResult, Self::Error> {
+        let future = self.0.send_email(to, subject, body, is_html);
+        Box::pin(async move { future.await.map_err(box_error) })
+    }
+
+    fn send_sms(&self, to: &str, body: &str) -> BoxFuture<'_, NotificationResult, Self::Error> {
+        let future = self.0.send_sms(to, body);
+        Box::pin(async move { future.await.map_err(box_error) })
+    }
+}
+
+impl<S: EmailService> EmailService for BoxErrors<S> {
+    type Error = BoxedError;
+
+    fn send_templated_email(
+        &self,
+        email: TemplatedEmail,
+    ) -> BoxFuture<'_, EmailResult, Self::Error> {
+        let future = self.0.send_templated_email(email);
+        Box::pin(async move { future.await.map_err(box_error) })
+    }
 }
 
 /// Data structures for calendar service operations.
@@ -175,17 +529,55 @@ pub struct CalendarEvent {
     pub summary: String,
     /// An optional description of the event.
     pub description: Option<String>,
-    // The payment method used for the event (e.g., "stripe").
-    #[serde(skip)]
+    /// The payment method used for the event (e.g., "stripe").
+    ///
+    /// Calendar services store this and the following booking metadata with the event
+    /// (e.g. as private extended properties) and return it in [`BookedEvent`].
+    #[serde(default)]
     pub payment_method: Option<String>,
-    // The payment ID or reference for the event.
-    #[serde(skip)]
+    /// The payment ID or reference for the event.
+    #[serde(default)]
     pub payment_id: Option<String>,
-    // The payment amount in cents.
-    #[serde(skip)]
+    /// The payment amount in cents.
+    #[serde(default)]
     pub payment_amount: Option<i64>,
-    #[serde(skip)]
+    /// The name of the video room of the event, if any.
+    #[serde(default)]
     pub room_name: Option<String>,
+    /// The URL of the invoice of the payment, e.g. a Stripe invoice PDF.
+    #[serde(default)]
+    pub invoice_url: Option<String>,
+    /// Email addresses of attendees, who receive an invitation to the event.
+    #[serde(default)]
+    pub attendees: Vec<String>,
+    /// Whether to attach a new video conference (e.g. Google Meet) to the event.
+    #[serde(default)]
+    pub create_meet_link: bool,
+    /// Whether the event leaves its time free in the calendar, e.g. one seat of a group slot.
+    #[serde(default)]
+    pub transparent: bool,
+    /// The color of the event in the calendar, e.g. a Google Calendar `colorId` ("1".."11").
+    #[serde(default)]
+    pub color_id: Option<String>,
+    /// Tags stored with the event, to filter booked events by, e.g. the product booked.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Changes to apply to an existing calendar event.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CalendarEventPatch {
+    /// The new start time of the event.
+    pub start_time: Option<String>,
+    /// The new end time of the event.
+    pub end_time: Option<String>,
+    /// The new summary or title of the event.
+    pub summary: Option<String>,
+    /// The new description of the event.
+    pub description: Option<String>,
+    /// Whether to notify attendees about the change.
+    #[serde(default)]
+    pub notify_attendees: bool,
 }
 
 /// Represents the result of a calendar event operation.
@@ -195,6 +587,9 @@ pub struct CalendarEventResult {
     pub event_id: Option<String>,
     /// The status of the event.
     pub status: String,
+    /// The URL of the event's video conference, if one was created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meet_link: Option<String>,
 }
 
 /// Represents a booked event.
@@ -221,6 +616,12 @@ pub struct BookedEvent {
     pub payment_id: Option<String>,
     pub payment_amount: Option<i64>,
     pub room_name: Option<String>,
+    #[serde(default)]
+    pub invoice_url: Option<String>,
+    #[serde(default)]
+    pub color_id: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 /// Data structures for payment service operations.
@@ -261,3 +662,144 @@ pub struct NotificationResult {
     /// The status of the notification.
     pub status: String,
 }
+
+/// Data structures for email service operations.
+/// Represents an email rendered by the provider from a template.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplatedEmail {
+    /// The recipient addresses.
+    pub to: Vec<String>,
+    /// Additional recipients in copy.
+    #[serde(default)]
+    pub cc: Vec<String>,
+    /// Additional hidden recipients.
+    #[serde(default)]
+    pub bcc: Vec<String>,
+    /// An optional reply-to address.
+    pub reply_to: Option<String>,
+    /// The provider's template name or ID (e.g., "booking_confirmation").
+    pub template: String,
+    /// The values substituted into the template.
+    #[serde(default)]
+    pub variables: serde_json::Map<String, serde_json::Value>,
+    /// An optional language of the template (e.g., "de").
+    pub language: Option<String>,
+    /// Files attached to the email.
+    #[serde(default)]
+    pub attachments: Vec<EmailAttachment>,
+}
+
+impl TemplatedEmail {
+    /// Create an email to one recipient using the given template.
+    pub fn new(to: impl Into<String>, template: impl Into<String>) -> Self {
+        Self {
+            to: vec![to.into()],
+            template: template.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Set a template variable.
+    pub fn with_variable(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.variables.insert(name.into(), value.into());
+        self
+    }
+
+    /// Add an attachment.
+    pub fn with_attachment(mut self, attachment: EmailAttachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+}
+
+/// Represents a file attached to an email.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailAttachment {
+    /// The file name shown to the recipient.
+    pub filename: String,
+    /// The MIME type (e.g., "text/calendar").
+    pub content_type: String,
+    /// The file content.
+    pub content: Vec<u8>,
+}
+
+/// Represents the result of an email operation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailResult {
+    /// The provider's message ID.
+    pub id: String,
+    /// The status of the email (e.g., "queued").
+    pub status: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockSms(&'static str);
+
+    impl NotificationService for MockSms {
+        type Error = std::io::Error;
+
+        fn send_email(
+            &self,
+            _to: &str,
+            _subject: &str,
+            _body: &str,
+            _is_html: bool,
+        ) -> BoxFuture<'_, NotificationResult, Self::Error> {
+            Box::pin(async { Err(std::io::Error::other("email not supported")) })
+        }
+
+        fn send_sms(
+            &self,
+            _to: &str,
+            _body: &str,
+        ) -> BoxFuture<'_, NotificationResult, Self::Error> {
+            Box::pin(async move {
+                Ok(NotificationResult {
+                    id: self.0.to_string(),
+                    status: "sent".to_string(),
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registry_lookup_by_priority_and_name() {
+        let registry = ServiceRegistry::new();
+        assert!(registry.notification_service().is_none());
+
+        registry.register::<DynNotificationService>("first", Arc::new(BoxErrors(MockSms("first"))));
+        registry
+            .register::<DynNotificationService>("second", Arc::new(BoxErrors(MockSms("second"))));
+        let service = registry.notification_service().unwrap();
+        assert_eq!(service.send_sms("+41", "hi").await.unwrap().id, "first");
+        assert!(service.send_email("a@b.c", "s", "b", false).await.is_err());
+
+        registry.register_with_priority::<DynNotificationService>(
+            "preferred",
+            10,
+            Arc::new(BoxErrors(MockSms("preferred"))),
+        );
+        assert_eq!(
+            registry.names::<DynNotificationService>(),
+            vec!["preferred", "first", "second"]
+        );
+        let service = registry.get::<DynNotificationService>().unwrap();
+        assert_eq!(service.send_sms("+41", "hi").await.unwrap().id, "preferred");
+
+        let service = registry
+            .get_named::<DynNotificationService>("second")
+            .unwrap();
+        assert_eq!(service.send_sms("+41", "hi").await.unwrap().id, "second");
+
+        assert!(registry.unregister::<DynNotificationService>("preferred"));
+        assert!(!registry.unregister::<DynNotificationService>("preferred"));
+        assert!(registry.payment_service().is_none());
+    }
+}
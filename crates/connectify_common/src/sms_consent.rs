@@ -0,0 +1,232 @@
+//! Replies to SMS and the consent of numbers to receive SMS.
+//!
+//! Every SMS received is kept, so replies to reminders can be read. A number that replied with
+//! an opt-out keyword like `STOP` is opted out until it replies with `START`, and no further SMS
+//! are sent to it.
+//!
+//! The store defaults to an in-memory one; the backend replaces it with the database
+//! repository via [`configure_sms_consent_store`], so opt-outs survive restarts.
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// An SMS received from a number.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InboundSmsRecord {
+    /// The provider's ID of the message, e.g. a Twilio `SM…` SID.
+    pub message_sid: String,
+    /// The sender's number.
+    pub from: String,
+    /// The number the SMS was sent to, one of ours.
+    pub to: String,
+    pub body: String,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Whether a number may be sent SMS.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SmsConsentRecord {
+    /// The number, as SMS are sent to it.
+    pub phone: String,
+    pub opted_out: bool,
+    /// The keyword the consent was last changed with, e.g. `STOP`.
+    pub keyword: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Storage for received SMS and the consent of numbers.
+pub trait SmsConsentStore: Send + Sync {
+    /// Store a received SMS, ignoring one already stored.
+    fn save_message(&self, record: InboundSmsRecord) -> BoxFuture<'_, (), ConnectifyError>;
+
+    /// The most recent SMS received from a number, newest first.
+    fn list_messages<'a>(
+        &'a self,
+        from: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<InboundSmsRecord>, ConnectifyError>;
+
+    /// Store a number's consent, replacing an earlier one.
+    fn save_consent(&self, record: SmsConsentRecord) -> BoxFuture<'_, (), ConnectifyError>;
+
+    /// Get the consent of a number, `None` if it never changed it.
+    fn get_consent<'a>(
+        &'a self,
+        phone: &'a str,
+    ) -> BoxFuture<'a, Option<SmsConsentRecord>, ConnectifyError>;
+}
+
+/// Whether a number opted out of SMS.
+pub async fn is_opted_out(
+    store: &dyn SmsConsentStore,
+    phone: &str,
+) -> Result<bool, ConnectifyError> {
+    Ok(store
+        .get_consent(phone)
+        .await?
+        .is_some_and(|consent| consent.opted_out))
+}
+
+/// A [`SmsConsentStore`] keeping SMS and consents in memory, for single-instance deployments
+/// and tests.
+#[derive(Debug, Default)]
+pub struct InMemorySmsConsentStore {
+    messages: Mutex<HashMap<String, InboundSmsRecord>>,
+    consents: Mutex<HashMap<String, SmsConsentRecord>>,
+}
+
+impl SmsConsentStore for InMemorySmsConsentStore {
+    fn save_message(&self, record: InboundSmsRecord) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move {
+            self.messages
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .entry(record.message_sid.clone())
+                .or_insert(record);
+            Ok(())
+        })
+    }
+
+    fn list_messages<'a>(
+        &'a self,
+        from: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<InboundSmsRecord>, ConnectifyError> {
+        Box::pin(async move {
+            let messages = self.messages.lock().unwrap_or_else(|e| e.into_inner());
+            let mut listed: Vec<InboundSmsRecord> = messages
+                .values()
+                .filter(|record| record.from == from)
+                .cloned()
+                .collect();
+            listed.sort_by_key(|record| std::cmp::Reverse(record.received_at));
+            listed.truncate(limit);
+            Ok(listed)
+        })
+    }
+
+    fn save_consent(&self, record: SmsConsentRecord) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move {
+            self.consents
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(record.phone.clone(), record);
+            Ok(())
+        })
+    }
+
+    fn get_consent<'a>(
+        &'a self,
+        phone: &'a str,
+    ) -> BoxFuture<'a, Option<SmsConsentRecord>, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .consents
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(phone)
+                .cloned())
+        })
+    }
+}
+
+/// The global store returned by [`sms_consent_store`].
+static SMS_CONSENT_STORE: Lazy<RwLock<Arc<dyn SmsConsentStore>>> =
+    Lazy::new(|| RwLock::new(Arc::new(InMemorySmsConsentStore::default())));
+
+/// Replace the store used for received SMS and consents.
+pub fn configure_sms_consent_store(store: Arc<dyn SmsConsentStore>) {
+    *SMS_CONSENT_STORE.write().unwrap_or_else(|e| e.into_inner()) = store;
+}
+
+/// The store used for received SMS and consents.
+pub fn sms_consent_store() -> Arc<dyn SmsConsentStore> {
+    SMS_CONSENT_STORE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(second: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(second, 0).unwrap()
+    }
+
+    fn message(sid: &str, from: &str, second: i64) -> InboundSmsRecord {
+        InboundSmsRecord {
+            message_sid: sid.to_string(),
+            from: from.to_string(),
+            to: "+41790000000".to_string(),
+            body: format!("Reply {}", sid),
+            received_at: at(second),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_messages() {
+        let store = InMemorySmsConsentStore::default();
+        store
+            .save_message(message("SM1", "+41791234567", 0))
+            .await
+            .unwrap();
+        store
+            .save_message(message("SM2", "+41791234567", 10))
+            .await
+            .unwrap();
+        store
+            .save_message(message("SM3", "+41797654321", 20))
+            .await
+            .unwrap();
+        // Twilio retries webhooks, a message is kept once
+        store
+            .save_message(message("SM1", "+41791234567", 30))
+            .await
+            .unwrap();
+
+        let listed = store.list_messages("+41791234567", 10).await.unwrap();
+        let sids: Vec<&str> = listed.iter().map(|m| m.message_sid.as_str()).collect();
+        assert_eq!(sids, vec!["SM2", "SM1"]);
+        assert_eq!(listed[1].received_at, at(0));
+        assert_eq!(
+            store.list_messages("+41791234567", 1).await.unwrap().len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_is_opted_out() {
+        let store = InMemorySmsConsentStore::default();
+        assert!(!is_opted_out(&store, "+41791234567").await.unwrap());
+
+        let consent = |opted_out, keyword: &str| SmsConsentRecord {
+            phone: "+41791234567".to_string(),
+            opted_out,
+            keyword: keyword.to_string(),
+            updated_at: at(0),
+        };
+        store.save_consent(consent(true, "STOP")).await.unwrap();
+        assert!(is_opted_out(&store, "+41791234567").await.unwrap());
+        assert!(!is_opted_out(&store, "+41797654321").await.unwrap());
+
+        store.save_consent(consent(false, "START")).await.unwrap();
+        assert!(!is_opted_out(&store, "+41791234567").await.unwrap());
+        assert_eq!(
+            store
+                .get_consent("+41791234567")
+                .await
+                .unwrap()
+                .unwrap()
+                .keyword,
+            "START"
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Validated request extractors for the Connectify application.
+//!
+//! Request types derive [`validator::Validate`] and declare their rules next to the fields;
+//! handlers extract them with [`ValidatedJson`] instead of `Json`, so invalid requests are
+//! rejected before the handler runs:
+//!
+//! ```ignore
+//! #[derive(Deserialize, Validate)]
+//! struct RegisterDeviceRequest {
+//!     #[validate(length(min = 1))]
+//!     user_id: String,
+//! }
+//!
+//! async fn register(ValidatedJson(payload): ValidatedJson<RegisterDeviceRequest>) -> ... {}
+//! ```
+//!
+//! Invalid requests get a `422 Unprocessable Entity` with the failed rules per field, in the
+//! usual error format:
+//!
+//! ```json
+//! {
+//!   "error": {
+//!     "message": "Validation failed: user_id",
+//!     "code": 422,
+//!     "fields": { "user_id": ["length"] }
+//!   }
+//! }
+//! ```
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{FromRequest, Request};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::collections::BTreeMap;
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use crate::error::ConnectifyError;
+
+/// JSON request body that is deserialized and then validated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedJson<T>(pub T);
+
+/// Rejection of a [`ValidatedJson`] extractor.
+#[derive(Debug)]
+pub enum ValidationRejection {
+    /// The body is not valid JSON for the type.
+    Json(JsonRejection),
+    /// The body violates validation rules.
+    Invalid(ValidationErrors),
+}
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ValidationRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(ValidationRejection::Json)?;
+        value.validate().map_err(ValidationRejection::Invalid)?;
+        Ok(ValidatedJson(value))
+    }
+}
+
+/// Flatten validation errors to the failed rules per field path, e.g. `items[0].name`.
+///
+/// Each rule is reported by its message if it has one, and by its code otherwise. Struct-level
+/// (schema) rules are reported under `__all__`.
+pub fn field_errors(errors: &ValidationErrors) -> BTreeMap<String, Vec<String>> {
+    let mut fields = BTreeMap::new();
+    collect_field_errors(errors, "", &mut fields);
+    fields
+}
+
+fn collect_field_errors(
+    errors: &ValidationErrors,
+    prefix: &str,
+    fields: &mut BTreeMap<String, Vec<String>>,
+) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", prefix, field)
+        };
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => {
+                fields
+                    .entry(path)
+                    .or_default()
+                    .extend(field_errors.iter().map(|error| {
+                        error
+                            .message
+                            .as_ref()
+                            .map(|message| message.to_string())
+                            .unwrap_or_else(|| error.code.to_string())
+                    }));
+            }
+            ValidationErrorsKind::Struct(nested) => collect_field_errors(nested, &path, fields),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_field_errors(nested, &format!("{}[{}]", path, index), fields);
+                }
+            }
+        }
+    }
+}
+
+/// Validation rule checking that a string is an RFC 3339 timestamp.
+///
+/// Use with `#[validate(custom(function = "connectify_common::validation::validate_rfc3339"))]`.
+pub fn validate_rfc3339(value: &str) -> Result<(), ValidationError> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|_| ())
+        .map_err(|_| ValidationError::new("rfc3339"))
+}
+
+fn summary(fields: &BTreeMap<String, Vec<String>>) -> String {
+    let names: Vec<&str> = fields.keys().map(String::as_str).collect();
+    format!("Validation failed: {}", names.join(", "))
+}
+
+impl From<ValidationErrors> for ConnectifyError {
+    fn from(errors: ValidationErrors) -> Self {
+        ConnectifyError::ValidationError(summary(&field_errors(&errors)))
+    }
+}
+
+impl IntoResponse for ValidationRejection {
+    fn into_response(self) -> Response {
+        match self {
+            ValidationRejection::Json(rejection) => {
+                let status = rejection.status();
+                crate::metrics::record_error("http", status.as_str());
+                let body = Json(json!({
+                    "error": {
+                        "message": rejection.body_text(),
+                        "code": status.as_u16(),
+                    }
+                }));
+                (status, body).into_response()
+            }
+            ValidationRejection::Invalid(errors) => {
+                let status = StatusCode::UNPROCESSABLE_ENTITY;
+                crate::metrics::record_error("http", status.as_str());
+                let fields = field_errors(&errors);
+                let body = Json(json!({
+                    "error": {
+                        "message": summary(&fields),
+                        "code": status.as_u16(),
+                        "fields": fields,
+                    }
+                }));
+                (status, body).into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, Validate)]
+    #[validate(schema(function = "validate_range"))]
+    struct Booking {
+        #[validate(length(min = 1, message = "must not be empty"))]
+        summary: String,
+        #[validate(range(min = 1))]
+        start: u32,
+        end: u32,
+    }
+
+    fn validate_range(booking: &Booking) -> Result<(), ValidationError> {
+        if booking.end <= booking.start {
+            return Err(ValidationError::new("end_before_start"));
+        }
+        Ok(())
+    }
+
+    async fn extract(body: &str) -> Result<ValidatedJson<Booking>, ValidationRejection> {
+        let request = Request::builder()
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        ValidatedJson::<Booking>::from_request(request, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_valid_request() {
+        let ValidatedJson(booking) = extract(r#"{"summary":"Call","start":1,"end":2}"#)
+            .await
+            .unwrap();
+        assert_eq!(booking.summary, "Call");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_request() {
+        let Err(ValidationRejection::Invalid(errors)) =
+            extract(r#"{"summary":"","start":0,"end":0}"#).await
+        else {
+            panic!("expected validation errors");
+        };
+        let fields = field_errors(&errors);
+        assert_eq!(fields["summary"], vec!["must not be empty"]);
+        assert_eq!(fields["start"], vec!["range"]);
+
+        let response = ValidationRejection::Invalid(errors).into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Schema rules run once the fields are valid
+        let Err(ValidationRejection::Invalid(errors)) =
+            extract(r#"{"summary":"Call","start":2,"end":1}"#).await
+        else {
+            panic!("expected validation errors");
+        };
+        assert_eq!(field_errors(&errors)["__all__"], vec!["end_before_start"]);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json() {
+        let rejection = extract("{").await.unwrap_err();
+        assert!(matches!(rejection, ValidationRejection::Json(_)));
+        assert_eq!(rejection.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,155 @@
+//! Webhook signature verification for the Connectify application.
+//!
+//! Every payment or messaging provider signs its webhooks differently, but almost all of them
+//! use an HMAC-SHA256 over (parts of) the raw request body. Provider crates implement the
+//! [`WebhookVerifier`] trait on top of the helpers in this module, so that signatures are
+//! always compared in constant time and failures are reported the same way.
+//!
+//! ## Usage
+//!
+//! ```ignore
+//! let event = StripeWebhookVerifier::new(secret).verify(&body, &headers)?;
+//! let payload: StripeEvent = event.json()?;
+//! ```
+
+use axum::http::HeaderMap;
+use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::error::ConnectifyError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Errors returned by webhook verification.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum WebhookError {
+    /// The signature header is missing or malformed.
+    #[error("Missing or malformed signature: {0}")]
+    MissingSignature(String),
+
+    /// The signature does not match the payload.
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+
+    /// The verifier is not configured correctly, e.g. the secret is empty.
+    #[error("Webhook verification misconfigured: {0}")]
+    Config(String),
+}
+
+impl From<WebhookError> for ConnectifyError {
+    fn from(err: WebhookError) -> Self {
+        match err {
+            WebhookError::Config(msg) => ConnectifyError::ConfigError(msg),
+            other => ConnectifyError::AuthError(other.to_string()),
+        }
+    }
+}
+
+/// A webhook whose signature has been verified.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedEvent {
+    /// The provider that sent the webhook, e.g. "stripe".
+    pub provider: &'static str,
+    /// The raw, verified request body.
+    pub payload: Vec<u8>,
+    /// The signing time as a Unix timestamp, if the provider includes one.
+    pub timestamp: Option<i64>,
+}
+
+impl VerifiedEvent {
+    /// Deserialize the verified payload as JSON.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, ConnectifyError> {
+        serde_json::from_slice(&self.payload)
+            .map_err(|e| ConnectifyError::ParseError(format!("Invalid webhook payload: {}", e)))
+    }
+}
+
+/// Verifies the signature of incoming webhooks of one provider.
+pub trait WebhookVerifier: Send + Sync {
+    /// Verify a webhook request.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - The raw request body, exactly as received
+    /// * `headers` - The request headers
+    ///
+    /// # Returns
+    ///
+    /// The verified event, or a `WebhookError` if the signature is missing or invalid.
+    fn verify(&self, payload: &[u8], headers: &HeaderMap) -> Result<VerifiedEvent, WebhookError>;
+}
+
+/// Compute the HMAC-SHA256 of the data.
+pub fn hmac_sha256(secret: &[u8], data: &[u8]) -> Vec<u8> {
+    // HMAC accepts keys of any length, so this cannot fail
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Compute the HMAC-SHA256 of the data as lowercase hex.
+pub fn hmac_sha256_hex(secret: &[u8], data: &[u8]) -> String {
+    hex::encode(hmac_sha256(secret, data))
+}
+
+/// Check a hex-encoded HMAC-SHA256 signature in constant time.
+pub fn verify_hmac_sha256_hex(secret: &[u8], data: &[u8], signature_hex: &str) -> bool {
+    let expected = hmac_sha256_hex(secret, data);
+    constant_time_eq(
+        expected.as_bytes(),
+        signature_hex.trim().to_ascii_lowercase().as_bytes(),
+    )
+}
+
+/// Compare two byte strings in constant time (for equal lengths).
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Get a header value as a string.
+///
+/// # Returns
+///
+/// The header value, or `MissingSignature` if it is absent or not valid UTF-8.
+pub fn signature_header<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, WebhookError> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| WebhookError::MissingSignature(format!("Missing {} header", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256_hex() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hmac_sha256_hex(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert!(verify_hmac_sha256_hex(
+            b"Jefe",
+            b"what do ya want for nothing?",
+            "5BDCC146BF60754E6A042426089575C75A003F089D2739839DEC58B964EC3843"
+        ));
+        assert!(!verify_hmac_sha256_hex(b"Jefe", b"tampered", "5bdc"));
+    }
+
+    #[test]
+    fn test_signature_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-signature", "abc".parse().unwrap());
+        assert_eq!(signature_header(&headers, "x-signature"), Ok("abc"));
+        assert!(matches!(
+            signature_header(&headers, "other"),
+            Err(WebhookError::MissingSignature(_))
+        ));
+    }
+}
@@ -0,0 +1,319 @@
+//! Received webhook events and how far their processing got.
+//!
+//! Providers deliver webhooks at least once and retry them until they get a success response,
+//! so the same event can arrive several times. Each verified event is stored here, keyed by
+//! provider and event id, before it is processed. Events that were processed are skipped when
+//! they arrive again, and the stored payload allows replaying events whose processing failed.
+//!
+//! The store defaults to an in-memory one; the backend replaces it with the database
+//! repository via [`configure_webhook_event_store`], so duplicates are detected across
+//! restarts and instances.
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::error::ConnectifyError;
+use crate::services::BoxFuture;
+
+/// How far the processing of a webhook event got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookEventStatus {
+    /// Stored, but not (yet) processed successfully, e.g. because the instance crashed.
+    Received,
+    /// Processed successfully; further deliveries are skipped.
+    Processed,
+    /// Processing failed; the provider's retry or a replay processes it again.
+    Failed,
+}
+
+impl WebhookEventStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventStatus::Received => "received",
+            WebhookEventStatus::Processed => "processed",
+            WebhookEventStatus::Failed => "failed",
+        }
+    }
+}
+
+impl fmt::Display for WebhookEventStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WebhookEventStatus {
+    type Err = ConnectifyError;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "received" => Ok(WebhookEventStatus::Received),
+            "processed" => Ok(WebhookEventStatus::Processed),
+            "failed" => Ok(WebhookEventStatus::Failed),
+            other => Err(ConnectifyError::ValidationError(format!(
+                "Unknown webhook event status: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A stored webhook event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookEventRecord {
+    /// The provider that sent the event, e.g. "stripe".
+    pub provider: String,
+    /// The provider's id of the event.
+    pub event_id: String,
+    /// The provider's type of the event, e.g. "checkout.session.completed".
+    pub event_type: String,
+    /// The raw, verified request body.
+    pub payload: String,
+    pub status: WebhookEventStatus,
+    /// How often processing was attempted.
+    pub attempts: u32,
+    /// The error of the last failed attempt.
+    pub last_error: Option<String>,
+    pub received_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl WebhookEventRecord {
+    /// A newly received event that has not been processed yet.
+    pub fn received(
+        provider: impl Into<String>,
+        event_id: impl Into<String>,
+        event_type: impl Into<String>,
+        payload: impl Into<String>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            provider: provider.into(),
+            event_id: event_id.into(),
+            event_type: event_type.into(),
+            payload: payload.into(),
+            status: WebhookEventStatus::Received,
+            attempts: 0,
+            last_error: None,
+            received_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Storage for received webhook events.
+///
+/// Implementations must make [`record`](WebhookEventStore::record) atomic, so that an event
+/// delivered to two instances at once is only stored once.
+pub trait WebhookEventStore: Send + Sync {
+    /// Store a newly received event, unless it is already stored.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the event was stored, the existing record if it had been received before.
+    fn record(
+        &self,
+        event: WebhookEventRecord,
+    ) -> BoxFuture<'_, Option<WebhookEventRecord>, ConnectifyError>;
+
+    /// Get a stored event.
+    fn get<'a>(
+        &'a self,
+        provider: &'a str,
+        event_id: &'a str,
+    ) -> BoxFuture<'a, Option<WebhookEventRecord>, ConnectifyError>;
+
+    /// Record the outcome of processing an event, counting the attempt.
+    ///
+    /// # Returns
+    ///
+    /// Whether the event is stored.
+    fn finish<'a>(
+        &'a self,
+        provider: &'a str,
+        event_id: &'a str,
+        status: WebhookEventStatus,
+        error: Option<String>,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, bool, ConnectifyError>;
+
+    /// The most recently received events of a provider, optionally only those with a status.
+    fn list<'a>(
+        &'a self,
+        provider: &'a str,
+        status: Option<WebhookEventStatus>,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<WebhookEventRecord>, ConnectifyError>;
+}
+
+/// A [`WebhookEventStore`] keeping events in memory, for single-instance deployments and tests.
+#[derive(Debug, Default)]
+pub struct InMemoryWebhookEventStore {
+    events: Mutex<HashMap<(String, String), WebhookEventRecord>>,
+}
+
+impl WebhookEventStore for InMemoryWebhookEventStore {
+    fn record(
+        &self,
+        event: WebhookEventRecord,
+    ) -> BoxFuture<'_, Option<WebhookEventRecord>, ConnectifyError> {
+        Box::pin(async move {
+            let mut events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+            let key = (event.provider.clone(), event.event_id.clone());
+            if let Some(existing) = events.get(&key) {
+                return Ok(Some(existing.clone()));
+            }
+            events.insert(key, event);
+            Ok(None)
+        })
+    }
+
+    fn get<'a>(
+        &'a self,
+        provider: &'a str,
+        event_id: &'a str,
+    ) -> BoxFuture<'a, Option<WebhookEventRecord>, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .events
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(&(provider.to_string(), event_id.to_string()))
+                .cloned())
+        })
+    }
+
+    fn finish<'a>(
+        &'a self,
+        provider: &'a str,
+        event_id: &'a str,
+        status: WebhookEventStatus,
+        error: Option<String>,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, bool, ConnectifyError> {
+        Box::pin(async move {
+            let mut events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(event) = events.get_mut(&(provider.to_string(), event_id.to_string())) else {
+                return Ok(false);
+            };
+            event.status = status;
+            event.attempts += 1;
+            event.last_error = error;
+            event.updated_at = now;
+            Ok(true)
+        })
+    }
+
+    fn list<'a>(
+        &'a self,
+        provider: &'a str,
+        status: Option<WebhookEventStatus>,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<WebhookEventRecord>, ConnectifyError> {
+        Box::pin(async move {
+            let events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+            let mut listed: Vec<WebhookEventRecord> = events
+                .values()
+                .filter(|event| event.provider == provider)
+                .filter(|event| status.is_none_or(|status| event.status == status))
+                .cloned()
+                .collect();
+            listed.sort_by_key(|event| std::cmp::Reverse(event.received_at));
+            listed.truncate(limit);
+            Ok(listed)
+        })
+    }
+}
+
+/// The global store returned by [`webhook_event_store`].
+static WEBHOOK_EVENT_STORE: Lazy<RwLock<Arc<dyn WebhookEventStore>>> =
+    Lazy::new(|| RwLock::new(Arc::new(InMemoryWebhookEventStore::default())));
+
+/// Replace the store used for received webhook events.
+pub fn configure_webhook_event_store(store: Arc<dyn WebhookEventStore>) {
+    *WEBHOOK_EVENT_STORE
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = store;
+}
+
+/// The store used for received webhook events.
+pub fn webhook_event_store() -> Arc<dyn WebhookEventStore> {
+    WEBHOOK_EVENT_STORE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn received(event_id: &str, second: i64) -> WebhookEventRecord {
+        WebhookEventRecord::received(
+            "stripe",
+            event_id,
+            "checkout.session.completed",
+            "{}",
+            DateTime::from_timestamp(second, 0).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemoryWebhookEventStore::default();
+        assert_eq!(store.record(received("evt_1", 1)).await.unwrap(), None);
+        assert_eq!(store.record(received("evt_2", 2)).await.unwrap(), None);
+
+        // Duplicates return the stored event
+        assert_eq!(
+            store.record(received("evt_1", 3)).await.unwrap(),
+            Some(received("evt_1", 1))
+        );
+
+        let now = DateTime::from_timestamp(4, 0).unwrap();
+        assert!(store
+            .finish(
+                "stripe",
+                "evt_1",
+                WebhookEventStatus::Failed,
+                Some("timeout".to_string()),
+                now
+            )
+            .await
+            .unwrap());
+        assert!(store
+            .finish("stripe", "evt_2", WebhookEventStatus::Processed, None, now)
+            .await
+            .unwrap());
+        assert!(!store
+            .finish("stripe", "evt_3", WebhookEventStatus::Processed, None, now)
+            .await
+            .unwrap());
+
+        let failed = store.get("stripe", "evt_1").await.unwrap().unwrap();
+        assert_eq!(failed.status, WebhookEventStatus::Failed);
+        assert_eq!(failed.attempts, 1);
+        assert_eq!(failed.last_error.as_deref(), Some("timeout"));
+        assert_eq!(failed.updated_at, now);
+
+        let listed = |status| store.list("stripe", status, 10);
+        assert_eq!(
+            listed(Some(WebhookEventStatus::Failed)).await.unwrap(),
+            vec![failed]
+        );
+        let all: Vec<String> = listed(None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|event| event.event_id)
+            .collect();
+        assert_eq!(all, vec!["evt_2", "evt_1"]);
+        assert!(store.list("payrexx", None, 10).await.unwrap().is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use connectify_config_static::load_config;
+use connectify_config_static::load_config_with_warnings;
 use serde_json::Value;
 use std::path::Path;
 use std::{env, fs};
@@ -10,7 +10,7 @@ fn main() {
     info!("build.rs: starting config load");
 
     // Load the configuration with improved error handling
-    let config = load_config().unwrap_or_else(|err| {
+    let (config, warnings) = load_config_with_warnings().unwrap_or_else(|err| {
         info!("Error loading configuration:");
         info!("  {}", err);
 
@@ -36,6 +36,9 @@ fn main() {
     });
 
     info!("build.rs: successfully loaded config");
+    for warning in warnings {
+        println!("cargo:warning={}", warning);
+    }
 
     // Convert the configuration to JSON
     let json = serde_json::to_value(&config).unwrap_or_else(|err| {
@@ -83,6 +83,9 @@ fn migrate_config(source_path: &str, target_path: &str) -> Result<(), Box<dyn st
     // Merge source into target
     merge_json(&mut target_value, &source_value);
 
+    // Move settings from deprecated keys to their current ones
+    migrate_deprecated_keys(&mut target_value);
+
     // Encrypt sensitive values
     secrets::process_json_for_encryption(&mut target_value)?;
 
@@ -113,6 +116,37 @@ fn migrate_config(source_path: &str, target_path: &str) -> Result<(), Box<dyn st
     Ok(())
 }
 
+/// Moves `stripe.price_tiers` and `stripe.default_currency` to the `pricing` section, unless it
+/// sets them already, in which case the deprecated keys are dropped.
+fn migrate_deprecated_keys(config: &mut Value) {
+    for key in ["price_tiers", "default_currency"] {
+        let Some(value) = config
+            .get_mut("stripe")
+            .and_then(Value::as_object_mut)
+            .and_then(|stripe| stripe.remove(key))
+        else {
+            continue;
+        };
+        let Some(pricing) = config.as_object_mut().and_then(|config| {
+            config
+                .entry("pricing")
+                .or_insert_with(|| serde_json::json!({}))
+                .as_object_mut()
+        }) else {
+            continue;
+        };
+        if pricing.contains_key(key) {
+            info!(
+                "Dropped deprecated stripe.{}, as pricing.{} is set",
+                key, key
+            );
+        } else {
+            pricing.insert(key.to_string(), value);
+            info!("Moved deprecated stripe.{} to pricing.{}", key, key);
+        }
+    }
+}
+
 /// Recursively merges source JSON into target JSON
 fn merge_json(target: &mut Value, source: &Value) {
     match (target, source) {
@@ -134,3 +168,32 @@ fn merge_json(target: &mut Value, source: &Value) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migrate_deprecated_keys() {
+        let mut config = json!({
+            "stripe": {
+                "success_url": "https://example.com/success",
+                "default_currency": "EUR",
+                "price_tiers": [{"duration_minutes": 60, "unit_amount": 9000}]
+            },
+            "pricing": {"default_currency": "CHF"}
+        });
+        migrate_deprecated_keys(&mut config);
+        assert_eq!(
+            config,
+            json!({
+                "stripe": {"success_url": "https://example.com/success"},
+                "pricing": {
+                    "default_currency": "CHF",
+                    "price_tiers": [{"duration_minutes": 60, "unit_amount": 9000}]
+                }
+            })
+        );
+    }
+}
@@ -86,7 +86,10 @@ pub fn load_config() -> Result<AppConfig, ConfigurationError> {
         })?;
 
     // Apply environment overrides
-    let config = apply_env_overrides(config)?;
+    let mut config = apply_env_overrides(config)?;
+    for warning in config.migrate_deprecated_keys() {
+        tracing::warn!("{}", warning);
+    }
 
     // Decrypt any encrypted values
     let config = decrypt_config(config)?;
@@ -234,3 +237,32 @@ fn validate_config(config: &AppConfig) -> Result<(), ConfigurationError> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_deprecated_pricing_keys() {
+        let mut config: AppConfig = serde_json::from_value(serde_json::json!({
+            "server": {"host": "127.0.0.1", "port": 8080},
+            "stripe": {
+                "success_url": "https://example.com/success",
+                "cancel_url": "https://example.com/cancel",
+                "payment_success_url": "https://example.com/payment-success",
+                "default_currency": "EUR",
+                "price_tiers": [{"duration_minutes": 60, "unit_amount": 9000}]
+            }
+        }))
+        .unwrap();
+        assert_eq!(config.migrate_deprecated_keys().len(), 2);
+
+        let pricing = config.pricing.as_ref().unwrap();
+        assert_eq!(pricing.default_currency.as_deref(), Some("EUR"));
+        assert_eq!(pricing.tier_for_duration(60).unwrap().unit_amount, 9000);
+        // The deprecated keys are not serialized again
+        let stripe = serde_json::to_value(config.stripe.as_ref().unwrap()).unwrap();
+        assert!(stripe.get("price_tiers").is_none());
+        assert!(config.migrate_deprecated_keys().is_empty());
+    }
+}
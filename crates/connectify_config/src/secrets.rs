@@ -231,11 +231,9 @@ pub fn process_json_for_encryption(value: &mut Value) -> Result<(), SecretError>
                 process_json_for_encryption(v)?;
             }
         }
-        Value::String(s) => {
-            // Don't encrypt values that are already encrypted
-            if !is_encrypted(s) && should_encrypt(s) {
-                *s = ensure_encrypted(s)?;
-            }
+        // Don't encrypt values that are already encrypted
+        Value::String(s) if !is_encrypted(s) && should_encrypt(s) => {
+            *s = ensure_encrypted(s)?;
         }
         _ => {}
     }
@@ -255,10 +253,8 @@ pub fn process_json_for_decryption(value: &mut Value) -> Result<(), SecretError>
                 process_json_for_decryption(v)?;
             }
         }
-        Value::String(s) => {
-            if is_encrypted(s) {
-                *s = ensure_decrypted(s)?;
-            }
+        Value::String(s) if is_encrypted(s) => {
+            *s = ensure_decrypted(s)?;
         }
         _ => {}
     }
@@ -6,9 +6,19 @@ use std::path::PathBuf;
 pub mod models;
 // use dotenv;
 pub use models::*;
-use tracing::info;
+use tracing::{info, warn};
 
 pub fn load_config() -> Result<AppConfig, ConfigError> {
+    let (config, warnings) = load_config_with_warnings()?;
+    for warning in warnings {
+        warn!("{}", warning);
+    }
+    Ok(config)
+}
+
+/// Like [`load_config`], returning the deprecation warnings of the configuration instead of
+/// logging them, e.g. for the build script to report them.
+pub fn load_config_with_warnings() -> Result<(AppConfig, Vec<String>), ConfigError> {
     ensure_dotenv_loaded();
 
     let run_env = env::var("RUN_ENV").unwrap_or_else(|_| "debug".to_string());
@@ -35,8 +45,9 @@ pub fn load_config() -> Result<AppConfig, ConfigError> {
 
     info!("build.rs: starting config load builder: {builder:?}");
 
-    let raw_config: AppConfig = builder.build()?.try_deserialize()?;
-    Ok(raw_config)
+    let mut raw_config: AppConfig = builder.build()?.try_deserialize()?;
+    let warnings = raw_config.migrate_deprecated_keys();
+    Ok((raw_config, warnings))
     // Ok(apply_env_overrides_from_marker(raw_config))
 }
 
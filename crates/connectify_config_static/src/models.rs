@@ -1,6 +1,7 @@
 // --- File: crates/connectify_config/src/models.rs ---
 
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 // #[cfg(feature = "openapi")]
 // use utoipa::{ToSchema, PartialSchema}; // , IntoParams};
 // --- General Server Config ---
@@ -21,14 +22,105 @@ pub struct DatabaseConfig {
 // --- Twilio Config ---
 // Holds non-secret Twilio config. Secrets loaded directly from env vars.
 #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
 pub struct TwilioConfig {
-    pub account_sid: String, // Loaded via TWILIO_ACCOUNT_SID
-    pub api_key_sid: String, // Loaded via TWILIO_API_KEY_SID
-    pub api_key_secret: String,
-    pub auth_token: String, // Loaded via TWILIO_AUTH_TOKEN
-    pub phone_number: String, // Loaded via TWILIO_PHONE_NUMBER
-                            // Secret loaded directly from env var: TWILIO_API_KEY_SECRET
+    pub account_sid: String,    // Loaded via TWILIO_ACCOUNT_SID
+    pub api_key_sid: String,    // Loaded via TWILIO_API_KEY_SID
+    pub api_key_secret: String, // Secret loaded directly from env var: TWILIO_API_KEY_SECRET
+    pub auth_token: String,     // Loaded via TWILIO_AUTH_TOKEN
+    pub phone_number: String,   // Loaded via TWILIO_PHONE_NUMBER
+    /// Number voice calls are placed from, a Twilio number or a verified caller ID.
+    #[serde(default)]
+    pub caller_id: Option<String>,
+    /// Public base URL of the API, e.g. `https://connectify.example.com/api`; Twilio reports
+    /// the progress of calls and SMS to `/twilio/calls/status` and `/twilio/messages/status`
+    /// below it. No status callbacks if not set.
+    #[serde(default)]
+    pub status_callback_base_url: Option<String>,
+    /// Text-to-speech voice of calls, e.g. `Polly.Marlene` (default: Twilio's default voice).
+    #[serde(default)]
+    pub voice: Option<String>,
+    /// Language of the text read in calls, e.g. `de-DE` (default: `en-US`).
+    #[serde(default)]
+    pub voice_language: Option<String>,
+    /// Days recordings and compositions of video rooms are kept before they are deleted; kept
+    /// until deleted in the Twilio console if not set.
+    #[serde(default)]
+    pub recording_retention_days: Option<u32>,
+    /// Locale of the SMS sent, e.g. `de` or `de-CH` (default: `en`).
+    #[serde(default)]
+    pub sms_locale: Option<String>,
+    /// SMS bodies keyed by locale and template name, e.g. `de.booking_confirmed`, overriding
+    /// the built-in English ones. Placeholders like `{start_time}` are substituted when sent.
+    #[serde(default)]
+    pub sms_templates: BTreeMap<String, BTreeMap<String, String>>,
+    /// Seconds access tokens are valid if the request sets no TTL (default: 3600).
+    #[serde(default)]
+    pub token_ttl_seconds: Option<u32>,
+    /// Longest TTL a token request can ask for (default: 14400, at most Twilio's 86400).
+    #[serde(default)]
+    pub token_max_ttl_seconds: Option<u32>,
+    /// Conversations service of chat grants; no chat grants if not set.
+    #[serde(default)]
+    pub chat_service_sid: Option<String>,
+    /// TwiML app handling the calls of voice grants; no voice grants if not set.
+    #[serde(default)]
+    pub voice_twiml_app_sid: Option<String>,
+    /// Only issue access tokens to signed-in users, for their user id.
+    #[serde(default)]
+    pub token_require_auth: bool,
+    /// SMS sent per second by the queue worker of each backend instance (default: 1, the
+    /// throughput of a long code).
+    #[serde(default)]
+    pub sms_per_second: Option<u32>,
+    /// SMS the queue worker may send at once after being idle (default: 1, spacing all sends).
+    #[serde(default)]
+    pub sms_burst: Option<u32>,
+    /// Messaging Service SMS are sent through, e.g. `MG0123…`; its sender pool, sticky sender
+    /// and fallback numbers pick the number of each message. Sent from the fixed sender if not
+    /// set.
+    #[serde(default)]
+    pub messaging_service_sid: Option<String>,
+    /// Compliance rules of SMS per destination country, keyed by calling code, e.g. `+41` or
+    /// `+1`; the longest code the recipient's number starts with applies.
+    #[serde(default)]
+    pub sms_countries: BTreeMap<String, SmsCountryConfig>,
+    /// SendGrid API key emails are sent with, e.g. `SG.…`; emails can't be sent without it.
+    #[serde(default)]
+    pub sendgrid_api_key: Option<String>,
+    /// Verified sender address of the emails.
+    #[serde(default)]
+    pub email_from: Option<String>,
+    /// Display name of the sender, e.g. `Connectify`.
+    #[serde(default)]
+    pub email_from_name: Option<String>,
+    /// SendGrid categories of all emails sent, grouping them in SendGrid's statistics.
+    #[serde(default)]
+    pub email_categories: Vec<String>,
+}
+
+/// How SMS to the numbers of a country are sent, see `TwilioConfig::sms_countries`.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct SmsCountryConfig {
+    /// Sender of the SMS, an alphanumeric sender ID like `Connectify` where the country allows
+    /// one or a local long code. Overrides `messaging_service_sid` and the fixed sender.
+    #[serde(default)]
+    pub from: Option<String>,
+    /// Time zone of the sending hours, e.g. `Europe/Zurich` (default: UTC).
+    #[serde(default)]
+    pub time_zone: Option<String>,
+    /// Local time SMS may be sent from, as "HH:MM", e.g. `08:00`.
+    #[serde(default)]
+    pub send_from: Option<String>,
+    /// Local time SMS may be sent until, as "HH:MM", e.g. `21:00`. Before `send_from`, the
+    /// hours span midnight.
+    #[serde(default)]
+    pub send_until: Option<String>,
+    /// Text the country requires in each SMS, e.g. `Reply STOP to opt out`, appended unless the
+    /// message contains it.
+    #[serde(default)]
+    pub opt_out_text: Option<String>,
 }
 
 #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
@@ -36,16 +128,87 @@ pub struct TwilioConfig {
 pub struct PriceTier {
     /// Duration in minutes for this price tier.
     pub duration_minutes: i64,
-    /// Price in the smallest currency unit (e.g., cents).
+    /// Price in the smallest currency unit (e.g., cents), in `currency`.
     pub unit_amount: i64,
     /// Optional product name specific to this tier.
     pub product_name: Option<String>,
-    /// Optional currency code for this tier.
+    /// Optional currency code of `unit_amount`, the default currency if not set.
     pub currency: Option<String>,
+    /// Prices in further currencies, keyed by currency code, e.g. `EUR: 6500`.
+    #[serde(default)]
+    pub amounts: BTreeMap<String, i64>,
+    /// Minutes kept free before an appointment of this tier, overriding the calendar's default.
+    #[serde(default)]
+    pub buffer_before_minutes: Option<i64>,
+    /// Minutes kept free after an appointment of this tier, overriding the calendar's default.
+    #[serde(default)]
+    pub buffer_after_minutes: Option<i64>,
+    /// Google Calendar color ("1".."11") of events of this tier.
+    #[serde(default)]
+    pub color_id: Option<String>,
+    /// Tags stored with events of this tier, to filter booked events by.
+    #[serde(default)]
+    pub tags: Vec<String>,
     // You could add a Price ID here if you manage prices directly in Stripe Dashboard
     // pub price_id: Option<String>,
 }
 
+impl PriceTier {
+    /// The currency of `unit_amount`, uppercase.
+    pub fn base_currency(&self, default_currency: Option<&str>) -> Option<String> {
+        self.currency
+            .as_deref()
+            .or(default_currency)
+            .map(str::to_uppercase)
+    }
+
+    /// The price of this tier in a currency, matched case-insensitively.
+    pub fn amount_in(&self, currency: &str, default_currency: Option<&str>) -> Option<i64> {
+        self.amounts
+            .iter()
+            .find(|(code, _)| code.eq_ignore_ascii_case(currency))
+            .map(|(_, amount)| *amount)
+            .or_else(|| {
+                self.base_currency(default_currency)
+                    .filter(|base| base.eq_ignore_ascii_case(currency))
+                    .map(|_| self.unit_amount)
+            })
+    }
+
+    /// The currencies this tier has a price in, uppercase.
+    pub fn currencies(&self, default_currency: Option<&str>) -> Vec<String> {
+        let mut currencies: Vec<String> = self
+            .base_currency(default_currency)
+            .into_iter()
+            .chain(self.amounts.keys().map(|code| code.to_uppercase()))
+            .collect();
+        currencies.sort();
+        currencies.dedup();
+        currencies
+    }
+}
+
+// --- Pricing Config ---
+// Prices of bookings, shared by availability queries and all payment providers.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct PricingConfig {
+    /// Currency of price tiers that don't set their own, e.g. `CHF`.
+    pub default_currency: Option<String>,
+    /// List of price tiers for different durations.
+    #[serde(default)]
+    pub price_tiers: Vec<PriceTier>,
+}
+
+impl PricingConfig {
+    /// The price tier for a duration in minutes.
+    pub fn tier_for_duration(&self, duration_minutes: i64) -> Option<&PriceTier> {
+        self.price_tiers
+            .iter()
+            .find(|tier| tier.duration_minutes == duration_minutes)
+    }
+}
+
 // --- Stripe Config ---
 // Holds non-secret Stripe config. Secret key loaded directly from env var.
 #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
@@ -53,13 +216,104 @@ pub struct PriceTier {
 pub struct StripeConfig {
     pub success_url: String, // Mandatory
     pub cancel_url: String,  // Mandatory
-    pub default_currency: Option<String>,
     pub unit_amount: Option<i64>,
     pub product_name: Option<String>,
     pub payment_success_url: String, // Mandatory
-    /// List of price tiers for different durations.
-    #[serde(default)] // Defaults to an empty vec if not present in config
-    pub price_tiers: Vec<PriceTier>,
+    /// Recurring prices that can be sold as subscriptions.
+    #[serde(default)]
+    pub subscription_plans: Vec<SubscriptionPlan>,
+    /// Let Stripe calculate the taxes of checkouts from the customer's address.
+    #[serde(default)]
+    pub automatic_tax: bool,
+    /// Whether the price tiers include taxes; the Stripe account's default if not set.
+    pub tax_behavior: Option<TaxBehavior>,
+    /// When checkouts ask for the billing address; Stripe asks only if needed if not set.
+    pub billing_address_collection: Option<BillingAddressCollection>,
+    /// Minutes until an unpaid checkout expires, from 30 to 1440 (default: 30).
+    #[serde(default)]
+    pub checkout_expires_after_minutes: Option<i64>,
+    /// Have Stripe create an invoice for each paid booking checkout.
+    #[serde(default)]
+    pub create_invoices: bool,
+    /// How often a failed fulfillment is attempted before it is left to an admin (default: 8).
+    #[serde(default)]
+    pub fulfillment_max_attempts: Option<u32>,
+    /// Payment methods offered by checkouts, e.g. `card`, `twint` or `sepa_debit` (default:
+    /// `card`). `automatic` offers the methods enabled in the Stripe dashboard instead, including
+    /// Apple Pay and Google Pay.
+    #[serde(default)]
+    pub payment_method_types: Vec<String>,
+    /// Base URL of the Stripe API, e.g. `http://localhost:12111/v1` for stripe-mock (default:
+    /// `https://api.stripe.com/v1`).
+    #[serde(default)]
+    pub api_base_url: Option<String>,
+    /// Page the Billing Portal links back to, unless a request sets its own; the portal's
+    /// default in the Stripe dashboard if not set.
+    #[serde(default)]
+    pub portal_return_url: Option<String>,
+    /// Seconds a webhook signature is accepted before or after its timestamp (default: 300).
+    #[serde(default)]
+    pub webhook_tolerance_seconds: Option<i64>,
+    /// Key the success tokens of checkouts are signed with, e.g. `secret_from_env` to read
+    /// `STRIPE_SUCCESS_TOKEN_SECRET`. Checkouts fail without it.
+    #[serde(default)]
+    pub success_token_secret: Option<String>,
+    /// Deprecated `stripe.default_currency`, moved to `pricing` on load.
+    #[serde(rename = "default_currency", default, skip_serializing)]
+    pub deprecated_default_currency: Option<String>,
+    /// Deprecated `stripe.price_tiers`, moved to `pricing` on load.
+    #[serde(rename = "price_tiers", default, skip_serializing)]
+    pub deprecated_price_tiers: Vec<PriceTier>,
+}
+
+/// Whether prices include taxes.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaxBehavior {
+    /// Taxes are included in the price.
+    Inclusive,
+    /// Taxes are added to the price.
+    Exclusive,
+}
+
+impl TaxBehavior {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaxBehavior::Inclusive => "inclusive",
+            TaxBehavior::Exclusive => "exclusive",
+        }
+    }
+}
+
+/// When a checkout asks for the customer's billing address.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BillingAddressCollection {
+    /// Only if the payment method or tax calculation needs it.
+    Auto,
+    /// Always.
+    Required,
+}
+
+impl BillingAddressCollection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BillingAddressCollection::Auto => "auto",
+            BillingAddressCollection::Required => "required",
+        }
+    }
+}
+
+/// A recurring Stripe price offered for subscription checkouts.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SubscriptionPlan {
+    /// The id of the recurring price in Stripe (price_...).
+    pub price_id: String,
+    /// Optional name of the plan, e.g. "Monthly coaching".
+    pub name: Option<String>,
 }
 #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
@@ -79,9 +333,17 @@ pub struct PayrexxConfig {
     pub currency: Option<String>,
     pub unit_amount: Option<i64>,
     pub product_name: Option<String>,
-    /// List of price tiers for different durations.
-    #[serde(default)] // Defaults to an empty vec if not present in config
-    pub price_tiers: Vec<PriceTier>,
+    /// Payment methods offered by gateways in this order (Payrexx `pm`), e.g. `twint` or
+    /// `postfinance-efinance`; all methods of the instance if empty.
+    #[serde(default)]
+    pub payment_methods: Vec<String>,
+    /// IDs of the payment service providers gateways may use (Payrexx `psp`), all if empty.
+    #[serde(default)]
+    pub psp: Vec<i64>,
+    /// Payment method of QR-bill invoices paid by bank transfer (Payrexx `pm`), default
+    /// `invoice`.
+    #[serde(default)]
+    pub qr_bill_payment_method: Option<String>,
     // API Secret loaded directly from env var: PAYREXX_API_SECRET
 }
 
@@ -99,20 +361,169 @@ pub struct CalendlyConfig {
 }
 
 // --- Google Calendar Config ---
+/// A further calendar whose availability is offered, e.g. of one staff member.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GcalCalendar {
+    /// The Google calendar ID.
+    pub id: String,
+    /// Name of the staff member or resource, returned with each slot.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Seats per slot, e.g. 8 for a group class; each slot is booked once if not set.
+    #[serde(default)]
+    pub capacity: Option<u32>,
+}
+
+/// The calendar server bookings are stored in.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CalendarBackend {
+    /// Google Calendar, authenticated with the service account in `key_path`.
+    #[default]
+    Google,
+    /// A CalDAV server such as Nextcloud, Fastmail or Radicale, see `caldav`.
+    Caldav,
+}
+
+/// Holds non-secret CalDAV config. The password is loaded directly from env var: CALDAV_PASSWORD
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CaldavConfig {
+    /// URL of the collection containing the calendars, e.g.
+    /// `https://cloud.example.com/remote.php/dav/calendars/anna/`. Calendar IDs are resolved
+    /// relative to it, unless they are absolute URLs themselves.
+    pub url: String,
+    pub username: String,
+}
+
+/// Holds the non-secret config of a Google OAuth client, used to connect a personal calendar
+/// through the consent screen. The client secret is loaded directly from env var:
+/// GCAL_OAUTH_CLIENT_SECRET
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GcalOAuthConfig {
+    /// The client ID of a "Web application" OAuth client in the Google Cloud console.
+    pub client_id: String,
+    /// Public URL of `/api/gcal/oauth/callback`, registered as redirect URI of the client.
+    pub redirect_uri: String,
+}
+
 #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct GcalConfig {
     // pub api_key: String, // Mandatory
     // pub key_path: Option<String>,
     // pub calendar_id: String, // Mandatory
-    pub key_path: Option<String>,        // Mandatory
+    pub key_path: Option<String>, // Mandatory
+    /// Workspace user the service account acts as (domain-wide delegation), so that bookings
+    /// land in their calendar and invitations are sent in their name.
+    pub impersonate_user: Option<String>,
     pub calendar_id: Option<String>,     // Mandatory
     pub time_slot_duration: Option<u16>, // In minutes
     pub preparation_time_minutes: Option<i64>,
+    /// How many days ahead slots can be booked; unlimited if not set.
+    pub max_advance_days: Option<i64>,
     pub time_zone: Option<String>,         // Time zone for the calendar
     pub working_days: Option<Vec<String>>, // Working days of the week
     pub work_start_time: Option<String>,   // Start time of the working day
     pub work_end_time: Option<String>,     // End time of the working day
+    /// Working hours per weekday ("Mon".."Sun"); replaces `working_days`, `work_start_time`
+    /// and `work_end_time` when set. Days not listed are off, gaps between intervals are breaks.
+    pub weekly_schedule: Option<HashMap<String, Vec<WorkInterval>>>,
+    /// Dates without availability, e.g. vacations; more can be added at runtime.
+    #[serde(default)]
+    pub blackout_dates: Vec<BlackoutPeriod>,
+    /// Calendars whose all-day events block availability, e.g. a Google public holiday
+    /// calendar like `de.swiss#holiday@group.v.calendar.google.com`.
+    #[serde(default)]
+    pub holiday_calendars: Vec<String>,
+    /// Calendars offered in addition to `calendar_id`; availability is combined across all.
+    #[serde(default)]
+    pub calendars: Vec<GcalCalendar>,
+    /// How long free/busy results are cached, in seconds (default 30, 0 disables caching).
+    pub busy_times_cache_seconds: Option<u64>,
+    /// Public URL of `/api/gcal/notifications`; when set, calendar changes are watched.
+    /// The channel token is read from `GCAL_PUSH_CHANNEL_TOKEN`.
+    pub push_notification_url: Option<String>,
+    /// How long a slot hold blocks its slot while the customer pays, in minutes (default 15).
+    pub hold_ttl_minutes: Option<i64>,
+    /// Minutes kept free before each appointment (default 0); price tiers can override it.
+    pub buffer_before_minutes: Option<i64>,
+    /// Minutes kept free after each appointment (default 0); price tiers can override it.
+    pub buffer_after_minutes: Option<i64>,
+    /// The calendar server to use (default: google).
+    #[serde(default)]
+    pub backend: CalendarBackend,
+    /// Connection to the CalDAV server, required if `backend` is `caldav`.
+    pub caldav: Option<CaldavConfig>,
+    /// Authenticate as a user who connected their calendar through the OAuth consent screen,
+    /// instead of with the service account key in `key_path`.
+    pub oauth: Option<GcalOAuthConfig>,
+    /// Mirror the calendars' events into the database, so conflict checks keep working from
+    /// the mirror while Google Calendar is unavailable.
+    #[serde(default)]
+    pub mirror_events: bool,
+    /// Color and tags of events created per fulfillment type (`gcal_booking`, `adhoc`);
+    /// a price tier's color takes precedence, tags are combined.
+    #[serde(default)]
+    pub fulfillment_styles: HashMap<String, EventStyleConfig>,
+    /// How bookings without a `calendar_id` are spread across the configured calendars;
+    /// if not set, they go to `calendar_id`.
+    pub assignment: Option<AssignmentStrategy>,
+}
+
+/// How a new booking picks one of several staff calendars.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AssignmentStrategy {
+    /// Take turns, starting after the calendar of the last assigned booking.
+    RoundRobin,
+    /// The calendar with the fewest upcoming assigned bookings.
+    LeastBooked,
+}
+
+/// How events created by a fulfillment type look in the calendar.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct EventStyleConfig {
+    /// Google Calendar color ("1".."11").
+    pub color_id: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+// --- Microsoft Graph Config ---
+/// Holds non-secret Microsoft Graph config. The client secret of the app registration is loaded
+/// directly from env var: MSGRAPH_CLIENT_SECRET
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MsGraphConfig {
+    /// The Azure AD tenant of the app registration.
+    pub tenant_id: String,
+    /// The application (client) ID, granted the `Calendars.ReadWrite` application permission.
+    pub client_id: String,
+    /// The mailbox (user ID or principal name) whose calendars are used.
+    pub user_id: String,
+}
+
+/// Dates without availability, as "YYYY-MM-DD"; `end_date` is inclusive and defaults to `start_date`.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct BlackoutPeriod {
+    pub start_date: String,
+    pub end_date: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// A working interval of a day, times as "HH:MM".
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct WorkInterval {
+    pub start: String,
+    pub end: String,
 }
 
 #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
@@ -134,6 +545,200 @@ pub struct FirebaseConfig {
     pub server_key: Option<String>,
 }
 
+// --- Rate Limit Config ---
+/// Token bucket limits applied per client (API key or IP address).
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RateLimitRule {
+    /// Number of requests a client may make per minute (the bucket refill rate).
+    pub requests_per_minute: u32,
+    /// Maximum burst size. Defaults to `requests_per_minute` if not set.
+    #[serde(default)]
+    pub burst: Option<u32>,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RateLimitConfig {
+    /// Limits applied to all API routes.
+    pub global: RateLimitRule,
+    /// Stricter limits for individual route groups, e.g. "stripe_webhook".
+    #[serde(default)]
+    pub groups: HashMap<String, RateLimitRule>,
+}
+
+// --- API Key Config ---
+/// A single API key accepted by the `X-Api-Key` authentication layer.
+/// Either the plain `key` (e.g. injected from the environment) or its hex encoded
+/// SHA-256 digest `key_sha256` must be set.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ApiKeyEntry {
+    /// Name of the caller, injected as the caller identity on success.
+    pub name: String,
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub key_sha256: Option<String>,
+    /// Scopes granted to this key, e.g. "fulfillment" or "admin".
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ApiKeysConfig {
+    #[serde(default)]
+    pub keys: Vec<ApiKeyEntry>,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct JwtConfig {
+    /// Shared secret for HS256 signed tokens.
+    #[serde(default)]
+    pub hs256_secret: Option<String>,
+    /// PEM encoded RSA public key for RS256 signed tokens.
+    #[serde(default)]
+    pub rs256_public_key_pem: Option<String>,
+    /// JWKS endpoint to fetch RS256 keys from, selected by the token's `kid`.
+    #[serde(default)]
+    pub jwks_url: Option<String>,
+    /// How long fetched JWKS keys are cached, in seconds (default: 3600).
+    #[serde(default)]
+    pub jwks_cache_seconds: Option<u64>,
+    /// Expected `iss` claim.
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// Expected `aud` claim.
+    #[serde(default)]
+    pub audience: Option<String>,
+    /// Allowed clock skew when validating `exp` and `nbf`, in seconds (default: 60).
+    #[serde(default)]
+    pub leeway_seconds: Option<u64>,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CircuitBreakerRule {
+    /// Consecutive failures after which the circuit opens.
+    #[serde(default = "default_circuit_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Seconds the circuit stays open before a half-open probe is allowed.
+    #[serde(default = "default_circuit_open_seconds")]
+    pub open_seconds: u64,
+}
+
+impl Default for CircuitBreakerRule {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_circuit_failure_threshold(),
+            open_seconds: default_circuit_open_seconds(),
+        }
+    }
+}
+
+fn default_circuit_failure_threshold() -> u32 {
+    5
+}
+
+fn default_circuit_open_seconds() -> u64 {
+    30
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct CircuitBreakerConfig {
+    /// Rule applied to hosts without a specific rule.
+    #[serde(default)]
+    pub default: CircuitBreakerRule,
+    /// Per-host rules, keyed by host name (e.g. "api.stripe.com").
+    #[serde(default)]
+    pub hosts: HashMap<String, CircuitBreakerRule>,
+}
+
+/// Settings of a single background job.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ScheduledJobConfig {
+    /// Cron expression overriding the job's default schedule, e.g. "0 3 * * *".
+    #[serde(default)]
+    pub cron: Option<String>,
+    /// Set to true to disable the job.
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct SchedulerConfig {
+    /// Time zone cron expressions are evaluated in (default: UTC).
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Per-job settings, keyed by job name (e.g. "idempotency_cleanup").
+    #[serde(default)]
+    pub jobs: HashMap<String, ScheduledJobConfig>,
+}
+
+/// Runtime kill switches for features.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct RuntimeFlagsConfig {
+    /// Flag states keyed by flag name (e.g. "bookings"); unlisted flags are enabled.
+    #[serde(default)]
+    pub flags: HashMap<String, bool>,
+}
+
+/// Cache settings.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct CacheConfig {
+    /// Redis URL (e.g. "redis://localhost:6379") to share the cache between instances.
+    /// The in-memory cache is used if unset.
+    #[serde(default)]
+    pub redis_url: Option<String>,
+    /// Maximum number of entries of the in-memory cache (default: 10000).
+    #[serde(default)]
+    pub max_entries: Option<u64>,
+}
+
+/// Distributed lock settings, for deployments with several backend instances.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct LockConfig {
+    /// "memory" (default, single instance), "redis" or "postgres" (uses the database).
+    #[serde(default)]
+    pub backend: Option<String>,
+    /// Redis URL for the "redis" backend; defaults to `cache.redis_url`.
+    #[serde(default)]
+    pub redis_url: Option<String>,
+}
+
+/// Message queue settings, for work processed outside the request that triggers it.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct QueueConfig {
+    /// "memory" (default, single instance), "redis" or "sqs".
+    #[serde(default)]
+    pub backend: Option<String>,
+    /// Redis URL for the "redis" backend; defaults to `cache.redis_url`.
+    #[serde(default)]
+    pub redis_url: Option<String>,
+    /// AWS region for the "sqs" backend. Credentials are read from `AWS_ACCESS_KEY_ID` and
+    /// `AWS_SECRET_ACCESS_KEY`.
+    #[serde(default)]
+    pub sqs_region: Option<String>,
+    /// Prefix of the SQS queue URLs; the queue name is appended.
+    #[serde(default)]
+    pub sqs_queue_url_prefix: Option<String>,
+    /// Custom SQS endpoint, e.g. a local emulator.
+    #[serde(default)]
+    pub sqs_endpoint: Option<String>,
+    /// Seconds a consumed message stays hidden before it is delivered again (default 30).
+    #[serde(default)]
+    pub visibility_timeout_seconds: Option<u64>,
+}
+
 fn default_adhoc_preparation_time() -> i64 {
     15
 } // Default 15 minutes preparation
@@ -162,11 +767,15 @@ pub struct AppConfig {
     pub use_adhoc: bool,
     #[serde(default)]
     pub use_firebase: bool,
+    #[serde(default)]
+    pub use_msgraph: bool,
 
     // --- Optional Feature Configurations ---
     #[serde(default)]
     pub database: Option<DatabaseConfig>, // Central DB config
     #[serde(default)]
+    pub pricing: Option<PricingConfig>, // Shared by gcal and the payment providers
+    #[serde(default)]
     pub twilio: Option<TwilioConfig>,
     #[serde(default)]
     pub stripe: Option<StripeConfig>,
@@ -180,6 +789,77 @@ pub struct AppConfig {
     pub adhoc_settings: Option<AdhocSessionSettings>,
     #[serde(default)]
     pub firebase: Option<FirebaseConfig>,
+    #[serde(default)]
+    pub msgraph: Option<MsGraphConfig>,
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitConfig>,
+    #[serde(default)]
+    pub api_keys: Option<ApiKeysConfig>,
+    #[serde(default)]
+    pub jwt: Option<JwtConfig>,
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    #[serde(default)]
+    pub scheduler: Option<SchedulerConfig>,
+    #[serde(default)]
+    pub runtime_flags: Option<RuntimeFlagsConfig>,
+    #[serde(default)]
+    pub cache: Option<CacheConfig>,
+    #[serde(default)]
+    pub lock: Option<LockConfig>,
+    #[serde(default)]
+    pub queue: Option<QueueConfig>,
+}
+
+impl AppConfig {
+    /// Moves settings from their deprecated keys to their current ones: `stripe.price_tiers`
+    /// and `stripe.default_currency` to `pricing`. Settings at the current keys win over
+    /// deprecated ones.
+    ///
+    /// # Returns
+    ///
+    /// A deprecation warning for each deprecated key that was set.
+    pub fn migrate_deprecated_keys(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let Some(stripe) = self.stripe.as_mut() else {
+            return warnings;
+        };
+        let price_tiers = std::mem::take(&mut stripe.deprecated_price_tiers);
+        let default_currency = stripe.deprecated_default_currency.take();
+        if price_tiers.is_empty() && default_currency.is_none() {
+            return warnings;
+        }
+        let pricing = self.pricing.get_or_insert_with(PricingConfig::default);
+        if !price_tiers.is_empty() {
+            if pricing.price_tiers.is_empty() {
+                pricing.price_tiers = price_tiers;
+                warnings.push(
+                    "stripe.price_tiers is deprecated and was moved to pricing.price_tiers"
+                        .to_string(),
+                );
+            } else {
+                warnings.push(
+                    "stripe.price_tiers is deprecated and ignored, as pricing.price_tiers is set"
+                        .to_string(),
+                );
+            }
+        }
+        if default_currency.is_some() {
+            if pricing.default_currency.is_none() {
+                pricing.default_currency = default_currency;
+                warnings.push(
+                    "stripe.default_currency is deprecated and was moved to pricing.default_currency"
+                        .to_string(),
+                );
+            } else {
+                warnings.push(
+                    "stripe.default_currency is deprecated and ignored, as pricing.default_currency is set"
+                        .to_string(),
+                );
+            }
+        }
+        warnings
+    }
 }
 
 impl Default for AppConfig {
@@ -197,7 +877,9 @@ impl Default for AppConfig {
             use_calendly: false,
             use_adhoc: false,
             use_firebase: false,
+            use_msgraph: false,
             database: None,
+            pricing: None,
             twilio: None,
             stripe: None,
             fulfillment: None,
@@ -205,6 +887,16 @@ impl Default for AppConfig {
             gcal: None,
             adhoc_settings: None,
             firebase: None,
+            msgraph: None,
+            rate_limit: None,
+            api_keys: None,
+            jwt: None,
+            circuit_breaker: None,
+            scheduler: None,
+            runtime_flags: None,
+            cache: None,
+            lock: None,
+            queue: None,
         }
     }
 }
@@ -1,5 +1,6 @@
 //! Error types for the database client
 
+use connectify_common::ConnectifyError;
 use thiserror::Error;
 
 /// Errors that can occur when working with the database client
@@ -37,3 +38,13 @@ pub enum DbError {
     #[error("Other database error: {0}")]
     Other(String),
 }
+
+/// Convert DbError to ConnectifyError
+impl From<DbError> for ConnectifyError {
+    fn from(err: DbError) -> Self {
+        match err {
+            DbError::ConfigError(msg) | DbError::UrlError(msg) => ConnectifyError::ConfigError(msg),
+            other => ConnectifyError::DatabaseError(other.to_string()),
+        }
+    }
+}
@@ -63,5 +63,9 @@ pub use repository::{Repository, RepositoryFactory};
 // Re-export the repositories module components for ease of use
 pub use repositories::{
     DeviceRegistration, DeviceRegistrationRepository, DeviceRegistrationRepositoryFactory,
-    SqlDeviceRegistrationRepository,
+    SqlAdvisoryLock, SqlAuditRepository, SqlBookingRepository, SqlDeadLetterRepository,
+    SqlDeviceRegistrationRepository, SqlEventMirrorRepository, SqlFulfillmentOrderRepository,
+    SqlIdempotencyRepository, SqlMessageStatusRepository, SqlOAuthTokenRepository,
+    SqlPaymentRepository, SqlRuntimeFlagRepository, SqlScheduleExceptionRepository,
+    SqlSlotHoldRepository, SqlSmsConsentRepository, SqlWebhookEventRepository,
 };
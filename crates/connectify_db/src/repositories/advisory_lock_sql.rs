@@ -0,0 +1,201 @@
+//! Postgres advisory lock implementation of the distributed lock
+//!
+//! This module provides an implementation of the `DistributedLock` trait from
+//! connectify_common based on Postgres session-level advisory locks. Each held lock keeps its
+//! own pooled connection; if the instance dies, Postgres releases the lock with the connection.
+
+use crate::error::DbError;
+use crate::DbClient;
+use connectify_common::lock::{DistributedLock, LockLease};
+use connectify_common::services::BoxFuture;
+use connectify_common::ConnectifyError;
+use sqlx::pool::PoolConnection;
+use sqlx::{Any, Row};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, error};
+
+/// A lock held on a dedicated connection
+struct HeldLock {
+    lock_id: i64,
+    connection: PoolConnection<Any>,
+    expires_at: Instant,
+}
+
+/// Postgres advisory lock implementation of the distributed lock
+#[derive(Clone)]
+pub struct SqlAdvisoryLock {
+    /// The database client
+    db_client: DbClient,
+    /// The held locks, by lease token
+    held: Arc<Mutex<HashMap<String, HeldLock>>>,
+}
+
+/// Map a lock key to an advisory lock ID
+///
+/// Uses 64-bit FNV-1a, which is stable across instances and releases.
+fn lock_id(key: &str) -> i64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in key.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash as i64
+}
+
+impl SqlAdvisoryLock {
+    /// Create a new advisory lock
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client, which must be connected to Postgres
+    ///
+    /// # Returns
+    ///
+    /// A new advisory lock
+    pub fn new(db_client: DbClient) -> Self {
+        Self {
+            db_client,
+            held: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    async fn try_lock(&self, key: &str, ttl: Duration) -> Result<Option<LockLease>, DbError> {
+        let lock_id = lock_id(key);
+        let mut connection = self
+            .db_client
+            .pool()
+            .acquire()
+            .await
+            .map_err(|e| DbError::ConnectionError(e.to_string()))?;
+
+        let row = sqlx::query("SELECT pg_try_advisory_lock($1)")
+            .bind(lock_id)
+            .fetch_one(&mut *connection)
+            .await
+            .map_err(|e| {
+                error!("Failed to acquire advisory lock: {}", e);
+                DbError::QueryError(e.to_string())
+            })?;
+        let acquired: bool = row
+            .try_get(0)
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        if !acquired {
+            return Ok(None);
+        }
+
+        debug!("Acquired advisory lock {} ({})", key, lock_id);
+        let lease = LockLease::new(key);
+        self.held.lock().unwrap_or_else(|e| e.into_inner()).insert(
+            lease.token.clone(),
+            HeldLock {
+                lock_id,
+                connection,
+                expires_at: Instant::now() + ttl,
+            },
+        );
+        self.spawn_expiry(lease.token.clone());
+        Ok(Some(lease))
+    }
+
+    /// Release the lock once it expires without being renewed
+    fn spawn_expiry(&self, token: String) {
+        let lock = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let expires_at = {
+                    let held = lock.held.lock().unwrap_or_else(|e| e.into_inner());
+                    match held.get(&token) {
+                        Some(held_lock) => held_lock.expires_at,
+                        None => return,
+                    }
+                };
+                tokio::time::sleep_until(expires_at.into()).await;
+
+                let expired = {
+                    let mut held = lock.held.lock().unwrap_or_else(|e| e.into_inner());
+                    match held.get(&token) {
+                        Some(held_lock) if held_lock.expires_at <= Instant::now() => {
+                            held.remove(&token)
+                        }
+                        Some(_) => continue,
+                        None => return,
+                    }
+                };
+                if let Some(held_lock) = expired {
+                    debug!("Advisory lock {} expired", held_lock.lock_id);
+                    unlock(held_lock).await;
+                }
+                return;
+            }
+        });
+    }
+
+    fn extend(&self, lease: &LockLease, ttl: Duration) -> bool {
+        let mut held = self.held.lock().unwrap_or_else(|e| e.into_inner());
+        match held.get_mut(&lease.token) {
+            Some(held_lock) if held_lock.expires_at > Instant::now() => {
+                held_lock.expires_at = Instant::now() + ttl;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    async fn unlock_lease(&self, lease: &LockLease) {
+        let held_lock = self
+            .held
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&lease.token);
+        if let Some(held_lock) = held_lock {
+            unlock(held_lock).await;
+        }
+    }
+}
+
+/// Unlock a held lock, closing its connection if unlocking fails
+async fn unlock(mut held_lock: HeldLock) {
+    let result = sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(held_lock.lock_id)
+        .execute(&mut *held_lock.connection)
+        .await;
+    if let Err(e) = result {
+        // Closing the session releases the lock
+        error!(
+            "Failed to release advisory lock {}: {}",
+            held_lock.lock_id, e
+        );
+        let _ = held_lock.connection.close().await;
+    }
+}
+
+impl DistributedLock for SqlAdvisoryLock {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn acquire<'a>(
+        &'a self,
+        key: &'a str,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Option<LockLease>, ConnectifyError> {
+        Box::pin(async move { Ok(self.try_lock(key, ttl).await?) })
+    }
+
+    fn renew<'a>(
+        &'a self,
+        lease: &'a LockLease,
+        ttl: Duration,
+    ) -> BoxFuture<'a, bool, ConnectifyError> {
+        Box::pin(async move { Ok(self.extend(lease, ttl)) })
+    }
+
+    fn release<'a>(&'a self, lease: &'a LockLease) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move {
+            self.unlock_lease(lease).await;
+            Ok(())
+        })
+    }
+}
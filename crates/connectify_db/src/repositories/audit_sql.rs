@@ -0,0 +1,96 @@
+//! SQL implementation of the audit sink
+//!
+//! This module provides a SQL implementation of the `AuditSink` trait from connectify_common,
+//! so that audit events are kept in the database next to the data they concern.
+
+use crate::error::DbError;
+use crate::DbClient;
+use connectify_common::audit::{AuditEvent, AuditSink};
+use connectify_common::services::BoxFuture;
+use connectify_common::ConnectifyError;
+use tracing::{debug, error, info};
+
+/// SQL implementation of the audit sink
+#[derive(Debug, Clone)]
+pub struct SqlAuditRepository {
+    /// The database client
+    db_client: DbClient,
+}
+
+impl SqlAuditRepository {
+    /// Create a new SQL audit repository
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client
+    ///
+    /// # Returns
+    ///
+    /// A new SQL audit repository
+    pub fn new(db_client: DbClient) -> Self {
+        Self { db_client }
+    }
+
+    /// Initialize the database schema
+    ///
+    /// This function creates the table for storing audit events if it doesn't exist.
+    pub async fn init_schema(&self) -> Result<(), DbError> {
+        debug!("Initializing audit schema");
+
+        let query = r#"
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id TEXT PRIMARY KEY,
+                occurred_at BIGINT NOT NULL,
+                actor TEXT NOT NULL,
+                action TEXT NOT NULL,
+                resource TEXT NOT NULL,
+                outcome TEXT NOT NULL,
+                request_id TEXT,
+                metadata TEXT NOT NULL
+            )
+        "#;
+
+        self.db_client.execute(query).await?;
+
+        info!("Audit schema initialized successfully");
+        Ok(())
+    }
+
+    async fn insert_event(&self, event: &AuditEvent) -> Result<(), DbError> {
+        let metadata = serde_json::to_string(&event.metadata)
+            .map_err(|e| DbError::Other(format!("Failed to serialize metadata: {}", e)))?;
+
+        sqlx::query(
+            r#"
+                INSERT INTO audit_log
+                    (id, occurred_at, actor, action, resource, outcome, request_id, metadata)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(&event.id)
+        .bind(event.timestamp.timestamp())
+        .bind(&event.actor)
+        .bind(&event.action)
+        .bind(&event.resource)
+        .bind(event.outcome.as_str())
+        .bind(&event.request_id)
+        .bind(metadata)
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to store audit event: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+}
+
+impl AuditSink for SqlAuditRepository {
+    fn name(&self) -> &'static str {
+        "database"
+    }
+
+    fn record<'a>(&'a self, event: &'a AuditEvent) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.insert_event(event).await?) })
+    }
+}
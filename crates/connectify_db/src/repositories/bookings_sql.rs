@@ -0,0 +1,326 @@
+//! SQL implementation of the booking ledger
+//!
+//! This module provides a SQL implementation of the `BookingLedger` trait from
+//! connectify_common, so that the seats of group slots are counted across backend instances,
+//! and bookings assigned to staff calendars can be looked up on all of them.
+
+use crate::error::DbError;
+use crate::DbClient;
+use chrono::{DateTime, Utc};
+use connectify_common::booking_ledger::{BookingAssignment, BookingLedger, SeatBooking};
+use connectify_common::services::BoxFuture;
+use connectify_common::ConnectifyError;
+use sqlx::any::AnyRow;
+use sqlx::Row;
+use std::collections::HashMap;
+use tracing::{debug, error, info};
+
+/// SQL implementation of the booking ledger
+#[derive(Debug, Clone)]
+pub struct SqlBookingRepository {
+    /// The database client
+    db_client: DbClient,
+}
+
+impl SqlBookingRepository {
+    /// Create a new SQL booking repository
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client
+    ///
+    /// # Returns
+    ///
+    /// A new SQL booking repository
+    pub fn new(db_client: DbClient) -> Self {
+        Self { db_client }
+    }
+
+    /// Initialize the database schema
+    ///
+    /// This function creates the tables for storing booked seats and the calendars bookings
+    /// were assigned to if they don't exist.
+    pub async fn init_schema(&self) -> Result<(), DbError> {
+        debug!("Initializing bookings schema");
+
+        let query = r#"
+            CREATE TABLE IF NOT EXISTS bookings (
+                id TEXT PRIMARY KEY,
+                calendar_id TEXT NOT NULL,
+                start_time BIGINT NOT NULL,
+                end_time BIGINT NOT NULL
+            )
+        "#;
+
+        self.db_client.execute(query).await?;
+
+        let assignments_query = r#"
+            CREATE TABLE IF NOT EXISTS booking_assignments (
+                event_id TEXT PRIMARY KEY,
+                calendar_id TEXT NOT NULL,
+                start_time BIGINT NOT NULL,
+                end_time BIGINT NOT NULL,
+                assigned_at BIGINT NOT NULL
+            )
+        "#;
+        self.db_client.execute(assignments_query).await?;
+
+        info!("Bookings schema initialized successfully");
+        Ok(())
+    }
+
+    fn from_row(row: &AnyRow) -> Result<SeatBooking, DbError> {
+        let timestamp = |column: &str| -> Result<DateTime<Utc>, DbError> {
+            let seconds: i64 = row
+                .try_get(column)
+                .map_err(|e| DbError::QueryError(e.to_string()))?;
+            DateTime::from_timestamp(seconds, 0)
+                .ok_or_else(|| DbError::Other(format!("Invalid {}: {}", column, seconds)))
+        };
+        Ok(SeatBooking {
+            id: row
+                .try_get("id")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            calendar_id: row
+                .try_get("calendar_id")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            start_time: timestamp("start_time")?,
+            end_time: timestamp("end_time")?,
+        })
+    }
+
+    fn assignment_from_row(row: &AnyRow) -> Result<BookingAssignment, DbError> {
+        let timestamp = |column: &str| -> Result<DateTime<Utc>, DbError> {
+            let seconds: i64 = row
+                .try_get(column)
+                .map_err(|e| DbError::QueryError(e.to_string()))?;
+            DateTime::from_timestamp(seconds, 0)
+                .ok_or_else(|| DbError::Other(format!("Invalid {}: {}", column, seconds)))
+        };
+        Ok(BookingAssignment {
+            event_id: row
+                .try_get("event_id")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            calendar_id: row
+                .try_get("calendar_id")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            start_time: timestamp("start_time")?,
+            end_time: timestamp("end_time")?,
+            assigned_at: timestamp("assigned_at")?,
+        })
+    }
+
+    async fn insert_booking(&self, booking: &SeatBooking) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+                INSERT INTO bookings (id, calendar_id, start_time, end_time)
+                VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(&booking.id)
+        .bind(&booking.calendar_id)
+        .bind(booking.start_time.timestamp())
+        .bind(booking.end_time.timestamp())
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to store booking: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+
+    async fn find_booking(&self, id: &str) -> Result<Option<SeatBooking>, DbError> {
+        let row = sqlx::query(
+            r#"
+                SELECT id, calendar_id, start_time, end_time
+                FROM bookings
+                WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.db_client.pool())
+        .await
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    async fn find_overlapping(
+        &self,
+        calendar_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<SeatBooking>, DbError> {
+        let rows = sqlx::query(
+            r#"
+                SELECT id, calendar_id, start_time, end_time
+                FROM bookings
+                WHERE calendar_id = $1 AND start_time < $2 AND end_time > $3
+                ORDER BY start_time
+            "#,
+        )
+        .bind(calendar_id)
+        .bind(end.timestamp())
+        .bind(start.timestamp())
+        .fetch_all(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to load bookings: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    async fn delete_booking(&self, id: &str) -> Result<bool, DbError> {
+        sqlx::query("DELETE FROM bookings WHERE id = $1")
+            .bind(id)
+            .execute(self.db_client.pool())
+            .await
+            .map(|result| result.rows_affected() > 0)
+            .map_err(|e| DbError::QueryError(e.to_string()))
+    }
+
+    async fn upsert_assignment(&self, assignment: &BookingAssignment) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+                INSERT INTO booking_assignments
+                    (event_id, calendar_id, start_time, end_time, assigned_at)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (event_id) DO UPDATE SET
+                    calendar_id = $2, start_time = $3, end_time = $4, assigned_at = $5
+            "#,
+        )
+        .bind(&assignment.event_id)
+        .bind(&assignment.calendar_id)
+        .bind(assignment.start_time.timestamp())
+        .bind(assignment.end_time.timestamp())
+        .bind(assignment.assigned_at.timestamp())
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to store booking assignment: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+
+    async fn find_assignment(&self, event_id: &str) -> Result<Option<BookingAssignment>, DbError> {
+        let row = sqlx::query(
+            r#"
+                SELECT event_id, calendar_id, start_time, end_time, assigned_at
+                FROM booking_assignments
+                WHERE event_id = $1
+            "#,
+        )
+        .bind(event_id)
+        .fetch_optional(self.db_client.pool())
+        .await
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+        row.as_ref().map(Self::assignment_from_row).transpose()
+    }
+
+    async fn find_latest_assignment(&self) -> Result<Option<BookingAssignment>, DbError> {
+        let row = sqlx::query(
+            r#"
+                SELECT event_id, calendar_id, start_time, end_time, assigned_at
+                FROM booking_assignments
+                ORDER BY assigned_at DESC
+                LIMIT 1
+            "#,
+        )
+        .fetch_optional(self.db_client.pool())
+        .await
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+        row.as_ref().map(Self::assignment_from_row).transpose()
+    }
+
+    async fn count_upcoming_assignments(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<HashMap<String, usize>, DbError> {
+        let rows = sqlx::query(
+            r#"
+                SELECT calendar_id, COUNT(*) AS bookings
+                FROM booking_assignments
+                WHERE end_time > $1
+                GROUP BY calendar_id
+            "#,
+        )
+        .bind(since.timestamp())
+        .fetch_all(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to count booking assignments: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        rows.iter()
+            .map(|row| {
+                let calendar_id: String = row
+                    .try_get("calendar_id")
+                    .map_err(|e| DbError::QueryError(e.to_string()))?;
+                let bookings: i64 = row
+                    .try_get("bookings")
+                    .map_err(|e| DbError::QueryError(e.to_string()))?;
+                Ok((calendar_id, bookings as usize))
+            })
+            .collect()
+    }
+
+    async fn delete_assignment(&self, event_id: &str) -> Result<bool, DbError> {
+        sqlx::query("DELETE FROM booking_assignments WHERE event_id = $1")
+            .bind(event_id)
+            .execute(self.db_client.pool())
+            .await
+            .map(|result| result.rows_affected() > 0)
+            .map_err(|e| DbError::QueryError(e.to_string()))
+    }
+}
+
+impl BookingLedger for SqlBookingRepository {
+    fn record(&self, booking: SeatBooking) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.insert_booking(&booking).await?) })
+    }
+
+    fn get<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Option<SeatBooking>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_booking(id).await?) })
+    }
+
+    fn overlapping<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> BoxFuture<'a, Vec<SeatBooking>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_overlapping(calendar_id, start, end).await?) })
+    }
+
+    fn release<'a>(&'a self, id: &'a str) -> BoxFuture<'a, bool, ConnectifyError> {
+        Box::pin(async move { Ok(self.delete_booking(id).await?) })
+    }
+
+    fn assign(&self, assignment: BookingAssignment) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.upsert_assignment(&assignment).await?) })
+    }
+
+    fn assignment<'a>(
+        &'a self,
+        event_id: &'a str,
+    ) -> BoxFuture<'a, Option<BookingAssignment>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_assignment(event_id).await?) })
+    }
+
+    fn latest_assignment(&self) -> BoxFuture<'_, Option<BookingAssignment>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_latest_assignment().await?) })
+    }
+
+    fn upcoming_assignments(
+        &self,
+        since: DateTime<Utc>,
+    ) -> BoxFuture<'_, HashMap<String, usize>, ConnectifyError> {
+        Box::pin(async move { Ok(self.count_upcoming_assignments(since).await?) })
+    }
+
+    fn unassign<'a>(&'a self, event_id: &'a str) -> BoxFuture<'a, bool, ConnectifyError> {
+        Box::pin(async move { Ok(self.delete_assignment(event_id).await?) })
+    }
+}
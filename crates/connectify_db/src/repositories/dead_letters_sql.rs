@@ -0,0 +1,255 @@
+//! SQL implementation of the dead letter store
+//!
+//! This module provides a SQL implementation of the `DeadLetterStore` trait from
+//! connectify_common, so that failed fulfillments are retried after restarts and by any backend
+//! instance.
+
+use crate::error::DbError;
+use crate::DbClient;
+use chrono::{DateTime, Utc};
+use connectify_common::dead_letters::{DeadLetter, DeadLetterStatus, DeadLetterStore};
+use connectify_common::services::BoxFuture;
+use connectify_common::ConnectifyError;
+use sqlx::any::AnyRow;
+use sqlx::Row;
+use tracing::{debug, error, info};
+
+/// Columns selected for a dead letter
+const COLUMNS: &str = "provider, payment_id, fulfillment_type, payload, status, attempts, \
+                       last_error, next_attempt_at, created_at, updated_at";
+
+/// SQL implementation of the dead letter store
+#[derive(Debug, Clone)]
+pub struct SqlDeadLetterRepository {
+    /// The database client
+    db_client: DbClient,
+}
+
+impl SqlDeadLetterRepository {
+    /// Create a new SQL dead letter repository
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client
+    ///
+    /// # Returns
+    ///
+    /// A new SQL dead letter repository
+    pub fn new(db_client: DbClient) -> Self {
+        Self { db_client }
+    }
+
+    /// Initialize the database schema
+    ///
+    /// This function creates the table for storing dead letters if it doesn't exist.
+    pub async fn init_schema(&self) -> Result<(), DbError> {
+        debug!("Initializing dead letters schema");
+
+        let query = r#"
+            CREATE TABLE IF NOT EXISTS fulfillment_dead_letters (
+                provider TEXT NOT NULL,
+                payment_id TEXT NOT NULL,
+                fulfillment_type TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                status TEXT NOT NULL,
+                attempts BIGINT NOT NULL,
+                last_error TEXT,
+                next_attempt_at BIGINT NOT NULL,
+                created_at BIGINT NOT NULL,
+                updated_at BIGINT NOT NULL,
+                PRIMARY KEY (provider, payment_id)
+            )
+        "#;
+
+        self.db_client.execute(query).await?;
+
+        info!("Dead letters schema initialized successfully");
+        Ok(())
+    }
+
+    fn from_row(row: &AnyRow) -> Result<DeadLetter, DbError> {
+        let timestamp = |column: &str| -> Result<DateTime<Utc>, DbError> {
+            let seconds: i64 = row
+                .try_get(column)
+                .map_err(|e| DbError::QueryError(e.to_string()))?;
+            DateTime::from_timestamp(seconds, 0)
+                .ok_or_else(|| DbError::Other(format!("Invalid {}: {}", column, seconds)))
+        };
+        let status: String = row
+            .try_get("status")
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        let attempts: i64 = row
+            .try_get("attempts")
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        Ok(DeadLetter {
+            provider: row
+                .try_get("provider")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            payment_id: row
+                .try_get("payment_id")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            fulfillment_type: row
+                .try_get("fulfillment_type")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            payload: row
+                .try_get("payload")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            status: status
+                .parse::<DeadLetterStatus>()
+                .map_err(|e| DbError::Other(e.to_string()))?,
+            attempts: attempts as u32,
+            last_error: row
+                .try_get("last_error")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            next_attempt_at: timestamp("next_attempt_at")?,
+            created_at: timestamp("created_at")?,
+            updated_at: timestamp("updated_at")?,
+        })
+    }
+
+    async fn save_letter(&self, letter: &DeadLetter) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+                INSERT INTO fulfillment_dead_letters
+                    (provider, payment_id, fulfillment_type, payload, status, attempts,
+                     last_error, next_attempt_at, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                ON CONFLICT (provider, payment_id)
+                DO UPDATE SET fulfillment_type = $3, payload = $4, status = $5, attempts = $6,
+                    last_error = $7, next_attempt_at = $8, updated_at = $10
+            "#,
+        )
+        .bind(&letter.provider)
+        .bind(&letter.payment_id)
+        .bind(&letter.fulfillment_type)
+        .bind(&letter.payload)
+        .bind(letter.status.as_str())
+        .bind(letter.attempts as i64)
+        .bind(&letter.last_error)
+        .bind(letter.next_attempt_at.timestamp())
+        .bind(letter.created_at.timestamp())
+        .bind(letter.updated_at.timestamp())
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!(
+                "Failed to store dead letter of {}: {}",
+                letter.payment_id, e
+            );
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+
+    async fn find_letter(
+        &self,
+        provider: &str,
+        payment_id: &str,
+    ) -> Result<Option<DeadLetter>, DbError> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM fulfillment_dead_letters WHERE provider = $1 AND payment_id = $2",
+            COLUMNS
+        ))
+        .bind(provider)
+        .bind(payment_id)
+        .fetch_optional(self.db_client.pool())
+        .await
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    async fn find_due(
+        &self,
+        provider: &str,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<DeadLetter>, DbError> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM fulfillment_dead_letters \
+             WHERE provider = $1 AND status = $2 AND next_attempt_at <= $3 \
+             ORDER BY next_attempt_at LIMIT $4",
+            COLUMNS
+        ))
+        .bind(provider)
+        .bind(DeadLetterStatus::Pending.as_str())
+        .bind(now.timestamp())
+        .bind(limit as i64)
+        .fetch_all(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to load due dead letters: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    async fn find_letters(
+        &self,
+        provider: &str,
+        status: Option<DeadLetterStatus>,
+        limit: usize,
+    ) -> Result<Vec<DeadLetter>, DbError> {
+        let rows = match status {
+            Some(status) => {
+                sqlx::query(&format!(
+                    "SELECT {} FROM fulfillment_dead_letters WHERE provider = $1 AND status = $2 \
+                     ORDER BY created_at DESC LIMIT $3",
+                    COLUMNS
+                ))
+                .bind(provider)
+                .bind(status.as_str())
+                .bind(limit as i64)
+                .fetch_all(self.db_client.pool())
+                .await
+            }
+            None => {
+                sqlx::query(&format!(
+                    "SELECT {} FROM fulfillment_dead_letters WHERE provider = $1 \
+                     ORDER BY created_at DESC LIMIT $2",
+                    COLUMNS
+                ))
+                .bind(provider)
+                .bind(limit as i64)
+                .fetch_all(self.db_client.pool())
+                .await
+            }
+        }
+        .map_err(|e| {
+            error!("Failed to load dead letters: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        rows.iter().map(Self::from_row).collect()
+    }
+}
+
+impl DeadLetterStore for SqlDeadLetterRepository {
+    fn save(&self, letter: DeadLetter) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.save_letter(&letter).await?) })
+    }
+
+    fn get<'a>(
+        &'a self,
+        provider: &'a str,
+        payment_id: &'a str,
+    ) -> BoxFuture<'a, Option<DeadLetter>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_letter(provider, payment_id).await?) })
+    }
+
+    fn due<'a>(
+        &'a self,
+        provider: &'a str,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<DeadLetter>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_due(provider, now, limit).await?) })
+    }
+
+    fn list<'a>(
+        &'a self,
+        provider: &'a str,
+        status: Option<DeadLetterStatus>,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<DeadLetter>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_letters(provider, status, limit).await?) })
+    }
+}
@@ -4,6 +4,7 @@
 //! in the database.
 
 use crate::error::DbError;
+use connectify_common::models::{Page, SortOrder};
 use sqlx::FromRow;
 
 // Re-export DeviceRegistration from connectify_common for convenience
@@ -117,6 +118,26 @@ pub trait DeviceRegistrationRepository {
         &self,
     ) -> impl std::future::Future<Output = Result<Vec<DeviceRegistration>, DbError>> + Send;
 
+    /// List a page of device registrations, ordered by ID
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Only list the registrations of this user, if set
+    /// * `after_id` - Only list registrations after this ID in the given order, if set
+    /// * `limit` - The maximum number of registrations on the page
+    /// * `order` - Whether to list the oldest or the newest registrations first
+    ///
+    /// # Returns
+    ///
+    /// A page of device registrations, whose cursor is the ID of its last registration
+    fn list_page(
+        &self,
+        user_id: Option<&str>,
+        after_id: Option<i64>,
+        limit: usize,
+        order: SortOrder,
+    ) -> impl std::future::Future<Output = Result<Page<DeviceRegistration>, DbError>> + Send;
+
     /// Delete a device registration
     ///
     /// # Arguments
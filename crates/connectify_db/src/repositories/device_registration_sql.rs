@@ -5,6 +5,7 @@
 use crate::error::DbError;
 use crate::repositories::device_registration::{DeviceRegistration, DeviceRegistrationRepository};
 use crate::DbClient;
+use connectify_common::models::{Page, SortOrder};
 use sqlx::Row;
 use tracing::{debug, error, info};
 
@@ -248,6 +249,80 @@ impl DeviceRegistrationRepository for SqlDeviceRegistrationRepository {
         Ok(results)
     }
 
+    async fn list_page(
+        &self,
+        user_id: Option<&str>,
+        after_id: Option<i64>,
+        limit: usize,
+        order: SortOrder,
+    ) -> Result<Page<DeviceRegistration>, DbError> {
+        debug!("Listing a page of device registrations");
+
+        let (comparison, direction) = match order {
+            SortOrder::Asc => (">", "ASC"),
+            SortOrder::Desc => ("<", "DESC"),
+        };
+        let mut conditions = Vec::new();
+        if user_id.is_some() {
+            conditions.push(format!("user_id = ${}", conditions.len() + 1));
+        }
+        if after_id.is_some() {
+            conditions.push(format!("id {} ${}", comparison, conditions.len() + 1));
+        }
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let query = format!(
+            r#"
+            SELECT id, user_id, device_id, registration_token, created_at, updated_at
+            FROM device_registrations
+            {}
+            ORDER BY id {}
+            LIMIT ${}
+        "#,
+            filter,
+            direction,
+            conditions.len() + 1
+        );
+
+        let mut sql_query = sqlx::query(&query);
+        if let Some(user_id) = user_id {
+            sql_query = sql_query.bind(user_id);
+        }
+        if let Some(after_id) = after_id {
+            sql_query = sql_query.bind(after_id);
+        }
+        // Fetch one more row than requested to tell whether there is a next page
+        let rows = sql_query
+            .bind(limit as i64 + 1)
+            .fetch_all(self.db_client.pool())
+            .await
+            .map_err(|e| {
+                error!("Failed to list device registrations: {}", e);
+                DbError::QueryError(e.to_string())
+            })?;
+
+        let results = rows
+            .into_iter()
+            .map(|row| {
+                DeviceRegistration {
+                    id: row.try_get("id").ok(),
+                    user_id: row.try_get("user_id").unwrap_or_default(),
+                    device_id: row.try_get("device_id").unwrap_or_default(),
+                    registration_token: row.try_get("registration_token").unwrap_or_default(),
+                    created_at: None, // DateTime<Utc> doesn't implement Decode for sqlx::Any
+                    updated_at: None, // DateTime<Utc> doesn't implement Decode for sqlx::Any
+                }
+            })
+            .collect();
+
+        Ok(Page::from_items(results, limit, |registration| {
+            registration.id.unwrap_or_default().to_string()
+        }))
+    }
+
     async fn delete_registration(&self, user_id: &str, device_id: &str) -> Result<bool, DbError> {
         debug!(
             "Deleting device registration for user: {} and device: {}",
@@ -0,0 +1,263 @@
+//! SQL implementation of the event mirror
+//!
+//! This module provides a SQL implementation of the `EventMirror` trait from
+//! connectify_common, so that mirrored calendar events survive restarts and are shared by all
+//! backend instances.
+
+use crate::error::DbError;
+use crate::DbClient;
+use chrono::{DateTime, Utc};
+use connectify_common::event_mirror::{EventMirror, MirroredEvent, MirroredEventStatus};
+use connectify_common::services::BoxFuture;
+use connectify_common::ConnectifyError;
+use sqlx::any::AnyRow;
+use sqlx::Row;
+use tracing::{debug, error, info};
+
+/// SQL implementation of the event mirror
+#[derive(Debug, Clone)]
+pub struct SqlEventMirrorRepository {
+    /// The database client
+    db_client: DbClient,
+}
+
+impl SqlEventMirrorRepository {
+    /// Create a new SQL event mirror repository
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client
+    ///
+    /// # Returns
+    ///
+    /// A new SQL event mirror repository
+    pub fn new(db_client: DbClient) -> Self {
+        Self { db_client }
+    }
+
+    /// Initialize the database schema
+    ///
+    /// This function creates the tables for storing mirrored events and the sync token of
+    /// each calendar if they don't exist.
+    pub async fn init_schema(&self) -> Result<(), DbError> {
+        debug!("Initializing event mirror schema");
+
+        let events_query = r#"
+            CREATE TABLE IF NOT EXISTS mirrored_events (
+                calendar_id TEXT NOT NULL,
+                id TEXT NOT NULL,
+                summary TEXT,
+                start_time BIGINT NOT NULL,
+                end_time BIGINT NOT NULL,
+                status TEXT NOT NULL,
+                updated_at BIGINT NOT NULL,
+                PRIMARY KEY (calendar_id, id)
+            )
+        "#;
+        self.db_client.execute(events_query).await?;
+
+        let sync_tokens_query = r#"
+            CREATE TABLE IF NOT EXISTS calendar_sync_tokens (
+                calendar_id TEXT PRIMARY KEY,
+                sync_token TEXT NOT NULL
+            )
+        "#;
+        self.db_client.execute(sync_tokens_query).await?;
+
+        info!("Event mirror schema initialized successfully");
+        Ok(())
+    }
+
+    fn from_row(row: &AnyRow) -> Result<MirroredEvent, DbError> {
+        let timestamp = |column: &str| -> Result<DateTime<Utc>, DbError> {
+            let seconds: i64 = row
+                .try_get(column)
+                .map_err(|e| DbError::QueryError(e.to_string()))?;
+            DateTime::from_timestamp(seconds, 0)
+                .ok_or_else(|| DbError::Other(format!("Invalid {}: {}", column, seconds)))
+        };
+        let status: String = row
+            .try_get("status")
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        Ok(MirroredEvent {
+            id: row
+                .try_get("id")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            calendar_id: row
+                .try_get("calendar_id")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            summary: row
+                .try_get("summary")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            start_time: timestamp("start_time")?,
+            end_time: timestamp("end_time")?,
+            status: status
+                .parse::<MirroredEventStatus>()
+                .map_err(|e| DbError::Other(e.to_string()))?,
+            updated_at: timestamp("updated_at")?,
+        })
+    }
+
+    async fn upsert_events(&self, events: &[MirroredEvent]) -> Result<(), DbError> {
+        for event in events {
+            sqlx::query(
+                r#"
+                    INSERT INTO mirrored_events
+                        (calendar_id, id, summary, start_time, end_time, status, updated_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    ON CONFLICT (calendar_id, id) DO UPDATE SET
+                        summary = $3, start_time = $4, end_time = $5, status = $6,
+                        updated_at = $7
+                "#,
+            )
+            .bind(&event.calendar_id)
+            .bind(&event.id)
+            .bind(&event.summary)
+            .bind(event.start_time.timestamp())
+            .bind(event.end_time.timestamp())
+            .bind(event.status.as_str())
+            .bind(event.updated_at.timestamp())
+            .execute(self.db_client.pool())
+            .await
+            .map_err(|e| {
+                error!("Failed to store mirrored event: {}", e);
+                DbError::QueryError(e.to_string())
+            })?;
+        }
+        Ok(())
+    }
+
+    async fn update_cancelled(
+        &self,
+        calendar_id: &str,
+        ids: &[String],
+        updated_at: DateTime<Utc>,
+    ) -> Result<(), DbError> {
+        for id in ids {
+            sqlx::query(
+                r#"
+                    UPDATE mirrored_events SET status = $1, updated_at = $2
+                    WHERE calendar_id = $3 AND id = $4
+                "#,
+            )
+            .bind(MirroredEventStatus::Cancelled.as_str())
+            .bind(updated_at.timestamp())
+            .bind(calendar_id)
+            .bind(id)
+            .execute(self.db_client.pool())
+            .await
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    async fn find_overlapping(
+        &self,
+        calendar_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<MirroredEvent>, DbError> {
+        let rows = sqlx::query(
+            r#"
+                SELECT calendar_id, id, summary, start_time, end_time, status, updated_at
+                FROM mirrored_events
+                WHERE calendar_id = $1 AND start_time < $2 AND end_time > $3
+                ORDER BY start_time
+            "#,
+        )
+        .bind(calendar_id)
+        .bind(end.timestamp())
+        .bind(start.timestamp())
+        .fetch_all(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to load mirrored events: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    async fn find_sync_token(&self, calendar_id: &str) -> Result<Option<String>, DbError> {
+        let row = sqlx::query("SELECT sync_token FROM calendar_sync_tokens WHERE calendar_id = $1")
+            .bind(calendar_id)
+            .fetch_optional(self.db_client.pool())
+            .await
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        row.map(|row| row.try_get("sync_token"))
+            .transpose()
+            .map_err(|e| DbError::QueryError(e.to_string()))
+    }
+
+    async fn upsert_sync_token(&self, calendar_id: &str, sync_token: &str) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+                INSERT INTO calendar_sync_tokens (calendar_id, sync_token)
+                VALUES ($1, $2)
+                ON CONFLICT (calendar_id) DO UPDATE SET sync_token = $2
+            "#,
+        )
+        .bind(calendar_id)
+        .bind(sync_token)
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn delete_calendar(&self, calendar_id: &str) -> Result<(), DbError> {
+        for query in [
+            "DELETE FROM mirrored_events WHERE calendar_id = $1",
+            "DELETE FROM calendar_sync_tokens WHERE calendar_id = $1",
+        ] {
+            sqlx::query(query)
+                .bind(calendar_id)
+                .execute(self.db_client.pool())
+                .await
+                .map_err(|e| DbError::QueryError(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl EventMirror for SqlEventMirrorRepository {
+    fn upsert(&self, events: Vec<MirroredEvent>) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.upsert_events(&events).await?) })
+    }
+
+    fn mark_cancelled<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        ids: Vec<String>,
+        updated_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.update_cancelled(calendar_id, &ids, updated_at).await?) })
+    }
+
+    fn overlapping<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> BoxFuture<'a, Vec<MirroredEvent>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_overlapping(calendar_id, start, end).await?) })
+    }
+
+    fn sync_token<'a>(
+        &'a self,
+        calendar_id: &'a str,
+    ) -> BoxFuture<'a, Option<String>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_sync_token(calendar_id).await?) })
+    }
+
+    fn set_sync_token<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        sync_token: String,
+    ) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.upsert_sync_token(calendar_id, &sync_token).await?) })
+    }
+
+    fn clear<'a>(&'a self, calendar_id: &'a str) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.delete_calendar(calendar_id).await?) })
+    }
+}
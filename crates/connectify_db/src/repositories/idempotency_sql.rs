@@ -0,0 +1,210 @@
+//! SQL implementation of the idempotency store
+//!
+//! This module provides a SQL implementation of the `IdempotencyStore` trait from
+//! connectify_common, so that idempotency keys survive restarts and are shared between
+//! backend instances.
+
+use crate::error::DbError;
+use crate::DbClient;
+use base64::{engine::general_purpose::STANDARD as base64_engine, Engine as _};
+use connectify_common::idempotency::{
+    IdempotencyRecord, IdempotencyStore, StoredResponse, DEFAULT_IDEMPOTENCY_TTL,
+};
+use connectify_common::services::BoxFuture;
+use connectify_common::ConnectifyError;
+use sqlx::Row;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+/// SQL implementation of the idempotency store
+#[derive(Debug, Clone)]
+pub struct SqlIdempotencyRepository {
+    /// The database client
+    db_client: DbClient,
+    /// How long keys are kept
+    ttl: Duration,
+}
+
+impl SqlIdempotencyRepository {
+    /// Create a new SQL idempotency repository
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client
+    ///
+    /// # Returns
+    ///
+    /// A new SQL idempotency repository keeping keys for 24 hours
+    pub fn new(db_client: DbClient) -> Self {
+        Self {
+            db_client,
+            ttl: DEFAULT_IDEMPOTENCY_TTL,
+        }
+    }
+
+    /// Set how long keys are kept
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Initialize the database schema
+    ///
+    /// This function creates the table for storing idempotency keys if it doesn't exist.
+    /// Response bodies are stored base64 encoded, since SQLite and Postgres share no binary
+    /// column type.
+    pub async fn init_schema(&self) -> Result<(), DbError> {
+        debug!("Initializing idempotency schema");
+
+        let query = r#"
+            CREATE TABLE IF NOT EXISTS idempotency_keys (
+                idempotency_key TEXT PRIMARY KEY,
+                fingerprint TEXT NOT NULL,
+                status_code INTEGER,
+                headers TEXT,
+                body TEXT,
+                created_at BIGINT NOT NULL
+            )
+        "#;
+
+        self.db_client.execute(query).await?;
+
+        info!("Idempotency schema initialized successfully");
+        Ok(())
+    }
+
+    async fn reserve_key(
+        &self,
+        key: &str,
+        fingerprint: &str,
+    ) -> Result<Option<IdempotencyRecord>, DbError> {
+        let now = chrono::Utc::now().timestamp();
+        self.purge_expired_keys().await?;
+
+        // The primary key makes the insert the atomic reservation
+        let inserted = sqlx::query(
+            r#"
+                INSERT INTO idempotency_keys (idempotency_key, fingerprint, created_at)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (idempotency_key) DO NOTHING
+            "#,
+        )
+        .bind(key)
+        .bind(fingerprint)
+        .bind(now)
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to reserve idempotency key: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        if inserted.rows_affected() > 0 {
+            return Ok(None);
+        }
+
+        let row = sqlx::query(
+            r#"
+                SELECT fingerprint, status_code, headers, body
+                FROM idempotency_keys
+                WHERE idempotency_key = $1
+            "#,
+        )
+        .bind(key)
+        .fetch_one(self.db_client.pool())
+        .await
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+
+        let status: Option<i64> = row.try_get("status_code").unwrap_or_default();
+        let response = match status {
+            Some(status) => {
+                let headers: Option<String> = row.try_get("headers").unwrap_or_default();
+                Some(StoredResponse {
+                    status: status as u16,
+                    headers: headers
+                        .and_then(|h| serde_json::from_str(&h).ok())
+                        .unwrap_or_default(),
+                    body: row
+                        .try_get::<Option<String>, _>("body")
+                        .unwrap_or_default()
+                        .and_then(|body| base64_engine.decode(body).ok())
+                        .unwrap_or_default(),
+                })
+            }
+            None => None,
+        };
+        Ok(Some(IdempotencyRecord {
+            fingerprint: row.try_get("fingerprint").unwrap_or_default(),
+            response,
+        }))
+    }
+
+    async fn complete_key(&self, key: &str, response: StoredResponse) -> Result<(), DbError> {
+        let headers = serde_json::to_string(&response.headers)
+            .map_err(|e| DbError::Other(format!("Failed to serialize headers: {}", e)))?;
+
+        sqlx::query(
+            r#"
+                UPDATE idempotency_keys
+                SET status_code = $1, headers = $2, body = $3
+                WHERE idempotency_key = $4
+            "#,
+        )
+        .bind(response.status as i64)
+        .bind(headers)
+        .bind(base64_engine.encode(&response.body))
+        .bind(key)
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to store idempotent response: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+
+    async fn purge_expired_keys(&self) -> Result<u64, DbError> {
+        let expired_before = chrono::Utc::now().timestamp() - self.ttl.as_secs() as i64;
+
+        sqlx::query("DELETE FROM idempotency_keys WHERE created_at < $1")
+            .bind(expired_before)
+            .execute(self.db_client.pool())
+            .await
+            .map(|result| result.rows_affected())
+            .map_err(|e| DbError::QueryError(e.to_string()))
+    }
+
+    async fn release_key(&self, key: &str) -> Result<(), DbError> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE idempotency_key = $1")
+            .bind(key)
+            .execute(self.db_client.pool())
+            .await
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        Ok(())
+    }
+}
+
+impl IdempotencyStore for SqlIdempotencyRepository {
+    fn reserve<'a>(
+        &'a self,
+        key: &'a str,
+        fingerprint: &'a str,
+    ) -> BoxFuture<'a, Option<IdempotencyRecord>, ConnectifyError> {
+        Box::pin(async move { Ok(self.reserve_key(key, fingerprint).await?) })
+    }
+
+    fn complete<'a>(
+        &'a self,
+        key: &'a str,
+        response: StoredResponse,
+    ) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.complete_key(key, response).await?) })
+    }
+
+    fn release<'a>(&'a self, key: &'a str) -> BoxFuture<'a, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.release_key(key).await?) })
+    }
+
+    fn purge_expired(&self) -> BoxFuture<'_, u64, ConnectifyError> {
+        Box::pin(async move { Ok(self.purge_expired_keys().await?) })
+    }
+}
@@ -0,0 +1,171 @@
+//! SQL implementation of the message status store
+//!
+//! This module provides a SQL implementation of the `MessageStatusStore` trait from
+//! connectify_common, so that SMS delivery statuses can be looked up after restarts and on any
+//! backend instance.
+
+use crate::error::DbError;
+use crate::DbClient;
+use chrono::{DateTime, Utc};
+use connectify_common::message_status::{MessageStatusRecord, MessageStatusStore};
+use connectify_common::services::BoxFuture;
+use connectify_common::ConnectifyError;
+use sqlx::any::AnyRow;
+use sqlx::Row;
+use tracing::{debug, error, info};
+
+/// Columns selected for a message status
+const COLUMNS: &str = "message_sid, recipient, status, error_code, created_at, updated_at";
+
+/// SQL implementation of the message status store
+#[derive(Debug, Clone)]
+pub struct SqlMessageStatusRepository {
+    /// The database client
+    db_client: DbClient,
+}
+
+impl SqlMessageStatusRepository {
+    /// Create a new SQL message status repository
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client
+    ///
+    /// # Returns
+    ///
+    /// A new SQL message status repository
+    pub fn new(db_client: DbClient) -> Self {
+        Self { db_client }
+    }
+
+    /// Initialize the database schema
+    ///
+    /// This function creates the table for storing message statuses if it doesn't exist.
+    pub async fn init_schema(&self) -> Result<(), DbError> {
+        debug!("Initializing message statuses schema");
+
+        let query = r#"
+            CREATE TABLE IF NOT EXISTS sms_message_statuses (
+                message_sid TEXT PRIMARY KEY,
+                recipient TEXT NOT NULL,
+                status TEXT NOT NULL,
+                error_code TEXT,
+                created_at BIGINT NOT NULL,
+                updated_at BIGINT NOT NULL
+            )
+        "#;
+
+        self.db_client.execute(query).await?;
+
+        info!("Message statuses schema initialized successfully");
+        Ok(())
+    }
+
+    fn from_row(row: &AnyRow) -> Result<MessageStatusRecord, DbError> {
+        let timestamp = |column: &str| -> Result<DateTime<Utc>, DbError> {
+            let seconds: i64 = row
+                .try_get(column)
+                .map_err(|e| DbError::QueryError(e.to_string()))?;
+            DateTime::from_timestamp(seconds, 0)
+                .ok_or_else(|| DbError::Other(format!("Invalid {}: {}", column, seconds)))
+        };
+        Ok(MessageStatusRecord {
+            message_sid: row
+                .try_get("message_sid")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            to: row
+                .try_get("recipient")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            status: row
+                .try_get("status")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            error_code: row
+                .try_get("error_code")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            created_at: timestamp("created_at")?,
+            updated_at: timestamp("updated_at")?,
+        })
+    }
+
+    async fn save_status(&self, record: &MessageStatusRecord) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+                INSERT INTO sms_message_statuses
+                    (message_sid, recipient, status, error_code, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (message_sid)
+                DO UPDATE SET recipient = $2, status = $3, error_code = $4, updated_at = $6
+            "#,
+        )
+        .bind(&record.message_sid)
+        .bind(&record.to)
+        .bind(&record.status)
+        .bind(&record.error_code)
+        .bind(record.created_at.timestamp())
+        .bind(record.updated_at.timestamp())
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!(
+                "Failed to store status of message {}: {}",
+                record.message_sid, e
+            );
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+
+    async fn find_status(&self, message_sid: &str) -> Result<Option<MessageStatusRecord>, DbError> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM sms_message_statuses WHERE message_sid = $1",
+            COLUMNS
+        ))
+        .bind(message_sid)
+        .fetch_optional(self.db_client.pool())
+        .await
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    async fn find_for_recipient(
+        &self,
+        to: &str,
+        limit: usize,
+    ) -> Result<Vec<MessageStatusRecord>, DbError> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM sms_message_statuses WHERE recipient = $1 \
+             ORDER BY created_at DESC LIMIT $2",
+            COLUMNS
+        ))
+        .bind(to)
+        .bind(limit as i64)
+        .fetch_all(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to load message statuses: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        rows.iter().map(Self::from_row).collect()
+    }
+}
+
+impl MessageStatusStore for SqlMessageStatusRepository {
+    fn save(&self, record: MessageStatusRecord) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.save_status(&record).await?) })
+    }
+
+    fn get<'a>(
+        &'a self,
+        message_sid: &'a str,
+    ) -> BoxFuture<'a, Option<MessageStatusRecord>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_status(message_sid).await?) })
+    }
+
+    fn list_for_recipient<'a>(
+        &'a self,
+        to: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<MessageStatusRecord>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_for_recipient(to, limit).await?) })
+    }
+}
@@ -3,11 +3,41 @@
 //! This module contains repository traits and implementations for different
 //! database entities.
 
+pub mod advisory_lock_sql;
+pub mod audit_sql;
+pub mod bookings_sql;
+pub mod dead_letters_sql;
 pub mod device_registration;
 pub mod device_registration_factory;
 pub mod device_registration_sql;
+pub mod event_mirror_sql;
+pub mod idempotency_sql;
+pub mod message_statuses_sql;
+pub mod oauth_tokens_sql;
+pub mod orders_sql;
+pub mod payments_sql;
+pub mod runtime_flags_sql;
+pub mod schedule_exceptions_sql;
+pub mod slot_holds_sql;
+pub mod sms_consent_sql;
+pub mod webhook_events_sql;
 
 // Re-export the device registration repository and factory for ease of use
+pub use advisory_lock_sql::SqlAdvisoryLock;
+pub use audit_sql::SqlAuditRepository;
+pub use bookings_sql::SqlBookingRepository;
+pub use dead_letters_sql::SqlDeadLetterRepository;
 pub use device_registration::{DeviceRegistration, DeviceRegistrationRepository};
 pub use device_registration_factory::DeviceRegistrationRepositoryFactory;
 pub use device_registration_sql::SqlDeviceRegistrationRepository;
+pub use event_mirror_sql::SqlEventMirrorRepository;
+pub use idempotency_sql::SqlIdempotencyRepository;
+pub use message_statuses_sql::SqlMessageStatusRepository;
+pub use oauth_tokens_sql::SqlOAuthTokenRepository;
+pub use orders_sql::SqlFulfillmentOrderRepository;
+pub use payments_sql::SqlPaymentRepository;
+pub use runtime_flags_sql::SqlRuntimeFlagRepository;
+pub use schedule_exceptions_sql::SqlScheduleExceptionRepository;
+pub use slot_holds_sql::SqlSlotHoldRepository;
+pub use sms_consent_sql::SqlSmsConsentRepository;
+pub use webhook_events_sql::SqlWebhookEventRepository;
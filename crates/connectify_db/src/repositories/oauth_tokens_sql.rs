@@ -0,0 +1,133 @@
+//! SQL implementation of the OAuth token store
+//!
+//! This module provides a SQL implementation of the `OAuthTokenStore` trait from
+//! connectify_common, so that calendars connected through an OAuth consent screen stay
+//! connected across restarts and backend instances.
+
+use crate::error::DbError;
+use crate::DbClient;
+use chrono::DateTime;
+use connectify_common::oauth_tokens::{OAuthRefreshToken, OAuthTokenStore};
+use connectify_common::services::BoxFuture;
+use connectify_common::ConnectifyError;
+use sqlx::Row;
+use tracing::{debug, error, info};
+
+/// SQL implementation of the OAuth token store
+#[derive(Debug, Clone)]
+pub struct SqlOAuthTokenRepository {
+    /// The database client
+    db_client: DbClient,
+}
+
+impl SqlOAuthTokenRepository {
+    /// Create a new SQL OAuth token repository
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client
+    ///
+    /// # Returns
+    ///
+    /// A new SQL OAuth token repository
+    pub fn new(db_client: DbClient) -> Self {
+        Self { db_client }
+    }
+
+    /// Initialize the database schema
+    ///
+    /// This function creates the table for storing refresh tokens if it doesn't exist.
+    pub async fn init_schema(&self) -> Result<(), DbError> {
+        debug!("Initializing OAuth tokens schema");
+
+        let query = r#"
+            CREATE TABLE IF NOT EXISTS oauth_tokens (
+                provider TEXT PRIMARY KEY,
+                refresh_token TEXT NOT NULL,
+                updated_at BIGINT NOT NULL
+            )
+        "#;
+
+        self.db_client.execute(query).await?;
+
+        info!("OAuth tokens schema initialized successfully");
+        Ok(())
+    }
+
+    async fn find_token(&self, provider: &str) -> Result<Option<OAuthRefreshToken>, DbError> {
+        let row = sqlx::query(
+            r#"
+                SELECT provider, refresh_token, updated_at
+                FROM oauth_tokens
+                WHERE provider = $1
+            "#,
+        )
+        .bind(provider)
+        .fetch_optional(self.db_client.pool())
+        .await
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let updated_at: i64 = row
+            .try_get("updated_at")
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        Ok(Some(OAuthRefreshToken {
+            provider: row
+                .try_get("provider")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            refresh_token: row
+                .try_get("refresh_token")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            updated_at: DateTime::from_timestamp(updated_at, 0)
+                .ok_or_else(|| DbError::Other(format!("Invalid updated_at: {}", updated_at)))?,
+        }))
+    }
+
+    async fn upsert_token(&self, token: &OAuthRefreshToken) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+                INSERT INTO oauth_tokens (provider, refresh_token, updated_at)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (provider) DO UPDATE SET refresh_token = $2, updated_at = $3
+            "#,
+        )
+        .bind(&token.provider)
+        .bind(&token.refresh_token)
+        .bind(token.updated_at.timestamp())
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to store OAuth token: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+
+    async fn delete_token(&self, provider: &str) -> Result<bool, DbError> {
+        sqlx::query("DELETE FROM oauth_tokens WHERE provider = $1")
+            .bind(provider)
+            .execute(self.db_client.pool())
+            .await
+            .map(|result| result.rows_affected() > 0)
+            .map_err(|e| DbError::QueryError(e.to_string()))
+    }
+}
+
+impl OAuthTokenStore for SqlOAuthTokenRepository {
+    fn get<'a>(
+        &'a self,
+        provider: &'a str,
+    ) -> BoxFuture<'a, Option<OAuthRefreshToken>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_token(provider).await?) })
+    }
+
+    fn put(&self, token: OAuthRefreshToken) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.upsert_token(&token).await?) })
+    }
+
+    fn delete<'a>(&'a self, provider: &'a str) -> BoxFuture<'a, bool, ConnectifyError> {
+        Box::pin(async move { Ok(self.delete_token(provider).await?) })
+    }
+}
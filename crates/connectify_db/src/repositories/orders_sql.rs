@@ -0,0 +1,127 @@
+//! SQL implementation of the fulfillment order store
+//!
+//! This module provides a SQL implementation of the `FulfillmentOrderStore` trait from
+//! connectify_common, so that the fulfillment payloads of checkouts survive restarts and are
+//! found by the backend instance receiving the payment webhook.
+
+use crate::error::DbError;
+use crate::DbClient;
+use chrono::DateTime;
+use connectify_common::orders::{FulfillmentOrder, FulfillmentOrderStore};
+use connectify_common::services::BoxFuture;
+use connectify_common::ConnectifyError;
+use sqlx::any::AnyRow;
+use sqlx::Row;
+use tracing::{debug, error, info};
+
+/// SQL implementation of the fulfillment order store
+#[derive(Debug, Clone)]
+pub struct SqlFulfillmentOrderRepository {
+    /// The database client
+    db_client: DbClient,
+}
+
+impl SqlFulfillmentOrderRepository {
+    /// Create a new SQL fulfillment order repository
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client
+    ///
+    /// # Returns
+    ///
+    /// A new SQL fulfillment order repository
+    pub fn new(db_client: DbClient) -> Self {
+        Self { db_client }
+    }
+
+    /// Initialize the database schema
+    ///
+    /// This function creates the table for storing fulfillment orders if it doesn't exist.
+    pub async fn init_schema(&self) -> Result<(), DbError> {
+        debug!("Initializing fulfillment orders schema");
+
+        let query = r#"
+            CREATE TABLE IF NOT EXISTS fulfillment_orders (
+                order_id TEXT PRIMARY KEY,
+                fulfillment_type TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                created_at BIGINT NOT NULL
+            )
+        "#;
+
+        self.db_client.execute(query).await?;
+
+        info!("Fulfillment orders schema initialized successfully");
+        Ok(())
+    }
+
+    fn from_row(row: &AnyRow) -> Result<FulfillmentOrder, DbError> {
+        let created_at: i64 = row
+            .try_get("created_at")
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        Ok(FulfillmentOrder {
+            order_id: row
+                .try_get("order_id")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            fulfillment_type: row
+                .try_get("fulfillment_type")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            payload: row
+                .try_get("payload")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            created_at: DateTime::from_timestamp(created_at, 0)
+                .ok_or_else(|| DbError::Other(format!("Invalid created_at: {}", created_at)))?,
+        })
+    }
+
+    async fn save_order(&self, order: &FulfillmentOrder) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+                INSERT INTO fulfillment_orders (order_id, fulfillment_type, payload, created_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (order_id)
+                DO UPDATE SET fulfillment_type = $2, payload = $3
+            "#,
+        )
+        .bind(&order.order_id)
+        .bind(&order.fulfillment_type)
+        .bind(&order.payload)
+        .bind(order.created_at.timestamp())
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!(
+                "Failed to store fulfillment order {}: {}",
+                order.order_id, e
+            );
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+
+    async fn find_order(&self, order_id: &str) -> Result<Option<FulfillmentOrder>, DbError> {
+        let row = sqlx::query(
+            "SELECT order_id, fulfillment_type, payload, created_at \
+             FROM fulfillment_orders WHERE order_id = $1",
+        )
+        .bind(order_id)
+        .fetch_optional(self.db_client.pool())
+        .await
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+        row.as_ref().map(Self::from_row).transpose()
+    }
+}
+
+impl FulfillmentOrderStore for SqlFulfillmentOrderRepository {
+    fn save(&self, order: FulfillmentOrder) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.save_order(&order).await?) })
+    }
+
+    fn get<'a>(
+        &'a self,
+        order_id: &'a str,
+    ) -> BoxFuture<'a, Option<FulfillmentOrder>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_order(order_id).await?) })
+    }
+}
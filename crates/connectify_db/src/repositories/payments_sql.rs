@@ -0,0 +1,250 @@
+//! SQL implementation of the payment customer and payment record stores
+//!
+//! This module provides a SQL implementation of the `PaymentCustomerStore` and
+//! `PaymentRecordStore` traits from connectify_common, so that returning clients are linked to
+//! their existing payment provider customer across restarts and backend instances, and
+//! completed payments are kept with their tax amounts.
+
+use crate::error::DbError;
+use crate::DbClient;
+use chrono::DateTime;
+use connectify_common::payments::{
+    normalize_email, PaymentCustomer, PaymentCustomerStore, PaymentRecord, PaymentRecordStore,
+};
+use connectify_common::services::BoxFuture;
+use connectify_common::ConnectifyError;
+use sqlx::Row;
+use tracing::{debug, error, info};
+
+/// SQL implementation of the payment customer and payment record stores
+#[derive(Debug, Clone)]
+pub struct SqlPaymentRepository {
+    /// The database client
+    db_client: DbClient,
+}
+
+impl SqlPaymentRepository {
+    /// Create a new SQL payment repository
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client
+    ///
+    /// # Returns
+    ///
+    /// A new SQL payment repository
+    pub fn new(db_client: DbClient) -> Self {
+        Self { db_client }
+    }
+
+    /// Initialize the database schema
+    ///
+    /// This function creates the tables for storing payment customers and payments if they
+    /// don't exist.
+    pub async fn init_schema(&self) -> Result<(), DbError> {
+        debug!("Initializing payments schema");
+
+        let query = r#"
+            CREATE TABLE IF NOT EXISTS payment_customers (
+                provider TEXT NOT NULL,
+                email TEXT NOT NULL,
+                customer_id TEXT NOT NULL,
+                created_at BIGINT NOT NULL,
+                PRIMARY KEY (provider, email)
+            )
+        "#;
+        self.db_client.execute(query).await?;
+
+        let query = r#"
+            CREATE TABLE IF NOT EXISTS payments (
+                provider TEXT NOT NULL,
+                payment_id TEXT NOT NULL,
+                reference TEXT,
+                customer_id TEXT,
+                currency TEXT,
+                amount_total BIGINT,
+                amount_subtotal BIGINT,
+                amount_tax BIGINT,
+                country TEXT,
+                created_at BIGINT NOT NULL,
+                PRIMARY KEY (provider, payment_id)
+            )
+        "#;
+        self.db_client.execute(query).await?;
+
+        info!("Payments schema initialized successfully");
+        Ok(())
+    }
+
+    async fn save_customer(&self, customer: &PaymentCustomer) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+                INSERT INTO payment_customers (provider, email, customer_id, created_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (provider, email)
+                DO UPDATE SET customer_id = $3, created_at = $4
+            "#,
+        )
+        .bind(&customer.provider)
+        .bind(normalize_email(&customer.email))
+        .bind(&customer.customer_id)
+        .bind(customer.created_at.timestamp())
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to store payment customer: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+
+    async fn find_customer(
+        &self,
+        provider: &str,
+        email: &str,
+    ) -> Result<Option<PaymentCustomer>, DbError> {
+        let row = sqlx::query(
+            r#"
+                SELECT provider, email, customer_id, created_at
+                FROM payment_customers
+                WHERE provider = $1 AND email = $2
+            "#,
+        )
+        .bind(provider)
+        .bind(normalize_email(email))
+        .fetch_optional(self.db_client.pool())
+        .await
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let created_at: i64 = row
+            .try_get("created_at")
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        Ok(Some(PaymentCustomer {
+            provider: row
+                .try_get("provider")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            email: row
+                .try_get("email")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            customer_id: row
+                .try_get("customer_id")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            created_at: DateTime::from_timestamp(created_at, 0)
+                .ok_or_else(|| DbError::Other(format!("Invalid created_at: {}", created_at)))?,
+        }))
+    }
+
+    async fn save_payment(&self, payment: &PaymentRecord) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+                INSERT INTO payments
+                    (provider, payment_id, reference, customer_id, currency, amount_total,
+                     amount_subtotal, amount_tax, country, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                ON CONFLICT (provider, payment_id)
+                DO UPDATE SET reference = $3, customer_id = $4, currency = $5, amount_total = $6,
+                    amount_subtotal = $7, amount_tax = $8, country = $9
+            "#,
+        )
+        .bind(&payment.provider)
+        .bind(&payment.payment_id)
+        .bind(&payment.reference)
+        .bind(&payment.customer_id)
+        .bind(&payment.currency)
+        .bind(payment.amount_total)
+        .bind(payment.amount_subtotal)
+        .bind(payment.amount_tax)
+        .bind(&payment.country)
+        .bind(payment.created_at.timestamp())
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to store payment {}: {}", payment.payment_id, e);
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+
+    async fn find_payment(
+        &self,
+        provider: &str,
+        payment_id: &str,
+    ) -> Result<Option<PaymentRecord>, DbError> {
+        let row = sqlx::query(
+            r#"
+                SELECT provider, payment_id, reference, customer_id, currency, amount_total,
+                       amount_subtotal, amount_tax, country, created_at
+                FROM payments
+                WHERE provider = $1 AND payment_id = $2
+            "#,
+        )
+        .bind(provider)
+        .bind(payment_id)
+        .fetch_optional(self.db_client.pool())
+        .await
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let column = |name: &str| -> Result<Option<String>, DbError> {
+            row.try_get(name)
+                .map_err(|e| DbError::QueryError(e.to_string()))
+        };
+        let amount = |name: &str| -> Result<Option<i64>, DbError> {
+            row.try_get(name)
+                .map_err(|e| DbError::QueryError(e.to_string()))
+        };
+        let created_at: i64 = row
+            .try_get("created_at")
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        Ok(Some(PaymentRecord {
+            provider: row
+                .try_get("provider")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            payment_id: row
+                .try_get("payment_id")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            reference: column("reference")?,
+            customer_id: column("customer_id")?,
+            currency: column("currency")?,
+            amount_total: amount("amount_total")?,
+            amount_subtotal: amount("amount_subtotal")?,
+            amount_tax: amount("amount_tax")?,
+            country: column("country")?,
+            created_at: DateTime::from_timestamp(created_at, 0)
+                .ok_or_else(|| DbError::Other(format!("Invalid created_at: {}", created_at)))?,
+        }))
+    }
+}
+
+impl PaymentCustomerStore for SqlPaymentRepository {
+    fn save(&self, customer: PaymentCustomer) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.save_customer(&customer).await?) })
+    }
+
+    fn find_by_email<'a>(
+        &'a self,
+        provider: &'a str,
+        email: &'a str,
+    ) -> BoxFuture<'a, Option<PaymentCustomer>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_customer(provider, email).await?) })
+    }
+}
+
+impl PaymentRecordStore for SqlPaymentRepository {
+    fn record_payment(&self, payment: PaymentRecord) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.save_payment(&payment).await?) })
+    }
+
+    fn get_payment<'a>(
+        &'a self,
+        provider: &'a str,
+        payment_id: &'a str,
+    ) -> BoxFuture<'a, Option<PaymentRecord>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_payment(provider, payment_id).await?) })
+    }
+}
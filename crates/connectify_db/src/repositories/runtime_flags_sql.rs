@@ -0,0 +1,118 @@
+//! SQL implementation of the runtime flag source
+//!
+//! This module provides a SQL implementation of the `RuntimeFlagSource` trait from
+//! connectify_common, so that operators can switch features off for all backend instances
+//! by updating a row.
+
+use crate::error::DbError;
+use crate::DbClient;
+use connectify_common::runtime_flags::RuntimeFlagSource;
+use connectify_common::services::BoxFuture;
+use connectify_common::ConnectifyError;
+use sqlx::Row;
+use std::collections::HashMap;
+use tracing::{debug, error, info};
+
+/// SQL implementation of the runtime flag source
+#[derive(Debug, Clone)]
+pub struct SqlRuntimeFlagRepository {
+    /// The database client
+    db_client: DbClient,
+}
+
+impl SqlRuntimeFlagRepository {
+    /// Create a new SQL runtime flag repository
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client
+    ///
+    /// # Returns
+    ///
+    /// A new SQL runtime flag repository
+    pub fn new(db_client: DbClient) -> Self {
+        Self { db_client }
+    }
+
+    /// Initialize the database schema
+    ///
+    /// This function creates the table for storing runtime flags if it doesn't exist.
+    pub async fn init_schema(&self) -> Result<(), DbError> {
+        debug!("Initializing runtime flags schema");
+
+        let query = r#"
+            CREATE TABLE IF NOT EXISTS runtime_flags (
+                name TEXT PRIMARY KEY,
+                enabled BOOLEAN NOT NULL,
+                updated_at BIGINT NOT NULL
+            )
+        "#;
+
+        self.db_client.execute(query).await?;
+
+        info!("Runtime flags schema initialized successfully");
+        Ok(())
+    }
+
+    /// Enable or disable a flag
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The flag name, e.g. "bookings"
+    /// * `enabled` - Whether the flag is enabled
+    pub async fn set_flag(&self, name: &str, enabled: bool) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+                INSERT INTO runtime_flags (name, enabled, updated_at)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (name) DO UPDATE SET enabled = $2, updated_at = $3
+            "#,
+        )
+        .bind(name)
+        .bind(enabled)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to set runtime flag: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+
+    /// Load all flags
+    ///
+    /// # Returns
+    ///
+    /// The flag states keyed by flag name
+    pub async fn load_flags(&self) -> Result<HashMap<String, bool>, DbError> {
+        let rows = sqlx::query("SELECT name, enabled FROM runtime_flags")
+            .fetch_all(self.db_client.pool())
+            .await
+            .map_err(|e| {
+                error!("Failed to load runtime flags: {}", e);
+                DbError::QueryError(e.to_string())
+            })?;
+
+        rows.iter()
+            .map(|row| {
+                Ok((
+                    row.try_get("name")
+                        .map_err(|e| DbError::QueryError(e.to_string()))?,
+                    row.try_get("enabled")
+                        .map_err(|e| DbError::QueryError(e.to_string()))?,
+                ))
+            })
+            .collect()
+    }
+}
+
+impl RuntimeFlagSource for SqlRuntimeFlagRepository {
+    fn name(&self) -> &'static str {
+        "database"
+    }
+
+    fn load(&self) -> BoxFuture<'_, HashMap<String, bool>, ConnectifyError> {
+        Box::pin(async move { Ok(self.load_flags().await?) })
+    }
+}
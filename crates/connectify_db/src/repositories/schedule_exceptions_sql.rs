@@ -0,0 +1,191 @@
+//! SQL implementation of the schedule exception store
+//!
+//! This module provides a SQL implementation of the `ScheduleExceptionStore` trait from
+//! connectify_common, so that one-off working hours changes survive restarts and apply on all
+//! backend instances.
+
+use crate::error::DbError;
+use crate::DbClient;
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use connectify_common::schedule_exceptions::{
+    ScheduleException, ScheduleExceptionKind, ScheduleExceptionStore,
+};
+use connectify_common::services::BoxFuture;
+use connectify_common::ConnectifyError;
+use sqlx::any::AnyRow;
+use sqlx::Row;
+use tracing::{debug, error, info};
+
+/// Format of the stored dates, which sorts like the dates themselves
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Format of the stored times
+const TIME_FORMAT: &str = "%H:%M:%S";
+
+/// SQL implementation of the schedule exception store
+#[derive(Debug, Clone)]
+pub struct SqlScheduleExceptionRepository {
+    /// The database client
+    db_client: DbClient,
+}
+
+impl SqlScheduleExceptionRepository {
+    /// Create a new SQL schedule exception repository
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client
+    ///
+    /// # Returns
+    ///
+    /// A new SQL schedule exception repository
+    pub fn new(db_client: DbClient) -> Self {
+        Self { db_client }
+    }
+
+    /// Initialize the database schema
+    ///
+    /// This function creates the table for storing schedule exceptions if it doesn't exist.
+    pub async fn init_schema(&self) -> Result<(), DbError> {
+        debug!("Initializing schedule exceptions schema");
+
+        let query = r#"
+            CREATE TABLE IF NOT EXISTS schedule_exceptions (
+                id TEXT PRIMARY KEY,
+                date TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                start_time TEXT,
+                end_time TEXT,
+                reason TEXT,
+                created_at BIGINT NOT NULL
+            )
+        "#;
+
+        self.db_client.execute(query).await?;
+
+        info!("Schedule exceptions schema initialized successfully");
+        Ok(())
+    }
+
+    fn from_row(row: &AnyRow) -> Result<ScheduleException, DbError> {
+        let text = |column: &str| -> Result<Option<String>, DbError> {
+            row.try_get(column)
+                .map_err(|e| DbError::QueryError(e.to_string()))
+        };
+        let time = |column: &str| -> Result<Option<NaiveTime>, DbError> {
+            text(column)?
+                .map(|time| {
+                    NaiveTime::parse_from_str(&time, TIME_FORMAT)
+                        .map_err(|_| DbError::Other(format!("Invalid {}: {}", column, time)))
+                })
+                .transpose()
+        };
+        let date: String = row
+            .try_get("date")
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        let kind: String = row
+            .try_get("kind")
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        let created_at: i64 = row
+            .try_get("created_at")
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        Ok(ScheduleException {
+            id: row
+                .try_get("id")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            date: NaiveDate::parse_from_str(&date, DATE_FORMAT)
+                .map_err(|_| DbError::Other(format!("Invalid date: {}", date)))?,
+            kind: kind
+                .parse::<ScheduleExceptionKind>()
+                .map_err(|e| DbError::Other(e.to_string()))?,
+            start_time: time("start_time")?,
+            end_time: time("end_time")?,
+            reason: text("reason")?,
+            created_at: DateTime::from_timestamp(created_at, 0)
+                .ok_or_else(|| DbError::Other(format!("Invalid created_at: {}", created_at)))?,
+        })
+    }
+
+    async fn insert_exception(&self, exception: &ScheduleException) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+                INSERT INTO schedule_exceptions
+                    (id, date, kind, start_time, end_time, reason, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(&exception.id)
+        .bind(exception.date.format(DATE_FORMAT).to_string())
+        .bind(exception.kind.as_str())
+        .bind(
+            exception
+                .start_time
+                .map(|time| time.format(TIME_FORMAT).to_string()),
+        )
+        .bind(
+            exception
+                .end_time
+                .map(|time| time.format(TIME_FORMAT).to_string()),
+        )
+        .bind(&exception.reason)
+        .bind(exception.created_at.timestamp())
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to store schedule exception: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+
+    async fn find_between(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<ScheduleException>, DbError> {
+        let rows = sqlx::query(
+            r#"
+                SELECT id, date, kind, start_time, end_time, reason, created_at
+                FROM schedule_exceptions
+                WHERE date >= $1 AND date <= $2
+                ORDER BY date, start_time
+            "#,
+        )
+        .bind(start.format(DATE_FORMAT).to_string())
+        .bind(end.format(DATE_FORMAT).to_string())
+        .fetch_all(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to load schedule exceptions: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    async fn delete_exception(&self, id: &str) -> Result<bool, DbError> {
+        sqlx::query("DELETE FROM schedule_exceptions WHERE id = $1")
+            .bind(id)
+            .execute(self.db_client.pool())
+            .await
+            .map(|result| result.rows_affected() > 0)
+            .map_err(|e| DbError::QueryError(e.to_string()))
+    }
+}
+
+impl ScheduleExceptionStore for SqlScheduleExceptionRepository {
+    fn create(&self, exception: ScheduleException) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.insert_exception(&exception).await?) })
+    }
+
+    fn between(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> BoxFuture<'_, Vec<ScheduleException>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_between(start, end).await?) })
+    }
+
+    fn delete<'a>(&'a self, id: &'a str) -> BoxFuture<'a, bool, ConnectifyError> {
+        Box::pin(async move { Ok(self.delete_exception(id).await?) })
+    }
+}
@@ -0,0 +1,193 @@
+//! SQL implementation of the slot hold store
+//!
+//! This module provides a SQL implementation of the `SlotHoldStore` trait from
+//! connectify_common, so that slot holds are shared between backend instances and survive
+//! restarts while the customer pays.
+
+use crate::error::DbError;
+use crate::DbClient;
+use chrono::{DateTime, Utc};
+use connectify_common::holds::{SlotHold, SlotHoldStore};
+use connectify_common::services::BoxFuture;
+use connectify_common::ConnectifyError;
+use sqlx::any::AnyRow;
+use sqlx::Row;
+use tracing::{debug, error, info};
+
+/// SQL implementation of the slot hold store
+#[derive(Debug, Clone)]
+pub struct SqlSlotHoldRepository {
+    /// The database client
+    db_client: DbClient,
+}
+
+impl SqlSlotHoldRepository {
+    /// Create a new SQL slot hold repository
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client
+    ///
+    /// # Returns
+    ///
+    /// A new SQL slot hold repository
+    pub fn new(db_client: DbClient) -> Self {
+        Self { db_client }
+    }
+
+    /// Initialize the database schema
+    ///
+    /// This function creates the table for storing slot holds if it doesn't exist.
+    pub async fn init_schema(&self) -> Result<(), DbError> {
+        debug!("Initializing slot holds schema");
+
+        let query = r#"
+            CREATE TABLE IF NOT EXISTS slot_holds (
+                id TEXT PRIMARY KEY,
+                calendar_id TEXT NOT NULL,
+                start_time BIGINT NOT NULL,
+                end_time BIGINT NOT NULL,
+                expires_at BIGINT NOT NULL,
+                reference TEXT
+            )
+        "#;
+
+        self.db_client.execute(query).await?;
+
+        info!("Slot holds schema initialized successfully");
+        Ok(())
+    }
+
+    fn from_row(row: &AnyRow) -> Result<SlotHold, DbError> {
+        let timestamp = |column: &str| -> Result<DateTime<Utc>, DbError> {
+            let seconds: i64 = row
+                .try_get(column)
+                .map_err(|e| DbError::QueryError(e.to_string()))?;
+            DateTime::from_timestamp(seconds, 0)
+                .ok_or_else(|| DbError::Other(format!("Invalid {}: {}", column, seconds)))
+        };
+        Ok(SlotHold {
+            id: row
+                .try_get("id")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            calendar_id: row
+                .try_get("calendar_id")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            start_time: timestamp("start_time")?,
+            end_time: timestamp("end_time")?,
+            expires_at: timestamp("expires_at")?,
+            reference: row.try_get("reference").unwrap_or_default(),
+        })
+    }
+
+    async fn insert_hold(&self, hold: &SlotHold) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+                INSERT INTO slot_holds (id, calendar_id, start_time, end_time, expires_at, reference)
+                VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(&hold.id)
+        .bind(&hold.calendar_id)
+        .bind(hold.start_time.timestamp())
+        .bind(hold.end_time.timestamp())
+        .bind(hold.expires_at.timestamp())
+        .bind(hold.reference.clone())
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to store slot hold: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+
+    async fn find_hold(&self, id: &str) -> Result<Option<SlotHold>, DbError> {
+        let row = sqlx::query(
+            r#"
+                SELECT id, calendar_id, start_time, end_time, expires_at, reference
+                FROM slot_holds
+                WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.db_client.pool())
+        .await
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    async fn find_overlapping(
+        &self,
+        calendar_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<SlotHold>, DbError> {
+        let rows = sqlx::query(
+            r#"
+                SELECT id, calendar_id, start_time, end_time, expires_at, reference
+                FROM slot_holds
+                WHERE calendar_id = $1 AND start_time < $2 AND end_time > $3 AND expires_at > $4
+                ORDER BY start_time
+            "#,
+        )
+        .bind(calendar_id)
+        .bind(end.timestamp())
+        .bind(start.timestamp())
+        .bind(now.timestamp())
+        .fetch_all(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to load slot holds: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        rows.iter().map(Self::from_row).collect()
+    }
+
+    async fn delete_hold(&self, id: &str) -> Result<bool, DbError> {
+        sqlx::query("DELETE FROM slot_holds WHERE id = $1")
+            .bind(id)
+            .execute(self.db_client.pool())
+            .await
+            .map(|result| result.rows_affected() > 0)
+            .map_err(|e| DbError::QueryError(e.to_string()))
+    }
+
+    async fn purge_expired_holds(&self, now: DateTime<Utc>) -> Result<u64, DbError> {
+        sqlx::query("DELETE FROM slot_holds WHERE expires_at <= $1")
+            .bind(now.timestamp())
+            .execute(self.db_client.pool())
+            .await
+            .map(|result| result.rows_affected())
+            .map_err(|e| DbError::QueryError(e.to_string()))
+    }
+}
+
+impl SlotHoldStore for SqlSlotHoldRepository {
+    fn create(&self, hold: SlotHold) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.insert_hold(&hold).await?) })
+    }
+
+    fn get<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Option<SlotHold>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_hold(id).await?) })
+    }
+
+    fn overlapping<'a>(
+        &'a self,
+        calendar_id: &'a str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, Vec<SlotHold>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_overlapping(calendar_id, start, end, now).await?) })
+    }
+
+    fn release<'a>(&'a self, id: &'a str) -> BoxFuture<'a, bool, ConnectifyError> {
+        Box::pin(async move { Ok(self.delete_hold(id).await?) })
+    }
+
+    fn purge_expired(&self, now: DateTime<Utc>) -> BoxFuture<'_, u64, ConnectifyError> {
+        Box::pin(async move { Ok(self.purge_expired_holds(now).await?) })
+    }
+}
@@ -0,0 +1,220 @@
+//! SQL implementation of the SMS consent store
+//!
+//! This module provides a SQL implementation of the `SmsConsentStore` trait from
+//! connectify_common, so that received SMS and opt-outs survive restarts and are shared by all
+//! backend instances.
+
+use crate::error::DbError;
+use crate::DbClient;
+use chrono::{DateTime, Utc};
+use connectify_common::services::BoxFuture;
+use connectify_common::sms_consent::{InboundSmsRecord, SmsConsentRecord, SmsConsentStore};
+use connectify_common::ConnectifyError;
+use sqlx::any::AnyRow;
+use sqlx::Row;
+use tracing::{debug, error, info};
+
+/// Columns selected for a received SMS
+const MESSAGE_COLUMNS: &str = "message_sid, sender, recipient, body, received_at";
+
+/// Columns selected for a consent
+const CONSENT_COLUMNS: &str = "phone, opted_out, keyword, updated_at";
+
+/// SQL implementation of the SMS consent store
+#[derive(Debug, Clone)]
+pub struct SqlSmsConsentRepository {
+    /// The database client
+    db_client: DbClient,
+}
+
+/// Reads a column of Unix seconds as a timestamp.
+fn timestamp(row: &AnyRow, column: &str) -> Result<DateTime<Utc>, DbError> {
+    let seconds: i64 = row
+        .try_get(column)
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+    DateTime::from_timestamp(seconds, 0)
+        .ok_or_else(|| DbError::Other(format!("Invalid {}: {}", column, seconds)))
+}
+
+impl SqlSmsConsentRepository {
+    /// Create a new SQL SMS consent repository
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client
+    ///
+    /// # Returns
+    ///
+    /// A new SQL SMS consent repository
+    pub fn new(db_client: DbClient) -> Self {
+        Self { db_client }
+    }
+
+    /// Initialize the database schema
+    ///
+    /// This function creates the tables for storing received SMS and consents if they don't
+    /// exist.
+    pub async fn init_schema(&self) -> Result<(), DbError> {
+        debug!("Initializing SMS consent schema");
+
+        let messages = r#"
+            CREATE TABLE IF NOT EXISTS sms_inbound_messages (
+                message_sid TEXT PRIMARY KEY,
+                sender TEXT NOT NULL,
+                recipient TEXT NOT NULL,
+                body TEXT NOT NULL,
+                received_at BIGINT NOT NULL
+            )
+        "#;
+        self.db_client.execute(messages).await?;
+
+        let consents = r#"
+            CREATE TABLE IF NOT EXISTS sms_consents (
+                phone TEXT PRIMARY KEY,
+                opted_out BOOLEAN NOT NULL,
+                keyword TEXT NOT NULL,
+                updated_at BIGINT NOT NULL
+            )
+        "#;
+        self.db_client.execute(consents).await?;
+
+        info!("SMS consent schema initialized successfully");
+        Ok(())
+    }
+
+    fn message_from_row(row: &AnyRow) -> Result<InboundSmsRecord, DbError> {
+        Ok(InboundSmsRecord {
+            message_sid: row
+                .try_get("message_sid")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            from: row
+                .try_get("sender")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            to: row
+                .try_get("recipient")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            body: row
+                .try_get("body")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            received_at: timestamp(row, "received_at")?,
+        })
+    }
+
+    fn consent_from_row(row: &AnyRow) -> Result<SmsConsentRecord, DbError> {
+        Ok(SmsConsentRecord {
+            phone: row
+                .try_get("phone")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            opted_out: row
+                .try_get("opted_out")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            keyword: row
+                .try_get("keyword")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            updated_at: timestamp(row, "updated_at")?,
+        })
+    }
+
+    async fn insert_message(&self, record: &InboundSmsRecord) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+                INSERT INTO sms_inbound_messages
+                    (message_sid, sender, recipient, body, received_at)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (message_sid) DO NOTHING
+            "#,
+        )
+        .bind(&record.message_sid)
+        .bind(&record.from)
+        .bind(&record.to)
+        .bind(&record.body)
+        .bind(record.received_at.timestamp())
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to store SMS {}: {}", record.message_sid, e);
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+
+    async fn find_messages(
+        &self,
+        from: &str,
+        limit: usize,
+    ) -> Result<Vec<InboundSmsRecord>, DbError> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM sms_inbound_messages WHERE sender = $1 \
+             ORDER BY received_at DESC LIMIT $2",
+            MESSAGE_COLUMNS
+        ))
+        .bind(from)
+        .bind(limit as i64)
+        .fetch_all(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to load received SMS: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        rows.iter().map(Self::message_from_row).collect()
+    }
+
+    async fn upsert_consent(&self, record: &SmsConsentRecord) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+                INSERT INTO sms_consents (phone, opted_out, keyword, updated_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (phone)
+                DO UPDATE SET opted_out = $2, keyword = $3, updated_at = $4
+            "#,
+        )
+        .bind(&record.phone)
+        .bind(record.opted_out)
+        .bind(&record.keyword)
+        .bind(record.updated_at.timestamp())
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to store SMS consent of {}: {}", record.phone, e);
+            DbError::QueryError(e.to_string())
+        })?;
+        Ok(())
+    }
+
+    async fn find_consent(&self, phone: &str) -> Result<Option<SmsConsentRecord>, DbError> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM sms_consents WHERE phone = $1",
+            CONSENT_COLUMNS
+        ))
+        .bind(phone)
+        .fetch_optional(self.db_client.pool())
+        .await
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+        row.as_ref().map(Self::consent_from_row).transpose()
+    }
+}
+
+impl SmsConsentStore for SqlSmsConsentRepository {
+    fn save_message(&self, record: InboundSmsRecord) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.insert_message(&record).await?) })
+    }
+
+    fn list_messages<'a>(
+        &'a self,
+        from: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<InboundSmsRecord>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_messages(from, limit).await?) })
+    }
+
+    fn save_consent(&self, record: SmsConsentRecord) -> BoxFuture<'_, (), ConnectifyError> {
+        Box::pin(async move { Ok(self.upsert_consent(&record).await?) })
+    }
+
+    fn get_consent<'a>(
+        &'a self,
+        phone: &'a str,
+    ) -> BoxFuture<'a, Option<SmsConsentRecord>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_consent(phone).await?) })
+    }
+}
@@ -0,0 +1,269 @@
+//! SQL implementation of the webhook event store
+//!
+//! This module provides a SQL implementation of the `WebhookEventStore` trait from
+//! connectify_common, so that webhook deliveries are deduplicated across restarts and backend
+//! instances, and failed events can be replayed.
+
+use crate::error::DbError;
+use crate::DbClient;
+use chrono::{DateTime, Utc};
+use connectify_common::services::BoxFuture;
+use connectify_common::webhook_events::{
+    WebhookEventRecord, WebhookEventStatus, WebhookEventStore,
+};
+use connectify_common::ConnectifyError;
+use sqlx::any::AnyRow;
+use sqlx::Row;
+use tracing::{debug, error, info};
+
+/// Columns selected for a webhook event
+const COLUMNS: &str = "provider, event_id, event_type, payload, status, attempts, last_error, \
+                       received_at, updated_at";
+
+/// SQL implementation of the webhook event store
+#[derive(Debug, Clone)]
+pub struct SqlWebhookEventRepository {
+    /// The database client
+    db_client: DbClient,
+}
+
+impl SqlWebhookEventRepository {
+    /// Create a new SQL webhook event repository
+    ///
+    /// # Arguments
+    ///
+    /// * `db_client` - The database client
+    ///
+    /// # Returns
+    ///
+    /// A new SQL webhook event repository
+    pub fn new(db_client: DbClient) -> Self {
+        Self { db_client }
+    }
+
+    /// Initialize the database schema
+    ///
+    /// This function creates the table for storing webhook events if it doesn't exist.
+    pub async fn init_schema(&self) -> Result<(), DbError> {
+        debug!("Initializing webhook events schema");
+
+        let query = r#"
+            CREATE TABLE IF NOT EXISTS webhook_events (
+                provider TEXT NOT NULL,
+                event_id TEXT NOT NULL,
+                event_type TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                status TEXT NOT NULL,
+                attempts BIGINT NOT NULL,
+                last_error TEXT,
+                received_at BIGINT NOT NULL,
+                updated_at BIGINT NOT NULL,
+                PRIMARY KEY (provider, event_id)
+            )
+        "#;
+
+        self.db_client.execute(query).await?;
+
+        info!("Webhook events schema initialized successfully");
+        Ok(())
+    }
+
+    fn from_row(row: &AnyRow) -> Result<WebhookEventRecord, DbError> {
+        let timestamp = |column: &str| -> Result<DateTime<Utc>, DbError> {
+            let seconds: i64 = row
+                .try_get(column)
+                .map_err(|e| DbError::QueryError(e.to_string()))?;
+            DateTime::from_timestamp(seconds, 0)
+                .ok_or_else(|| DbError::Other(format!("Invalid {}: {}", column, seconds)))
+        };
+        let status: String = row
+            .try_get("status")
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        let attempts: i64 = row
+            .try_get("attempts")
+            .map_err(|e| DbError::QueryError(e.to_string()))?;
+        Ok(WebhookEventRecord {
+            provider: row
+                .try_get("provider")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            event_id: row
+                .try_get("event_id")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            event_type: row
+                .try_get("event_type")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            payload: row
+                .try_get("payload")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            status: status
+                .parse::<WebhookEventStatus>()
+                .map_err(|e| DbError::Other(e.to_string()))?,
+            attempts: attempts as u32,
+            last_error: row
+                .try_get("last_error")
+                .map_err(|e| DbError::QueryError(e.to_string()))?,
+            received_at: timestamp("received_at")?,
+            updated_at: timestamp("updated_at")?,
+        })
+    }
+
+    async fn insert_event(
+        &self,
+        event: &WebhookEventRecord,
+    ) -> Result<Option<WebhookEventRecord>, DbError> {
+        // The primary key makes the insert the atomic deduplication
+        let inserted = sqlx::query(
+            r#"
+                INSERT INTO webhook_events
+                    (provider, event_id, event_type, payload, status, attempts, last_error,
+                     received_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ON CONFLICT (provider, event_id) DO NOTHING
+            "#,
+        )
+        .bind(&event.provider)
+        .bind(&event.event_id)
+        .bind(&event.event_type)
+        .bind(&event.payload)
+        .bind(event.status.as_str())
+        .bind(event.attempts as i64)
+        .bind(&event.last_error)
+        .bind(event.received_at.timestamp())
+        .bind(event.updated_at.timestamp())
+        .execute(self.db_client.pool())
+        .await
+        .map_err(|e| {
+            error!("Failed to store webhook event: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        if inserted.rows_affected() > 0 {
+            return Ok(None);
+        }
+        self.find_event(&event.provider, &event.event_id).await
+    }
+
+    async fn find_event(
+        &self,
+        provider: &str,
+        event_id: &str,
+    ) -> Result<Option<WebhookEventRecord>, DbError> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM webhook_events WHERE provider = $1 AND event_id = $2",
+            COLUMNS
+        ))
+        .bind(provider)
+        .bind(event_id)
+        .fetch_optional(self.db_client.pool())
+        .await
+        .map_err(|e| DbError::QueryError(e.to_string()))?;
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    async fn update_status(
+        &self,
+        provider: &str,
+        event_id: &str,
+        status: WebhookEventStatus,
+        error: Option<String>,
+        now: DateTime<Utc>,
+    ) -> Result<bool, DbError> {
+        sqlx::query(
+            r#"
+                UPDATE webhook_events
+                SET status = $1, attempts = attempts + 1, last_error = $2, updated_at = $3
+                WHERE provider = $4 AND event_id = $5
+            "#,
+        )
+        .bind(status.as_str())
+        .bind(error)
+        .bind(now.timestamp())
+        .bind(provider)
+        .bind(event_id)
+        .execute(self.db_client.pool())
+        .await
+        .map(|result| result.rows_affected() > 0)
+        .map_err(|e| {
+            error!("Failed to update webhook event {}: {}", event_id, e);
+            DbError::QueryError(e.to_string())
+        })
+    }
+
+    async fn find_events(
+        &self,
+        provider: &str,
+        status: Option<WebhookEventStatus>,
+        limit: usize,
+    ) -> Result<Vec<WebhookEventRecord>, DbError> {
+        let rows = match status {
+            Some(status) => {
+                sqlx::query(&format!(
+                    "SELECT {} FROM webhook_events WHERE provider = $1 AND status = $2 \
+                     ORDER BY received_at DESC LIMIT $3",
+                    COLUMNS
+                ))
+                .bind(provider)
+                .bind(status.as_str())
+                .bind(limit as i64)
+                .fetch_all(self.db_client.pool())
+                .await
+            }
+            None => {
+                sqlx::query(&format!(
+                    "SELECT {} FROM webhook_events WHERE provider = $1 \
+                     ORDER BY received_at DESC LIMIT $2",
+                    COLUMNS
+                ))
+                .bind(provider)
+                .bind(limit as i64)
+                .fetch_all(self.db_client.pool())
+                .await
+            }
+        }
+        .map_err(|e| {
+            error!("Failed to load webhook events: {}", e);
+            DbError::QueryError(e.to_string())
+        })?;
+        rows.iter().map(Self::from_row).collect()
+    }
+}
+
+impl WebhookEventStore for SqlWebhookEventRepository {
+    fn record(
+        &self,
+        event: WebhookEventRecord,
+    ) -> BoxFuture<'_, Option<WebhookEventRecord>, ConnectifyError> {
+        Box::pin(async move { Ok(self.insert_event(&event).await?) })
+    }
+
+    fn get<'a>(
+        &'a self,
+        provider: &'a str,
+        event_id: &'a str,
+    ) -> BoxFuture<'a, Option<WebhookEventRecord>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_event(provider, event_id).await?) })
+    }
+
+    fn finish<'a>(
+        &'a self,
+        provider: &'a str,
+        event_id: &'a str,
+        status: WebhookEventStatus,
+        error: Option<String>,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, bool, ConnectifyError> {
+        Box::pin(async move {
+            Ok(self
+                .update_status(provider, event_id, status, error, now)
+                .await?)
+        })
+    }
+
+    fn list<'a>(
+        &'a self,
+        provider: &'a str,
+        status: Option<WebhookEventStatus>,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<WebhookEventRecord>, ConnectifyError> {
+        Box::pin(async move { Ok(self.find_events(provider, status, limit).await?) })
+    }
+}
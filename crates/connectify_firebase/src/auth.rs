@@ -4,15 +4,20 @@
 //! using a service account key file. It generates OAuth2 tokens that can be used
 //! to authenticate API requests to the Firebase Cloud Messaging API.
 
+use connectify_common::cache::{self, cache};
 use connectify_config::FirebaseConfig;
-use std::{error::Error, path::Path};
+use std::{error::Error, path::Path, time::Duration};
 use yup_oauth2::{read_service_account_key, ServiceAccountAuthenticator};
 
+/// How long access tokens are cached. Google issues them for an hour.
+const TOKEN_CACHE_TTL: Duration = Duration::from_secs(50 * 60);
+
 /// Obtains an OAuth2 access token for Firebase Cloud Messaging
 ///
 /// This function reads a service account key file from the path specified in the
 /// FirebaseConfig and uses it to authenticate with Google's OAuth2 service.
 /// It requests a token with the appropriate scope for Firebase Cloud Messaging.
+/// Tokens are cached until shortly before they expire.
 ///
 /// # Arguments
 ///
@@ -38,6 +43,11 @@ pub async fn get_firebase_auth_token(
         .as_deref()
         .ok_or("Missing key_path in FirebaseConfig")?;
 
+    let cache_key = format!("firebase:auth_token:{}", key_path);
+    if let Some(token) = cache::get_json::<String>(&*cache(), &cache_key).await {
+        return Ok(token);
+    }
+
     let sa_key = read_service_account_key(Path::new(key_path)).await?;
 
     // FCM requires the "https://www.googleapis.com/auth/firebase.messaging" scope
@@ -54,5 +64,6 @@ pub async fn get_firebase_auth_token(
         }
     };
 
+    cache::set_json(&*cache(), &cache_key, &fcm_result_token, TOKEN_CACHE_TTL).await;
     Ok(fcm_result_token.to_string())
 }
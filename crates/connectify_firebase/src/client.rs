@@ -14,13 +14,26 @@ use crate::models::DeviceRegistration;
 use crate::repository::DeviceRegistrationRepository;
 #[cfg(not(feature = "database"))]
 use crate::repository::DeviceRegistrationRepository;
-use connectify_config::{AppConfig, FirebaseConfig};
+#[cfg(feature = "database")]
+use connectify_common::events::NotificationFailed;
+use connectify_common::http::circuit_breaker::HttpClientError;
+use connectify_common::metrics::observe_external_call;
+use connectify_common::models::{CursorQuery, Page};
+use connectify_common::retry::{send_with_retry, RetryPolicy};
+use connectify_common::{external_service_error, ConnectifyError};
+#[cfg(feature = "database")]
+use connectify_config::AppConfig;
+use connectify_config::FirebaseConfig;
 #[cfg(feature = "database")]
 use connectify_db::error::DbError;
 use reqwest::{header, Client};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 use thiserror::Error;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+#[cfg(feature = "database")]
 use tracing::{debug, error, info, warn};
 
 /// Errors that can occur when interacting with the Firebase Cloud Messaging API
@@ -42,12 +55,44 @@ pub enum FirebaseError {
     #[error("Firebase API error: {0}")]
     ApiError(String),
 
+    /// The request is invalid, e.g. a malformed topic name
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
     /// Error with the database
     #[cfg(feature = "database")]
     #[error("Database error: {0}")]
     DbError(#[from] DbError),
 }
 
+impl From<HttpClientError> for FirebaseError {
+    fn from(err: HttpClientError) -> Self {
+        match err {
+            HttpClientError::Request(e) => FirebaseError::RequestError(e),
+            circuit_open => FirebaseError::ApiError(circuit_open.to_string()),
+        }
+    }
+}
+
+/// Convert FirebaseError to ConnectifyError
+impl From<FirebaseError> for ConnectifyError {
+    fn from(err: FirebaseError) -> Self {
+        match err {
+            FirebaseError::AuthError(msg) => {
+                external_service_error("Firebase auth", format!("Authentication error: {}", msg))
+            }
+            FirebaseError::RequestError(e) => {
+                ConnectifyError::HttpError(format!("Firebase request error: {}", e))
+            }
+            FirebaseError::ConfigError(msg) => ConnectifyError::ConfigError(msg),
+            FirebaseError::ApiError(msg) => external_service_error("Firebase API", msg),
+            FirebaseError::InvalidRequest(msg) => ConnectifyError::ValidationError(msg),
+            #[cfg(feature = "database")]
+            FirebaseError::DbError(e) => ConnectifyError::DatabaseError(e.to_string()),
+        }
+    }
+}
+
 /// A message to be sent via Firebase Cloud Messaging
 ///
 /// This is the top-level structure that wraps a Message object
@@ -91,6 +136,205 @@ pub struct Message {
     ///
     /// This data will be available to the client app that receives the message.
     pub data: Option<std::collections::HashMap<String, String>>,
+
+    /// iOS-specific options of the message, applied by APNs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apns: Option<ApnsConfig>,
+
+    /// Android-specific options of the message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub android: Option<AndroidConfig>,
+}
+
+/// Delivery priority of a notification on iOS
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ApnsPriority {
+    /// Delivered immediately (APNs priority 10)
+    High,
+    /// Delivered considering the device's power, e.g. batched (APNs priority 5)
+    Normal,
+}
+
+/// iOS-specific options of a notification
+///
+/// These options express iOS-only behaviors, like the sound played or the badge shown
+/// on the app icon. They are sent as the `apns` section of the FCM message.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ApnsOptions {
+    /// The sound played, `default` or the name of a sound file of the app
+    pub sound: Option<String>,
+
+    /// The number shown on the app icon, 0 to remove it
+    pub badge: Option<u32>,
+
+    /// Whether a notification service extension of the app may modify the notification,
+    /// e.g. to download an image
+    pub mutable_content: Option<bool>,
+
+    /// The notification category, selecting the actions the app registered for it
+    pub category: Option<String>,
+
+    /// The delivery priority (default: high)
+    pub priority: Option<ApnsPriority>,
+}
+
+/// The `apns` section of an FCM message
+///
+/// This is the APNs request FCM sends, with its headers and the `aps` dictionary
+/// of its payload, see <https://firebase.google.com/docs/reference/fcm/rest/v1/projects.messages#apnsconfig>.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ApnsConfig {
+    /// APNs request headers, e.g. `apns-priority`
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+
+    /// The APNs payload
+    pub payload: ApnsPayload,
+}
+
+/// The payload of an APNs request
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ApnsPayload {
+    /// The dictionary of Apple-defined keys
+    pub aps: Aps,
+}
+
+/// The Apple-defined keys of an APNs payload; FCM adds the alert of the notification
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Aps {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sound: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub badge: Option<u32>,
+
+    /// 1 if a notification service extension may modify the notification
+    #[serde(rename = "mutable-content", skip_serializing_if = "Option::is_none")]
+    pub mutable_content: Option<u8>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+impl From<&ApnsOptions> for ApnsConfig {
+    fn from(options: &ApnsOptions) -> Self {
+        let mut headers = HashMap::new();
+        if let Some(priority) = options.priority {
+            let value = match priority {
+                ApnsPriority::High => "10",
+                ApnsPriority::Normal => "5",
+            };
+            headers.insert("apns-priority".to_string(), value.to_string());
+        }
+        ApnsConfig {
+            headers,
+            payload: ApnsPayload {
+                aps: Aps {
+                    sound: options.sound.clone(),
+                    badge: options.badge,
+                    mutable_content: options.mutable_content.filter(|m| *m).map(|_| 1),
+                    category: options.category.clone(),
+                },
+            },
+        }
+    }
+}
+
+/// Delivery priority of a message on Android
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum AndroidPriority {
+    /// Delivered immediately, waking a sleeping device
+    High,
+    /// Delivered when the device is awake, e.g. batched in Doze mode
+    Normal,
+}
+
+/// Android-specific options of a notification
+///
+/// These options select the notification channel and control how FCM stores and
+/// dedupes undelivered messages. They are sent as the `android` section of the FCM message.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AndroidOptions {
+    /// The notification channel of the app the notification is shown in
+    pub channel_id: Option<String>,
+
+    /// The delivery priority (default: normal)
+    pub priority: Option<AndroidPriority>,
+
+    /// Messages with the same key replace each other while undelivered,
+    /// so only the latest one is shown
+    pub collapse_key: Option<String>,
+
+    /// How long FCM keeps the message while the device is offline, in seconds
+    pub ttl: Option<u64>,
+
+    /// The activity intent filter action started when the user taps the notification
+    pub click_action: Option<String>,
+}
+
+/// The `android` section of an FCM message
+///
+/// See <https://firebase.google.com/docs/reference/fcm/rest/v1/projects.messages#androidconfig>.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AndroidConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collapse_key: Option<String>,
+
+    /// `HIGH` or `NORMAL`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+
+    /// A duration in seconds with the suffix `s`, e.g. `3600s`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification: Option<AndroidNotification>,
+}
+
+/// The Android-specific options of the displayed notification
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AndroidNotification {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub click_action: Option<String>,
+}
+
+impl From<&AndroidOptions> for AndroidConfig {
+    fn from(options: &AndroidOptions) -> Self {
+        let notification =
+            (options.channel_id.is_some() || options.click_action.is_some()).then(|| {
+                AndroidNotification {
+                    channel_id: options.channel_id.clone(),
+                    click_action: options.click_action.clone(),
+                }
+            });
+        AndroidConfig {
+            collapse_key: options.collapse_key.clone(),
+            priority: options.priority.map(|priority| {
+                match priority {
+                    AndroidPriority::High => "HIGH",
+                    AndroidPriority::Normal => "NORMAL",
+                }
+                .to_string()
+            }),
+            ttl: options.ttl.map(|seconds| format!("{}s", seconds)),
+            notification,
+        }
+    }
 }
 
 /// The notification to be displayed on the user's device
@@ -119,6 +363,190 @@ pub struct FcmResponse {
     pub name: String,
 }
 
+/// Endpoint of the Instance ID API subscribing tokens to a topic.
+const IID_BATCH_ADD_URL: &str = "https://iid.googleapis.com/iid/v1:batchAdd";
+
+/// Endpoint of the Instance ID API unsubscribing tokens from a topic.
+const IID_BATCH_REMOVE_URL: &str = "https://iid.googleapis.com/iid/v1:batchRemove";
+
+/// Most registration tokens the Instance ID API accepts per request.
+pub const MAX_TOPIC_TOKENS_PER_REQUEST: usize = 1000;
+
+/// Whether tokens are subscribed to or unsubscribed from a topic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopicOperation {
+    Subscribe,
+    Unsubscribe,
+}
+
+impl TopicOperation {
+    /// The Instance ID API endpoint of the operation
+    fn url(self) -> &'static str {
+        match self {
+            TopicOperation::Subscribe => IID_BATCH_ADD_URL,
+            TopicOperation::Unsubscribe => IID_BATCH_REMOVE_URL,
+        }
+    }
+}
+
+/// Checks that a topic name is one FCM accepts: letters, digits and `-_.~%`, without the
+/// `/topics/` prefix.
+pub fn validate_topic(topic: &str) -> Result<(), FirebaseError> {
+    let valid = !topic.is_empty()
+        && topic
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.~%".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err(FirebaseError::InvalidRequest(format!(
+            "Invalid topic name '{}': use letters, digits and -_.~% only",
+            topic
+        )))
+    }
+}
+
+/// Request body of the Instance ID batch API
+#[derive(Debug, Serialize)]
+struct IidBatchRequest<'a> {
+    /// The topic, as `/topics/{topic}`
+    to: String,
+    registration_tokens: &'a [String],
+}
+
+/// Response of the Instance ID batch API, one result per token in the order sent
+#[derive(Debug, Deserialize)]
+struct IidBatchResponse {
+    #[serde(default)]
+    results: Vec<IidBatchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IidBatchResult {
+    error: Option<String>,
+}
+
+/// A token that could not be subscribed to or unsubscribed from a topic
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TopicTokenError {
+    /// The registration token
+    pub token: String,
+
+    /// The error reported for the token, e.g. `NOT_FOUND` or `INVALID_ARGUMENT`
+    pub error: String,
+}
+
+/// Outcome of subscribing tokens to or unsubscribing them from a topic
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TopicManagementResult {
+    /// The number of tokens the operation succeeded for
+    pub success_count: usize,
+
+    /// The number of tokens the operation failed for
+    pub failure_count: usize,
+
+    /// The tokens the operation failed for, with their errors
+    pub errors: Vec<TopicTokenError>,
+}
+
+impl TopicManagementResult {
+    /// Adds the results of a batch of tokens, matched to the tokens by position
+    fn add_batch(&mut self, tokens: &[String], results: Vec<IidBatchResult>) {
+        for (index, token) in tokens.iter().enumerate() {
+            let error = match results.get(index) {
+                Some(result) => result.error.clone(),
+                None => Some("MISSING_RESULT".to_string()),
+            };
+            match error {
+                Some(error) => {
+                    self.failure_count += 1;
+                    self.errors.push(TopicTokenError {
+                        token: token.clone(),
+                        error,
+                    });
+                }
+                None => self.success_count += 1,
+            }
+        }
+    }
+}
+
+/// Most registration tokens a multicast is sent to, as in the Firebase Admin SDKs.
+pub const MAX_MULTICAST_TOKENS: usize = 500;
+
+/// Messages of a multicast sent at once.
+pub const MULTICAST_CONCURRENCY: usize = 10;
+
+/// Outcome of sending a multicast to one token
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MulticastSendResult {
+    /// The registration token
+    pub token: String,
+
+    /// The ID of the message if it was sent successfully
+    pub message_id: Option<String>,
+
+    /// Error message if the message failed to send
+    pub error: Option<String>,
+}
+
+/// Outcome of sending a multicast
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MulticastResult {
+    /// The number of tokens the message was sent to
+    pub success_count: usize,
+
+    /// The number of tokens the message failed to send to
+    pub failure_count: usize,
+
+    /// The outcome per token, in the order of the tokens
+    pub responses: Vec<MulticastSendResult>,
+}
+
+impl MulticastResult {
+    /// Collects the outcomes of the sends to the tokens, in the order of the tokens
+    fn collect(
+        tokens: &[String],
+        mut results: Vec<Option<Result<String, String>>>,
+    ) -> MulticastResult {
+        let responses: Vec<MulticastSendResult> = tokens
+            .iter()
+            .enumerate()
+            .map(|(index, token)| {
+                let result = results
+                    .get_mut(index)
+                    .and_then(Option::take)
+                    .unwrap_or_else(|| Err("The message was not sent".to_string()));
+                match result {
+                    Ok(message_id) => MulticastSendResult {
+                        token: token.clone(),
+                        message_id: Some(message_id),
+                        error: None,
+                    },
+                    Err(error) => MulticastSendResult {
+                        token: token.clone(),
+                        message_id: None,
+                        error: Some(error),
+                    },
+                }
+            })
+            .collect();
+        let success_count = responses
+            .iter()
+            .filter(|response| response.error.is_none())
+            .count();
+        MulticastResult {
+            success_count,
+            failure_count: responses.len() - success_count,
+            responses,
+        }
+    }
+}
+
 /// Client for interacting with the Firebase Cloud Messaging API
 ///
 /// This struct handles authentication and communication with the Firebase Cloud Messaging
@@ -293,6 +721,55 @@ impl FirebaseClient {
         ))
     }
 
+    /// List a page of registered devices
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Only list the devices of this user, if set
+    /// * `query` - The requested page
+    ///
+    /// # Returns
+    ///
+    /// A page of device registrations, ordered by registration
+    ///
+    /// # Errors
+    ///
+    /// This method will return an error if:
+    ///
+    /// * The repository is not set
+    /// * The cursor is malformed
+    /// * The database operation fails
+    #[cfg(feature = "database")]
+    pub async fn list_devices(
+        &self,
+        user_id: Option<&str>,
+        query: &CursorQuery,
+    ) -> Result<Page<DeviceRegistration>, FirebaseError> {
+        let repository = self.repository.as_ref().ok_or_else(|| {
+            FirebaseError::ConfigError("Device registration repository not set".to_string())
+        })?;
+
+        repository.list_page(user_id, query).await
+    }
+
+    /// List a page of registered devices
+    ///
+    /// This method is a stub when the database feature is not enabled.
+    ///
+    /// # Returns
+    ///
+    /// An error indicating that the database feature is not enabled
+    #[cfg(not(feature = "database"))]
+    pub async fn list_devices(
+        &self,
+        _user_id: Option<&str>,
+        _query: &CursorQuery,
+    ) -> Result<Page<crate::models::DeviceRegistration>, FirebaseError> {
+        Err(FirebaseError::ConfigError(
+            "Database feature is not enabled".to_string(),
+        ))
+    }
+
     /// Send a notification to all devices registered for a user
     ///
     /// This method sends a notification to all devices registered for the given user ID.
@@ -302,6 +779,8 @@ impl FirebaseClient {
     /// * `user_id` - The user ID to send notifications to
     /// * `notification` - The notification to send
     /// * `data` - Optional custom data to include with the notification
+    /// * `apns` - Optional iOS-specific options of the notification
+    /// * `android` - Optional Android-specific options of the notification
     ///
     /// # Returns
     ///
@@ -321,6 +800,8 @@ impl FirebaseClient {
         user_id: &str,
         notification: Notification,
         data: Option<std::collections::HashMap<String, String>>,
+        apns: Option<ApnsOptions>,
+        android: Option<AndroidOptions>,
     ) -> Result<Vec<String>, FirebaseError> {
         let repository = self.repository.as_ref().ok_or_else(|| {
             FirebaseError::ConfigError("Device registration repository not set".to_string())
@@ -349,6 +830,8 @@ impl FirebaseClient {
                     topic: None,
                     notification: Some(notification.clone()),
                     data: data.clone(),
+                    apns: apns.as_ref().map(ApnsConfig::from),
+                    android: android.as_ref().map(AndroidConfig::from),
                 },
             };
 
@@ -358,6 +841,11 @@ impl FirebaseClient {
                 }
                 Err(e) => {
                     error!("Failed to send notification to device: {}", e);
+                    connectify_common::events::publish(NotificationFailed {
+                        channel: "firebase".to_string(),
+                        recipient: format!("{}/{}", user_id, registration.device_id),
+                        error: e.to_string(),
+                    });
                     // Continue sending to other devices even if one fails
                 }
             }
@@ -382,6 +870,8 @@ impl FirebaseClient {
     /// * `user_id` - The user ID to send notifications to
     /// * `notification` - The notification to send
     /// * `data` - Optional custom data to include with the notification
+    /// * `apns` - Optional iOS-specific options of the notification
+    /// * `android` - Optional Android-specific options of the notification
     ///
     /// # Returns
     ///
@@ -392,6 +882,8 @@ impl FirebaseClient {
         _user_id: &str,
         _notification: Notification,
         _data: Option<std::collections::HashMap<String, String>>,
+        _apns: Option<ApnsOptions>,
+        _android: Option<AndroidOptions>,
     ) -> Result<Vec<String>, FirebaseError> {
         Err(FirebaseError::ConfigError(
             "Database feature is not enabled".to_string(),
@@ -447,6 +939,8 @@ impl FirebaseClient {
     ///                 body: "World".to_string(),
     ///             }),
     ///             data: Some(data),
+    ///             apns: None,
+    ///             android: None,
     ///         },
     ///     };
     ///     
@@ -470,13 +964,18 @@ impl FirebaseClient {
             .await
             .map_err(|e| FirebaseError::AuthError(e.to_string()))?;
 
-        let response = self
-            .client
-            .post(&url)
-            .header(header::AUTHORIZATION, format!("Bearer {}", token))
-            .json(&message)
-            .send()
-            .await?;
+        let response = observe_external_call(
+            "firebase",
+            "send_message",
+            send_with_retry(
+                &RetryPolicy::default(),
+                self.client
+                    .post(&url)
+                    .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                    .json(&message),
+            ),
+        )
+        .await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
@@ -486,4 +985,345 @@ impl FirebaseClient {
         let fcm_response: FcmResponse = response.json().await?;
         Ok(fcm_response.name)
     }
+
+    /// Sends a notification to a list of registration tokens
+    ///
+    /// FCM's HTTP v1 API sends a message to one token, so a message is sent per token, up to
+    /// [`MULTICAST_CONCURRENCY`] at once. A failed send doesn't stop the others.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - The registration tokens, at most [`MAX_MULTICAST_TOKENS`]
+    /// * `notification` - The notification to send
+    /// * `data` - Optional custom data to include with the notification
+    /// * `apns` - Optional iOS-specific options of the notification
+    /// * `android` - Optional Android-specific options of the notification
+    ///
+    /// # Returns
+    ///
+    /// The number of tokens the message was sent and failed to send to, with the outcome of
+    /// each token.
+    ///
+    /// # Errors
+    ///
+    /// This method will return an error if:
+    /// * No or more than [`MAX_MULTICAST_TOKENS`] tokens are given
+    /// * The project_id is missing from the FirebaseConfig
+    /// * Authentication fails
+    pub async fn send_multicast(
+        &self,
+        tokens: &[String],
+        notification: Notification,
+        data: Option<HashMap<String, String>>,
+        apns: Option<ApnsOptions>,
+        android: Option<AndroidOptions>,
+    ) -> Result<MulticastResult, FirebaseError> {
+        if tokens.is_empty() || tokens.len() > MAX_MULTICAST_TOKENS {
+            return Err(FirebaseError::InvalidRequest(format!(
+                "A multicast is sent to 1 to {} tokens, got {}",
+                MAX_MULTICAST_TOKENS,
+                tokens.len()
+            )));
+        }
+        if self.config.project_id.is_none() {
+            return Err(FirebaseError::ConfigError(
+                "Missing project_id in FirebaseConfig".to_string(),
+            ));
+        }
+        // Fails fast on bad credentials; the sends reuse the cached token
+        get_firebase_auth_token(&self.config)
+            .await
+            .map_err(|e| FirebaseError::AuthError(e.to_string()))?;
+
+        let apns = apns.as_ref().map(ApnsConfig::from);
+        let android = android.as_ref().map(AndroidConfig::from);
+        let permits = Arc::new(Semaphore::new(MULTICAST_CONCURRENCY));
+        let mut sends = JoinSet::new();
+        for (index, token) in tokens.iter().enumerate() {
+            let client = self.clone();
+            let permits = permits.clone();
+            let message = FcmMessage {
+                message: Message {
+                    token: Some(token.clone()),
+                    topic: None,
+                    notification: Some(notification.clone()),
+                    data: data.clone(),
+                    apns: apns.clone(),
+                    android: android.clone(),
+                },
+            };
+            sends.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                let result = client.send_message(message).await;
+                (index, result.map_err(|e| e.to_string()))
+            });
+        }
+
+        let mut results: Vec<Option<Result<String, String>>> = vec![None; tokens.len()];
+        while let Some(joined) = sends.join_next().await {
+            if let Ok((index, result)) = joined {
+                results[index] = Some(result);
+            }
+        }
+        Ok(MulticastResult::collect(tokens, results))
+    }
+
+    /// Subscribes registration tokens to a topic, so they receive its messages
+    ///
+    /// See [`FirebaseClient::manage_topic`].
+    pub async fn subscribe_to_topic(
+        &self,
+        topic: &str,
+        tokens: &[String],
+    ) -> Result<TopicManagementResult, FirebaseError> {
+        self.manage_topic(TopicOperation::Subscribe, topic, tokens)
+            .await
+    }
+
+    /// Unsubscribes registration tokens from a topic
+    ///
+    /// See [`FirebaseClient::manage_topic`].
+    pub async fn unsubscribe_from_topic(
+        &self,
+        topic: &str,
+        tokens: &[String],
+    ) -> Result<TopicManagementResult, FirebaseError> {
+        self.manage_topic(TopicOperation::Unsubscribe, topic, tokens)
+            .await
+    }
+
+    /// Subscribes tokens to or unsubscribes them from a topic through the Instance ID batch API
+    ///
+    /// The tokens are sent in batches of up to 1000, the most the API accepts per request.
+    ///
+    /// # Returns
+    ///
+    /// The number of tokens the operation succeeded and failed for, with the error of each
+    /// failed token, e.g. `NOT_FOUND` for a token that is no longer valid.
+    ///
+    /// # Errors
+    ///
+    /// This method will return an error if:
+    /// * The topic name is invalid
+    /// * Authentication fails
+    /// * The HTTP request fails
+    /// * The Instance ID API returns an error response
+    pub async fn manage_topic(
+        &self,
+        operation: TopicOperation,
+        topic: &str,
+        tokens: &[String],
+    ) -> Result<TopicManagementResult, FirebaseError> {
+        validate_topic(topic)?;
+        let token = get_firebase_auth_token(&self.config)
+            .await
+            .map_err(|e| FirebaseError::AuthError(e.to_string()))?;
+
+        let mut result = TopicManagementResult::default();
+        for batch in tokens.chunks(MAX_TOPIC_TOKENS_PER_REQUEST) {
+            let response = observe_external_call(
+                "firebase",
+                "manage_topic",
+                send_with_retry(
+                    &RetryPolicy::default(),
+                    self.client
+                        .post(operation.url())
+                        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                        .header("access_token_auth", "true")
+                        .json(&IidBatchRequest {
+                            to: format!("/topics/{}", topic),
+                            registration_tokens: batch,
+                        }),
+                ),
+            )
+            .await?;
+
+            if !response.status().is_success() {
+                let error_text = response.text().await?;
+                return Err(FirebaseError::ApiError(error_text));
+            }
+
+            let batch_response: IidBatchResponse = response.json().await?;
+            result.add_batch(batch, batch_response.results);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apns_config() {
+        let options = ApnsOptions {
+            sound: Some("default".to_string()),
+            badge: Some(3),
+            mutable_content: Some(true),
+            category: Some("BOOKING_REMINDER".to_string()),
+            priority: Some(ApnsPriority::Normal),
+        };
+        assert_eq!(
+            serde_json::to_value(ApnsConfig::from(&options)).unwrap(),
+            serde_json::json!({
+                "headers": {"apns-priority": "5"},
+                "payload": {"aps": {
+                    "sound": "default",
+                    "badge": 3,
+                    "mutable-content": 1,
+                    "category": "BOOKING_REMINDER"
+                }}
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(ApnsConfig::from(&ApnsOptions {
+                mutable_content: Some(false),
+                ..Default::default()
+            }))
+            .unwrap(),
+            serde_json::json!({"payload": {"aps": {}}})
+        );
+    }
+
+    #[test]
+    fn test_android_config() {
+        let options = AndroidOptions {
+            channel_id: Some("bookings".to_string()),
+            priority: Some(AndroidPriority::High),
+            collapse_key: Some("booking_123".to_string()),
+            ttl: Some(3600),
+            click_action: Some("OPEN_BOOKING".to_string()),
+        };
+        assert_eq!(
+            serde_json::to_value(AndroidConfig::from(&options)).unwrap(),
+            serde_json::json!({
+                "collapse_key": "booking_123",
+                "priority": "HIGH",
+                "ttl": "3600s",
+                "notification": {
+                    "channel_id": "bookings",
+                    "click_action": "OPEN_BOOKING"
+                }
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(AndroidConfig::from(&AndroidOptions {
+                ttl: Some(0),
+                ..Default::default()
+            }))
+            .unwrap(),
+            serde_json::json!({"ttl": "0s"})
+        );
+    }
+
+    #[test]
+    fn test_multicast_result() {
+        let tokens = vec![
+            "token-1".to_string(),
+            "token-2".to_string(),
+            "token-3".to_string(),
+        ];
+        let result = MulticastResult::collect(
+            &tokens,
+            vec![
+                Some(Ok("projects/p/messages/1".to_string())),
+                Some(Err("Firebase API error: UNREGISTERED".to_string())),
+                None,
+            ],
+        );
+        assert_eq!(result.success_count, 1);
+        assert_eq!(result.failure_count, 2);
+        assert_eq!(
+            result.responses[0].message_id.as_deref(),
+            Some("projects/p/messages/1")
+        );
+        assert_eq!(result.responses[1].token, "token-2");
+        assert_eq!(
+            result.responses[1].error.as_deref(),
+            Some("Firebase API error: UNREGISTERED")
+        );
+        assert!(result.responses[2].error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_send_multicast_token_count() {
+        let client = FirebaseClient::new(FirebaseConfig {
+            project_id: Some("my-project".to_string()),
+            key_path: None,
+            server_key: None,
+        });
+        let notification = Notification {
+            title: "Hello".to_string(),
+            body: "World".to_string(),
+        };
+        assert!(matches!(
+            client
+                .send_multicast(&[], notification.clone(), None, None, None)
+                .await,
+            Err(FirebaseError::InvalidRequest(_))
+        ));
+        let tokens = vec!["token".to_string(); MAX_MULTICAST_TOKENS + 1];
+        assert!(matches!(
+            client
+                .send_multicast(&tokens, notification, None, None, None)
+                .await,
+            Err(FirebaseError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_topic() {
+        assert!(validate_topic("app_general-alerts.v2~%20").is_ok());
+        assert!(matches!(
+            validate_topic(""),
+            Err(FirebaseError::InvalidRequest(_))
+        ));
+        assert!(validate_topic("/topics/news").is_err());
+        assert!(validate_topic("breaking news").is_err());
+    }
+
+    #[test]
+    fn test_iid_batch_request() {
+        let tokens = vec!["token-1".to_string(), "token-2".to_string()];
+        let request = IidBatchRequest {
+            to: "/topics/news".to_string(),
+            registration_tokens: &tokens,
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "to": "/topics/news",
+                "registration_tokens": ["token-1", "token-2"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_topic_management_result() {
+        let tokens = vec![
+            "token-1".to_string(),
+            "token-2".to_string(),
+            "token-3".to_string(),
+        ];
+        let response: IidBatchResponse =
+            serde_json::from_str(r#"{"results": [{}, {"error": "NOT_FOUND"}]}"#).unwrap();
+
+        let mut result = TopicManagementResult::default();
+        result.add_batch(&tokens, response.results);
+        assert_eq!(result.success_count, 1);
+        assert_eq!(result.failure_count, 2);
+        assert_eq!(
+            result.errors,
+            vec![
+                TopicTokenError {
+                    token: "token-2".to_string(),
+                    error: "NOT_FOUND".to_string(),
+                },
+                TopicTokenError {
+                    token: "token-3".to_string(),
+                    error: "MISSING_RESULT".to_string(),
+                },
+            ]
+        );
+    }
 }
@@ -1,11 +1,17 @@
 #![allow(dead_code)]
 // #![cfg(feature = "openapi")] // not needed as we do this in lib.rs already!
+use connectify_common::models::{CursorQuery, Page, SortOrder};
 use utoipa::OpenApi;
 
-use crate::client::{FcmMessage, Message, Notification};
+use crate::client::{
+    AndroidConfig, AndroidNotification, AndroidOptions, AndroidPriority, ApnsConfig, ApnsOptions,
+    ApnsPayload, ApnsPriority, Aps, FcmMessage, Message, MulticastSendResult, Notification,
+    TopicTokenError,
+};
 use crate::handlers::{
-    RegisterDeviceRequest, RegisterDeviceResponse, SendNotificationRequest,
-    SendNotificationResponse,
+    DeviceResponse, ListDevicesQuery, RegisterDeviceRequest, RegisterDeviceResponse,
+    SendMulticastRequest, SendMulticastResponse, SendNotificationRequest, SendNotificationResponse,
+    TopicSubscriptionRequest, TopicSubscriptionResponse,
 };
 
 #[utoipa::path(
@@ -18,6 +24,20 @@ use crate::handlers::{
         "data": {
             "message_id": "123456",
             "sender": "John Doe"
+        },
+        "apns": {
+            "sound": "default",
+            "badge": 1,
+            "mutable_content": true,
+            "category": "NEW_MESSAGE",
+            "priority": "high"
+        },
+        "android": {
+            "channel_id": "messages",
+            "priority": "high",
+            "collapse_key": "conversation_42",
+            "ttl": 86400,
+            "click_action": "OPEN_CONVERSATION"
         }
     })),
     responses(
@@ -28,11 +48,14 @@ use crate::handlers::{
              "error": null
          })
         ),
-        (status = 400, description = "Bad Request",
+        (status = 400, description = "Bad Request"),
+        (status = 422, description = "Validation failed",
          example = json!({
-             "success": false,
-             "message_id": null,
-             "error": "Either token or topic must be provided"
+             "error": {
+                 "message": "Validation failed: __all__",
+                 "code": 422,
+                 "fields": {"__all__": ["Either token or topic must be provided"]}
+             }
          })
         ),
         (status = 401, description = "Unauthorized",
@@ -54,6 +77,37 @@ use crate::handlers::{
 )]
 fn doc_send_notification_handler() {}
 
+#[utoipa::path(
+    post,
+    path = "/firebase/send-multicast",
+    request_body(content = SendMulticastRequest, example = json!({
+        "tokens": ["fcm-registration-token-1", "fcm-registration-token-2"],
+        "title": "Maintenance tonight",
+        "body": "The app is unavailable from 22:00 to 23:00",
+        "data": {"kind": "maintenance"}
+    })),
+    responses(
+        (status = 200, description = "Notification sent, with the outcome per token", body = SendMulticastResponse,
+         example = json!({
+             "success": true,
+             "success_count": 1,
+             "failure_count": 1,
+             "responses": [
+                 {"token": "fcm-registration-token-1", "message_id": "projects/my-project/messages/1234567890", "error": null},
+                 {"token": "fcm-registration-token-2", "message_id": null, "error": "Firebase API error: Requested entity was not found."}
+             ],
+             "error": null
+         })
+        ),
+        (status = 400, description = "Bad Request"),
+        (status = 422, description = "Validation failed"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    tag = "Firebase"
+)]
+fn doc_send_multicast_handler() {}
+
 #[utoipa::path(
     post,
     path = "/firebase/register-device",
@@ -92,21 +146,125 @@ fn doc_send_notification_handler() {}
 )]
 fn doc_register_device_handler() {}
 
+#[utoipa::path(
+    get,
+    path = "/admin/firebase/devices",
+    params(ListDevicesQuery, CursorQuery),
+    responses(
+        (status = 200, description = "A page of registered devices", body = Page<DeviceResponse>,
+         example = json!({
+             "items": [
+                 {"id": 42, "user_id": "user123", "device_id": "device456"}
+             ],
+             "next_cursor": "42",
+             "has_more": true
+         })
+        ),
+        (status = 401, description = "Unauthorized"),
+        (status = 422, description = "Invalid cursor"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    tag = "Firebase"
+)]
+fn doc_list_devices_handler() {}
+
+#[utoipa::path(
+    post,
+    path = "/firebase/topics/{topic}/subscribe",
+    params(("topic" = String, Path, description = "The topic, e.g. app_general_alerts")),
+    request_body(content = TopicSubscriptionRequest, example = json!({
+        "tokens": ["fcm-registration-token-1", "fcm-registration-token-2"]
+    })),
+    responses(
+        (status = 200, description = "Tokens subscribed, except those listed in errors", body = TopicSubscriptionResponse,
+         example = json!({
+             "success": true,
+             "topic": "app_general_alerts",
+             "success_count": 1,
+             "failure_count": 1,
+             "errors": [{"token": "fcm-registration-token-2", "error": "NOT_FOUND"}],
+             "error": null
+         })
+        ),
+        (status = 400, description = "Invalid topic name",
+         example = json!({
+             "success": false,
+             "topic": "general alerts",
+             "success_count": 0,
+             "failure_count": 2,
+             "errors": [],
+             "error": "Invalid request: Invalid topic name 'general alerts': use letters, digits and -_.~% only"
+         })
+        ),
+        (status = 422, description = "Validation failed"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    tag = "Firebase"
+)]
+fn doc_subscribe_to_topic_handler() {}
+
+#[utoipa::path(
+    post,
+    path = "/firebase/topics/{topic}/unsubscribe",
+    params(("topic" = String, Path, description = "The topic, e.g. app_general_alerts")),
+    request_body(content = TopicSubscriptionRequest, example = json!({
+        "tokens": ["fcm-registration-token-1"]
+    })),
+    responses(
+        (status = 200, description = "Tokens unsubscribed, except those listed in errors", body = TopicSubscriptionResponse,
+         example = json!({
+             "success": true,
+             "topic": "app_general_alerts",
+             "success_count": 1,
+             "failure_count": 0,
+             "errors": [],
+             "error": null
+         })
+        ),
+        (status = 400, description = "Invalid topic name"),
+        (status = 422, description = "Validation failed"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    tag = "Firebase"
+)]
+fn doc_unsubscribe_from_topic_handler() {}
+
 #[derive(OpenApi)]
 #[openapi(
     paths(
         doc_send_notification_handler,
+        doc_send_multicast_handler,
         doc_register_device_handler,
+        doc_list_devices_handler,
+        doc_subscribe_to_topic_handler,
+        doc_unsubscribe_from_topic_handler,
     ),
     components(
         schemas(
             SendNotificationRequest,
             SendNotificationResponse,
+            SendMulticastRequest,
+            SendMulticastResponse,
+            MulticastSendResult,
             RegisterDeviceRequest,
             RegisterDeviceResponse,
+            DeviceResponse,
+            SortOrder,
+            TopicSubscriptionRequest,
+            TopicSubscriptionResponse,
+            TopicTokenError,
             FcmMessage,
             Message,
             Notification,
+            ApnsOptions,
+            ApnsPriority,
+            ApnsConfig,
+            ApnsPayload,
+            Aps,
+            AndroidOptions,
+            AndroidPriority,
+            AndroidConfig,
+            AndroidNotification,
         )
     ),
     tags(
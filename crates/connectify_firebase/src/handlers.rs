@@ -8,15 +8,22 @@
 //! OpenAPI documentation when the `openapi` feature is enabled.
 
 use axum::{
-    extract::{Json, State},
+    extract::{Json, Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
 };
+use connectify_common::models::{CursorQuery, Page};
+use connectify_common::validation::ValidatedJson;
+use connectify_common::ConnectifyError;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::{debug, error, info};
+use validator::{Validate, ValidationError};
 
-use crate::client::{FcmMessage, FirebaseClient, FirebaseError, Message, Notification};
+use crate::client::{
+    AndroidConfig, AndroidOptions, ApnsConfig, ApnsOptions, FcmMessage, FirebaseClient,
+    FirebaseError, Message, MulticastSendResult, Notification, TopicOperation, TopicTokenError,
+};
 
 /// Shared state for Firebase handlers
 ///
@@ -34,8 +41,9 @@ pub struct FirebaseState {
 /// `/send-notification` endpoint to send a push notification.
 ///
 /// Either `token` or `topic` must be provided, but not both.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[validate(schema(function = "validate_notification_target"))]
 pub struct SendNotificationRequest {
     /// Token identifying the target device (for single device targeting)
     ///
@@ -59,6 +67,36 @@ pub struct SendNotificationRequest {
     ///
     /// This data will be available to the client app that receives the message.
     pub data: Option<std::collections::HashMap<String, String>>,
+
+    /// iOS-specific options of the notification
+    pub apns: Option<ApnsOptions>,
+
+    /// Android-specific options of the notification
+    pub android: Option<AndroidOptions>,
+}
+
+/// The HTTP status of a failed Firebase operation.
+fn error_status(err: &FirebaseError) -> StatusCode {
+    match err {
+        FirebaseError::AuthError(_) => StatusCode::UNAUTHORIZED,
+        FirebaseError::ConfigError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        FirebaseError::RequestError(_) => StatusCode::BAD_REQUEST,
+        FirebaseError::ApiError(_) => StatusCode::BAD_REQUEST,
+        FirebaseError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+        #[cfg(feature = "database")]
+        FirebaseError::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Checks that exactly one of `token` and `topic` is provided.
+fn validate_notification_target(request: &SendNotificationRequest) -> Result<(), ValidationError> {
+    match (&request.token, &request.topic) {
+        (None, None) => Err(ValidationError::new("missing_target")
+            .with_message("Either token or topic must be provided".into())),
+        (Some(_), Some(_)) => Err(ValidationError::new("ambiguous_target")
+            .with_message("Cannot provide both token and topic".into())),
+        _ => Ok(()),
+    }
 }
 
 /// Response body for the send notification endpoint
@@ -88,16 +126,19 @@ pub struct SendNotificationResponse {
 ///
 /// This struct represents the JSON payload that should be sent to the
 /// `/register-device` endpoint to register a device for push notifications.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct RegisterDeviceRequest {
     /// The user ID to associate with the registration
+    #[validate(length(min = 1))]
     pub user_id: String,
 
     /// The device ID to associate with the registration
+    #[validate(length(min = 1))]
     pub device_id: String,
 
     /// The Firebase Cloud Messaging registration token
+    #[validate(length(min = 1))]
     pub registration_token: String,
 }
 
@@ -121,15 +162,43 @@ pub struct RegisterDeviceResponse {
     pub error: Option<String>,
 }
 
+/// Query parameters for listing registered devices
+///
+/// The page itself is selected with the shared [`CursorQuery`] parameters.
+#[derive(Debug, Default, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct ListDevicesQuery {
+    /// Only list the devices of this user
+    pub user_id: Option<String>,
+}
+
+/// A registered device, as listed by the admin device endpoint
+///
+/// The registration token is not included, as it allows sending notifications to the device.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DeviceResponse {
+    /// The unique identifier of the registration
+    pub id: Option<i64>,
+
+    /// The user ID associated with the registration
+    pub user_id: String,
+
+    /// The device ID associated with the registration
+    pub device_id: String,
+}
+
 /// Request body for sending a notification to a user
 ///
 /// This struct represents the JSON payload that should be sent to the
 /// `/send-notification-to-user` endpoint to send a push notification to all
 /// devices registered for a user.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SendNotificationToUserRequest {
     /// The user ID to send notifications to
+    #[validate(length(min = 1))]
     pub user_id: String,
 
     /// The title of the notification
@@ -140,6 +209,12 @@ pub struct SendNotificationToUserRequest {
 
     /// Custom key-value data to be sent with the message
     pub data: Option<std::collections::HashMap<String, String>>,
+
+    /// iOS-specific options of the notification
+    pub apns: Option<ApnsOptions>,
+
+    /// Android-specific options of the notification
+    pub android: Option<AndroidOptions>,
 }
 
 /// Response body for the send notification to user endpoint
@@ -163,6 +238,296 @@ pub struct SendNotificationToUserResponse {
     pub error: Option<String>,
 }
 
+/// Request body for sending a notification to a list of devices
+///
+/// This struct represents the JSON payload that should be sent to the
+/// `/send-multicast` endpoint to send a push notification to explicit tokens.
+#[derive(Debug, Deserialize, Validate)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SendMulticastRequest {
+    /// The registration tokens of the target devices, at most 500 (`MAX_MULTICAST_TOKENS`)
+    #[validate(length(min = 1, max = 500))]
+    pub tokens: Vec<String>,
+
+    /// The title of the notification
+    pub title: String,
+
+    /// The body text of the notification
+    pub body: String,
+
+    /// Custom key-value data to be sent with the message
+    pub data: Option<std::collections::HashMap<String, String>>,
+
+    /// iOS-specific options of the notification
+    pub apns: Option<ApnsOptions>,
+
+    /// Android-specific options of the notification
+    pub android: Option<AndroidOptions>,
+}
+
+/// Response body for the send multicast endpoint
+///
+/// This struct represents the JSON response that is returned from the
+/// `/send-multicast` endpoint after attempting to send a notification to each token.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SendMulticastResponse {
+    /// Whether the notification was sent to at least one token
+    pub success: bool,
+
+    /// The number of tokens the notification was sent to
+    pub success_count: usize,
+
+    /// The number of tokens the notification failed to send to
+    pub failure_count: usize,
+
+    /// The outcome per token, in the order of the request
+    pub responses: Vec<MulticastSendResult>,
+
+    /// Error message if the request failed
+    pub error: Option<String>,
+}
+
+/// Handler for sending a push notification to a list of devices
+///
+/// This handler sends the notification to each registration token of the request,
+/// several at once, and reports the outcome per token.
+///
+/// # Responses
+///
+/// - 200 OK: Request processed, with the outcome per token
+/// - 400 Bad Request: Invalid parameters
+/// - 401 Unauthorized: Authentication failed
+/// - 500 Internal Server Error: Server-side error
+///
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/firebase/send-multicast",
+    request_body = SendMulticastRequest,
+    responses(
+        (status = 200, description = "Notification sent, with the outcome per token", body = SendMulticastResponse),
+        (status = 400, description = "Bad Request"),
+        (status = 422, description = "Validation failed"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    tag = "Firebase"
+))]
+pub async fn send_multicast_handler(
+    State(state): State<Arc<FirebaseState>>,
+    ValidatedJson(payload): ValidatedJson<SendMulticastRequest>,
+) -> Response {
+    debug!(
+        "Sending multicast notification to {} devices",
+        payload.tokens.len()
+    );
+
+    let notification = Notification {
+        title: payload.title,
+        body: payload.body,
+    };
+
+    match state
+        .client
+        .send_multicast(
+            &payload.tokens,
+            notification,
+            payload.data,
+            payload.apns,
+            payload.android,
+        )
+        .await
+    {
+        Ok(result) => {
+            info!(
+                "Sent multicast notification to {}/{} devices",
+                result.success_count,
+                payload.tokens.len()
+            );
+            Json(SendMulticastResponse {
+                success: result.success_count > 0,
+                success_count: result.success_count,
+                failure_count: result.failure_count,
+                responses: result.responses,
+                error: None,
+            })
+            .into_response()
+        }
+        Err(err) => {
+            error!("Failed to send multicast notification: {:?}", err);
+            let status = error_status(&err);
+
+            (
+                status,
+                Json(SendMulticastResponse {
+                    success: false,
+                    success_count: 0,
+                    failure_count: payload.tokens.len(),
+                    responses: Vec::new(),
+                    error: Some(err.to_string()),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Request body for subscribing devices to or unsubscribing them from a topic
+///
+/// This struct represents the JSON payload that should be sent to the
+/// `/topics/{topic}/subscribe` and `/topics/{topic}/unsubscribe` endpoints.
+#[derive(Debug, Deserialize, Validate)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TopicSubscriptionRequest {
+    /// The Firebase Cloud Messaging registration tokens of the devices
+    #[validate(length(min = 1))]
+    pub tokens: Vec<String>,
+}
+
+/// Response body for the topic subscription endpoints
+///
+/// This struct represents the JSON response that is returned after attempting
+/// to subscribe devices to or unsubscribe them from a topic.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TopicSubscriptionResponse {
+    /// Whether the request reached Firebase; tokens may still have failed individually
+    pub success: bool,
+
+    /// The topic
+    pub topic: String,
+
+    /// The number of tokens the operation succeeded for
+    pub success_count: usize,
+
+    /// The number of tokens the operation failed for
+    pub failure_count: usize,
+
+    /// The tokens the operation failed for, with their errors
+    pub errors: Vec<TopicTokenError>,
+
+    /// Error message if the request failed
+    pub error: Option<String>,
+}
+
+/// Subscribes or unsubscribes the tokens of a request and responds with the outcome.
+async fn manage_topic(
+    state: &FirebaseState,
+    operation: TopicOperation,
+    topic: String,
+    tokens: &[String],
+) -> Response {
+    debug!(
+        "{:?} {} devices for topic: {}",
+        operation,
+        tokens.len(),
+        topic
+    );
+
+    match state.client.manage_topic(operation, &topic, tokens).await {
+        Ok(result) => {
+            info!(
+                "{:?} topic {}: {} succeeded, {} failed",
+                operation, topic, result.success_count, result.failure_count
+            );
+            Json(TopicSubscriptionResponse {
+                success: true,
+                topic,
+                success_count: result.success_count,
+                failure_count: result.failure_count,
+                errors: result.errors,
+                error: None,
+            })
+            .into_response()
+        }
+        Err(err) => {
+            error!("Failed to {:?} topic {}: {:?}", operation, topic, err);
+            let status = error_status(&err);
+
+            (
+                status,
+                Json(TopicSubscriptionResponse {
+                    success: false,
+                    topic,
+                    success_count: 0,
+                    failure_count: tokens.len(),
+                    errors: Vec::new(),
+                    error: Some(err.to_string()),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Handler for subscribing devices to a topic
+///
+/// This handler subscribes the registration tokens of the request to the topic
+/// through the Instance ID batch API, so they receive the messages sent to it.
+///
+/// # Responses
+///
+/// - 200 OK: Request processed, with the tokens that failed
+/// - 400 Bad Request: Invalid topic name
+/// - 401 Unauthorized: Authentication failed
+/// - 500 Internal Server Error: Server-side error
+///
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/firebase/topics/{topic}/subscribe",
+    params(("topic" = String, Path, description = "The topic, e.g. app_general_alerts")),
+    request_body = TopicSubscriptionRequest,
+    responses(
+        (status = 200, description = "Tokens subscribed, except those listed in errors", body = TopicSubscriptionResponse),
+        (status = 400, description = "Invalid topic name"),
+        (status = 422, description = "Validation failed"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    tag = "Firebase"
+))]
+pub async fn subscribe_to_topic_handler(
+    State(state): State<Arc<FirebaseState>>,
+    Path(topic): Path<String>,
+    ValidatedJson(payload): ValidatedJson<TopicSubscriptionRequest>,
+) -> Response {
+    manage_topic(&state, TopicOperation::Subscribe, topic, &payload.tokens).await
+}
+
+/// Handler for unsubscribing devices from a topic
+///
+/// This handler unsubscribes the registration tokens of the request from the topic
+/// through the Instance ID batch API.
+///
+/// # Responses
+///
+/// - 200 OK: Request processed, with the tokens that failed
+/// - 400 Bad Request: Invalid topic name
+/// - 401 Unauthorized: Authentication failed
+/// - 500 Internal Server Error: Server-side error
+///
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/firebase/topics/{topic}/unsubscribe",
+    params(("topic" = String, Path, description = "The topic, e.g. app_general_alerts")),
+    request_body = TopicSubscriptionRequest,
+    responses(
+        (status = 200, description = "Tokens unsubscribed, except those listed in errors", body = TopicSubscriptionResponse),
+        (status = 400, description = "Invalid topic name"),
+        (status = 422, description = "Validation failed"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    tag = "Firebase"
+))]
+pub async fn unsubscribe_from_topic_handler(
+    State(state): State<Arc<FirebaseState>>,
+    Path(topic): Path<String>,
+    ValidatedJson(payload): ValidatedJson<TopicSubscriptionRequest>,
+) -> Response {
+    manage_topic(&state, TopicOperation::Unsubscribe, topic, &payload.tokens).await
+}
+
 /// Handler for sending push notifications via Firebase Cloud Messaging
 ///
 /// This handler accepts a JSON payload with notification details and sends
@@ -204,13 +569,14 @@ pub struct SendNotificationToUserResponse {
     responses(
         (status = 200, description = "Device registered successfully", body = RegisterDeviceResponse),
         (status = 400, description = "Bad Request"),
+        (status = 422, description = "Validation failed"),
         (status = 500, description = "Internal Server Error")
     ),
     tag = "Firebase"
 ))]
 pub async fn register_device_handler(
     State(state): State<Arc<FirebaseState>>,
-    Json(payload): Json<RegisterDeviceRequest>,
+    ValidatedJson(payload): ValidatedJson<RegisterDeviceRequest>,
 ) -> Response {
     debug!("Registering device for user: {}", payload.user_id);
 
@@ -238,14 +604,7 @@ pub async fn register_device_handler(
         }
         Err(err) => {
             error!("Failed to register device: {:?}", err);
-            let status = match &err {
-                FirebaseError::AuthError(_) => StatusCode::UNAUTHORIZED,
-                FirebaseError::ConfigError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-                FirebaseError::RequestError(_) => StatusCode::BAD_REQUEST,
-                FirebaseError::ApiError(_) => StatusCode::BAD_REQUEST,
-                #[cfg(feature = "database")]
-                FirebaseError::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            };
+            let status = error_status(&err);
 
             (
                 status,
@@ -261,6 +620,53 @@ pub async fn register_device_handler(
     }
 }
 
+/// Handler for listing registered devices
+///
+/// This handler returns a page of the registered devices, optionally of a single user,
+/// oldest or newest registration first.
+///
+/// # Responses
+///
+/// - 200 OK: A page of devices
+/// - 422 Unprocessable Entity: Invalid cursor
+/// - 500 Internal Server Error: Server-side error
+///
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/admin/firebase/devices",
+    params(ListDevicesQuery, CursorQuery),
+    responses(
+        (status = 200, description = "A page of registered devices", body = Page<DeviceResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 422, description = "Invalid cursor"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    tag = "Firebase"
+))]
+pub async fn list_devices_handler(
+    State(state): State<Arc<FirebaseState>>,
+    Query(filter): Query<ListDevicesQuery>,
+    Query(page): Query<CursorQuery>,
+) -> Result<Json<Page<DeviceResponse>>, ConnectifyError> {
+    debug!("Listing registered devices");
+
+    let devices = state
+        .client
+        .list_devices(filter.user_id.as_deref(), &page)
+        .await
+        .map_err(|err| {
+            error!("Failed to list devices: {:?}", err);
+            ConnectifyError::from(err)
+        })?;
+
+    Ok(Json(devices.map(|registration| DeviceResponse {
+        id: registration.id,
+        user_id: registration.user_id,
+        device_id: registration.device_id,
+    })))
+}
+
 /// Handler for sending push notifications to all devices registered for a user
 ///
 /// This handler accepts a JSON payload with notification details and sends
@@ -286,13 +692,14 @@ pub async fn register_device_handler(
     responses(
         (status = 200, description = "Notifications sent successfully", body = SendNotificationToUserResponse),
         (status = 400, description = "Bad Request"),
+        (status = 422, description = "Validation failed"),
         (status = 500, description = "Internal Server Error")
     ),
     tag = "Firebase"
 ))]
 pub async fn send_notification_to_user_handler(
     State(state): State<Arc<FirebaseState>>,
-    Json(payload): Json<SendNotificationToUserRequest>,
+    ValidatedJson(payload): ValidatedJson<SendNotificationToUserRequest>,
 ) -> Response {
     debug!(
         "Sending notification to all devices for user: {}",
@@ -306,7 +713,13 @@ pub async fn send_notification_to_user_handler(
 
     match state
         .client
-        .send_notification_to_user(&payload.user_id, notification, payload.data)
+        .send_notification_to_user(
+            &payload.user_id,
+            notification,
+            payload.data,
+            payload.apns,
+            payload.android,
+        )
         .await
     {
         Ok(message_ids) => {
@@ -326,14 +739,7 @@ pub async fn send_notification_to_user_handler(
         }
         Err(err) => {
             error!("Failed to send notifications to user: {:?}", err);
-            let status = match &err {
-                FirebaseError::AuthError(_) => StatusCode::UNAUTHORIZED,
-                FirebaseError::ConfigError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-                FirebaseError::RequestError(_) => StatusCode::BAD_REQUEST,
-                FirebaseError::ApiError(_) => StatusCode::BAD_REQUEST,
-                #[cfg(feature = "database")]
-                FirebaseError::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            };
+            let status = error_status(&err);
 
             (
                 status,
@@ -357,38 +763,15 @@ pub async fn send_notification_to_user_handler(
     responses(
         (status = 200, description = "Notification sent successfully", body = SendNotificationResponse),
         (status = 400, description = "Bad Request"),
+        (status = 422, description = "Validation failed"),
         (status = 500, description = "Internal Server Error")
     ),
     tag = "Firebase"
 ))]
 pub async fn send_notification_handler(
     State(state): State<Arc<FirebaseState>>,
-    Json(payload): Json<SendNotificationRequest>,
+    ValidatedJson(payload): ValidatedJson<SendNotificationRequest>,
 ) -> Response {
-    if payload.token.is_none() && payload.topic.is_none() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(SendNotificationResponse {
-                success: false,
-                message_id: None,
-                error: Some("Either token or topic must be provided".to_string()),
-            }),
-        )
-            .into_response();
-    }
-
-    if payload.token.is_some() && payload.topic.is_some() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(SendNotificationResponse {
-                success: false,
-                message_id: None,
-                error: Some("Cannot provide both token and topic".to_string()),
-            }),
-        )
-            .into_response();
-    }
-
     let message = FcmMessage {
         message: Message {
             token: payload.token,
@@ -398,6 +781,8 @@ pub async fn send_notification_handler(
                 body: payload.body,
             }),
             data: payload.data,
+            apns: payload.apns.as_ref().map(ApnsConfig::from),
+            android: payload.android.as_ref().map(AndroidConfig::from),
         },
     };
 
@@ -413,14 +798,7 @@ pub async fn send_notification_handler(
         }
         Err(err) => {
             error!("Failed to send FCM notification: {:?}", err);
-            let status = match &err {
-                FirebaseError::AuthError(_) => StatusCode::UNAUTHORIZED,
-                FirebaseError::ConfigError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-                FirebaseError::RequestError(_) => StatusCode::BAD_REQUEST,
-                FirebaseError::ApiError(_) => StatusCode::BAD_REQUEST,
-                #[cfg(feature = "database")]
-                FirebaseError::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            };
+            let status = error_status(&err);
 
             (
                 status,
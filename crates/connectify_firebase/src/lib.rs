@@ -8,8 +8,12 @@
 //! - Authentication with Firebase using service account credentials
 //! - Sending push notifications to specific devices using FCM tokens
 //! - Sending push notifications to topics
+//! - Sending a push notification to a list of devices (multicast)
+//! - Subscribing devices to topics and unsubscribing them
 //! - Support for notification payload (title and body)
 //! - Support for custom data payload
+//! - iOS-specific options (sound, badge, mutable-content, category, priority) per notification
+//! - Android-specific options (channel, priority, collapse key, TTL, click action) per notification
 //! - Integration with Axum for HTTP API endpoints
 //! - OpenAPI/Swagger documentation (with the `openapi` feature)
 //!
@@ -46,6 +50,10 @@
 //! # API Endpoints
 //!
 //! - `POST /send-notification` - Send a push notification to a device or topic
+//! - `POST /send-multicast` - Send a push notification to a list of device tokens
+//! - `POST /topics/{topic}/subscribe` - Subscribe device tokens to a topic
+//! - `POST /topics/{topic}/unsubscribe` - Unsubscribe device tokens from a topic
+//! - `GET /admin/firebase/devices` - List a page of registered devices (admin API keys only)
 
 pub mod auth;
 pub mod client;
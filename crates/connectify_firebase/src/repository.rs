@@ -5,6 +5,7 @@
 
 use crate::client::FirebaseError;
 use crate::models::DeviceRegistration;
+use connectify_common::models::{CursorQuery, Page};
 
 #[cfg(feature = "database")]
 use connectify_db::{
@@ -82,6 +83,7 @@ impl DeviceRegistrationRepository {
     /// # Returns
     ///
     /// The stored device registration with its ID and timestamps set
+    #[cfg_attr(not(feature = "database"), allow(unused_variables))]
     pub async fn register_device(
         &self,
         registration: DeviceRegistration,
@@ -191,6 +193,47 @@ impl DeviceRegistrationRepository {
         }
     }
 
+    /// List a page of device registrations
+    ///
+    /// The cursor of a page is the ID of its last registration.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Only list the registrations of this user, if set
+    /// * `query` - The requested page
+    ///
+    /// # Returns
+    ///
+    /// A page of device registrations, or `InvalidRequest` if the cursor is malformed
+    #[cfg_attr(not(feature = "database"), allow(unused_variables))]
+    pub async fn list_page(
+        &self,
+        user_id: Option<&str>,
+        query: &CursorQuery,
+    ) -> Result<Page<DeviceRegistration>, FirebaseError> {
+        let after_id = query
+            .cursor
+            .as_deref()
+            .map(str::parse::<i64>)
+            .transpose()
+            .map_err(|_| FirebaseError::InvalidRequest("Invalid cursor".to_string()))?;
+
+        #[cfg(feature = "database")]
+        {
+            self.inner
+                .list_page(user_id, after_id, query.limit(), query.order())
+                .await
+                .map_err(FirebaseError::DbError)
+        }
+
+        #[cfg(not(feature = "database"))]
+        {
+            Ok(Page::from_items(Vec::new(), query.limit(), |_| {
+                String::new()
+            }))
+        }
+    }
+
     /// Delete a device registration
     ///
     /// # Arguments
@@ -201,6 +244,7 @@ impl DeviceRegistrationRepository {
     /// # Returns
     ///
     /// `true` if a registration was deleted, `false` if no registration was found
+    #[cfg_attr(not(feature = "database"), allow(unused_variables))]
     pub async fn delete_registration(
         &self,
         user_id: &str,
@@ -1,14 +1,24 @@
-use axum::{routing::post, Router};
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use connectify_common::api_key::ApiKeyAuthLayer;
+use connectify_common::rate_limit::RateLimitLayer;
+use connectify_common::runtime_flags::{feature_guard, NOTIFICATIONS};
 use connectify_config::AppConfig;
 use std::sync::Arc;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::handlers::{
-    register_device_handler, send_notification_handler, send_notification_to_user_handler,
-    FirebaseState,
+    list_devices_handler, register_device_handler, send_multicast_handler,
+    send_notification_handler, send_notification_to_user_handler, subscribe_to_topic_handler,
+    unsubscribe_from_topic_handler, FirebaseState,
 };
 use crate::service::FirebaseServiceFactory;
 
+/// Scope an API key needs to list registered devices.
+pub const ADMIN_SCOPE: &str = "admin";
+
 /// Create Firebase routes for the API
 ///
 /// This function creates a router with the Firebase Cloud Messaging API endpoints.
@@ -38,15 +48,45 @@ pub fn routes(config: Arc<AppConfig>) -> Router {
         client: Arc::new(firebase_client),
     });
 
-    Router::new()
+    let mut router = Router::new()
         .route(
             "/firebase/send-notification",
-            post(send_notification_handler),
+            post(send_notification_handler).layer((
+                feature_guard(NOTIFICATIONS),
+                RateLimitLayer::for_group(&config, "firebase_notifications"),
+            )),
+        )
+        .route(
+            "/firebase/send-multicast",
+            post(send_multicast_handler).layer((
+                feature_guard(NOTIFICATIONS),
+                RateLimitLayer::for_group(&config, "firebase_notifications"),
+            )),
         )
         .route("/firebase/register-device", post(register_device_handler))
         .route(
             "/firebase/send-notification-to-user",
-            post(send_notification_to_user_handler),
+            post(send_notification_to_user_handler).layer(feature_guard(NOTIFICATIONS)),
         )
-        .with_state(state)
+        .route(
+            "/firebase/topics/{topic}/subscribe",
+            post(subscribe_to_topic_handler),
+        )
+        .route(
+            "/firebase/topics/{topic}/unsubscribe",
+            post(unsubscribe_from_topic_handler),
+        );
+
+    // Registered devices are personal data, so they are only listed to admin API keys
+    match ApiKeyAuthLayer::from_config(&config) {
+        Some(admin_auth) => {
+            router = router.route(
+                "/admin/firebase/devices",
+                get(list_devices_handler).layer(admin_auth.with_scope(ADMIN_SCOPE)),
+            );
+        }
+        None => warn!("No API keys configured, /admin/firebase/devices is disabled"),
+    }
+
+    router.with_state(state)
 }
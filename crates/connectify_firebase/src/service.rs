@@ -3,12 +3,12 @@
 //! This module provides an implementation of the ServiceFactory trait for Firebase services.
 
 use crate::client::FirebaseClient;
-use connectify_common::services::{
-    BoxedError, CalendarService, NotificationService, PaymentService, ServiceFactory,
-};
+use connectify_common::services::{BoxedError, ServiceFactory, ServiceRegistry};
 use connectify_config::AppConfig;
 use std::sync::Arc;
-use tracing::{debug, error, info};
+use tracing::debug;
+#[cfg(feature = "database")]
+use tracing::{error, info};
 
 #[cfg(feature = "database")]
 use crate::repository::DeviceRegistrationRepository;
@@ -35,6 +35,9 @@ pub struct FirebaseServiceFactory {
     /// This is None if database integration is not enabled.
     #[cfg(feature = "database")]
     repository: Option<DeviceRegistrationRepository>,
+
+    /// The services provided by Firebase.
+    registry: ServiceRegistry,
 }
 
 impl FirebaseServiceFactory {
@@ -45,13 +48,17 @@ impl FirebaseServiceFactory {
             config,
             db_client: None,
             repository: None,
+            registry: ServiceRegistry::new(),
         }
     }
 
     /// Create a new Firebase service factory.
     #[cfg(not(feature = "database"))]
     pub fn new(config: Arc<AppConfig>) -> Self {
-        Self { config }
+        Self {
+            config,
+            registry: ServiceRegistry::new(),
+        }
     }
 
     /// Initialize the database client and repository.
@@ -159,21 +166,9 @@ impl FirebaseServiceFactory {
 }
 
 impl ServiceFactory for FirebaseServiceFactory {
-    fn calendar_service(&self) -> Option<Arc<dyn CalendarService<Error = BoxedError>>> {
-        // Firebase doesn't provide calendar services
-        None
-    }
-
-    fn payment_service(&self) -> Option<Arc<dyn PaymentService<Error = BoxedError>>> {
-        // Firebase doesn't provide payment services
-        None
-    }
-
-    fn notification_service(&self) -> Option<Arc<dyn NotificationService<Error = BoxedError>>> {
-        // Firebase could potentially be used for notifications, but it would require
-        // implementing the NotificationService trait for FirebaseClient, which is
-        // beyond the scope of this change.
-        None
+    fn registry(&self) -> &ServiceRegistry {
+        // Firebase doesn't provide calendar, payment, notification or email services yet
+        &self.registry
     }
 }
 
@@ -182,7 +177,9 @@ pub mod mock {
     use super::*;
 
     /// Mock Firebase service factory for testing.
-    pub struct MockFirebaseServiceFactory;
+    pub struct MockFirebaseServiceFactory {
+        registry: ServiceRegistry,
+    }
 
     impl Default for MockFirebaseServiceFactory {
         fn default() -> Self {
@@ -193,21 +190,15 @@ pub mod mock {
     impl MockFirebaseServiceFactory {
         /// Create a new mock Firebase service factory.
         pub fn new() -> Self {
-            Self
+            Self {
+                registry: ServiceRegistry::new(),
+            }
         }
     }
 
     impl ServiceFactory for MockFirebaseServiceFactory {
-        fn calendar_service(&self) -> Option<Arc<dyn CalendarService<Error = BoxedError>>> {
-            None
-        }
-
-        fn payment_service(&self) -> Option<Arc<dyn PaymentService<Error = BoxedError>>> {
-            None
-        }
-
-        fn notification_service(&self) -> Option<Arc<dyn NotificationService<Error = BoxedError>>> {
-            None
+        fn registry(&self) -> &ServiceRegistry {
+            &self.registry
         }
     }
 }
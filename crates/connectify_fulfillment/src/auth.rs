@@ -7,6 +7,7 @@ use axum::{
     middleware::Next,
     response::{IntoResponse, Response},
 };
+use connectify_common::api_key::{ApiKeyStore, API_KEY_HEADER};
 use connectify_config::AppConfig; // To access the shared secret
 use constant_time_eq::constant_time_eq;
 use std::sync::Arc;
@@ -17,12 +18,32 @@ use tracing::{info, warn}; // For secure string comparison
 #[derive(Clone)]
 pub struct FulfillmentAuthState {
     pub config: Arc<AppConfig>,
+    /// API keys accepted via the `X-Api-Key` header, if configured.
+    pub api_keys: Option<Arc<ApiKeyStore>>,
 }
 
+impl FulfillmentAuthState {
+    pub fn new(config: Arc<AppConfig>) -> Self {
+        let api_keys = config
+            .api_keys
+            .as_ref()
+            .map(ApiKeyStore::from_config)
+            .filter(|store| !store.is_empty())
+            .map(Arc::new);
+        Self { config, api_keys }
+    }
+}
+
+/// Legacy shared secret header, still accepted when no `X-Api-Key` is sent.
 const INTERNAL_AUTH_HEADER: &str = "X-Internal-Auth-Secret";
 
+/// Scope an API key needs to call the fulfillment endpoints.
+pub const FULFILLMENT_SCOPE: &str = "fulfillment";
+
 /// Axum middleware to authenticate internal fulfillment requests.
-/// Checks for a shared secret in the `X-Internal-Auth-Secret` header.
+/// Checks for an API key with the `fulfillment` scope in the `X-Api-Key` header and
+/// injects the caller's identity. Falls back to the legacy shared secret in the
+/// `X-Internal-Auth-Secret` header if no API key is provided.
 pub async fn fulfillment_auth_middleware<B>(
     // B is the request body type
     State(auth_state): State<Arc<FulfillmentAuthState>>, // Specific state for this middleware
@@ -33,6 +54,49 @@ pub async fn fulfillment_auth_middleware<B>(
 where
     B: Send + 'static, // Add bound for B as Request<B> is passed to next.run()
 {
+    let mut req = req;
+
+    // 0. Prefer per-caller API keys over the shared secret
+    if let Some(api_key) = req
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        let caller = auth_state
+            .api_keys
+            .as_ref()
+            .and_then(|store| store.authenticate(api_key));
+        return match caller {
+            Some(caller) if caller.has_scope(FULFILLMENT_SCOPE) => {
+                info!(
+                    "✅ Fulfillment request authenticated for API caller '{}'.",
+                    caller.name
+                );
+                req.extensions_mut().insert(caller);
+                next.run(req).await
+            }
+            Some(caller) => {
+                warn!(
+                    "🚨 Fulfillment request: API caller '{}' lacks the '{}' scope.",
+                    caller.name, FULFILLMENT_SCOPE
+                );
+                (
+                    StatusCode::FORBIDDEN,
+                    "Forbidden: API key is not allowed to call fulfillment.".to_string(),
+                )
+                    .into_response()
+            }
+            None => {
+                warn!("🚨 Fulfillment request: Invalid API key provided.");
+                (
+                    StatusCode::UNAUTHORIZED,
+                    "Unauthorized: Invalid credentials.".to_string(),
+                )
+                    .into_response()
+            }
+        };
+    }
+
     // 1. Get the expected shared secret from config as &str
     // Ensure the fulfillment config and shared_secret field exist in AppConfig
     let expected_secret_as_str: String = match auth_state
@@ -27,7 +27,8 @@ use crate::logic::{
         })
     ),
     params(
-        ("X-Internal-Auth-Secret" = String, Header, description = "Shared secret for internal API authentication.", example = "your_super_secret_key_here")
+        ("X-Api-Key" = Option<String>, Header, description = "API key with the `fulfillment` scope.", example = "your_api_key_here"),
+        ("X-Internal-Auth-Secret" = Option<String>, Header, description = "Legacy shared secret, used when no X-Api-Key is sent.", example = "your_super_secret_key_here")
     ),
     responses(
         (status = 200, description = "Booking fulfilled successfully", body = FulfillmentResponse, example = json!({
@@ -46,6 +47,7 @@ use crate::logic::{
                 value = json!("Unauthorized: Invalid credentials.")
             ))
         )),
+        (status = 403, description = "Forbidden - API key lacks the fulfillment scope"),
         (status = 409, description = "Booking conflict in Google Calendar"),
         (status = 500, description = "Internal Server Error - Fulfillment failed")
     ),
@@ -72,7 +74,8 @@ fn doc_handle_gcal_booking_fulfillment() {
         })
     ),
     params(
-        ("X-Internal-Auth-Secret" = String, Header, description = "Shared secret for internal API authentication.", example = "your_super_secret_key_here")
+        ("X-Api-Key" = Option<String>, Header, description = "API key with the `fulfillment` scope.", example = "your_api_key_here"),
+        ("X-Internal-Auth-Secret" = Option<String>, Header, description = "Legacy shared secret, used when no X-Api-Key is sent.", example = "your_super_secret_key_here")
     ),
     responses(
         (status = 200, description = "Adhoc session fulfilled (GCal booked)", body = FulfillmentResponse, example = json!({
@@ -83,6 +86,7 @@ fn doc_handle_gcal_booking_fulfillment() {
         })),
         (status = 400, description = "Bad Request - Invalid payload for fulfillment"),
         (status = 401, description = "Unauthorized - Missing or invalid internal auth token"),
+        (status = 403, description = "Forbidden - API key lacks the fulfillment scope"),
         (status = 409, description = "Booking conflict in Google Calendar"),
         (status = 500, description = "Internal Server Error - Fulfillment failed")
     ),
@@ -20,16 +20,24 @@ use crate::FulfillmentState;
 /// * `HubType` (aliased as `GcalHubType`) - The type representing the Google Calendar API client
 #[cfg(feature = "gcal")]
 use connectify_gcal::{
+    assignment::{candidate_calendars, record_assignment},
     auth::create_calendar_hub, // Function to create the GCal Hub
+    capacity::{calendar_capacity, record_seat},
     logic::{
         create_calendar_event as gcal_create_event, BookSlotRequest as GcalBookSlotRequest,
-        GcalError,
-    }, // GCal's booking logic and request struct
+        EventStyle, GcalError, FULFILLMENT_TYPE_ADHOC, FULFILLMENT_TYPE_BOOKING,
+    },
+    service::GcalServiceError, // GCal's booking logic and request struct
 };
 
 // Import SmsRequest directly from connectify_twilio to avoid confusion
 #[cfg(feature = "twilio")]
-use connectify_twilio::twilio_sms::SmsRequest;
+use {
+    connectify_config::TwilioConfig,
+    connectify_twilio::twilio_sms::SmsRequest,
+    connectify_twilio::twilio_sms_queue::queue_sms,
+    connectify_twilio::twilio_sms_templates::{SmsTemplates, ADHOC_BOOKED, BOOKING_CONFIRMED},
+};
 
 // --- Error Handling for Fulfillment ---
 #[derive(Error, Debug)]
@@ -55,6 +63,33 @@ pub enum FulfillmentError {
     #[error("Internal fulfillment error: {0}")]
     InternalError(String),
 }
+
+/// The SMS notifying of a booking, rendered from a template in the configured locale.
+#[cfg(feature = "twilio")]
+fn notification_sms(
+    twilio_config: &TwilioConfig,
+    template: &str,
+    variables: &[(&str, &str)],
+) -> Option<SmsRequest> {
+    match SmsTemplates::from_config(twilio_config).render(template, variables) {
+        Ok(sms) => {
+            if sms.segments.segments > 1 {
+                info!(
+                    "SMS notification {} is sent as {} segments",
+                    template, sms.segments.segments
+                );
+            }
+            Some(SmsRequest {
+                to: twilio_config.phone_number.to_string(),
+                message: sms.body,
+            })
+        }
+        Err(e) => {
+            warn!("Failed to render SMS notification {}: {}", template, e);
+            None
+        }
+    }
+}
 // --- Request Structures for Fulfillment Tasks ---
 
 /// Data needed to fulfill a Google Calendar booking.
@@ -80,6 +115,13 @@ pub struct GcalBookingFulfillmentRequest {
     pub payment_method: Option<String>, // e.g., "stripe"
     pub payment_amount: Option<i64>,    // e.g., 1000 (in cents)
     pub room_name: Option<String>,
+    /// The URL of the payment's invoice, stored with the booking
+    pub invoice_url: Option<String>,
+    /// Email addresses to send a calendar invitation to
+    #[serde(default)]
+    pub attendees: Vec<String>,
+    /// The hold on the slot taken before the payment, released once booked
+    pub hold_id: Option<String>,
 }
 
 // --- Response Structures for Fulfillment Tasks ---
@@ -120,6 +162,21 @@ pub async fn fulfill_gcal_booking_logic(
         FulfillmentError::GcalApiError(format!("Failed to create GCal client: {}", e))
     })?;
 
+    let default_calendar_id = gcal_config.calendar_id.as_ref().ok_or_else(|| {
+        FulfillmentError::ConfigError("Missing GCal calendar_id in config".to_string())
+    })?;
+    // With an assignment strategy, the booking goes to the first free staff calendar
+    let assigned = candidate_calendars(gcal_config, chrono::Utc::now())
+        .await
+        .map_err(|e| {
+            FulfillmentError::GcalApiError(format!("Failed to assign a calendar: {}", e))
+        })?;
+    let candidates = assigned
+        .clone()
+        .unwrap_or_else(|| vec![default_calendar_id.clone()]);
+    let group_seat =
+        assigned.is_none() && calendar_capacity(gcal_config, default_calendar_id).is_some();
+
     // 2. Prepare the booking request for the connectify_gcal::logic module
     let gcal_book_request = GcalBookSlotRequest {
         start_time: payload.start_time.clone(),
@@ -134,23 +191,84 @@ pub async fn fulfill_gcal_booking_logic(
                 .unwrap_or_else(|| format!("gcal-booking-{}", chrono::Utc::now().timestamp())),
         ),
         room_name: payload.room_name.clone(),
-        // Add other fields if GcalBookSlotRequest expects them
+        invoice_url: payload.invoice_url,
+        calendar_id: None,
+        attendees: payload.attendees,
+        create_meet_link: false,
+        hold_id: payload.hold_id.clone(),
+        group_seat,
+        style: EventStyle::for_booking(
+            &state.config,
+            &payload.start_time,
+            &payload.end_time,
+            Some(FULFILLMENT_TYPE_BOOKING),
+        ),
     };
 
-    // 3. Call the booking function from connectify_gcal
-    match gcal_create_event(
-        &hub,
-        gcal_config.calendar_id.as_ref().ok_or_else(|| {
-            FulfillmentError::ConfigError("Missing GCal calendar_id in config".to_string())
-        })?,
-        gcal_book_request,
-    )
-    .await
-    {
+    // 3. Call the booking function from connectify_gcal, moving on to the next candidate
+    // calendar while the slot is taken
+    let mut calendar_id = &candidates[0];
+    let mut result = Err(GcalError::Conflict);
+    for candidate in &candidates {
+        calendar_id = candidate;
+        result = gcal_create_event(&hub, calendar_id, gcal_book_request.clone()).await;
+        if !matches!(
+            result,
+            Err(GcalError::Conflict) | Err(GcalError::ServiceError(GcalServiceError::Conflict))
+        ) {
+            break;
+        }
+    }
+    match result {
         Ok(created_event) => {
             let event_id = created_event.id;
             info!("Successfully booked GCal event. ID: {:?}", event_id);
 
+            // Remember which staff calendar the booking went to
+            if let (Some(_), Some(id)) = (&assigned, event_id.as_deref()) {
+                let parse = |time: &str| {
+                    chrono::DateTime::parse_from_rfc3339(time)
+                        .map(|time| time.with_timezone(&chrono::Utc))
+                };
+                if let (Ok(start), Ok(end)) = (parse(&payload.start_time), parse(&payload.end_time))
+                {
+                    if let Err(e) =
+                        record_assignment(calendar_id, id, start, end, chrono::Utc::now()).await
+                    {
+                        warn!("Failed to record the calendar of booking {}: {}", id, e);
+                    }
+                }
+            }
+
+            // Count the seat of a group slot
+            if let (true, Some(id)) = (group_seat, event_id.as_deref()) {
+                let parse = |time: &str| {
+                    chrono::DateTime::parse_from_rfc3339(time)
+                        .map(|time| time.with_timezone(&chrono::Utc))
+                };
+                match (parse(&payload.start_time), parse(&payload.end_time)) {
+                    (Ok(start), Ok(end)) => {
+                        if let Err(e) = record_seat(calendar_id, id, start, end).await {
+                            warn!("Failed to record the seat of booking {}: {}", id, e);
+                        }
+                    }
+                    _ => warn!(
+                        "Cannot record the seat of booking {}: invalid slot times",
+                        id
+                    ),
+                }
+            }
+
+            // The slot is booked now, so its hold is no longer needed
+            if let Some(hold_id) = payload.hold_id.as_deref() {
+                if let Err(e) = connectify_common::holds::slot_hold_store()
+                    .release(hold_id)
+                    .await
+                {
+                    warn!("Failed to release slot hold {}: {}", hold_id, e);
+                }
+            }
+
             // Send SMS notification if Twilio is enabled - using modified approach
             #[cfg(feature = "twilio")]
             {
@@ -159,27 +277,25 @@ pub async fn fulfill_gcal_booking_logic(
                     if state.config.use_twilio {
                         info!("Twilio is enabled in runtime config, preparing to send SMS");
 
-                        // Create an explicit instance of SmsRequest from connectify_twilio
-                        let sms_request = SmsRequest {
-                            to: twilio_config.phone_number.to_string(),
-                            message: format!(
-                                "Appointment confirmed: start_time: {}, end_time: {}, summary: {}",
-                                &payload.start_time, &payload.end_time, &payload.summary
-                            ),
-                        };
-
-                        // Use the full path for the send_sms function
-                        match connectify_twilio::twilio_sms::send_sms(
-                            State(state.config.clone()),
-                            axum::Json(sms_request),
-                        )
-                        .await
-                        {
-                            Ok(_) => {
-                                info!("SMS notification sent successfully");
-                            }
-                            Err(e) => {
-                                warn!("Failed to send SMS notification: {:?}", e);
+                        let sms_request = notification_sms(
+                            twilio_config,
+                            BOOKING_CONFIRMED,
+                            &[
+                                ("start_time", &payload.start_time),
+                                ("end_time", &payload.end_time),
+                                ("summary", &payload.summary),
+                            ],
+                        );
+
+                        // Sent by the SMS queue worker at the sender's throughput
+                        if let Some(sms_request) = sms_request {
+                            match queue_sms(&sms_request).await {
+                                Ok(_) => {
+                                    info!("SMS notification queued successfully");
+                                }
+                                Err(e) => {
+                                    warn!("Failed to queue SMS notification: {}", e);
+                                }
                             }
                         }
                     } else {
@@ -202,7 +318,7 @@ pub async fn fulfill_gcal_booking_logic(
                 room_name: payload.room_name,
             })
         }
-        Err(GcalError::Conflict) => {
+        Err(GcalError::Conflict) | Err(GcalError::ServiceError(GcalServiceError::Conflict)) => {
             warn!("GCal booking conflict for summary: {}", payload.summary);
             Err(FulfillmentError::GcalBookingConflict)
         }
@@ -232,6 +348,8 @@ pub struct AdhocGcalTwilioFulfillmentRequest {
     pub payment_method: Option<String>,
     pub payment_amount: Option<i64>,
     pub payment_id: Option<String>,
+    /// The URL of the payment's invoice, stored with the booking
+    pub invoice_url: Option<String>,
 }
 
 #[cfg(feature = "gcal")] // This fulfillment type also depends on GCal
@@ -272,6 +390,18 @@ pub async fn fulfill_adhoc_gcal_twilio_logic(
                 .unwrap_or_else(|| format!("adhoc-booking-{}", chrono::Utc::now().timestamp())),
         ),
         room_name: Some(payload.room_name.clone()),
+        invoice_url: payload.invoice_url,
+        calendar_id: None,
+        attendees: Vec::new(),
+        create_meet_link: false,
+        hold_id: None,
+        group_seat: false,
+        style: EventStyle::for_booking(
+            &state.config,
+            &payload.start_time,
+            &payload.end_time,
+            Some(FULFILLMENT_TYPE_ADHOC),
+        ),
     };
 
     match gcal_create_event(&hub, calendar_id_to_use, gcal_book_request).await {
@@ -291,30 +421,26 @@ pub async fn fulfill_adhoc_gcal_twilio_logic(
                     if state.config.use_twilio {
                         info!("Twilio is enabled in runtime config, preparing to send adhoc SMS");
 
-                        // Create an explicit instance of SmsRequest
-                        let sms_request = SmsRequest {
-                            to: twilio_config.phone_number.to_string(),
-                            message: format!(
-                                "Adhoc session booked: room: {}, start: {}, end: {}, summary: {}",
-                                &payload.room_name,
-                                &payload.start_time,
-                                &payload.end_time,
-                                &payload.summary
-                            ),
-                        };
-
-                        // Use the full path for the send_sms function
-                        match connectify_twilio::twilio_sms::send_sms(
-                            State(state.config.clone()),
-                            axum::Json(sms_request),
-                        )
-                        .await
-                        {
-                            Ok(_) => {
-                                info!("Adhoc SMS notification sent successfully");
-                            }
-                            Err(e) => {
-                                warn!("Failed to send adhoc SMS notification: {:?}", e);
+                        let sms_request = notification_sms(
+                            twilio_config,
+                            ADHOC_BOOKED,
+                            &[
+                                ("room_name", &payload.room_name),
+                                ("start_time", &payload.start_time),
+                                ("end_time", &payload.end_time),
+                                ("summary", &payload.summary),
+                            ],
+                        );
+
+                        // Sent by the SMS queue worker at the sender's throughput
+                        if let Some(sms_request) = sms_request {
+                            match queue_sms(&sms_request).await {
+                                Ok(_) => {
+                                    info!("Adhoc SMS notification queued successfully");
+                                }
+                                Err(e) => {
+                                    warn!("Failed to queue adhoc SMS notification: {}", e);
+                                }
                             }
                         }
                     } else {
@@ -337,7 +463,7 @@ pub async fn fulfill_adhoc_gcal_twilio_logic(
                 room_name: Some(payload.room_name),
             })
         }
-        Err(GcalError::Conflict) => {
+        Err(GcalError::Conflict) | Err(GcalError::ServiceError(GcalServiceError::Conflict)) => {
             info!(
                 "[Fulfillment Logic] Adhoc GCal booking conflict for summary: {}",
                 payload.summary
@@ -28,9 +28,7 @@ pub fn routes(
         gcal_state_for_fulfillment: gcal_state_option,
     });
 
-    let auth_middleware_state = Arc::new(FulfillmentAuthState {
-        config: config.clone(),
-    });
+    let auth_middleware_state = Arc::new(FulfillmentAuthState::new(config.clone()));
 
     #[allow(unused_mut)]
     let mut fulfillment_api_router = Router::new();
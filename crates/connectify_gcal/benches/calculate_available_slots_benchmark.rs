@@ -1,6 +1,6 @@
 use chrono::{DateTime, Duration, NaiveTime, Utc, Weekday};
 use chrono_tz::Tz;
-use connectify_gcal::logic::calculate_available_slots;
+use connectify_gcal::logic::{calculate_available_slots, WorkingHoursConfig};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 // Helper function to create a valid time range
@@ -57,9 +57,11 @@ fn benchmark_calculate_available_slots(c: &mut Criterion) {
                 black_box(end),
                 black_box(&busy_periods),
                 black_box(duration),
-                black_box(work_start),
-                black_box(work_end),
-                black_box(&working_days),
+                black_box(&WorkingHoursConfig::uniform(
+                    work_start,
+                    work_end,
+                    &working_days,
+                )),
                 black_box(buffer),
                 black_box(step),
             )
@@ -88,9 +90,11 @@ fn benchmark_calculate_available_slots(c: &mut Criterion) {
                 black_box(end),
                 black_box(&busy_periods),
                 black_box(duration),
-                black_box(work_start),
-                black_box(work_end),
-                black_box(&working_days),
+                black_box(&WorkingHoursConfig::uniform(
+                    work_start,
+                    work_end,
+                    &working_days,
+                )),
                 black_box(buffer),
                 black_box(step),
             )
@@ -120,9 +124,11 @@ fn benchmark_calculate_available_slots(c: &mut Criterion) {
                 black_box(end),
                 black_box(&busy_periods),
                 black_box(duration),
-                black_box(work_start),
-                black_box(work_end),
-                black_box(&working_days),
+                black_box(&WorkingHoursConfig::uniform(
+                    work_start,
+                    work_end,
+                    &working_days,
+                )),
                 black_box(buffer),
                 black_box(step),
             )
@@ -152,9 +158,11 @@ fn benchmark_calculate_available_slots(c: &mut Criterion) {
                 black_box(end),
                 black_box(&busy_periods),
                 black_box(duration),
-                black_box(work_start),
-                black_box(work_end),
-                black_box(&working_days),
+                black_box(&WorkingHoursConfig::uniform(
+                    work_start,
+                    work_end,
+                    &working_days,
+                )),
                 black_box(buffer),
                 black_box(step),
             )
@@ -184,9 +192,11 @@ fn benchmark_calculate_available_slots(c: &mut Criterion) {
                 black_box(end),
                 black_box(&busy_periods),
                 black_box(duration),
-                black_box(work_start),
-                black_box(work_end),
-                black_box(&working_days),
+                black_box(&WorkingHoursConfig::uniform(
+                    work_start,
+                    work_end,
+                    &working_days,
+                )),
                 black_box(buffer),
                 black_box(step),
             )
@@ -216,9 +226,11 @@ fn benchmark_calculate_available_slots(c: &mut Criterion) {
                 black_box(end),
                 black_box(&busy_periods),
                 black_box(duration),
-                black_box(work_start),
-                black_box(work_end),
-                black_box(&working_days),
+                black_box(&WorkingHoursConfig::uniform(
+                    work_start,
+                    work_end,
+                    &working_days,
+                )),
                 black_box(buffer),
                 black_box(step),
             )
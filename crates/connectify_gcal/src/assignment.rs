@@ -0,0 +1,127 @@
+// --- File: crates/connectify_gcal/src/assignment.rs ---
+//! Assignment of bookings across staff calendars.
+//!
+//! With an `assignment` strategy configured, a booking that doesn't ask for a `calendar_id`
+//! goes to one of the configured calendars: they are tried in the order of the strategy until
+//! one is free at the requested time. Calendars with a capacity offer group slots and are never
+//! assigned. The chosen calendar is recorded in the booking ledger of connectify_common, so
+//! rescheduling, cancelling and exporting the booking find it there later.
+
+use crate::logic::configured_calendars;
+use chrono::{DateTime, Utc};
+use connectify_common::booking_ledger::{booking_ledger, BookingAssignment};
+use connectify_common::ConnectifyError;
+use connectify_config::{AssignmentStrategy, GcalConfig};
+use std::collections::HashMap;
+
+/// The calendars bookings can be assigned to: the configured ones without a capacity.
+pub fn assignable_calendars(config: &GcalConfig) -> Vec<String> {
+    configured_calendars(config)
+        .into_iter()
+        .filter(|calendar| calendar.capacity.is_none_or(|capacity| capacity <= 1))
+        .map(|calendar| calendar.id)
+        .collect()
+}
+
+/// The order in which `calendars` are tried for a new booking.
+///
+/// Round-robin starts after `latest`, the calendar of the last assigned booking. Least-booked
+/// starts with the calendar with the fewest upcoming bookings in `upcoming`; ties keep the
+/// configured order.
+pub fn order_calendars(
+    strategy: AssignmentStrategy,
+    mut calendars: Vec<String>,
+    latest: Option<&str>,
+    upcoming: &HashMap<String, usize>,
+) -> Vec<String> {
+    match strategy {
+        AssignmentStrategy::RoundRobin => {
+            if let Some(position) =
+                latest.and_then(|latest| calendars.iter().position(|calendar| calendar == latest))
+            {
+                calendars.rotate_left(position + 1);
+            }
+        }
+        AssignmentStrategy::LeastBooked => {
+            calendars.sort_by_key(|calendar| upcoming.get(calendar).copied().unwrap_or(0));
+        }
+    }
+    calendars
+}
+
+/// The calendars to try for a new booking, in order, or `None` if bookings aren't assigned,
+/// i.e. no strategy is configured or there is only one calendar to choose from.
+pub async fn candidate_calendars(
+    config: &GcalConfig,
+    now: DateTime<Utc>,
+) -> Result<Option<Vec<String>>, ConnectifyError> {
+    let Some(strategy) = config.assignment else {
+        return Ok(None);
+    };
+    let calendars = assignable_calendars(config);
+    if calendars.len() < 2 {
+        return Ok(None);
+    }
+    let ledger = booking_ledger();
+    let (latest, upcoming) = match strategy {
+        AssignmentStrategy::RoundRobin => (ledger.latest_assignment().await?, HashMap::new()),
+        AssignmentStrategy::LeastBooked => (None, ledger.upcoming_assignments(now).await?),
+    };
+    Ok(Some(order_calendars(
+        strategy,
+        calendars,
+        latest.as_ref().map(|latest| latest.calendar_id.as_str()),
+        &upcoming,
+    )))
+}
+
+/// Record the calendar a booking was assigned to.
+pub async fn record_assignment(
+    calendar_id: &str,
+    event_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<(), ConnectifyError> {
+    booking_ledger()
+        .assign(BookingAssignment {
+            event_id: event_id.to_string(),
+            calendar_id: calendar_id.to_string(),
+            start_time: start,
+            end_time: end,
+            assigned_at: now,
+        })
+        .await
+}
+
+/// Move the recorded assignment of a rescheduled booking to its new time, if it was assigned.
+pub async fn move_assignment(
+    event_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<(), ConnectifyError> {
+    let ledger = booking_ledger();
+    if let Some(assignment) = ledger.assignment(event_id).await? {
+        ledger
+            .assign(BookingAssignment {
+                start_time: start,
+                end_time: end,
+                ..assignment
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+/// The calendar of a booking: the one it was assigned to, else the default `calendar_id`.
+pub async fn booking_calendar_id(
+    config: &GcalConfig,
+    event_id: &str,
+) -> Result<Option<String>, ConnectifyError> {
+    if config.assignment.is_some() {
+        if let Some(assignment) = booking_ledger().assignment(event_id).await? {
+            return Ok(Some(assignment.calendar_id));
+        }
+    }
+    Ok(config.calendar_id.clone())
+}
@@ -0,0 +1,100 @@
+#[cfg(test)]
+mod tests {
+    use crate::assignment::{
+        assignable_calendars, booking_calendar_id, candidate_calendars, order_calendars,
+        record_assignment,
+    };
+    use chrono::{TimeZone, Utc};
+    use connectify_config::{AssignmentStrategy, GcalConfig};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn config(assignment: Option<&str>) -> GcalConfig {
+        serde_json::from_value(json!({
+            "calendar_id": "anna@example.com",
+            "calendars": [
+                { "id": "ben@example.com" },
+                { "id": "classes@example.com", "capacity": 12 },
+                { "id": "cara@example.com" }
+            ],
+            "assignment": assignment
+        }))
+        .unwrap()
+    }
+
+    fn calendars() -> Vec<String> {
+        ["anna", "ben", "cara"]
+            .iter()
+            .map(|name| format!("{}@example.com", name))
+            .collect()
+    }
+
+    #[test]
+    fn test_order_calendars() {
+        // Group calendars are never assigned
+        assert_eq!(assignable_calendars(&config(None)), calendars());
+
+        let none = HashMap::new();
+        let round_robin =
+            |latest| order_calendars(AssignmentStrategy::RoundRobin, calendars(), latest, &none);
+        assert_eq!(round_robin(None), calendars());
+        assert_eq!(
+            round_robin(Some("ben@example.com")),
+            vec!["cara@example.com", "anna@example.com", "ben@example.com"]
+        );
+        assert_eq!(
+            round_robin(Some("cara@example.com")),
+            vec!["anna@example.com", "ben@example.com", "cara@example.com"]
+        );
+        // Calendars no longer configured start from the beginning
+        assert_eq!(round_robin(Some("gone@example.com")), calendars());
+
+        let upcoming = HashMap::from([
+            ("anna@example.com".to_string(), 3),
+            ("cara@example.com".to_string(), 1),
+        ]);
+        assert_eq!(
+            order_calendars(
+                AssignmentStrategy::LeastBooked,
+                calendars(),
+                None,
+                &upcoming
+            ),
+            vec!["ben@example.com", "cara@example.com", "anna@example.com"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_candidate_calendars() {
+        let now = Utc.with_ymd_and_hms(2025, 5, 1, 12, 0, 0).unwrap();
+        assert_eq!(candidate_calendars(&config(None), now).await.unwrap(), None);
+
+        let config = config(Some("round_robin"));
+        assert_eq!(config.assignment, Some(AssignmentStrategy::RoundRobin));
+        let start = Utc.with_ymd_and_hms(2025, 5, 15, 10, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2025, 5, 15, 11, 0, 0).unwrap();
+        record_assignment("ben@example.com", "assigned-event", start, end, now)
+            .await
+            .unwrap();
+        assert_eq!(
+            candidate_calendars(&config, now).await.unwrap(),
+            Some(vec![
+                "cara@example.com".to_string(),
+                "anna@example.com".to_string(),
+                "ben@example.com".to_string()
+            ])
+        );
+
+        // Assigned bookings are found in their calendar, others in the default one
+        assert_eq!(
+            booking_calendar_id(&config, "assigned-event")
+                .await
+                .unwrap(),
+            Some("ben@example.com".to_string())
+        );
+        assert_eq!(
+            booking_calendar_id(&config, "other-event").await.unwrap(),
+            Some("anna@example.com".to_string())
+        );
+    }
+}
@@ -1,4 +1,5 @@
 // File: crates/connectify_gcal/src/auth.rs
+use crate::oauth::{client_secret, ConnectedUserAuth};
 use connectify_config::GcalConfig;
 use google_calendar3::{
     hyper_rustls::{self, HttpsConnectorBuilder},
@@ -14,9 +15,32 @@ type Connector = hyper_rustls::HttpsConnector<HttpConnector>;
 
 pub type HubType = CalendarHub<Connector>;
 
+/// The user to impersonate through domain-wide delegation, if any.
+pub fn delegation_subject(config: &GcalConfig) -> Option<&str> {
+    config
+        .impersonate_user
+        .as_deref()
+        .map(str::trim)
+        .filter(|user| !user.is_empty())
+}
+
+/// Create a hub authenticated with the service account in `key_path`.
+///
+/// With `impersonate_user`, the service account needs domain-wide delegation of the calendar
+/// scope in the Google Workspace admin console; requests are then made as that user.
+///
+/// With `oauth`, the hub authenticates as the user who connected their calendar through the
+/// consent screen instead, see [`crate::oauth`].
 pub async fn create_calendar_hub(
     config: &GcalConfig,
 ) -> Result<HubType, Box<dyn Error + Send + Sync>> {
+    if let Some(oauth) = &config.oauth {
+        let auth = ConnectedUserAuth::new(oauth, client_secret()?);
+        let client =
+            Client::builder(hyper_util::rt::TokioExecutor::new()).build(https_connector()?);
+        return Ok(CalendarHub::new(client, auth));
+    }
+
     let key_path = config
         .key_path
         .as_deref()
@@ -24,18 +48,25 @@ pub async fn create_calendar_hub(
 
     let sa_key = read_service_account_key(Path::new(key_path)).await?;
 
-    let auth = ServiceAccountAuthenticator::builder(sa_key).build().await?;
-
-    let https = HttpsConnectorBuilder::new()
-        .with_native_roots()?
-        .https_or_http()
-        .enable_http1()
-        .build();
+    let mut auth = ServiceAccountAuthenticator::builder(sa_key);
+    if let Some(subject) = delegation_subject(config) {
+        auth = auth.subject(subject);
+    }
+    let auth = auth.build().await?;
 
     // Create client without specifying body type
-    let client = Client::builder(hyper_util::rt::TokioExecutor::new()).build(https);
+    let client = Client::builder(hyper_util::rt::TokioExecutor::new()).build(https_connector()?);
 
     let hub = CalendarHub::new(client, auth);
 
     Ok(hub)
 }
+
+/// The connector calendar requests are sent through.
+fn https_connector() -> Result<Connector, Box<dyn Error + Send + Sync>> {
+    Ok(HttpsConnectorBuilder::new()
+        .with_native_roots()?
+        .https_or_http()
+        .enable_http1()
+        .build())
+}
@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod tests {
-    use crate::auth::create_calendar_hub;
+    use crate::auth::{create_calendar_hub, delegation_subject};
     use connectify_config::load_config;
 
     #[tokio::test]
@@ -65,6 +65,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_delegation_subject() {
+        let mut config = load_config()
+            .expect("Failed to load config")
+            .gcal
+            .expect("Failed to load gcal config");
+
+        config.impersonate_user = None;
+        assert_eq!(delegation_subject(&config), None);
+
+        // An empty value, e.g. from an environment override, disables impersonation
+        config.impersonate_user = Some(" ".to_string());
+        assert_eq!(delegation_subject(&config), None);
+
+        config.impersonate_user = Some("anna@example.com".to_string());
+        assert_eq!(delegation_subject(&config), Some("anna@example.com"));
+    }
+
     // Note: We can't easily test the success case without a real service account key file
     // In a real test suite, you would mock the file reading and authentication parts
 }
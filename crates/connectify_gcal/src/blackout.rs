@@ -0,0 +1,195 @@
+// --- File: crates/connectify_gcal/src/blackout.rs ---
+//! Blackout dates: days without availability, e.g. vacations or public holidays.
+//!
+//! Blackout periods come from the `blackout_dates` of the GCal config and can be added or
+//! removed at runtime through the admin API. Public holidays are read from the all-day events
+//! of the configured `holiday_calendars`.
+
+use crate::auth::HubType;
+use crate::logic::GcalError;
+use crate::service::GoogleCalendarService;
+use chrono::{DateTime, NaiveDate};
+use chrono_tz::Tz;
+use connectify_common::cache::{self, cache};
+use connectify_config::{BlackoutPeriod, GcalConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+/// How long the dates of holiday calendars are cached.
+const HOLIDAY_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+/// A period without availability.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct Blackout {
+    pub id: String,
+    /// First blocked date
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = "date", example = "2025-12-24"))]
+    pub start_date: NaiveDate,
+    /// Last blocked date (inclusive)
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = "date", example = "2025-12-26"))]
+    pub end_date: NaiveDate,
+    pub reason: Option<String>,
+}
+
+impl Blackout {
+    /// Whether the period covers a date.
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start_date <= date && date <= self.end_date
+    }
+}
+
+/// Request to add a blackout period.
+#[derive(Deserialize, Debug, Validate)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[validate(schema(function = "validate_blackout_dates"))]
+pub struct CreateBlackoutRequest {
+    /// First blocked date in YYYY-MM-DD format
+    #[validate(custom(function = "validate_date"))]
+    pub start_date: String,
+    /// Last blocked date in YYYY-MM-DD format (default: `start_date`)
+    #[validate(custom(function = "validate_date"))]
+    pub end_date: Option<String>,
+    pub reason: Option<String>,
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+/// Checks that a value is a date in YYYY-MM-DD format.
+fn validate_date(value: &str) -> Result<(), ValidationError> {
+    parse_date(value)
+        .map(|_| ())
+        .ok_or_else(|| ValidationError::new("date"))
+}
+
+/// Checks that a blackout period doesn't end before it starts.
+fn validate_blackout_dates(request: &CreateBlackoutRequest) -> Result<(), ValidationError> {
+    let start = parse_date(&request.start_date);
+    let end = request.end_date.as_deref().and_then(parse_date);
+    match (start, end) {
+        (Some(start), Some(end)) if end < start => Err(ValidationError::new("end_before_start")),
+        _ => Ok(()),
+    }
+}
+
+/// The blackout periods of the running service.
+#[derive(Debug, Default)]
+pub struct BlackoutStore {
+    blackouts: RwLock<Vec<Blackout>>,
+}
+
+impl BlackoutStore {
+    /// Create a store with the `blackout_dates` of the config; invalid entries are skipped.
+    pub fn from_config(config: &GcalConfig) -> Self {
+        let store = Self::default();
+        for period in &config.blackout_dates {
+            match Self::parse_period(period) {
+                Some((start, end)) => {
+                    store.add(start, end, period.reason.clone());
+                }
+                None => warn!("Ignoring invalid blackout period {:?}", period),
+            }
+        }
+        store
+    }
+
+    fn parse_period(period: &BlackoutPeriod) -> Option<(NaiveDate, NaiveDate)> {
+        let start = parse_date(&period.start_date)?;
+        let end = match period.end_date.as_deref() {
+            Some(end) => parse_date(end)?,
+            None => start,
+        };
+        (start <= end).then_some((start, end))
+    }
+
+    /// All blackout periods, sorted by start date.
+    pub fn list(&self) -> Vec<Blackout> {
+        let mut blackouts = self
+            .blackouts
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        blackouts.sort_by_key(|blackout| blackout.start_date);
+        blackouts
+    }
+
+    /// Add a blackout period from `start_date` to `end_date` (inclusive).
+    pub fn add(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        reason: Option<String>,
+    ) -> Blackout {
+        let blackout = Blackout {
+            id: uuid::Uuid::new_v4().to_string(),
+            start_date,
+            end_date,
+            reason,
+        };
+        self.blackouts
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(blackout.clone());
+        blackout
+    }
+
+    /// Add a blackout period from an admin request, which must have been validated.
+    pub fn add_request(&self, request: CreateBlackoutRequest) -> Option<Blackout> {
+        let start = parse_date(&request.start_date)?;
+        let end = match request.end_date.as_deref() {
+            Some(end) => parse_date(end)?,
+            None => start,
+        };
+        Some(self.add(start, end, request.reason))
+    }
+
+    /// Remove a blackout period; returns whether it existed.
+    pub fn remove(&self, id: &str) -> bool {
+        let mut blackouts = self.blackouts.write().unwrap_or_else(|e| e.into_inner());
+        let count = blackouts.len();
+        blackouts.retain(|blackout| blackout.id != id);
+        blackouts.len() != count
+    }
+
+    /// The blacked out dates from `start` to `end` (inclusive).
+    pub fn dates_between(&self, start: NaiveDate, end: NaiveDate) -> BTreeSet<NaiveDate> {
+        let blackouts = self.blackouts.read().unwrap_or_else(|e| e.into_inner());
+        start
+            .iter_days()
+            .take_while(|date| *date <= end)
+            .filter(|date| blackouts.iter().any(|blackout| blackout.contains(*date)))
+            .collect()
+    }
+}
+
+/// The dates of the all-day events of a holiday calendar, cached for a day.
+pub async fn get_holiday_dates(
+    hub: &HubType,
+    calendar_id: &str,
+    start_time: DateTime<Tz>,
+    end_time: DateTime<Tz>,
+) -> Result<Vec<NaiveDate>, GcalError> {
+    let cache_key = format!(
+        "gcal:holidays:{}:{}:{}",
+        calendar_id,
+        start_time.timestamp(),
+        end_time.timestamp()
+    );
+    if let Some(dates) = cache::get_json::<Vec<NaiveDate>>(&*cache(), &cache_key).await {
+        return Ok(dates);
+    }
+
+    let service = GoogleCalendarService::new(Arc::new(hub.clone()));
+    let dates = service
+        .get_all_day_dates(calendar_id, start_time, end_time)
+        .await?;
+    cache::set_json(&*cache(), &cache_key, &dates, HOLIDAY_CACHE_TTL).await;
+    Ok(dates)
+}
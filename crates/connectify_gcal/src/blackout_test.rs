@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod tests {
+    use crate::blackout::{BlackoutStore, CreateBlackoutRequest};
+    use chrono::NaiveDate;
+    use validator::Validate;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 12, day).unwrap()
+    }
+
+    #[test]
+    fn test_blackout_store() {
+        let store = BlackoutStore::default();
+        let christmas = store.add(date(24), date(26), Some("Christmas".to_string()));
+        let new_year = store
+            .add_request(CreateBlackoutRequest {
+                start_date: "2025-12-31".to_string(),
+                end_date: None,
+                reason: None,
+            })
+            .unwrap();
+
+        assert_eq!(
+            store
+                .dates_between(date(20), date(31))
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![date(24), date(25), date(26), date(31)]
+        );
+        assert!(store.dates_between(date(27), date(30)).is_empty());
+
+        assert!(store.remove(&christmas.id));
+        assert!(!store.remove(&christmas.id));
+        assert_eq!(store.list(), vec![new_year]);
+    }
+
+    #[test]
+    fn test_create_blackout_request_validation() {
+        let request = |start: &str, end: Option<&str>| CreateBlackoutRequest {
+            start_date: start.to_string(),
+            end_date: end.map(str::to_string),
+            reason: None,
+        };
+
+        assert!(request("2025-12-24", Some("2025-12-26")).validate().is_ok());
+        assert!(request("2025-12-24", None).validate().is_ok());
+        assert!(request("2025-12-26", Some("2025-12-24"))
+            .validate()
+            .is_err());
+        assert!(request("24.12.2025", None).validate().is_err());
+    }
+}
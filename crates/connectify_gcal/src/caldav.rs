@@ -0,0 +1,804 @@
+// --- File: crates/connectify_gcal/src/caldav.rs ---
+//! CalDAV calendar service implementation.
+//!
+//! This module provides an implementation of the CalendarService trait for CalDAV servers such
+//! as Nextcloud, Fastmail or Radicale, selected with `gcal.backend: caldav`. Every booking is
+//! stored as a calendar object resource named after its UID, which doubles as the event ID.
+
+use crate::ics::{escape_text, fold_line};
+use crate::service::extract_payment_metadata;
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use connectify_common::http::circuit_breaker::HttpClientError;
+use connectify_common::request_id::RequestIdExt;
+use connectify_common::retry::{send_with_retry, RetryPolicy};
+use connectify_common::services::{
+    BookedEvent, BoxFuture, CalendarEvent, CalendarEventPatch, CalendarEventResult, CalendarService,
+};
+use connectify_common::{external_service_error, ConnectifyError, HTTP_CLIENT};
+use connectify_config::{CaldavConfig, GcalConfig};
+use reqwest::{header, Method, RequestBuilder, StatusCode};
+use std::collections::HashMap;
+use std::str::FromStr;
+use thiserror::Error;
+use tracing::{debug, info};
+
+/// Environment variable holding the password of the CalDAV user.
+pub const CALDAV_PASSWORD_ENV: &str = "CALDAV_PASSWORD";
+
+const PRODUCT_ID: &str = "-//Connectify//Connectify CalDAV//EN";
+/// Prefix of the properties storing payment metadata, e.g. `X-CONNECTIFY-PAYMENT-ID`.
+const METADATA_PROPERTY_PREFIX: &str = "X-CONNECTIFY-";
+
+/// Errors that can occur when interacting with a CalDAV server.
+#[derive(Error, Debug)]
+pub enum CaldavError {
+    #[error("CalDAV request failed: {0}")]
+    Request(#[from] HttpClientError),
+    #[error("CalDAV server returned {status}: {body}")]
+    Status { status: StatusCode, body: String },
+    #[error("Event not found: {0}")]
+    NotFound(String),
+    #[error("Booking conflict")]
+    Conflict,
+    #[error("Invalid calendar data: {0}")]
+    InvalidData(String),
+    #[error("Failed to parse time: {0}")]
+    TimeParseError(String),
+}
+
+/// Convert CaldavError to ConnectifyError
+impl From<CaldavError> for ConnectifyError {
+    fn from(err: CaldavError) -> Self {
+        match err {
+            CaldavError::Request(e) => e.into(),
+            CaldavError::Status { status, body } => {
+                external_service_error("CalDAV", format!("{}: {}", status, body))
+            }
+            CaldavError::NotFound(event_id) => {
+                ConnectifyError::NotFoundError(format!("Event not found: {}", event_id))
+            }
+            CaldavError::Conflict => ConnectifyError::ConflictError("Booking conflict".to_string()),
+            CaldavError::InvalidData(msg) | CaldavError::TimeParseError(msg) => {
+                ConnectifyError::ParseError(msg)
+            }
+        }
+    }
+}
+
+impl From<reqwest::Error> for CaldavError {
+    fn from(err: reqwest::Error) -> Self {
+        CaldavError::Request(err.into())
+    }
+}
+
+/// A VEVENT as stored on the CalDAV server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaldavEvent {
+    pub uid: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub summary: String,
+    pub description: Option<String>,
+    /// Lowercase like Google's: "confirmed", "tentative" or "cancelled".
+    pub status: String,
+    pub created: Option<DateTime<Utc>>,
+    pub updated: Option<DateTime<Utc>>,
+    pub attendees: Vec<String>,
+    /// Transparent events (`TRANSP:TRANSPARENT`) don't block availability.
+    pub transparent: bool,
+    /// Payment metadata as returned by `extract_payment_metadata`.
+    pub metadata: HashMap<String, String>,
+}
+
+impl CaldavEvent {
+    fn blocks_time(&self) -> bool {
+        !self.transparent && self.status != "cancelled"
+    }
+
+    fn into_booked_event(self, time_zone: &Tz) -> BookedEvent {
+        let format = |time: Option<DateTime<Utc>>| time.map(|t| t.to_rfc3339()).unwrap_or_default();
+        BookedEvent {
+            event_id: self.uid,
+            summary: self.summary,
+            description: self.description,
+            start_time: self.start.with_timezone(time_zone).to_rfc3339(),
+            end_time: self.end.with_timezone(time_zone).to_rfc3339(),
+            status: self.status,
+            created: format(self.created),
+            updated: format(self.updated),
+            payment_method: self.metadata.get("payment_method").cloned(),
+            payment_id: self.metadata.get("payment_id").cloned(),
+            payment_amount: self
+                .metadata
+                .get("payment_amount")
+                .and_then(|amount| amount.parse().ok()),
+            room_name: self.metadata.get("room_name").cloned(),
+            invoice_url: self.metadata.get("invoice_url").cloned(),
+            color_id: None,
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// Calendar service backed by a CalDAV server.
+#[derive(Debug, Clone)]
+pub struct CaldavCalendarService {
+    /// URL of the collection containing the calendars, ending with a slash.
+    base_url: String,
+    username: String,
+    password: String,
+    /// Time zone of floating times and all-day events.
+    time_zone: Tz,
+}
+
+impl CaldavCalendarService {
+    /// Create a new CalDAV calendar service.
+    pub fn new(config: &CaldavConfig, password: String, time_zone: Tz) -> Self {
+        let mut base_url = config.url.clone();
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+        Self {
+            base_url,
+            username: config.username.clone(),
+            password,
+            time_zone,
+        }
+    }
+
+    /// Create the service from the GCal config, reading the password from `CALDAV_PASSWORD`.
+    pub fn from_config(gcal_config: &GcalConfig) -> Result<Self, ConnectifyError> {
+        let caldav_config = gcal_config.caldav.as_ref().ok_or_else(|| {
+            ConnectifyError::ConfigError("gcal.caldav is required for the caldav backend".into())
+        })?;
+        let password = std::env::var(CALDAV_PASSWORD_ENV).map_err(|_| {
+            ConnectifyError::ConfigError(format!("{} is not set", CALDAV_PASSWORD_ENV))
+        })?;
+        let time_zone = gcal_config
+            .time_zone
+            .as_deref()
+            .and_then(|time_zone| Tz::from_str(time_zone).ok())
+            .unwrap_or(Tz::Europe__Zurich);
+        Ok(Self::new(caldav_config, password, time_zone))
+    }
+
+    /// The URL of a calendar collection; absolute calendar IDs are used as they are.
+    fn calendar_url(&self, calendar_id: &str) -> String {
+        let mut url = if calendar_id.starts_with("http://") || calendar_id.starts_with("https://") {
+            calendar_id.to_string()
+        } else {
+            format!("{}{}", self.base_url, calendar_id.trim_start_matches('/'))
+        };
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        url
+    }
+
+    fn event_url(&self, calendar_id: &str, event_id: &str) -> String {
+        format!("{}{}.ics", self.calendar_url(calendar_id), event_id)
+    }
+
+    /// Send an authenticated request, returning the status, ETag and body of the response.
+    async fn send(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<(StatusCode, Option<String>, String), CaldavError> {
+        let response = send_with_retry(
+            &RetryPolicy::default(),
+            request
+                .with_request_id()
+                .basic_auth(&self.username, Some(&self.password)),
+        )
+        .await?;
+        let status = response.status();
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+        let body = response.text().await?;
+        Ok((status, etag, body))
+    }
+
+    /// The events of a calendar overlapping `start`-`end`, with recurring events expanded.
+    async fn query_events(
+        &self,
+        calendar_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<CaldavEvent>, CaldavError> {
+        let (start, end) = (caldav_time(start), caldav_time(end));
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<C:calendar-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+  <D:prop>
+    <C:calendar-data><C:expand start="{start}" end="{end}"/></C:calendar-data>
+  </D:prop>
+  <C:filter>
+    <C:comp-filter name="VCALENDAR">
+      <C:comp-filter name="VEVENT">
+        <C:time-range start="{start}" end="{end}"/>
+      </C:comp-filter>
+    </C:comp-filter>
+  </C:filter>
+</C:calendar-query>"#
+        );
+        let report = Method::from_bytes(b"REPORT").expect("REPORT is a valid method");
+        let (status, _, response) = self
+            .send(
+                HTTP_CLIENT
+                    .request(report, self.calendar_url(calendar_id))
+                    .header("Depth", "1")
+                    .header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+                    .body(body),
+            )
+            .await?;
+        if status != StatusCode::MULTI_STATUS && !status.is_success() {
+            return Err(CaldavError::Status {
+                status,
+                body: response,
+            });
+        }
+
+        let mut events = Vec::new();
+        for calendar_data in calendar_data_elements(&response) {
+            events.extend(parse_calendar_data(&calendar_data, self.time_zone)?);
+        }
+        debug!("Fetched {} CalDAV events of {}", events.len(), calendar_id);
+        Ok(events)
+    }
+
+    /// Fetch an event and the ETag of its resource.
+    async fn get_event(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+    ) -> Result<(CaldavEvent, Option<String>), CaldavError> {
+        let (status, etag, body) = self
+            .send(HTTP_CLIENT.get(self.event_url(calendar_id, event_id)))
+            .await?;
+        match status {
+            StatusCode::NOT_FOUND => Err(CaldavError::NotFound(event_id.to_string())),
+            status if !status.is_success() => Err(CaldavError::Status { status, body }),
+            _ => parse_calendar_data(&body, self.time_zone)?
+                .into_iter()
+                .next()
+                .map(|event| (event, etag))
+                .ok_or_else(|| CaldavError::InvalidData(format!("No VEVENT in {}", event_id))),
+        }
+    }
+
+    /// Store an event; a new one must not exist yet, an existing one must still match `etag`.
+    async fn put_event(
+        &self,
+        calendar_id: &str,
+        event: &CaldavEvent,
+        etag: Option<&str>,
+    ) -> Result<(), CaldavError> {
+        let request = HTTP_CLIENT
+            .put(self.event_url(calendar_id, &event.uid))
+            .header(header::CONTENT_TYPE, "text/calendar; charset=utf-8")
+            .body(render_calendar_object(event, Utc::now()));
+        let request = match etag {
+            Some(etag) => request.header(header::IF_MATCH, etag),
+            None => request.header(header::IF_NONE_MATCH, "*"),
+        };
+        let (status, _, body) = self.send(request).await?;
+        match status {
+            // The resource was changed or created concurrently
+            StatusCode::PRECONDITION_FAILED => Err(CaldavError::Conflict),
+            status if !status.is_success() => Err(CaldavError::Status { status, body }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl CalendarService for CaldavCalendarService {
+    type Error = CaldavError;
+
+    fn get_busy_times(
+        &self,
+        calendar_id: &str,
+        start_time: DateTime<Tz>,
+        end_time: DateTime<Tz>,
+    ) -> BoxFuture<'_, Vec<(DateTime<Tz>, DateTime<Tz>)>, Self::Error> {
+        let calendar_id = calendar_id.to_string();
+        Box::pin(async move {
+            let time_zone = start_time.timezone();
+            let mut busy_periods: Vec<_> = self
+                .query_events(
+                    &calendar_id,
+                    start_time.with_timezone(&Utc),
+                    end_time.with_timezone(&Utc),
+                )
+                .await?
+                .into_iter()
+                .filter(CaldavEvent::blocks_time)
+                .map(|event| {
+                    (
+                        event.start.with_timezone(&time_zone),
+                        event.end.with_timezone(&time_zone),
+                    )
+                })
+                .collect();
+            busy_periods.sort();
+            Ok(busy_periods)
+        })
+    }
+
+    fn create_event(
+        &self,
+        calendar_id: &str,
+        event: CalendarEvent,
+    ) -> BoxFuture<'_, CalendarEventResult, Self::Error> {
+        let calendar_id = calendar_id.to_string();
+        Box::pin(async move {
+            if event.create_meet_link {
+                info!("Video conferences are not supported by CalDAV, creating the event without");
+            }
+            let now = Utc::now();
+            let caldav_event = CaldavEvent {
+                uid: uuid::Uuid::new_v4().to_string(),
+                start: parse_time(&event.start_time)?,
+                end: parse_time(&event.end_time)?,
+                summary: event.summary.clone(),
+                description: event.description.clone(),
+                status: "confirmed".to_string(),
+                created: Some(now),
+                updated: Some(now),
+                attendees: event.attendees.clone(),
+                transparent: event.transparent,
+                metadata: extract_payment_metadata(&event),
+            };
+            self.put_event(&calendar_id, &caldav_event, None).await?;
+            info!(
+                "Created CalDAV event {} in {}",
+                caldav_event.uid, calendar_id
+            );
+            Ok(CalendarEventResult {
+                event_id: Some(caldav_event.uid),
+                status: caldav_event.status,
+                meet_link: None,
+            })
+        })
+    }
+
+    fn update_event(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+        patch: CalendarEventPatch,
+    ) -> BoxFuture<'_, CalendarEventResult, Self::Error> {
+        let calendar_id = calendar_id.to_string();
+        let event_id = event_id.to_string();
+        Box::pin(async move {
+            let (mut event, etag) = self.get_event(&calendar_id, &event_id).await?;
+            let start = patch.start_time.as_deref().map(parse_time).transpose()?;
+            let end = patch.end_time.as_deref().map(parse_time).transpose()?;
+            if start.is_some() || end.is_some() {
+                event.start = start.unwrap_or(event.start);
+                event.end = end.unwrap_or(event.end);
+                if event.end <= event.start {
+                    return Err(CaldavError::TimeParseError(
+                        "end_time must be after start_time".to_string(),
+                    ));
+                }
+                // The new time must not overlap any other event
+                let conflicting = self
+                    .query_events(&calendar_id, event.start, event.end)
+                    .await?
+                    .into_iter()
+                    .any(|other| other.uid != event.uid && other.blocks_time());
+                if conflicting {
+                    return Err(CaldavError::Conflict);
+                }
+            }
+            if let Some(summary) = patch.summary {
+                event.summary = summary;
+            }
+            if let Some(description) = patch.description {
+                event.description = Some(description);
+            }
+            event.updated = Some(Utc::now());
+            self.put_event(&calendar_id, &event, etag.as_deref())
+                .await?;
+            Ok(CalendarEventResult {
+                event_id: Some(event.uid),
+                status: event.status,
+                meet_link: None,
+            })
+        })
+    }
+
+    fn delete_event(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+        _notify_attendees: bool,
+    ) -> BoxFuture<'_, (), Self::Error> {
+        let url = self.event_url(calendar_id, event_id);
+        let event_id = event_id.to_string();
+        Box::pin(async move {
+            let (status, _, body) = self.send(HTTP_CLIENT.delete(url)).await?;
+            match status {
+                StatusCode::NOT_FOUND => Err(CaldavError::NotFound(event_id)),
+                status if !status.is_success() => Err(CaldavError::Status { status, body }),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    fn mark_event_cancelled(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+        _notify_attendees: bool,
+    ) -> BoxFuture<'_, CalendarEventResult, Self::Error> {
+        let calendar_id = calendar_id.to_string();
+        let event_id = event_id.to_string();
+        Box::pin(async move {
+            let (mut event, etag) = self.get_event(&calendar_id, &event_id).await?;
+            event.status = "cancelled".to_string();
+            event.updated = Some(Utc::now());
+            self.put_event(&calendar_id, &event, etag.as_deref())
+                .await?;
+            Ok(CalendarEventResult {
+                event_id: Some(event.uid),
+                status: event.status,
+                meet_link: None,
+            })
+        })
+    }
+
+    fn get_booked_events(
+        &self,
+        calendar_id: &str,
+        start_time: DateTime<Tz>,
+        end_time: DateTime<Tz>,
+        include_cancelled: bool,
+    ) -> BoxFuture<'_, Vec<BookedEvent>, Self::Error> {
+        let calendar_id = calendar_id.to_string();
+        Box::pin(async move {
+            let time_zone = start_time.timezone();
+            let mut events = self
+                .query_events(
+                    &calendar_id,
+                    start_time.with_timezone(&Utc),
+                    end_time.with_timezone(&Utc),
+                )
+                .await?;
+            events.sort_by_key(|event| event.start);
+            Ok(events
+                .into_iter()
+                .filter(|event| include_cancelled || event.status != "cancelled")
+                .map(|event| event.into_booked_event(&time_zone))
+                .collect())
+        })
+    }
+}
+
+fn parse_time(time: &str) -> Result<DateTime<Utc>, CaldavError> {
+    DateTime::parse_from_rfc3339(time)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|e| CaldavError::TimeParseError(format!("{}: {}", time, e)))
+}
+
+/// Format a UTC time as an iCalendar DATE-TIME, e.g. `20250515T100000Z`.
+fn caldav_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// The contents of all `calendar-data` elements of a multistatus response.
+pub fn calendar_data_elements(xml: &str) -> Vec<String> {
+    let mut elements = Vec::new();
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        let local_name = name.rsplit(':').next().unwrap_or(name);
+        if local_name != "calendar-data" {
+            continue;
+        }
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        if rest[..tag_end].ends_with('/') {
+            continue;
+        }
+        let content = &rest[tag_end + 1..];
+        let close = format!("</{}>", name);
+        let Some(content_end) = content.find(&close) else {
+            break;
+        };
+        elements.push(unescape_xml(&content[..content_end]));
+        rest = &content[content_end + close.len()..];
+    }
+    elements
+}
+
+fn unescape_xml(text: &str) -> String {
+    let text = text.trim();
+    if let Some(cdata) = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|text| text.strip_suffix("]]>"))
+    {
+        return cdata.to_string();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&#xD;", "\r")
+        .replace("&amp;", "&")
+}
+
+/// A content line split into its name, parameters and value.
+struct ContentLine<'a> {
+    name: String,
+    params: HashMap<String, &'a str>,
+    value: &'a str,
+}
+
+impl<'a> ContentLine<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        // The value starts at the first colon outside of quoted parameter values
+        let mut quoted = false;
+        let colon = line.char_indices().find_map(|(index, c)| match c {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ':' if !quoted => Some(index),
+            _ => None,
+        })?;
+        let mut parts = line[..colon].split(';');
+        let name = parts.next()?.to_ascii_uppercase();
+        let params = parts
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_ascii_uppercase(), value.trim_matches('"')))
+            .collect();
+        Some(Self {
+            name,
+            params,
+            value: &line[colon + 1..],
+        })
+    }
+
+    /// The time of a DATE or DATE-TIME property; floating times are in `default_tz`.
+    fn time(&self, default_tz: Tz) -> Result<(DateTime<Utc>, bool), CaldavError> {
+        let invalid = || CaldavError::TimeParseError(format!("{}:{}", self.name, self.value));
+        if self.params.get("VALUE") == Some(&"DATE") || self.value.len() == 8 {
+            let date = NaiveDate::parse_from_str(self.value, "%Y%m%d").map_err(|_| invalid())?;
+            let midnight = default_tz
+                .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+                .earliest()
+                .ok_or_else(invalid)?;
+            return Ok((midnight.with_timezone(&Utc), true));
+        }
+        if let Some(utc) = self.value.strip_suffix('Z') {
+            let time =
+                NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+            return Ok((Utc.from_utc_datetime(&time), false));
+        }
+        let time =
+            NaiveDateTime::parse_from_str(self.value, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+        // TZIDs are often IANA names, sometimes with a prefix like "/mozilla.org/20050126_1/"
+        let time_zone = self
+            .params
+            .get("TZID")
+            .and_then(|tzid| {
+                tzid.char_indices()
+                    .filter(|(index, c)| *index == 0 || *c == '/')
+                    .find_map(|(index, _)| Tz::from_str(tzid[index..].trim_start_matches('/')).ok())
+            })
+            .unwrap_or(default_tz);
+        let local = time_zone
+            .from_local_datetime(&time)
+            .earliest()
+            .ok_or_else(invalid)?;
+        Ok((local.with_timezone(&Utc), false))
+    }
+}
+
+/// Parse a DURATION value such as `PT1H30M` or `P1D` (RFC 5545, section 3.3.6).
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.strip_prefix('+').unwrap_or(value);
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value),
+    };
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+    for c in value.strip_prefix('P')?.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            unit => {
+                let amount: i64 = number.parse().ok()?;
+                number.clear();
+                duration += match unit {
+                    'W' => Duration::weeks(amount),
+                    'D' => Duration::days(amount),
+                    'H' => Duration::hours(amount),
+                    'M' => Duration::minutes(amount),
+                    'S' => Duration::seconds(amount),
+                    _ => return None,
+                };
+            }
+        }
+    }
+    Some(if negative { -duration } else { duration })
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => text.push('\n'),
+            Some(escaped) => text.push(escaped),
+            None => {}
+        }
+    }
+    text
+}
+
+/// Parse the VEVENTs of an iCalendar object; floating times and dates are in `default_tz`.
+pub fn parse_calendar_data(data: &str, default_tz: Tz) -> Result<Vec<CaldavEvent>, CaldavError> {
+    // Unfold continuation lines, which start with a space or tab
+    let mut lines: Vec<String> = Vec::new();
+    for line in data.split('\n').map(|line| line.trim_end_matches('\r')) {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut properties: Option<Vec<ContentLine>> = None;
+    // Depth of components nested in the VEVENT, e.g. VALARM
+    let mut nested = 0;
+    for line in &lines {
+        let Some(content_line) = ContentLine::parse(line) else {
+            continue;
+        };
+        match (content_line.name.as_str(), content_line.value) {
+            ("BEGIN", "VEVENT") => properties = Some(Vec::new()),
+            ("END", "VEVENT") => {
+                if let Some(properties) = properties.take() {
+                    events.push(event_from_properties(properties, default_tz)?);
+                }
+            }
+            ("BEGIN", _) if properties.is_some() => nested += 1,
+            ("END", _) if properties.is_some() => nested -= 1,
+            _ => {
+                if let Some(properties) = properties.as_mut().filter(|_| nested == 0) {
+                    properties.push(content_line);
+                }
+            }
+        }
+    }
+    Ok(events)
+}
+
+fn event_from_properties(
+    properties: Vec<ContentLine>,
+    default_tz: Tz,
+) -> Result<CaldavEvent, CaldavError> {
+    let mut uid = None;
+    let mut start = None;
+    let mut end = None;
+    let mut duration = None;
+    let mut event = CaldavEvent {
+        uid: String::new(),
+        start: DateTime::<Utc>::MIN_UTC,
+        end: DateTime::<Utc>::MIN_UTC,
+        summary: String::new(),
+        description: None,
+        status: "confirmed".to_string(),
+        created: None,
+        updated: None,
+        attendees: Vec::new(),
+        transparent: false,
+        metadata: HashMap::new(),
+    };
+    for property in properties {
+        match property.name.as_str() {
+            "UID" => uid = Some(property.value.to_string()),
+            "DTSTART" => start = Some(property.time(default_tz)?),
+            "DTEND" => end = Some(property.time(default_tz)?.0),
+            "DURATION" => duration = parse_duration(property.value),
+            "SUMMARY" => event.summary = unescape_text(property.value),
+            "DESCRIPTION" => event.description = Some(unescape_text(property.value)),
+            "STATUS" => event.status = property.value.to_ascii_lowercase(),
+            "TRANSP" => event.transparent = property.value.eq_ignore_ascii_case("TRANSPARENT"),
+            "CREATED" => event.created = property.time(default_tz).ok().map(|time| time.0),
+            "LAST-MODIFIED" => event.updated = property.time(default_tz).ok().map(|time| time.0),
+            "ATTENDEE" => {
+                let address = property.value;
+                let email = address
+                    .strip_prefix("mailto:")
+                    .or_else(|| address.strip_prefix("MAILTO:"))
+                    .unwrap_or(address);
+                event.attendees.push(email.to_string());
+            }
+            name => {
+                if let Some(key) = name.strip_prefix(METADATA_PROPERTY_PREFIX) {
+                    event.metadata.insert(
+                        key.to_ascii_lowercase().replace('-', "_"),
+                        unescape_text(property.value),
+                    );
+                }
+            }
+        }
+    }
+
+    event.uid = uid.ok_or_else(|| CaldavError::InvalidData("VEVENT without UID".to_string()))?;
+    let (start, all_day) = start
+        .ok_or_else(|| CaldavError::InvalidData(format!("VEVENT {} without DTSTART", event.uid)))?;
+    event.start = start;
+    // Without DTEND or DURATION, a date lasts one day and a date-time no time at all
+    event.end = end
+        .or_else(|| duration.map(|duration| start + duration))
+        .unwrap_or(if all_day {
+            start + Duration::days(1)
+        } else {
+            start
+        });
+    Ok(event)
+}
+
+/// Render an event as the iCalendar object stored in its resource.
+pub fn render_calendar_object(event: &CaldavEvent, now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODUCT_ID),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", event.uid),
+        format!("DTSTAMP:{}", caldav_time(now)),
+        format!("DTSTART:{}", caldav_time(event.start)),
+        format!("DTEND:{}", caldav_time(event.end)),
+        format!("SUMMARY:{}", escape_text(&event.summary)),
+        format!("STATUS:{}", event.status.to_ascii_uppercase()),
+    ];
+    if let Some(description) = &event.description {
+        lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+    }
+    if let Some(created) = event.created {
+        lines.push(format!("CREATED:{}", caldav_time(created)));
+    }
+    if let Some(updated) = event.updated {
+        lines.push(format!("LAST-MODIFIED:{}", caldav_time(updated)));
+    }
+    if event.transparent {
+        lines.push("TRANSP:TRANSPARENT".to_string());
+    }
+    for attendee in &event.attendees {
+        lines.push(format!("ATTENDEE;RSVP=TRUE:mailto:{}", attendee));
+    }
+    let mut metadata: Vec<_> = event.metadata.iter().collect();
+    metadata.sort();
+    for (key, value) in metadata {
+        lines.push(format!(
+            "{}{}:{}",
+            METADATA_PROPERTY_PREFIX,
+            key.to_ascii_uppercase().replace('_', "-"),
+            escape_text(value)
+        ));
+    }
+    lines.extend(["END:VEVENT".to_string(), "END:VCALENDAR".to_string()]);
+
+    lines.iter().map(|line| fold_line(line)).collect()
+}
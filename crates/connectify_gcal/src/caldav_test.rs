@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use crate::caldav::{calendar_data_elements, parse_calendar_data, render_calendar_object};
+    use chrono::{TimeZone, Utc};
+    use chrono_tz::Tz;
+    use std::collections::HashMap;
+
+    const MULTISTATUS: &str = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/remote.php/dav/calendars/anna/personal/a.ics</d:href>
+    <d:propstat>
+      <d:prop>
+        <cal:calendar-data>BEGIN:VCALENDAR&#13;
+VERSION:2.0&#13;
+BEGIN:VEVENT&#13;
+UID:a&#13;
+DTSTART;TZID=Europe/Zurich:20250515T100000&#13;
+DTEND;TZID=Europe/Zurich:20250515T110000&#13;
+SUMMARY:Lunch &amp; Learn&#13;
+BEGIN:VALARM&#13;
+TRIGGER:-PT15M&#13;
+DESCRIPTION:Reminder&#13;
+END:VALARM&#13;
+END:VEVENT&#13;
+END:VCALENDAR&#13;
+</cal:calendar-data>
+      </d:prop>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/remote.php/dav/calendars/anna/personal/b.ics</d:href>
+    <d:propstat>
+      <d:prop>
+        <cal:calendar-data><![CDATA[BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:b
+DTSTART:20250516
+STATUS:CANCELLED
+END:VEVENT
+END:VCALENDAR
+]]></cal:calendar-data>
+      </d:prop>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#;
+
+    #[test]
+    fn test_parse_multistatus_events() {
+        let time_zone = Tz::Europe__Zurich;
+        let events: Vec<_> = calendar_data_elements(MULTISTATUS)
+            .iter()
+            .flat_map(|data| parse_calendar_data(data, time_zone).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+
+        assert_eq!(events[0].uid, "a");
+        assert_eq!(
+            events[0].start,
+            Utc.with_ymd_and_hms(2025, 5, 15, 8, 0, 0).unwrap()
+        );
+        assert_eq!(
+            events[0].end,
+            Utc.with_ymd_and_hms(2025, 5, 15, 9, 0, 0).unwrap()
+        );
+        assert_eq!(events[0].summary, "Lunch & Learn");
+        // The alarm's description is not the event's
+        assert_eq!(events[0].description, None);
+        assert_eq!(events[0].status, "confirmed");
+
+        // An all-day event without DTEND lasts the whole local day
+        assert_eq!(events[1].status, "cancelled");
+        assert_eq!(
+            events[1].start,
+            Utc.with_ymd_and_hms(2025, 5, 15, 22, 0, 0).unwrap()
+        );
+        assert_eq!(
+            events[1].end,
+            Utc.with_ymd_and_hms(2025, 5, 16, 22, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_render_and_parse_round_trip() {
+        let event = parse_calendar_data(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:c\r\nDTSTART:20250515T100000Z\r\nDURATION:PT1H30M\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+            Tz::UTC,
+        )
+        .unwrap()
+        .remove(0);
+        assert_eq!(
+            event.end,
+            Utc.with_ymd_and_hms(2025, 5, 15, 11, 30, 0).unwrap()
+        );
+
+        let event = crate::caldav::CaldavEvent {
+            summary: "Consultation; follow-up, part 2".to_string(),
+            description: Some("Line one\nLine two ".repeat(10)),
+            attendees: vec!["client@example.com".to_string()],
+            metadata: HashMap::from([
+                ("payment_id".to_string(), "pi_123".to_string()),
+                ("payment_amount".to_string(), "7500".to_string()),
+            ]),
+            created: Some(Utc.with_ymd_and_hms(2025, 5, 1, 9, 0, 0).unwrap()),
+            ..event
+        };
+        let rendered = render_calendar_object(&event, Utc::now());
+        assert!(rendered.contains("X-CONNECTIFY-PAYMENT-ID:pi_123\r\n"));
+        assert!(rendered.lines().all(|line| line.len() <= 76));
+
+        let parsed = parse_calendar_data(&rendered, Tz::UTC).unwrap();
+        assert_eq!(parsed, vec![event]);
+    }
+}
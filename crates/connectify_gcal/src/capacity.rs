@@ -0,0 +1,117 @@
+// --- File: crates/connectify_gcal/src/capacity.rs ---
+//! Capacity-based group slots.
+//!
+//! A calendar configured with a `capacity` offers each slot to that many customers, e.g. the
+//! seats of a group class. Every booked seat becomes a transparent event, so the calendar's
+//! free/busy times stay unaffected, and is counted in the booking ledger of connectify_common;
+//! active holds take a seat as well. A slot overlapping a group slot at a different time is
+//! not offered, so groups don't overlap each other.
+
+use crate::logic::configured_calendars;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use connectify_common::booking_ledger::{booking_ledger, SeatBooking};
+use connectify_common::holds::slot_hold_store;
+use connectify_common::ConnectifyError;
+use connectify_config::GcalConfig;
+use std::collections::HashMap;
+
+/// The seats of each slot of a calendar, if it has a capacity of more than one.
+pub fn calendar_capacity(config: &GcalConfig, calendar_id: &str) -> Option<u32> {
+    configured_calendars(config)
+        .into_iter()
+        .find(|calendar| calendar.id == calendar_id)
+        .and_then(|calendar| calendar.capacity)
+        .filter(|capacity| *capacity > 1)
+}
+
+/// The seats taken in the group slots of a calendar.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GroupSlots {
+    capacity: u32,
+    taken: HashMap<(DateTime<Utc>, DateTime<Utc>), u32>,
+}
+
+impl GroupSlots {
+    /// Group slots with the given seats per slot and no seats taken yet.
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            capacity,
+            taken: HashMap::new(),
+        }
+    }
+
+    /// Count a taken seat of the slot from `start` to `end`.
+    pub fn take_seat(&mut self, start: DateTime<Utc>, end: DateTime<Utc>) {
+        *self.taken.entry((start, end)).or_default() += 1;
+    }
+
+    /// The free seats of the slot from `start` to `end`.
+    ///
+    /// Returns `None` if the slot overlaps a group slot at a different time, so it cannot be
+    /// booked at all.
+    pub fn remaining_seats(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Option<u32> {
+        let mut taken = 0;
+        for (&(slot_start, slot_end), &seats) in &self.taken {
+            if (slot_start, slot_end) == (start, end) {
+                taken = seats;
+            } else if slot_start < end && start < slot_end {
+                return None;
+            }
+        }
+        Some(self.capacity.saturating_sub(taken))
+    }
+}
+
+/// Load the seats taken in the group slots of a calendar from `start` to `end`.
+///
+/// Counts the seats booked in the ledger and the holds active at `now`, other than the hold
+/// `except`.
+pub async fn load_group_slots(
+    calendar_id: &str,
+    capacity: u32,
+    start: DateTime<Tz>,
+    end: DateTime<Tz>,
+    now: DateTime<Utc>,
+    except: Option<&str>,
+) -> Result<GroupSlots, ConnectifyError> {
+    let (start, end) = (start.with_timezone(&Utc), end.with_timezone(&Utc));
+    let mut slots = GroupSlots::new(capacity);
+    for seat in booking_ledger()
+        .overlapping(calendar_id, start, end)
+        .await?
+    {
+        slots.take_seat(seat.start_time, seat.end_time);
+    }
+    for hold in slot_hold_store()
+        .overlapping(calendar_id, start, end, now)
+        .await?
+    {
+        if Some(hold.id.as_str()) != except {
+            slots.take_seat(hold.start_time, hold.end_time);
+        }
+    }
+    Ok(slots)
+}
+
+/// Record the seat of a booked group slot under the ID of its calendar event.
+pub async fn record_seat(
+    calendar_id: &str,
+    event_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<(), ConnectifyError> {
+    booking_ledger()
+        .record(SeatBooking {
+            id: event_id.to_string(),
+            calendar_id: calendar_id.to_string(),
+            start_time: start,
+            end_time: end,
+        })
+        .await
+}
+
+/// Free the seat of a cancelled booking; bookings without a seat are ignored.
+pub async fn release_seat(event_id: &str) -> Result<bool, ConnectifyError> {
+    booking_ledger().release(event_id).await
+}
@@ -0,0 +1,27 @@
+#[cfg(test)]
+mod tests {
+    use crate::capacity::GroupSlots;
+    use chrono::{DateTime, TimeZone, Utc};
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 5, 15, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_remaining_seats() {
+        let mut slots = GroupSlots::new(3);
+        assert_eq!(slots.remaining_seats(at(10, 0), at(11, 0)), Some(3));
+
+        slots.take_seat(at(10, 0), at(11, 0));
+        slots.take_seat(at(10, 0), at(11, 0));
+        assert_eq!(slots.remaining_seats(at(10, 0), at(11, 0)), Some(1));
+
+        slots.take_seat(at(10, 0), at(11, 0));
+        assert_eq!(slots.remaining_seats(at(10, 0), at(11, 0)), Some(0));
+
+        // A slot overlapping the group at another time cannot be booked
+        assert_eq!(slots.remaining_seats(at(10, 30), at(11, 30)), None);
+        // Adjacent slots are unaffected
+        assert_eq!(slots.remaining_seats(at(11, 0), at(12, 0)), Some(3));
+    }
+}
@@ -2,13 +2,18 @@
 
 #![allow(dead_code)]
 #![cfg(feature = "openapi")]
+use crate::blackout::{Blackout, CreateBlackoutRequest};
+use crate::holds::{CreateHoldRequest, HoldResponse};
 use crate::logic::BookedEventsResponse;
+use crate::schedule_exceptions::{CreateScheduleExceptionRequest, ScheduleExceptionResponse};
+use connectify_common::models::SortOrder;
 use utoipa;
 use utoipa::OpenApi;
 
 use crate::logic::{
-    AvailabilityQuery, AvailableSlotsResponse, BookSlotRequest, BookedEvent, BookedEventsQuery,
-    BookingResponse, CancelBookingRequest, CancellationResponse,
+    AvailabilityQuery, AvailableSlotsResponse, BatchAvailabilityRequest, BatchAvailabilityResponse,
+    BatchAvailabilityResult, BookSlotRequest, BookedEvent, BookedEventsQuery, BookingResponse,
+    CancelBookingRequest, CancellationResponse, NextAvailabilityQuery, RescheduleBookingRequest,
 };
 #[utoipa::path(
     get,
@@ -16,15 +21,58 @@ use crate::logic::{
     params(
         ("start_date" = String, Query, description = "Start date in YYYY-MM-DD format", example = "2025-05-05", format="date"),
         ("end_date" = String, Query, description = "End date in YYYY-MM-DD format", example = "2025-05-24", format="date"),
-        ("duration_minutes" = i64, Query, description = "Duration in minutes", example = 60)
+        ("duration_minutes" = i64, Query, description = "Duration in minutes", example = 60),
+        ("calendar_id" = Option<String>, Query, description = "Only return slots of this calendar"),
+        ("time_zone" = Option<String>, Query, description = "IANA time zone to render slot times in (default: the configured time zone)", example = "America/New_York"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a previous response, answered with 304 if the slots are unchanged")
     ),
     responses(
-        (status = 200, description = "Available time slots", body = AvailableSlotsResponse),
-        (status = 500, description = "Internal error", body = String)
+        (status = 200, description = "Available time slots, with an ETag header", body = AvailableSlotsResponse),
+        (status = 304, description = "The slots still match the ETag sent in If-None-Match"),
+        (status = 400, description = "Invalid time_zone"),
+        (status = 500, description = "Internal error", body = String),
+        (status = 503, description = "Google Calendar is rate limiting requests, retry later", body = String)
     )
 )]
 fn doc_get_availability_handler() {}
 
+#[utoipa::path(
+    post,
+    path = "/gcal/availability/batch",
+    request_body(content = BatchAvailabilityRequest, example = json!({
+        "queries": [
+            { "start_date": "2025-05-05", "end_date": "2025-05-12", "duration_minutes": 30 },
+            { "start_date": "2025-05-05", "end_date": "2025-05-12", "duration_minutes": 60 }
+        ]
+    })),
+    responses(
+        (status = 200, description = "Available time slots of each query, in the order of the queries", body = BatchAvailabilityResponse),
+        (status = 400, description = "A query is invalid, e.g. no matching price tier"),
+        (status = 422, description = "No queries or more than 20"),
+        (status = 500, description = "Internal error", body = String),
+        (status = 503, description = "Google Calendar is rate limiting requests, retry later", body = String)
+    )
+)]
+fn doc_batch_availability_handler() {}
+
+#[utoipa::path(
+    get,
+    path = "/gcal/availability/next",
+    params(
+        ("duration_minutes" = i64, Query, description = "Duration in minutes", example = 60),
+        ("count" = Option<u32>, Query, description = "How many slots to return (default 5, at most 50)", example = 5),
+        ("calendar_id" = Option<String>, Query, description = "Only return slots of this calendar"),
+        ("time_zone" = Option<String>, Query, description = "IANA time zone to render slot times in (default: the configured time zone)", example = "America/New_York")
+    ),
+    responses(
+        (status = 200, description = "The next bookable slots, searched up to max_advance_days (default 90) ahead", body = AvailableSlotsResponse),
+        (status = 400, description = "Invalid count or time_zone, or no matching price tier"),
+        (status = 500, description = "Internal error", body = String),
+        (status = 503, description = "Google Calendar is rate limiting requests, retry later", body = String)
+    )
+)]
+fn doc_next_availability_handler() {}
+
 #[utoipa::path(
     post,
     path = "/book",
@@ -32,17 +80,23 @@ fn doc_get_availability_handler() {}
         "start_time": "2025-05-15T10:00:00Z",
         "end_time": "2025-05-15T11:00:00Z",
         "summary": "Meeting with Client",
-        "description": "Discussion about the new project requirements"
+        "description": "Discussion about the new project requirements",
+        "attendees": ["client@example.com"],
+        "create_meet_link": true,
+        "hold_id": "5f0c9a2e-7d4b-4c1e-9a51-0b6f3e2d8c41"
     })),
     responses(
         (status = 200, description = "Booking result", body = BookingResponse,
          example = json!({
              "success": true,
              "event_id": "abc123xyz456",
-             "message": "Appointment booked successfully."
+             "message": "Appointment booked successfully.",
+             "meet_link": "https://meet.google.com/abc-defg-hij"
          })
         ),
-        (status = 409, description = "Slot already booked",
+        (status = 400, description = "Slot is too soon or too far ahead, or unknown calendar_id"),
+        (status = 422, description = "Validation failed, e.g. end_time before start_time"),
+        (status = 409, description = "Slot already booked or held for another booking",
          example = json!({
              "success": false,
              "event_id": null,
@@ -60,6 +114,87 @@ fn doc_get_availability_handler() {}
 )]
 fn doc_book_slot_handler() {}
 
+#[utoipa::path(
+    patch,
+    path = "/gcal/bookings/{event_id}",
+    params(
+        ("event_id" = String, Path, description = "The ID of the event to reschedule")
+    ),
+    request_body(content = RescheduleBookingRequest, example = json!({
+        "start_time": "2025-05-16T14:00:00Z",
+        "end_time": "2025-05-16T15:00:00Z",
+        "notify_attendees": true
+    })),
+    responses(
+        (status = 200, description = "Reschedule result", body = BookingResponse,
+         example = json!({
+             "success": true,
+             "event_id": "abc123xyz456",
+             "message": "Appointment rescheduled successfully."
+         })
+        ),
+        (status = 400, description = "New slot is too soon, too far ahead or invalid"),
+        (status = 404, description = "Event not found"),
+        (status = 409, description = "New slot overlaps another booking"),
+        (status = 422, description = "Validation failed, e.g. end_time before start_time"),
+        (status = 500, description = "Rescheduling failed")
+    )
+)]
+fn doc_reschedule_booking_handler() {}
+
+#[utoipa::path(
+    get,
+    path = "/gcal/bookings/{event_id}/ics",
+    params(
+        ("event_id" = String, Path, description = "The ID of the booked event")
+    ),
+    responses(
+        (status = 200, description = "The booking as an iCalendar file with a reminder 30 minutes before", content_type = "text/calendar", body = String),
+        (status = 404, description = "Event not found"),
+        (status = 500, description = "Export failed")
+    )
+)]
+fn doc_ics_export_handler() {}
+
+#[utoipa::path(
+    post,
+    path = "/gcal/holds",
+    request_body(content = CreateHoldRequest, example = json!({
+        "start_time": "2025-05-15T10:00:00Z",
+        "end_time": "2025-05-15T11:00:00Z",
+        "reference": "cs_test_a1b2c3"
+    })),
+    responses(
+        (status = 201, description = "Slot held until expires_at", body = HoldResponse,
+         example = json!({
+             "hold_id": "5f0c9a2e-7d4b-4c1e-9a51-0b6f3e2d8c41",
+             "calendar_id": "primary",
+             "start_time": "2025-05-15T10:00:00+00:00",
+             "end_time": "2025-05-15T11:00:00+00:00",
+             "expires_at": "2025-05-14T08:15:00+00:00"
+         })
+        ),
+        (status = 400, description = "Slot is too soon or too far ahead, or unknown calendar_id"),
+        (status = 409, description = "Slot already booked or held"),
+        (status = 422, description = "Validation failed, e.g. end_time before start_time"),
+        (status = 500, description = "Holding the slot failed")
+    )
+)]
+fn doc_create_hold_handler() {}
+
+#[utoipa::path(
+    delete,
+    path = "/gcal/holds/{hold_id}",
+    params(
+        ("hold_id" = String, Path, description = "The ID of the hold to release")
+    ),
+    responses(
+        (status = 204, description = "Hold released, the slot is available again"),
+        (status = 404, description = "Hold not found")
+    )
+)]
+fn doc_delete_hold_handler() {}
+
 #[utoipa::path(
     delete,
     path = "admin/delete/{event_id}",
@@ -96,26 +231,37 @@ fn doc_cancel_booking_handler() {}
     params(
         ("start_date" = String, Query, description = "Start date in YYYY-MM-DD format", example = "2025-05-15", format="date"),
         ("end_date" = String, Query, description = "End date in YYYY-MM-DD format", example = "2025-05-20", format="date"),
-        ("include_cancelled" = bool, Query, description = "Whether to include cancelled events", example = false)
+        ("include_cancelled" = bool, Query, description = "Whether to include cancelled events", example = false),
+        ("time_zone" = Option<String>, Query, description = "IANA time zone to render event times in", example = "Europe/Zurich"),
+        ("limit" = Option<u32>, Query, description = "Events per page (1-100, default 25); all events are returned if neither limit nor cursor is set", example = 25),
+        ("cursor" = Option<String>, Query, description = "The next_cursor of the previous page"),
+        ("order" = Option<SortOrder>, Query, description = "Only `asc`, events are listed by start time"),
+        ("tag" = Option<String>, Query, description = "Only events with this tag, set from the price tier or fulfillment type", example = "consultation")
     ),
     responses(
-        (status = 200, description = "List of booked events", body = BookedEventsResponse,
+        (status = 200, description = "A page of booked events", body = BookedEventsResponse,
          example = json!({
-             "events": [
+             "items": [
                  {
                      "event_id": "abc123xyz456",
                      "summary": "Meeting with Client",
                      "description": "Discussion about the new project requirements",
                      "start_time": "2025-05-15T10:00:00Z",
                      "end_time": "2025-05-15T11:00:00Z",
+                     "start_time_utc": "2025-05-15T10:00:00+00:00",
+                     "end_time_utc": "2025-05-15T11:00:00+00:00",
                      "status": "confirmed",
                      "created": "2025-05-10T09:00:00Z",
-                     "updated": "2025-05-10T09:00:00Z"
+                     "updated": "2025-05-10T09:00:00Z",
+                     "color_id": "9",
+                     "tags": ["consultation"]
                  }
-             ]
+             ],
+             "next_cursor": "CigKGjRv...",
+             "has_more": true
          })
         ),
-        (status = 400, description = "Invalid date format",
+        (status = 400, description = "Invalid date format, order or cursor",
          example = json!("Invalid start_date format (YYYY-MM-DD)")
         ),
         (status = 500, description = "Failed to fetch events",
@@ -155,26 +301,188 @@ fn doc_get_booked_events_handler() {}
 )]
 fn doc_mark_booking_cancelled_handler() {}
 
+#[utoipa::path(
+    post,
+    path = "/gcal/notifications",
+    params(
+        ("X-Goog-Channel-Token" = String, Header, description = "Token of the watch channel, must match GCAL_PUSH_CHANNEL_TOKEN"),
+        ("X-Goog-Resource-State" = String, Header, description = "\"sync\" for a new channel, \"exists\" for changes"),
+        ("X-Goog-Resource-URI" = Option<String>, Header, description = "The changed calendar's events resource")
+    ),
+    responses(
+        (status = 200, description = "Notification processed, cached availability dropped"),
+        (status = 401, description = "Invalid channel token"),
+        (status = 500, description = "GCAL_PUSH_CHANNEL_TOKEN not configured")
+    )
+)]
+fn doc_push_notification_handler() {}
+
+#[utoipa::path(
+    get,
+    path = "/admin/gcal/oauth/connect",
+    responses(
+        (status = 303, description = "Redirect to Google's consent screen"),
+        (status = 404, description = "OAuth is not configured"),
+        (status = 500, description = "GCAL_OAUTH_CLIENT_SECRET not configured")
+    )
+)]
+fn doc_oauth_connect_handler() {}
+
+#[utoipa::path(
+    get,
+    path = "/gcal/oauth/callback",
+    params(
+        ("code" = Option<String>, Query, description = "Authorization code granted by the user"),
+        ("state" = Option<String>, Query, description = "State of the consent request"),
+        ("error" = Option<String>, Query, description = "Set if the user denied access")
+    ),
+    responses(
+        (status = 200, description = "Calendar connected, refresh token stored"),
+        (status = 400, description = "Invalid or expired state, access denied or no refresh token returned"),
+        (status = 404, description = "OAuth is not configured"),
+        (status = 502, description = "Google rejected the authorization code")
+    )
+)]
+fn doc_oauth_callback_handler() {}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/gcal/oauth",
+    responses(
+        (status = 204, description = "Refresh token removed"),
+        (status = 404, description = "OAuth is not configured or no calendar is connected")
+    )
+)]
+fn doc_oauth_disconnect_handler() {}
+
+#[utoipa::path(
+    get,
+    path = "/admin/gcal/blackouts",
+    responses(
+        (status = 200, description = "Blackout periods, sorted by start date", body = [Blackout])
+    )
+)]
+fn doc_list_blackouts_handler() {}
+
+#[utoipa::path(
+    post,
+    path = "/admin/gcal/blackouts",
+    request_body(content = CreateBlackoutRequest, example = json!({
+        "start_date": "2025-12-24",
+        "end_date": "2025-12-26",
+        "reason": "Christmas"
+    })),
+    responses(
+        (status = 201, description = "Blackout period added", body = Blackout),
+        (status = 422, description = "Validation failed, e.g. end_date before start_date")
+    )
+)]
+fn doc_create_blackout_handler() {}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/gcal/blackouts/{blackout_id}",
+    params(
+        ("blackout_id" = String, Path, description = "The ID of the blackout period to remove")
+    ),
+    responses(
+        (status = 204, description = "Blackout period removed"),
+        (status = 404, description = "Blackout period not found")
+    )
+)]
+fn doc_delete_blackout_handler() {}
+
+#[utoipa::path(
+    get,
+    path = "/admin/gcal/schedule-exceptions",
+    params(
+        ("from" = Option<String>, Query, description = "First date in YYYY-MM-DD format (default: today)", format = "date"),
+        ("to" = Option<String>, Query, description = "Last date in YYYY-MM-DD format (default: a year after from)", format = "date")
+    ),
+    responses(
+        (status = 200, description = "Schedule exceptions, sorted by date and start time", body = [ScheduleExceptionResponse]),
+        (status = 400, description = "Invalid date")
+    )
+)]
+fn doc_list_schedule_exceptions_handler() {}
+
+#[utoipa::path(
+    post,
+    path = "/admin/gcal/schedule-exceptions",
+    request_body(content = CreateScheduleExceptionRequest, example = json!({
+        "date": "2025-12-24",
+        "kind": "closed",
+        "start_time": "15:00",
+        "reason": "Christmas Eve"
+    })),
+    responses(
+        (status = 201, description = "Schedule exception added", body = ScheduleExceptionResponse),
+        (status = 422, description = "Validation failed, e.g. extra hours without times")
+    )
+)]
+fn doc_create_schedule_exception_handler() {}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/gcal/schedule-exceptions/{exception_id}",
+    params(
+        ("exception_id" = String, Path, description = "The ID of the schedule exception to remove")
+    ),
+    responses(
+        (status = 204, description = "Schedule exception removed"),
+        (status = 404, description = "Schedule exception not found")
+    )
+)]
+fn doc_delete_schedule_exception_handler() {}
+
 #[derive(OpenApi)]
 #[openapi(
     paths(
         doc_get_availability_handler,
+        doc_batch_availability_handler,
+        doc_next_availability_handler,
         doc_book_slot_handler,
+        doc_reschedule_booking_handler,
+        doc_ics_export_handler,
+        doc_create_hold_handler,
+        doc_delete_hold_handler,
         doc_cancel_booking_handler,
         doc_get_booked_events_handler,
-        doc_mark_booking_cancelled_handler
+        doc_mark_booking_cancelled_handler,
+        doc_push_notification_handler,
+        doc_oauth_connect_handler,
+        doc_oauth_callback_handler,
+        doc_oauth_disconnect_handler,
+        doc_list_blackouts_handler,
+        doc_create_blackout_handler,
+        doc_delete_blackout_handler,
+        doc_list_schedule_exceptions_handler,
+        doc_create_schedule_exception_handler,
+        doc_delete_schedule_exception_handler
     ),
     components(
         schemas(
             AvailabilityQuery,
             AvailableSlotsResponse,
+            BatchAvailabilityRequest,
+            BatchAvailabilityResult,
+            BatchAvailabilityResponse,
+            NextAvailabilityQuery,
             BookSlotRequest,
             BookingResponse,
+            RescheduleBookingRequest,
             CancelBookingRequest,
             CancellationResponse,
             BookedEventsQuery,
             BookedEvent,
-            BookedEventsResponse
+            BookedEventsResponse,
+            SortOrder,
+            Blackout,
+            CreateBlackoutRequest,
+            CreateScheduleExceptionRequest,
+            ScheduleExceptionResponse,
+            CreateHoldRequest,
+            HoldResponse
         )
     ),
     tags(
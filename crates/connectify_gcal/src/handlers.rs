@@ -1,30 +1,77 @@
 // File: crates/connectify_gcal/src/handlers.rs
 // use google_calendar3::api::Event;
+use crate::assignment::{
+    booking_calendar_id, candidate_calendars, move_assignment, record_assignment,
+};
+use crate::blackout::{get_holiday_dates, Blackout, BlackoutStore, CreateBlackoutRequest};
+use crate::capacity::{calendar_capacity, load_group_slots, record_seat, release_seat, GroupSlots};
+use crate::holds::{held_periods, hold_ttl, CreateHoldRequest, HoldResponse};
+use crate::ics::{render_ics, IcsEvent};
 use crate::logic::{
-    calculate_available_slots, create_calendar_event, delete_calendar_event, get_booked_events,
-    mark_event_cancelled, AvailabilityQuery, AvailableSlotsResponse, BookSlotRequest,
-    BookedEventsQuery, BookedEventsResponse, BookingResponse, CancelBookingRequest,
-    CancellationResponse, GcalError, PricedSlot,
+    busy_times_cache_ttl, calculate_combined_available_slots, calendar_id_from_resource_uri,
+    configured_calendars, create_calendar_event, delete_calendar_event, get_booked_events,
+    get_calendar_event, invalidate_busy_times_cache, mark_event_cancelled, parse_client_time_zone,
+    price_tier_for_duration, reschedule_calendar_event, validate_booked_events_page,
+    validate_next_slots_count, AppointmentConfig, AvailabilityQuery, AvailableSlotsResponse,
+    BatchAvailabilityRequest, BatchAvailabilityResponse, BatchAvailabilityResult, BookSlotRequest,
+    BookedEventsQuery, BookedEventsResponse, BookingResponse, BookingWindow, CalendarBusyTimes,
+    CancelBookingRequest, CancellationResponse, EventStyle, GcalError, NextAvailabilityQuery,
+    PricedSlot, RescheduleBookingRequest, SlotBuffers, WorkingHoursConfig, NEXT_SLOTS_SEARCH_DAYS,
+};
+use crate::mirror;
+use crate::oauth::{self, OAuthCallbackQuery, OAuthError};
+use crate::schedule_exceptions::{
+    apply_schedule_exceptions, parse_date, parse_time, CreateScheduleExceptionRequest,
+    ScheduleExceptionResponse, ScheduleExceptionsQuery,
 };
+use crate::service::GcalServiceError;
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
-    response::Json,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Redirect, Response},
 };
-use chrono::{Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Timelike, Utc, Weekday};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc};
+#[cfg(test)]
+use chrono::{NaiveTime, Weekday};
 use chrono_tz::Tz;
-use connectify_config::{AppConfig, PriceTier}; // Use the unified config
+use connectify_common::audit::{self, AuditActor, AuditEvent};
+use connectify_common::booking_ledger::booking_ledger;
+use connectify_common::clock::SharedClock;
+use connectify_common::events::{self, BookingCancelled, BookingCreated, BookingRescheduled};
+use connectify_common::holds::{slot_hold_store, SlotHold};
+use connectify_common::http::etag::json_with_etag;
+use connectify_common::lock::{
+    acquire_with_wait, distributed_lock, release_quietly, DistributedLock, LockLease,
+};
+use connectify_common::models::CursorQuery;
+use connectify_common::oauth_tokens::oauth_token_store;
+use connectify_common::schedule_exceptions::{schedule_exception_store, ScheduleException};
+use connectify_common::validation::ValidatedJson;
+use connectify_common::webhook::constant_time_eq;
+use connectify_config::{AppConfig, GcalCalendar, GcalConfig, GcalOAuthConfig, PriceTier}; // Use the unified config
+use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
-use tracing::info;
+use tracing::{error, info, warn};
 
 use crate::auth::HubType; // Import the Hub type alias
 
+/// How long a booking may hold the calendar's booking lock.
+const BOOKING_LOCK_TTL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// How long a booking waits for another booking of the same calendar to finish.
+const BOOKING_LOCK_WAIT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Environment variable holding the token Google echoes back in push notifications.
+pub const PUSH_CHANNEL_TOKEN_ENV: &str = "GCAL_PUSH_CHANNEL_TOKEN";
+
 // Define shared state needed by GCal handlers
 #[derive(Clone)]
 pub struct GcalState {
     pub config: Arc<AppConfig>,
     pub calendar_hub: Arc<HubType>, // Share the authenticated Calendar client
+    pub clock: SharedClock,         // Source of "now" for slot calculation
+    pub blackouts: Arc<BlackoutStore>, // Blackout periods, manageable at runtime
 }
 
 /// Handler to get available time slots.
@@ -35,6 +82,7 @@ pub struct GcalState {
     params(AvailabilityQuery),
     responses(
         (status = 200, description = "Available time slots with pricing", body = AvailableSlotsResponse),
+        (status = 304, description = "Not modified since the ETag given in If-None-Match"),
         (status = 400, description = "Bad request (e.g., invalid date format, no matching price tier)"),
         (status = 500, description = "Internal error")
     ),
@@ -42,8 +90,190 @@ pub struct GcalState {
 ))]
 pub async fn get_availability_handler(
     State(state): State<Arc<GcalState>>,
+    headers: HeaderMap,
     Query(query): Query<AvailabilityQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let gcal_config = availability_config(&state)?;
+    let request = parse_availability_query(&state, gcal_config, &query)?;
+
+    // Polling frontends get a 304 without a body while the slots stay the same
+    let response = AvailableSlotsResponse {
+        time_zone: request.display_time_zone.name().to_string(),
+        slots: available_slots(&state, gcal_config, &request).await?,
+    };
+    Ok(json_with_etag(&headers, &response))
+}
+
+/// Days of availability calculated at once while searching the next slots.
+const NEXT_SLOTS_CHUNK_DAYS: i64 = 7;
+
+/// Handler to get the next bookable slots, e.g. to show the earliest appointment.
+///
+/// Scans forward from today a week at a time until `count` slots are found or the booking
+/// window ends, `max_advance_days` ahead or [`NEXT_SLOTS_SEARCH_DAYS`] if not configured.
+#[axum::debug_handler]
+pub async fn next_availability_handler(
+    State(state): State<Arc<GcalState>>,
+    Query(query): Query<NextAvailabilityQuery>,
 ) -> Result<Json<AvailableSlotsResponse>, (StatusCode, String)> {
+    let gcal_config = availability_config(&state)?;
+    let count = validate_next_slots_count(query.count)
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    let time_zone = Tz::from_str(gcal_config.time_zone.as_deref().unwrap_or("Europe/Zurich"))
+        .unwrap_or(Tz::Europe__Zurich);
+    let display_time_zone = client_time_zone(query.time_zone.as_deref())?.unwrap_or(time_zone);
+
+    let today = state.clock.now().with_timezone(&time_zone).date_naive();
+    let search_days = gcal_config
+        .max_advance_days
+        .unwrap_or(NEXT_SLOTS_SEARCH_DAYS)
+        .max(0);
+    let last_day = today + Duration::days(search_days);
+    let mut slots = Vec::new();
+    let mut chunk_start = today;
+    while slots.len() < count && chunk_start <= last_day {
+        let chunk_end = (chunk_start + Duration::days(NEXT_SLOTS_CHUNK_DAYS - 1)).min(last_day);
+        let chunk_query = AvailabilityQuery {
+            start_date: chunk_start.format("%Y-%m-%d").to_string(),
+            end_date: chunk_end.format("%Y-%m-%d").to_string(),
+            duration_minutes: query.duration_minutes,
+            calendar_id: query.calendar_id.clone(),
+            time_zone: query.time_zone.clone(),
+            currency: query.currency.clone(),
+        };
+        let request = parse_availability_query(&state, gcal_config, &chunk_query)?;
+        slots.extend(available_slots(&state, gcal_config, &request).await?);
+        chunk_start = chunk_end + Duration::days(1);
+    }
+    slots.truncate(count);
+
+    Ok(Json(AvailableSlotsResponse {
+        time_zone: display_time_zone.name().to_string(),
+        slots,
+    }))
+}
+
+/// The priced slots of an availability query, fetching what the calendars have booked.
+async fn available_slots(
+    state: &GcalState,
+    gcal_config: &GcalConfig,
+    request: &AvailabilityRequest<'_>,
+) -> Result<Vec<PricedSlot>, (StatusCode, String)> {
+    // --- Fetch Busy Times of every calendar ---
+    let calendar_ids: Vec<&str> = request
+        .calendars
+        .iter()
+        .map(|calendar| calendar.id.as_str())
+        .collect();
+    let occupancy = fetch_occupancy(
+        state,
+        gcal_config,
+        &calendar_ids,
+        request.query_start_tz,
+        request.query_end_tz,
+    )
+    .await?;
+    let working_hours = availability_working_hours(
+        state,
+        gcal_config,
+        request.query_start_tz,
+        request.query_end_tz,
+    )
+    .await;
+    Ok(priced_slots(request, &occupancy, &working_hours))
+}
+
+/// Handler to get available time slots for several durations or date ranges at once.
+///
+/// The free/busy times of each calendar are fetched once for the combined date range.
+#[axum::debug_handler]
+pub async fn batch_availability_handler(
+    State(state): State<Arc<GcalState>>,
+    ValidatedJson(payload): ValidatedJson<BatchAvailabilityRequest>,
+) -> Result<Json<BatchAvailabilityResponse>, (StatusCode, String)> {
+    let gcal_config = availability_config(&state)?;
+    let requests = payload
+        .queries
+        .iter()
+        .enumerate()
+        .map(|(index, query)| {
+            parse_availability_query(&state, gcal_config, query)
+                .map_err(|(status, message)| (status, format!("queries[{}]: {}", index, message)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Fetch everything needed by any of the queries in one go
+    let (Some(range_start), Some(range_end)) = (
+        requests.iter().map(|request| request.query_start_tz).min(),
+        requests.iter().map(|request| request.query_end_tz).max(),
+    ) else {
+        return Ok(Json(BatchAvailabilityResponse {
+            results: Vec::new(),
+        }));
+    };
+    let mut calendar_ids: Vec<&str> = requests
+        .iter()
+        .flat_map(|request| {
+            request
+                .calendars
+                .iter()
+                .map(|calendar| calendar.id.as_str())
+        })
+        .collect();
+    calendar_ids.sort_unstable();
+    calendar_ids.dedup();
+    let occupancy =
+        fetch_occupancy(&state, gcal_config, &calendar_ids, range_start, range_end).await?;
+    let working_hours =
+        availability_working_hours(&state, gcal_config, range_start, range_end).await;
+
+    let results = payload
+        .queries
+        .into_iter()
+        .zip(&requests)
+        .map(|(query, request)| BatchAvailabilityResult {
+            slots: priced_slots(request, &occupancy, &working_hours),
+            start_date: query.start_date,
+            end_date: query.end_date,
+            duration_minutes: query.duration_minutes,
+            calendar_id: query.calendar_id,
+            time_zone: request.display_time_zone.name().to_string(),
+        })
+        .collect();
+    Ok(Json(BatchAvailabilityResponse { results }))
+}
+
+/// Busy periods keyed by calendar id.
+type BusyPeriodsByCalendar = HashMap<String, Vec<(DateTime<Tz>, DateTime<Tz>)>>;
+
+/// What is booked in the queried calendars.
+struct CalendarOccupancy {
+    busy_periods: BusyPeriodsByCalendar,
+    /// Seats taken in the group slots of calendars with a capacity, keyed by calendar id
+    group_slots: HashMap<String, GroupSlots>,
+}
+
+/// An availability query, checked against the configuration.
+struct AvailabilityRequest<'a> {
+    calendars: Vec<GcalCalendar>,
+    price_tier: &'a PriceTier,
+    /// Free time kept around appointments of the price tier
+    buffers: SlotBuffers,
+    /// The price of the price tier in `currency`
+    price: i64,
+    currency: String,
+    time_zone: Tz,
+    /// The time zone slot times are rendered in
+    display_time_zone: Tz,
+    duration_minutes: i64,
+    query_start_tz: DateTime<Tz>,
+    query_end_tz: DateTime<Tz>,
+    /// The query start, or the earliest bookable time if later
+    effective_start_tz: DateTime<Tz>,
+}
+
+/// The GCal config, if availability can be queried.
+fn availability_config(state: &GcalState) -> Result<&GcalConfig, (StatusCode, String)> {
     // Ensure GCal feature is enabled via runtime config
     if !state.config.use_gcal {
         return Err((
@@ -52,36 +282,49 @@ pub async fn get_availability_handler(
         ));
     }
 
-    let gcal_config = state.config.gcal.as_ref().ok_or_else(|| {
+    state.config.gcal.as_ref().ok_or_else(|| {
         info!("GCal configuration missing in AppConfig.");
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             "Server configuration error: GCal config missing.".to_string(),
         )
-    })?;
-    let calendar_id = gcal_config.calendar_id.as_ref().ok_or_else(|| {
+    })
+}
+
+fn parse_availability_query<'a>(
+    state: &'a GcalState,
+    gcal_config: &GcalConfig,
+    query: &AvailabilityQuery,
+) -> Result<AvailabilityRequest<'a>, (StatusCode, String)> {
+    let mut calendars = configured_calendars(gcal_config);
+    if calendars.is_empty() {
         info!("GCal calendar_id missing in GcalConfig.");
-        (
+        return Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             "Server configuration error: GCal calendar ID missing.".to_string(),
-        )
-    })?;
+        ));
+    }
+    if let Some(calendar_id) = query.calendar_id.as_deref() {
+        calendars.retain(|calendar| calendar.id == calendar_id);
+        if calendars.is_empty() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Unknown calendar_id: {}", calendar_id),
+            ));
+        }
+    }
 
     // --- Find Price Tier ---
-    // Price tiers are assumed to be in StripeConfig for now.
-    // This could be moved to a more generic "ServicePricingConfig" if needed.
-    let stripe_config = state.config.stripe.as_ref().ok_or_else(|| {
-        info!("Stripe configuration (for price tiers) missing in AppConfig.");
+    let pricing_config = state.config.pricing.as_ref().ok_or_else(|| {
+        info!("Pricing configuration missing in AppConfig.");
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             "Pricing configuration error on server.".to_string(),
         )
     })?;
 
-    let price_tier: &PriceTier = stripe_config
-        .price_tiers
-        .iter()
-        .find(|tier| tier.duration_minutes == query.duration_minutes)
+    let price_tier: &PriceTier = pricing_config
+        .tier_for_duration(query.duration_minutes)
         .ok_or_else(|| {
             let err_msg = format!(
                 "No service offered for {} minute duration.",
@@ -90,6 +333,31 @@ pub async fn get_availability_handler(
             info!("{}", err_msg);
             (StatusCode::BAD_REQUEST, err_msg)
         })?;
+    let default_currency = pricing_config.default_currency.as_deref();
+    let (price, currency) = match query.currency.as_deref() {
+        Some(requested) => {
+            let price = price_tier
+                .amount_in(requested, default_currency)
+                .ok_or_else(|| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        format!(
+                            "No price in {} for {} minute duration, available: {}.",
+                            requested.to_uppercase(),
+                            query.duration_minutes,
+                            price_tier.currencies(default_currency).join(", ")
+                        ),
+                    )
+                })?;
+            (price, requested.to_uppercase())
+        }
+        None => (
+            price_tier.unit_amount,
+            price_tier
+                .base_currency(default_currency)
+                .unwrap_or_else(|| "USD".to_string()),
+        ),
+    };
 
     // --- Parse Dates & Validate ---
     let start_naive_date =
@@ -125,100 +393,184 @@ pub async fn get_availability_handler(
         .clone()
         .unwrap_or("Zurich".to_string());
     let time_zone = Tz::from_str(time_zone).unwrap_or(Tz::Europe__Zurich);
-    // Get current time in the configured timezone
-    let now_tz = Utc::now().with_timezone(&time_zone);
     let query_start_tz = time_zone
         .from_local_datetime(&start_naive_datetime)
         .unwrap();
     let query_end_tz = time_zone.from_local_datetime(&end_naive_datetime).unwrap();
 
-    let preparation_time = gcal_config.preparation_time_minutes.unwrap_or(120);
-    // don’t allow slots before now + prep time
-    let effective_start_tz = {
-        // now in TZ already computed as `now_tz`
-        let min_start = now_tz + Duration::minutes(preparation_time);
-        if query_start_tz < min_start {
-            min_start
-        } else {
-            query_start_tz
-        }
+    // don’t allow slots before now + prep time, or further ahead than bookings are taken
+    let window = BookingWindow::from_config(gcal_config, state.clock.now());
+    let effective_start_tz = query_start_tz.max(window.earliest.with_timezone(&time_zone));
+    let query_end_tz = match window.latest {
+        Some(latest) => query_end_tz.min(latest.with_timezone(&time_zone).max(effective_start_tz)),
+        None => query_end_tz,
     };
-    let appointment_duration_chrono = Duration::minutes(query.duration_minutes);
-    if appointment_duration_chrono <= Duration::zero() {
+    if query.duration_minutes <= 0 {
         return Err((
             StatusCode::BAD_REQUEST,
             "duration_minutes must be positive".to_string(),
         ));
     }
+    // Dates and working hours stay in the configured time zone, only the rendering changes
+    let display_time_zone = client_time_zone(query.time_zone.as_deref())?.unwrap_or(time_zone);
 
-    // --- Fetch Busy Times ---
-    let busy_periods = match crate::logic::get_busy_times(
-        &state.calendar_hub,
-        calendar_id,
+    Ok(AvailabilityRequest {
+        calendars,
+        price_tier,
+        buffers: SlotBuffers::for_tier(gcal_config, Some(price_tier)),
+        price,
+        currency,
+        time_zone,
+        display_time_zone,
+        duration_minutes: query.duration_minutes,
         query_start_tz,
         query_end_tz,
-    )
-    .await
-    {
-        Ok(periods) => periods,
-        Err(e) => {
-            info!("Error fetching GCal free/busy: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to query calendar availability".to_string(),
-            ));
+        effective_start_tz,
+    })
+}
+
+/// The buffers kept around a booking from `start` to `end`, those of the price tier of its
+/// duration if there is one.
+fn booking_buffers<T: TimeZone>(
+    state: &GcalState,
+    gcal_config: &GcalConfig,
+    start: &DateTime<T>,
+    end: &DateTime<T>,
+) -> SlotBuffers {
+    let duration_minutes = (end.clone() - start.clone()).num_minutes();
+    let tier = price_tier_for_duration(&state.config, duration_minutes);
+    SlotBuffers::for_tier(gcal_config, tier)
+}
+
+/// Rejects a slot starting at `start_time` outside the booking window with 400.
+fn check_booking_window(
+    state: &GcalState,
+    gcal_config: &GcalConfig,
+    start_time: &str,
+) -> Result<(), (StatusCode, String)> {
+    let start = chrono::DateTime::parse_from_rfc3339(start_time).expect("start_time is validated");
+    BookingWindow::from_config(gcal_config, state.clock.now())
+        .check(start.with_timezone(&Utc))
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))
+}
+
+/// The status for a failed calendar call: 503 while Google rate-limits us, so clients retry
+/// later instead of treating it as a server fault, 500 otherwise.
+fn calendar_error_status(error: &GcalError) -> StatusCode {
+    if error.is_rate_limited() {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// The time zone a client asked times to be rendered in, if any.
+fn client_time_zone(time_zone: Option<&str>) -> Result<Option<Tz>, (StatusCode, String)> {
+    time_zone
+        .map(parse_client_time_zone)
+        .transpose()
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))
+}
+
+/// The busy periods of each calendar from `start` to `end`, including held slots, and the
+/// seats taken in the group slots of calendars with a capacity.
+async fn fetch_occupancy(
+    state: &GcalState,
+    gcal_config: &GcalConfig,
+    calendar_ids: &[&str],
+    start: DateTime<Tz>,
+    end: DateTime<Tz>,
+) -> Result<CalendarOccupancy, (StatusCode, String)> {
+    let cache_ttl = busy_times_cache_ttl(gcal_config);
+    let mut busy_periods_by_calendar = HashMap::with_capacity(calendar_ids.len());
+    let mut group_slots = HashMap::new();
+    for calendar_id in calendar_ids {
+        let capacity = calendar_capacity(gcal_config, calendar_id);
+        if let Some(capacity) = capacity {
+            // Holds take a seat of a group slot rather than blocking it
+            let slots =
+                load_group_slots(calendar_id, capacity, start, end, state.clock.now(), None)
+                    .await
+                    .map_err(|e| {
+                        info!("Error fetching group slot seats of {}: {}", calendar_id, e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            "Failed to query calendar availability".to_string(),
+                        )
+                    })?;
+            group_slots.insert(calendar_id.to_string(), slots);
         }
-    };
+        let busy_times = match crate::logic::get_cached_busy_times(
+            &state.calendar_hub,
+            calendar_id,
+            start,
+            end,
+            cache_ttl,
+        )
+        .await
+        {
+            Err(e) if gcal_config.mirror_events => {
+                match mirror::mirrored_busy_times(calendar_id, start, end).await {
+                    Ok(Some(periods)) => {
+                        warn!(
+                            "Error fetching GCal free/busy of {}, using the event mirror: {}",
+                            calendar_id, e
+                        );
+                        Ok(periods)
+                    }
+                    Ok(None) => Err(e),
+                    Err(mirror_error) => {
+                        warn!(
+                            "Error reading the event mirror of {}: {}",
+                            calendar_id, mirror_error
+                        );
+                        Err(e)
+                    }
+                }
+            }
+            busy_times => busy_times,
+        };
+        match busy_times {
+            Ok(mut periods) => {
+                // Held slots are busy until booked or expired
+                if capacity.is_none() {
+                    match held_periods(calendar_id, start, end, state.clock.now(), None).await {
+                        Ok(held) => periods.extend(held),
+                        Err(e) => warn!("Error fetching slot holds of {}: {}", calendar_id, e),
+                    }
+                }
+                busy_periods_by_calendar.insert(calendar_id.to_string(), periods);
+            }
+            Err(e) => {
+                info!("Error fetching GCal free/busy of {}: {}", calendar_id, e);
+                return Err((
+                    calendar_error_status(&e),
+                    "Failed to query calendar availability".to_string(),
+                ));
+            }
+        }
+    }
+    Ok(CalendarOccupancy {
+        busy_periods: busy_periods_by_calendar,
+        group_slots,
+    })
+}
 
+/// The working hours from `start` to `end` with their schedule exceptions, without blackout
+/// periods and public holidays.
+async fn availability_working_hours(
+    state: &GcalState,
+    gcal_config: &GcalConfig,
+    start: DateTime<Tz>,
+    end: DateTime<Tz>,
+) -> WorkingHoursConfig {
     // --- Calculate Slots using configuration values or defaults ---
     // For tests, use hardcoded values to avoid configuration issues
     #[cfg(test)]
-    let work_start = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
-    #[cfg(test)]
-    let work_end = NaiveTime::from_hms_opt(23, 59, 59).unwrap();
-    #[cfg(test)]
-    let working_days = vec![
-        Weekday::Mon,
-        Weekday::Tue,
-        Weekday::Wed,
-        Weekday::Thu,
-        Weekday::Fri,
-        Weekday::Sat,
-        Weekday::Sun,
-    ];
-
-    // For non-test environments, try to use configuration values
-    #[cfg(not(test))]
-    let work_start = match &gcal_config.work_start_time {
-        Some(time_str) => NaiveTime::parse_from_str(time_str, "%H:%M")
-            .unwrap_or_else(|_| NaiveTime::from_hms_opt(0, 0, 0).unwrap()),
-        None => NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-    };
-
-    #[cfg(not(test))]
-    let work_end = match &gcal_config.work_end_time {
-        Some(time_str) => NaiveTime::parse_from_str(time_str, "%H:%M")
-            .unwrap_or_else(|_| NaiveTime::from_hms_opt(23, 59, 59).unwrap()),
-        None => NaiveTime::from_hms_opt(23, 59, 59).unwrap(),
-    };
-
-    // Parse working days from config
-    #[cfg(not(test))]
-    let working_days: Vec<Weekday> = match &gcal_config.working_days {
-        Some(days) => days
-            .iter()
-            .filter_map(|day| match day.as_str() {
-                "Mon" => Some(Weekday::Mon),
-                "Tue" => Some(Weekday::Tue),
-                "Wed" => Some(Weekday::Wed),
-                "Thu" => Some(Weekday::Thu),
-                "Fri" => Some(Weekday::Fri),
-                "Sat" => Some(Weekday::Sat),
-                "Sun" => Some(Weekday::Sun),
-                _ => None,
-            })
-            .collect(),
-        None => vec![
+    let working_hours = WorkingHoursConfig::uniform(
+        NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+        NaiveTime::from_hms_opt(23, 59, 59).unwrap(),
+        &[
             Weekday::Mon,
             Weekday::Tue,
             Weekday::Wed,
@@ -227,44 +579,82 @@ pub async fn get_availability_handler(
             Weekday::Sat,
             Weekday::Sun,
         ],
+    );
+
+    // For non-test environments, use the configured (weekly) working hours
+    #[cfg(not(test))]
+    let working_hours = WorkingHoursConfig::from_config(gcal_config);
+
+    // --- Merge one-off schedule exceptions ---
+    let working_hours = match schedule_exception_store()
+        .between(start.date_naive(), end.date_naive())
+        .await
+    {
+        Ok(exceptions) => apply_schedule_exceptions(working_hours, &exceptions),
+        // The weekly hours are still the best guess
+        Err(e) => {
+            warn!("Error loading schedule exceptions: {}", e);
+            working_hours
+        }
     };
 
-    let buffer = Duration::minutes(0); // No buffer by default
-    let step = Duration::minutes(15); // Check every 15 minutes
+    // --- Exclude blackout periods and public holidays ---
+    let mut blackout_dates = state
+        .blackouts
+        .dates_between(start.date_naive(), end.date_naive());
+    for holiday_calendar in &gcal_config.holiday_calendars {
+        match get_holiday_dates(&state.calendar_hub, holiday_calendar, start, end).await {
+            Ok(dates) => blackout_dates.extend(dates),
+            // Offering a holiday is better than offering nothing
+            Err(e) => warn!("Error fetching holidays of {}: {}", holiday_calendar, e),
+        }
+    }
+    working_hours.with_blackout_dates(blackout_dates)
+}
 
-    let available_datetime_slots = calculate_available_slots(
-        effective_start_tz, // which is the
-        query_end_tz,
-        &busy_periods,
-        appointment_duration_chrono,
-        work_start,
-        work_end,
-        &working_days,
-        buffer,
-        step,
-    )
-    // Ensure all returned slots are UTC for consistency
-    .into_iter()
-    .collect::<Vec<_>>();
+/// The priced slots answering an availability query.
+fn priced_slots(
+    request: &AvailabilityRequest,
+    occupancy: &CalendarOccupancy,
+    working_hours: &WorkingHoursConfig,
+) -> Vec<PricedSlot> {
+    let step = Duration::minutes(15); // Check every 15 minutes
+    let appointment_duration_chrono = Duration::minutes(request.duration_minutes);
 
-    // Convert query start and end to local time for date comparison
-    let tz = chrono_tz::Tz::from_str(
-        &gcal_config
-            .time_zone
-            .clone()
-            .unwrap_or("Europe/Zurich".to_string()),
-    )
-    .unwrap();
+    let calendar_busy_times: Vec<CalendarBusyTimes> = request
+        .calendars
+        .iter()
+        .map(|calendar| CalendarBusyTimes {
+            calendar_id: &calendar.id,
+            busy_periods: occupancy
+                .busy_periods
+                .get(&calendar.id)
+                .map_or(&[], |periods| periods.as_slice()),
+        })
+        .collect();
+    let available_datetime_slots = calculate_combined_available_slots(
+        request.effective_start_tz,
+        request.query_end_tz,
+        &calendar_busy_times,
+        working_hours,
+        &AppointmentConfig {
+            duration: appointment_duration_chrono,
+            buffer_before: request.buffers.before,
+            buffer_time: request.buffers.after,
+            step,
+        },
+    );
 
     // Convert query start and end to local time for date comparison
-    let start_local = query_start_tz;
-    let end_local = query_end_tz;
+    let tz = request.time_zone;
+    let start_local = request.query_start_tz;
+    let end_local = request.query_end_tz;
 
     // --- Transform to PricedSlots, filtering and rounding based on local time (zero out seconds/nanos) ---
-    let priced_slots: Vec<PricedSlot> = available_datetime_slots
+    available_datetime_slots
         .iter()
-        .filter_map(|slot_start_tz| {
-            let slot_local = chrono::DateTime::parse_from_rfc3339(slot_start_tz.0.as_str())
+        .filter_map(|slot| {
+            let slot_local = chrono::DateTime::parse_from_rfc3339(slot.start_time.as_str())
                 .ok()
                 .map(|dt| dt.with_timezone(&tz))?;
 
@@ -278,9 +668,21 @@ pub async fn get_availability_handler(
                 return None;
             }
 
-            let floored_tz = rounded_local.with_timezone(&time_zone);
+            let floored_tz = rounded_local.with_timezone(&request.display_time_zone);
             let slot_end_tz = floored_tz + appointment_duration_chrono;
 
+            // Group slots are offered while they have seats left
+            let remaining_seats = match occupancy.group_slots.get(&slot.calendar_id) {
+                Some(group_slots) => match group_slots.remaining_seats(
+                    floored_tz.with_timezone(&Utc),
+                    slot_end_tz.with_timezone(&Utc),
+                ) {
+                    Some(seats) if seats > 0 => Some(seats),
+                    _ => return None,
+                },
+                None => None,
+            };
+
             tracing::debug!(
                 "🕒 Slot interpreted locally as: {} ({:?})",
                 slot_local,
@@ -290,31 +692,193 @@ pub async fn get_availability_handler(
             Some(PricedSlot {
                 start_time: floored_tz.to_rfc3339(),
                 end_time: slot_end_tz.to_rfc3339(),
-                duration_minutes: query.duration_minutes,
-                price: price_tier.unit_amount,
-                currency: price_tier.currency.clone().unwrap_or_else(|| {
-                    stripe_config
-                        .default_currency
-                        .clone()
-                        .unwrap_or_else(|| "USD".to_string())
-                }),
-                product_name: price_tier.product_name.clone(),
+                start_time_utc: floored_tz.with_timezone(&Utc).to_rfc3339(),
+                end_time_utc: slot_end_tz.with_timezone(&Utc).to_rfc3339(),
+                duration_minutes: request.duration_minutes,
+                price: request.price,
+                currency: request.currency.clone(),
+                product_name: request.price_tier.product_name.clone(),
+                calendar_id: slot.calendar_id.clone(),
+                provider_name: request
+                    .calendars
+                    .iter()
+                    .find(|calendar| calendar.id == slot.calendar_id)
+                    .and_then(|calendar| calendar.name.clone()),
+                remaining_seats,
             })
         })
-        .collect();
-
-    Ok(Json(AvailableSlotsResponse {
-        slots: priced_slots,
-    }))
+        .collect()
 }
 
 /// Handler to book a time slot.
 #[axum::debug_handler]
 pub async fn book_slot_handler(
-    State(state): State<Arc<GcalState>>,  // Extract shared GCal state
-    Json(payload): Json<BookSlotRequest>, // Extract JSON body
+    State(state): State<Arc<GcalState>>, // Extract shared GCal state
+    ValidatedJson(payload): ValidatedJson<BookSlotRequest>, // Extract and validate JSON body
 ) -> Result<Json<BookingResponse>, (StatusCode, String)> {
     // Get GCal specific config
+    let gcal_config = state.config.gcal.as_ref().expect("GCal config missing");
+    let calendar_id = resolve_calendar_id(gcal_config, payload.calendar_id.as_deref())?;
+    check_booking_window(&state, gcal_config, &payload.start_time)?;
+
+    if payload.calendar_id.is_none() {
+        let candidates = candidate_calendars(gcal_config, state.clock.now())
+            .await
+            .map_err(|e| {
+                info!("Error assigning a calendar: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to book appointment.".to_string(),
+                )
+            })?;
+        if let Some(candidates) = candidates {
+            return book_assigned_slot(&state, candidates, payload).await;
+        }
+    }
+
+    book_slot_locked(&state, &calendar_id, payload).await
+}
+
+/// Books the slot in the first of the `candidates` calendars that is free at the time, and
+/// records the assignment.
+async fn book_assigned_slot(
+    state: &GcalState,
+    candidates: Vec<String>,
+    payload: BookSlotRequest,
+) -> Result<Json<BookingResponse>, (StatusCode, String)> {
+    let mut result = Err((
+        StatusCode::CONFLICT,
+        "Requested time slot is no longer available".to_string(),
+    ));
+    for calendar_id in candidates {
+        result = book_slot_locked(state, &calendar_id, payload.clone()).await;
+        match &result {
+            Ok(Json(booking)) => {
+                if let Some(event_id) = booking.event_id.as_deref() {
+                    let parse = |time: &str| {
+                        DateTime::parse_from_rfc3339(time)
+                            .expect("times are validated")
+                            .with_timezone(&Utc)
+                    };
+                    if let Err(e) = record_assignment(
+                        &calendar_id,
+                        event_id,
+                        parse(&payload.start_time),
+                        parse(&payload.end_time),
+                        state.clock.now(),
+                    )
+                    .await
+                    {
+                        error!(
+                            "Failed to record the calendar of booking {}: {}",
+                            event_id, e
+                        );
+                    }
+                }
+                break;
+            }
+            // Taken in this calendar, try the next one
+            Err((StatusCode::CONFLICT, _)) => continue,
+            Err(_) => break,
+        }
+    }
+    result
+}
+
+/// Books the slot while holding the calendar's booking lock.
+async fn book_slot_locked(
+    state: &GcalState,
+    calendar_id: &str,
+    payload: BookSlotRequest,
+) -> Result<Json<BookingResponse>, (StatusCode, String)> {
+    // Bookings of a calendar are serialized across instances, so that two requests cannot
+    // both pass the availability check for the same slot
+    let lock = distributed_lock();
+    let lease = acquire_booking_lock(&*lock, calendar_id).await?;
+
+    let result = book_slot(state, calendar_id, payload).await;
+    release_quietly(&*lock, &lease).await;
+    result
+}
+
+/// The calendar of a booking, see [`booking_calendar_id`].
+async fn booking_calendar(
+    gcal_config: &GcalConfig,
+    event_id: &str,
+) -> Result<String, (StatusCode, String)> {
+    let calendar_id = booking_calendar_id(gcal_config, event_id)
+        .await
+        .map_err(|e| {
+            info!(
+                "Error looking up the calendar of booking {}: {}",
+                event_id, e
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to look up the booking.".to_string(),
+            )
+        })?;
+    Ok(calendar_id.expect("Calendar ID is required"))
+}
+
+/// The requested calendar if it is one of the configured calendars, else the default one.
+fn resolve_calendar_id(
+    gcal_config: &GcalConfig,
+    calendar_id: Option<&str>,
+) -> Result<String, (StatusCode, String)> {
+    match calendar_id {
+        Some(calendar_id) => {
+            if !configured_calendars(gcal_config)
+                .iter()
+                .any(|calendar| calendar.id == calendar_id)
+            {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("Unknown calendar_id: {}", calendar_id),
+                ));
+            }
+            Ok(calendar_id.to_string())
+        }
+        None => Ok(gcal_config
+            .calendar_id
+            .clone()
+            .expect("Calendar ID is required")),
+    }
+}
+
+/// Acquire the lock serializing bookings and holds of a calendar.
+async fn acquire_booking_lock(
+    lock: &dyn DistributedLock,
+    calendar_id: &str,
+) -> Result<LockLease, (StatusCode, String)> {
+    acquire_with_wait(
+        lock,
+        &format!("gcal:booking:{}", calendar_id),
+        BOOKING_LOCK_TTL,
+        BOOKING_LOCK_WAIT,
+    )
+    .await
+    .map_err(|e| {
+        info!("Error acquiring booking lock: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to check slot availability".to_string(),
+        )
+    })?
+    .ok_or_else(|| {
+        (
+            StatusCode::CONFLICT,
+            "Another booking is in progress, please retry.".to_string(),
+        )
+    })
+}
+
+/// Checks that the slot is free and books it. Callers must hold the calendar's booking lock.
+async fn book_slot(
+    state: &GcalState,
+    calendar_id: &str,
+    mut payload: BookSlotRequest,
+) -> Result<Json<BookingResponse>, (StatusCode, String)> {
     let gcal_config = state.config.gcal.as_ref().expect("GCal config missing");
     let time_zone = chrono_tz::Tz::from_str(
         gcal_config
@@ -325,41 +889,35 @@ pub async fn book_slot_handler(
     )
     .unwrap();
     // Validate time slot availability
-    let slot_start = chrono::DateTime::parse_from_rfc3339(&payload.start_time).map_err(|_| {
-        (
-            StatusCode::BAD_REQUEST,
-            "Invalid start_time format".to_string(),
-        )
-    })?;
-    let slot_end = chrono::DateTime::parse_from_rfc3339(&payload.end_time).map_err(|_| {
-        (
-            StatusCode::BAD_REQUEST,
-            "Invalid end_time format".to_string(),
-        )
-    })?;
+    let slot_start =
+        chrono::DateTime::parse_from_rfc3339(&payload.start_time).expect("start_time is validated");
+    let slot_end =
+        chrono::DateTime::parse_from_rfc3339(&payload.end_time).expect("end_time is validated");
+    payload.style =
+        EventStyle::for_booking(&state.config, &payload.start_time, &payload.end_time, None);
 
-    // Check current availability
+    // Check current availability, keeping the buffers around the slot free
+    let (buffered_start, buffered_end) =
+        booking_buffers(state, gcal_config, &slot_start, &slot_end)
+            .widen(slot_start.with_timezone(&Utc), slot_end.with_timezone(&Utc));
     let busy_periods = crate::logic::get_busy_times(
         &state.calendar_hub,
-        gcal_config
-            .calendar_id
-            .as_ref()
-            .expect("Calendar ID is required"),
-        slot_start.with_timezone(&time_zone),
-        slot_end.with_timezone(&time_zone),
+        calendar_id,
+        buffered_start.with_timezone(&time_zone),
+        buffered_end.with_timezone(&time_zone),
     )
     .await
     .map_err(|e| {
         info!("Error checking availability: {}", e);
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            calendar_error_status(&e),
             "Failed to check slot availability".to_string(),
         )
     })?;
 
     // Check if there are any overlapping busy periods
     for busy in &busy_periods {
-        if !(busy.1 <= slot_start.with_timezone(&Utc) || busy.0 >= slot_end.with_timezone(&Utc)) {
+        if !(busy.1 <= buffered_start || busy.0 >= buffered_end) {
             return Err((
                 StatusCode::CONFLICT,
                 "Requested time slot is no longer available".to_string(),
@@ -367,90 +925,501 @@ pub async fn book_slot_handler(
         }
     }
 
+    let hold_id = payload.hold_id.clone();
+    let capacity = calendar_capacity(gcal_config, calendar_id);
+    if let Some(capacity) = capacity {
+        // The booking's own hold already counts as one of the seats
+        check_group_seat(
+            state,
+            calendar_id,
+            capacity,
+            slot_start.with_timezone(&time_zone),
+            slot_end.with_timezone(&time_zone),
+            hold_id.as_deref(),
+        )
+        .await?;
+        payload.group_seat = true;
+    } else {
+        // The slot may only be held by the booking's own hold
+        let held = held_periods(
+            calendar_id,
+            slot_start.with_timezone(&time_zone),
+            slot_end.with_timezone(&time_zone),
+            state.clock.now(),
+            hold_id.as_deref(),
+        )
+        .await
+        .map_err(|e| {
+            info!("Error checking slot holds: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to check slot availability".to_string(),
+            )
+        })?;
+        if !held.is_empty() {
+            return Err((
+                StatusCode::CONFLICT,
+                "Requested time slot is held for another booking".to_string(),
+            ));
+        }
+    }
+
     // TODO: Add payment validation here once payment system is integrated
     // if let Some(payment_info) = payload.payment_info {
     //     validate_payment(payment_info).await?;
     // }
 
-    match create_calendar_event(
-        &state.calendar_hub,
-        gcal_config
-            .calendar_id
-            .as_ref()
-            .expect("Calendar ID is required"),
-        payload,
-    )
-    .await
-    {
+    let (start_time, end_time, summary) = (
+        payload.start_time.clone(),
+        payload.end_time.clone(),
+        payload.summary.clone(),
+    );
+    match create_calendar_event(&state.calendar_hub, calendar_id, payload).await {
         Ok(created_event) => {
             info!("Successfully created event: {:?}", created_event.id);
+            invalidate_busy_times_cache(calendar_id).await;
+            if let Some(hold_id) = hold_id {
+                if let Err(e) = slot_hold_store().release(&hold_id).await {
+                    warn!("Failed to release slot hold {}: {}", hold_id, e);
+                }
+            }
+            if let (Some(_), Some(event_id)) = (capacity, created_event.id.as_deref()) {
+                if let Err(e) = record_seat(
+                    calendar_id,
+                    event_id,
+                    slot_start.with_timezone(&Utc),
+                    slot_end.with_timezone(&Utc),
+                )
+                .await
+                {
+                    error!("Failed to record the seat of booking {}: {}", event_id, e);
+                }
+            }
+            if let Some(event_id) = created_event.id.clone() {
+                events::publish(BookingCreated {
+                    event_id,
+                    start_time,
+                    end_time,
+                    summary: Some(summary),
+                });
+            }
             Ok(Json(BookingResponse {
                 success: true,
                 event_id: created_event.id, // Send back the Google Calendar event ID
                 message: "Appointment booked successfully.".to_string(),
+                calendar_id: Some(calendar_id.to_string()),
+                meet_link: created_event.hangout_link,
             }))
         }
-        Err(GcalError::Conflict) => {
-            // Example specific error handling
+        Err(GcalError::Conflict) | Err(GcalError::ServiceError(GcalServiceError::Conflict)) => {
             Err((
                 StatusCode::CONFLICT,
                 "Requested time slot is no longer available.".to_string(),
             ))
         }
+        Err(GcalError::ServiceError(GcalServiceError::Busy)) => Err((
+            StatusCode::CONFLICT,
+            "Another booking is in progress, please retry.".to_string(),
+        )),
         Err(e) => {
             info!("Error booking slot: {}", e);
             Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
+                calendar_error_status(&e),
                 "Failed to book appointment.".to_string(),
             ))
         }
     }
 }
 
+/// Checks that the group slot from `start` to `end` has a free seat, not counting the hold
+/// `except`.
+async fn check_group_seat(
+    state: &GcalState,
+    calendar_id: &str,
+    capacity: u32,
+    start: DateTime<Tz>,
+    end: DateTime<Tz>,
+    except: Option<&str>,
+) -> Result<(), (StatusCode, String)> {
+    let group_slots =
+        load_group_slots(calendar_id, capacity, start, end, state.clock.now(), except)
+            .await
+            .map_err(|e| {
+                info!("Error checking group slot seats: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to check slot availability".to_string(),
+                )
+            })?;
+    match group_slots.remaining_seats(start.with_timezone(&Utc), end.with_timezone(&Utc)) {
+        None => Err((
+            StatusCode::CONFLICT,
+            "Requested time slot overlaps another group slot".to_string(),
+        )),
+        Some(0) => Err((
+            StatusCode::CONFLICT,
+            "Requested group slot is fully booked".to_string(),
+        )),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Handler to move a booking to another time.
+///
+/// The new slot must respect the preparation time and must not overlap other events of the
+/// calendar; the booking's own current time is not counted as busy.
+#[axum::debug_handler]
+pub async fn reschedule_booking_handler(
+    State(state): State<Arc<GcalState>>,
+    actor: AuditActor,
+    axum::extract::Path(event_id): axum::extract::Path<String>,
+    ValidatedJson(payload): ValidatedJson<RescheduleBookingRequest>,
+) -> Result<Json<BookingResponse>, (StatusCode, String)> {
+    // Get GCal specific config
+    let gcal_config = state.config.gcal.as_ref().expect("GCal config missing");
+    let calendar_id = &booking_calendar(gcal_config, &event_id).await?;
+
+    // A seat cannot move on its own, the group slot stays where it is
+    match booking_ledger().get(&event_id).await {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Group bookings cannot be rescheduled; cancel and book again.".to_string(),
+            ))
+        }
+        Err(e) => {
+            info!("Error looking up booked seat {}: {}", event_id, e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to reschedule appointment.".to_string(),
+            ));
+        }
+    }
+
+    check_booking_window(&state, gcal_config, &payload.start_time)?;
+
+    // Share the booking lock, so a booking cannot take the new slot at the same time
+    let lock = distributed_lock();
+    let lease = acquire_with_wait(
+        &*lock,
+        &format!("gcal:booking:{}", calendar_id),
+        BOOKING_LOCK_TTL,
+        BOOKING_LOCK_WAIT,
+    )
+    .await
+    .map_err(|e| {
+        info!("Error acquiring booking lock: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to check slot availability".to_string(),
+        )
+    })?
+    .ok_or_else(|| {
+        (
+            StatusCode::CONFLICT,
+            "Another booking is in progress, please retry.".to_string(),
+        )
+    })?;
+
+    let result =
+        reschedule_calendar_event(&state.calendar_hub, calendar_id, &event_id, &payload).await;
+    release_quietly(&*lock, &lease).await;
+    audit::record(
+        AuditEvent::new(
+            actor,
+            "booking.reschedule",
+            format!("gcal_event:{}", event_id),
+        )
+        .with_metadata("start_time", payload.start_time.as_str())
+        .with_metadata("end_time", payload.end_time.as_str())
+        .with_result(&result),
+    )
+    .await;
+
+    match result {
+        Ok(updated_event) => {
+            invalidate_busy_times_cache(calendar_id).await;
+            let parse = |time: &str| {
+                DateTime::parse_from_rfc3339(time)
+                    .expect("times are validated")
+                    .with_timezone(&Utc)
+            };
+            if let Err(e) = move_assignment(
+                &event_id,
+                parse(&payload.start_time),
+                parse(&payload.end_time),
+            )
+            .await
+            {
+                warn!(
+                    "Failed to move the assignment of booking {}: {}",
+                    event_id, e
+                );
+            }
+            events::publish(BookingRescheduled {
+                event_id: event_id.clone(),
+                start_time: payload.start_time,
+                end_time: payload.end_time,
+            });
+            Ok(Json(BookingResponse {
+                success: true,
+                event_id: updated_event.id.or(Some(event_id)),
+                message: "Appointment rescheduled successfully.".to_string(),
+                calendar_id: Some(calendar_id.clone()),
+                meet_link: updated_event.hangout_link,
+            }))
+        }
+        Err(GcalError::Conflict) | Err(GcalError::ServiceError(GcalServiceError::Conflict)) => {
+            Err((
+                StatusCode::CONFLICT,
+                "Requested time slot is no longer available.".to_string(),
+            ))
+        }
+        Err(GcalError::ServiceError(GcalServiceError::CalculationError(message))) => {
+            Err((StatusCode::BAD_REQUEST, message))
+        }
+        Err(GcalError::ServiceError(GcalServiceError::Busy)) => Err((
+            StatusCode::CONFLICT,
+            "Another booking is in progress, please retry.".to_string(),
+        )),
+        Err(e) => {
+            info!("Error rescheduling event: {}", e);
+            if e.to_string().contains("404") {
+                return Err((StatusCode::NOT_FOUND, "Event not found.".to_string()));
+            }
+            Err((
+                calendar_error_status(&e),
+                "Failed to reschedule appointment.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Handler to hold a slot while the customer pays.
+///
+/// The slot must be free and not held by anyone else. It stays blocked for `hold_ttl_minutes`
+/// or until it is booked with the returned `hold_id`.
+#[axum::debug_handler]
+pub async fn create_hold_handler(
+    State(state): State<Arc<GcalState>>,
+    ValidatedJson(payload): ValidatedJson<CreateHoldRequest>,
+) -> Result<(StatusCode, Json<HoldResponse>), (StatusCode, String)> {
+    let gcal_config = state.config.gcal.as_ref().expect("GCal config missing");
+    let calendar_id = resolve_calendar_id(gcal_config, payload.calendar_id.as_deref())?;
+    check_booking_window(&state, gcal_config, &payload.start_time)?;
+
+    let lock = distributed_lock();
+    let lease = acquire_booking_lock(&*lock, &calendar_id).await?;
+    let result = hold_slot(&state, &calendar_id, payload).await;
+    release_quietly(&*lock, &lease).await;
+    result
+}
+
+/// Checks that the slot is free and holds it. Callers must hold the calendar's booking lock.
+async fn hold_slot(
+    state: &GcalState,
+    calendar_id: &str,
+    payload: CreateHoldRequest,
+) -> Result<(StatusCode, Json<HoldResponse>), (StatusCode, String)> {
+    let gcal_config = state.config.gcal.as_ref().expect("GCal config missing");
+    let time_zone = Tz::from_str(gcal_config.time_zone.as_deref().unwrap_or("Europe/Zurich"))
+        .unwrap_or(Tz::Europe__Zurich);
+    let slot_start = chrono::DateTime::parse_from_rfc3339(&payload.start_time)
+        .expect("start_time is validated")
+        .with_timezone(&time_zone);
+    let slot_end = chrono::DateTime::parse_from_rfc3339(&payload.end_time)
+        .expect("end_time is validated")
+        .with_timezone(&time_zone);
+    let now = state.clock.now();
+
+    let check_failed = |e: String| {
+        info!("Error checking slot before holding it: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to check slot availability".to_string(),
+        )
+    };
+    let (buffered_start, buffered_end) =
+        booking_buffers(state, gcal_config, &slot_start, &slot_end).widen(slot_start, slot_end);
+    let busy_periods = crate::logic::get_busy_times(
+        &state.calendar_hub,
+        calendar_id,
+        buffered_start,
+        buffered_end,
+    )
+    .await
+    .map_err(|e| check_failed(e.to_string()))?;
+    // Holds of group slots take a seat, other holds block the slot
+    let held = match calendar_capacity(gcal_config, calendar_id) {
+        Some(capacity) => {
+            check_group_seat(state, calendar_id, capacity, slot_start, slot_end, None).await?;
+            Vec::new()
+        }
+        None => held_periods(calendar_id, slot_start, slot_end, now, None)
+            .await
+            .map_err(|e| check_failed(e.to_string()))?,
+    };
+    if busy_periods
+        .iter()
+        .any(|(start, end)| *start < buffered_end && buffered_start < *end)
+        || held
+            .iter()
+            .any(|(start, end)| *start < slot_end && slot_start < *end)
+    {
+        return Err((
+            StatusCode::CONFLICT,
+            "Requested time slot is no longer available".to_string(),
+        ));
+    }
+
+    let hold = SlotHold {
+        id: uuid::Uuid::new_v4().to_string(),
+        calendar_id: calendar_id.to_string(),
+        start_time: slot_start.with_timezone(&Utc),
+        end_time: slot_end.with_timezone(&Utc),
+        expires_at: now + hold_ttl(gcal_config),
+        reference: payload.reference,
+    };
+    slot_hold_store().create(hold.clone()).await.map_err(|e| {
+        info!("Error storing slot hold: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to hold slot".to_string(),
+        )
+    })?;
+    invalidate_busy_times_cache(calendar_id).await;
+    Ok((StatusCode::CREATED, Json(hold.into())))
+}
+
+/// Handler to release a slot hold, e.g. when the payment was abandoned.
+#[axum::debug_handler]
+pub async fn delete_hold_handler(
+    axum::extract::Path(hold_id): axum::extract::Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let store = slot_hold_store();
+    let hold = store.get(&hold_id).await.map_err(|e| {
+        info!("Error loading slot hold {}: {}", hold_id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to release slot hold".to_string(),
+        )
+    })?;
+    let Some(hold) = hold else {
+        return Err((StatusCode::NOT_FOUND, "Hold not found.".to_string()));
+    };
+    store.release(&hold_id).await.map_err(|e| {
+        info!("Error releasing slot hold {}: {}", hold_id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to release slot hold".to_string(),
+        )
+    })?;
+    invalidate_busy_times_cache(&hold.calendar_id).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler to download a booking as an iCalendar (.ics) file.
+#[axum::debug_handler]
+pub async fn ics_export_handler(
+    State(state): State<Arc<GcalState>>,
+    axum::extract::Path(event_id): axum::extract::Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let gcal_config = state.config.gcal.as_ref().expect("GCal config missing");
+    let calendar_id = &booking_calendar(gcal_config, &event_id).await?;
+    let time_zone = Tz::from_str(gcal_config.time_zone.as_deref().unwrap_or("Europe/Zurich"))
+        .unwrap_or(Tz::Europe__Zurich);
+
+    let event = match get_calendar_event(&state.calendar_hub, calendar_id, &event_id).await {
+        Ok(event) => event,
+        Err(e) => {
+            info!("Error fetching event {} for export: {}", event_id, e);
+            if e.to_string().contains("404") {
+                return Err((StatusCode::NOT_FOUND, "Event not found.".to_string()));
+            }
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to export event.".to_string(),
+            ));
+        }
+    };
+    let ics_event = IcsEvent::from_google(&event, time_zone).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            "Event has no start and end time.".to_string(),
+        )
+    })?;
+
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                "text/calendar; charset=utf-8".to_string(),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"booking-{}.ics\"", event_id),
+            ),
+        ],
+        render_ics(&ics_event, state.clock.now()),
+    ))
+}
+
 /// Handler to delete a booking completely from the calendar.
 #[axum::debug_handler]
 pub async fn delete_event_handler(
     State(state): State<Arc<GcalState>>,
+    actor: AuditActor,
     axum::extract::Path(event_id): axum::extract::Path<String>,
     Query(params): Query<CancelBookingRequest>,
 ) -> Result<Json<CancellationResponse>, (StatusCode, String)> {
     // Get GCal specific config
     let gcal_config = state.config.gcal.as_ref().expect("GCal config missing");
-    let calendar_id = gcal_config
-        .calendar_id
-        .as_ref()
-        .expect("Calendar ID is required");
+    let calendar_id = &booking_calendar(gcal_config, &event_id).await?;
 
     // Use notify_attendees parameter if provided, or default to true
     let notify_attendees = params.notify_attendees.unwrap_or(true);
 
-    match delete_calendar_event(
+    let result = delete_calendar_event(
         &state.calendar_hub,
         calendar_id,
         &event_id,
         notify_attendees,
     )
-    .await
-    {
-        Ok(_) => Ok(Json(CancellationResponse {
-            success: true,
-            message: "Event deleted successfully.".to_string(),
-        })),
+    .await;
+    audit::record(
+        AuditEvent::new(actor, "booking.delete", format!("gcal_event:{}", event_id))
+            .with_metadata("notify_attendees", notify_attendees)
+            .with_result(&result),
+    )
+    .await;
+
+    match result {
+        Ok(_) => {
+            invalidate_busy_times_cache(calendar_id).await;
+            if let Err(e) = release_seat(&event_id).await {
+                warn!("Failed to release the seat of booking {}: {}", event_id, e);
+            }
+            if let Err(e) = booking_ledger().unassign(&event_id).await {
+                warn!(
+                    "Failed to release the assignment of booking {}: {}",
+                    event_id, e
+                );
+            }
+            events::publish(BookingCancelled { event_id });
+            Ok(Json(CancellationResponse {
+                success: true,
+                message: "Event deleted successfully.".to_string(),
+            }))
+        }
         Err(e) => {
             info!("Error deleting event: {}", e);
             match e {
-                GcalError::ApiError(error) => {
-                    // Handle specific error codes if needed
-                    if error.to_string().contains("404") {
-                        return Err((StatusCode::NOT_FOUND, "Event not found.".to_string()));
-                    }
-                    Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "Failed to delete event.".to_string(),
-                    ))
+                GcalError::ApiError(ref error) if error.to_string().contains("404") => {
+                    Err((StatusCode::NOT_FOUND, "Event not found.".to_string()))
                 }
                 _ => Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
+                    calendar_error_status(&e),
                     "Failed to delete event.".to_string(),
                 )),
             }
@@ -462,46 +1431,56 @@ pub async fn delete_event_handler(
 #[axum::debug_handler]
 pub async fn mark_booking_cancelled_handler(
     State(state): State<Arc<GcalState>>,
+    actor: AuditActor,
     axum::extract::Path(event_id): axum::extract::Path<String>,
     Query(params): Query<CancelBookingRequest>,
 ) -> Result<Json<CancellationResponse>, (StatusCode, String)> {
     // Get GCal specific config
     let gcal_config = state.config.gcal.as_ref().expect("GCal config missing");
-    let calendar_id = gcal_config
-        .calendar_id
-        .as_ref()
-        .expect("Calendar ID is required");
+    let calendar_id = &booking_calendar(gcal_config, &event_id).await?;
 
     // Use notify_attendees parameter if provided, or default to true
     let notify_attendees = params.notify_attendees.unwrap_or(true);
 
-    match mark_event_cancelled(
+    let result = mark_event_cancelled(
         &state.calendar_hub,
         calendar_id,
         &event_id,
         notify_attendees,
     )
-    .await
-    {
-        Ok(_) => Ok(Json(CancellationResponse {
-            success: true,
-            message: "Appointment marked as cancelled successfully.".to_string(),
-        })),
+    .await;
+    audit::record(
+        AuditEvent::new(actor, "booking.cancel", format!("gcal_event:{}", event_id))
+            .with_metadata("notify_attendees", notify_attendees)
+            .with_result(&result),
+    )
+    .await;
+
+    match result {
+        Ok(_) => {
+            invalidate_busy_times_cache(calendar_id).await;
+            if let Err(e) = release_seat(&event_id).await {
+                warn!("Failed to release the seat of booking {}: {}", event_id, e);
+            }
+            if let Err(e) = booking_ledger().unassign(&event_id).await {
+                warn!(
+                    "Failed to release the assignment of booking {}: {}",
+                    event_id, e
+                );
+            }
+            Ok(Json(CancellationResponse {
+                success: true,
+                message: "Appointment marked as cancelled successfully.".to_string(),
+            }))
+        }
         Err(e) => {
             info!("Error marking event as cancelled: {}", e);
             match e {
-                GcalError::ApiError(error) => {
-                    // Handle specific error codes if needed
-                    if error.to_string().contains("404") {
-                        return Err((StatusCode::NOT_FOUND, "Event not found.".to_string()));
-                    }
-                    Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "Failed to mark appointment as cancelled.".to_string(),
-                    ))
+                GcalError::ApiError(ref error) if error.to_string().contains("404") => {
+                    Err((StatusCode::NOT_FOUND, "Event not found.".to_string()))
                 }
                 _ => Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
+                    calendar_error_status(&e),
                     "Failed to mark appointment as cancelled.".to_string(),
                 )),
             }
@@ -509,6 +1488,321 @@ pub async fn mark_booking_cancelled_handler(
     }
 }
 
+/// The OAuth client config, if calendars are connected through the consent screen.
+fn oauth_config(gcal_config: &GcalConfig) -> Result<&GcalOAuthConfig, (StatusCode, String)> {
+    gcal_config.oauth.as_ref().ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            "OAuth is not configured for Google Calendar.".to_string(),
+        )
+    })
+}
+
+/// The client secret of the OAuth client, or 500 if it is missing.
+fn oauth_client_secret() -> Result<String, (StatusCode, String)> {
+    oauth::client_secret().map_err(|e| {
+        error!("Cannot connect Google Calendar: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Server configuration error: OAuth client secret missing.".to_string(),
+        )
+    })
+}
+
+/// Handler to connect a personal Google calendar, redirecting to Google's consent screen.
+#[axum::debug_handler]
+pub async fn oauth_connect_handler(
+    State(state): State<Arc<GcalState>>,
+) -> Result<Redirect, (StatusCode, String)> {
+    let gcal_config = state.config.gcal.as_ref().expect("GCal config missing");
+    let config = oauth_config(gcal_config)?;
+    let consent_state = oauth::consent_state(&oauth_client_secret()?, state.clock.now());
+    Ok(Redirect::to(&oauth::authorization_url(
+        config,
+        &consent_state,
+    )))
+}
+
+/// Handler Google redirects to after the consent screen; stores the granted refresh token.
+#[axum::debug_handler]
+pub async fn oauth_callback_handler(
+    State(state): State<Arc<GcalState>>,
+    actor: AuditActor,
+    Query(query): Query<OAuthCallbackQuery>,
+) -> Result<String, (StatusCode, String)> {
+    let gcal_config = state.config.gcal.as_ref().expect("GCal config missing");
+    let config = oauth_config(gcal_config)?;
+    let client_secret = oauth_client_secret()?;
+    let now = state.clock.now();
+
+    let consent_state = query.state.as_deref().unwrap_or_default();
+    if !oauth::verify_consent_state(&client_secret, consent_state, now) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            OAuthError::InvalidState.to_string(),
+        ));
+    }
+    if let Some(error) = query.error {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Google Calendar was not connected: {}", error),
+        ));
+    }
+    let code = query.code.ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            "Missing authorization code.".to_string(),
+        )
+    })?;
+
+    let result = oauth::connect_with_code(config, &client_secret, &code, now).await;
+    audit::record(AuditEvent::new(actor, "gcal.oauth.connect", "gcal_oauth").with_result(&result))
+        .await;
+    match result {
+        Ok(()) => {
+            info!("Google Calendar connected through OAuth");
+            Ok("Google Calendar connected. You can close this window.".to_string())
+        }
+        Err(e @ OAuthError::NoRefreshToken) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+        Err(e) => {
+            error!("Failed to connect Google Calendar: {}", e);
+            Err((
+                StatusCode::BAD_GATEWAY,
+                "Failed to connect Google Calendar.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Handler to disconnect the personal Google calendar by forgetting its refresh token.
+#[axum::debug_handler]
+pub async fn oauth_disconnect_handler(
+    State(state): State<Arc<GcalState>>,
+    actor: AuditActor,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let gcal_config = state.config.gcal.as_ref().expect("GCal config missing");
+    oauth_config(gcal_config)?;
+
+    let result = oauth_token_store().delete(oauth::OAUTH_PROVIDER).await;
+    audit::record(
+        AuditEvent::new(actor, "gcal.oauth.disconnect", "gcal_oauth").with_result(&result),
+    )
+    .await;
+    match result {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            "Google Calendar is not connected.".to_string(),
+        )),
+        Err(e) => {
+            error!("Failed to disconnect Google Calendar: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to disconnect Google Calendar.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Handler for Google Calendar push notifications of watched calendars.
+///
+/// Drops the cached free/busy results of the changed calendar, so the next availability
+/// request sees the change.
+#[axum::debug_handler]
+pub async fn push_notification_handler(
+    State(state): State<Arc<GcalState>>,
+    headers: HeaderMap,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let Ok(expected_token) = std::env::var(PUSH_CHANNEL_TOKEN_ENV) else {
+        error!("{} environment variable not set!", PUSH_CHANNEL_TOKEN_ENV);
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("{} environment variable not set", PUSH_CHANNEL_TOKEN_ENV),
+        ));
+    };
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let token = header("x-goog-channel-token").unwrap_or_default();
+    if !constant_time_eq(token.as_bytes(), expected_token.as_bytes()) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "Invalid channel token".to_string(),
+        ));
+    }
+
+    // Google confirms a new channel with a "sync" message, nothing changed yet
+    if header("x-goog-resource-state") == Some("sync") {
+        return Ok(StatusCode::OK);
+    }
+
+    let gcal_config = state.config.gcal.as_ref().expect("GCal config missing");
+    let calendars = configured_calendars(gcal_config);
+    let changed = header("x-goog-resource-uri")
+        .and_then(calendar_id_from_resource_uri)
+        .filter(|id| calendars.iter().any(|calendar| &calendar.id == id));
+    match changed {
+        Some(calendar_id) => invalidate_busy_times_cache(&calendar_id).await,
+        // Unknown resource: don't guess, drop everything
+        None => {
+            for calendar in &calendars {
+                invalidate_busy_times_cache(&calendar.id).await;
+            }
+        }
+    }
+    Ok(StatusCode::OK)
+}
+
+/// Handler to list the blackout periods.
+#[axum::debug_handler]
+pub async fn list_blackouts_handler(State(state): State<Arc<GcalState>>) -> Json<Vec<Blackout>> {
+    Json(state.blackouts.list())
+}
+
+/// Handler to add a blackout period at runtime.
+#[axum::debug_handler]
+pub async fn create_blackout_handler(
+    State(state): State<Arc<GcalState>>,
+    actor: AuditActor,
+    ValidatedJson(payload): ValidatedJson<CreateBlackoutRequest>,
+) -> Result<(StatusCode, Json<Blackout>), (StatusCode, String)> {
+    let blackout = state.blackouts.add_request(payload).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            "Invalid blackout dates (YYYY-MM-DD)".to_string(),
+        )
+    })?;
+    audit::record(
+        AuditEvent::new(
+            actor,
+            "blackout.create",
+            format!("gcal_blackout:{}", blackout.id),
+        )
+        .with_metadata("start_date", blackout.start_date.to_string())
+        .with_metadata("end_date", blackout.end_date.to_string()),
+    )
+    .await;
+    Ok((StatusCode::CREATED, Json(blackout)))
+}
+
+/// Handler to remove a blackout period.
+#[axum::debug_handler]
+pub async fn delete_blackout_handler(
+    State(state): State<Arc<GcalState>>,
+    actor: AuditActor,
+    axum::extract::Path(blackout_id): axum::extract::Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !state.blackouts.remove(&blackout_id) {
+        return Err((StatusCode::NOT_FOUND, "Blackout not found.".to_string()));
+    }
+    audit::record(AuditEvent::new(
+        actor,
+        "blackout.delete",
+        format!("gcal_blackout:{}", blackout_id),
+    ))
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler to list the schedule exceptions, from today for a year by default.
+#[axum::debug_handler]
+pub async fn list_schedule_exceptions_handler(
+    State(state): State<Arc<GcalState>>,
+    Query(query): Query<ScheduleExceptionsQuery>,
+) -> Result<Json<Vec<ScheduleExceptionResponse>>, (StatusCode, String)> {
+    let parse = |date: Option<&str>| {
+        date.map(|date| {
+            parse_date(date).ok_or_else(|| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid date (YYYY-MM-DD): {}", date),
+                )
+            })
+        })
+        .transpose()
+    };
+    let from = parse(query.from.as_deref())?.unwrap_or_else(|| state.clock.now().date_naive());
+    let to = parse(query.to.as_deref())?.unwrap_or(from + chrono::Days::new(365));
+    let exceptions = schedule_exception_store()
+        .between(from, to)
+        .await
+        .map_err(|e| {
+            info!("Error listing schedule exceptions: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to list schedule exceptions.".to_string(),
+            )
+        })?;
+    Ok(Json(exceptions.into_iter().map(Into::into).collect()))
+}
+
+/// Handler to add a schedule exception, e.g. extra hours or closing early on a date.
+#[axum::debug_handler]
+pub async fn create_schedule_exception_handler(
+    State(state): State<Arc<GcalState>>,
+    actor: AuditActor,
+    ValidatedJson(payload): ValidatedJson<CreateScheduleExceptionRequest>,
+) -> Result<(StatusCode, Json<ScheduleExceptionResponse>), (StatusCode, String)> {
+    let exception = ScheduleException {
+        id: uuid::Uuid::new_v4().to_string(),
+        date: parse_date(&payload.date).expect("date is validated"),
+        kind: payload.kind,
+        start_time: payload.start_time.as_deref().and_then(parse_time),
+        end_time: payload.end_time.as_deref().and_then(parse_time),
+        reason: payload.reason,
+        created_at: state.clock.now(),
+    };
+    let result = schedule_exception_store().create(exception.clone()).await;
+    audit::record(
+        AuditEvent::new(
+            actor,
+            "schedule_exception.create",
+            format!("gcal_schedule_exception:{}", exception.id),
+        )
+        .with_metadata("date", exception.date.to_string())
+        .with_metadata("kind", exception.kind.as_str())
+        .with_result(&result),
+    )
+    .await;
+    result.map_err(|e| {
+        info!("Error storing schedule exception: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to add schedule exception.".to_string(),
+        )
+    })?;
+    Ok((StatusCode::CREATED, Json(exception.into())))
+}
+
+/// Handler to remove a schedule exception.
+#[axum::debug_handler]
+pub async fn delete_schedule_exception_handler(
+    actor: AuditActor,
+    axum::extract::Path(exception_id): axum::extract::Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let deleted = schedule_exception_store()
+        .delete(&exception_id)
+        .await
+        .map_err(|e| {
+            info!("Error removing schedule exception: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to remove schedule exception.".to_string(),
+            )
+        })?;
+    if !deleted {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Schedule exception not found.".to_string(),
+        ));
+    }
+    audit::record(AuditEvent::new(
+        actor,
+        "schedule_exception.delete",
+        format!("gcal_schedule_exception:{}", exception_id),
+    ))
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 /// Handler for OPTIONS requests to support CORS preflight
 pub async fn options_handler() -> impl axum::response::IntoResponse {
     // Return appropriate CORS headers for preflight requests
@@ -527,7 +1821,9 @@ pub async fn options_handler() -> impl axum::response::IntoResponse {
 #[axum::debug_handler]
 pub async fn get_booked_events_handler(
     State(state): State<Arc<GcalState>>,
+    actor: AuditActor,
     Query(query): Query<BookedEventsQuery>,
+    Query(page): Query<CursorQuery>,
 ) -> Result<Json<BookedEventsResponse>, (StatusCode, String)> {
     // Get GCal specific config
     let gcal_config = state.config.gcal.as_ref().expect("GCal config missing");
@@ -576,9 +1872,12 @@ pub async fn get_booked_events_handler(
 
     // Get include_cancelled parameter, default to false if not provided
     let include_cancelled = query.include_cancelled.unwrap_or(false);
+    let display_time_zone = client_time_zone(query.time_zone.as_deref())?;
+    let page_size =
+        validate_booked_events_page(&page).map_err(|message| (StatusCode::BAD_REQUEST, message))?;
 
     // Fetch booked events
-    match get_booked_events(
+    let result = get_booked_events(
         &state.calendar_hub,
         gcal_config
             .calendar_id
@@ -587,14 +1886,32 @@ pub async fn get_booked_events_handler(
         query_start_tz,
         query_end_tz,
         include_cancelled,
+        query.tag.as_deref(),
+        display_time_zone,
+        page_size,
+        page.cursor.as_deref(),
     )
-    .await
-    {
-        Ok(events) => Ok(Json(BookedEventsResponse { events })),
+    .await;
+    audit::record(
+        AuditEvent::new(actor, "booking.list", "gcal_bookings")
+            .with_metadata("start_date", query.start_date.as_str())
+            .with_metadata("end_date", query.end_date.as_str())
+            .with_metadata("include_cancelled", include_cancelled)
+            .with_metadata("tag", query.tag.clone())
+            .with_result(&result),
+    )
+    .await;
+
+    match result {
+        Ok(response) => Ok(Json(response)),
         Err(e) => {
             info!("Error fetching booked events: {}", e);
+            // Google rejects unknown or expired page tokens as a bad request
+            if page.cursor.is_some() && e.to_string().contains("400") {
+                return Err((StatusCode::BAD_REQUEST, "Invalid cursor".to_string()));
+            }
             Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
+                calendar_error_status(&e),
                 "Failed to fetch booked events".to_string(),
             ))
         }
@@ -0,0 +1,107 @@
+// --- File: crates/connectify_gcal/src/holds.rs ---
+//! Temporary holds on slots while the customer pays.
+//!
+//! `POST /gcal/holds` reserves a slot for `hold_ttl_minutes`. Until the hold expires, the slot
+//! is missing from the availability and can only be booked with the hold's id, so a payment
+//! completing late cannot run into a booking made in the meantime.
+
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use connectify_common::holds::{slot_hold_store, SlotHold};
+use connectify_common::ConnectifyError;
+use connectify_config::GcalConfig;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+/// How long a hold blocks its slot by default, in minutes.
+const DEFAULT_HOLD_TTL_MINUTES: i64 = 15;
+
+/// Request to hold a slot.
+#[derive(Deserialize, Debug, Validate)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[validate(schema(function = "validate_hold_times"))]
+pub struct CreateHoldRequest {
+    #[validate(custom(function = "connectify_common::validation::validate_rfc3339"))]
+    pub start_time: String, // ISO 8601 format string
+    #[validate(custom(function = "connectify_common::validation::validate_rfc3339"))]
+    pub end_time: String, // ISO 8601 format string
+    /// The calendar of the slot, one of the configured calendars (default: `calendar_id`)
+    pub calendar_id: Option<String>,
+    /// Free-form reference of the holder, e.g. a checkout session
+    pub reference: Option<String>,
+}
+
+/// Checks that a held slot ends after it starts.
+fn validate_hold_times(request: &CreateHoldRequest) -> Result<(), ValidationError> {
+    let start = DateTime::parse_from_rfc3339(&request.start_time);
+    let end = DateTime::parse_from_rfc3339(&request.end_time);
+    match (start, end) {
+        (Ok(start), Ok(end)) if end <= start => Err(ValidationError::new("end_before_start")),
+        _ => Ok(()),
+    }
+}
+
+/// A created hold; pass `hold_id` along with the booking.
+#[derive(Serialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct HoldResponse {
+    pub hold_id: String,
+    pub calendar_id: String,
+    pub start_time: String,
+    pub end_time: String,
+    /// When the slot becomes available again unless booked
+    pub expires_at: String,
+}
+
+impl From<SlotHold> for HoldResponse {
+    fn from(hold: SlotHold) -> Self {
+        Self {
+            hold_id: hold.id,
+            calendar_id: hold.calendar_id,
+            start_time: hold.start_time.to_rfc3339(),
+            end_time: hold.end_time.to_rfc3339(),
+            expires_at: hold.expires_at.to_rfc3339(),
+        }
+    }
+}
+
+/// The configured hold lifetime.
+pub fn hold_ttl(config: &GcalConfig) -> Duration {
+    Duration::minutes(
+        config
+            .hold_ttl_minutes
+            .filter(|minutes| *minutes > 0)
+            .unwrap_or(DEFAULT_HOLD_TTL_MINUTES),
+    )
+}
+
+/// The periods of a calendar blocked by holds at `now`, other than the hold `except`.
+pub async fn held_periods(
+    calendar_id: &str,
+    start_time: DateTime<Tz>,
+    end_time: DateTime<Tz>,
+    now: DateTime<Utc>,
+    except: Option<&str>,
+) -> Result<Vec<(DateTime<Tz>, DateTime<Tz>)>, ConnectifyError> {
+    let timezone = start_time.timezone();
+    let holds = slot_hold_store()
+        .overlapping(
+            calendar_id,
+            start_time.with_timezone(&Utc),
+            end_time.with_timezone(&Utc),
+            now,
+        )
+        .await?;
+    Ok(holds
+        .into_iter()
+        .filter(|hold| Some(hold.id.as_str()) != except)
+        .map(|hold| {
+            (
+                hold.start_time.with_timezone(&timezone),
+                hold.end_time.with_timezone(&timezone),
+            )
+        })
+        .collect())
+}
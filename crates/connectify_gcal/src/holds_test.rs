@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use crate::holds::{held_periods, CreateHoldRequest};
+    use chrono::{Duration, TimeZone, Utc};
+    use chrono_tz::Tz;
+    use connectify_common::holds::{slot_hold_store, SlotHold};
+    use validator::Validate;
+
+    #[tokio::test]
+    async fn test_held_periods() {
+        let time_zone = Tz::Europe__Zurich;
+        let now = Utc.with_ymd_and_hms(2025, 5, 14, 8, 0, 0).unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 5, 15, 8, 0, 0).unwrap();
+        // A calendar of its own, as the store is shared by all tests
+        let calendar_id = "holds-test@example.com";
+        let hold = |id: &str, expires_at| SlotHold {
+            id: id.to_string(),
+            calendar_id: calendar_id.to_string(),
+            start_time: start,
+            end_time: start + Duration::hours(1),
+            expires_at,
+            reference: None,
+        };
+        let store = slot_hold_store();
+        store
+            .create(hold("holds-test-active", now + Duration::minutes(15)))
+            .await
+            .unwrap();
+        store
+            .create(hold("holds-test-expired", now - Duration::minutes(1)))
+            .await
+            .unwrap();
+
+        let day_start = time_zone.with_ymd_and_hms(2025, 5, 15, 0, 0, 0).unwrap();
+        let day_end = time_zone.with_ymd_and_hms(2025, 5, 16, 0, 0, 0).unwrap();
+        let held = held_periods(calendar_id, day_start, day_end, now, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            held,
+            vec![(
+                time_zone.with_ymd_and_hms(2025, 5, 15, 10, 0, 0).unwrap(),
+                time_zone.with_ymd_and_hms(2025, 5, 15, 11, 0, 0).unwrap()
+            )]
+        );
+
+        // A booking with the hold's id is not blocked by it
+        let held = held_periods(
+            calendar_id,
+            day_start,
+            day_end,
+            now,
+            Some("holds-test-active"),
+        )
+        .await
+        .unwrap();
+        assert!(held.is_empty());
+
+        // After expiry the slot is free again
+        let held = held_periods(
+            calendar_id,
+            day_start,
+            day_end,
+            now + Duration::minutes(15),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(held.is_empty());
+    }
+
+    #[test]
+    fn test_create_hold_request_validation() {
+        let request = CreateHoldRequest {
+            start_time: "2025-05-15T11:00:00Z".to_string(),
+            end_time: "2025-05-15T10:00:00Z".to_string(),
+            calendar_id: None,
+            reference: None,
+        };
+        assert!(request.validate().is_err());
+
+        let request = CreateHoldRequest {
+            end_time: "2025-05-15T12:00:00Z".to_string(),
+            ..request
+        };
+        assert!(request.validate().is_ok());
+    }
+}
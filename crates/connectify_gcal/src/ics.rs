@@ -0,0 +1,203 @@
+// --- File: crates/connectify_gcal/src/ics.rs ---
+//! iCalendar (RFC 5545) export of booked events.
+//!
+//! Times are written in the calendar's time zone, together with a `VTIMEZONE` describing the
+//! zone's offsets in the years of the event, so clients show the booking at the right local
+//! time whatever their own zone.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Offset, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+use google_calendar3::api::Event;
+
+/// Minutes before the start of a booking at which the exported alarm goes off.
+pub const ALARM_MINUTES_BEFORE: i64 = 30;
+
+/// The product identifier of exported calendars.
+const PRODUCT_ID: &str = "-//Connectify//Connectify GCal//EN";
+
+/// A booked event to export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IcsEvent {
+    pub uid: String,
+    pub start: DateTime<Tz>,
+    pub end: DateTime<Tz>,
+    pub summary: String,
+    pub description: Option<String>,
+    /// Link to the video conference, if any
+    pub url: Option<String>,
+    pub cancelled: bool,
+}
+
+impl IcsEvent {
+    /// Convert a Google Calendar event with a start and end time to the calendar's time zone.
+    ///
+    /// Returns `None` for events without an id or times, e.g. all-day events.
+    pub fn from_google(event: &Event, time_zone: Tz) -> Option<Self> {
+        let start = event.start.as_ref()?.date_time?;
+        let end = event.end.as_ref()?.date_time?;
+        Some(Self {
+            uid: format!("{}@connectify", event.id.as_deref()?),
+            start: start.with_timezone(&time_zone),
+            end: end.with_timezone(&time_zone),
+            summary: event.summary.clone().unwrap_or_default(),
+            description: event.description.clone(),
+            url: crate::service::meet_link(event),
+            cancelled: event.status.as_deref() == Some("cancelled"),
+        })
+    }
+}
+
+/// Render an event as an iCalendar file; `now` is used as its timestamp.
+pub fn render_ics(event: &IcsEvent, now: DateTime<Utc>) -> String {
+    let time_zone = event.start.timezone();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODUCT_ID),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+    ];
+    lines.extend(vtimezone(time_zone, event.start.year(), event.end.year()));
+    lines.extend([
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", escape_text(&event.uid)),
+        format!("DTSTAMP:{}", now.format("%Y%m%dT%H%M%SZ")),
+        format!(
+            "DTSTART;TZID={}:{}",
+            time_zone.name(),
+            local_time(&event.start)
+        ),
+        format!("DTEND;TZID={}:{}", time_zone.name(), local_time(&event.end)),
+        format!("SUMMARY:{}", escape_text(&event.summary)),
+    ]);
+    if let Some(description) = &event.description {
+        lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+    }
+    if let Some(url) = &event.url {
+        lines.push(format!("URL:{}", url));
+        lines.push(format!("LOCATION:{}", escape_text(url)));
+    }
+    lines.push(format!(
+        "STATUS:{}",
+        if event.cancelled {
+            "CANCELLED"
+        } else {
+            "CONFIRMED"
+        }
+    ));
+    if !event.cancelled {
+        lines.extend([
+            "BEGIN:VALARM".to_string(),
+            "ACTION:DISPLAY".to_string(),
+            format!("DESCRIPTION:{}", escape_text(&event.summary)),
+            format!("TRIGGER:-PT{}M", ALARM_MINUTES_BEFORE),
+            "END:VALARM".to_string(),
+        ]);
+    }
+    lines.extend(["END:VEVENT".to_string(), "END:VCALENDAR".to_string()]);
+
+    lines.iter().map(|line| fold_line(line)).collect()
+}
+
+fn local_time(time: &DateTime<Tz>) -> String {
+    time.format("%Y%m%dT%H%M%S").to_string()
+}
+
+/// Escape a TEXT value (RFC 5545, section 3.3.11).
+pub(crate) fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold a content line into lines of at most 75 octets, ending it with CRLF.
+pub(crate) fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// The UTC offset of a zone at an instant, in seconds, and whether it is daylight saving time.
+fn offset_at(time_zone: Tz, instant: DateTime<Utc>) -> (i32, bool, String) {
+    let offset = time_zone.offset_from_utc_datetime(&instant.naive_utc());
+    (
+        offset.fix().local_minus_utc(),
+        !offset.dst_offset().is_zero(),
+        offset.abbreviation().unwrap_or_default().to_string(),
+    )
+}
+
+fn format_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let minutes = seconds.abs() / 60;
+    format!("{}{:02}{:02}", sign, minutes / 60, minutes % 60)
+}
+
+/// A `VTIMEZONE` with the offset in effect at the start of `first_year` and every
+/// transition until the end of `last_year`.
+fn vtimezone(time_zone: Tz, first_year: i32, last_year: i32) -> Vec<String> {
+    let year_start = |year: i32| {
+        NaiveDate::from_ymd_opt(year, 1, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|date| date.and_utc())
+    };
+    let (Some(scan_start), Some(scan_end)) = (year_start(first_year), year_start(last_year + 1))
+    else {
+        return Vec::new();
+    };
+
+    let component = |instant: DateTime<Utc>, from: i32, to: (i32, bool, String)| {
+        let local = instant + Duration::seconds(from as i64);
+        vec![
+            format!("BEGIN:{}", if to.1 { "DAYLIGHT" } else { "STANDARD" }),
+            format!("DTSTART:{}", local.format("%Y%m%dT%H%M%S")),
+            format!("TZOFFSETFROM:{}", format_offset(from)),
+            format!("TZOFFSETTO:{}", format_offset(to.0)),
+            format!("TZNAME:{}", to.2),
+            format!("END:{}", if to.1 { "DAYLIGHT" } else { "STANDARD" }),
+        ]
+    };
+
+    let mut lines = vec![
+        "BEGIN:VTIMEZONE".to_string(),
+        format!("TZID:{}", time_zone.name()),
+    ];
+    let mut current = offset_at(time_zone, scan_start);
+    lines.extend(component(scan_start, current.0, current.clone()));
+
+    // Transitions happen at most every few months, so scanning hourly finds them all
+    let mut instant = scan_start;
+    while instant < scan_end {
+        let next = instant + Duration::hours(1);
+        let next_offset = offset_at(time_zone, next);
+        if next_offset.0 != current.0 || next_offset.1 != current.1 {
+            // Narrow down to the second of the transition
+            let (mut before, mut after) = (instant, next);
+            while after - before > Duration::seconds(1) {
+                let middle = before + (after - before) / 2;
+                if offset_at(time_zone, middle) == current {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+            lines.extend(component(after, current.0, next_offset.clone()));
+            current = next_offset;
+        }
+        instant = next;
+    }
+    lines.push("END:VTIMEZONE".to_string());
+    lines
+}
@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use crate::ics::{render_ics, IcsEvent};
+    use chrono::{TimeZone, Utc};
+    use chrono_tz::Tz;
+    use google_calendar3::api::{Event, EventDateTime};
+
+    fn event(summary: &str) -> IcsEvent {
+        let time_zone = Tz::Europe__Zurich;
+        IcsEvent {
+            uid: "abc123@connectify".to_string(),
+            start: time_zone.with_ymd_and_hms(2025, 5, 15, 10, 0, 0).unwrap(),
+            end: time_zone.with_ymd_and_hms(2025, 5, 15, 11, 0, 0).unwrap(),
+            summary: summary.to_string(),
+            description: Some("Bring notes; slides, too\nThanks".to_string()),
+            url: Some("https://meet.google.com/abc-defg-hij".to_string()),
+            cancelled: false,
+        }
+    }
+
+    fn unfold(ics: &str) -> Vec<String> {
+        ics.replace("\r\n ", "")
+            .split("\r\n")
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_render_ics() {
+        let now = Utc.with_ymd_and_hms(2025, 5, 10, 8, 30, 0).unwrap();
+        let ics = render_ics(&event("Meeting with Client"), now);
+
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(!ics.replace("\r\n", "").contains('\n'));
+        let lines = unfold(&ics);
+        for expected in [
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            "TZID:Europe/Zurich",
+            "UID:abc123@connectify",
+            "DTSTAMP:20250510T083000Z",
+            "DTSTART;TZID=Europe/Zurich:20250515T100000",
+            "DTEND;TZID=Europe/Zurich:20250515T110000",
+            "SUMMARY:Meeting with Client",
+            "DESCRIPTION:Bring notes\\; slides\\, too\\nThanks",
+            "URL:https://meet.google.com/abc-defg-hij",
+            "STATUS:CONFIRMED",
+            "TRIGGER:-PT30M",
+        ] {
+            assert!(lines.iter().any(|line| line == expected), "{}", expected);
+        }
+    }
+
+    #[test]
+    fn test_render_ics_timezone_transitions() {
+        let ics = render_ics(&event("Meeting"), Utc::now());
+        let lines = unfold(&ics);
+        let daylight = lines
+            .iter()
+            .position(|line| line == "BEGIN:DAYLIGHT")
+            .unwrap();
+        assert_eq!(
+            lines[daylight + 1..daylight + 5],
+            [
+                "DTSTART:20250330T020000",
+                "TZOFFSETFROM:+0100",
+                "TZOFFSETTO:+0200",
+                "TZNAME:CEST"
+            ]
+        );
+        assert!(lines.iter().any(|line| line == "DTSTART:20251026T030000"));
+    }
+
+    #[test]
+    fn test_render_ics_folds_long_lines() {
+        let summary = "Ä".repeat(100);
+        let ics = render_ics(&event(&summary), Utc::now());
+        assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+        assert!(unfold(&ics).contains(&format!("SUMMARY:{}", summary)));
+    }
+
+    #[test]
+    fn test_ics_event_from_google() {
+        let start = Utc.with_ymd_and_hms(2025, 5, 15, 8, 0, 0).unwrap();
+        let google_event = Event {
+            id: Some("abc123".to_string()),
+            summary: Some("Meeting".to_string()),
+            status: Some("cancelled".to_string()),
+            start: Some(EventDateTime {
+                date_time: Some(start),
+                ..Default::default()
+            }),
+            end: Some(EventDateTime {
+                date_time: Some(start + chrono::Duration::hours(1)),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let ics_event = IcsEvent::from_google(&google_event, Tz::Europe__Zurich).unwrap();
+        assert_eq!(ics_event.uid, "abc123@connectify");
+        assert_eq!(ics_event.start.to_rfc3339(), "2025-05-15T10:00:00+02:00");
+        assert!(ics_event.cancelled);
+        let ics = render_ics(&ics_event, Utc::now());
+        assert!(ics.contains("STATUS:CANCELLED"));
+        assert!(!ics.contains("BEGIN:VALARM"));
+
+        let all_day = Event {
+            id: Some("holiday".to_string()),
+            ..Default::default()
+        };
+        assert!(IcsEvent::from_google(&all_day, Tz::Europe__Zurich).is_none());
+    }
+}
@@ -1,12 +1,30 @@
 // --- File: crates/connectify_gcal/src/lib.rs ---
 // Declare modules within this crate
+pub mod assignment;
+#[cfg(test)]
+mod assignment_test;
 pub mod auth;
 #[cfg(test)]
 mod auth_test;
+pub mod blackout;
+#[cfg(test)]
+mod blackout_test;
+pub mod caldav;
+#[cfg(test)]
+mod caldav_test;
+pub mod capacity;
+#[cfg(test)]
+mod capacity_test;
 pub mod doc;
 pub mod handlers;
 #[cfg(test)]
 mod handlers_test;
+pub mod holds;
+#[cfg(test)]
+mod holds_test;
+pub mod ics;
+#[cfg(test)]
+mod ics_test;
 pub mod logic;
 #[cfg(test)]
 mod logic_midnight_test;
@@ -14,6 +32,15 @@ mod logic_midnight_test;
 mod logic_proptest;
 #[cfg(test)]
 mod logic_test;
+pub mod mirror;
+#[cfg(test)]
+mod mirror_test;
+pub mod oauth;
+#[cfg(test)]
+mod oauth_test;
 pub mod routes;
+pub mod schedule_exceptions;
+#[cfg(test)]
+mod schedule_exceptions_test;
 pub mod service;
 mod test;
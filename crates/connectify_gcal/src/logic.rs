@@ -1,15 +1,23 @@
 // --- File: crates/connectify_gcal/src/logic.rs ---
 use crate::auth::HubType; // Use the specific Hub type alias
 use crate::service::{GcalServiceError, GoogleCalendarService};
-use chrono::{DateTime, Datelike, Duration, NaiveTime, Weekday}; // Use chrono Duration
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday}; // Use chrono Duration
 use chrono_tz::Tz;
-use connectify_common::services::{CalendarEvent as CommonCalendarEvent, CalendarService};
+use connectify_common::cache::{self, cache};
+use connectify_common::models::{CursorQuery, Page, SortOrder};
+use connectify_common::services::{
+    CalendarEvent as CommonCalendarEvent, CalendarEventPatch, CalendarService,
+};
+use connectify_common::{external_service_error, ConnectifyError};
+use connectify_config::{AppConfig, GcalCalendar, GcalConfig, PriceTier};
 use google_calendar3::api::Event; //, EventDateTime};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc; //, CalendarEventResult, , BookedEvent as CommonBookedEvent};
-use tracing::{debug, error};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use tracing::debug;
 #[cfg(feature = "openapi")]
-use utoipa::ToSchema; //, IntoParams};
+use utoipa::ToSchema;
+use validator::{Validate, ValidateEmail, ValidationError}; //, CalendarEventResult, , BookedEvent as CommonBookedEvent}; //, IntoParams};
 
 // --- Error Handling ---
 use thiserror::Error;
@@ -29,8 +37,36 @@ pub enum GcalError {
     ServiceError(#[from] GcalServiceError),
 }
 
+impl GcalError {
+    /// Returns `true` if Google kept rate-limiting the call after all retries.
+    pub fn is_rate_limited(&self) -> bool {
+        match self {
+            GcalError::ApiError(e) => crate::service::is_rate_limit_error(e),
+            GcalError::ServiceError(GcalServiceError::RateLimited { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Convert GcalError to ConnectifyError
+impl From<GcalError> for ConnectifyError {
+    fn from(err: GcalError) -> Self {
+        match err {
+            GcalError::ApiError(e) => external_service_error("Google Calendar API", e),
+            GcalError::TimeParseError(msg) => ConnectifyError::ParseError(msg),
+            GcalError::CalculationError(msg) => ConnectifyError::InternalError(msg),
+            GcalError::Conflict => ConnectifyError::ConflictError("Booking conflict".to_string()),
+            GcalError::NoMatchingPriceTier(duration) => ConnectifyError::ValidationError(format!(
+                "No matching price tier found for duration: {} minutes",
+                duration
+            )),
+            GcalError::ServiceError(e) => e.into(),
+        }
+    }
+}
+
 // --- Data Structures ---
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 #[cfg_attr(feature = "openapi", derive(utoipa::IntoParams, utoipa::ToSchema))]
 #[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
 pub struct AvailabilityQuery {
@@ -45,14 +81,100 @@ pub struct AvailabilityQuery {
     /// Duration in minutes
     #[cfg_attr(feature = "openapi", schema(example = 45))]
     pub duration_minutes: i64,
+
+    /// Only return slots of this calendar (default: all configured calendars)
+    pub calendar_id: Option<String>,
+
+    /// IANA time zone to render slot times in (default: the configured time zone)
+    #[cfg_attr(feature = "openapi", schema(example = "America/New_York"))]
+    pub time_zone: Option<String>,
+
+    /// Currency to price slots in (default: the price tier's currency)
+    #[cfg_attr(feature = "openapi", schema(example = "EUR"))]
+    pub currency: Option<String>,
+}
+
+/// Query for the next bookable slots from now on.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams, utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct NextAvailabilityQuery {
+    /// Duration in minutes
+    #[cfg_attr(feature = "openapi", schema(example = 60))]
+    pub duration_minutes: i64,
+
+    /// How many slots to return (default 5, at most 50)
+    #[cfg_attr(feature = "openapi", schema(example = 5))]
+    pub count: Option<u32>,
+
+    /// Only return slots of this calendar (default: all configured calendars)
+    pub calendar_id: Option<String>,
+
+    /// IANA time zone to render slot times in (default: the configured time zone)
+    #[cfg_attr(feature = "openapi", schema(example = "America/New_York"))]
+    pub time_zone: Option<String>,
+
+    /// Currency to price slots in (default: the price tier's currency)
+    #[cfg_attr(feature = "openapi", schema(example = "EUR"))]
+    pub currency: Option<String>,
+}
+
+/// The number of next slots returned if the query doesn't say.
+pub const DEFAULT_NEXT_SLOTS_COUNT: u32 = 5;
+
+/// The most next slots returned at once.
+pub const MAX_NEXT_SLOTS_COUNT: u32 = 50;
+
+/// How many days ahead the next slots are searched if `max_advance_days` isn't configured.
+pub const NEXT_SLOTS_SEARCH_DAYS: i64 = 90;
+
+/// Checks the requested number of next slots.
+pub fn validate_next_slots_count(count: Option<u32>) -> Result<usize, String> {
+    match count.unwrap_or(DEFAULT_NEXT_SLOTS_COUNT) {
+        count @ 1..=MAX_NEXT_SLOTS_COUNT => Ok(count as usize),
+        _ => Err(format!(
+            "count must be between 1 and {}",
+            MAX_NEXT_SLOTS_COUNT
+        )),
+    }
 }
 
 #[derive(Serialize, Debug)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct AvailableSlotsResponse {
+    /// The time zone the slot times are rendered in
+    #[cfg_attr(feature = "openapi", schema(example = "Europe/Zurich"))]
+    pub time_zone: String,
+    pub slots: Vec<PricedSlot>,
+}
+
+/// Several availability queries answered at once, e.g. one per offered duration.
+#[derive(Deserialize, Debug, Validate)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct BatchAvailabilityRequest {
+    #[validate(length(min = 1, max = 20))]
+    pub queries: Vec<AvailabilityQuery>,
+}
+
+/// The slots of one query of a batch, in the order of the queries.
+#[derive(Serialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct BatchAvailabilityResult {
+    pub start_date: String,
+    pub end_date: String,
+    pub duration_minutes: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calendar_id: Option<String>,
+    pub time_zone: String,
     pub slots: Vec<PricedSlot>,
 }
 
+#[derive(Serialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct BatchAvailabilityResponse {
+    pub results: Vec<BatchAvailabilityResult>,
+}
+
 #[derive(Serialize, Debug, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct PricedSlot {
@@ -60,6 +182,10 @@ pub struct PricedSlot {
     pub start_time: String, // ISO 8601 format
     #[cfg_attr(feature = "openapi", schema(example = "2025-05-15T11:00:00Z"))]
     pub end_time: String, // ISO 8601 format
+    #[cfg_attr(feature = "openapi", schema(example = "2025-05-15T08:00:00+00:00"))]
+    pub start_time_utc: String,
+    #[cfg_attr(feature = "openapi", schema(example = "2025-05-15T09:00:00+00:00"))]
+    pub end_time_utc: String,
     #[cfg_attr(feature = "openapi", schema(example = 60))]
     pub duration_minutes: i64,
     #[cfg_attr(feature = "openapi", schema(example = 7500))] // e.g. 75.00 CHF in cents
@@ -68,19 +194,97 @@ pub struct PricedSlot {
     pub currency: String,
     #[cfg_attr(feature = "openapi", schema(example = "Premium Beratung (60 Min)"))]
     pub product_name: Option<String>,
+    /// The calendar the slot would be booked in
+    #[cfg_attr(feature = "openapi", schema(example = "primary"))]
+    pub calendar_id: String,
+    /// Name of the staff member or resource owning the calendar, if configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_name: Option<String>,
+    /// Free seats of a group slot, for calendars with a capacity
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "openapi", schema(example = 5))]
+    pub remaining_seats: Option<u32>,
 }
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone, Validate)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[validate(schema(function = "validate_booking_times"))]
 pub struct BookSlotRequest {
+    #[validate(custom(function = "connectify_common::validation::validate_rfc3339"))]
     pub start_time: String, // ISO 8601 format string
-    pub end_time: String,   // ISO 8601 format string
-    pub summary: String,    // Event title
+    #[validate(custom(function = "connectify_common::validation::validate_rfc3339"))]
+    pub end_time: String, // ISO 8601 format string
+    #[validate(length(min = 1))]
+    pub summary: String, // Event title
     pub description: Option<String>,
     pub payment_method: Option<String>,
     pub payment_id: Option<String>,
     pub payment_amount: Option<i64>,
     pub room_name: Option<String>,
-    // Add attendee emails, etc., if needed
+    /// The URL of the invoice of the payment, stored with the event
+    #[serde(default)]
+    pub invoice_url: Option<String>,
+    /// The calendar to book in, one of the configured calendars (default: assigned by the
+    /// configured `assignment` strategy, else `calendar_id`)
+    pub calendar_id: Option<String>,
+    /// Email addresses to send a calendar invitation to, e.g. the customer's
+    #[serde(default)]
+    #[validate(custom(function = "validate_attendees"))]
+    pub attendees: Vec<String>,
+    /// Attach a Google Meet video conference and return its link
+    #[serde(default)]
+    pub create_meet_link: bool,
+    /// The hold on the slot, released once booked (see `POST /gcal/holds`)
+    #[serde(default)]
+    pub hold_id: Option<String>,
+    /// Set for calendars with a capacity: the event leaves its time free, and its seat is
+    /// counted in the booking ledger instead
+    #[serde(skip)]
+    pub group_seat: bool,
+    /// Color and tags of the event, set from the price tier and fulfillment type
+    #[serde(skip)]
+    pub style: EventStyle,
+}
+
+/// Checks that all attendees are email addresses.
+fn validate_attendees(attendees: &[String]) -> Result<(), ValidationError> {
+    if attendees.iter().all(|email| email.validate_email()) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("email"))
+    }
+}
+
+/// Checks that a booking ends after it starts.
+fn validate_booking_times(request: &BookSlotRequest) -> Result<(), ValidationError> {
+    let start = DateTime::parse_from_rfc3339(&request.start_time);
+    let end = DateTime::parse_from_rfc3339(&request.end_time);
+    match (start, end) {
+        (Ok(start), Ok(end)) if end <= start => Err(ValidationError::new("end_before_start")),
+        _ => Ok(()),
+    }
+}
+
+/// Request to move a booking to another time.
+#[derive(Deserialize, Debug, Validate)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[validate(schema(function = "validate_reschedule_times"))]
+pub struct RescheduleBookingRequest {
+    #[validate(custom(function = "connectify_common::validation::validate_rfc3339"))]
+    pub start_time: String, // ISO 8601 format string
+    #[validate(custom(function = "connectify_common::validation::validate_rfc3339"))]
+    pub end_time: String, // ISO 8601 format string
+    /// Whether to notify attendees about the new time (default: true)
+    pub notify_attendees: Option<bool>,
+}
+
+/// Checks that a rescheduled booking ends after it starts.
+fn validate_reschedule_times(request: &RescheduleBookingRequest) -> Result<(), ValidationError> {
+    let start = DateTime::parse_from_rfc3339(&request.start_time);
+    let end = DateTime::parse_from_rfc3339(&request.end_time);
+    match (start, end) {
+        (Ok(start), Ok(end)) if end <= start => Err(ValidationError::new("end_before_start")),
+        _ => Ok(()),
+    }
 }
 
 #[derive(Serialize, Debug)]
@@ -89,6 +293,12 @@ pub struct BookingResponse {
     pub success: bool,
     pub event_id: Option<String>,
     pub message: String,
+    /// The calendar the booking is in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calendar_id: Option<String>,
+    /// The Google Meet URL, if a video conference was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meet_link: Option<String>,
 }
 
 // --- Availability Logic ---
@@ -117,30 +327,591 @@ pub async fn get_busy_times(
     Ok(converted_busy_periods)
 }
 
-/// Configuration for working hours and days
-pub struct WorkingHoursConfig<'a> {
-    /// Start time of the working day (e.g., 9:00 AM)
-    pub start_time: NaiveTime,
-    /// End time of the working day (e.g., 5:00 PM)
-    pub end_time: NaiveTime,
-    /// Days of the week when appointments can be scheduled
-    pub working_days: &'a [Weekday],
+/// How long free/busy results of the availability endpoint are cached by default.
+const DEFAULT_BUSY_TIMES_CACHE_SECONDS: u64 = 30;
+
+/// How long watched calendars send push notifications before the channel must be renewed.
+const PUSH_CHANNEL_LIFETIME_HOURS: i64 = 48;
+
+/// The configured free/busy cache lifetime; zero disables caching.
+pub fn busy_times_cache_ttl(config: &GcalConfig) -> std::time::Duration {
+    std::time::Duration::from_secs(
+        config
+            .busy_times_cache_seconds
+            .unwrap_or(DEFAULT_BUSY_TIMES_CACHE_SECONDS),
+    )
+}
+
+fn busy_times_cache_prefix(calendar_id: &str) -> String {
+    format!("gcal:busy:{}:", calendar_id)
+}
+
+/// Like [`get_busy_times`], but served from the cache for a short time.
+///
+/// Only meant for displaying availability: bookings must check the calendar itself.
+pub async fn get_cached_busy_times(
+    hub: &HubType,
+    calendar_id: &str,
+    start_time: DateTime<Tz>,
+    end_time: DateTime<Tz>,
+    ttl: std::time::Duration,
+) -> Result<Vec<(DateTime<Tz>, DateTime<Tz>)>, GcalError> {
+    if ttl.is_zero() {
+        return get_busy_times(hub, calendar_id, start_time, end_time).await;
+    }
+    let timezone = start_time.timezone();
+    let cache_key = format!(
+        "{}{}:{}",
+        busy_times_cache_prefix(calendar_id),
+        start_time.timestamp(),
+        end_time.timestamp()
+    );
+    if let Some(periods) =
+        cache::get_json::<Vec<(DateTime<Utc>, DateTime<Utc>)>>(&*cache(), &cache_key).await
+    {
+        return Ok(periods
+            .into_iter()
+            .map(|(start, end)| (start.with_timezone(&timezone), end.with_timezone(&timezone)))
+            .collect());
+    }
+
+    let periods = get_busy_times(hub, calendar_id, start_time, end_time).await?;
+    let utc_periods: Vec<(DateTime<Utc>, DateTime<Utc>)> = periods
+        .iter()
+        .map(|(start, end)| (start.with_timezone(&Utc), end.with_timezone(&Utc)))
+        .collect();
+    cache::set_json(&*cache(), &cache_key, &utc_periods, ttl).await;
+    Ok(periods)
+}
+
+/// Drop the cached free/busy results of a calendar, e.g. after a booking.
+pub async fn invalidate_busy_times_cache(calendar_id: &str) {
+    cache::invalidate_prefix(&*cache(), &busy_times_cache_prefix(calendar_id)).await;
+}
+
+/// Extract the calendar id from the `X-Goog-Resource-URI` of a push notification,
+/// e.g. `https://www.googleapis.com/calendar/v3/calendars/team%40example.com/events?alt=json`.
+pub fn calendar_id_from_resource_uri(uri: &str) -> Option<String> {
+    let encoded = uri.split("/calendars/").nth(1)?.split(['/', '?']).next()?;
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            let byte = u8::from_str_radix(hex, 16).ok()?;
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok().filter(|id| !id.is_empty())
+}
+
+/// Watch every configured calendar for changes, so that push notifications can invalidate
+/// the free/busy cache. Channels expire after two days and have to be renewed before.
+///
+/// Returns the number of watched calendars.
+pub async fn watch_configured_calendars(
+    hub: &HubType,
+    config: &GcalConfig,
+    token: &str,
+) -> Result<usize, GcalError> {
+    let Some(address) = config.push_notification_url.as_deref() else {
+        return Ok(0);
+    };
+    let service = GoogleCalendarService::new(Arc::new(hub.clone()));
+    let expiration = Utc::now() + Duration::hours(PUSH_CHANNEL_LIFETIME_HOURS);
+    let calendars = configured_calendars(config);
+    for calendar in &calendars {
+        let channel_id = service
+            .watch_events(&calendar.id, address, token, expiration)
+            .await?;
+        debug!(
+            "Watching calendar {} via channel {}",
+            calendar.id, channel_id
+        );
+    }
+    Ok(calendars.len())
+}
+
+/// The calendars whose availability is offered: `calendar_id` first, then `calendars`.
+pub fn configured_calendars(config: &GcalConfig) -> Vec<GcalCalendar> {
+    let mut calendars: Vec<GcalCalendar> = config
+        .calendar_id
+        .iter()
+        .map(|id| GcalCalendar {
+            id: id.clone(),
+            name: None,
+            capacity: None,
+        })
+        .collect();
+    for calendar in &config.calendars {
+        match calendars.iter_mut().find(|known| known.id == calendar.id) {
+            Some(known) => {
+                known.name = known.name.take().or_else(|| calendar.name.clone());
+                known.capacity = known.capacity.or(calendar.capacity);
+            }
+            None => calendars.push(calendar.clone()),
+        }
+    }
+    calendars
+}
+
+/// Working hours per weekday: any number of intervals a day, the gaps between them are breaks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkingHoursConfig {
+    /// Intervals of each weekday, indexed by days from Monday
+    days: [Vec<(NaiveTime, NaiveTime)>; 7],
+    /// Dates without any working hours, e.g. holidays
+    blackout_dates: BTreeSet<NaiveDate>,
+    /// Intervals of single dates replacing those of their weekday, e.g. closing early
+    date_intervals: BTreeMap<NaiveDate, Vec<(NaiveTime, NaiveTime)>>,
+}
+
+impl WorkingHoursConfig {
+    /// The same working hours on each of `working_days`.
+    pub fn uniform(start_time: NaiveTime, end_time: NaiveTime, working_days: &[Weekday]) -> Self {
+        working_days.iter().fold(Self::default(), |hours, day| {
+            hours.with_interval(*day, start_time, end_time)
+        })
+    }
+
+    /// Add a working interval to a weekday.
+    pub fn with_interval(
+        mut self,
+        day: Weekday,
+        start_time: NaiveTime,
+        end_time: NaiveTime,
+    ) -> Self {
+        let intervals = &mut self.days[day.num_days_from_monday() as usize];
+        intervals.push((start_time, end_time));
+        intervals.sort();
+        intervals.dedup();
+        self
+    }
+
+    /// The working intervals of a weekday, sorted by start time.
+    pub fn intervals(&self, day: Weekday) -> &[(NaiveTime, NaiveTime)] {
+        &self.days[day.num_days_from_monday() as usize]
+    }
+
+    /// Exclude the given dates completely, whatever their weekday.
+    pub fn with_blackout_dates(mut self, dates: impl IntoIterator<Item = NaiveDate>) -> Self {
+        self.blackout_dates.extend(dates);
+        self
+    }
+
+    /// Whether a date is excluded completely.
+    pub fn is_blackout_date(&self, date: NaiveDate) -> bool {
+        self.blackout_dates.contains(&date)
+    }
+
+    /// Replace the working intervals of a single date.
+    pub fn with_date_intervals(
+        mut self,
+        date: NaiveDate,
+        mut intervals: Vec<(NaiveTime, NaiveTime)>,
+    ) -> Self {
+        intervals.sort();
+        intervals.dedup();
+        self.date_intervals.insert(date, intervals);
+        self
+    }
+
+    /// The working intervals of a date, sorted by start time.
+    pub fn intervals_on(&self, date: NaiveDate) -> &[(NaiveTime, NaiveTime)] {
+        self.date_intervals
+            .get(&date)
+            .map(Vec::as_slice)
+            .unwrap_or_else(|| self.intervals(date.weekday()))
+    }
+
+    /// Working hours from the GCal config: `weekly_schedule` if set, otherwise `working_days`
+    /// from `work_start_time` to `work_end_time` (every day, all day by default).
+    ///
+    /// Invalid days or times are skipped.
+    pub fn from_config(config: &GcalConfig) -> Self {
+        if let Some(schedule) = &config.weekly_schedule {
+            return schedule
+                .iter()
+                .filter_map(|(day, intervals)| Some((day.parse::<Weekday>().ok()?, intervals)))
+                .flat_map(|(day, intervals)| intervals.iter().map(move |interval| (day, interval)))
+                .filter_map(|(day, interval)| {
+                    let start = NaiveTime::parse_from_str(&interval.start, "%H:%M").ok()?;
+                    let end = NaiveTime::parse_from_str(&interval.end, "%H:%M").ok()?;
+                    (start < end).then_some((day, start, end))
+                })
+                .fold(Self::default(), |hours, (day, start, end)| {
+                    hours.with_interval(day, start, end)
+                });
+        }
+
+        let parse_time = |time: &Option<String>, default: NaiveTime| {
+            time.as_deref()
+                .and_then(|time| NaiveTime::parse_from_str(time, "%H:%M").ok())
+                .unwrap_or(default)
+        };
+        let start = parse_time(&config.work_start_time, NaiveTime::MIN);
+        let end = parse_time(
+            &config.work_end_time,
+            NaiveTime::from_hms_opt(23, 59, 59).unwrap(),
+        );
+        let working_days: Vec<Weekday> = match &config.working_days {
+            Some(days) => days.iter().filter_map(|day| day.parse().ok()).collect(),
+            None => ALL_WEEKDAYS.to_vec(),
+        };
+        Self::uniform(start, end, &working_days)
+    }
+}
+
+const ALL_WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Free time kept around an appointment, e.g. to prepare a room or take notes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SlotBuffers {
+    /// Time kept free before the appointment
+    pub before: Duration,
+    /// Time kept free after the appointment
+    pub after: Duration,
+}
+
+impl SlotBuffers {
+    /// The buffers of appointments of a price tier, falling back to the calendar's defaults
+    /// for buffers the tier doesn't set.
+    pub fn for_tier(config: &GcalConfig, tier: Option<&PriceTier>) -> Self {
+        let minutes = |tier_minutes: Option<i64>, default_minutes: Option<i64>| {
+            Duration::minutes(tier_minutes.or(default_minutes).unwrap_or(0).max(0))
+        };
+        Self {
+            before: minutes(
+                tier.and_then(|tier| tier.buffer_before_minutes),
+                config.buffer_before_minutes,
+            ),
+            after: minutes(
+                tier.and_then(|tier| tier.buffer_after_minutes),
+                config.buffer_after_minutes,
+            ),
+        }
+    }
+
+    /// The time from `start` to `end` including the buffers, which must not overlap busy times.
+    pub fn widen<T: chrono::TimeZone>(
+        &self,
+        start: DateTime<T>,
+        end: DateTime<T>,
+    ) -> (DateTime<T>, DateTime<T>) {
+        (start - self.before, end + self.after)
+    }
+}
+
+/// Fulfillment type of bookings paid through the checkout (`/fulfill/gcal-booking`).
+pub const FULFILLMENT_TYPE_BOOKING: &str = "gcal_booking";
+/// Fulfillment type of adhoc video sessions (`/fulfill/adhoc-gcal-twilio`).
+pub const FULFILLMENT_TYPE_ADHOC: &str = "adhoc";
+
+/// How the event of a booking looks in the calendar, so mixed-purpose calendars stay readable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventStyle {
+    /// Google Calendar `colorId` of the event
+    pub color_id: Option<String>,
+    /// Tags stored with the event, to filter booked events by
+    pub tags: Vec<String>,
+}
+
+impl EventStyle {
+    /// The style of a booking of a price tier made through a fulfillment type: the tier's
+    /// color takes precedence over the fulfillment type's, tags of both are combined.
+    pub fn resolve(
+        config: &GcalConfig,
+        tier: Option<&PriceTier>,
+        fulfillment_type: Option<&str>,
+    ) -> Self {
+        let fulfillment_style = fulfillment_type
+            .and_then(|fulfillment_type| config.fulfillment_styles.get(fulfillment_type));
+        let color_id = tier
+            .and_then(|tier| tier.color_id.clone())
+            .or_else(|| fulfillment_style.and_then(|style| style.color_id.clone()));
+        let tags: BTreeSet<String> = fulfillment_style
+            .into_iter()
+            .flat_map(|style| style.tags.iter())
+            .chain(tier.into_iter().flat_map(|tier| tier.tags.iter()))
+            .cloned()
+            .collect();
+        Self {
+            color_id,
+            tags: tags.into_iter().collect(),
+        }
+    }
+
+    /// The style of a booking from `start_time` to `end_time` (RFC 3339), whose price tier is
+    /// found by its duration.
+    pub fn for_booking(
+        config: &AppConfig,
+        start_time: &str,
+        end_time: &str,
+        fulfillment_type: Option<&str>,
+    ) -> Self {
+        let Some(gcal_config) = config.gcal.as_ref() else {
+            return Self::default();
+        };
+        let duration_minutes = match (
+            DateTime::parse_from_rfc3339(start_time),
+            DateTime::parse_from_rfc3339(end_time),
+        ) {
+            (Ok(start), Ok(end)) => Some((end - start).num_minutes()),
+            _ => None,
+        };
+        let tier = duration_minutes
+            .and_then(|duration_minutes| price_tier_for_duration(config, duration_minutes));
+        Self::resolve(gcal_config, tier, fulfillment_type)
+    }
+}
+
+/// The price tier of appointments lasting `duration_minutes`, if one is configured.
+pub fn price_tier_for_duration(config: &AppConfig, duration_minutes: i64) -> Option<&PriceTier> {
+    config
+        .pricing
+        .as_ref()
+        .and_then(|pricing_config| pricing_config.tier_for_duration(duration_minutes))
+}
+
+/// The times slots can be booked at: from the preparation time on, up to the maximum number of
+/// days in advance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookingWindow {
+    /// The earliest bookable start time
+    pub earliest: DateTime<Utc>,
+    /// The latest bookable start time, if limited
+    pub latest: Option<DateTime<Utc>>,
+}
+
+impl BookingWindow {
+    /// The booking window at `now`; the preparation time defaults to 120 minutes.
+    pub fn from_config(config: &GcalConfig, now: DateTime<Utc>) -> Self {
+        Self {
+            earliest: now + Duration::minutes(config.preparation_time_minutes.unwrap_or(120)),
+            latest: config
+                .max_advance_days
+                .map(|days| now + Duration::days(days.max(0))),
+        }
+    }
+
+    /// Checks that a slot starting at `start` can be booked, explaining why not otherwise.
+    pub fn check(&self, start: DateTime<Utc>) -> Result<(), String> {
+        if start < self.earliest {
+            return Err("Requested time slot is too soon.".to_string());
+        }
+        match self.latest {
+            Some(latest) if start > latest => Err(format!(
+                "Requested time slot is too far ahead; bookings are possible until {}.",
+                latest.to_rfc3339()
+            )),
+            _ => Ok(()),
+        }
+    }
 }
 
 /// Configuration for appointment scheduling
 pub struct AppointmentConfig {
     /// Duration of each appointment
     pub duration: Duration,
+    /// Buffer time kept free before each appointment
+    pub buffer_before: Duration,
     /// Buffer time between appointments
     pub buffer_time: Duration,
     /// Time step for checking available slots
     pub step: Duration,
 }
 
+/// The busy periods of one of several calendars.
+pub struct CalendarBusyTimes<'a> {
+    pub calendar_id: &'a str,
+    pub busy_periods: &'a [(DateTime<Tz>, DateTime<Tz>)],
+}
+
+/// An available slot of one of several calendars.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarSlot {
+    /// RFC3339 start time
+    pub start_time: String,
+    /// RFC3339 end time
+    pub end_time: String,
+    pub calendar_id: String,
+}
+
+/// Calculates the combined available slots of several calendars, e.g. one per staff member.
+///
+/// The slots of each calendar are calculated with [`calculate_available_slots`]. A time free
+/// in several calendars is returned once per calendar; slots are sorted by start time, then
+/// in the order of `calendars`.
+///
+/// The buffer before appointments is kept by extending the end of every busy period by it.
+pub fn calculate_combined_available_slots(
+    query_start: DateTime<Tz>,
+    query_end: DateTime<Tz>,
+    calendars: &[CalendarBusyTimes<'_>],
+    working_hours: &WorkingHoursConfig,
+    appointment: &AppointmentConfig,
+) -> Vec<CalendarSlot> {
+    let mut slots: Vec<(DateTime<chrono::FixedOffset>, CalendarSlot)> = calendars
+        .iter()
+        .flat_map(|calendar| {
+            let busy_periods: Vec<(DateTime<Tz>, DateTime<Tz>)> = calendar
+                .busy_periods
+                .iter()
+                .map(|(start, end)| (*start, *end + appointment.buffer_before))
+                .collect();
+            calculate_available_slots(
+                query_start,
+                query_end,
+                &busy_periods,
+                appointment.duration,
+                working_hours,
+                appointment.buffer_time,
+                appointment.step,
+            )
+            .into_iter()
+            .filter_map(|(start_time, end_time)| {
+                let start = DateTime::parse_from_rfc3339(&start_time).ok()?;
+                Some((
+                    start,
+                    CalendarSlot {
+                        start_time,
+                        end_time,
+                        calendar_id: calendar.calendar_id.to_string(),
+                    },
+                ))
+            })
+        })
+        .collect();
+    // Stable sort, so calendars keep their order among slots starting at the same time
+    slots.sort_by_key(|(start, _)| *start);
+    slots.into_iter().map(|(_, slot)| slot).collect()
+}
+
 /// Calculates available slots based on busy times, working hours, etc.
 /// Returns slots as pairs of RFC3339 strings in Europe/Zurich time zone.
-#[allow(clippy::too_many_arguments)]
+///
+/// Every distinct working interval is calculated separately for the days it applies to,
+/// so a slot never spans a break.
 pub fn calculate_available_slots(
+    query_start: DateTime<Tz>,
+    query_end: DateTime<Tz>,
+    busy_periods: &[(DateTime<Tz>, DateTime<Tz>)],
+    duration: Duration,
+    working_hours: &WorkingHoursConfig,
+    buffer_time: Duration,
+    step: Duration,
+) -> Vec<(String, String)> {
+    // Group the weekdays by working interval, e.g. 09-12 and 13-17 on Mon-Fri
+    let mut days_by_interval: Vec<((NaiveTime, NaiveTime), Vec<Weekday>)> = Vec::new();
+    for day in ALL_WEEKDAYS {
+        for interval in working_hours.intervals(day) {
+            match days_by_interval
+                .iter_mut()
+                .find(|(known, _)| known == interval)
+            {
+                Some((_, days)) => days.push(day),
+                None => days_by_interval.push((*interval, vec![day])),
+            }
+        }
+    }
+
+    let time_zone = query_start.timezone();
+    let touches = |start: &DateTime<chrono::FixedOffset>,
+                   is_excluded: &dyn Fn(NaiveDate) -> bool| {
+        let start = start.with_timezone(&time_zone);
+        let last_moment = start + duration - Duration::nanoseconds(1);
+        is_excluded(start.date_naive()) || is_excluded(last_moment.date_naive())
+    };
+    let has_date_intervals = |date: NaiveDate| working_hours.date_intervals.contains_key(&date);
+
+    let mut slots: Vec<(DateTime<chrono::FixedOffset>, (String, String))> = days_by_interval
+        .iter()
+        .flat_map(|((start, end), days)| {
+            calculate_interval_slots(
+                query_start,
+                query_end,
+                busy_periods,
+                duration,
+                *start,
+                *end,
+                days,
+                buffer_time,
+                step,
+            )
+        })
+        .filter_map(|slot| Some((DateTime::parse_from_rfc3339(&slot.0).ok()?, slot)))
+        // Dates with their own intervals are calculated below
+        .filter(|(start, _)| !touches(start, &has_date_intervals))
+        .collect();
+
+    // Each date with its own intervals is calculated on its own, within the query
+    for (date, intervals) in working_hours
+        .date_intervals
+        .range(query_start.date_naive()..=query_end.date_naive())
+    {
+        let day_start = local_midnight(time_zone, *date);
+        let day_end = local_midnight(time_zone, *date + chrono::Days::new(1));
+        let (window_start, window_end) = (query_start.max(day_start), query_end.min(day_end));
+        if window_start >= window_end {
+            continue;
+        }
+        for (start, end) in intervals {
+            slots.extend(
+                calculate_interval_slots(
+                    window_start,
+                    window_end,
+                    busy_periods,
+                    duration,
+                    *start,
+                    *end,
+                    &[date.weekday()],
+                    buffer_time,
+                    step,
+                )
+                .into_iter()
+                .filter_map(|slot| Some((DateTime::parse_from_rfc3339(&slot.0).ok()?, slot))),
+            );
+        }
+    }
+
+    slots.retain(|(start, _)| !touches(start, &|date| working_hours.is_blackout_date(date)));
+    slots.sort_by_key(|(start, _)| *start);
+    slots.dedup_by_key(|(start, _)| *start);
+    slots.into_iter().map(|(_, slot)| slot).collect()
+}
+
+/// The start of a date in a time zone, or the first moment of it if the clocks go forward at
+/// midnight.
+fn local_midnight(time_zone: Tz, date: NaiveDate) -> DateTime<Tz> {
+    use chrono::TimeZone;
+    let midnight = date.and_time(NaiveTime::MIN);
+    time_zone
+        .from_local_datetime(&midnight)
+        .earliest()
+        .or_else(|| {
+            time_zone
+                .from_local_datetime(&(midnight + Duration::hours(1)))
+                .earliest()
+        })
+        .unwrap_or_else(|| time_zone.from_utc_datetime(&midnight))
+}
+
+/// Calculates the available slots within a single working interval of `working_days`.
+#[allow(clippy::too_many_arguments)]
+fn calculate_interval_slots(
     query_start: DateTime<Tz>,
     query_end: DateTime<Tz>,
     busy_periods: &[(DateTime<Tz>, DateTime<Tz>)],
@@ -173,6 +944,21 @@ pub fn calculate_available_slots(
         merged
     }
 
+    /// The given local time, the earlier one when the clocks go back and an hour later
+    /// when it falls into the gap of the clocks going forward.
+    fn local_datetime(time_zone: Tz, date: NaiveDate, time: NaiveTime) -> DateTime<Tz> {
+        let naive = date.and_time(time);
+        time_zone
+            .from_local_datetime(&naive)
+            .earliest()
+            .or_else(|| {
+                time_zone
+                    .from_local_datetime(&(naive + chrono::Duration::hours(1)))
+                    .earliest()
+            })
+            .unwrap_or_else(|| time_zone.from_utc_datetime(&naive))
+    }
+
     fn advance_to_next_working_time(
         current: DateTime<Tz>,
         work_start: NaiveTime,
@@ -183,28 +969,10 @@ pub fn calculate_available_slots(
         loop {
             let weekday = local.weekday();
             if working_days.contains(&weekday) && local.time() <= work_start {
-                return time_zone
-                    .with_ymd_and_hms(
-                        local.year(),
-                        local.month(),
-                        local.day(),
-                        work_start.hour(),
-                        work_start.minute(),
-                        0,
-                    )
-                    .unwrap();
+                return local_datetime(time_zone, local.date_naive(), work_start);
             }
             local += chrono::Duration::days(1);
-            local = time_zone
-                .with_ymd_and_hms(
-                    local.year(),
-                    local.month(),
-                    local.day(),
-                    work_start.hour(),
-                    work_start.minute(),
-                    0,
-                )
-                .unwrap();
+            local = local_datetime(time_zone, local.date_naive(), work_start);
         }
     }
 
@@ -248,16 +1016,11 @@ pub fn calculate_available_slots(
             if minute > 0 {
                 // If we're not at the start of an hour, move to the next hour
                 let next_hour = hour + 1;
-                let next_hour_time = time_zone
-                    .with_ymd_and_hms(
-                        local_now.year(),
-                        local_now.month(),
-                        local_now.day(),
-                        next_hour % 24,
-                        0,
-                        0,
-                    )
-                    .unwrap();
+                let next_hour_time = local_datetime(
+                    time_zone,
+                    local_now.date_naive(),
+                    NaiveTime::from_hms_opt(next_hour % 24, 0, 0).unwrap(),
+                );
 
                 // If we crossed to the next day, adjust the date
                 let next_hour_time = if next_hour >= 24 {
@@ -417,11 +1180,17 @@ pub async fn create_calendar_event(
         end_time: request.end_time.clone(),
         summary: request.summary.clone(),
         description: request.description.clone(),
-        // TODO move these values into Description / Summary fields as needed, for now they are skipped on serde serialize
+        // Stored as private extended properties of the event
         payment_method: request.payment_method.clone(),
         payment_id: request.payment_id.clone(),
         payment_amount: request.payment_amount,
         room_name: request.room_name.clone(),
+        invoice_url: request.invoice_url.clone(),
+        attendees: request.attendees.clone(),
+        create_meet_link: request.create_meet_link,
+        transparent: request.group_seat,
+        color_id: request.style.color_id.clone(),
+        tags: request.style.tags.clone(),
     };
     // Use the service to create the event
     let result = service.create_event(calendar_id, calendar_event).await?;
@@ -430,12 +1199,45 @@ pub async fn create_calendar_event(
     let created_event = Event {
         id: result.event_id.clone(),
         status: Some(result.status.clone()),
+        hangout_link: result.meet_link.clone(),
         ..Default::default()
     };
 
     Ok(created_event)
 }
 
+/// Moves an event in the specified Google Calendar to a new time.
+///
+/// The new slot is checked against the other events of the calendar, so an event can be
+/// moved to a time overlapping its current one.
+pub async fn reschedule_calendar_event(
+    hub: &HubType,
+    calendar_id: &str,
+    event_id: &str,
+    request: &RescheduleBookingRequest,
+) -> Result<Event, GcalError> {
+    // Create a GoogleCalendarService instance
+    let service = GoogleCalendarService::new(Arc::new(hub.clone()));
+
+    let patch = CalendarEventPatch {
+        start_time: Some(request.start_time.clone()),
+        end_time: Some(request.end_time.clone()),
+        notify_attendees: request.notify_attendees.unwrap_or(true),
+        ..Default::default()
+    };
+    let result = service.update_event(calendar_id, event_id, patch).await?;
+
+    // Construct a minimal Event object with the event ID and status
+    let updated_event = Event {
+        id: result.event_id.clone(),
+        status: Some(result.status.clone()),
+        hangout_link: result.meet_link.clone(),
+        ..Default::default()
+    };
+
+    Ok(updated_event)
+}
+
 // Add these new types to your logic module (logic.rs or logic/mod.rs)
 
 #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
@@ -491,17 +1293,31 @@ pub async fn mark_event_cancelled(
     let updated_event = Event {
         id: result.event_id.clone(),
         status: Some(result.status.clone()),
+        hangout_link: result.meet_link.clone(),
         ..Default::default()
     };
 
     Ok(updated_event)
 }
+
+/// Fetches a single event from Google Calendar.
+pub async fn get_calendar_event(
+    hub: &HubType,
+    calendar_id: &str,
+    event_id: &str,
+) -> Result<Event, GcalError> {
+    let service = GoogleCalendarService::new(Arc::new(hub.clone()));
+    Ok(service.get_event(calendar_id, event_id).await?)
+}
+
 #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct BookedEventsQuery {
     pub start_date: String,              // YYYY-MM-DD format
     pub end_date: String,                // YYYY-MM-DD format
     pub include_cancelled: Option<bool>, // Whether to include cancelled events
+    pub time_zone: Option<String>,       // IANA time zone to render event times in
+    pub tag: Option<String>,             // Only events with this tag
 }
 
 #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
@@ -512,36 +1328,92 @@ pub struct BookedEvent {
     pub description: Option<String>,
     pub start_time: String, // ISO 8601 format
     pub end_time: String,   // ISO 8601 format
-    pub status: String,     // "confirmed", "cancelled", etc.
-    pub created: String,    // ISO 8601 format
-    pub updated: String,    // ISO 8601 format
+    pub start_time_utc: String,
+    pub end_time_utc: String,
+    pub status: String,  // "confirmed", "cancelled", etc.
+    pub created: String, // ISO 8601 format
+    pub updated: String, // ISO 8601 format
     pub payment_method: Option<String>,
     pub payment_id: Option<String>,
     pub payment_amount: Option<i64>,
     pub room_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invoice_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color_id: Option<String>,
+    pub tags: Vec<String>,
 }
 
-#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
-#[derive(Debug, serde::Serialize)]
-pub struct BookedEventsResponse {
-    pub events: Vec<BookedEvent>,
+/// A page of booked events; the cursor is the Google Calendar page token of the next page.
+pub type BookedEventsResponse = Page<BookedEvent>;
+
+/// Checks the requested page of booked events and returns its size.
+///
+/// Google Calendar lists events by start time, oldest first, so only `order=asc` is accepted.
+/// Without `limit` and `cursor`, `None` is returned to list all events.
+pub fn validate_booked_events_page(page: &CursorQuery) -> Result<Option<i32>, String> {
+    if page.order == Some(SortOrder::Desc) {
+        return Err("Booked events can only be listed by start time, with order=asc".to_string());
+    }
+    if page.limit.is_none() && page.cursor.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(page.limit() as i32))
+}
+
+/// Parses the IANA time zone a client asked times to be rendered in.
+pub fn parse_client_time_zone(time_zone: &str) -> Result<Tz, String> {
+    time_zone
+        .parse()
+        .map_err(|_| format!("Invalid time_zone: {}", time_zone))
+}
+
+/// Renders an RFC 3339 time in the given time zone; other values are returned unchanged.
+pub fn render_in_time_zone(time: &str, time_zone: &Tz) -> String {
+    DateTime::parse_from_rfc3339(time)
+        .map(|time| time.with_timezone(time_zone).to_rfc3339())
+        .unwrap_or_else(|_| time.to_string())
 }
 
 /// Fetches booked events from Google Calendar within a specified date range.
+///
+/// Without `page_size` and `cursor`, all events are returned; otherwise a single page, along
+/// with the Google Calendar page token of the next one as its cursor. With a `tag`, only events tagged with it are
+/// returned. Event times are rendered in `time_zone` if given, else as returned by Google.
+#[allow(clippy::too_many_arguments)]
 pub async fn get_booked_events(
     hub: &HubType,
     calendar_id: &str,
     start_time: DateTime<Tz>,
     end_time: DateTime<Tz>,
     include_cancelled: bool,
-) -> Result<Vec<BookedEvent>, GcalError> {
+    tag: Option<&str>,
+    time_zone: Option<Tz>,
+    page_size: Option<i32>,
+    cursor: Option<&str>,
+) -> Result<BookedEventsResponse, GcalError> {
     // Create a GoogleCalendarService instance
     let service = GoogleCalendarService::new(Arc::new(hub.clone()));
 
     // Use the service to get booked events
-    let events = service
-        .get_booked_events(calendar_id, start_time, end_time, include_cancelled)
-        .await?;
+    let (events, next_page_token) = if page_size.is_none() && cursor.is_none() {
+        let events = service
+            .get_all_booked_events(calendar_id, start_time, end_time, include_cancelled, tag)
+            .await?;
+        (events, None)
+    } else {
+        service
+            .get_booked_events_page(
+                calendar_id,
+                start_time,
+                end_time,
+                include_cancelled,
+                tag,
+                cursor,
+                page_size,
+            )
+            .await?
+    };
 
     // Convert the events to the format expected by the handlers
     let booked_events = events
@@ -550,8 +1422,16 @@ pub async fn get_booked_events(
             event_id: event.event_id,
             summary: event.summary,
             description: event.description,
-            start_time: event.start_time,
-            end_time: event.end_time,
+            start_time_utc: render_in_time_zone(&event.start_time, &Tz::UTC),
+            end_time_utc: render_in_time_zone(&event.end_time, &Tz::UTC),
+            start_time: match &time_zone {
+                Some(time_zone) => render_in_time_zone(&event.start_time, time_zone),
+                None => event.start_time,
+            },
+            end_time: match &time_zone {
+                Some(time_zone) => render_in_time_zone(&event.end_time, time_zone),
+                None => event.end_time,
+            },
             status: event.status,
             created: event.created,
             updated: event.updated,
@@ -559,8 +1439,15 @@ pub async fn get_booked_events(
             payment_id: event.payment_id,
             payment_amount: event.payment_amount,
             room_name: event.room_name,
+            invoice_url: event.invoice_url,
+            color_id: event.color_id,
+            tags: event.tags,
         })
         .collect();
 
-    Ok(booked_events)
+    Ok(Page {
+        items: booked_events,
+        has_more: next_page_token.is_some(),
+        next_cursor: next_page_token,
+    })
 }
@@ -1,8 +1,9 @@
 #[cfg(test)]
 mod tests {
-    use crate::logic::calculate_available_slots;
+    use crate::logic::{calculate_available_slots, WorkingHoursConfig};
     use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Timelike, Utc, Weekday};
     use chrono_tz::Tz;
+    use connectify_common::clock::{Clock, MockClock};
     use std::str::FromStr;
     use tracing::debug;
 
@@ -11,8 +12,9 @@ mod tests {
         // Test case: Ensure slots are available at 23:00-00:00 and first slot is 2h in the future
         let time_zone = Tz::from_str("Europe/Zurich").unwrap();
 
-        // Use the current time in Zurich timezone
-        let now = Utc::now().with_timezone(&time_zone);
+        // Fix "now" in the morning, so the 23:00 slot is always ahead of the preparation time
+        let clock = MockClock::new(Utc.with_ymd_and_hms(2025, 5, 5, 7, 0, 0).unwrap());
+        let now = clock.now().with_timezone(&time_zone);
 
         // Set prepare_time_minutes to 120 (2 hours) as required
         let prepare_time = Duration::minutes(120);
@@ -63,9 +65,7 @@ mod tests {
             query_end,
             &busy_periods,
             duration,
-            work_start,
-            work_end,
-            &working_days,
+            &WorkingHoursConfig::uniform(work_start, work_end, &working_days),
             buffer,
             step,
         );
@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod tests {
-    use crate::logic::calculate_available_slots;
+    use crate::logic::{calculate_available_slots, WorkingHoursConfig};
     use chrono::{DateTime, Duration, NaiveTime, Utc, Weekday};
     use chrono_tz::Tz;
     use proptest::prelude::*;
@@ -80,9 +80,7 @@ mod tests {
                 end,
                 &busy_periods,
                 appointment_duration,
-                work_start,
-                work_end,
-                &working_days,
+                &WorkingHoursConfig::uniform(work_start, work_end, &working_days),
                 Duration::minutes(0), // No buffer
                 Duration::minutes(15), // 15-minute step
             );
@@ -141,9 +139,7 @@ mod tests {
                 end,
                 &busy_periods,
                 appointment_duration,
-                work_start,
-                work_end,
-                &working_days,
+                &WorkingHoursConfig::uniform(work_start, work_end, &working_days),
                 Duration::minutes(0), // No buffer
                 Duration::minutes(15), // 15-minute step
             );
@@ -198,9 +194,7 @@ mod tests {
                 end,
                 &busy_periods,
                 appointment_duration,
-                work_start,
-                work_end,
-                &working_days,
+                &WorkingHoursConfig::uniform(work_start, work_end, &working_days),
                 buffer,
                 Duration::minutes(15), // 15-minute step
             );
@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
-    use crate::logic::calculate_available_slots;
-    use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc, Weekday};
+    use crate::logic::{calculate_available_slots, WorkingHoursConfig};
+    use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Timelike, Utc, Weekday};
     use chrono_tz::Tz;
     use std::str::FromStr;
     use tracing::info;
@@ -31,9 +31,7 @@ mod tests {
             query_end,
             &busy_periods,
             duration,
-            work_start,
-            work_end,
-            &working_days,
+            &WorkingHoursConfig::uniform(work_start, work_end, &working_days),
             buffer,
             step,
         );
@@ -128,9 +126,7 @@ mod tests {
             query_end,
             &busy_periods,
             duration,
-            work_start,
-            work_end,
-            &working_days,
+            &WorkingHoursConfig::uniform(work_start, work_end, &working_days),
             buffer,
             step,
         );
@@ -197,9 +193,7 @@ mod tests {
             query_end,
             &busy_periods,
             duration,
-            work_start,
-            work_end,
-            &working_days,
+            &WorkingHoursConfig::uniform(work_start, work_end, &working_days),
             buffer,
             step,
         );
@@ -281,9 +275,7 @@ mod tests {
             query_end,
             &busy_periods,
             duration,
-            work_start,
-            work_end,
-            &working_days,
+            &WorkingHoursConfig::uniform(work_start, work_end, &working_days),
             buffer,
             step,
         );
@@ -333,4 +325,434 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_calculate_combined_available_slots() {
+        use crate::logic::{
+            calculate_combined_available_slots, AppointmentConfig, CalendarBusyTimes,
+        };
+
+        let time_zone = Tz::from_str("Europe/Zurich").unwrap();
+        let query_start = time_zone.with_ymd_and_hms(2025, 5, 5, 9, 0, 0).unwrap(); // Monday
+        let query_end = time_zone.with_ymd_and_hms(2025, 5, 5, 12, 0, 0).unwrap();
+        // Anna is busy from 9 to 11, Ben from 10 to 12
+        let anna_busy = [(query_start, query_start + Duration::hours(2))];
+        let ben_busy = [(query_start + Duration::hours(1), query_end)];
+        let working_days = [Weekday::Mon];
+
+        let slots = calculate_combined_available_slots(
+            query_start,
+            query_end,
+            &[
+                CalendarBusyTimes {
+                    calendar_id: "anna",
+                    busy_periods: &anna_busy,
+                },
+                CalendarBusyTimes {
+                    calendar_id: "ben",
+                    busy_periods: &ben_busy,
+                },
+            ],
+            &WorkingHoursConfig::uniform(
+                NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+                &working_days,
+            ),
+            &AppointmentConfig {
+                duration: Duration::minutes(60),
+                buffer_before: Duration::minutes(0),
+                buffer_time: Duration::minutes(0),
+                step: Duration::minutes(15),
+            },
+        );
+
+        let summary: Vec<(u32, &str)> = slots
+            .iter()
+            .map(|slot| {
+                let start = DateTime::parse_from_rfc3339(&slot.start_time).unwrap();
+                (start.hour(), slot.calendar_id.as_str())
+            })
+            .collect();
+        assert_eq!(summary, vec![(9, "ben"), (11, "anna")]);
+    }
+
+    #[test]
+    fn test_booking_window() {
+        use crate::logic::BookingWindow;
+        use connectify_config::GcalConfig;
+
+        let now = Utc.with_ymd_and_hms(2025, 5, 5, 8, 0, 0).unwrap();
+        let config: GcalConfig =
+            serde_json::from_str(r#"{ "preparation_time_minutes": 120, "max_advance_days": 60 }"#)
+                .unwrap();
+        let window = BookingWindow::from_config(&config, now);
+        assert!(window.check(now + Duration::hours(1)).is_err());
+        assert!(window.check(now + Duration::hours(2)).is_ok());
+        assert!(window.check(now + Duration::days(60)).is_ok());
+        assert!(window
+            .check(now + Duration::days(61))
+            .unwrap_err()
+            .contains("too far ahead"));
+
+        // Without max_advance_days, bookings can be made any time ahead
+        let config: GcalConfig = serde_json::from_str("{}").unwrap();
+        let window = BookingWindow::from_config(&config, now);
+        assert_eq!(window.latest, None);
+        assert!(window.check(now + Duration::days(365)).is_ok());
+    }
+
+    #[test]
+    fn test_slot_buffers() {
+        use crate::logic::{
+            calculate_combined_available_slots, AppointmentConfig, CalendarBusyTimes, SlotBuffers,
+        };
+        use connectify_config::{GcalConfig, PriceTier};
+        use std::collections::BTreeMap;
+
+        let config: GcalConfig =
+            serde_json::from_str(r#"{ "buffer_before_minutes": 15, "buffer_after_minutes": 10 }"#)
+                .unwrap();
+        let tier = PriceTier {
+            duration_minutes: 60,
+            unit_amount: 10000,
+            product_name: None,
+            currency: None,
+            buffer_before_minutes: Some(30),
+            buffer_after_minutes: None,
+            color_id: None,
+            tags: Vec::new(),
+            amounts: BTreeMap::new(),
+        };
+        assert_eq!(
+            SlotBuffers::for_tier(&config, Some(&tier)),
+            SlotBuffers {
+                before: Duration::minutes(30),
+                after: Duration::minutes(10),
+            }
+        );
+        assert_eq!(
+            SlotBuffers::for_tier(&config, None).before,
+            Duration::minutes(15)
+        );
+
+        // Busy from 10 to 11, with 30 minutes kept free before and after each appointment
+        let time_zone = Tz::from_str("Europe/Zurich").unwrap();
+        let query_start = time_zone.with_ymd_and_hms(2025, 5, 5, 9, 0, 0).unwrap(); // Monday
+        let query_end = time_zone.with_ymd_and_hms(2025, 5, 5, 13, 0, 0).unwrap();
+        let busy = [(
+            query_start + Duration::hours(1),
+            query_start + Duration::hours(2),
+        )];
+        let slots = calculate_combined_available_slots(
+            query_start,
+            query_end,
+            &[CalendarBusyTimes {
+                calendar_id: "anna",
+                busy_periods: &busy,
+            }],
+            &WorkingHoursConfig::uniform(
+                NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(13, 0, 0).unwrap(),
+                &[Weekday::Mon],
+            ),
+            &AppointmentConfig {
+                duration: Duration::minutes(60),
+                buffer_before: Duration::minutes(30),
+                buffer_time: Duration::minutes(30),
+                step: Duration::minutes(15),
+            },
+        );
+        let hours: Vec<u32> = slots
+            .iter()
+            .map(|slot| {
+                DateTime::parse_from_rfc3339(&slot.start_time)
+                    .unwrap()
+                    .hour()
+            })
+            .collect();
+        assert_eq!(hours, vec![12]);
+    }
+
+    #[test]
+    fn test_event_style() {
+        use crate::logic::{EventStyle, FULFILLMENT_TYPE_ADHOC, FULFILLMENT_TYPE_BOOKING};
+        use connectify_config::{GcalConfig, PriceTier};
+
+        let config: GcalConfig = serde_json::from_str(
+            r#"{ "fulfillment_styles": {
+                "gcal_booking": { "color_id": "2", "tags": ["paid"] },
+                "adhoc": { "tags": ["adhoc", "video"] }
+            } }"#,
+        )
+        .unwrap();
+        let tier: PriceTier = serde_json::from_str(
+            r#"{ "duration_minutes": 60, "unit_amount": 10000, "color_id": "9",
+                 "tags": ["consultation", "video"] }"#,
+        )
+        .unwrap();
+
+        // The tier's color wins, tags are combined without duplicates
+        assert_eq!(
+            EventStyle::resolve(&config, Some(&tier), Some(FULFILLMENT_TYPE_ADHOC)),
+            EventStyle {
+                color_id: Some("9".to_string()),
+                tags: vec![
+                    "adhoc".to_string(),
+                    "consultation".to_string(),
+                    "video".to_string()
+                ],
+            }
+        );
+        assert_eq!(
+            EventStyle::resolve(&config, None, Some(FULFILLMENT_TYPE_BOOKING)),
+            EventStyle {
+                color_id: Some("2".to_string()),
+                tags: vec!["paid".to_string()],
+            }
+        );
+        assert_eq!(
+            EventStyle::resolve(&config, None, None),
+            EventStyle::default()
+        );
+    }
+
+    #[test]
+    fn test_calculate_available_slots_with_lunch_break() {
+        let time_zone = Tz::from_str("Europe/Zurich").unwrap();
+        let query_start = time_zone.with_ymd_and_hms(2025, 5, 5, 0, 0, 0).unwrap(); // Monday
+        let query_end = query_start + Duration::days(2);
+        let at = |hour, minute| NaiveTime::from_hms_opt(hour, minute, 0).unwrap();
+        // Monday 9-12 and 13-15, Tuesday only 14-16
+        let working_hours = WorkingHoursConfig::default()
+            .with_interval(Weekday::Mon, at(13, 0), at(15, 0))
+            .with_interval(Weekday::Mon, at(9, 0), at(12, 0))
+            .with_interval(Weekday::Tue, at(14, 0), at(16, 0));
+
+        let slots = calculate_available_slots(
+            query_start,
+            query_end,
+            &[],
+            Duration::minutes(60),
+            &working_hours,
+            Duration::minutes(0),
+            Duration::minutes(15),
+        );
+
+        let starts: Vec<(u32, u32)> = slots
+            .iter()
+            .map(|(start, _)| {
+                let start = DateTime::parse_from_rfc3339(start).unwrap();
+                (start.day(), start.hour())
+            })
+            .collect();
+        assert_eq!(
+            starts,
+            vec![(5, 9), (5, 10), (5, 11), (5, 13), (5, 14), (6, 14), (6, 15)]
+        );
+    }
+
+    #[test]
+    fn test_calculate_available_slots_skips_blackout_dates() {
+        let time_zone = Tz::from_str("Europe/Zurich").unwrap();
+        let query_start = time_zone.with_ymd_and_hms(2025, 5, 5, 0, 0, 0).unwrap(); // Monday
+        let query_end = query_start + Duration::days(3);
+        let working_hours = WorkingHoursConfig::uniform(
+            NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(11, 0, 0).unwrap(),
+            &[Weekday::Mon, Weekday::Tue, Weekday::Wed],
+        )
+        .with_blackout_dates([query_start.date_naive() + Duration::days(1)]);
+
+        let slots = calculate_available_slots(
+            query_start,
+            query_end,
+            &[],
+            Duration::minutes(60),
+            &working_hours,
+            Duration::minutes(0),
+            Duration::minutes(15),
+        );
+
+        let days: Vec<u32> = slots
+            .iter()
+            .map(|(start, _)| DateTime::parse_from_rfc3339(start).unwrap().day())
+            .collect();
+        assert_eq!(days, vec![5, 5, 7, 7]);
+    }
+
+    #[test]
+    fn test_working_hours_from_config() {
+        use connectify_config::{GcalConfig, WorkInterval};
+        use std::collections::HashMap;
+
+        let at = |hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+        let interval = |start: &str, end: &str| WorkInterval {
+            start: start.to_string(),
+            end: end.to_string(),
+        };
+        let mut config = GcalConfig {
+            key_path: None,
+            calendar_id: None,
+            time_slot_duration: None,
+            preparation_time_minutes: None,
+            max_advance_days: None,
+            time_zone: None,
+            working_days: Some(vec!["Mon".to_string(), "Fri".to_string()]),
+            work_start_time: Some("09:00".to_string()),
+            work_end_time: Some("17:00".to_string()),
+            weekly_schedule: None,
+            blackout_dates: Vec::new(),
+            holiday_calendars: Vec::new(),
+            calendars: Vec::new(),
+            busy_times_cache_seconds: None,
+            push_notification_url: None,
+            hold_ttl_minutes: None,
+            buffer_before_minutes: None,
+            buffer_after_minutes: None,
+            impersonate_user: None,
+            backend: Default::default(),
+            caldav: None,
+            oauth: None,
+            mirror_events: false,
+            fulfillment_styles: Default::default(),
+            assignment: None,
+        };
+
+        let working_hours = WorkingHoursConfig::from_config(&config);
+        assert_eq!(working_hours.intervals(Weekday::Mon), &[(at(9), at(17))]);
+        assert!(working_hours.intervals(Weekday::Tue).is_empty());
+
+        config.weekly_schedule = Some(HashMap::from([
+            (
+                "Mon".to_string(),
+                vec![interval("13:00", "17:00"), interval("08:00", "12:00")],
+            ),
+            // Invalid entries are skipped
+            ("Tue".to_string(), vec![interval("18:00", "10:00")]),
+            ("Someday".to_string(), vec![interval("08:00", "12:00")]),
+        ]));
+        let working_hours = WorkingHoursConfig::from_config(&config);
+        assert_eq!(
+            working_hours.intervals(Weekday::Mon),
+            &[(at(8), at(12)), (at(13), at(17))]
+        );
+        assert!(working_hours.intervals(Weekday::Tue).is_empty());
+        assert!(working_hours.intervals(Weekday::Fri).is_empty());
+    }
+
+    #[test]
+    fn test_batch_availability_request_limits_queries() {
+        use crate::logic::BatchAvailabilityRequest;
+        use validator::Validate;
+
+        let query = serde_json::json!({
+            "start_date": "2025-05-05",
+            "end_date": "2025-05-12",
+            "duration_minutes": 60
+        });
+        let request = |count: usize| -> BatchAvailabilityRequest {
+            serde_json::from_value(serde_json::json!({ "queries": vec![query.clone(); count] }))
+                .unwrap()
+        };
+        assert!(request(0).validate().is_err());
+        assert!(request(2).validate().is_ok());
+        assert!(request(21).validate().is_err());
+    }
+
+    #[test]
+    fn test_client_time_zone_rendering() {
+        use crate::logic::{parse_client_time_zone, render_in_time_zone};
+
+        let new_york = parse_client_time_zone("America/New_York").unwrap();
+        assert_eq!(
+            render_in_time_zone("2025-05-15T14:00:00Z", &new_york),
+            "2025-05-15T10:00:00-04:00"
+        );
+        assert_eq!(
+            render_in_time_zone("2025-05-15T16:00:00+02:00", &Tz::UTC),
+            "2025-05-15T14:00:00+00:00"
+        );
+        // All-day events have no time to convert
+        assert_eq!(render_in_time_zone("2025-05-15", &new_york), "2025-05-15");
+        assert!(parse_client_time_zone("Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn test_validate_booked_events_page() {
+        use crate::logic::validate_booked_events_page;
+        use connectify_common::models::{CursorQuery, SortOrder};
+
+        let page = |limit: Option<u32>, cursor: Option<&str>, order: Option<SortOrder>| {
+            validate_booked_events_page(&CursorQuery {
+                limit,
+                cursor: cursor.map(str::to_string),
+                order,
+            })
+        };
+        assert_eq!(page(None, None, None), Ok(None));
+        assert_eq!(page(Some(50), None, None), Ok(Some(50)));
+        assert_eq!(page(None, Some("token"), None), Ok(Some(25)));
+        assert_eq!(page(Some(500), None, Some(SortOrder::Asc)), Ok(Some(100)));
+        assert!(page(Some(50), None, Some(SortOrder::Desc)).is_err());
+    }
+
+    #[test]
+    fn test_validate_next_slots_count() {
+        use crate::logic::validate_next_slots_count;
+
+        assert_eq!(validate_next_slots_count(None), Ok(5));
+        assert_eq!(validate_next_slots_count(Some(1)), Ok(1));
+        assert_eq!(validate_next_slots_count(Some(50)), Ok(50));
+        assert!(validate_next_slots_count(Some(0)).is_err());
+        assert!(validate_next_slots_count(Some(51)).is_err());
+    }
+
+    #[test]
+    fn test_book_slot_request_validates_attendees() {
+        use crate::logic::BookSlotRequest;
+        use validator::Validate;
+
+        let request: BookSlotRequest = serde_json::from_value(serde_json::json!({
+            "start_time": "2025-05-15T10:00:00Z",
+            "end_time": "2025-05-15T11:00:00Z",
+            "summary": "Consultation",
+        }))
+        .unwrap();
+        assert!(request.attendees.is_empty());
+        assert!(request.validate().is_ok());
+
+        let request = BookSlotRequest {
+            attendees: vec!["customer@example.com".to_string(), "no-email".to_string()],
+            ..request
+        };
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn test_calendar_id_from_resource_uri() {
+        use crate::logic::calendar_id_from_resource_uri;
+
+        assert_eq!(
+            calendar_id_from_resource_uri(
+                "https://www.googleapis.com/calendar/v3/calendars/team%40example.com/events?alt=json"
+            ),
+            Some("team@example.com".to_string())
+        );
+        assert_eq!(
+            calendar_id_from_resource_uri(
+                "https://www.googleapis.com/calendar/v3/calendars/primary/events"
+            ),
+            Some("primary".to_string())
+        );
+        assert_eq!(
+            calendar_id_from_resource_uri("https://www.googleapis.com/calendar/v3/users/me"),
+            None
+        );
+        assert_eq!(
+            calendar_id_from_resource_uri(
+                "https://www.googleapis.com/calendar/v3/calendars/bad%4/events"
+            ),
+            None
+        );
+    }
 }
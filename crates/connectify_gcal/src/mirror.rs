@@ -0,0 +1,174 @@
+// --- File: crates/connectify_gcal/src/mirror.rs ---
+//! Local mirror of the configured calendars' events.
+//!
+//! With `mirror_events` enabled, the backend syncs every configured calendar into the event
+//! mirror of connectify_common (the database when one is configured) every few minutes. The
+//! first sync fetches the events from [`MIRROR_HISTORY_DAYS`] ago on; later ones only fetch
+//! the changes since, using the calendar's incremental sync token. When Google expires a
+//! token, the calendar is mirrored from scratch.
+//!
+//! If the free/busy query fails, availability and conflict checks fall back to the confirmed
+//! events in the mirror.
+
+use crate::auth::HubType;
+use crate::logic::{configured_calendars, GcalError};
+use crate::service::{is_sync_token_expired, GoogleCalendarService};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use connectify_common::event_mirror::{event_mirror, MirroredEvent, MirroredEventStatus};
+use connectify_common::ConnectifyError;
+use connectify_config::GcalConfig;
+use google_calendar3::api::{Event, EventDateTime};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// How often the calendars are synced into the mirror, as a cron expression.
+pub const MIRROR_SYNC_SCHEDULE: &str = "*/5 * * * *";
+
+/// How far back the first sync of a calendar fetches events.
+pub const MIRROR_HISTORY_DAYS: i64 = 30;
+
+/// A change of a calendar to apply to the mirror.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MirrorChange {
+    /// A new or changed event, confirmed or cancelled.
+    Upsert(MirroredEvent),
+    /// A deleted event Google only reported by its ID.
+    Cancelled(String),
+}
+
+/// The time of an event's start or end; all-day events start and end at midnight UTC.
+fn event_time(time: Option<&EventDateTime>) -> Option<DateTime<Utc>> {
+    let time = time?;
+    time.date_time.or_else(|| {
+        time.date
+            .and_then(|date: NaiveDate| date.and_hms_opt(0, 0, 0))
+            .map(|midnight| Utc.from_utc_datetime(&midnight))
+    })
+}
+
+/// The change a calendar event makes to the mirror, or `None` for events without an ID.
+///
+/// Tentative events block their time like confirmed ones. `now` is used for events without
+/// an update time.
+pub fn mirror_change(calendar_id: &str, event: Event, now: DateTime<Utc>) -> Option<MirrorChange> {
+    let id = event.id.clone()?;
+    let status = if event.status.as_deref() == Some("cancelled") {
+        MirroredEventStatus::Cancelled
+    } else {
+        MirroredEventStatus::Confirmed
+    };
+    let (Some(start_time), Some(end_time)) = (
+        event_time(event.start.as_ref()),
+        event_time(event.end.as_ref()),
+    ) else {
+        return (status == MirroredEventStatus::Cancelled).then_some(MirrorChange::Cancelled(id));
+    };
+    Some(MirrorChange::Upsert(MirroredEvent {
+        id,
+        calendar_id: calendar_id.to_string(),
+        summary: event.summary,
+        start_time,
+        end_time,
+        status,
+        updated_at: event.updated.unwrap_or(now),
+    }))
+}
+
+/// Sync the changes of a calendar since its last sync into the mirror.
+///
+/// Returns the number of changed events.
+pub async fn sync_calendar(
+    hub: &HubType,
+    calendar_id: &str,
+    now: DateTime<Utc>,
+) -> Result<usize, ConnectifyError> {
+    let service = GoogleCalendarService::new(Arc::new(hub.clone()));
+    let mirror = event_mirror();
+    let time_min = now - Duration::days(MIRROR_HISTORY_DAYS);
+
+    let sync_token = mirror.sync_token(calendar_id).await?;
+    let result = service
+        .list_event_changes(calendar_id, sync_token.as_deref(), time_min)
+        .await;
+    let (events, next_sync_token) = match result {
+        Err(e) if sync_token.is_some() && is_sync_token_expired(&e) => {
+            info!("Sync token of {} expired, mirroring it again", calendar_id);
+            mirror.clear(calendar_id).await?;
+            service
+                .list_event_changes(calendar_id, None, time_min)
+                .await
+                .map_err(GcalError::from)?
+        }
+        result => result.map_err(GcalError::from)?,
+    };
+
+    let changed = events.len();
+    let mut upserts = Vec::new();
+    let mut cancelled = Vec::new();
+    for change in events
+        .into_iter()
+        .filter_map(|event| mirror_change(calendar_id, event, now))
+    {
+        match change {
+            MirrorChange::Upsert(event) => upserts.push(event),
+            MirrorChange::Cancelled(id) => cancelled.push(id),
+        }
+    }
+    mirror.upsert(upserts).await?;
+    mirror.mark_cancelled(calendar_id, cancelled, now).await?;
+    // Only move on once the changes are stored, so failed syncs are retried
+    if let Some(next_sync_token) = next_sync_token {
+        mirror.set_sync_token(calendar_id, next_sync_token).await?;
+    }
+    debug!("Mirrored {} changed events of {}", changed, calendar_id);
+    Ok(changed)
+}
+
+/// Sync every configured calendar into the mirror.
+///
+/// Returns the number of changed events.
+pub async fn sync_configured_calendars(
+    hub: &HubType,
+    config: &GcalConfig,
+    now: DateTime<Utc>,
+) -> Result<usize, ConnectifyError> {
+    let mut changed = 0;
+    for calendar in configured_calendars(config) {
+        changed += sync_calendar(hub, &calendar.id, now).await?;
+    }
+    Ok(changed)
+}
+
+/// The busy periods of a calendar from `start` to `end` according to the mirror, or `None`
+/// if the calendar hasn't been mirrored yet.
+pub async fn mirrored_busy_times(
+    calendar_id: &str,
+    start: DateTime<Tz>,
+    end: DateTime<Tz>,
+) -> Result<Option<Vec<(DateTime<Tz>, DateTime<Tz>)>>, ConnectifyError> {
+    let mirror = event_mirror();
+    if mirror.sync_token(calendar_id).await?.is_none() {
+        return Ok(None);
+    }
+    let timezone = start.timezone();
+    let events = mirror
+        .overlapping(
+            calendar_id,
+            start.with_timezone(&Utc),
+            end.with_timezone(&Utc),
+        )
+        .await?;
+    Ok(Some(
+        events
+            .into_iter()
+            .filter(|event| event.status == MirroredEventStatus::Confirmed)
+            .map(|event| {
+                (
+                    event.start_time.with_timezone(&timezone),
+                    event.end_time.with_timezone(&timezone),
+                )
+            })
+            .collect(),
+    ))
+}
@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod tests {
+    use crate::mirror::{mirror_change, mirrored_busy_times, MirrorChange};
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use chrono_tz::Tz;
+    use connectify_common::event_mirror::{event_mirror, MirroredEvent, MirroredEventStatus};
+    use google_calendar3::api::{Event, EventDateTime};
+
+    fn timed(hour: u32) -> Option<EventDateTime> {
+        Some(EventDateTime {
+            date_time: Some(Utc.with_ymd_and_hms(2025, 5, 15, hour, 0, 0).unwrap()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_mirror_change() {
+        let now = Utc.with_ymd_and_hms(2025, 5, 1, 12, 0, 0).unwrap();
+        let updated = Utc.with_ymd_and_hms(2025, 4, 30, 8, 0, 0).unwrap();
+
+        let event = Event {
+            id: Some("confirmed".to_string()),
+            status: Some("confirmed".to_string()),
+            summary: Some("Consultation".to_string()),
+            start: timed(10),
+            end: timed(11),
+            updated: Some(updated),
+            ..Default::default()
+        };
+        assert_eq!(
+            mirror_change("primary", event, now),
+            Some(MirrorChange::Upsert(MirroredEvent {
+                id: "confirmed".to_string(),
+                calendar_id: "primary".to_string(),
+                summary: Some("Consultation".to_string()),
+                start_time: Utc.with_ymd_and_hms(2025, 5, 15, 10, 0, 0).unwrap(),
+                end_time: Utc.with_ymd_and_hms(2025, 5, 15, 11, 0, 0).unwrap(),
+                status: MirroredEventStatus::Confirmed,
+                updated_at: updated,
+            }))
+        );
+
+        // Tentative all-day events block the whole day
+        let all_day = |date: NaiveDate| {
+            Some(EventDateTime {
+                date: Some(date),
+                ..Default::default()
+            })
+        };
+        let event = Event {
+            id: Some("all-day".to_string()),
+            status: Some("tentative".to_string()),
+            start: all_day(NaiveDate::from_ymd_opt(2025, 5, 15).unwrap()),
+            end: all_day(NaiveDate::from_ymd_opt(2025, 5, 16).unwrap()),
+            ..Default::default()
+        };
+        let Some(MirrorChange::Upsert(mirrored)) = mirror_change("primary", event, now) else {
+            panic!("all-day event not mirrored");
+        };
+        assert_eq!(mirrored.status, MirroredEventStatus::Confirmed);
+        assert_eq!(
+            mirrored.start_time,
+            Utc.with_ymd_and_hms(2025, 5, 15, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            mirrored.end_time,
+            Utc.with_ymd_and_hms(2025, 5, 16, 0, 0, 0).unwrap()
+        );
+        assert_eq!(mirrored.updated_at, now);
+
+        // Incremental syncs report deleted events by their ID only
+        let deleted = Event {
+            id: Some("deleted".to_string()),
+            status: Some("cancelled".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            mirror_change("primary", deleted, now),
+            Some(MirrorChange::Cancelled("deleted".to_string()))
+        );
+
+        assert_eq!(mirror_change("primary", Event::default(), now), None);
+    }
+
+    #[tokio::test]
+    async fn test_mirrored_busy_times() {
+        let calendar_id = "mirror-test@example.com";
+        let tz: Tz = "Europe/Zurich".parse().unwrap();
+        let start = tz.with_ymd_and_hms(2025, 5, 15, 0, 0, 0).unwrap();
+        let end = tz.with_ymd_and_hms(2025, 5, 16, 0, 0, 0).unwrap();
+        assert_eq!(
+            mirrored_busy_times(calendar_id, start, end).await.unwrap(),
+            None
+        );
+
+        let event = |id: &str, hour: u32, status: MirroredEventStatus| MirroredEvent {
+            id: id.to_string(),
+            calendar_id: calendar_id.to_string(),
+            summary: None,
+            start_time: Utc.with_ymd_and_hms(2025, 5, 15, hour, 0, 0).unwrap(),
+            end_time: Utc.with_ymd_and_hms(2025, 5, 15, hour + 1, 0, 0).unwrap(),
+            status,
+            updated_at: Utc::now(),
+        };
+        let mirror = event_mirror();
+        mirror
+            .upsert(vec![
+                event("booked", 8, MirroredEventStatus::Confirmed),
+                event("cancelled", 12, MirroredEventStatus::Cancelled),
+            ])
+            .await
+            .unwrap();
+        mirror
+            .set_sync_token(calendar_id, "token".to_string())
+            .await
+            .unwrap();
+
+        // Only confirmed events are busy, in the time zone of the query
+        assert_eq!(
+            mirrored_busy_times(calendar_id, start, end).await.unwrap(),
+            Some(vec![(
+                tz.with_ymd_and_hms(2025, 5, 15, 10, 0, 0).unwrap(),
+                tz.with_ymd_and_hms(2025, 5, 15, 11, 0, 0).unwrap()
+            )])
+        );
+    }
+}
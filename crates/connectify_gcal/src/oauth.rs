@@ -0,0 +1,254 @@
+// --- File: crates/connectify_gcal/src/oauth.rs ---
+//! OAuth consent flow for personal Google calendars.
+//!
+//! Instead of provisioning a service account key, a professional can connect their own Google
+//! calendar: `/admin/gcal/oauth/connect` redirects to Google's consent screen, Google redirects
+//! back to `/gcal/oauth/callback` with an authorization code, and the refresh token it is
+//! exchanged for is kept in the OAuth token store of connectify_common (the database when one
+//! is configured). [`ConnectedUserAuth`] then obtains access tokens from that refresh token,
+//! so the calendar hub works as soon as the calendar is connected, without a restart.
+//!
+//! The `state` parameter of the consent request is signed with the client secret and expires
+//! after [`CONSENT_STATE_TTL_MINUTES`], so callbacks are accepted by any backend instance.
+
+use chrono::{DateTime, Duration, Utc};
+use connectify_common::metrics::observe_external_call;
+use connectify_common::oauth_tokens::{oauth_token_store, OAuthRefreshToken};
+use connectify_common::webhook::{hmac_sha256_hex, verify_hmac_sha256_hex};
+use connectify_common::ConnectifyError;
+use connectify_config::GcalOAuthConfig;
+use google_calendar3::common::GetToken;
+use serde::Deserialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// Environment variable holding the client secret of the OAuth client.
+pub const GCAL_OAUTH_CLIENT_SECRET_ENV: &str = "GCAL_OAUTH_CLIENT_SECRET";
+
+/// The provider the refresh token is stored under.
+pub const OAUTH_PROVIDER: &str = "google_calendar";
+
+/// The scope requested on the consent screen.
+pub const CALENDAR_SCOPE: &str = "https://www.googleapis.com/auth/calendar";
+
+/// How long a consent request may take, from the redirect to Google to the callback.
+pub const CONSENT_STATE_TTL_MINUTES: i64 = 10;
+
+const AUTHORIZATION_ENDPOINT: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const TOKEN_ENDPOINT: &str = "https://oauth2.googleapis.com/token";
+
+/// Access tokens are renewed this long before Google expires them.
+const ACCESS_TOKEN_EXPIRY_MARGIN_SECONDS: i64 = 60;
+
+#[derive(Error, Debug)]
+pub enum OAuthError {
+    #[error("{} is not set", GCAL_OAUTH_CLIENT_SECRET_ENV)]
+    MissingClientSecret,
+    #[error("Google Calendar is not connected yet; connect it at /admin/gcal/oauth/connect")]
+    NotConnected,
+    #[error("The consent request is invalid or expired")]
+    InvalidState,
+    #[error("Token request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Google rejected the token request: {0}")]
+    Rejected(String),
+    #[error("Google returned no refresh token; revoke the app's access and connect again")]
+    NoRefreshToken,
+    #[error("Failed to access the stored token: {0}")]
+    Store(#[from] ConnectifyError),
+}
+
+/// The client secret of the OAuth client, read from `GCAL_OAUTH_CLIENT_SECRET`.
+pub fn client_secret() -> Result<String, OAuthError> {
+    std::env::var(GCAL_OAUTH_CLIENT_SECRET_ENV)
+        .ok()
+        .filter(|secret| !secret.is_empty())
+        .ok_or(OAuthError::MissingClientSecret)
+}
+
+/// A new `state` for a consent request, valid for [`CONSENT_STATE_TTL_MINUTES`].
+pub fn consent_state(client_secret: &str, now: DateTime<Utc>) -> String {
+    let payload = format!(
+        "{}.{}",
+        (now + Duration::minutes(CONSENT_STATE_TTL_MINUTES)).timestamp(),
+        uuid::Uuid::new_v4().simple()
+    );
+    let signature = hmac_sha256_hex(client_secret.as_bytes(), payload.as_bytes());
+    format!("{}.{}", payload, signature)
+}
+
+/// Checks that a callback's `state` was issued by [`consent_state`] and hasn't expired.
+pub fn verify_consent_state(client_secret: &str, state: &str, now: DateTime<Utc>) -> bool {
+    let Some((payload, signature)) = state.rsplit_once('.') else {
+        return false;
+    };
+    let expires_at = payload
+        .split_once('.')
+        .and_then(|(expires_at, _)| expires_at.parse::<i64>().ok());
+    matches!(expires_at, Some(expires_at) if now.timestamp() <= expires_at)
+        && verify_hmac_sha256_hex(client_secret.as_bytes(), payload.as_bytes(), signature)
+}
+
+/// The URL of Google's consent screen, asking for offline access to the user's calendars.
+///
+/// `prompt=consent` makes Google return a refresh token even if the user connected before.
+pub fn authorization_url(config: &GcalOAuthConfig, state: &str) -> String {
+    reqwest::Url::parse_with_params(
+        AUTHORIZATION_ENDPOINT,
+        &[
+            ("client_id", config.client_id.as_str()),
+            ("redirect_uri", config.redirect_uri.as_str()),
+            ("response_type", "code"),
+            ("scope", CALENDAR_SCOPE),
+            ("access_type", "offline"),
+            ("prompt", "consent"),
+            ("include_granted_scopes", "true"),
+            ("state", state),
+        ],
+    )
+    .expect("the authorization endpoint is a valid URL")
+    .to_string()
+}
+
+/// The query Google redirects the user back to the callback with.
+#[derive(Deserialize, Debug)]
+pub struct OAuthCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Set instead of `code` if the user denied access, e.g. `access_denied`
+    pub error: Option<String>,
+}
+
+/// The answer of Google's token endpoint.
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+    refresh_token: Option<String>,
+}
+
+async fn request_token(
+    http: &reqwest::Client,
+    params: &[(&str, &str)],
+) -> Result<TokenResponse, OAuthError> {
+    let response = observe_external_call(
+        "gcal",
+        "request_token",
+        http.post(TOKEN_ENDPOINT).form(params).send(),
+    )
+    .await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(OAuthError::Rejected(format!("{}: {}", status, body)));
+    }
+    Ok(response.json().await?)
+}
+
+/// Exchanges the authorization code of a callback for a refresh token and stores it.
+pub async fn connect_with_code(
+    config: &GcalOAuthConfig,
+    client_secret: &str,
+    code: &str,
+    now: DateTime<Utc>,
+) -> Result<(), OAuthError> {
+    let tokens = request_token(
+        &reqwest::Client::new(),
+        &[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", client_secret),
+            ("redirect_uri", config.redirect_uri.as_str()),
+        ],
+    )
+    .await?;
+    let refresh_token = tokens.refresh_token.ok_or(OAuthError::NoRefreshToken)?;
+    oauth_token_store()
+        .put(OAuthRefreshToken {
+            provider: OAUTH_PROVIDER.to_string(),
+            refresh_token,
+            updated_at: now,
+        })
+        .await?;
+    Ok(())
+}
+
+/// An access token and when it has to be renewed.
+type CachedAccessToken = (String, DateTime<Utc>);
+
+/// Authenticates calendar requests as the user who connected their calendar.
+///
+/// Access tokens are obtained from the stored refresh token and cached until shortly before
+/// they expire.
+#[derive(Clone)]
+pub struct ConnectedUserAuth {
+    client_id: String,
+    client_secret: String,
+    http: reqwest::Client,
+    access_token: Arc<Mutex<Option<CachedAccessToken>>>,
+}
+
+impl ConnectedUserAuth {
+    /// Authentication with the given OAuth client.
+    pub fn new(config: &GcalOAuthConfig, client_secret: String) -> Self {
+        Self {
+            client_id: config.client_id.clone(),
+            client_secret,
+            http: reqwest::Client::new(),
+            access_token: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// A valid access token, refreshed if the cached one is about to expire.
+    pub async fn access_token(&self) -> Result<String, OAuthError> {
+        let now = Utc::now();
+        if let Some((token, expires_at)) = self
+            .access_token
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+        {
+            if now < *expires_at {
+                return Ok(token.clone());
+            }
+        }
+
+        let stored = oauth_token_store()
+            .get(OAUTH_PROVIDER)
+            .await?
+            .ok_or(OAuthError::NotConnected)?;
+        let tokens = request_token(
+            &self.http,
+            &[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", stored.refresh_token.as_str()),
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+            ],
+        )
+        .await?;
+        let expires_at =
+            now + Duration::seconds(tokens.expires_in - ACCESS_TOKEN_EXPIRY_MARGIN_SECONDS);
+        *self.access_token.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some((tokens.access_token.clone(), expires_at));
+        Ok(tokens.access_token)
+    }
+}
+
+impl GetToken for ConnectedUserAuth {
+    fn get_token<'a>(
+        &'a self,
+        _scopes: &'a [&str],
+    ) -> Pin<
+        Box<
+            dyn Future<Output = Result<Option<String>, Box<dyn std::error::Error + Send + Sync>>>
+                + Send
+                + 'a,
+        >,
+    > {
+        Box::pin(async move { Ok(Some(self.access_token().await?)) })
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use crate::oauth::{
+        authorization_url, consent_state, verify_consent_state, CONSENT_STATE_TTL_MINUTES,
+    };
+    use chrono::{Duration, TimeZone, Utc};
+    use connectify_config::GcalOAuthConfig;
+
+    const SECRET: &str = "client-secret";
+
+    #[test]
+    fn test_consent_state() {
+        let now = Utc.with_ymd_and_hms(2025, 5, 15, 10, 0, 0).unwrap();
+        let state = consent_state(SECRET, now);
+
+        assert!(verify_consent_state(SECRET, &state, now));
+        assert!(verify_consent_state(
+            SECRET,
+            &state,
+            now + Duration::minutes(CONSENT_STATE_TTL_MINUTES)
+        ));
+        // Expired
+        assert!(!verify_consent_state(
+            SECRET,
+            &state,
+            now + Duration::minutes(CONSENT_STATE_TTL_MINUTES + 1)
+        ));
+        // Signed with another secret
+        assert!(!verify_consent_state("other-secret", &state, now));
+        // Tampered expiry
+        let (expires_at, rest) = state.split_once('.').unwrap();
+        let extended = format!("{}.{}", expires_at.parse::<i64>().unwrap() + 3600, rest);
+        assert!(!verify_consent_state(SECRET, &extended, now));
+        assert!(!verify_consent_state(SECRET, "", now));
+        // Each consent request gets its own state
+        assert_ne!(state, consent_state(SECRET, now));
+    }
+
+    #[test]
+    fn test_authorization_url() {
+        let config = GcalOAuthConfig {
+            client_id: "client-id.apps.googleusercontent.com".to_string(),
+            redirect_uri: "https://example.com/api/gcal/oauth/callback".to_string(),
+        };
+        let url = reqwest::Url::parse(&authorization_url(&config, "the-state")).unwrap();
+        let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+
+        assert_eq!(params["client_id"], config.client_id);
+        assert_eq!(params["redirect_uri"], config.redirect_uri);
+        assert_eq!(params["access_type"], "offline");
+        assert_eq!(params["prompt"], "consent");
+        assert_eq!(params["state"], "the-state");
+        assert_eq!(params["scope"], "https://www.googleapis.com/auth/calendar");
+    }
+}
@@ -2,8 +2,13 @@
 
 use crate::handlers::get_booked_events_handler;
 use crate::handlers::{
-    book_slot_handler, delete_event_handler, get_availability_handler,
-    mark_booking_cancelled_handler, options_handler, GcalState,
+    batch_availability_handler, book_slot_handler, create_blackout_handler, create_hold_handler,
+    create_schedule_exception_handler, delete_blackout_handler, delete_event_handler,
+    delete_hold_handler, delete_schedule_exception_handler, get_availability_handler,
+    ics_export_handler, list_blackouts_handler, list_schedule_exceptions_handler,
+    mark_booking_cancelled_handler, next_availability_handler, oauth_callback_handler,
+    oauth_connect_handler, oauth_disconnect_handler, options_handler, push_notification_handler,
+    reschedule_booking_handler, GcalState,
 };
 use axum::{
     routing::{delete, get, options, patch, post}, // Add options here
@@ -11,6 +16,10 @@ use axum::{
 };
 
 use crate::auth::create_calendar_hub;
+use crate::blackout::BlackoutStore;
+use connectify_common::clock::system_clock;
+use connectify_common::idempotency::IdempotencyLayer;
+use connectify_common::runtime_flags::{feature_guard, BOOKINGS};
 use connectify_config::AppConfig; // Implement this function
                                   // Import handlers from the handlers module
 use std::sync::Arc; // Needed for State type hint if not using AppState directly
@@ -28,17 +37,42 @@ pub async fn routes(config: Arc<AppConfig>) -> Router {
         create_calendar_hub(config.clone().gcal.as_ref().expect("GCal config missing"))
             .await
             .unwrap(); // Implement this function
+    let blackouts = BlackoutStore::from_config(config.gcal.as_ref().expect("GCal config missing"));
     let gcal_state = Arc::new(GcalState {
         config,
         calendar_hub: Arc::new(calendar_hub),
+        clock: system_clock(),
+        blackouts: Arc::new(blackouts),
     });
 
     Router::new()
         .route("/availability", get(get_availability_handler))
         .route("/available-slots", get(get_availability_handler))
         .route("/gcal/available-slots", get(get_availability_handler))
-        .route("/book", post(book_slot_handler))
-        .route("/gcal/book", post(book_slot_handler))
+        .route("/gcal/availability/batch", post(batch_availability_handler))
+        .route("/gcal/availability/next", get(next_availability_handler))
+        .route(
+            "/book",
+            post(book_slot_handler).layer((feature_guard(BOOKINGS), IdempotencyLayer::new())),
+        )
+        .route(
+            "/gcal/book",
+            post(book_slot_handler).layer((feature_guard(BOOKINGS), IdempotencyLayer::new())),
+        )
+        .route(
+            "/gcal/bookings/{event_id}",
+            patch(reschedule_booking_handler).layer(feature_guard(BOOKINGS)),
+        )
+        .route("/gcal/bookings/{event_id}/ics", get(ics_export_handler))
+        .route(
+            "/gcal/holds",
+            post(create_hold_handler).layer((feature_guard(BOOKINGS), IdempotencyLayer::new())),
+        )
+        .route("/gcal/holds/{hold_id}", delete(delete_hold_handler))
+        .route("/gcal/notifications", post(push_notification_handler))
+        .route("/gcal/oauth/callback", get(oauth_callback_handler))
+        .route("/admin/gcal/oauth/connect", get(oauth_connect_handler))
+        .route("/admin/gcal/oauth", delete(oauth_disconnect_handler))
         .route("/admin/delete/{event_id}", delete(delete_event_handler))
         .route(
             "/admin/gcal/delete/{event_id}",
@@ -48,8 +82,25 @@ pub async fn routes(config: Arc<AppConfig>) -> Router {
             "/admin/mark_cancelled/{event_id}",
             patch(mark_booking_cancelled_handler),
         )
+        .route(
+            "/admin/gcal/blackouts",
+            get(list_blackouts_handler).post(create_blackout_handler),
+        )
+        .route(
+            "/admin/gcal/blackouts/{blackout_id}",
+            delete(delete_blackout_handler),
+        )
+        .route(
+            "/admin/gcal/schedule-exceptions",
+            get(list_schedule_exceptions_handler).post(create_schedule_exception_handler),
+        )
+        .route(
+            "/admin/gcal/schedule-exceptions/{exception_id}",
+            delete(delete_schedule_exception_handler),
+        )
         .route("/admin/bookings", get(get_booked_events_handler))
         .route("/admin/bookings", options(options_handler))
+        .route("/gcal/bookings", get(get_booked_events_handler))
         // Add this new route
         .with_state(gcal_state)
 }
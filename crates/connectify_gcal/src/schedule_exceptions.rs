@@ -0,0 +1,179 @@
+// --- File: crates/connectify_gcal/src/schedule_exceptions.rs ---
+//! One-off changes of the working hours on a single date, managed through the admin API.
+//!
+//! An `open` exception adds working hours to a date, e.g. a Saturday morning; a `closed`
+//! exception removes them, e.g. closing at 15:00 on Christmas Eve, or the whole day without
+//! times. The exceptions are stored in the schedule exception store of connectify_common (the
+//! database when one is configured) and merged into the weekly working hours when availability
+//! is calculated. Blackout dates still block the whole day.
+
+use crate::logic::WorkingHoursConfig;
+use chrono::{NaiveDate, NaiveTime};
+use connectify_common::schedule_exceptions::{ScheduleException, ScheduleExceptionKind};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+/// The last working moment of a day.
+fn end_of_day() -> NaiveTime {
+    NaiveTime::from_hms_opt(23, 59, 59).unwrap()
+}
+
+/// Request to add a schedule exception.
+#[derive(Deserialize, Debug, Validate)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[validate(schema(function = "validate_exception_times"))]
+pub struct CreateScheduleExceptionRequest {
+    /// The changed date in YYYY-MM-DD format, in the calendar's time zone
+    #[validate(custom(function = "validate_date"))]
+    pub date: String,
+    /// `open` to add working hours, `closed` to remove them
+    #[cfg_attr(feature = "openapi", schema(value_type = String, example = "closed"))]
+    pub kind: ScheduleExceptionKind,
+    /// Start of the changed hours in HH:MM format; required for `open`, closing from the start
+    /// of the day if not set
+    #[validate(custom(function = "validate_time"))]
+    pub start_time: Option<String>,
+    /// End of the changed hours in HH:MM format; required for `open`, closing until the end of
+    /// the day if not set
+    #[validate(custom(function = "validate_time"))]
+    pub end_time: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// A schedule exception as returned by the admin API.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ScheduleExceptionResponse {
+    pub id: String,
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = "date", example = "2025-12-24"))]
+    pub date: NaiveDate,
+    #[cfg_attr(feature = "openapi", schema(value_type = String, example = "closed"))]
+    pub kind: ScheduleExceptionKind,
+    /// Start of the changed hours (HH:MM)
+    #[cfg_attr(feature = "openapi", schema(example = "15:00"))]
+    pub start_time: Option<String>,
+    /// End of the changed hours (HH:MM)
+    pub end_time: Option<String>,
+    pub reason: Option<String>,
+}
+
+impl From<ScheduleException> for ScheduleExceptionResponse {
+    fn from(exception: ScheduleException) -> Self {
+        let format = |time: Option<NaiveTime>| time.map(|time| time.format("%H:%M").to_string());
+        Self {
+            id: exception.id,
+            date: exception.date,
+            kind: exception.kind,
+            start_time: format(exception.start_time),
+            end_time: format(exception.end_time),
+            reason: exception.reason,
+        }
+    }
+}
+
+/// Query of the schedule exceptions to list.
+#[derive(Deserialize, Debug)]
+pub struct ScheduleExceptionsQuery {
+    /// First date in YYYY-MM-DD format (default: today)
+    pub from: Option<String>,
+    /// Last date in YYYY-MM-DD format (default: a year after `from`)
+    pub to: Option<String>,
+}
+
+pub(crate) fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+pub(crate) fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M").ok()
+}
+
+/// Checks that a value is a date in YYYY-MM-DD format.
+fn validate_date(value: &str) -> Result<(), ValidationError> {
+    parse_date(value)
+        .map(|_| ())
+        .ok_or_else(|| ValidationError::new("date"))
+}
+
+/// Checks that a value is a time in HH:MM format.
+fn validate_time(value: &str) -> Result<(), ValidationError> {
+    parse_time(value)
+        .map(|_| ())
+        .ok_or_else(|| ValidationError::new("time"))
+}
+
+/// Checks that extra hours have both times, and that the hours don't end before they start.
+fn validate_exception_times(
+    request: &CreateScheduleExceptionRequest,
+) -> Result<(), ValidationError> {
+    let start = request.start_time.as_deref().and_then(parse_time);
+    let end = request.end_time.as_deref().and_then(parse_time);
+    if request.kind == ScheduleExceptionKind::Open && (start.is_none() || end.is_none()) {
+        return Err(ValidationError::new("open_requires_times"));
+    }
+    match (start, end) {
+        (Some(start), Some(end)) if end <= start => Err(ValidationError::new("end_before_start")),
+        _ => Ok(()),
+    }
+}
+
+/// Add `interval` to sorted intervals, merging the ones it overlaps or touches.
+fn add_interval(
+    intervals: &mut Vec<(NaiveTime, NaiveTime)>,
+    (mut start, mut end): (NaiveTime, NaiveTime),
+) {
+    intervals.retain(|&(known_start, known_end)| {
+        let overlaps = known_start <= end && start <= known_end;
+        if overlaps {
+            start = start.min(known_start);
+            end = end.max(known_end);
+        }
+        !overlaps
+    });
+    intervals.push((start, end));
+    intervals.sort();
+}
+
+/// Remove `closed` from intervals, splitting the ones it falls into.
+fn remove_interval(
+    intervals: &mut Vec<(NaiveTime, NaiveTime)>,
+    (closed_start, closed_end): (NaiveTime, NaiveTime),
+) {
+    *intervals = intervals
+        .iter()
+        .flat_map(|&(start, end)| [(start, end.min(closed_start)), (start.max(closed_end), end)])
+        .filter(|(start, end)| start < end)
+        .collect();
+}
+
+/// Merge schedule exceptions into the working hours of their dates.
+///
+/// Extra hours are added before closed hours are removed, so closing always wins.
+pub fn apply_schedule_exceptions(
+    working_hours: WorkingHoursConfig,
+    exceptions: &[ScheduleException],
+) -> WorkingHoursConfig {
+    let mut by_date: BTreeMap<NaiveDate, Vec<&ScheduleException>> = BTreeMap::new();
+    for exception in exceptions {
+        by_date.entry(exception.date).or_default().push(exception);
+    }
+
+    by_date
+        .into_iter()
+        .fold(working_hours, |working_hours, (date, mut exceptions)| {
+            exceptions.sort_by_key(|exception| exception.kind == ScheduleExceptionKind::Closed);
+            let mut intervals = working_hours.intervals_on(date).to_vec();
+            for exception in exceptions {
+                let start = exception.start_time.unwrap_or(NaiveTime::MIN);
+                let end = exception.end_time.unwrap_or_else(end_of_day);
+                match exception.kind {
+                    ScheduleExceptionKind::Open => add_interval(&mut intervals, (start, end)),
+                    ScheduleExceptionKind::Closed => remove_interval(&mut intervals, (start, end)),
+                }
+            }
+            working_hours.with_date_intervals(date, intervals)
+        })
+}
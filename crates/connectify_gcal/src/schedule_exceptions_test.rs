@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+    use crate::logic::{calculate_available_slots, WorkingHoursConfig};
+    use crate::schedule_exceptions::apply_schedule_exceptions;
+    use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Weekday};
+    use chrono_tz::Tz;
+    use connectify_common::schedule_exceptions::{ScheduleException, ScheduleExceptionKind};
+
+    fn time(hour: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 5, day).unwrap()
+    }
+
+    fn exception(
+        day: u32,
+        kind: ScheduleExceptionKind,
+        start: Option<u32>,
+        end: Option<u32>,
+    ) -> ScheduleException {
+        ScheduleException {
+            id: format!("{}-{}", day, kind),
+            date: date(day),
+            kind,
+            start_time: start.map(time),
+            end_time: end.map(time),
+            reason: None,
+            created_at: DateTime::from_timestamp(0, 0).unwrap(),
+        }
+    }
+
+    fn office_hours() -> WorkingHoursConfig {
+        WorkingHoursConfig::uniform(
+            time(9),
+            time(17),
+            &[
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+        )
+    }
+
+    #[test]
+    fn test_apply_schedule_exceptions() {
+        use ScheduleExceptionKind::{Closed, Open};
+
+        let working_hours = apply_schedule_exceptions(
+            office_hours(),
+            &[
+                // Tuesday: closing early
+                exception(13, Closed, Some(15), None),
+                // Wednesday: extra evening hours, but a lunch break
+                exception(14, Open, Some(16), Some(19)),
+                exception(14, Closed, Some(12), Some(13)),
+                // Thursday: closed all day
+                exception(15, Closed, None, None),
+                // Saturday: open in the morning
+                exception(17, Open, Some(10), Some(12)),
+            ],
+        );
+
+        assert_eq!(working_hours.intervals_on(date(12)), &[(time(9), time(17))]);
+        assert_eq!(working_hours.intervals_on(date(13)), &[(time(9), time(15))]);
+        assert_eq!(
+            working_hours.intervals_on(date(14)),
+            &[(time(9), time(12)), (time(13), time(19))]
+        );
+        assert!(working_hours.intervals_on(date(15)).is_empty());
+        assert_eq!(
+            working_hours.intervals_on(date(17)),
+            &[(time(10), time(12))]
+        );
+        // Other weeks keep the weekly hours
+        assert_eq!(working_hours.intervals_on(date(20)), &[(time(9), time(17))]);
+    }
+
+    #[test]
+    fn test_slots_with_schedule_exceptions() {
+        use ScheduleExceptionKind::{Closed, Open};
+
+        let time_zone: Tz = "Europe/Zurich".parse().unwrap();
+        let working_hours = apply_schedule_exceptions(
+            office_hours(),
+            &[
+                exception(13, Closed, Some(15), None),
+                exception(15, Closed, None, None),
+                exception(17, Open, Some(10), Some(12)),
+            ],
+        );
+
+        let slots = calculate_available_slots(
+            time_zone.with_ymd_and_hms(2025, 5, 12, 0, 0, 0).unwrap(),
+            time_zone.with_ymd_and_hms(2025, 5, 19, 0, 0, 0).unwrap(),
+            &[],
+            Duration::minutes(60),
+            &working_hours,
+            Duration::zero(),
+            Duration::minutes(60),
+        );
+        let starts_on = |day: u32| -> Vec<String> {
+            slots
+                .iter()
+                .filter_map(|(start, _)| {
+                    let start = DateTime::parse_from_rfc3339(start).ok()?;
+                    (start.date_naive() == date(day)).then(|| start.format("%H:%M").to_string())
+                })
+                .collect()
+        };
+
+        assert_eq!(starts_on(12).len(), 8);
+        assert_eq!(
+            starts_on(13),
+            vec!["09:00", "10:00", "11:00", "12:00", "13:00", "14:00"]
+        );
+        assert_eq!(starts_on(14).len(), 8);
+        assert!(starts_on(15).is_empty());
+        assert_eq!(starts_on(16).len(), 8);
+        assert_eq!(starts_on(17), vec!["10:00", "11:00"]);
+        assert!(starts_on(18).is_empty());
+
+        // Blackout dates still block the extra hours
+        let blacked_out = working_hours.with_blackout_dates([date(17)]);
+        let slots = calculate_available_slots(
+            time_zone.with_ymd_and_hms(2025, 5, 17, 0, 0, 0).unwrap(),
+            time_zone.with_ymd_and_hms(2025, 5, 18, 0, 0, 0).unwrap(),
+            &[],
+            Duration::minutes(60),
+            &blacked_out,
+            Duration::zero(),
+            Duration::minutes(60),
+        );
+        assert!(slots.is_empty());
+    }
+}
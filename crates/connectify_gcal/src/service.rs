@@ -4,42 +4,114 @@
 //! This module provides an implementation of the CalendarService trait for Google Calendar.
 
 use crate::auth::HubType;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use chrono_tz::Tz;
+use connectify_common::lock::{
+    acquire_with_wait, distributed_lock, release_quietly, DistributedLock, LockLease,
+};
+use connectify_common::metrics::observe_external_call;
+use connectify_common::retry::{
+    is_retryable_status, parse_retry_after, retry_async_with_retry_after, RetryPolicy, Retryable,
+};
 use connectify_common::services::{
-    BookedEvent, CalendarEvent, CalendarEventResult, CalendarService,
+    BookedEvent, CalendarEvent, CalendarEventPatch, CalendarEventResult, CalendarService,
 };
+use connectify_common::{external_service_error, ConnectifyError};
 use google_calendar3::api::{
-    Event, EventDateTime, EventExtendedProperties, FreeBusyRequest, FreeBusyRequestItem,
+    Channel, ConferenceData, ConferenceSolutionKey, CreateConferenceRequest, Event, EventAttendee,
+    EventDateTime, EventExtendedProperties, FreeBusyRequest, FreeBusyRequestItem,
 };
-use std::{collections::HashMap, sync::Arc};
+use google_calendar3::hyper::StatusCode;
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
 use thiserror::Error;
 use tracing::{debug, info};
 
+// Keys of the booking metadata in the private extended properties of Google events
+const PAYMENT_METHOD_KEY: &str = "payment_method";
+const PAYMENT_ID_KEY: &str = "payment_id";
+const PAYMENT_AMOUNT_KEY: &str = "payment_amount";
+const ROOM_NAME_KEY: &str = "room_name";
+const INVOICE_URL_KEY: &str = "invoice_url";
+/// Each tag is stored as its own property, so that events can be listed by tag.
+const TAG_KEY_PREFIX: &str = "tag_";
+const TAG_VALUE: &str = "true";
+
+/// The `privateExtendedProperty` filter matching events with a tag.
+fn tag_filter(tag: &str) -> String {
+    format!("{}{}={}", TAG_KEY_PREFIX, tag, TAG_VALUE)
+}
+
+/// The booking metadata of an event as private extended properties.
 pub fn extract_payment_metadata(event: &CalendarEvent) -> HashMap<String, String> {
     let mut map = HashMap::new();
 
     if let Some(method) = &event.payment_method {
-        map.insert("payment_method".to_string(), method.clone());
+        map.insert(PAYMENT_METHOD_KEY.to_string(), method.clone());
     }
     if let Some(id) = &event.payment_id {
-        map.insert("payment_id".to_string(), id.clone());
+        map.insert(PAYMENT_ID_KEY.to_string(), id.clone());
     }
     if let Some(amount) = event.payment_amount {
-        map.insert("payment_amount".to_string(), amount.to_string());
+        map.insert(PAYMENT_AMOUNT_KEY.to_string(), amount.to_string());
     }
     if let Some(room_name) = &event.room_name {
-        map.insert("room_name".to_string(), room_name.to_string());
+        map.insert(ROOM_NAME_KEY.to_string(), room_name.to_string());
+    }
+    if let Some(invoice_url) = &event.invoice_url {
+        map.insert(INVOICE_URL_KEY.to_string(), invoice_url.clone());
+    }
+    for tag in &event.tags {
+        map.insert(format!("{}{}", TAG_KEY_PREFIX, tag), TAG_VALUE.to_string());
     }
 
     map
 }
 
+/// Booking metadata stored in the private extended properties of a Google event.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BookingMetadata {
+    pub payment_method: Option<String>,
+    pub payment_id: Option<String>,
+    pub payment_amount: Option<i64>,
+    pub room_name: Option<String>,
+    pub invoice_url: Option<String>,
+    /// The tags of the event, sorted.
+    pub tags: Vec<String>,
+}
+
+impl BookingMetadata {
+    /// Read the metadata back from an event, ignoring amounts that are not numbers.
+    pub fn from_event(event: &Event) -> Self {
+        let private = event
+            .extended_properties
+            .as_ref()
+            .and_then(|properties| properties.private.as_ref());
+        let get = |key: &str| private.and_then(|private| private.get(key).cloned());
+        let mut tags: Vec<String> = private
+            .into_iter()
+            .flatten()
+            .filter_map(|(key, _)| key.strip_prefix(TAG_KEY_PREFIX).map(str::to_string))
+            .collect();
+        tags.sort();
+        Self {
+            payment_method: get(PAYMENT_METHOD_KEY),
+            payment_id: get(PAYMENT_ID_KEY),
+            payment_amount: get(PAYMENT_AMOUNT_KEY).and_then(|amount| amount.parse().ok()),
+            room_name: get(ROOM_NAME_KEY),
+            invoice_url: get(INVOICE_URL_KEY),
+            tags,
+        }
+    }
+}
+
 /// Errors that can occur when interacting with Google Calendar.
 #[derive(Error, Debug)]
 pub enum GcalServiceError {
     #[error("Google API Error: {0}")]
-    ApiError(#[from] google_calendar3::Error),
+    ApiError(google_calendar3::Error),
+    /// Google kept rejecting the call with a rate limit error after all retries.
+    #[error("Google API rate limit exceeded")]
+    RateLimited { retry_after: Option<Duration> },
     #[error("Failed to parse time: {0}")]
     TimeParseError(String),
     #[error("Calculation error: {0}")]
@@ -48,21 +120,504 @@ pub enum GcalServiceError {
     Conflict,
     #[error("No matching price tier found for duration: {0} minutes")]
     NoMatchingPriceTier(i64),
+    /// Another write to the calendar kept its event lock for too long.
+    #[error("Another booking of the calendar is in progress")]
+    Busy,
+    #[error("Lock error: {0}")]
+    Lock(String),
+}
+
+impl From<google_calendar3::Error> for GcalServiceError {
+    fn from(err: google_calendar3::Error) -> Self {
+        if is_rate_limit_error(&err) {
+            GcalServiceError::RateLimited {
+                retry_after: google_retry_after(&err),
+            }
+        } else {
+            GcalServiceError::ApiError(err)
+        }
+    }
+}
+
+/// Convert GcalServiceError to ConnectifyError
+impl From<GcalServiceError> for ConnectifyError {
+    fn from(err: GcalServiceError) -> Self {
+        match err {
+            GcalServiceError::ApiError(e) => external_service_error("Google Calendar API", e),
+            GcalServiceError::RateLimited { .. } => ConnectifyError::RateLimitError(
+                "Google Calendar API rate limit exceeded".to_string(),
+            ),
+            GcalServiceError::TimeParseError(msg) => ConnectifyError::ParseError(msg),
+            GcalServiceError::CalculationError(msg) => ConnectifyError::InternalError(msg),
+            GcalServiceError::Conflict => {
+                ConnectifyError::ConflictError("Booking conflict".to_string())
+            }
+            GcalServiceError::Busy => ConnectifyError::ConflictError(
+                "Another booking of the calendar is in progress".to_string(),
+            ),
+            GcalServiceError::Lock(msg) => ConnectifyError::InternalError(msg),
+            GcalServiceError::NoMatchingPriceTier(duration) => {
+                ConnectifyError::ValidationError(format!(
+                    "No matching price tier found for duration: {} minutes",
+                    duration
+                ))
+            }
+        }
+    }
+}
+
+/// The HTTP status of a failed Google API call, if it got a response.
+fn google_error_status(err: &google_calendar3::Error) -> Option<StatusCode> {
+    match err {
+        google_calendar3::Error::Failure(response) => Some(response.status()),
+        google_calendar3::Error::BadRequest(body) => body
+            .pointer("/error/code")
+            .and_then(|code| code.as_u64())
+            .and_then(|code| StatusCode::from_u16(code as u16).ok()),
+        _ => None,
+    }
+}
+
+/// Returns `true` if Google rejected a sync token as expired (410 Gone); the calendar then has
+/// to be synced from scratch.
+pub(crate) fn is_sync_token_expired(err: &GcalServiceError) -> bool {
+    matches!(err, GcalServiceError::ApiError(e) if google_error_status(e) == Some(StatusCode::GONE))
+}
+
+/// Returns `true` if Google rejected the call because of a quota or rate limit.
+///
+/// Besides 429, Google Calendar reports exceeded usage limits as 403 with a
+/// `rateLimitExceeded` or `userRateLimitExceeded` reason.
+pub(crate) fn is_rate_limit_error(err: &google_calendar3::Error) -> bool {
+    match google_error_status(err) {
+        Some(StatusCode::TOO_MANY_REQUESTS) => true,
+        Some(StatusCode::FORBIDDEN) => matches!(
+            err,
+            google_calendar3::Error::BadRequest(body)
+                if body
+                    .pointer("/error/errors/0/reason")
+                    .and_then(|reason| reason.as_str())
+                    .is_some_and(|reason| {
+                        reason == "rateLimitExceeded" || reason == "userRateLimitExceeded"
+                    })
+        ),
+        _ => false,
+    }
+}
+
+/// Returns `true` for Google API errors worth retrying: connection errors, rate limits and
+/// 5xx responses.
+pub(crate) fn is_transient_google_error(err: &google_calendar3::Error) -> bool {
+    match err {
+        google_calendar3::Error::HttpError(_) | google_calendar3::Error::Io(_) => true,
+        _ => is_rate_limit_error(err) || google_error_status(err).is_some_and(is_retryable_status),
+    }
+}
+
+/// The delay Google asked for in the `Retry-After` header of a failed call.
+fn google_retry_after(err: &google_calendar3::Error) -> Option<Duration> {
+    match err {
+        google_calendar3::Error::Failure(response) => response
+            .headers()
+            .get("retry-after")
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after),
+        _ => None,
+    }
+}
+
+/// Runs a Google API call, retrying transient failures with exponential backoff.
+///
+/// The call is recorded in the external call metrics under the given `operation`.
+async fn retry_google<T, F, Fut>(operation: &str, op: F) -> Result<T, google_calendar3::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, google_calendar3::Error>>,
+{
+    observe_external_call(
+        "gcal",
+        operation,
+        retry_async_with_retry_after(
+            &RetryPolicy::default(),
+            is_transient_google_error,
+            google_retry_after,
+            op,
+        ),
+    )
+    .await
+}
+
+impl Retryable for GcalServiceError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            GcalServiceError::ApiError(e) => is_transient_google_error(e),
+            GcalServiceError::RateLimited { .. } => true,
+            _ => false,
+        }
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            GcalServiceError::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
 }
 
 // The standard library already provides a generic implementation for
 // converting any type that implements std::error::Error into Box<dyn std::error::Error + Send + Sync>
 
+/// How long a calendar's event lock may be held while checking for conflicts and writing.
+const EVENT_LOCK_TTL: Duration = Duration::from_secs(30);
+
+/// How long a write waits for another write to the same calendar to finish.
+const EVENT_LOCK_WAIT: Duration = Duration::from_secs(5);
+
+/// The key of the lock serializing conflict checks and writes of a calendar's events.
+///
+/// It names the calendar rather than the slot, so that overlapping slots of different lengths
+/// exclude each other as well.
+pub fn event_lock_key(calendar_id: &str) -> String {
+    format!("gcal:events:{}", calendar_id)
+}
+
+/// Acquire the event lock of a calendar, waiting for other writes to finish.
+pub(crate) async fn acquire_event_lock(
+    lock: &dyn DistributedLock,
+    calendar_id: &str,
+) -> Result<LockLease, GcalServiceError> {
+    acquire_with_wait(
+        lock,
+        &event_lock_key(calendar_id),
+        EVENT_LOCK_TTL,
+        EVENT_LOCK_WAIT,
+    )
+    .await
+    .map_err(|e| GcalServiceError::Lock(e.to_string()))?
+    .ok_or(GcalServiceError::Busy)
+}
+
+/// Parse an RFC 3339 time of an event patch, if set.
+fn parse_patch_time(time: Option<&str>, field: &str) -> Result<Option<DateTime<Utc>>, String> {
+    time.map(|time| {
+        DateTime::parse_from_rfc3339(time)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| format!("Invalid {}: {}", field, e))
+    })
+    .transpose()
+}
+
+/// Check that rescheduling an event to `start`-`end` doesn't overlap any other event.
+async fn check_reschedule_conflicts<S>(
+    service: &S,
+    calendar_id: &str,
+    event_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<(), GcalServiceError>
+where
+    S: CalendarService<Error = GcalServiceError> + ?Sized,
+{
+    if end <= start {
+        return Err(GcalServiceError::CalculationError(
+            "End time must be after start time".to_string(),
+        ));
+    }
+
+    let tz = chrono_tz::Tz::UTC;
+    let others = service
+        .get_booked_events(
+            calendar_id,
+            start.with_timezone(&tz),
+            end.with_timezone(&tz),
+            false,
+        )
+        .await?;
+    if others.iter().any(|event| event.event_id != event_id) {
+        return Err(GcalServiceError::Conflict);
+    }
+    Ok(())
+}
+
+/// The video link of an event's conference, e.g. its Google Meet URL.
+pub(crate) fn meet_link(event: &Event) -> Option<String> {
+    event.hangout_link.clone().or_else(|| {
+        event
+            .conference_data
+            .as_ref()?
+            .entry_points
+            .as_ref()?
+            .iter()
+            .find(|entry| entry.entry_point_type.as_deref() == Some("video"))?
+            .uri
+            .clone()
+    })
+}
+
 /// Google Calendar service implementation.
 pub struct GoogleCalendarService {
     calendar_hub: Arc<HubType>,
 }
 
+/// Convert a Google event into a `BookedEvent`, formatting all times as RFC 3339.
+fn booked_event_from_google(event: Event) -> BookedEvent {
+    let metadata = BookingMetadata::from_event(&event);
+    let event_id = event.id.unwrap_or_default();
+    let summary = event.summary.unwrap_or_default();
+    let description = event.description;
+
+    // Handle start time
+    let start_time = match event.start {
+        Some(start) => match start.date_time {
+            Some(dt) => dt.to_rfc3339(),
+            None => match start.date {
+                Some(d) => format!("{}T00:00:00Z", d),
+                None => "Unknown start time".to_string(),
+            },
+        },
+        None => "Unknown start time".to_string(),
+    };
+
+    // Handle end time
+    let end_time = match event.end {
+        Some(end) => match end.date_time {
+            Some(dt) => dt.to_rfc3339(),
+            None => match end.date {
+                Some(d) => format!("{}T23:59:59Z", d),
+                None => "Unknown end time".to_string(),
+            },
+        },
+        None => "Unknown end time".to_string(),
+    };
+
+    let status = event.status.unwrap_or_else(|| "confirmed".to_string());
+    let created = event.created.map(|dt| dt.to_rfc3339()).unwrap_or_default();
+    let updated = event.updated.map(|dt| dt.to_rfc3339()).unwrap_or_default();
+
+    BookedEvent {
+        event_id,
+        summary,
+        description,
+        start_time,
+        end_time,
+        status,
+        created,
+        updated,
+        payment_id: metadata.payment_id,
+        payment_method: metadata.payment_method,
+        payment_amount: metadata.payment_amount,
+        room_name: metadata.room_name,
+        invoice_url: metadata.invoice_url,
+        color_id: event.color_id,
+        tags: metadata.tags,
+    }
+}
+
 impl GoogleCalendarService {
     /// Create a new Google Calendar service.
     pub fn new(calendar_hub: Arc<HubType>) -> Self {
         Self { calendar_hub }
     }
+
+    /// Ask Google to POST a push notification to `address` whenever events of the calendar change.
+    ///
+    /// Returns the id of the created notification channel.
+    pub async fn watch_events(
+        &self,
+        calendar_id: &str,
+        address: &str,
+        token: &str,
+        expiration: DateTime<Utc>,
+    ) -> Result<String, GcalServiceError> {
+        let channel = Channel {
+            id: Some(format!("connectify-{}", uuid::Uuid::new_v4())),
+            type_: Some("web_hook".to_string()),
+            address: Some(address.to_string()),
+            token: Some(token.to_string()),
+            expiration: Some(expiration.timestamp_millis()),
+            ..Default::default()
+        };
+        let (_response, channel) = retry_google("watch_events", || {
+            self.calendar_hub
+                .events()
+                .watch(channel.clone(), calendar_id)
+                .doit()
+        })
+        .await?;
+        Ok(channel.id.unwrap_or_default())
+    }
+
+    /// Fetch the events of a calendar changed since the sync that returned `sync_token`,
+    /// including cancelled ones, following all pages.
+    ///
+    /// Without a sync token, all events ending after `time_min` are fetched. Returns the events
+    /// and the token for the next sync.
+    pub async fn list_event_changes(
+        &self,
+        calendar_id: &str,
+        sync_token: Option<&str>,
+        time_min: DateTime<Utc>,
+    ) -> Result<(Vec<Event>, Option<String>), GcalServiceError> {
+        let mut events = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let (_, events_list) = retry_google("list_events", || {
+                let mut call = self
+                    .calendar_hub
+                    .events()
+                    .list(calendar_id)
+                    .single_events(true)
+                    .show_deleted(true);
+                // Google rejects time bounds together with a sync token
+                call = match sync_token {
+                    Some(sync_token) => call.sync_token(sync_token),
+                    None => call.time_min(time_min),
+                };
+                if let Some(page_token) = &page_token {
+                    call = call.page_token(page_token);
+                }
+                call.doit()
+            })
+            .await?;
+            events.extend(events_list.items.unwrap_or_default());
+            match events_list.next_page_token {
+                Some(next_page_token) => page_token = Some(next_page_token),
+                None => return Ok((events, events_list.next_sync_token)),
+            }
+        }
+    }
+
+    /// Fetch a single event of a calendar.
+    pub async fn get_event(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+    ) -> Result<Event, GcalServiceError> {
+        let (_, event) = retry_google("get_event", || {
+            self.calendar_hub.events().get(calendar_id, event_id).doit()
+        })
+        .await?;
+        Ok(event)
+    }
+
+    /// Fetch one page of the booked events of a calendar, sorted by start time.
+    ///
+    /// Returns the events and the token of the next page, if there is one. `max_results`
+    /// defaults to Google's page size of 250 events. With a `tag`, only events tagged with it
+    /// are returned.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn get_booked_events_page(
+        &self,
+        calendar_id: &str,
+        start_time: DateTime<Tz>,
+        end_time: DateTime<Tz>,
+        include_cancelled: bool,
+        tag: Option<&str>,
+        page_token: Option<&str>,
+        max_results: Option<i32>,
+    ) -> Result<(Vec<BookedEvent>, Option<String>), GcalServiceError> {
+        let (_, events_list) = retry_google("list_events", || {
+            let mut call = self
+                .calendar_hub
+                .events()
+                .list(calendar_id)
+                .time_min(start_time.with_timezone(&Utc))
+                .time_max(end_time.with_timezone(&Utc))
+                .single_events(true) // Expand recurring events
+                .order_by("startTime") // Sort by start time
+                // This is a key parameter for Google Calendar API to include cancelled events
+                .show_deleted(include_cancelled);
+            if let Some(page_token) = page_token {
+                call = call.page_token(page_token);
+            }
+            if let Some(max_results) = max_results {
+                call = call.max_results(max_results);
+            }
+            if let Some(tag) = tag {
+                call = call.add_private_extended_property(&tag_filter(tag));
+            }
+            call.doit()
+        })
+        .await?;
+
+        let booked_events = events_list
+            .items
+            .unwrap_or_default()
+            .into_iter()
+            // Skip cancelled events if not including them
+            .filter(|event| include_cancelled || event.status.as_deref() != Some("cancelled"))
+            .map(booked_event_from_google)
+            .collect();
+        Ok((booked_events, events_list.next_page_token))
+    }
+
+    /// Fetch all booked events of a calendar, optionally only those tagged with `tag`.
+    pub async fn get_all_booked_events(
+        &self,
+        calendar_id: &str,
+        start_time: DateTime<Tz>,
+        end_time: DateTime<Tz>,
+        include_cancelled: bool,
+        tag: Option<&str>,
+    ) -> Result<Vec<BookedEvent>, GcalServiceError> {
+        // Follow nextPageToken, so busy calendars are not truncated to the first page
+        let mut booked_events = Vec::new();
+        let mut page_token = None;
+        loop {
+            let (events, next_page_token) = self
+                .get_booked_events_page(
+                    calendar_id,
+                    start_time,
+                    end_time,
+                    include_cancelled,
+                    tag,
+                    page_token.as_deref(),
+                    None,
+                )
+                .await?;
+            booked_events.extend(events);
+            match next_page_token {
+                Some(token) => page_token = Some(token),
+                None => return Ok(booked_events),
+            }
+        }
+    }
+
+    /// The dates covered by all-day events of a calendar, e.g. a public holiday calendar.
+    pub async fn get_all_day_dates(
+        &self,
+        calendar_id: &str,
+        start_time: DateTime<Tz>,
+        end_time: DateTime<Tz>,
+    ) -> Result<Vec<NaiveDate>, GcalServiceError> {
+        let (_, events_list) = retry_google("list_events", || {
+            self.calendar_hub
+                .events()
+                .list(calendar_id)
+                .time_min(start_time.with_timezone(&Utc))
+                .time_max(end_time.with_timezone(&Utc))
+                .single_events(true)
+                .doit()
+        })
+        .await?;
+
+        let mut dates = Vec::new();
+        for event in events_list.items.unwrap_or_default() {
+            if event.status.as_deref() == Some("cancelled") {
+                continue;
+            }
+            if let Some(start) = event.start.and_then(|start| start.date) {
+                // The end date of all-day events is exclusive
+                let end = event
+                    .end
+                    .and_then(|end| end.date)
+                    .filter(|end| *end > start);
+                let days = end.map_or(1, |end| (end - start).num_days());
+                dates.extend(start.iter_days().take(days as usize));
+            }
+        }
+        Ok(dates)
+    }
 }
 
 impl CalendarService for GoogleCalendarService {
@@ -143,8 +698,11 @@ impl CalendarService for GoogleCalendarService {
                 ..Default::default()
             };
 
-            // Make the API call
-            let (_response, freebusy_response) = calendar_hub.freebusy().query(req).doit().await?;
+            // Make the API call, retrying transient Google API failures
+            let (_response, freebusy_response) = retry_google("query_freebusy", || {
+                calendar_hub.freebusy().query(req.clone()).doit()
+            })
+            .await?;
             debug!("Retrieved busy times: {:?}", _response);
             let mut busy_periods = Vec::new();
 
@@ -251,63 +809,123 @@ impl CalendarService for GoogleCalendarService {
                 ));
             }
 
-            // Check for conflicts with existing events
-            // Convert UTC datetimes to Tz format for get_busy_times
-            let tz = chrono_tz::Tz::UTC;
-            let start_dt_tz = start_dt.with_timezone(&tz);
-            let end_dt_tz = end_dt.with_timezone(&tz);
-            let busy_times = this
-                .get_busy_times(&calendar_id, start_dt_tz, end_dt_tz)
-                .await?;
+            // The conflict check and the insert must not interleave with another write to
+            // the calendar, or two bookings could both pass the check
+            let lock = distributed_lock();
+            let lease = acquire_event_lock(&*lock, &calendar_id).await?;
+            let result = async {
+                // Check for conflicts with existing events
+                // Convert UTC datetimes to Tz format for get_busy_times
+                let tz = chrono_tz::Tz::UTC;
+                let start_dt_tz = start_dt.with_timezone(&tz);
+                let end_dt_tz = end_dt.with_timezone(&tz);
+                let busy_times = this
+                    .get_busy_times(&calendar_id, start_dt_tz, end_dt_tz)
+                    .await?;
 
-            // If there are any busy periods that overlap with our proposed event time, it's a conflict
-            for (busy_start, busy_end) in &busy_times {
-                // Check for overlap: (StartA < EndB) and (EndA > StartB)
-                if start_dt < *busy_end && end_dt > *busy_start {
-                    return Err(GcalServiceError::Conflict);
+                // If there are any busy periods that overlap with our proposed event time, it's a conflict
+                for (busy_start, busy_end) in &busy_times {
+                    // Check for overlap: (StartA < EndB) and (EndA > StartB)
+                    if start_dt < *busy_end && end_dt > *busy_start {
+                        return Err(GcalServiceError::Conflict);
+                    }
+                }
+                // Add payment information to extended properties if available
+                let payment_metadata = extract_payment_metadata(&event);
+
+                // Construct the Event object
+                let mut gcal_event = Event {
+                    summary: Some(event.summary),
+                    description: event.description,
+                    start: Some(EventDateTime {
+                        date_time: Some(start_dt),
+                        time_zone: Some("UTC".to_string()), // Store event times in UTC
+                        ..Default::default()
+                    }),
+                    end: Some(EventDateTime {
+                        date_time: Some(end_dt),
+                        time_zone: Some("UTC".to_string()),
+                        ..Default::default()
+                    }),
+                    // Transparent events don't show up in free/busy queries
+                    transparency: event.transparent.then(|| "transparent".to_string()),
+                    color_id: event.color_id.clone(),
+                    ..Default::default() // Use default for other fields
+                };
+
+                // Add payment metadata if available to the private extended properties
+                if !payment_metadata.is_empty() {
+                    gcal_event.extended_properties = Some(EventExtendedProperties {
+                        private: Some(payment_metadata),
+                        shared: None,
+                    });
                 }
-            }
-            // Add payment information to extended properties if available
-            let payment_metadata = extract_payment_metadata(&event);
-
-            // Construct the Event object
-            let mut gcal_event = Event {
-                summary: Some(event.summary),
-                description: event.description,
-                start: Some(EventDateTime {
-                    date_time: Some(start_dt),
-                    time_zone: Some("UTC".to_string()), // Store event times in UTC
-                    ..Default::default()
-                }),
-                end: Some(EventDateTime {
-                    date_time: Some(end_dt),
-                    time_zone: Some("UTC".to_string()),
-                    ..Default::default()
-                }),
-                ..Default::default() // Use default for other fields
-            };
 
-            // Add payment metadata if available to the private extended properties
-            if !payment_metadata.is_empty() {
-                gcal_event.extended_properties = Some(EventExtendedProperties {
-                    private: Some(payment_metadata),
-                    shared: None,
-                });
-            }
+                let has_attendees = !event.attendees.is_empty();
+                if has_attendees {
+                    gcal_event.attendees = Some(
+                        event
+                            .attendees
+                            .iter()
+                            .map(|email| EventAttendee {
+                                email: Some(email.clone()),
+                                ..Default::default()
+                            })
+                            .collect(),
+                    );
+                }
 
-            // Make the API call to insert the event
-            let (_response, created_event) = calendar_hub
-                .events()
-                .insert(gcal_event, &calendar_id)
-                .doit()
+                if event.create_meet_link {
+                    gcal_event.conference_data = Some(ConferenceData {
+                        create_request: Some(CreateConferenceRequest {
+                            request_id: Some(uuid::Uuid::new_v4().to_string()),
+                            conference_solution_key: Some(ConferenceSolutionKey {
+                                type_: Some("hangoutsMeet".to_string()),
+                            }),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    });
+                }
+
+                // Make the API call to insert the event, inviting the attendees by email. Inserts
+                // are not idempotent, so only rate limit errors (where Google did not create the
+                // event) are retried.
+                let (_response, created_event) = observe_external_call(
+                    "gcal",
+                    "insert_event",
+                    retry_async_with_retry_after(
+                        &RetryPolicy::default(),
+                        is_rate_limit_error,
+                        google_retry_after,
+                        || {
+                            let mut insert = calendar_hub
+                                .events()
+                                .insert(gcal_event.clone(), &calendar_id);
+                            if has_attendees {
+                                insert = insert.send_updates("all");
+                            }
+                            if event.create_meet_link {
+                                // Without it, Google ignores the conference data
+                                insert = insert.conference_data_version(1);
+                            }
+                            insert.doit()
+                        },
+                    ),
+                )
                 .await?;
 
-            Ok(CalendarEventResult {
-                event_id: created_event.id,
-                status: created_event
-                    .status
-                    .unwrap_or_else(|| "confirmed".to_string()),
-            })
+                Ok(CalendarEventResult {
+                    meet_link: meet_link(&created_event),
+                    event_id: created_event.id,
+                    status: created_event
+                        .status
+                        .unwrap_or_else(|| "confirmed".to_string()),
+                })
+            }
+            .await;
+            release_quietly(&*lock, &lease).await;
+            result
         })
     }
 
@@ -367,18 +985,17 @@ impl CalendarService for GoogleCalendarService {
 
         Box::pin(async move {
             // First check if the event exists and get its status
-            let get_result = calendar_hub
-                .events()
-                .get(&calendar_id, &event_id)
-                .doit()
-                .await;
+            let get_result = retry_google("get_event", || {
+                calendar_hub.events().get(&calendar_id, &event_id).doit()
+            })
+            .await;
 
             // If the event doesn't exist, consider it "successfully deleted"
             if let Err(e) = get_result {
                 if e.to_string().contains("404") {
                     return Ok(());
                 }
-                return Err(GcalServiceError::ApiError(e));
+                return Err(e.into());
             }
 
             // Extract event status for making delete decision
@@ -386,12 +1003,14 @@ impl CalendarService for GoogleCalendarService {
             let status = event.status.as_deref().unwrap_or("confirmed");
 
             // Try to delete the event normally first
-            let delete_result = calendar_hub
-                .events()
-                .delete(&calendar_id, &event_id)
-                .send_updates(if notify_attendees { "all" } else { "none" })
-                .doit()
-                .await;
+            let delete_result = retry_google("delete_event", || {
+                calendar_hub
+                    .events()
+                    .delete(&calendar_id, &event_id)
+                    .send_updates(if notify_attendees { "all" } else { "none" })
+                    .doit()
+            })
+            .await;
 
             match delete_result {
                 Ok(_) => Ok(()), // Normal deletion successful
@@ -410,23 +1029,27 @@ impl CalendarService for GoogleCalendarService {
                         };
 
                         // First restore to confirmed status
-                        let restore_result = calendar_hub
-                            .events()
-                            .patch(restored_event, &calendar_id, &event_id)
-                            .send_updates("none") // Don't notify for intermediate step
-                            .doit()
-                            .await;
+                        let restore_result = retry_google("patch_event", || {
+                            calendar_hub
+                                .events()
+                                .patch(restored_event.clone(), &calendar_id, &event_id)
+                                .send_updates("none") // Don't notify for intermediate step
+                                .doit()
+                        })
+                        .await;
 
                         // Handle restoration result
                         match restore_result {
                             Ok(_) => {
                                 // Now try deleting again
-                                calendar_hub
-                                    .events()
-                                    .delete(&calendar_id, &event_id)
-                                    .send_updates(if notify_attendees { "all" } else { "none" })
-                                    .doit()
-                                    .await?;
+                                retry_google("delete_event", || {
+                                    calendar_hub
+                                        .events()
+                                        .delete(&calendar_id, &event_id)
+                                        .send_updates(if notify_attendees { "all" } else { "none" })
+                                        .doit()
+                                })
+                                .await?;
                                 Ok(())
                             }
                             Err(_) => {
@@ -440,13 +1063,134 @@ impl CalendarService for GoogleCalendarService {
                         }
                     } else {
                         // For any other error, just pass it through
-                        Err(GcalServiceError::ApiError(e))
+                        Err(e.into())
                     }
                 }
             }
         })
     }
 
+    /// Updates an existing event in the specified calendar.
+    ///
+    /// Only the fields set in the patch are changed, so an event can be rescheduled without
+    /// deleting and recreating it (which would lose its ID, attendees and payment metadata).
+    /// When the time changes, the new slot is checked for conflicts with other events, and
+    /// the event's sequence number is incremented so attendees' calendars pick up the change.
+    ///
+    /// # Arguments
+    ///
+    /// * `calendar_id` - The ID of the calendar containing the event
+    /// * `event_id` - The ID of the event to update
+    /// * `patch` - The fields to change
+    ///
+    /// # Returns
+    ///
+    /// A `CalendarEventResult` containing the ID of the updated event and its status.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `GcalServiceError` if:
+    /// * A new start or end time cannot be parsed (TimeParseError)
+    /// * The end time is not after the start time (CalculationError)
+    /// * The new time overlaps another event (Conflict)
+    /// * The event doesn't exist or the API call fails (ApiError)
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let patch = CalendarEventPatch {
+    ///     start_time: Some("2025-05-15T14:00:00Z".to_string()),
+    ///     end_time: Some("2025-05-15T15:00:00Z".to_string()),
+    ///     notify_attendees: true,
+    ///     ..Default::default()
+    /// };
+    /// let result = calendar_service.update_event("primary", "event123", patch).await?;
+    /// ```
+    fn update_event(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+        patch: CalendarEventPatch,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<CalendarEventResult, Self::Error>> + Send + '_>,
+    > {
+        let calendar_id = calendar_id.to_string();
+        let event_id = event_id.to_string();
+        let calendar_hub = self.calendar_hub.clone();
+
+        Box::pin(async move {
+            let new_start = parse_patch_time(patch.start_time.as_deref(), "start_time")
+                .map_err(GcalServiceError::TimeParseError)?;
+            let new_end = parse_patch_time(patch.end_time.as_deref(), "end_time")
+                .map_err(GcalServiceError::TimeParseError)?;
+
+            // Moving an event is checked for conflicts like a new booking
+            let lock = distributed_lock();
+            let lease = if new_start.is_some() || new_end.is_some() {
+                Some(acquire_event_lock(&*lock, &calendar_id).await?)
+            } else {
+                None
+            };
+            let result = async {
+                let (_response, event) = retry_google("get_event", || {
+                    calendar_hub.events().get(&calendar_id, &event_id).doit()
+                })
+                .await?;
+
+                if new_start.is_some() || new_end.is_some() {
+                    let current_start = event.start.as_ref().and_then(|s| s.date_time);
+                    let current_end = event.end.as_ref().and_then(|e| e.date_time);
+                    let (Some(start), Some(end)) =
+                        (new_start.or(current_start), new_end.or(current_end))
+                    else {
+                        return Err(GcalServiceError::CalculationError(
+                            "Cannot reschedule an all-day event".to_string(),
+                        ));
+                    };
+                    check_reschedule_conflicts(self, &calendar_id, &event_id, start, end).await?;
+                }
+
+                let event_time = |dt: DateTime<Utc>| EventDateTime {
+                    date_time: Some(dt),
+                    time_zone: Some("UTC".to_string()),
+                    ..Default::default()
+                };
+                let changes = Event {
+                    summary: patch.summary,
+                    description: patch.description,
+                    start: new_start.map(event_time),
+                    end: new_end.map(event_time),
+                    sequence: Some(event.sequence.map(|n| n + 1).unwrap_or(1)),
+                    ..Default::default()
+                };
+
+                let (_response, updated) = retry_google("patch_event", || {
+                    calendar_hub
+                        .events()
+                        .patch(changes.clone(), &calendar_id, &event_id)
+                        .send_updates(if patch.notify_attendees {
+                            "all"
+                        } else {
+                            "none"
+                        })
+                        .doit()
+                })
+                .await?;
+
+                Ok(CalendarEventResult {
+                    meet_link: meet_link(&updated),
+                    event_id: updated.id,
+                    status: updated.status.unwrap_or_else(|| "confirmed".to_string()),
+                })
+            }
+            .await;
+            if let Some(lease) = lease {
+                release_quietly(&*lock, &lease).await;
+            }
+            result
+        })
+    }
+
     /// Marks an event as cancelled in the specified calendar without deleting it.
     ///
     /// This function updates an event's status to "cancelled" in Google Calendar.
@@ -503,11 +1247,10 @@ impl CalendarService for GoogleCalendarService {
         let calendar_hub = self.calendar_hub.clone();
 
         Box::pin(async move {
-            let (_response, event) = calendar_hub
-                .events()
-                .get(&calendar_id, &event_id)
-                .doit()
-                .await?;
+            let (_response, event) = retry_google("get_event", || {
+                calendar_hub.events().get(&calendar_id, &event_id).doit()
+            })
+            .await?;
 
             // Create a minimal event with sequence number + 1
             let sequence = event.sequence.map(|n| n + 1).unwrap_or(1);
@@ -518,16 +1261,19 @@ impl CalendarService for GoogleCalendarService {
                 ..Default::default()
             };
 
-            let (_response, updated) = calendar_hub
-                .events()
-                .patch(cancelled_event, &calendar_id, &event_id)
-                .send_updates(if notify_attendees { "all" } else { "none" })
-                .doit()
-                .await?;
+            let (_response, updated) = retry_google("patch_event", || {
+                calendar_hub
+                    .events()
+                    .patch(cancelled_event.clone(), &calendar_id, &event_id)
+                    .send_updates(if notify_attendees { "all" } else { "none" })
+                    .doit()
+            })
+            .await?;
 
             Ok(CalendarEventResult {
                 event_id: updated.id,
                 status: updated.status.unwrap_or_else(|| "cancelled".to_string()),
+                meet_link: None,
             })
         })
     }
@@ -605,113 +1351,9 @@ impl CalendarService for GoogleCalendarService {
         Box<dyn std::future::Future<Output = Result<Vec<BookedEvent>, Self::Error>> + Send + '_>,
     > {
         let calendar_id = calendar_id.to_string();
-        let calendar_hub = self.calendar_hub.clone();
-        // let time_zone = start_time.timezone();
         Box::pin(async move {
-            // Import Utc type
-            use chrono::Utc;
-
-            // Create the events list request
-            let mut request = calendar_hub
-                .events()
-                .list(&calendar_id)
-                .time_min(start_time.with_timezone(&Utc))
-                .time_max(end_time.with_timezone(&Utc))
-                .single_events(true) // Expand recurring events
-                .order_by("startTime"); // Sort by start time
-
-            // This is a key parameter for Google Calendar API to include cancelled events
-            request = request.show_deleted(include_cancelled);
-
-            // Make the API call
-            let (_, events_list) = request.doit().await?;
-
-            let mut booked_events = Vec::new();
-
-            if let Some(items) = events_list.items {
-                for event in items {
-                    // Skip cancelled events if not including them
-                    let status = event.status.as_deref().unwrap_or("confirmed");
-                    if !include_cancelled && status == "cancelled" {
-                        continue;
-                    }
-
-                    // Extract the needed fields
-                    let event_id = event.id.unwrap_or_default();
-                    let summary = event.summary.unwrap_or_default();
-                    let description = event.description;
-                    // Line 626 in service.rs
-                    // Line 626 in service.rs
-                    let payment_id = event.extended_properties.as_ref().and_then(|p| {
-                        p.private
-                            .as_ref()
-                            .and_then(|private_map| private_map.get("payment_id").cloned())
-                    });
-                    let payment_method = event.extended_properties.as_ref().and_then(|p| {
-                        p.private
-                            .as_ref()
-                            .and_then(|private_map| private_map.get("payment_method").cloned())
-                    });
-                    let payment_amount = event.extended_properties.as_ref().and_then(|p| {
-                        p.private.as_ref().and_then(|private_map| {
-                            private_map
-                                .get("payment_amount")
-                                .and_then(|amount_str| amount_str.parse::<i64>().ok())
-                        })
-                    });
-                    let room_name = event.extended_properties.as_ref().and_then(|p| {
-                        p.private
-                            .as_ref()
-                            .and_then(|private_map| private_map.get("room_name").cloned())
-                    });
-
-                    // Handle start time
-                    let start_time = match event.start {
-                        Some(start) => match start.date_time {
-                            Some(dt) => dt.to_rfc3339(),
-                            None => match start.date {
-                                Some(d) => format!("{}T00:00:00Z", d),
-                                None => "Unknown start time".to_string(),
-                            },
-                        },
-                        None => "Unknown start time".to_string(),
-                    };
-
-                    // Handle end time
-                    let end_time = match event.end {
-                        Some(end) => match end.date_time {
-                            Some(dt) => dt.to_rfc3339(),
-                            None => match end.date {
-                                Some(d) => format!("{}T23:59:59Z", d),
-                                None => "Unknown end time".to_string(),
-                            },
-                        },
-                        None => "Unknown end time".to_string(),
-                    };
-
-                    let status = event.status.unwrap_or_else(|| "confirmed".to_string());
-                    let created = event.created.map(|dt| dt.to_rfc3339()).unwrap_or_default();
-                    let updated = event.updated.map(|dt| dt.to_rfc3339()).unwrap_or_default();
-
-                    // Add this event to our results
-                    booked_events.push(BookedEvent {
-                        event_id,
-                        summary,
-                        description,
-                        start_time,
-                        end_time,
-                        status,
-                        created,
-                        updated,
-                        payment_id,
-                        payment_method,
-                        payment_amount,
-                        room_name,
-                    });
-                }
-            }
-
-            Ok(booked_events)
+            self.get_all_booked_events(&calendar_id, start_time, end_time, include_cancelled, None)
+                .await
         })
     }
 }
@@ -848,6 +1490,7 @@ pub mod mock {
                 Ok(CalendarEventResult {
                     event_id: Some(event_id),
                     status: "confirmed".to_string(),
+                    meet_link: None,
                 })
             })
         }
@@ -873,6 +1516,88 @@ pub mod mock {
             })
         }
 
+        fn update_event(
+            &self,
+            calendar_id: &str,
+            event_id: &str,
+            patch: CalendarEventPatch,
+        ) -> std::pin::Pin<
+            Box<
+                dyn std::future::Future<Output = Result<CalendarEventResult, Self::Error>>
+                    + Send
+                    + '_,
+            >,
+        > {
+            let calendar_id = calendar_id.to_string();
+            let event_id = event_id.to_string();
+
+            Box::pin(async move {
+                let new_start = parse_patch_time(patch.start_time.as_deref(), "start_time")
+                    .map_err(GcalServiceError::TimeParseError)?;
+                let new_end = parse_patch_time(patch.end_time.as_deref(), "end_time")
+                    .map_err(GcalServiceError::TimeParseError)?;
+
+                let current = {
+                    let events = self.events.lock().unwrap();
+                    events
+                        .get(&calendar_id)
+                        .and_then(|events| events.iter().find(|(id, _, _)| id == &event_id))
+                        .map(|(_, event, _)| event.clone())
+                };
+                let Some(current) = current else {
+                    return Err(GcalServiceError::CalculationError(format!(
+                        "Event not found: {}",
+                        event_id
+                    )));
+                };
+
+                if new_start.is_some() || new_end.is_some() {
+                    let start = match new_start {
+                        Some(start) => start,
+                        None => parse_patch_time(Some(&current.start_time), "start_time")
+                            .map_err(GcalServiceError::TimeParseError)?
+                            .unwrap_or_default(),
+                    };
+                    let end = match new_end {
+                        Some(end) => end,
+                        None => parse_patch_time(Some(&current.end_time), "end_time")
+                            .map_err(GcalServiceError::TimeParseError)?
+                            .unwrap_or_default(),
+                    };
+                    check_reschedule_conflicts(self, &calendar_id, &event_id, start, end).await?;
+                }
+
+                let mut events = self.events.lock().unwrap();
+                let calendar_events = events.entry(calendar_id).or_default();
+                for (id, event, status) in calendar_events.iter_mut() {
+                    if id == &event_id {
+                        if let Some(start_time) = patch.start_time {
+                            event.start_time = start_time;
+                        }
+                        if let Some(end_time) = patch.end_time {
+                            event.end_time = end_time;
+                        }
+                        if let Some(summary) = patch.summary {
+                            event.summary = summary;
+                        }
+                        if patch.description.is_some() {
+                            event.description = patch.description;
+                        }
+                        return Ok(CalendarEventResult {
+                            event_id: Some(id.clone()),
+                            status: status.clone(),
+                            meet_link: None,
+                        });
+                    }
+                }
+
+                Err(GcalServiceError::CalculationError(format!(
+                    "Event not found: {}",
+                    event_id
+                )))
+            })
+        }
+
         fn mark_event_cancelled(
             &self,
             calendar_id: &str,
@@ -898,6 +1623,7 @@ pub mod mock {
                             return Ok(CalendarEventResult {
                                 event_id: Some(id.clone()),
                                 status: "cancelled".to_string(),
+                                meet_link: None,
                             });
                         }
                     }
@@ -954,6 +1680,9 @@ pub mod mock {
                             payment_method: event.payment_method,
                             payment_amount: event.payment_amount,
                             room_name: event.room_name,
+                            invoice_url: event.invoice_url,
+                            color_id: event.color_id,
+                            tags: event.tags,
                         });
                     }
                 }
@@ -6,7 +6,7 @@ mod tests {
     use crate::service::mock::MockCalendarService;
     use chrono::{DateTime, Duration, Utc};
     use chrono_tz::Tz;
-    use connectify_common::services::{CalendarEvent, CalendarService};
+    use connectify_common::services::{CalendarEvent, CalendarEventPatch, CalendarService};
     use std::str::FromStr;
 
     #[tokio::test]
@@ -31,6 +31,12 @@ mod tests {
             payment_amount: None,
             payment_method: None,
             room_name: None,
+            invoice_url: None,
+            attendees: Vec::new(),
+            create_meet_link: false,
+            transparent: false,
+            color_id: None,
+            tags: Vec::new(),
         };
 
         // Create the event
@@ -125,6 +131,12 @@ mod tests {
             payment_amount: None,
             payment_method: None,
             room_name: None,
+            invoice_url: None,
+            attendees: Vec::new(),
+            create_meet_link: false,
+            transparent: false,
+            color_id: None,
+            tags: Vec::new(),
         };
 
         // Create the event
@@ -166,6 +178,12 @@ mod tests {
             payment_amount: None,
             payment_method: None,
             room_name: None,
+            invoice_url: None,
+            attendees: Vec::new(),
+            create_meet_link: false,
+            transparent: false,
+            color_id: None,
+            tags: Vec::new(),
         };
 
         // This should fail with a conflict error
@@ -184,6 +202,12 @@ mod tests {
             payment_amount: None,
             payment_method: None,
             room_name: None,
+            invoice_url: None,
+            attendees: Vec::new(),
+            create_meet_link: false,
+            transparent: false,
+            color_id: None,
+            tags: Vec::new(),
         };
 
         // This should succeed
@@ -201,4 +225,229 @@ mod tests {
         // Verify the busy times
         assert_eq!(busy_times.len(), 2);
     }
+
+    #[tokio::test]
+    async fn test_update_event() {
+        let service = MockCalendarService::new();
+        let calendar_id = "test-calendar";
+        let now = Utc::now().with_timezone(&Tz::UTC);
+        let start_time = now + Duration::hours(1);
+
+        let event = |start: DateTime<Tz>, summary: &str| CalendarEvent {
+            start_time: start.to_rfc3339(),
+            end_time: (start + Duration::hours(1)).to_rfc3339(),
+            summary: summary.to_string(),
+            description: None,
+            payment_id: None,
+            payment_amount: None,
+            payment_method: None,
+            room_name: None,
+            invoice_url: None,
+            attendees: Vec::new(),
+            create_meet_link: false,
+            transparent: false,
+            color_id: None,
+            tags: Vec::new(),
+        };
+        let event_id = service
+            .create_event(calendar_id, event(start_time, "Test Event"))
+            .await
+            .unwrap()
+            .event_id
+            .unwrap();
+        service
+            .create_event(calendar_id, event(start_time + Duration::hours(3), "Other"))
+            .await
+            .unwrap();
+
+        // Moving by half an hour overlaps only the event itself
+        let result = service
+            .update_event(
+                calendar_id,
+                &event_id,
+                CalendarEventPatch {
+                    start_time: Some((start_time + Duration::minutes(30)).to_rfc3339()),
+                    end_time: Some((start_time + Duration::minutes(90)).to_rfc3339()),
+                    summary: Some("Rescheduled".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.event_id.as_deref(), Some(event_id.as_str()));
+
+        let events = service
+            .get_booked_events(calendar_id, now, now + Duration::hours(2), false)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Rescheduled");
+        assert_eq!(
+            events[0].start_time,
+            (start_time + Duration::minutes(30)).to_rfc3339()
+        );
+
+        // Moving onto the other event is a conflict
+        let result = service
+            .update_event(
+                calendar_id,
+                &event_id,
+                CalendarEventPatch {
+                    start_time: Some((start_time + Duration::hours(3)).to_rfc3339()),
+                    end_time: Some((start_time + Duration::hours(4)).to_rfc3339()),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(crate::service::GcalServiceError::Conflict)
+        ));
+    }
+
+    #[test]
+    fn test_meet_link() {
+        use crate::service::meet_link;
+        use google_calendar3::api::{ConferenceData, EntryPoint, Event};
+
+        assert_eq!(meet_link(&Event::default()), None);
+
+        let event = Event {
+            conference_data: Some(ConferenceData {
+                entry_points: Some(vec![
+                    EntryPoint {
+                        entry_point_type: Some("phone".to_string()),
+                        uri: Some("tel:+41-44-000-00-00".to_string()),
+                        ..Default::default()
+                    },
+                    EntryPoint {
+                        entry_point_type: Some("video".to_string()),
+                        uri: Some("https://meet.google.com/abc-defg-hij".to_string()),
+                        ..Default::default()
+                    },
+                ]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            meet_link(&event).as_deref(),
+            Some("https://meet.google.com/abc-defg-hij")
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_errors() {
+        use crate::service::{is_rate_limit_error, is_transient_google_error, GcalServiceError};
+        use connectify_common::ConnectifyError;
+        use serde_json::json;
+
+        let google_error = |code: u16, reason: &str| {
+            google_calendar3::Error::BadRequest(json!({
+                "error": { "code": code, "errors": [{ "reason": reason }] }
+            }))
+        };
+
+        let error = google_error(403, "rateLimitExceeded");
+        assert!(is_rate_limit_error(&error));
+        assert!(is_transient_google_error(&error));
+        let error = GcalServiceError::from(error);
+        assert!(matches!(
+            error,
+            GcalServiceError::RateLimited { retry_after: None }
+        ));
+        assert!(matches!(
+            ConnectifyError::from(error),
+            ConnectifyError::RateLimitError(_)
+        ));
+        assert!(is_rate_limit_error(&google_error(429, "rateLimitExceeded")));
+
+        // Other permission errors are permanent
+        let error = google_error(403, "forbidden");
+        assert!(!is_rate_limit_error(&error));
+        assert!(!is_transient_google_error(&error));
+        assert!(matches!(
+            GcalServiceError::from(error),
+            GcalServiceError::ApiError(_)
+        ));
+
+        let error = google_error(503, "backendError");
+        assert!(!is_rate_limit_error(&error));
+        assert!(is_transient_google_error(&error));
+    }
+
+    #[tokio::test]
+    async fn test_event_lock() {
+        use crate::service::{acquire_event_lock, event_lock_key};
+        use connectify_common::lock::{DistributedLock, InMemoryLock};
+        use std::sync::Arc;
+
+        assert_ne!(event_lock_key("primary"), event_lock_key("other"));
+
+        let lock = Arc::new(InMemoryLock::new());
+        let lease = acquire_event_lock(&*lock, "primary").await.unwrap();
+        assert_eq!(lease.key, event_lock_key("primary"));
+
+        // Writes to other calendars don't wait
+        let other = acquire_event_lock(&*lock, "other").await.unwrap();
+        lock.release(&other).await.unwrap();
+
+        // A second write to the same calendar waits for the first one to finish
+        let waiting = tokio::spawn({
+            let lock = lock.clone();
+            async move { acquire_event_lock(&*lock, "primary").await.map(|_| ()) }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(!waiting.is_finished());
+        lock.release(&lease).await.unwrap();
+        assert!(waiting.await.unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_booking_metadata_round_trip() {
+        use crate::service::{extract_payment_metadata, BookingMetadata};
+        use google_calendar3::api::{Event, EventExtendedProperties};
+
+        let event = CalendarEvent {
+            start_time: "2025-05-15T10:00:00Z".to_string(),
+            end_time: "2025-05-15T11:00:00Z".to_string(),
+            summary: "Consultation".to_string(),
+            description: None,
+            payment_method: Some("stripe".to_string()),
+            payment_id: Some("pi_123".to_string()),
+            payment_amount: Some(7500),
+            room_name: Some("adhoc-room".to_string()),
+            invoice_url: Some("https://pay.stripe.com/invoice/acct_1/in_1/pdf".to_string()),
+            attendees: Vec::new(),
+            create_meet_link: false,
+            transparent: false,
+            color_id: Some("5".to_string()),
+            tags: vec!["video".to_string(), "consultation".to_string()],
+        };
+        let mut google_event = Event {
+            extended_properties: Some(EventExtendedProperties {
+                private: Some(extract_payment_metadata(&event)),
+                shared: None,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            BookingMetadata::from_event(&google_event),
+            BookingMetadata {
+                payment_method: Some("stripe".to_string()),
+                payment_id: Some("pi_123".to_string()),
+                payment_amount: Some(7500),
+                room_name: Some("adhoc-room".to_string()),
+                invoice_url: Some("https://pay.stripe.com/invoice/acct_1/in_1/pdf".to_string()),
+                tags: vec!["consultation".to_string(), "video".to_string()],
+            }
+        );
+
+        // Events created elsewhere have no metadata
+        google_event.extended_properties = None;
+        assert_eq!(
+            BookingMetadata::from_event(&google_event),
+            BookingMetadata::default()
+        );
+    }
 }
@@ -1,6 +1,7 @@
 use axum::{body::Body, http::Request};
-use connectify_config::{AppConfig, GcalConfig, PriceTier, StripeConfig};
+use connectify_config::{AppConfig, GcalConfig, PriceTier, PricingConfig, StripeConfig};
 use connectify_gcal::routes::routes;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 // tower import removed as it's not available in the test environment
 
@@ -12,12 +13,22 @@ fn create_mock_config() -> Arc<AppConfig> {
             unit_amount: 5000, // $50.00
             currency: Some("USD".to_string()),
             product_name: Some("30-minute consultation".to_string()),
+            buffer_before_minutes: None,
+            buffer_after_minutes: None,
+            color_id: None,
+            tags: Vec::new(),
+            amounts: BTreeMap::new(),
         },
         PriceTier {
             duration_minutes: 60,
             unit_amount: 10000, // $100.00
             currency: Some("USD".to_string()),
             product_name: Some("60-minute consultation".to_string()),
+            buffer_before_minutes: None,
+            buffer_after_minutes: None,
+            color_id: None,
+            tags: Vec::new(),
+            amounts: BTreeMap::new(),
         },
     ];
 
@@ -27,8 +38,25 @@ fn create_mock_config() -> Arc<AppConfig> {
         unit_amount: Some(10000),
         product_name: Some("Test Product".to_string()),
         payment_success_url: "https://example.com/payment-success".to_string(),
-        price_tiers,
+        subscription_plans: vec![],
+        automatic_tax: false,
+        tax_behavior: None,
+        billing_address_collection: None,
+        checkout_expires_after_minutes: None,
+        create_invoices: false,
+        fulfillment_max_attempts: None,
+        payment_method_types: Vec::new(),
+        api_base_url: None,
+        portal_return_url: None,
+        webhook_tolerance_seconds: None,
+        success_token_secret: None,
+        deprecated_default_currency: None,
+        deprecated_price_tiers: Vec::new(),
+    };
+
+    let pricing_config = PricingConfig {
         default_currency: Some("USD".to_string()),
+        price_tiers,
     };
 
     let gcal_config = GcalConfig {
@@ -36,6 +64,7 @@ fn create_mock_config() -> Arc<AppConfig> {
         key_path: Some("test_key.json".to_string()),
         time_slot_duration: Some(30),
         preparation_time_minutes: Some(120),
+        max_advance_days: None,
         time_zone: Some("Europe/Zurich".to_string()),
         working_days: Some(vec![
             "Mon".to_string(),
@@ -46,6 +75,22 @@ fn create_mock_config() -> Arc<AppConfig> {
         ]),
         work_start_time: Some("09:00".to_string()),
         work_end_time: Some("17:00".to_string()),
+        weekly_schedule: None,
+        blackout_dates: Vec::new(),
+        holiday_calendars: Vec::new(),
+        calendars: Vec::new(),
+        busy_times_cache_seconds: None,
+        push_notification_url: None,
+        hold_ttl_minutes: None,
+        buffer_before_minutes: None,
+        buffer_after_minutes: None,
+        impersonate_user: None,
+        backend: Default::default(),
+        caldav: None,
+        oauth: None,
+        mirror_events: false,
+        fulfillment_styles: Default::default(),
+        assignment: None,
     };
 
     Arc::new(AppConfig {
@@ -57,6 +102,7 @@ fn create_mock_config() -> Arc<AppConfig> {
         use_calendly: false,
         use_adhoc: false,
         use_firebase: false,
+        use_msgraph: false,
         server: connectify_config::ServerConfig {
             host: "127.0.0.1".to_string(),
             port: 8080,
@@ -64,6 +110,7 @@ fn create_mock_config() -> Arc<AppConfig> {
         database: Some(connectify_config::DatabaseConfig {
             url: "postgres://localhost/test".to_string(),
         }),
+        pricing: Some(pricing_config),
         gcal: Some(gcal_config),
         stripe: Some(stripe_config),
         twilio: None,
@@ -71,6 +118,16 @@ fn create_mock_config() -> Arc<AppConfig> {
         fulfillment: None,
         adhoc_settings: None,
         firebase: None,
+        msgraph: None,
+        rate_limit: None,
+        api_keys: None,
+        jwt: None,
+        circuit_breaker: None,
+        scheduler: None,
+        runtime_flags: None,
+        cache: None,
+        lock: None,
+        queue: None,
     })
 }
 
@@ -5,7 +5,8 @@
 
 use chrono::{DateTime, Duration, NaiveTime, Utc, Weekday};
 use connectify_common::services::CalendarEvent;
-use connectify_config::{AppConfig, GcalConfig, PriceTier, StripeConfig};
+use connectify_config::{AppConfig, GcalConfig, PriceTier, PricingConfig, StripeConfig};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 /// Creates a test calendar event with the given parameters
@@ -27,6 +28,12 @@ pub fn create_test_calendar_event(
         payment_amount: None,
         payment_method: None,
         room_name: None,
+        invoice_url: None,
+        attendees: Vec::new(),
+        create_meet_link: false,
+        transparent: false,
+        color_id: None,
+        tags: Vec::new(),
     }
 }
 
@@ -59,18 +66,33 @@ pub fn create_mock_config() -> Arc<AppConfig> {
             unit_amount: 5000, // $50.00
             currency: Some("USD".to_string()),
             product_name: Some("30-minute consultation".to_string()),
+            buffer_before_minutes: None,
+            buffer_after_minutes: None,
+            color_id: None,
+            tags: Vec::new(),
+            amounts: BTreeMap::new(),
         },
         PriceTier {
             duration_minutes: 60,
             unit_amount: 10000, // $100.00
             currency: Some("USD".to_string()),
             product_name: Some("60-minute consultation".to_string()),
+            buffer_before_minutes: None,
+            buffer_after_minutes: None,
+            color_id: None,
+            tags: Vec::new(),
+            amounts: BTreeMap::new(),
         },
         PriceTier {
             duration_minutes: 90,
             unit_amount: 15000, // $150.00
             currency: Some("USD".to_string()),
             product_name: Some("90-minute consultation".to_string()),
+            buffer_before_minutes: None,
+            buffer_after_minutes: None,
+            color_id: None,
+            tags: Vec::new(),
+            amounts: BTreeMap::new(),
         },
     ];
 
@@ -81,8 +103,25 @@ pub fn create_mock_config() -> Arc<AppConfig> {
         unit_amount: Some(10000),
         product_name: Some("Test Product".to_string()),
         payment_success_url: "https://example.com/payment-success".to_string(),
-        price_tiers,
+        subscription_plans: vec![],
+        automatic_tax: false,
+        tax_behavior: None,
+        billing_address_collection: None,
+        checkout_expires_after_minutes: None,
+        create_invoices: false,
+        fulfillment_max_attempts: None,
+        payment_method_types: Vec::new(),
+        api_base_url: None,
+        portal_return_url: None,
+        webhook_tolerance_seconds: None,
+        success_token_secret: None,
+        deprecated_default_currency: None,
+        deprecated_price_tiers: Vec::new(),
+    };
+
+    let pricing_config = PricingConfig {
         default_currency: Some("USD".to_string()),
+        price_tiers,
     };
 
     // Create GCal config
@@ -91,6 +130,7 @@ pub fn create_mock_config() -> Arc<AppConfig> {
         time_slot_duration: Some(30),
         key_path: Some("test_key.json".to_string()),
         preparation_time_minutes: Some(120),
+        max_advance_days: None,
         time_zone: Some("Europe/Zurich".to_string()),
         working_days: Some(vec![
             "Mon".to_string(),
@@ -101,6 +141,22 @@ pub fn create_mock_config() -> Arc<AppConfig> {
         ]),
         work_start_time: Some("09:00".to_string()),
         work_end_time: Some("17:00".to_string()),
+        weekly_schedule: None,
+        blackout_dates: Vec::new(),
+        holiday_calendars: Vec::new(),
+        calendars: Vec::new(),
+        busy_times_cache_seconds: None,
+        push_notification_url: None,
+        hold_ttl_minutes: None,
+        buffer_before_minutes: None,
+        buffer_after_minutes: None,
+        impersonate_user: None,
+        backend: Default::default(),
+        caldav: None,
+        oauth: None,
+        mirror_events: false,
+        fulfillment_styles: Default::default(),
+        assignment: None,
     };
 
     // Create and return the AppConfig
@@ -113,6 +169,7 @@ pub fn create_mock_config() -> Arc<AppConfig> {
         use_calendly: false,
         use_adhoc: false,
         use_firebase: false,
+        use_msgraph: false,
         server: connectify_config::ServerConfig {
             host: "127.0.0.1".to_string(),
             port: 8080,
@@ -120,6 +177,7 @@ pub fn create_mock_config() -> Arc<AppConfig> {
         database: Some(connectify_config::DatabaseConfig {
             url: "postgres://localhost/test".to_string(),
         }),
+        pricing: Some(pricing_config),
         gcal: Some(gcal_config),
         stripe: Some(stripe_config),
         twilio: None,
@@ -127,6 +185,16 @@ pub fn create_mock_config() -> Arc<AppConfig> {
         fulfillment: None,
         adhoc_settings: None,
         firebase: None,
+        msgraph: None,
+        rate_limit: None,
+        api_keys: None,
+        jwt: None,
+        circuit_breaker: None,
+        scheduler: None,
+        runtime_flags: None,
+        cache: None,
+        lock: None,
+        queue: None,
     })
 }
 
@@ -209,10 +277,18 @@ mod tests {
         // Check Stripe config
         assert!(config.use_stripe);
         assert!(config.stripe.is_some());
-        let stripe_config = config.stripe.as_ref().unwrap();
-        assert_eq!(stripe_config.price_tiers.len(), 3);
-        assert_eq!(stripe_config.price_tiers[0].duration_minutes, 30);
-        assert_eq!(stripe_config.price_tiers[1].duration_minutes, 60);
-        assert_eq!(stripe_config.price_tiers[2].duration_minutes, 90);
+
+        // Check pricing config
+        let pricing_config = config.pricing.as_ref().unwrap();
+        assert_eq!(pricing_config.price_tiers.len(), 3);
+        assert_eq!(pricing_config.price_tiers[0].duration_minutes, 30);
+        assert_eq!(pricing_config.price_tiers[1].duration_minutes, 60);
+        assert_eq!(pricing_config.price_tiers[2].duration_minutes, 90);
+        assert_eq!(
+            pricing_config
+                .tier_for_duration(60)
+                .and_then(|tier| tier.product_name.as_deref()),
+            Some("60-minute consultation")
+        );
     }
 }
@@ -0,0 +1,89 @@
+// --- File: crates/connectify_msgraph/src/auth.rs ---
+//! OAuth 2.0 client credentials flow of the Microsoft identity platform.
+//!
+//! The app registration authenticates as itself (no signed-in user), so the mailbox must be
+//! granted to it through the `Calendars.ReadWrite` application permission.
+
+use crate::error::MsGraphError;
+use connectify_common::request_id::RequestIdExt;
+use connectify_common::retry::{send_with_retry, RetryPolicy};
+use connectify_common::HTTP_CLIENT;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::debug;
+
+/// Environment variable holding the client secret of the app registration.
+pub const CLIENT_SECRET_ENV: &str = "MSGRAPH_CLIENT_SECRET";
+
+const GRAPH_SCOPE: &str = "https://graph.microsoft.com/.default";
+/// Tokens are renewed this long before they expire.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+struct CachedToken {
+    access_token: String,
+    expires_at: Instant,
+}
+
+/// Obtains and caches access tokens for Microsoft Graph.
+pub struct ClientCredentials {
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    token: Mutex<Option<CachedToken>>,
+}
+
+impl ClientCredentials {
+    /// Create client credentials of an app registration in the given tenant.
+    pub fn new(tenant_id: &str, client_id: &str, client_secret: String) -> Self {
+        Self {
+            token_url: format!(
+                "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
+                tenant_id
+            ),
+            client_id: client_id.to_string(),
+            client_secret,
+            token: Mutex::new(None),
+        }
+    }
+
+    /// A valid access token, requesting a new one if the cached one is about to expire.
+    pub async fn access_token(&self) -> Result<String, MsGraphError> {
+        let mut token = self.token.lock().await;
+        if let Some(cached) = token.as_ref() {
+            if cached.expires_at > Instant::now() + EXPIRY_MARGIN {
+                return Ok(cached.access_token.clone());
+            }
+        }
+
+        debug!("Requesting Microsoft Graph access token");
+        let response = send_with_retry(
+            &RetryPolicy::default(),
+            HTTP_CLIENT.post(&self.token_url).with_request_id().form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("scope", GRAPH_SCOPE),
+                ("grant_type", "client_credentials"),
+            ]),
+        )
+        .await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(MsGraphError::AuthError(format!("{}: {}", status, body)));
+        }
+        let response: TokenResponse = serde_json::from_str(&body)?;
+        let access_token = response.access_token.clone();
+        *token = Some(CachedToken {
+            access_token: response.access_token,
+            expires_at: Instant::now() + Duration::from_secs(response.expires_in),
+        });
+        Ok(access_token)
+    }
+}
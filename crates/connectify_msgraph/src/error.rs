@@ -0,0 +1,86 @@
+// --- File: crates/connectify_msgraph/src/error.rs ---
+use connectify_common::http::circuit_breaker::HttpClientError;
+use connectify_common::{external_service_error, ConnectifyError};
+use thiserror::Error;
+
+/// Microsoft Graph-specific error types.
+#[derive(Error, Debug)]
+pub enum MsGraphError {
+    /// Error occurred during a Graph API request
+    #[error("Microsoft Graph request failed: {0}")]
+    RequestError(#[from] reqwest::Error),
+
+    /// Graph API is unavailable (circuit breaker open)
+    #[error("Microsoft Graph unavailable: {0}")]
+    ServiceUnavailable(String),
+
+    /// Error returned by the Graph API
+    #[error("Microsoft Graph returned an error: {message} (Status: {status_code})")]
+    ApiError { status_code: u16, message: String },
+
+    /// No access token could be obtained for the app registration
+    #[error("Microsoft Graph authentication failed: {0}")]
+    AuthError(String),
+
+    /// The event or calendar does not exist
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// The event overlaps another one
+    #[error("Booking conflict")]
+    Conflict,
+
+    /// Error parsing a time
+    #[error("Failed to parse time: {0}")]
+    TimeParseError(String),
+
+    /// Error parsing a Graph API response
+    #[error("Failed to parse Microsoft Graph response: {0}")]
+    ParseError(#[from] serde_json::Error),
+
+    /// Missing or incomplete Graph configuration
+    #[error("Microsoft Graph configuration missing or incomplete: {0}")]
+    ConfigError(String),
+}
+
+/// Convert errors from requests sent through the circuit breaker
+impl From<HttpClientError> for MsGraphError {
+    fn from(err: HttpClientError) -> Self {
+        match err {
+            HttpClientError::CircuitOpen { host } => MsGraphError::ServiceUnavailable(format!(
+                "Circuit open for {}, not sending request",
+                host
+            )),
+            HttpClientError::Request(e) => MsGraphError::RequestError(e),
+        }
+    }
+}
+
+/// Convert MsGraphError to ConnectifyError
+impl From<MsGraphError> for ConnectifyError {
+    fn from(err: MsGraphError) -> Self {
+        match err {
+            MsGraphError::RequestError(e) => {
+                ConnectifyError::HttpError(format!("Microsoft Graph request error: {}", e))
+            }
+            MsGraphError::ServiceUnavailable(msg) => external_service_error("Microsoft Graph", msg),
+            MsGraphError::ApiError {
+                status_code,
+                message,
+            } => external_service_error(
+                "Microsoft Graph",
+                format!("Status: {}, Message: {}", status_code, message),
+            ),
+            MsGraphError::AuthError(msg) => external_service_error("Microsoft identity", msg),
+            MsGraphError::NotFound(msg) => ConnectifyError::NotFoundError(msg),
+            MsGraphError::Conflict => {
+                ConnectifyError::ConflictError("Booking conflict".to_string())
+            }
+            MsGraphError::TimeParseError(msg) => ConnectifyError::ParseError(msg),
+            MsGraphError::ParseError(e) => {
+                ConnectifyError::ParseError(format!("Microsoft Graph response parse error: {}", e))
+            }
+            MsGraphError::ConfigError(msg) => ConnectifyError::ConfigError(msg),
+        }
+    }
+}
@@ -0,0 +1,13 @@
+// --- File: crates/connectify_msgraph/src/lib.rs ---
+//! Microsoft Graph (Outlook) calendar integration for Connectify.
+//!
+//! Provides [`service::MsGraphCalendarService`], a `CalendarService` for Outlook calendars,
+//! which the backend registers in its service registry when `use_msgraph` is set.
+
+/// OAuth client credentials for Microsoft Graph.
+pub mod auth;
+pub mod error;
+/// This module provides the Microsoft Graph calendar service implementation.
+pub mod service;
+#[cfg(test)]
+mod service_test;
@@ -0,0 +1,605 @@
+// --- File: crates/connectify_msgraph/src/service.rs ---
+//! Microsoft Graph calendar service implementation.
+//!
+//! This module provides an implementation of the CalendarService trait for Outlook calendars
+//! through Microsoft Graph. Calendar IDs are those of the configured mailbox, with "primary"
+//! standing for its default calendar.
+
+use crate::auth::{ClientCredentials, CLIENT_SECRET_ENV};
+use crate::error::MsGraphError;
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use connectify_common::request_id::RequestIdExt;
+use connectify_common::retry::{send_with_retry, RetryPolicy};
+use connectify_common::services::{
+    BookedEvent, BoxFuture, CalendarEvent, CalendarEventPatch, CalendarEventResult, CalendarService,
+};
+use connectify_common::HTTP_CLIENT;
+use connectify_config::MsGraphConfig;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use tracing::{debug, info};
+
+const GRAPH_API_URL: &str = "https://graph.microsoft.com/v1.0";
+/// Property set of the extended properties storing payment metadata on events.
+const PROPERTY_SET_ID: &str = "{4f7c3b3e-8d2a-4a51-9c61-2b1f0e6d9a57}";
+/// The payment metadata stored on events.
+const METADATA_PROPERTIES: [&str; 5] = [
+    "payment_method",
+    "payment_id",
+    "payment_amount",
+    "room_name",
+    "invoice_url",
+];
+
+/// The ID of the extended property storing a payment metadata field.
+fn property_id(name: &str) -> String {
+    format!("String {} Name {}", PROPERTY_SET_ID, name)
+}
+
+/// A date and time with its time zone, as used by Graph.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphDateTime {
+    pub date_time: String,
+    pub time_zone: String,
+}
+
+impl GraphDateTime {
+    /// Parse the time; requests ask for UTC, but other zones are honoured as well.
+    pub fn parse(&self) -> Result<DateTime<Utc>, MsGraphError> {
+        let invalid = || MsGraphError::TimeParseError(self.date_time.clone());
+        let local = NaiveDateTime::parse_from_str(&self.date_time, "%Y-%m-%dT%H:%M:%S%.f")
+            .map_err(|_| invalid())?;
+        match Tz::from_str(&self.time_zone) {
+            Ok(time_zone) if self.time_zone != "UTC" => time_zone
+                .from_local_datetime(&local)
+                .earliest()
+                .map(|time| time.with_timezone(&Utc))
+                .ok_or_else(invalid),
+            _ => Ok(Utc.from_utc_datetime(&local)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphItemBody {
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphOnlineMeeting {
+    pub join_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GraphExtendedProperty {
+    pub id: String,
+    pub value: String,
+}
+
+/// An event as returned by Graph.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphEvent {
+    pub id: String,
+    pub subject: Option<String>,
+    pub body: Option<GraphItemBody>,
+    pub start: GraphDateTime,
+    pub end: GraphDateTime,
+    #[serde(default)]
+    pub is_cancelled: bool,
+    /// "free", "tentative", "busy", "oof", "workingElsewhere" or "unknown".
+    pub show_as: Option<String>,
+    pub created_date_time: Option<String>,
+    pub last_modified_date_time: Option<String>,
+    pub online_meeting: Option<GraphOnlineMeeting>,
+    #[serde(default)]
+    pub single_value_extended_properties: Vec<GraphExtendedProperty>,
+}
+
+impl GraphEvent {
+    /// Whether the event blocks its time, i.e. is neither cancelled nor shown as free.
+    pub fn blocks_time(&self) -> bool {
+        !self.is_cancelled
+            && !matches!(
+                self.show_as.as_deref(),
+                Some("free") | Some("workingElsewhere")
+            )
+    }
+
+    fn metadata(&self, name: &str) -> Option<String> {
+        let id = property_id(name);
+        self.single_value_extended_properties
+            .iter()
+            .find(|property| property.id.eq_ignore_ascii_case(&id))
+            .map(|property| property.value.clone())
+    }
+
+    /// Convert the event, rendering its times in `time_zone`.
+    pub fn into_booked_event(self, time_zone: &Tz) -> Result<BookedEvent, MsGraphError> {
+        let status = if self.is_cancelled {
+            "cancelled"
+        } else if self.show_as.as_deref() == Some("tentative") {
+            "tentative"
+        } else {
+            "confirmed"
+        };
+        Ok(BookedEvent {
+            start_time: self.start.parse()?.with_timezone(time_zone).to_rfc3339(),
+            end_time: self.end.parse()?.with_timezone(time_zone).to_rfc3339(),
+            status: status.to_string(),
+            payment_method: self.metadata("payment_method"),
+            payment_id: self.metadata("payment_id"),
+            payment_amount: self
+                .metadata("payment_amount")
+                .and_then(|amount| amount.parse().ok()),
+            room_name: self.metadata("room_name"),
+            invoice_url: self.metadata("invoice_url"),
+            color_id: None,
+            tags: Vec::new(),
+            event_id: self.id,
+            summary: self.subject.unwrap_or_default(),
+            description: self.body.and_then(|body| body.content),
+            created: self.created_date_time.unwrap_or_default(),
+            updated: self.last_modified_date_time.unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphEventPage {
+    value: Vec<GraphEvent>,
+    #[serde(rename = "@odata.nextLink")]
+    next_link: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MeetingTimeSuggestionsResult {
+    #[serde(default)]
+    meeting_time_suggestions: Vec<MeetingTimeSuggestion>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MeetingTimeSuggestion {
+    meeting_time_slot: TimeSlot,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimeSlot {
+    start: GraphDateTime,
+    end: GraphDateTime,
+}
+
+fn graph_date_time(time: DateTime<Utc>) -> Value {
+    json!({
+        "dateTime": time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        "timeZone": "UTC",
+    })
+}
+
+fn parse_time(time: &str) -> Result<DateTime<Utc>, MsGraphError> {
+    DateTime::parse_from_rfc3339(time)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|e| MsGraphError::TimeParseError(format!("{}: {}", time, e)))
+}
+
+/// Format a duration as ISO 8601, e.g. `PT1H30M`, as expected by findMeetingTimes.
+pub fn iso_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    match (minutes / 60, minutes % 60) {
+        (hours, 0) => format!("PT{}H", hours),
+        (0, minutes) => format!("PT{}M", minutes),
+        (hours, minutes) => format!("PT{}H{}M", hours, minutes),
+    }
+}
+
+/// The request body creating an event.
+///
+/// `transaction_id` makes retries of the request safe: Graph creates only one event per ID.
+pub fn event_body(event: &CalendarEvent, transaction_id: &str) -> Result<Value, MsGraphError> {
+    let mut body = json!({
+        "subject": event.summary,
+        "body": {
+            "contentType": "text",
+            "content": event.description.clone().unwrap_or_default(),
+        },
+        "start": graph_date_time(parse_time(&event.start_time)?),
+        "end": graph_date_time(parse_time(&event.end_time)?),
+        "showAs": if event.transparent { "free" } else { "busy" },
+        "transactionId": transaction_id,
+        "attendees": event.attendees.iter().map(|address| json!({
+            "emailAddress": { "address": address },
+            "type": "required",
+        })).collect::<Vec<_>>(),
+    });
+    if event.create_meet_link {
+        body["isOnlineMeeting"] = json!(true);
+        body["onlineMeetingProvider"] = json!("teamsForBusiness");
+    }
+    let payment_amount = event.payment_amount.map(|amount| amount.to_string());
+    let metadata = [
+        event.payment_method.as_ref(),
+        event.payment_id.as_ref(),
+        payment_amount.as_ref(),
+        event.room_name.as_ref(),
+        event.invoice_url.as_ref(),
+    ];
+    let properties: Vec<Value> = METADATA_PROPERTIES
+        .iter()
+        .zip(metadata)
+        .filter_map(|(name, value)| {
+            value.map(|value| json!({ "id": property_id(name), "value": value }))
+        })
+        .collect();
+    if !properties.is_empty() {
+        body["singleValueExtendedProperties"] = json!(properties);
+    }
+    Ok(body)
+}
+
+/// Calendar service backed by Microsoft Graph.
+pub struct MsGraphCalendarService {
+    credentials: ClientCredentials,
+    /// The mailbox whose calendars are used.
+    user_id: String,
+}
+
+impl MsGraphCalendarService {
+    /// Create a new Microsoft Graph calendar service.
+    pub fn new(config: &MsGraphConfig, client_secret: String) -> Self {
+        Self {
+            credentials: ClientCredentials::new(
+                &config.tenant_id,
+                &config.client_id,
+                client_secret,
+            ),
+            user_id: config.user_id.clone(),
+        }
+    }
+
+    /// Create the service from its config, reading the client secret from `MSGRAPH_CLIENT_SECRET`.
+    pub fn from_config(config: &MsGraphConfig) -> Result<Self, MsGraphError> {
+        let client_secret = std::env::var(CLIENT_SECRET_ENV)
+            .map_err(|_| MsGraphError::ConfigError(format!("{} is not set", CLIENT_SECRET_ENV)))?;
+        Ok(Self::new(config, client_secret))
+    }
+
+    fn calendar_url(&self, calendar_id: &str) -> String {
+        match calendar_id {
+            "" | "primary" => format!("{}/users/{}/calendar", GRAPH_API_URL, self.user_id),
+            calendar_id => format!(
+                "{}/users/{}/calendars/{}",
+                GRAPH_API_URL, self.user_id, calendar_id
+            ),
+        }
+    }
+
+    fn event_url(&self, event_id: &str) -> String {
+        format!(
+            "{}/users/{}/events/{}",
+            GRAPH_API_URL, self.user_id, event_id
+        )
+    }
+
+    /// Send an authenticated request, turning error responses into errors.
+    async fn send(&self, request: RequestBuilder) -> Result<Response, MsGraphError> {
+        let access_token = self.credentials.access_token().await?;
+        let response = send_with_retry(
+            &RetryPolicy::default(),
+            request
+                .with_request_id()
+                .bearer_auth(access_token)
+                // Times in responses are in UTC
+                .header("Prefer", "outlook.timezone=\"UTC\""),
+        )
+        .await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|error| {
+                error
+                    .pointer("/error/message")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            })
+            .unwrap_or(body);
+        Err(match status {
+            StatusCode::NOT_FOUND => MsGraphError::NotFound(message),
+            StatusCode::CONFLICT | StatusCode::PRECONDITION_FAILED => MsGraphError::Conflict,
+            status => MsGraphError::ApiError {
+                status_code: status.as_u16(),
+                message,
+            },
+        })
+    }
+
+    /// The events of a calendar overlapping `start`-`end`, with recurring events expanded.
+    async fn calendar_view(
+        &self,
+        calendar_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<GraphEvent>, MsGraphError> {
+        let expand = format!(
+            "singleValueExtendedProperties($filter={})",
+            METADATA_PROPERTIES
+                .iter()
+                .map(|name| format!("id eq '{}'", property_id(name)))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        );
+        let mut request = HTTP_CLIENT
+            .get(format!("{}/calendarView", self.calendar_url(calendar_id)))
+            .query(&[
+                ("startDateTime", start.to_rfc3339()),
+                ("endDateTime", end.to_rfc3339()),
+                ("$orderby", "start/dateTime".to_string()),
+                ("$top", "100".to_string()),
+                ("$expand", expand),
+            ]);
+        let mut events = Vec::new();
+        loop {
+            let page: GraphEventPage = self.send(request).await?.json().await?;
+            events.extend(page.value);
+            match page.next_link {
+                Some(next_link) => request = HTTP_CLIENT.get(next_link),
+                None => break,
+            }
+        }
+        debug!("Fetched {} Graph events of {}", events.len(), calendar_id);
+        Ok(events)
+    }
+
+    /// Free slots of `duration` between `start` and `end` in the mailbox, as suggested by
+    /// Graph's findMeetingTimes. Working hours are not applied, callers filter the slots.
+    pub async fn find_meeting_times(
+        &self,
+        start: DateTime<Tz>,
+        end: DateTime<Tz>,
+        duration: Duration,
+    ) -> Result<Vec<(DateTime<Tz>, DateTime<Tz>)>, MsGraphError> {
+        let time_zone = start.timezone();
+        let body = json!({
+            "attendees": [],
+            "timeConstraint": {
+                "activityDomain": "unrestricted",
+                "timeSlots": [{
+                    "start": graph_date_time(start.with_timezone(&Utc)),
+                    "end": graph_date_time(end.with_timezone(&Utc)),
+                }],
+            },
+            "meetingDuration": iso_duration(duration),
+            "maxCandidates": 100,
+            "isOrganizerOptional": false,
+            "returnSuggestionReasons": false,
+            "minimumAttendeePercentage": 100,
+        });
+        let result: MeetingTimeSuggestionsResult = self
+            .send(
+                HTTP_CLIENT
+                    .post(format!(
+                        "{}/users/{}/findMeetingTimes",
+                        GRAPH_API_URL, self.user_id
+                    ))
+                    .json(&body),
+            )
+            .await?
+            .json()
+            .await?;
+        let mut slots = result
+            .meeting_time_suggestions
+            .into_iter()
+            .map(|suggestion| {
+                Ok((
+                    suggestion
+                        .meeting_time_slot
+                        .start
+                        .parse()?
+                        .with_timezone(&time_zone),
+                    suggestion
+                        .meeting_time_slot
+                        .end
+                        .parse()?
+                        .with_timezone(&time_zone),
+                ))
+            })
+            .collect::<Result<Vec<_>, MsGraphError>>()?;
+        slots.sort();
+        Ok(slots)
+    }
+
+    async fn get_event(&self, event_id: &str) -> Result<GraphEvent, MsGraphError> {
+        Ok(self
+            .send(HTTP_CLIENT.get(self.event_url(event_id)))
+            .await?
+            .json()
+            .await?)
+    }
+}
+
+impl CalendarService for MsGraphCalendarService {
+    type Error = MsGraphError;
+
+    fn get_busy_times(
+        &self,
+        calendar_id: &str,
+        start_time: DateTime<Tz>,
+        end_time: DateTime<Tz>,
+    ) -> BoxFuture<'_, Vec<(DateTime<Tz>, DateTime<Tz>)>, Self::Error> {
+        let calendar_id = calendar_id.to_string();
+        Box::pin(async move {
+            let time_zone = start_time.timezone();
+            let mut busy_periods = self
+                .calendar_view(
+                    &calendar_id,
+                    start_time.with_timezone(&Utc),
+                    end_time.with_timezone(&Utc),
+                )
+                .await?
+                .into_iter()
+                .filter(GraphEvent::blocks_time)
+                .map(|event| {
+                    Ok((
+                        event.start.parse()?.with_timezone(&time_zone),
+                        event.end.parse()?.with_timezone(&time_zone),
+                    ))
+                })
+                .collect::<Result<Vec<_>, MsGraphError>>()?;
+            busy_periods.sort();
+            Ok(busy_periods)
+        })
+    }
+
+    fn create_event(
+        &self,
+        calendar_id: &str,
+        event: CalendarEvent,
+    ) -> BoxFuture<'_, CalendarEventResult, Self::Error> {
+        let url = format!("{}/events", self.calendar_url(calendar_id));
+        Box::pin(async move {
+            let body = event_body(&event, &uuid::Uuid::new_v4().to_string())?;
+            let created: GraphEvent = self
+                .send(HTTP_CLIENT.post(url).json(&body))
+                .await?
+                .json()
+                .await?;
+            info!("Created Microsoft Graph event {}", created.id);
+            Ok(CalendarEventResult {
+                meet_link: created
+                    .online_meeting
+                    .and_then(|online_meeting| online_meeting.join_url),
+                event_id: Some(created.id),
+                status: "confirmed".to_string(),
+            })
+        })
+    }
+
+    fn update_event(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+        patch: CalendarEventPatch,
+    ) -> BoxFuture<'_, CalendarEventResult, Self::Error> {
+        let calendar_id = calendar_id.to_string();
+        let event_id = event_id.to_string();
+        Box::pin(async move {
+            let mut body = json!({});
+            let start = patch.start_time.as_deref().map(parse_time).transpose()?;
+            let end = patch.end_time.as_deref().map(parse_time).transpose()?;
+            if start.is_some() || end.is_some() {
+                let current = self.get_event(&event_id).await?;
+                let start = start.map_or_else(|| current.start.parse(), Ok)?;
+                let end = end.map_or_else(|| current.end.parse(), Ok)?;
+                if end <= start {
+                    return Err(MsGraphError::TimeParseError(
+                        "end_time must be after start_time".to_string(),
+                    ));
+                }
+                // The new time must not overlap any other event
+                let conflicting = self
+                    .calendar_view(&calendar_id, start, end)
+                    .await?
+                    .iter()
+                    .any(|other| other.id != event_id && other.blocks_time());
+                if conflicting {
+                    return Err(MsGraphError::Conflict);
+                }
+                body["start"] = graph_date_time(start);
+                body["end"] = graph_date_time(end);
+            }
+            if let Some(summary) = patch.summary {
+                body["subject"] = json!(summary);
+            }
+            if let Some(description) = patch.description {
+                body["body"] = json!({ "contentType": "text", "content": description });
+            }
+            let updated: GraphEvent = self
+                .send(HTTP_CLIENT.patch(self.event_url(&event_id)).json(&body))
+                .await?
+                .json()
+                .await?;
+            Ok(CalendarEventResult {
+                meet_link: updated
+                    .online_meeting
+                    .and_then(|online_meeting| online_meeting.join_url),
+                event_id: Some(updated.id),
+                status: "confirmed".to_string(),
+            })
+        })
+    }
+
+    fn delete_event(
+        &self,
+        _calendar_id: &str,
+        event_id: &str,
+        notify_attendees: bool,
+    ) -> BoxFuture<'_, (), Self::Error> {
+        let url = self.event_url(event_id);
+        Box::pin(async move {
+            let request = if notify_attendees {
+                // Cancelling sends the attendees a cancellation and removes the event
+                HTTP_CLIENT
+                    .post(format!("{}/cancel", url))
+                    .json(&json!({ "comment": "" }))
+            } else {
+                HTTP_CLIENT.delete(url)
+            };
+            self.send(request).await?;
+            Ok(())
+        })
+    }
+
+    /// Graph keeps no cancelled events in the organizer's calendar, so cancelling removes it.
+    fn mark_event_cancelled(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+        notify_attendees: bool,
+    ) -> BoxFuture<'_, CalendarEventResult, Self::Error> {
+        let calendar_id = calendar_id.to_string();
+        let event_id = event_id.to_string();
+        Box::pin(async move {
+            self.delete_event(&calendar_id, &event_id, notify_attendees)
+                .await?;
+            Ok(CalendarEventResult {
+                event_id: Some(event_id),
+                status: "cancelled".to_string(),
+                meet_link: None,
+            })
+        })
+    }
+
+    fn get_booked_events(
+        &self,
+        calendar_id: &str,
+        start_time: DateTime<Tz>,
+        end_time: DateTime<Tz>,
+        include_cancelled: bool,
+    ) -> BoxFuture<'_, Vec<BookedEvent>, Self::Error> {
+        let calendar_id = calendar_id.to_string();
+        Box::pin(async move {
+            let time_zone = start_time.timezone();
+            self.calendar_view(
+                &calendar_id,
+                start_time.with_timezone(&Utc),
+                end_time.with_timezone(&Utc),
+            )
+            .await?
+            .into_iter()
+            .filter(|event| include_cancelled || !event.is_cancelled)
+            .map(|event| event.into_booked_event(&time_zone))
+            .collect()
+        })
+    }
+}
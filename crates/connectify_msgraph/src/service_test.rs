@@ -0,0 +1,91 @@
+#[cfg(test)]
+mod tests {
+    use crate::service::{event_body, iso_duration, GraphEvent};
+    use chrono::Duration;
+    use chrono_tz::Tz;
+    use connectify_common::services::CalendarEvent;
+    use serde_json::json;
+
+    #[test]
+    fn test_graph_event_into_booked_event() {
+        let event: GraphEvent = serde_json::from_value(json!({
+            "id": "AAMkAGI2",
+            "subject": "Consultation",
+            "body": { "contentType": "text", "content": "Notes" },
+            "start": { "dateTime": "2025-05-15T08:00:00.0000000", "timeZone": "UTC" },
+            "end": { "dateTime": "2025-05-15T09:00:00.0000000", "timeZone": "UTC" },
+            "isCancelled": false,
+            "showAs": "busy",
+            "singleValueExtendedProperties": [{
+                "id": "String {4f7c3b3e-8d2a-4a51-9c61-2b1f0e6d9a57} Name payment_amount",
+                "value": "7500"
+            }]
+        }))
+        .unwrap();
+        assert!(event.blocks_time());
+
+        let booked = event.into_booked_event(&Tz::Europe__Zurich).unwrap();
+        assert_eq!(booked.event_id, "AAMkAGI2");
+        assert_eq!(booked.start_time, "2025-05-15T10:00:00+02:00");
+        assert_eq!(booked.end_time, "2025-05-15T11:00:00+02:00");
+        assert_eq!(booked.status, "confirmed");
+        assert_eq!(booked.description.as_deref(), Some("Notes"));
+        assert_eq!(booked.payment_amount, Some(7500));
+        assert_eq!(booked.payment_id, None);
+    }
+
+    #[test]
+    fn test_free_events_do_not_block() {
+        let event: GraphEvent = serde_json::from_value(json!({
+            "id": "AAMkAGI3",
+            "start": { "dateTime": "2025-05-15T08:00:00", "timeZone": "Europe/Zurich" },
+            "end": { "dateTime": "2025-05-15T09:00:00", "timeZone": "Europe/Zurich" },
+            "showAs": "free"
+        }))
+        .unwrap();
+        assert!(!event.blocks_time());
+        assert_eq!(
+            event.start.parse().unwrap().to_rfc3339(),
+            "2025-05-15T06:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_event_body() {
+        let event = CalendarEvent {
+            start_time: "2025-05-15T10:00:00+02:00".to_string(),
+            end_time: "2025-05-15T11:00:00+02:00".to_string(),
+            summary: "Consultation".to_string(),
+            description: None,
+            payment_method: Some("stripe".to_string()),
+            payment_id: None,
+            payment_amount: Some(7500),
+            room_name: None,
+            invoice_url: None,
+            attendees: vec!["client@example.com".to_string()],
+            create_meet_link: true,
+            transparent: false,
+            color_id: None,
+            tags: Vec::new(),
+        };
+        let body = event_body(&event, "tx-1").unwrap();
+        assert_eq!(body["start"]["dateTime"], "2025-05-15T08:00:00");
+        assert_eq!(body["start"]["timeZone"], "UTC");
+        assert_eq!(body["transactionId"], "tx-1");
+        assert_eq!(
+            body["attendees"][0]["emailAddress"]["address"],
+            "client@example.com"
+        );
+        assert_eq!(body["isOnlineMeeting"], true);
+        let properties = body["singleValueExtendedProperties"].as_array().unwrap();
+        assert_eq!(properties.len(), 2);
+        assert_eq!(properties[1]["value"], "7500");
+    }
+
+    #[test]
+    fn test_iso_duration() {
+        assert_eq!(iso_duration(Duration::minutes(30)), "PT30M");
+        assert_eq!(iso_duration(Duration::minutes(60)), "PT1H");
+        assert_eq!(iso_duration(Duration::minutes(90)), "PT1H30M");
+    }
+}
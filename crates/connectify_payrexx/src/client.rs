@@ -0,0 +1,588 @@
+// --- File: crates/connectify_payrexx/src/client.rs ---
+//! A typed client for the Payrexx REST API.
+//!
+//! Payrexx expects request parameters form encoded, with nested objects and lists in bracket
+//! notation, e.g. `fields[email][value]` or `pm[0]`, and signed: the `ApiSignature` parameter is
+//! the base64 encoded HMAC-SHA256 of the encoded parameters, keyed with the API secret.
+//! [`PayrexxClient`] serializes typed parameter structs into that format, signs and sends the
+//! request with retries on transport errors and 429/5xx responses, and maps error responses to
+//! the variants of [`PayrexxError`].
+
+use crate::error::PayrexxError;
+use crate::logic::PayrexxWebhookTransaction;
+use base64::{engine::general_purpose::STANDARD as base64_engine, Engine as _};
+use connectify_common::metrics::observe_external_call;
+use connectify_common::request_id::RequestIdExt;
+use connectify_common::retry::{send_non_idempotent_with_retry, send_with_retry, RetryPolicy};
+use connectify_common::webhook::hmac_sha256;
+use connectify_common::HTTP_CLIENT;
+use connectify_config::PayrexxConfig;
+use reqwest::{RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use tracing::{error, info};
+
+/// Base URL of the Payrexx REST API.
+const PAYREXX_API_BASE_URL: &str = "https://api.payrexx.com/v1.0";
+
+/// Parameters of a new gateway, the payment page of one payment.
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayParams {
+    /// Amount in the smallest currency unit
+    pub amount: i64,
+    pub currency: String,
+    pub purpose: String,
+    pub reference_id: String,
+    pub success_redirect_url: String,
+    pub failed_redirect_url: String,
+    pub cancel_redirect_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<ContactFields>,
+    /// Only authorize the amount, to capture it later
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub pre_authorization: bool,
+    /// Payment methods offered in this order (`pm`), all of the instance if empty
+    #[serde(rename = "pm", skip_serializing_if = "Vec::is_empty")]
+    pub payment_methods: Vec<String>,
+    /// IDs of the payment service providers to use, all if empty
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub psp: Vec<i64>,
+}
+
+/// Parameters of a new invoice (a Payrexx paylink), e.g. paid by bank transfer.
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceParams {
+    pub title: String,
+    pub description: String,
+    pub purpose: String,
+    /// Amount in the smallest currency unit
+    pub amount: i64,
+    pub currency: String,
+    pub reference_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<ContactFields>,
+    /// Payment methods offered (`pm`)
+    #[serde(rename = "pm", skip_serializing_if = "Vec::is_empty")]
+    pub payment_methods: Vec<String>,
+}
+
+/// Prefilled contact fields of a payment page.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ContactFields {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<FieldValue>,
+}
+
+/// The value of a contact field.
+#[derive(Serialize, Debug, Clone)]
+pub struct FieldValue {
+    pub value: String,
+}
+
+/// Parameters of a transaction listing, a page of transactions ordered by time.
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ListTransactionsParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// `ASC` or `DESC`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_by_time: Option<String>,
+    /// Only transactions after this time (UTC, `YYYY-MM-DD HH:MM:SS`)
+    #[serde(
+        rename = "filterDatetimeUtcGreaterThan",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub created_after: Option<String>,
+    /// Only transactions before this time (UTC, `YYYY-MM-DD HH:MM:SS`)
+    #[serde(
+        rename = "filterDatetimeUtcLessThan",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub created_before: Option<String>,
+}
+
+/// Parameters capturing or refunding a transaction.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct AmountParams {
+    /// Amount in the smallest currency unit; the whole amount if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<i64>,
+}
+
+/// Envelope of all Payrexx API responses.
+#[derive(Deserialize, Debug)]
+struct ApiResponse<T> {
+    status: String,
+    #[serde(default = "Vec::new")]
+    data: Vec<T>,
+    message: Option<String>,
+}
+
+/// A newly created gateway.
+#[derive(Deserialize, Debug)]
+pub struct CreatedGateway {
+    pub id: Option<i64>,
+    /// The payment page
+    pub link: String,
+}
+
+/// A gateway as returned by `GET /Gateway/{id}/`.
+#[derive(Deserialize, Debug)]
+pub struct Gateway {
+    pub id: i64,
+    /// `waiting` until paid, then `confirmed`, `authorized` or `reserved`
+    pub status: String,
+    #[serde(rename = "referenceId")]
+    pub reference_id: Option<String>,
+    pub amount: Option<i64>,
+    pub currency: Option<String>,
+    pub link: Option<String>,
+    #[serde(default)]
+    pub invoices: Vec<GatewayInvoice>,
+}
+
+/// An invoice of a gateway, with the transactions paying it.
+#[derive(Deserialize, Debug)]
+pub struct GatewayInvoice {
+    #[serde(default)]
+    pub transactions: Vec<GatewayTransaction>,
+}
+
+/// A transaction paying a gateway.
+#[derive(Deserialize, Debug)]
+pub struct GatewayTransaction {
+    pub id: Option<i64>,
+}
+
+/// An invoice as returned by `POST /Invoice/`.
+#[derive(Deserialize, Debug)]
+pub struct Invoice {
+    pub id: i64,
+    pub link: Option<String>,
+}
+
+/// Client for the Payrexx REST API of one instance.
+#[derive(Clone)]
+pub struct PayrexxClient {
+    instance: String,
+    api_secret: String,
+    base_url: String,
+    retry_policy: RetryPolicy,
+}
+
+impl PayrexxClient {
+    /// Create a client for an instance, signing its requests with the given API secret.
+    pub fn new(instance: impl Into<String>, api_secret: impl Into<String>) -> Self {
+        Self {
+            instance: instance.into(),
+            api_secret: api_secret.into(),
+            base_url: PAYREXX_API_BASE_URL.to_string(),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Create a client for the configured instance, with the API secret in the
+    /// `PAYREXX_API_SECRET` environment variable.
+    pub fn from_config(config: &PayrexxConfig) -> Result<Self, PayrexxError> {
+        env::var("PAYREXX_API_SECRET")
+            .map(|api_secret| Self::new(&config.instance_name, api_secret))
+            .map_err(|_| PayrexxError::ConfigError)
+    }
+
+    /// Send requests to another base URL, e.g. a mock server.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    /// The signature of encoded parameters.
+    fn sign(&self, encoded_params: &str) -> String {
+        base64_engine.encode(hmac_sha256(
+            self.api_secret.as_bytes(),
+            encoded_params.as_bytes(),
+        ))
+    }
+
+    /// The form fields of the parameters with their `ApiSignature` appended.
+    fn signed_form<P: Serialize>(&self, params: &P) -> Result<Vec<(String, String)>, PayrexxError> {
+        let mut form = to_form(params)?;
+        let encoded = serde_urlencoded::to_string(&form).map_err(|e| {
+            PayrexxError::EncodingError(format!("Failed to urlencode params for signature: {}", e))
+        })?;
+        form.push(("ApiSignature".to_string(), self.sign(&encoded)));
+        Ok(form)
+    }
+
+    /// The form encoded parameters with their `ApiSignature` appended.
+    fn signed_body<P: Serialize>(&self, params: &P) -> Result<String, PayrexxError> {
+        serde_urlencoded::to_string(self.signed_form(params)?).map_err(|e| {
+            PayrexxError::EncodingError(format!("Failed to urlencode final params: {}", e))
+        })
+    }
+
+    /// Retrieve an object by its ID, e.g. a `Gateway`.
+    ///
+    /// GET requests have no parameters to sign, so the signature is the HMAC of an empty string.
+    pub async fn get<R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        id: i64,
+    ) -> Result<R, PayrexxError> {
+        let query = [
+            ("instance", self.instance.clone()),
+            ("ApiSignature", self.sign("")),
+        ];
+        let request = HTTP_CLIENT
+            .get(self.url(&format!("{}/{}/", endpoint, id)))
+            .query(&query);
+        self.send(endpoint, "get", request, true).await
+    }
+
+    /// List the objects of an endpoint, e.g. `Transaction`, with signed query parameters.
+    pub async fn list<P: Serialize, R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        params: &P,
+    ) -> Result<Vec<R>, PayrexxError> {
+        let mut query = self.signed_form(params)?;
+        query.push(("instance".to_string(), self.instance.clone()));
+        let request = HTTP_CLIENT
+            .get(self.url(&format!("{}/", endpoint)))
+            .query(&query);
+        let (status, body_text) = self.send_raw(endpoint, "list", request, true).await?;
+        api_data(endpoint, status, &body_text)
+    }
+
+    /// Post signed parameters to a path, e.g. `Transaction/{id}/capture/`, and return the
+    /// `endpoint` object of the response.
+    ///
+    /// Payrexx has no idempotency keys, so a POST is only retried if it surely wasn't
+    /// processed, see [`send_non_idempotent_with_retry`].
+    pub async fn post<P: Serialize, R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        path: &str,
+        params: &P,
+    ) -> Result<R, PayrexxError> {
+        let request = HTTP_CLIENT
+            .post(self.url(path))
+            .query(&[("instance", &self.instance)])
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .body(self.signed_body(params)?);
+        self.send(endpoint, path, request, false).await
+    }
+
+    /// Create a gateway, the payment page of one payment.
+    pub async fn create_gateway(
+        &self,
+        params: &GatewayParams,
+    ) -> Result<CreatedGateway, PayrexxError> {
+        self.post("Gateway", "Gateway/", params).await
+    }
+
+    /// Retrieve a gateway.
+    pub async fn get_gateway(&self, gateway_id: i64) -> Result<Gateway, PayrexxError> {
+        self.get("Gateway", gateway_id).await
+    }
+
+    /// Retrieve a transaction.
+    pub async fn get_transaction(
+        &self,
+        transaction_id: i64,
+    ) -> Result<PayrexxWebhookTransaction, PayrexxError> {
+        self.get("Transaction", transaction_id).await
+    }
+
+    /// Capture an authorized transaction, or a part of it.
+    pub async fn capture_transaction(
+        &self,
+        transaction_id: i64,
+        params: &AmountParams,
+    ) -> Result<PayrexxWebhookTransaction, PayrexxError> {
+        self.post(
+            "Transaction",
+            &format!("Transaction/{}/capture/", transaction_id),
+            params,
+        )
+        .await
+    }
+
+    /// Refund a confirmed transaction, or a part of it.
+    pub async fn refund_transaction(
+        &self,
+        transaction_id: i64,
+        params: &AmountParams,
+    ) -> Result<PayrexxWebhookTransaction, PayrexxError> {
+        self.post(
+            "Transaction",
+            &format!("Transaction/{}/refund/", transaction_id),
+            params,
+        )
+        .await
+    }
+
+    /// List a page of transactions.
+    pub async fn list_transactions(
+        &self,
+        params: &ListTransactionsParams,
+    ) -> Result<Vec<PayrexxWebhookTransaction>, PayrexxError> {
+        self.list("Transaction", params).await
+    }
+
+    /// Create an invoice.
+    pub async fn create_invoice(&self, params: &InvoiceParams) -> Result<Invoice, PayrexxError> {
+        self.post("Invoice", "Invoice/", params).await
+    }
+
+    async fn send<R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        operation: &str,
+        request: RequestBuilder,
+        idempotent: bool,
+    ) -> Result<R, PayrexxError> {
+        let (status, body_text) = self
+            .send_raw(endpoint, operation, request, idempotent)
+            .await?;
+        api_object(endpoint, status, &body_text)
+    }
+
+    /// Sends a request, retrying it on transient errors. Requests that aren't `idempotent`
+    /// are only retried if Payrexx surely didn't process them.
+    async fn send_raw(
+        &self,
+        endpoint: &str,
+        operation: &str,
+        request: RequestBuilder,
+        idempotent: bool,
+    ) -> Result<(StatusCode, String), PayrexxError> {
+        let request = request.with_request_id();
+        let response = if idempotent {
+            observe_external_call(
+                "payrexx",
+                operation,
+                send_with_retry(&self.retry_policy, request),
+            )
+            .await?
+        } else {
+            observe_external_call(
+                "payrexx",
+                operation,
+                send_non_idempotent_with_retry(&self.retry_policy, request),
+            )
+            .await?
+        };
+        let status = response.status();
+        let body_text = response.text().await?;
+        info!(
+            "[Payrexx Client] {} {} status: {}",
+            operation, endpoint, status
+        );
+        Ok((status, body_text))
+    }
+}
+
+/// The single object of a Payrexx API response.
+pub(crate) fn api_object<T: DeserializeOwned>(
+    endpoint: &str,
+    status: StatusCode,
+    body_text: &str,
+) -> Result<T, PayrexxError> {
+    api_data(endpoint, status, body_text)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| PayrexxError::NotFound(endpoint.to_lowercase()))
+}
+
+/// All objects of a Payrexx API response.
+pub(crate) fn api_data<T: DeserializeOwned>(
+    endpoint: &str,
+    status: StatusCode,
+    body_text: &str,
+) -> Result<Vec<T>, PayrexxError> {
+    if !status.is_success() {
+        let message = serde_json::from_str::<ApiResponse<Value>>(body_text)
+            .ok()
+            .and_then(|response| response.message)
+            .unwrap_or_else(|| body_text.to_string());
+        error!(
+            "[Payrexx Client] {} request failed with HTTP status {}: {}",
+            endpoint, status, message
+        );
+        return Err(PayrexxError::from_response(status, endpoint, message));
+    }
+    let response: ApiResponse<T> = serde_json::from_str(body_text)?;
+    if response.status != "success" {
+        return Err(PayrexxError::InvalidRequest(
+            response
+                .message
+                .unwrap_or_else(|| "Unknown Payrexx API error".to_string()),
+        ));
+    }
+    Ok(response.data)
+}
+
+/// Serialize parameters into form fields in Payrexx's bracket notation.
+///
+/// Unset (`null`) values are left out, and booleans are sent as `1` or `0`.
+pub fn to_form<P: Serialize>(params: &P) -> Result<Vec<(String, String)>, PayrexxError> {
+    let mut fields = Vec::new();
+    match serde_json::to_value(params)? {
+        Value::Object(object) => {
+            for (key, value) in object {
+                flatten_field(key, value, &mut fields);
+            }
+        }
+        Value::Null => {}
+        other => {
+            return Err(PayrexxError::EncodingError(format!(
+                "Payrexx parameters must be an object, got {}",
+                other
+            )))
+        }
+    }
+    Ok(fields)
+}
+
+fn flatten_field(key: String, value: Value, fields: &mut Vec<(String, String)>) {
+    match value {
+        Value::Null => {}
+        Value::Object(object) => {
+            for (nested_key, nested_value) in object {
+                flatten_field(format!("{}[{}]", key, nested_key), nested_value, fields);
+            }
+        }
+        Value::Array(values) => {
+            for (index, nested_value) in values.into_iter().enumerate() {
+                flatten_field(format!("{}[{}]", key, index), nested_value, fields);
+            }
+        }
+        Value::String(value) => fields.push((key, value)),
+        Value::Bool(value) => fields.push((key, if value { "1" } else { "0" }.to_string())),
+        other => fields.push((key, other.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_to_form() {
+        let params = GatewayParams {
+            amount: 5000,
+            currency: "CHF".to_string(),
+            purpose: "Beratung".to_string(),
+            reference_id: "ord_1".to_string(),
+            fields: Some(ContactFields {
+                email: Some(FieldValue {
+                    value: "anna@example.com".to_string(),
+                }),
+            }),
+            pre_authorization: true,
+            payment_methods: vec!["twint".to_string(), "postfinance-card".to_string()],
+            psp: vec![44],
+            ..Default::default()
+        };
+        let form = to_form(&params).unwrap();
+        let field = |key: &str| {
+            form.iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(field("amount"), Some("5000"));
+        assert_eq!(field("referenceId"), Some("ord_1"));
+        assert_eq!(field("fields[email][value]"), Some("anna@example.com"));
+        assert_eq!(field("preAuthorization"), Some("1"));
+        assert_eq!(field("pm[1]"), Some("postfinance-card"));
+        assert_eq!(field("psp[0]"), Some("44"));
+
+        // Defaults are left out
+        let form = to_form(&GatewayParams::default()).unwrap();
+        assert!(!form.iter().any(|(name, _)| name == "preAuthorization"
+            || name.starts_with("pm")
+            || name.starts_with("fields")));
+        assert!(to_form(&AmountParams::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_signed_body() {
+        let client = PayrexxClient::new("demo", "secret");
+        let body = client
+            .signed_body(&AmountParams { amount: Some(2500) })
+            .unwrap();
+        let expected = format!(
+            "amount=2500&{}",
+            serde_urlencoded::to_string([("ApiSignature", client.sign("amount=2500"))]).unwrap()
+        );
+        assert_eq!(body, expected);
+    }
+
+    #[test]
+    fn test_api_object_errors() {
+        let not_found = json!({ "status": "error", "message": "No Gateway found" }).to_string();
+        assert!(matches!(
+            api_object::<Gateway>("Gateway", StatusCode::NOT_FOUND, &not_found),
+            Err(PayrexxError::NotFound(what)) if what == "gateway"
+        ));
+        assert!(matches!(
+            api_object::<Gateway>("Gateway", StatusCode::OK, &not_found),
+            Err(PayrexxError::InvalidRequest(message)) if message == "No Gateway found"
+        ));
+        assert!(matches!(
+            api_object::<Gateway>("Gateway", StatusCode::FORBIDDEN, &not_found),
+            Err(PayrexxError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            api_object::<Gateway>("Gateway", StatusCode::BAD_GATEWAY, "upstream down"),
+            Err(PayrexxError::ServiceUnavailable(_))
+        ));
+        let empty = json!({ "status": "success", "data": [] }).to_string();
+        assert!(matches!(
+            api_object::<Gateway>("Gateway", StatusCode::OK, &empty),
+            Err(PayrexxError::NotFound(_))
+        ));
+        assert!(api_data::<Gateway>("Gateway", StatusCode::OK, &empty)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_list_transactions_params() {
+        let params = ListTransactionsParams {
+            offset: Some(20),
+            limit: Some(10),
+            order_by_time: Some("DESC".to_string()),
+            created_after: Some("2024-05-01 00:00:00".to_string()),
+            ..Default::default()
+        };
+        let form = to_form(&params).unwrap();
+        assert_eq!(
+            form,
+            vec![
+                (
+                    "filterDatetimeUtcGreaterThan".to_string(),
+                    "2024-05-01 00:00:00".to_string()
+                ),
+                ("limit".to_string(), "10".to_string()),
+                ("offset".to_string(), "20".to_string()),
+                ("orderByTime".to_string(), "DESC".to_string()),
+            ]
+        );
+    }
+}
@@ -3,10 +3,15 @@
 #![cfg(feature = "openapi")]
 use crate::handlers::RedirectQuery;
 use crate::logic::{
-    CreateGatewayRequest, CreateGatewayResponse, PayrexxWebhookContact, PayrexxWebhookCustomField,
-    PayrexxWebhookInstance, PayrexxWebhookInvoice, PayrexxWebhookInvoiceProduct,
-    PayrexxWebhookPayload, PayrexxWebhookPayment, PayrexxWebhookTransaction,
+    AdminPayrexxTransaction, CaptureTransactionRequest, CreateGatewayRequest,
+    CreateGatewayResponse, CreateQrInvoiceRequest, ListTransactionsAdminQuery,
+    ListTransactionsAdminResponse, PayrexxGatewayStatus, PayrexxTransactionStatus,
+    PayrexxWebhookContact, PayrexxWebhookCustomField, PayrexxWebhookInstance,
+    PayrexxWebhookInvoice, PayrexxWebhookInvoiceProduct, PayrexxWebhookPayload,
+    PayrexxWebhookPayment, PayrexxWebhookTransaction, QrInvoiceResponse, RefundTransactionRequest,
+    TransactionBooking,
 };
+use connectify_common::models::{CursorQuery, SortOrder};
 use utoipa::OpenApi; // Import schemas
 
 // Define a dummy function with the handler's attributes for utoipa
@@ -16,13 +21,107 @@ use utoipa::OpenApi; // Import schemas
     request_body = CreateGatewayRequest,
     responses(
         (status = 200, description = "Gateway created successfully", body = CreateGatewayResponse),
-        (status = 400, description = "Bad request (e.g., invalid input)"),
+        (status = 400, description = "Bad request (e.g., invalid input or no price tier matching the booked duration)"),
         (status = 500, description = "Internal server error or Payrexx API error")
     ),
     tag = "Payrexx" // Group under Payrexx tag
 )]
 fn doc_create_gateway_handler() {}
 
+#[utoipa::path(
+    post,
+    path = "/payrexx/invoices", // Path relative to /api
+    request_body(content = CreateQrInvoiceRequest, example = json!({
+        "amount_override": 7500,
+        "purpose_override": "Premium Beratung (60 Min)",
+        "user_email": "customer@example.com",
+        "fulfillment_type": "gcal_booking",
+        "fulfillment_data": {
+            "start_time": "2025-05-15T10:00:00Z",
+            "end_time": "2025-05-15T11:00:00Z",
+            "summary": "Premium Beratung"
+        }
+    })),
+    responses(
+        (status = 200, description = "Invoice created, its link shows the QR-bill payment part", body = QrInvoiceResponse),
+        (status = 400, description = "fulfillment_data is not a JSON object, or no price tier matches the booked duration"),
+        (status = 502, description = "Payrexx API error")
+    ),
+    tag = "Payrexx"
+)]
+fn doc_create_qr_invoice_handler() {}
+
+#[utoipa::path(
+    get,
+    path = "/payrexx/gateways/{gateway_id}", // Path relative to /api
+    params(("gateway_id" = i64, Path, description = "The Payrexx gateway id", example = 12345)),
+    responses(
+        (status = 200, description = "Status of the gateway, `confirmed` once paid", body = PayrexxGatewayStatus),
+        (status = 404, description = "Gateway not found"),
+        (status = 502, description = "Payrexx API error")
+    ),
+    tag = "Payrexx"
+)]
+fn doc_get_gateway_handler() {}
+
+#[utoipa::path(
+    get,
+    path = "/payrexx/transactions/{transaction_id}", // Path relative to /api
+    params(("transaction_id" = i64, Path, description = "The Payrexx transaction id", example = 67890)),
+    responses(
+        (status = 200, description = "Status of the transaction", body = PayrexxTransactionStatus),
+        (status = 404, description = "Transaction not found"),
+        (status = 502, description = "Payrexx API error")
+    ),
+    tag = "Payrexx"
+)]
+fn doc_get_transaction_handler() {}
+
+#[utoipa::path(
+    post,
+    path = "/payrexx/transactions/{transaction_id}/capture", // Path relative to /api
+    params(("transaction_id" = i64, Path, description = "The Payrexx transaction id", example = 67890)),
+    request_body(content = CaptureTransactionRequest, example = json!({ "amount": 5000 })),
+    responses(
+        (status = 200, description = "Transaction captured", body = PayrexxTransactionStatus),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Transaction not found"),
+        (status = 502, description = "Payrexx API error, e.g. the transaction is not authorized")
+    ),
+    tag = "Payrexx"
+)]
+fn doc_capture_transaction_handler() {}
+
+#[utoipa::path(
+    post,
+    path = "/payrexx/transactions/{transaction_id}/refund", // Path relative to /api
+    params(("transaction_id" = i64, Path, description = "The Payrexx transaction id", example = 67890)),
+    request_body(content = RefundTransactionRequest, example = json!({ "amount": 2500 })),
+    responses(
+        (status = 200, description = "Transaction refunded", body = PayrexxTransactionStatus),
+        (status = 400, description = "Payrexx rejected the refund, e.g. the transaction is not refundable"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Transaction not found"),
+        (status = 502, description = "Payrexx API error")
+    ),
+    tag = "Payrexx"
+)]
+fn doc_refund_transaction_handler() {}
+
+#[utoipa::path(
+    get,
+    path = "/admin/payrexx/transactions", // Path relative to /api
+    params(ListTransactionsAdminQuery, CursorQuery),
+    responses(
+        (status = 200, description = "A page of transactions with their bookings", body = ListTransactionsAdminResponse),
+        (status = 400, description = "Invalid filter, e.g. a malformed date or cursor"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 502, description = "Payrexx API error")
+    ),
+    tag = "Payrexx"
+)]
+fn doc_admin_list_transactions_handler() {}
+
 // --- Doc function for Webhook ---
 #[utoipa::path(
     post,
@@ -114,6 +213,12 @@ fn doc_payrexx_cancel_handler() {}
 #[openapi(
     paths(
         doc_create_gateway_handler,
+        doc_create_qr_invoice_handler,
+        doc_get_gateway_handler,
+        doc_get_transaction_handler,
+        doc_capture_transaction_handler,
+        doc_refund_transaction_handler,
+        doc_admin_list_transactions_handler,
         doc_payrexx_webhook_handler,
         doc_payrexx_success_handler,
         doc_payrexx_failure_handler,
@@ -122,6 +227,11 @@ fn doc_payrexx_cancel_handler() {}
     components(
         schemas(
             CreateGatewayRequest, CreateGatewayResponse,
+            CreateQrInvoiceRequest, QrInvoiceResponse,
+            PayrexxGatewayStatus, PayrexxTransactionStatus, CaptureTransactionRequest,
+            RefundTransactionRequest,
+            ListTransactionsAdminQuery, ListTransactionsAdminResponse,
+            AdminPayrexxTransaction, TransactionBooking, SortOrder,
             PayrexxWebhookPayload,
             RedirectQuery,
             PayrexxWebhookTransaction,
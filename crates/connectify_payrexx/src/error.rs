@@ -0,0 +1,186 @@
+// --- File: crates/connectify_payrexx/src/error.rs ---
+use connectify_common::http::circuit_breaker::HttpClientError;
+use connectify_common::{external_service_error, ConnectifyError};
+use thiserror::Error;
+
+/// Payrexx-specific error types.
+#[derive(Error, Debug)]
+pub enum PayrexxError {
+    /// Error occurred during a Payrexx API request
+    #[error("Payrexx API request failed: {0}")]
+    RequestError(#[from] reqwest::Error),
+
+    /// Payrexx API is unavailable (circuit breaker open, or still failing after retries)
+    #[error("Payrexx API unavailable: {0}")]
+    ServiceUnavailable(String),
+
+    /// Payrexx rejected the API signature or the instance, e.g. a wrong API secret
+    #[error("Payrexx rejected the credentials: {0}")]
+    Unauthorized(String),
+
+    /// Payrexx rejected the request parameters
+    #[error("Payrexx rejected the request: {0}")]
+    InvalidRequest(String),
+
+    /// Too many requests to the Payrexx API
+    #[error("Payrexx API rate limit exceeded: {0}")]
+    RateLimited(String),
+
+    /// Any other error returned by the Payrexx API
+    #[error("Payrexx API returned an error: {message} (Status: {status_code})")]
+    ApiError { status_code: u16, message: String },
+
+    /// Error parsing Payrexx API response
+    #[error("Failed to parse Payrexx API response: {0}")]
+    ParseError(#[from] serde_json::Error),
+
+    /// Missing or incomplete Payrexx configuration
+    #[error("Payrexx configuration missing or incomplete")]
+    ConfigError,
+
+    /// The requested object does not exist, e.g. "gateway"
+    #[error("Payrexx {0} not found")]
+    NotFound(String),
+
+    /// A filter of the transaction listing is invalid, e.g. a malformed date
+    #[error("Invalid transaction filter: {0}")]
+    InvalidTransactionFilter(String),
+
+    /// The payment cannot be priced, e.g. no price tier matches the booked duration
+    #[error("Cannot price the payment: {0}")]
+    PricingError(String),
+
+    /// Webhook signature verification failed
+    #[error("Webhook signature verification failed")]
+    WebhookSignatureError,
+
+    /// Webhook processing error
+    #[error("Webhook processing error: {0}")]
+    WebhookProcessingError(String),
+
+    /// Error encoding the request parameters
+    #[error("Failed to encode request body: {0}")]
+    EncodingError(String),
+
+    /// Internal processing error
+    #[error("Internal processing error: {0}")]
+    InternalError(String),
+}
+
+impl PayrexxError {
+    /// The error of a failed Payrexx API response, by its HTTP status.
+    pub fn from_response(status: reqwest::StatusCode, what: &str, message: String) -> Self {
+        match status.as_u16() {
+            401 | 403 => PayrexxError::Unauthorized(message),
+            404 => PayrexxError::NotFound(what.to_lowercase()),
+            400 | 422 => PayrexxError::InvalidRequest(message),
+            429 => PayrexxError::RateLimited(message),
+            500..=599 => PayrexxError::ServiceUnavailable(format!("{} - {}", status, message)),
+            status_code => PayrexxError::ApiError {
+                status_code,
+                message,
+            },
+        }
+    }
+}
+
+/// Convert errors from requests sent through the circuit breaker
+impl From<HttpClientError> for PayrexxError {
+    fn from(err: HttpClientError) -> Self {
+        match err {
+            HttpClientError::CircuitOpen { host } => PayrexxError::ServiceUnavailable(format!(
+                "Circuit open for {}, not sending request",
+                host
+            )),
+            HttpClientError::Request(e) => PayrexxError::RequestError(e),
+        }
+    }
+}
+
+/// Convert PayrexxError to ConnectifyError
+impl From<PayrexxError> for ConnectifyError {
+    fn from(err: PayrexxError) -> Self {
+        match err {
+            PayrexxError::RequestError(e) => {
+                ConnectifyError::HttpError(format!("Payrexx request error: {}", e))
+            }
+            PayrexxError::ServiceUnavailable(msg) => external_service_error("Payrexx API", msg),
+            // Our credentials are wrong, not the caller's
+            PayrexxError::Unauthorized(msg) => {
+                ConnectifyError::ConfigError(format!("Payrexx rejected the credentials: {}", msg))
+            }
+            PayrexxError::InvalidRequest(msg) => {
+                ConnectifyError::ValidationError(format!("Payrexx rejected the request: {}", msg))
+            }
+            PayrexxError::RateLimited(msg) => {
+                ConnectifyError::RateLimitError(format!("Payrexx API rate limit: {}", msg))
+            }
+            PayrexxError::ApiError {
+                status_code,
+                message,
+            } => external_service_error(
+                "Payrexx API",
+                format!("Status: {}, Message: {}", status_code, message),
+            ),
+            PayrexxError::ParseError(e) => {
+                ConnectifyError::ParseError(format!("Payrexx response parse error: {}", e))
+            }
+            PayrexxError::ConfigError => ConnectifyError::ConfigError(
+                "Payrexx configuration missing or incomplete".to_string(),
+            ),
+            PayrexxError::NotFound(what) => {
+                ConnectifyError::NotFoundError(format!("Payrexx {} not found", what))
+            }
+            PayrexxError::PricingError(msg) | PayrexxError::InvalidTransactionFilter(msg) => {
+                ConnectifyError::ValidationError(msg)
+            }
+            PayrexxError::WebhookSignatureError => ConnectifyError::AuthError(
+                "Payrexx webhook signature verification failed".to_string(),
+            ),
+            PayrexxError::WebhookProcessingError(msg) => {
+                external_service_error("Payrexx webhook", msg)
+            }
+            PayrexxError::EncodingError(msg) | PayrexxError::InternalError(msg) => {
+                ConnectifyError::InternalError(format!("Payrexx internal error: {}", msg))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_from_response() {
+        let error = |status| PayrexxError::from_response(status, "Gateway", "msg".to_string());
+        assert!(matches!(
+            error(StatusCode::UNAUTHORIZED),
+            PayrexxError::Unauthorized(_)
+        ));
+        assert!(matches!(
+            error(StatusCode::NOT_FOUND),
+            PayrexxError::NotFound(what) if what == "gateway"
+        ));
+        assert!(matches!(
+            error(StatusCode::UNPROCESSABLE_ENTITY),
+            PayrexxError::InvalidRequest(_)
+        ));
+        assert!(matches!(
+            error(StatusCode::TOO_MANY_REQUESTS),
+            PayrexxError::RateLimited(_)
+        ));
+        assert!(matches!(
+            error(StatusCode::BAD_GATEWAY),
+            PayrexxError::ServiceUnavailable(_)
+        ));
+        assert!(matches!(
+            error(StatusCode::IM_A_TEAPOT),
+            PayrexxError::ApiError {
+                status_code: 418,
+                ..
+            }
+        ));
+    }
+}
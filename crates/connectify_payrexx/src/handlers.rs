@@ -1,20 +1,45 @@
 // --- File: crates/connectify_payrexx/src/handlers.rs ---
 use axum::{
     body::Bytes,
-    extract::{Query, State}, // Added Query
+    extract::{Path, Query, State}, // Added Query
     http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse, Json, Response}, // Added Html, Response
 };
+use chrono::Utc;
+use connectify_common::audit::{self, AuditActor, AuditEvent};
+use connectify_common::lock::{distributed_lock, release_quietly};
+use connectify_common::models::CursorQuery;
+use connectify_common::webhook::WebhookVerifier;
+use connectify_common::webhook_events::{
+    webhook_event_store, WebhookEventRecord, WebhookEventStatus,
+};
+use connectify_common::ConnectifyError;
 use connectify_config::AppConfig;
 use std::sync::Arc;
-use tracing::info; // Use the unified config from the config crate
-                   // Import logic functions and types
+use tracing::{error, info, warn}; // Use the unified config from the config crate
+                                  // Import logic functions and types
 use crate::logic::{
+    capture_transaction,
     create_gateway_request,
+    create_qr_invoice,
+    get_gateway_status,
+    get_transaction_status,
+    list_transactions_admin,
+    refund_transaction,
+    CaptureTransactionRequest,
     CreateGatewayRequest,
     CreateGatewayResponse,
+    CreateQrInvoiceRequest,
+    ListTransactionsAdminQuery,
+    ListTransactionsAdminResponse,
     PayrexxError,
+    PayrexxGatewayStatus,
+    PayrexxTransactionStatus,
+    PayrexxWebhookVerifier,
+    QrInvoiceResponse,
+    RefundTransactionRequest,
     // PayrexxWebhookPayload, verify_payrexx_signature, process_webhook
+    PAYREXX_PROVIDER,
 };
 // Import serde::Deserialize for query params
 use serde::Deserialize;
@@ -22,6 +47,9 @@ use serde::Deserialize;
 #[cfg(feature = "openapi")]
 use utoipa::ToSchema;
 
+/// How long processing a webhook may hold its lock.
+const WEBHOOK_LOCK_TTL: std::time::Duration = std::time::Duration::from_secs(60);
+
 // --- State ---
 // Contains only the AppConfig Arc, as the HTTP client is static in logic.rs
 #[derive(Clone)]
@@ -39,7 +67,7 @@ pub struct PayrexxState {
     request_body = CreateGatewayRequest,
     responses(
         (status = 200, description = "Gateway created successfully", body = CreateGatewayResponse),
-        (status = 400, description = "Bad request (e.g., invalid input)"),
+        (status = 400, description = "Bad request (e.g., invalid input or no price tier matching the booked duration)"),
         (status = 500, description = "Internal server error or Payrexx API error")
     ),
     tag = "Payrexx"
@@ -60,7 +88,8 @@ pub async fn create_gateway_handler(
     if let Some(payrexx_config) = state.config.payrexx.as_ref() {
         // Call the logic function from logic.rs
         // It uses its own static client now
-        match create_gateway_request(payrexx_config, payload).await {
+        let pricing_config = state.config.pricing.clone().unwrap_or_default();
+        match create_gateway_request(payrexx_config, &pricing_config, payload).await {
             Ok(response) => Ok(Json(response)),
             Err(PayrexxError::ConfigError) => {
                 // Log potentially sensitive config errors internally only
@@ -77,6 +106,13 @@ pub async fn create_gateway_handler(
                     "Failed to communicate with payment provider.".to_string(),
                 ))
             }
+            Err(PayrexxError::ServiceUnavailable(msg)) => {
+                info!("Payrexx unavailable: {}", msg);
+                Err((
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Payment provider temporarily unavailable.".to_string(),
+                ))
+            }
             Err(PayrexxError::ParseError(e)) => {
                 info!("Payrexx Parse Error: {}", e);
                 Err((
@@ -84,9 +120,25 @@ pub async fn create_gateway_handler(
                     "Failed to understand payment provider response.".to_string(),
                 ))
             }
-            Err(PayrexxError::ApiError { status, message }) => {
+            Err(PayrexxError::Unauthorized(msg)) => {
+                // Log potentially sensitive credential errors internally only
+                info!("Payrexx rejected the credentials: {}", msg);
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Server configuration error.".to_string(),
+                ))
+            }
+            Err(PayrexxError::RateLimited(msg)) => {
+                info!("Payrexx rate limit exceeded: {}", msg);
+                Err((
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Payment provider temporarily unavailable.".to_string(),
+                ))
+            }
+            Err(PayrexxError::InvalidRequest(message))
+            | Err(PayrexxError::ApiError { message, .. }) => {
                 // Log the actual error from Payrexx
-                info!("Payrexx API Error ({}): {}", status, message);
+                info!("Payrexx API Error: {}", message);
                 // Return a generic error to the client
                 Err((
                     StatusCode::BAD_GATEWAY,
@@ -100,13 +152,19 @@ pub async fn create_gateway_handler(
                     "Failed to prepare payment request.".to_string(),
                 ))
             }
+            Err(PayrexxError::PricingError(msg)) => {
+                info!("Payrexx gateway cannot be priced: {}", msg);
+                Err((StatusCode::BAD_REQUEST, msg))
+            }
             Err(PayrexxError::InternalError(msg)) => {
                 info!("Payrexx Internal Logic Error: {}", msg);
                 Err((StatusCode::INTERNAL_SERVER_ERROR, msg)) // Or a more generic message
             }
-            // Webhook errors cannot originate from create_gateway_request
+            // Webhook, lookup and listing errors cannot originate from create_gateway_request
             Err(PayrexxError::WebhookSignatureError)
-            | Err(PayrexxError::WebhookProcessingError(_)) => {
+            | Err(PayrexxError::WebhookProcessingError(_))
+            | Err(PayrexxError::NotFound(_))
+            | Err(PayrexxError::InvalidTransactionFilter(_)) => {
                 // This case should be unreachable
                 info!("Unexpected webhook error during gateway creation!");
                 Err((
@@ -124,6 +182,222 @@ pub async fn create_gateway_handler(
     }
 }
 
+/// The Payrexx config, if Payrexx is enabled.
+fn payrexx_config(
+    state: &PayrexxState,
+) -> Result<&connectify_config::PayrexxConfig, ConnectifyError> {
+    state
+        .config
+        .payrexx
+        .as_ref()
+        .filter(|_| state.config.use_payrexx)
+        .ok_or_else(|| {
+            ConnectifyError::ConfigError("Payrexx service not configured or disabled".to_string())
+        })
+}
+
+/// Axum handler to create an invoice for customers paying by bank transfer, with a Swiss
+/// QR-bill payment part.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/payrexx/invoices", // Path relative to /api
+    request_body = CreateQrInvoiceRequest,
+    responses(
+        (status = 200, description = "Invoice created", body = QrInvoiceResponse),
+        (status = 400, description = "fulfillment_data is not a JSON object, or no price tier matches the booked duration"),
+        (status = 502, description = "Payrexx API error")
+    ),
+    tag = "Payrexx"
+))]
+pub async fn create_qr_invoice_handler(
+    State(state): State<Arc<PayrexxState>>,
+    Json(payload): Json<CreateQrInvoiceRequest>,
+) -> Result<Json<QrInvoiceResponse>, ConnectifyError> {
+    let payrexx_config = payrexx_config(&state)?;
+    if payload
+        .fulfillment_data
+        .as_ref()
+        .is_some_and(|data| !data.is_object())
+    {
+        return Err(ConnectifyError::ValidationError(
+            "fulfillment_data must be a JSON object".to_string(),
+        ));
+    }
+    let pricing_config = state.config.pricing.clone().unwrap_or_default();
+    Ok(Json(
+        create_qr_invoice(payrexx_config, &pricing_config, payload).await?,
+    ))
+}
+
+/// Axum handler to look up the status of a gateway, so the frontend can poll whether it was
+/// paid instead of waiting for the webhook.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/payrexx/gateways/{gateway_id}", // Path relative to /api
+    params(("gateway_id" = i64, Path, description = "The Payrexx gateway id")),
+    responses(
+        (status = 200, description = "Status of the gateway", body = PayrexxGatewayStatus),
+        (status = 404, description = "Gateway not found"),
+        (status = 502, description = "Payrexx API error")
+    ),
+    tag = "Payrexx"
+))]
+pub async fn get_gateway_handler(
+    State(state): State<Arc<PayrexxState>>,
+    Path(gateway_id): Path<i64>,
+) -> Result<Json<PayrexxGatewayStatus>, ConnectifyError> {
+    let payrexx_config = payrexx_config(&state)?;
+    Ok(Json(get_gateway_status(payrexx_config, gateway_id).await?))
+}
+
+/// Axum handler to look up the status of a transaction, e.g. for reconciliation jobs.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/payrexx/transactions/{transaction_id}", // Path relative to /api
+    params(("transaction_id" = i64, Path, description = "The Payrexx transaction id")),
+    responses(
+        (status = 200, description = "Status of the transaction", body = PayrexxTransactionStatus),
+        (status = 404, description = "Transaction not found"),
+        (status = 502, description = "Payrexx API error")
+    ),
+    tag = "Payrexx"
+))]
+pub async fn get_transaction_handler(
+    State(state): State<Arc<PayrexxState>>,
+    Path(transaction_id): Path<i64>,
+) -> Result<Json<PayrexxTransactionStatus>, ConnectifyError> {
+    let payrexx_config = payrexx_config(&state)?;
+    Ok(Json(
+        get_transaction_status(payrexx_config, transaction_id).await?,
+    ))
+}
+
+/// Admin handler to capture a transaction authorized by a `pre_authorization` gateway, e.g. once
+/// the booked session took place.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/payrexx/transactions/{transaction_id}/capture", // Path relative to /api
+    params(("transaction_id" = i64, Path, description = "The Payrexx transaction id")),
+    request_body = CaptureTransactionRequest,
+    responses(
+        (status = 200, description = "Transaction captured", body = PayrexxTransactionStatus),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Transaction not found"),
+        (status = 502, description = "Payrexx API error, e.g. the transaction is not authorized")
+    ),
+    tag = "Payrexx"
+))]
+pub async fn capture_transaction_handler(
+    State(state): State<Arc<PayrexxState>>,
+    actor: AuditActor,
+    Path(transaction_id): Path<i64>,
+    Json(payload): Json<CaptureTransactionRequest>,
+) -> Result<Json<PayrexxTransactionStatus>, ConnectifyError> {
+    info!(
+        "[ADMIN] Request to capture Payrexx transaction {}",
+        transaction_id
+    );
+    let payrexx_config = payrexx_config(&state)?;
+
+    let result = capture_transaction(payrexx_config, transaction_id, &payload).await;
+    audit::record(
+        AuditEvent::new(
+            actor,
+            "payment.capture",
+            format!("payrexx_transaction:{}", transaction_id),
+        )
+        .with_metadata("amount", payload.amount)
+        .with_result(&result),
+    )
+    .await;
+    Ok(Json(result?))
+}
+
+/// Admin handler to refund a confirmed transaction, e.g. of a cancelled booking.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/payrexx/transactions/{transaction_id}/refund", // Path relative to /api
+    params(("transaction_id" = i64, Path, description = "The Payrexx transaction id")),
+    request_body = RefundTransactionRequest,
+    responses(
+        (status = 200, description = "Transaction refunded", body = PayrexxTransactionStatus),
+        (status = 400, description = "Payrexx rejected the refund, e.g. the transaction is not refundable"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Transaction not found"),
+        (status = 502, description = "Payrexx API error")
+    ),
+    tag = "Payrexx"
+))]
+pub async fn refund_transaction_handler(
+    State(state): State<Arc<PayrexxState>>,
+    actor: AuditActor,
+    Path(transaction_id): Path<i64>,
+    Json(payload): Json<RefundTransactionRequest>,
+) -> Result<Json<PayrexxTransactionStatus>, ConnectifyError> {
+    info!(
+        "[ADMIN] Request to refund Payrexx transaction {}",
+        transaction_id
+    );
+    let payrexx_config = payrexx_config(&state)?;
+
+    let result = refund_transaction(payrexx_config, transaction_id, &payload).await;
+    audit::record(
+        AuditEvent::new(
+            actor,
+            "payment.refund",
+            format!("payrexx_transaction:{}", transaction_id),
+        )
+        .with_metadata("amount", payload.amount)
+        .with_result(&result),
+    )
+    .await;
+    Ok(Json(result?))
+}
+
+/// Admin handler listing transactions with the bookings they pay for.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/admin/payrexx/transactions", // Path relative to /api
+    params(ListTransactionsAdminQuery, CursorQuery),
+    responses(
+        (status = 200, description = "A page of transactions with their bookings", body = ListTransactionsAdminResponse),
+        (status = 400, description = "Invalid filter"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 502, description = "Payrexx API error")
+    ),
+    tag = "Payrexx"
+))]
+pub async fn admin_list_transactions_handler(
+    State(state): State<Arc<PayrexxState>>,
+    actor: AuditActor,
+    Query(query): Query<ListTransactionsAdminQuery>,
+    Query(page): Query<CursorQuery>,
+) -> Result<Json<ListTransactionsAdminResponse>, ConnectifyError> {
+    info!("[ADMIN] Listing Payrexx transactions. Params: {:?}", query);
+    let payrexx_config = payrexx_config(&state)?;
+
+    let result = list_transactions_admin(payrexx_config, &query, &page).await;
+    audit::record(
+        AuditEvent::new(actor, "payment.list", "payrexx_transactions")
+            .with_metadata("query", serde_json::to_value(&query).unwrap_or_default())
+            .with_result(&result),
+    )
+    .await;
+    Ok(Json(result?))
+}
+
 /// Axum handler for incoming Payrexx webhooks (Server-to-Server).
 #[axum::debug_handler]
 #[cfg_attr(feature = "openapi", utoipa::path(
@@ -154,13 +428,9 @@ pub async fn payrexx_webhook_handler(
             return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
     };
-    // Adjust header name if needed based on Payrexx documentation
-    let signature_header = headers
-        .get("Webhook-Signature")
-        .and_then(|hv| hv.to_str().ok());
-
-    // Call the verification logic function
-    if let Err(e) = crate::logic::verify_payrexx_signature(&api_secret, &body, signature_header) {
+    // Verify the HMAC-SHA256 signature of the raw body
+    let verifier = PayrexxWebhookVerifier::new(api_secret);
+    if let Err(e) = verifier.verify(&body, &headers).map_err(PayrexxError::from) {
         info!("Webhook signature verification failed: {:?}", e);
         // Check if it was specifically a signature error
         if matches!(e, PayrexxError::WebhookSignatureError) {
@@ -190,8 +460,83 @@ pub async fn payrexx_webhook_handler(
         }
     };
 
+    let Some(event_id) = crate::logic::webhook_event_id(&payload) else {
+        // Nothing to store or to skip, e.g. a webhook without a transaction
+        return match crate::logic::process_webhook(payload, &state.config).await {
+            Ok(()) => StatusCode::OK.into_response(),
+            Err(e) => {
+                info!("Error processing Payrexx webhook: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        };
+    };
+
+    // Payrexx may deliver a webhook to several instances; only one of them processes it
+    let lock = distributed_lock();
+    let lease = match lock
+        .acquire(&format!("payrexx:event:{}", event_id), WEBHOOK_LOCK_TTL)
+        .await
+    {
+        Ok(Some(lease)) => lease,
+        Ok(None) => {
+            info!("Payrexx webhook {} is already being processed.", event_id);
+            return (
+                StatusCode::CONFLICT,
+                "Webhook is already being processed".to_string(),
+            )
+                .into_response();
+        }
+        Err(e) => return e.into_response(),
+    };
+
+    // Store the webhook before processing it, so re-deliveries of processed ones are skipped
+    let store = webhook_event_store();
+    let received = WebhookEventRecord::received(
+        PAYREXX_PROVIDER,
+        &event_id,
+        payload.event_type.as_deref().unwrap_or("Transaction"),
+        String::from_utf8_lossy(&body),
+        Utc::now(),
+    );
+    match store.record(received).await {
+        Ok(None) => {}
+        Ok(Some(stored)) if stored.status == WebhookEventStatus::Processed => {
+            release_quietly(&*lock, &lease).await;
+            warn!(
+                "Payrexx webhook {} was already processed, skipping duplicate.",
+                event_id
+            );
+            // Acknowledge it, so Payrexx stops re-delivering it
+            return StatusCode::OK.into_response();
+        }
+        Ok(Some(stored)) => info!(
+            "Payrexx webhook {} is delivered again after status '{}', processing it.",
+            event_id, stored.status
+        ),
+        Err(e) => {
+            release_quietly(&*lock, &lease).await;
+            error!("Failed to store Payrexx webhook {}: {}", event_id, e);
+            return e.into_response();
+        }
+    }
+
     // Call the processing logic
-    match crate::logic::process_webhook(payload.clone()).await {
+    let result = crate::logic::process_webhook(payload.clone(), &state.config).await;
+    let (status, error) = match &result {
+        Ok(()) => (WebhookEventStatus::Processed, None),
+        Err(e) => (WebhookEventStatus::Failed, Some(e.to_string())),
+    };
+    if let Err(e) = store
+        .finish(PAYREXX_PROVIDER, &event_id, status, error, Utc::now())
+        .await
+    {
+        error!(
+            "Failed to record status '{}' of Payrexx webhook {}: {}",
+            status, event_id, e
+        );
+    }
+    release_quietly(&*lock, &lease).await;
+    match result {
         // Pass DB pool etc. if needed
         Ok(()) => {
             // Acknowledge receipt to Payrexx with 200 OK
@@ -2,7 +2,9 @@
 // Declare modules within this crate
 // pub mod auth; // Remove if not used
 mod auth;
+pub mod client;
 pub mod doc;
+pub mod error;
 pub mod handlers;
 pub mod logic;
 pub mod routes;
@@ -1,46 +1,36 @@
 // --- File: crates/connectify_payrexx/src/logic.rs ---
 #![allow(dead_code)] // Allow dead code for doc functions as long as they are not used, bcs of WIP
-use chrono::Utc;
-use connectify_config::PayrexxConfig; // Use config types from connectify_config
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use connectify_config::{AppConfig, PayrexxConfig, PricingConfig}; // Use config types from connectify_config
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
-use thiserror::Error;
-use tracing::info; // Use BTreeMap for ordered params for signing
+use std::collections::HashMap;
+use tracing::{debug, error, info};
 
-// Signature generation imports
+// Signature verification imports
+use axum::http::HeaderMap;
 use base64::{engine::general_purpose::STANDARD as base64_engine, Engine as _};
-use hmac::{Hmac, Mac};
-use sha2::Sha256;
+use connectify_common::webhook::{
+    constant_time_eq, hmac_sha256, signature_header, verify_hmac_sha256_hex, VerifiedEvent,
+    WebhookError, WebhookVerifier,
+};
 
-// URL encoding import
-use serde_urlencoded;
 // Conditionally import ToSchema if openapi feature is enabled
 #[cfg(feature = "openapi")]
 use utoipa::ToSchema;
 
+use crate::client::{
+    AmountParams, ContactFields, FieldValue, Gateway, GatewayParams, InvoiceParams,
+    ListTransactionsParams, PayrexxClient,
+};
 // Import the HTTP client from connectify_common
+use connectify_common::events::{self, PaymentFailed, PaymentSucceeded};
+use connectify_common::models::{CursorQuery, Page, SortOrder, MAX_PAGE_SIZE};
+use connectify_common::orders::{fulfillment_order_store, FulfillmentOrder};
+use connectify_common::request_id::RequestIdExt;
+use connectify_common::webhook_events::webhook_event_store;
 use connectify_common::HTTP_CLIENT;
 
-// --- Error Handling ---
-#[derive(Error, Debug)]
-pub enum PayrexxError {
-    #[error("Payrexx API request failed: {0}")]
-    RequestError(#[from] reqwest::Error),
-    #[error("Payrexx API returned an error: Status={status}, Message='{message}'")]
-    ApiError { status: String, message: String },
-    #[error("Failed to parse Payrexx API response: {0}")]
-    ParseError(#[from] serde_json::Error),
-    #[error("Payrexx configuration missing or incomplete")]
-    ConfigError,
-    #[error("Webhook signature verification failed")]
-    WebhookSignatureError,
-    #[error("Webhook processing error: {0}")]
-    WebhookProcessingError(String),
-    #[error("Failed to encode request body: {0}")]
-    EncodingError(String),
-    #[error("Internal processing error: {0}")]
-    InternalError(String),
-}
+pub use crate::error::PayrexxError;
 
 // --- Data Structures ---
 
@@ -56,49 +46,61 @@ pub struct CreateGatewayRequest {
     pub purpose_override: Option<String>,
     #[cfg_attr(feature = "openapi", schema(example = "customer@example.com"))]
     pub user_email: Option<String>,
+    /// Only authorize the amount, to capture it later with
+    /// `POST /payrexx/transactions/{id}/capture`, e.g. once the session took place
+    #[serde(default)]
+    #[cfg_attr(feature = "openapi", schema(example = false))]
+    pub pre_authorization: bool,
+    /// Payment methods to offer in this order, overriding `payment_methods` of the config
+    #[cfg_attr(feature = "openapi", schema(example = json!(["twint", "postfinance-efinance"])))]
+    pub payment_methods: Option<Vec<String>>,
+    /// Payment service provider IDs to use, overriding `psp` of the config
+    #[cfg_attr(feature = "openapi", schema(example = json!([44])))]
+    pub psp: Option<Vec<i64>>,
+    /// What is fulfilled once the gateway is paid, e.g. "gcal_booking"
+    #[cfg_attr(feature = "openapi", schema(example = "gcal_booking"))]
+    pub fulfillment_type: Option<String>,
+    /// The fulfillment data, stored under the gateway's reference until it is paid
+    #[cfg_attr(feature = "openapi", schema(value_type = Object))]
+    pub fulfillment_data: Option<serde_json::Value>,
 }
 
-// --- Structures for Payrexx API Payload (for Form Encoding) ---
-
-/// Represents an item within the 'basket' array for Payrexx API.
-#[derive(Serialize, Debug)]
-#[cfg_attr(feature = "openapi", derive(ToSchema))] // Add schema if needed
-struct PayrexxBasketItem<'a> {
-    name: &'a str,
-    description: Option<&'a str>,
-    quantity: i64,
-    amount: i64, // Amount for one item in cents/rappen
-    #[serde(rename = "vatRate", skip_serializing_if = "Option::is_none")]
-    vat_rate: Option<f64>,
-}
-
-// The top-level 'fields' object
-#[derive(Serialize, Debug, Default)]
-struct PayrexxFields<'a> {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    email: Option<PayrexxEmailField<'a>>,
-    // Add other 'fields' like firstname, lastname if needed
+/// Request to capture an authorized transaction.
+#[derive(Deserialize, Debug, Default)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct CaptureTransactionRequest {
+    /// Amount to capture in the smallest currency unit, the whole authorized amount if not set
+    #[cfg_attr(feature = "openapi", schema(example = 5000))]
+    pub amount: Option<i64>,
 }
 
-// Structure for fields that just have a 'value'
-#[derive(Serialize, Debug)]
-struct PayrexxValueField<'a> {
-    value: &'a str,
+/// Represents a request to create an invoice paid by bank transfer with a Swiss QR-bill.
+#[derive(Deserialize, Debug, Default)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct CreateQrInvoiceRequest {
+    #[cfg_attr(feature = "openapi", schema(example = 5000))]
+    pub amount_override: Option<i64>,
+    #[cfg_attr(feature = "openapi", schema(example = "CHF"))]
+    pub currency_override: Option<String>,
+    #[cfg_attr(feature = "openapi", schema(example = "Booking Fee - Room XYZ"))]
+    pub purpose_override: Option<String>,
+    #[cfg_attr(feature = "openapi", schema(example = "customer@example.com"))]
+    pub user_email: Option<String>,
+    /// What is fulfilled once the invoice is paid, e.g. "gcal_booking"
+    #[cfg_attr(feature = "openapi", schema(example = "gcal_booking"))]
+    pub fulfillment_type: Option<String>,
+    /// The fulfillment data, stored with the invoice's ID and link under the invoice's reference
+    #[cfg_attr(feature = "openapi", schema(value_type = Object))]
+    pub fulfillment_data: Option<serde_json::Value>,
 }
-// Specific struct for email for clarity (same as ValueField)
-type PayrexxEmailField<'a> = PayrexxValueField<'a>;
 
-// --- Structures for Payrexx API Response (Gateway Creation) ---
-#[derive(Deserialize, Debug)]
-struct PayrexxApiResponseData {
-    link: String,
-}
-#[derive(Deserialize, Debug)]
-struct PayrexxApiResponse {
-    status: String,
-    #[serde(default)]
-    data: Vec<PayrexxApiResponseData>,
-    message: Option<String>,
+/// Request to refund a confirmed transaction.
+#[derive(Deserialize, Debug, Default)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct RefundTransactionRequest {
+    /// Amount to refund in the smallest currency unit, the whole amount if not set
+    #[cfg_attr(feature = "openapi", schema(example = 5000))]
+    pub amount: Option<i64>,
 }
 
 // --- Structure for Response to our Frontend ---
@@ -110,6 +112,108 @@ pub struct CreateGatewayResponse {
         schema(example = "https://INSTANCE.payrexx.com/pay?tid=XYZ123")
     )]
     pub url: String,
+    /// ID of the gateway, to poll its status with `GET /payrexx/gateways/{id}`
+    #[cfg_attr(feature = "openapi", schema(example = 12345))]
+    pub gateway_id: Option<i64>,
+}
+
+/// A created QR-bill invoice.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct QrInvoiceResponse {
+    #[cfg_attr(feature = "openapi", schema(example = 4711))]
+    pub invoice_id: i64,
+    /// Link to the invoice, with the QR-bill payment part to pay it by bank transfer
+    #[cfg_attr(
+        feature = "openapi",
+        schema(example = "https://INSTANCE.payrexx.com/?payment=a1b2c3")
+    )]
+    pub url: Option<String>,
+    /// Reference of the invoice, the ID of its fulfillment order if it has fulfillment data
+    #[cfg_attr(feature = "openapi", schema(example = "ord_0123456789abcdef"))]
+    pub reference_id: String,
+}
+
+/// Status of a gateway, for polling whether it was paid.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct PayrexxGatewayStatus {
+    #[cfg_attr(feature = "openapi", schema(example = 12345))]
+    pub id: i64,
+    /// `waiting` until paid, then `confirmed`, `authorized` or `reserved`
+    #[cfg_attr(feature = "openapi", schema(example = "confirmed"))]
+    pub status: String,
+    #[cfg_attr(feature = "openapi", schema(example = "connectify-xxx-123"))]
+    pub reference_id: Option<String>,
+    /// Amount in the smallest currency unit
+    #[cfg_attr(feature = "openapi", schema(example = 5000))]
+    pub amount: Option<i64>,
+    #[cfg_attr(feature = "openapi", schema(example = "CHF"))]
+    pub currency: Option<String>,
+    pub link: Option<String>,
+    /// IDs of the transactions made on the gateway
+    pub transaction_ids: Vec<i64>,
+}
+
+impl From<Gateway> for PayrexxGatewayStatus {
+    fn from(gateway: Gateway) -> Self {
+        Self {
+            id: gateway.id,
+            status: gateway.status,
+            reference_id: gateway.reference_id,
+            amount: gateway.amount,
+            currency: gateway.currency,
+            link: gateway.link,
+            transaction_ids: gateway
+                .invoices
+                .iter()
+                .flat_map(|invoice| &invoice.transactions)
+                .filter_map(|transaction| transaction.id)
+                .collect(),
+        }
+    }
+}
+
+/// Status of a transaction, without the payer's contact data.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct PayrexxTransactionStatus {
+    #[cfg_attr(feature = "openapi", schema(example = 67890))]
+    pub id: Option<i64>,
+    pub uuid: Option<String>,
+    /// e.g. `waiting`, `confirmed`, `cancelled`, `declined` or `refunded`
+    #[cfg_attr(feature = "openapi", schema(example = "confirmed"))]
+    pub status: Option<String>,
+    #[cfg_attr(feature = "openapi", schema(example = "connectify-xxx-123"))]
+    pub reference_id: Option<String>,
+    /// Amount in the smallest currency unit
+    #[cfg_attr(feature = "openapi", schema(example = 5000))]
+    pub amount: Option<i64>,
+    #[cfg_attr(feature = "openapi", schema(example = "CHF"))]
+    pub currency: Option<String>,
+    /// Time of the transaction, e.g. "2025-04-01 12:00:00"
+    pub time: Option<String>,
+    pub refundable: Option<bool>,
+}
+
+impl From<PayrexxWebhookTransaction> for PayrexxTransactionStatus {
+    fn from(transaction: PayrexxWebhookTransaction) -> Self {
+        Self {
+            id: transaction.id,
+            uuid: transaction.uuid,
+            status: transaction.status,
+            reference_id: transaction.reference_id.or_else(|| {
+                transaction
+                    .invoice
+                    .as_ref()
+                    .and_then(|invoice| invoice.reference_id.clone())
+            }),
+            amount: transaction.amount,
+            currency: transaction.invoice.and_then(|invoice| invoice.currency),
+            time: transaction.time,
+            refundable: transaction.refundable,
+        }
+    }
 }
 
 // --- Webhook Payload Structures ---
@@ -266,177 +370,914 @@ pub struct PayrexxWebhookPayload {
 
 // --- Core Logic Functions ---
 
-/// Generates the HMAC-SHA256 signature required by Payrexx API.
-fn generate_payrexx_signature(query_string: &str, api_secret: &str) -> String {
-    type HmacSha256 = Hmac<Sha256>;
-    // Ensure secret is treated as bytes
-    let mut mac =
-        HmacSha256::new_from_slice(api_secret.as_bytes()).expect("HMAC can take key of any size"); // Handle error in production
-    mac.update(query_string.as_bytes());
-    let result = mac.finalize();
-    let code_bytes = result.into_bytes();
-    // Encode signature using standard base64
-    base64_engine.encode(code_bytes)
+/// The payment methods (`pm`) and payment service providers (`psp`) of a gateway, from the
+/// request or else the config.
+fn payment_methods(
+    config: &PayrexxConfig,
+    request_data: &CreateGatewayRequest,
+) -> (Vec<String>, Vec<i64>) {
+    let mut payment_methods: Vec<String> = Vec::new();
+    for method in request_data
+        .payment_methods
+        .as_ref()
+        .unwrap_or(&config.payment_methods)
+    {
+        let method = method.trim().to_lowercase();
+        if !method.is_empty() && !payment_methods.contains(&method) {
+            payment_methods.push(method);
+        }
+    }
+    let psp = request_data.psp.as_ref().unwrap_or(&config.psp).clone();
+    (payment_methods, psp)
+}
+
+/// The prefilled contact fields of a payment page.
+fn contact_fields(email: Option<&str>) -> Option<ContactFields> {
+    email.map(|email| ContactFields {
+        email: Some(FieldValue {
+            value: email.to_string(),
+        }),
+    })
+}
+
+/// The parameters of a new gateway.
+fn gateway_params(
+    config: &PayrexxConfig,
+    request_data: &CreateGatewayRequest,
+    price: PaymentPrice,
+    reference_id: String,
+) -> GatewayParams {
+    let (payment_methods, psp) = payment_methods(config, request_data);
+    GatewayParams {
+        amount: price.amount,
+        currency: price.currency,
+        purpose: price.purpose,
+        reference_id,
+        success_redirect_url: config.success_url.clone(),
+        failed_redirect_url: config.failed_url.clone(),
+        cancel_redirect_url: config.cancel_url.clone(),
+        fields: contact_fields(request_data.user_email.as_deref()),
+        pre_authorization: request_data.pre_authorization,
+        payment_methods,
+        psp,
+    }
 }
 
-/// Makes a request to the Payrexx API to create a payment gateway using form encoding and signature.
+/// Creates a Payrexx payment gateway, priced by the shared price tiers for bookings.
 pub async fn create_gateway_request(
     config: &PayrexxConfig,
+    pricing_config: &PricingConfig,
     request_data: CreateGatewayRequest,
 ) -> Result<CreateGatewayResponse, PayrexxError> {
-    info!("Initiating Payrexx gateway creation (Form Encoded)...");
+    info!("Initiating Payrexx gateway creation...");
+    let client = PayrexxClient::from_config(config)?;
 
     // Determine final values
-    let amount = request_data
-        .amount_override
-        .unwrap_or(config.unit_amount.unwrap_or(1000));
-    let currency = request_data
-        .currency_override
-        .as_deref()
-        .unwrap_or(config.currency.as_deref().unwrap_or("CHF"));
-    let purpose = request_data
+    let price = payment_price(
+        config,
+        pricing_config,
+        PriceRequest {
+            fulfillment_type: request_data.fulfillment_type.as_deref(),
+            fulfillment_data: request_data.fulfillment_data.as_ref(),
+            amount_override: request_data.amount_override,
+            currency_override: request_data.currency_override.as_deref(),
+            purpose_override: request_data.purpose_override.as_deref(),
+        },
+    )?;
+
+    // The fulfillment data is stored as an order, whose ID is the gateway's reference
+    let reference_id = match request_data.fulfillment_type.as_deref() {
+        Some(fulfillment_type) => {
+            let payload = request_data
+                .fulfillment_data
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?
+                .unwrap_or_else(|| "{}".to_string());
+            let order = FulfillmentOrder::new(fulfillment_type, payload, Utc::now());
+            let order_id = order.order_id.clone();
+            save_fulfillment_order(order).await?;
+            order_id
+        }
+        None => new_reference_id(),
+    };
+
+    let gateway = client
+        .create_gateway(&gateway_params(config, &request_data, price, reference_id))
+        .await?;
+    info!(
+        "Payrexx gateway created successfully. Link: {}",
+        gateway.link
+    );
+    Ok(CreateGatewayResponse {
+        url: gateway.link,
+        gateway_id: gateway.id,
+    })
+}
+
+/// Captures a transaction authorized by a gateway created with `pre_authorization`, charging
+/// the authorized amount or a part of it.
+pub async fn capture_transaction(
+    config: &PayrexxConfig,
+    transaction_id: i64,
+    request: &CaptureTransactionRequest,
+) -> Result<PayrexxTransactionStatus, PayrexxError> {
+    info!(
+        "Capturing Payrexx transaction {} (amount: {:?})",
+        transaction_id, request.amount
+    );
+    let transaction = PayrexxClient::from_config(config)?
+        .capture_transaction(
+            transaction_id,
+            &AmountParams {
+                amount: request.amount,
+            },
+        )
+        .await?;
+    Ok(transaction.into())
+}
+
+/// Refunds a confirmed transaction, the whole amount or a part of it.
+pub async fn refund_transaction(
+    config: &PayrexxConfig,
+    transaction_id: i64,
+    request: &RefundTransactionRequest,
+) -> Result<PayrexxTransactionStatus, PayrexxError> {
+    info!(
+        "Refunding Payrexx transaction {} (amount: {:?})",
+        transaction_id, request.amount
+    );
+    let transaction = PayrexxClient::from_config(config)?
+        .refund_transaction(
+            transaction_id,
+            &AmountParams {
+                amount: request.amount,
+            },
+        )
+        .await?;
+    Ok(transaction.into())
+}
+
+/// The price of a gateway or invoice.
+#[derive(Debug, PartialEq)]
+struct PaymentPrice {
+    /// Amount in the smallest currency unit
+    amount: i64,
+    currency: String,
+    purpose: String,
+}
+
+/// What a gateway or invoice request says about its price.
+struct PriceRequest<'a> {
+    fulfillment_type: Option<&'a str>,
+    fulfillment_data: Option<&'a serde_json::Value>,
+    amount_override: Option<i64>,
+    currency_override: Option<&'a str>,
+    purpose_override: Option<&'a str>,
+}
+
+/// The booked duration in minutes of the fulfillment data of a calendar booking.
+fn booking_duration_minutes(
+    fulfillment_data: Option<&serde_json::Value>,
+) -> Result<i64, PayrexxError> {
+    let time = |key: &str| -> Result<DateTime<Utc>, PayrexxError> {
+        let value = fulfillment_data
+            .and_then(|data| data.get(key))
+            .and_then(|value| value.as_str())
+            .ok_or_else(|| {
+                PayrexxError::PricingError(format!("Missing {} in fulfillment_data", key))
+            })?;
+        DateTime::parse_from_rfc3339(value)
+            .map(|time| time.with_timezone(&Utc))
+            .map_err(|e| PayrexxError::PricingError(format!("Invalid {} format: {}", key, e)))
+    };
+    let (start, end) = (time("start_time")?, time("end_time")?);
+    if end <= start {
+        return Err(PayrexxError::PricingError(
+            "End time must be after start time.".to_string(),
+        ));
+    }
+    Ok((end - start).num_minutes())
+}
+
+/// Determines the price of a payment. Calendar bookings are priced by their duration from the
+/// shared price tiers, in the requested currency or else the tier's own one; other payments use
+/// the overrides of the request or else the static amount of the config.
+fn payment_price(
+    config: &PayrexxConfig,
+    pricing_config: &PricingConfig,
+    request: PriceRequest<'_>,
+) -> Result<PaymentPrice, PayrexxError> {
+    if !matches!(
+        request.fulfillment_type,
+        Some("gcal_booking") | Some("adhoc_gcal_twilio")
+    ) {
+        return Ok(PaymentPrice {
+            amount: request
+                .amount_override
+                .unwrap_or(config.unit_amount.unwrap_or(1000)),
+            currency: request
+                .currency_override
+                .or(config.currency.as_deref())
+                .unwrap_or("CHF")
+                .to_string(),
+            purpose: request
+                .purpose_override
+                .or(config.product_name.as_deref())
+                .unwrap_or("Payment")
+                .to_string(),
+        });
+    }
+
+    let duration_minutes = booking_duration_minutes(request.fulfillment_data)?;
+    let tier = pricing_config
+        .tier_for_duration(duration_minutes)
+        .ok_or_else(|| {
+            PayrexxError::PricingError(format!(
+                "No price tier for a duration of {} minutes",
+                duration_minutes
+            ))
+        })?;
+    let default_currency = pricing_config.default_currency.as_deref();
+    let (amount, currency) = match request.currency_override {
+        Some(requested) => {
+            let amount = tier.amount_in(requested, default_currency).ok_or_else(|| {
+                PayrexxError::PricingError(format!(
+                    "No price in {} for a duration of {} minutes, available: {}",
+                    requested.to_uppercase(),
+                    duration_minutes,
+                    tier.currencies(default_currency).join(", ")
+                ))
+            })?;
+            (amount, requested.to_string())
+        }
+        None => (
+            tier.unit_amount,
+            tier.base_currency(default_currency)
+                .or_else(|| config.currency.clone())
+                .unwrap_or_else(|| "CHF".to_string()),
+        ),
+    };
+    // The tier's product name, else the booking's summary, else a generic one
+    let purpose = request
         .purpose_override
+        .map(str::to_string)
+        .or_else(|| tier.product_name.clone())
+        .or_else(|| {
+            request
+                .fulfillment_data
+                .and_then(|data| data.get("summary"))
+                .and_then(|summary| summary.as_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| format!("Service - {} min", duration_minutes));
+    info!(
+        "Payrexx price of {} minutes: {} {} ('{}')",
+        duration_minutes, amount, currency, purpose
+    );
+    Ok(PaymentPrice {
+        amount,
+        currency: currency.to_uppercase(),
+        purpose,
+    })
+}
+
+/// Payment method of QR-bill invoices, unless `qr_bill_payment_method` is configured.
+const DEFAULT_QR_BILL_PAYMENT_METHOD: &str = "invoice";
+
+/// The parameters of a new QR-bill invoice.
+fn qr_invoice_params(
+    config: &PayrexxConfig,
+    request: &CreateQrInvoiceRequest,
+    price: PaymentPrice,
+    reference_id: &str,
+) -> InvoiceParams {
+    let payment_method = config
+        .qr_bill_payment_method
+        .as_deref()
+        .unwrap_or(DEFAULT_QR_BILL_PAYMENT_METHOD);
+    InvoiceParams {
+        title: price.purpose.clone(),
+        description: price.purpose.clone(),
+        purpose: price.purpose,
+        amount: price.amount,
+        currency: price.currency,
+        reference_id: reference_id.to_string(),
+        fields: contact_fields(request.user_email.as_deref()),
+        payment_methods: vec![payment_method.to_string()],
+    }
+}
+
+/// The fulfillment data of an invoice, with the invoice's ID and link so they are stored with
+/// the booking.
+fn invoice_fulfillment_payload(
+    fulfillment_data: Option<&serde_json::Value>,
+    invoice: &QrInvoiceResponse,
+) -> Result<String, PayrexxError> {
+    let mut payload = fulfillment_data
+        .cloned()
+        .unwrap_or_else(|| serde_json::json!({}));
+    let Some(fields) = payload.as_object_mut() else {
+        return Err(PayrexxError::InternalError(
+            "fulfillment_data must be a JSON object".to_string(),
+        ));
+    };
+    fields.insert("payment_method".to_string(), "payrexx".into());
+    fields.insert(
+        "payment_id".to_string(),
+        invoice.invoice_id.to_string().into(),
+    );
+    fields.insert("payrexx_invoice_id".to_string(), invoice.invoice_id.into());
+    if let Some(url) = &invoice.url {
+        fields.insert("invoice_url".to_string(), url.clone().into());
+    }
+    Ok(serde_json::to_string(&payload)?)
+}
+
+/// Creates an invoice to be paid by bank transfer with the QR-bill payment part of its link.
+///
+/// With a `fulfillment_type`, the fulfillment data is stored as a fulfillment order, together
+/// with the invoice's ID and link; the order's ID is the invoice's `referenceId`, so the
+/// payment's webhook finds the booking again.
+pub async fn create_qr_invoice(
+    config: &PayrexxConfig,
+    pricing_config: &PricingConfig,
+    request: CreateQrInvoiceRequest,
+) -> Result<QrInvoiceResponse, PayrexxError> {
+    let price = payment_price(
+        config,
+        pricing_config,
+        PriceRequest {
+            fulfillment_type: request.fulfillment_type.as_deref(),
+            fulfillment_data: request.fulfillment_data.as_ref(),
+            amount_override: request.amount_override,
+            currency_override: request.currency_override.as_deref(),
+            purpose_override: request.purpose_override.as_deref(),
+        },
+    )?;
+    let order = request
+        .fulfillment_type
         .as_deref()
-        .unwrap_or(config.product_name.as_deref().unwrap_or("Payment"));
-    let reference_id = format!(
+        .map(|fulfillment_type| FulfillmentOrder::new(fulfillment_type, "", Utc::now()));
+    let reference_id = match &order {
+        Some(order) => order.order_id.clone(),
+        None => new_reference_id(),
+    };
+    info!("Creating Payrexx QR-bill invoice {}", reference_id);
+
+    let client = PayrexxClient::from_config(config)?;
+    let invoice = client
+        .create_invoice(&qr_invoice_params(config, &request, price, &reference_id))
+        .await?;
+    let response = QrInvoiceResponse {
+        invoice_id: invoice.id,
+        url: invoice.link,
+        reference_id,
+    };
+
+    if let Some(mut order) = order {
+        order.payload = invoice_fulfillment_payload(request.fulfillment_data.as_ref(), &response)?;
+        save_fulfillment_order(order).await?;
+    }
+    Ok(response)
+}
+
+/// A reference for a payment without fulfillment data.
+fn new_reference_id() -> String {
+    format!(
         "connectify-{}-{}",
         SERVICE_NAME,
         Utc::now().timestamp_millis()
-    );
+    )
+}
 
-    // --- Prepare data for form encoding using BTreeMap for ordered keys ---
-    let mut form_params: BTreeMap<String, String> = BTreeMap::new();
-
-    // Add required fields (convert numbers to strings)
-    form_params.insert("amount".to_string(), amount.to_string());
-    form_params.insert("currency".to_string(), currency.to_string());
-    form_params.insert("purpose".to_string(), purpose.to_string());
-    form_params.insert("referenceId".to_string(), reference_id.clone()); // Clone reference_id for use here
-    form_params.insert("successRedirectUrl".to_string(), config.success_url.clone());
-    form_params.insert("failedRedirectUrl".to_string(), config.failed_url.clone());
-    form_params.insert("cancelRedirectUrl".to_string(), config.cancel_url.clone());
-
-    // Add optional fields if present
-    if let Some(email) = request_data.user_email.as_deref() {
-        // Flatten 'fields' according to Payrexx convention (verify exact key format)
-        form_params.insert("fields[email][value]".to_string(), email.to_string());
-    }
-    // TODO: Add other optional fields like basket, pm, psp, preAuthorization etc.
-    //       to the form_params BTreeMap here if needed.
-
-    // --- Generate Signature ---
-    // URL-encode the parameters *before* signing
-    let query_string_for_sig = serde_urlencoded::to_string(&form_params).map_err(|e| {
-        PayrexxError::EncodingError(format!("Failed to urlencode params for signature: {}", e))
-    })?;
+/// Saves a fulfillment order before the payment referencing it is created.
+async fn save_fulfillment_order(order: FulfillmentOrder) -> Result<(), PayrexxError> {
+    let order_id = order.order_id.clone();
+    fulfillment_order_store().save(order).await.map_err(|e| {
+        PayrexxError::InternalError(format!(
+            "Failed to store fulfillment order {}: {}",
+            order_id, e
+        ))
+    })
+}
 
-    let api_secret = std::env::var("PAYREXX_API_SECRET").map_err(|_| PayrexxError::ConfigError)?;
-    let api_signature = generate_payrexx_signature(&query_string_for_sig, &api_secret);
+/// Looks up the status of a gateway, e.g. to poll whether it was paid.
+pub async fn get_gateway_status(
+    config: &PayrexxConfig,
+    gateway_id: i64,
+) -> Result<PayrexxGatewayStatus, PayrexxError> {
+    let gateway = PayrexxClient::from_config(config)?
+        .get_gateway(gateway_id)
+        .await?;
+    Ok(gateway.into())
+}
 
-    // Add signature to parameters AFTER encoding the rest for the signature base string
-    form_params.insert("ApiSignature".to_string(), api_signature);
+/// Looks up the status of a transaction, e.g. for reconciliation.
+pub async fn get_transaction_status(
+    config: &PayrexxConfig,
+    transaction_id: i64,
+) -> Result<PayrexxTransactionStatus, PayrexxError> {
+    let transaction = PayrexxClient::from_config(config)?
+        .get_transaction(transaction_id)
+        .await?;
+    Ok(transaction.into())
+}
 
-    // --- Prepare Final Request ---
-    // URL-encode the full set of parameters including the signature for the request body
-    let final_request_body = serde_urlencoded::to_string(&form_params).map_err(|e| {
-        PayrexxError::EncodingError(format!("Failed to urlencode final params: {}", e))
-    })?;
+/// Maximum number of Payrexx pages listed to fill one page of filtered transactions.
+const MAX_TRANSACTION_LIST_REQUESTS: usize = 10;
 
-    // Construct Payrexx API URL
-    let api_url = format!(
-        "https://api.payrexx.com/v1.0/Gateway/?instance={}",
-        config.instance_name
-    );
+/// Filters of the admin transaction listing; the page is selected with a [`CursorQuery`].
+///
+/// The cursor is the number of transactions Payrexx lists before the next page, in the
+/// requested order.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams, ToSchema))]
+pub struct ListTransactionsAdminQuery {
+    /// Only transactions on or after this day (UTC), as YYYY-MM-DD
+    #[cfg_attr(feature = "openapi", param(example = "2025-07-01", required = false))]
+    pub created_from: Option<String>,
+    /// Only transactions on or before this day (UTC), as YYYY-MM-DD
+    #[cfg_attr(feature = "openapi", param(example = "2025-07-31", required = false))]
+    pub created_to: Option<String>,
+    /// Only transactions with this status, e.g. `confirmed` or `refunded`
+    #[cfg_attr(feature = "openapi", param(example = "confirmed", required = false))]
+    pub status: Option<String>,
+    /// Only transactions with this reference, e.g. the order ID of a gateway
+    #[cfg_attr(feature = "openapi", param(example = "ord_123", required = false))]
+    pub reference_id: Option<String>,
+}
+
+impl ListTransactionsAdminQuery {
+    /// The parameters listing the transactions from Payrexx, which filters by time.
+    fn payrexx_params(&self, page: &CursorQuery) -> Result<ListTransactionsParams, PayrexxError> {
+        let offset = page
+            .cursor
+            .as_deref()
+            .map(|cursor| {
+                cursor.parse::<u32>().map_err(|_| {
+                    PayrexxError::InvalidTransactionFilter(format!("invalid cursor '{}'", cursor))
+                })
+            })
+            .transpose()?;
+        let day = |name: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|value| {
+                    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+                        PayrexxError::InvalidTransactionFilter(format!(
+                            "{} must be a date in YYYY-MM-DD format, got '{}'",
+                            name, value
+                        ))
+                    })
+                })
+                .transpose()
+        };
+        let from = day("created_from", &self.created_from)?;
+        let to = day("created_to", &self.created_to)?;
+        if let (Some(from), Some(to)) = (from, to) {
+            if to < from {
+                return Err(PayrexxError::InvalidTransactionFilter(
+                    "created_to is before created_from".to_string(),
+                ));
+            }
+        }
+        // Payrexx compares exclusively, so the bounds are just outside the days
+        Ok(ListTransactionsParams {
+            offset,
+            // One more transaction than requested tells whether there is a next page; filtering
+            // locally drops transactions, so list as many as a page may hold then
+            limit: Some(if self.filters_locally() {
+                MAX_PAGE_SIZE
+            } else {
+                page.limit() as u32 + 1
+            }),
+            order_by_time: Some(
+                match page.order() {
+                    SortOrder::Asc => "ASC",
+                    SortOrder::Desc => "DESC",
+                }
+                .to_string(),
+            ),
+            created_after: from
+                .and_then(|day| day.checked_sub_days(Days::new(1)))
+                .map(|day| format!("{} 23:59:59", day)),
+            created_before: to
+                .and_then(|day| day.checked_add_days(Days::new(1)))
+                .map(|day| format!("{} 00:00:00", day)),
+        })
+    }
 
-    info!("Sending POST request to Payrexx API: {}", api_url);
+    /// Whether some of the filters are applied to the listed transactions, not by Payrexx.
+    fn filters_locally(&self) -> bool {
+        self.status.is_some() || self.reference_id.is_some()
+    }
 
-    // --- Make the API Call ---
-    let response = HTTP_CLIENT
-        .post(&api_url)
-        .header(
-            reqwest::header::CONTENT_TYPE,
-            "application/x-www-form-urlencoded",
-        ) // Set correct content type
-        .body(final_request_body) // Send urlencoded string as body
-        .send()
-        .await?;
+    /// Whether a listed transaction matches the filters Payrexx can't apply.
+    fn matches(&self, transaction: &PayrexxWebhookTransaction) -> bool {
+        self.status
+            .as_deref()
+            .is_none_or(|status| transaction.status.as_deref() == Some(status))
+            && self
+                .reference_id
+                .as_deref()
+                .is_none_or(|reference| transaction_reference(transaction) == Some(reference))
+    }
+}
 
-    let status = response.status();
-    let body_text = response.text().await?;
+/// The booking a transaction pays for, from the data stored locally.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct TransactionBooking {
+    /// The fulfillment type, e.g. "gcal_booking"
+    #[cfg_attr(feature = "openapi", schema(example = "gcal_booking"))]
+    pub fulfillment_type: String,
+    /// The fulfillment data, e.g. the booked start and end time
+    pub fulfillment_data: Option<serde_json::Value>,
+    /// Status of the `confirmed` webhook triggering the fulfillment: `received`, `processed`
+    /// or `failed`; none if it was not received yet
+    #[cfg_attr(feature = "openapi", schema(example = "processed"))]
+    pub fulfillment_status: Option<String>,
+}
 
-    info!("Payrexx API response status: {}", status);
-    if !status.is_success() {
-        info!("Payrexx API response body (raw): {}", body_text);
+/// A transaction listed for admins, with the booking it pays for.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct AdminPayrexxTransaction {
+    #[serde(flatten)]
+    pub transaction: PayrexxTransactionStatus,
+    pub booking: Option<TransactionBooking>,
+}
+
+/// A page of the admin transaction listing.
+pub type ListTransactionsAdminResponse = Page<AdminPayrexxTransaction>;
+
+/// Lists transactions for admins, filtered and joined with the local booking data.
+///
+/// Payrexx filters by time; the status and reference are filtered here, listing further
+/// Payrexx pages until the page is full. After `MAX_TRANSACTION_LIST_REQUESTS` Payrexx pages,
+/// a page may hold fewer transactions than `limit`, with `has_more` set and the cursor
+/// pointing after the last listed transaction.
+pub async fn list_transactions_admin(
+    config: &PayrexxConfig,
+    query: &ListTransactionsAdminQuery,
+    page: &CursorQuery,
+) -> Result<ListTransactionsAdminResponse, PayrexxError> {
+    info!(
+        "[Payrexx Logic] Listing transactions for admin. Params: {:?}, page: {:?}",
+        query, page
+    );
+    let client = PayrexxClient::from_config(config)?;
+    let transactions_page = list_matching_transactions(&client, query, page).await?;
+
+    let mut items = Vec::with_capacity(transactions_page.items.len());
+    for transaction in transactions_page.items {
+        let booking = transaction_booking(&transaction).await;
+        items.push(AdminPayrexxTransaction {
+            transaction: transaction.into(),
+            booking,
+        });
     }
+    Ok(Page {
+        items,
+        next_cursor: transactions_page.next_cursor,
+        has_more: transactions_page.has_more,
+    })
+}
 
-    // --- Parse Response (Still JSON) ---
-    if status.is_success() {
-        let payrexx_response: PayrexxApiResponse = serde_json::from_str(&body_text)?;
+/// Lists the Payrexx pages of transactions until a page of matching ones is filled.
+async fn list_matching_transactions(
+    client: &PayrexxClient,
+    query: &ListTransactionsAdminQuery,
+    page: &CursorQuery,
+) -> Result<Page<PayrexxWebhookTransaction>, PayrexxError> {
+    let limit = page.limit();
+    let mut params = query.payrexx_params(page)?;
+    let batch_size = params.limit.unwrap_or_default() as usize;
+    let mut offset = params.offset.unwrap_or(0);
+    // The matching transactions with their offset in the Payrexx listing
+    let mut matching = Vec::with_capacity(limit + 1);
+    let mut complete = false;
+    for _ in 0..MAX_TRANSACTION_LIST_REQUESTS {
+        let transactions = client.list_transactions(&params).await?;
+        let listed = transactions.len();
+        matching.extend(
+            (offset..)
+                .zip(transactions)
+                .filter(|(_, transaction)| query.matches(transaction))
+                .take(limit + 1 - matching.len()),
+        );
+        offset += listed as u32;
+        if matching.len() > limit || listed < batch_size {
+            complete = true;
+            break;
+        }
+        params.offset = Some(offset);
+    }
+    let transactions_page = if complete {
+        Page::from_items(matching, limit, |(position, _)| (position + 1).to_string())
+    } else {
+        // Payrexx has more transactions, but the listed ones didn't fill the page
+        Page {
+            items: matching,
+            next_cursor: Some(offset.to_string()),
+            has_more: true,
+        }
+    };
+    Ok(transactions_page.map(|(_, transaction)| transaction))
+}
 
-        if payrexx_response.status == "success" {
-            if let Some(gateway_data) = payrexx_response.data.first() {
-                info!(
-                    "Payrexx gateway created successfully. Link: {}",
-                    gateway_data.link
-                );
-                Ok(CreateGatewayResponse {
-                    url: gateway_data.link.clone(),
-                })
-            } else {
-                info!("Payrexx API success status but missing data/link in response.");
-                Err(PayrexxError::InternalError(
-                    "Payrexx response missing gateway link".to_string(),
-                ))
-            }
-        } else {
-            let error_message = payrexx_response
-                .message
-                .unwrap_or_else(|| "Unknown Payrexx API error".to_string());
-            info!(
-                "Payrexx API reported error. Status: {}, Message: {}",
-                payrexx_response.status, error_message
+/// The local booking data of a transaction; lookups that fail are logged and left out, so the
+/// listing still shows the transaction.
+async fn transaction_booking(
+    transaction: &PayrexxWebhookTransaction,
+) -> Option<TransactionBooking> {
+    let payment_id = transaction_id(transaction);
+    let (fulfillment_type, fulfillment_data) = match transaction_fulfillment(transaction).await {
+        Ok(fulfillment) => fulfillment?,
+        Err(e) => {
+            error!("[Payrexx Logic] Transaction {}: {}", payment_id, e);
+            return None;
+        }
+    };
+    let event_id = format!("{}:confirmed", payment_id);
+    let fulfillment_status = match webhook_event_store().get(PAYREXX_PROVIDER, &event_id).await {
+        Ok(event) => event.map(|event| event.status.to_string()),
+        Err(e) => {
+            error!(
+                "[Payrexx Logic] Failed to load the webhook event {}: {}",
+                event_id, e
             );
-            Err(PayrexxError::ApiError {
-                status: payrexx_response.status,
-                message: error_message,
-            })
+            None
         }
-    } else {
-        info!(
-            "Payrexx API request failed with HTTP status: {}. Body: {}",
-            status, body_text
-        );
-        let message = match serde_json::from_str::<PayrexxApiResponse>(&body_text) {
-            Ok(err_resp) => err_resp.message.unwrap_or(body_text),
-            Err(_) => body_text,
-        };
-        Err(PayrexxError::ApiError {
-            status: status.to_string(),
-            message,
+    };
+    Some(TransactionBooking {
+        fulfillment_type,
+        fulfillment_data: serde_json::from_str(&fulfillment_data).ok(),
+        fulfillment_status,
+    })
+}
+
+// --- Webhook Processing Logic ---
+
+/// Header carrying the Payrexx webhook signature.
+pub const PAYREXX_SIGNATURE_HEADER: &str = "Webhook-Signature";
+
+/// Verifies Payrexx webhook signatures: an HMAC-SHA256 of the raw body keyed with the API
+/// secret, sent hex or base64 encoded.
+pub struct PayrexxWebhookVerifier {
+    api_secret: String,
+}
+
+impl PayrexxWebhookVerifier {
+    /// Create a verifier for the Payrexx API secret.
+    pub fn new(api_secret: impl Into<String>) -> Self {
+        Self {
+            api_secret: api_secret.into(),
+        }
+    }
+}
+
+impl WebhookVerifier for PayrexxWebhookVerifier {
+    fn verify(&self, payload: &[u8], headers: &HeaderMap) -> Result<VerifiedEvent, WebhookError> {
+        let signature = signature_header(headers, PAYREXX_SIGNATURE_HEADER)?;
+        check_payrexx_signature(&self.api_secret, payload, signature)?;
+        Ok(VerifiedEvent {
+            provider: "payrexx",
+            payload: payload.to_vec(),
+            timestamp: None,
         })
     }
 }
 
-// --- Webhook Processing Logic ---
+fn check_payrexx_signature(
+    api_secret: &str,
+    payload: &[u8],
+    signature: &str,
+) -> Result<(), WebhookError> {
+    if api_secret.is_empty() {
+        return Err(WebhookError::Config(
+            "Payrexx API secret is empty".to_string(),
+        ));
+    }
+    let signature = signature.trim();
+    let expected_base64 = base64_engine.encode(hmac_sha256(api_secret.as_bytes(), payload));
+    if verify_hmac_sha256_hex(api_secret.as_bytes(), payload, signature)
+        || constant_time_eq(expected_base64.as_bytes(), signature.as_bytes())
+    {
+        Ok(())
+    } else {
+        Err(WebhookError::InvalidSignature(
+            "Payrexx signature mismatch".to_string(),
+        ))
+    }
+}
+
+/// Converts webhook verification errors; details are logged, not exposed.
+impl From<WebhookError> for PayrexxError {
+    fn from(err: WebhookError) -> Self {
+        info!("Payrexx webhook verification failed: {}", err);
+        match err {
+            WebhookError::Config(_) => PayrexxError::ConfigError,
+            _ => PayrexxError::WebhookSignatureError,
+        }
+    }
+}
 
 /// Verifies the signature of an incoming Payrexx webhook request.
-/// Placeholder implementation - requires details from Payrexx documentation.
+///
+/// Prefer [`PayrexxWebhookVerifier`], which reads the header itself.
 pub fn verify_payrexx_signature(
-    _api_secret: &str,
-    _request_body: &[u8],
-    _signature_header: Option<&str>,
+    api_secret: &str,
+    request_body: &[u8],
+    signature_header: Option<&str>,
+) -> Result<(), PayrexxError> {
+    let signature = signature_header.ok_or(PayrexxError::WebhookSignatureError)?;
+    check_payrexx_signature(api_secret, request_body, signature)?;
+    Ok(())
+}
+
+/// The ID of a webhook transaction, as announced in payment events.
+fn transaction_id(transaction: &PayrexxWebhookTransaction) -> String {
+    transaction
+        .id
+        .map(|id| id.to_string())
+        .or_else(|| transaction.uuid.clone())
+        .unwrap_or_default()
+}
+
+/// Provider name of Payrexx webhooks in the webhook event store.
+pub(crate) const PAYREXX_PROVIDER: &str = "payrexx";
+
+/// The idempotency key of a webhook in the webhook event store: the transaction id and its
+/// status, as Payrexx announces each status change of a transaction (e.g. `authorized`, then
+/// `confirmed` once captured) in a webhook of its own.
+///
+/// `None` for payloads without a transaction, which don't trigger anything.
+pub fn webhook_event_id(payload: &PayrexxWebhookPayload) -> Option<String> {
+    let transaction = payload.transaction.as_ref()?;
+    let id = transaction_id(transaction);
+    if id.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "{}:{}",
+        id,
+        transaction.status.as_deref().unwrap_or("unknown")
+    ))
+}
+
+/// The reference of a transaction, set on the transaction or on its invoice (the gateway's).
+fn transaction_reference(transaction: &PayrexxWebhookTransaction) -> Option<&str> {
+    transaction.reference_id.as_deref().or_else(|| {
+        transaction
+            .invoice
+            .as_ref()
+            .and_then(|invoice| invoice.reference_id.as_deref())
+    })
+}
+
+/// A custom field of a transaction's invoice by name.
+fn custom_field<'a>(transaction: &'a PayrexxWebhookTransaction, name: &str) -> Option<&'a str> {
+    transaction
+        .invoice
+        .as_ref()?
+        .custom_fields
+        .iter()
+        .find(|field| field.name.as_deref() == Some(name))
+        .and_then(|field| field.value.as_deref())
+}
+
+/// The fulfillment type and JSON data of a transaction: from the fulfillment order named by its
+/// reference, or else from its `ff_type` and `ff_data_json` custom fields, e.g. for gateways
+/// created in the Payrexx dashboard.
+async fn transaction_fulfillment(
+    transaction: &PayrexxWebhookTransaction,
+) -> Result<Option<(String, String)>, PayrexxError> {
+    if let Some(reference) = transaction_reference(transaction) {
+        let order = fulfillment_order_store()
+            .get(reference)
+            .await
+            .map_err(|e| {
+                PayrexxError::WebhookProcessingError(format!(
+                    "Failed to load fulfillment order {}: {}",
+                    reference, e
+                ))
+            })?;
+        if let Some(order) = order {
+            return Ok(Some((order.fulfillment_type, order.payload)));
+        }
+    }
+    Ok(custom_field(transaction, "ff_type")
+        .zip(custom_field(transaction, "ff_data_json"))
+        .map(|(ff_type, ff_data)| (ff_type.to_string(), ff_data.to_string())))
+}
+
+/// The fulfillment data of a paid transaction, with its payment information added.
+fn fulfillment_payload(
+    transaction: &PayrexxWebhookTransaction,
+    ff_data: &str,
+) -> Result<serde_json::Value, PayrexxError> {
+    let mut payload: serde_json::Value = serde_json::from_str(ff_data).map_err(|e| {
+        PayrexxError::WebhookProcessingError(format!("Failed to parse fulfillment data: {}", e))
+    })?;
+    if let serde_json::Value::Object(ref mut map) = payload {
+        map.insert("payment_id".to_string(), transaction_id(transaction).into());
+        map.insert("payment_method".to_string(), "payrexx".into());
+        if let Some(amount) = transaction.amount {
+            map.insert("payment_amount".to_string(), amount.into());
+        }
+    }
+    Ok(payload)
+}
+
+/// The path of the fulfillment endpoint of a fulfillment type.
+fn fulfillment_endpoint_path(ff_type: &str) -> Option<&'static str> {
+    match ff_type {
+        "gcal_booking" => Some("/api/fulfill/gcal-booking"),
+        "adhoc_gcal_twilio" => Some("/api/fulfill/adhoc-gcal-twilio"),
+        _ => None,
+    }
+}
+
+/// Calls the fulfillment endpoint of a confirmed transaction, if it has fulfillment data.
+///
+/// A failed call fails the webhook, so Payrexx delivers it again.
+async fn trigger_fulfillment(
+    app_config: &AppConfig,
+    transaction: &PayrexxWebhookTransaction,
 ) -> Result<(), PayrexxError> {
-    // TODO: Implement actual signature verification based on Payrexx docs.
+    let payment_id = transaction_id(transaction);
+    let Some((ff_type, ff_data)) = transaction_fulfillment(transaction).await? else {
+        info!(
+            "[Payrexx Webhook] No fulfillment data for transaction {}, nothing to fulfill.",
+            payment_id
+        );
+        return Ok(());
+    };
+    let payload = fulfillment_payload(transaction, &ff_data)?;
+    debug!("fulfillment payload: {:?}", payload);
+
+    let shared_secret = app_config
+        .fulfillment
+        .as_ref()
+        .and_then(|f| f.shared_secret.as_deref())
+        .ok_or_else(|| {
+            error!("[Payrexx Webhook] Fulfillment shared secret not configured. Cannot call fulfillment service for transaction {}.", payment_id);
+            PayrexxError::ConfigError
+        })?;
+    let endpoint_path = fulfillment_endpoint_path(&ff_type).ok_or_else(|| {
+        error!("[Payrexx Webhook] Unknown fulfillment type: {}", ff_type);
+        PayrexxError::WebhookProcessingError(format!("Unknown fulfillment type: {}", ff_type))
+    })?;
+    let fulfillment_url = format!(
+        "http://{}:{}{}",
+        app_config.server.host, app_config.server.port, endpoint_path
+    );
+    info!(
+        "[Payrexx Webhook] Calling fulfillment service at {} for transaction {}",
+        fulfillment_url, payment_id
+    );
 
-    info!("⚠️ WARNING: Payrexx webhook signature verification is NOT implemented!");
+    let response = HTTP_CLIENT
+        .post(&fulfillment_url)
+        .with_request_id() // Correlate the fulfillment logs with this webhook
+        .header("X-Internal-Auth-Secret", shared_secret)
+        .json(&payload)
+        .send()
+        .await
+        .map_err(|e| {
+            PayrexxError::WebhookProcessingError(format!(
+                "Error calling fulfillment service: {}",
+                e
+            ))
+        })?;
+    let status = response.status();
+    if !status.is_success() {
+        let err_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error from fulfillment service".to_string());
+        error!(
+            "[Payrexx Webhook] Fulfillment call for transaction {} failed: {} - {}",
+            payment_id, status, err_text
+        );
+        return Err(PayrexxError::WebhookProcessingError(format!(
+            "Fulfillment service call failed: {} - {}",
+            status, err_text
+        )));
+    }
+    info!(
+        "[Payrexx Webhook] Fulfillment for transaction {} triggered successfully.",
+        payment_id
+    );
     Ok(())
 }
 
-/// Processes a verified Payrexx webhook payload.
+/// Processes a verified Payrexx webhook payload, fulfilling confirmed transactions.
 pub async fn process_webhook(
     payload: PayrexxWebhookPayload,
-    // Add other dependencies if needed (e.g., database access, GCal client)
-    // db_pool: &SqlitePool,
+    app_config: &AppConfig,
 ) -> Result<(), PayrexxError> {
     info!("Processing webhook event type: {:?}", payload.event_type);
 
@@ -453,11 +1294,27 @@ pub async fn process_webhook(
                     "✅ Payment confirmed for reference: {:?}",
                     transaction.reference_id
                 );
-                // TODO: Implement actions for successful payment
+                events::publish(PaymentSucceeded {
+                    provider: "payrexx".to_string(),
+                    payment_id: transaction_id(&transaction),
+                    amount: transaction.amount,
+                    currency: transaction
+                        .invoice
+                        .as_ref()
+                        .and_then(|invoice| invoice.currency.clone()),
+                    reference: transaction_reference(&transaction).map(str::to_string),
+                });
+                trigger_fulfillment(app_config, &transaction).await?;
             }
             Some("waiting") => {
                 info!("⏳ Payment waiting for confirmation.");
             }
+            Some("authorized") | Some("reserved") => {
+                info!(
+                    "⏸️ Payment authorized for reference: {:?}, waiting for capture.",
+                    transaction.reference_id
+                );
+            }
             Some("cancelled") => {
                 info!(
                     "❌ Payment cancelled for reference: {:?}",
@@ -470,7 +1327,11 @@ pub async fn process_webhook(
                     "❌ Payment failed for reference: {:?}",
                     transaction.reference_id
                 );
-                // TODO: Update order status if needed.
+                events::publish(PaymentFailed {
+                    provider: "payrexx".to_string(),
+                    payment_id: transaction_id(&transaction),
+                    reason: transaction.status.clone(),
+                });
             }
             Some(other_status) => {
                 info!("ℹ️ Received unhandled transaction status: {}", other_status);
@@ -488,3 +1349,535 @@ pub async fn process_webhook(
 
 // Placeholder for service name or better reference ID generation
 const SERVICE_NAME: &str = "connectify_payrexx";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{api_object, to_form, Invoice};
+    use connectify_common::webhook_events::{WebhookEventRecord, WebhookEventStatus};
+    use serde_json::json;
+
+    #[test]
+    fn test_gateway_status() {
+        let body = json!({
+            "status": "success",
+            "data": [{
+                "id": 12345,
+                "status": "confirmed",
+                "hash": "a1b2c3",
+                "referenceId": "connectify-xxx-123",
+                "link": "https://instance.payrexx.com/?payment=a1b2c3",
+                "amount": 5000,
+                "currency": "CHF",
+                "invoices": [{ "transactions": [{ "id": 67890, "status": "confirmed" }] }]
+            }]
+        })
+        .to_string();
+
+        let gateway: Gateway = api_object("Gateway", reqwest::StatusCode::OK, &body).unwrap();
+        let status = PayrexxGatewayStatus::from(gateway);
+        assert_eq!(status.id, 12345);
+        assert_eq!(status.status, "confirmed");
+        assert_eq!(status.reference_id.as_deref(), Some("connectify-xxx-123"));
+        assert_eq!(status.transaction_ids, vec![67890]);
+    }
+
+    #[test]
+    fn test_transaction_status() {
+        let body = json!({
+            "status": "success",
+            "data": [{
+                "id": 67890,
+                "uuid": "f0e1d2",
+                "status": "confirmed",
+                "time": "2025-04-01 12:00:00",
+                "amount": 5000,
+                "refundable": true,
+                "contact": { "email": "anna@example.com" },
+                "invoice": { "currency": "CHF", "referenceId": "connectify-xxx-123" }
+            }]
+        })
+        .to_string();
+
+        let transaction: PayrexxWebhookTransaction =
+            api_object("Transaction", reqwest::StatusCode::OK, &body).unwrap();
+        let status = PayrexxTransactionStatus::from(transaction);
+        assert_eq!(status.id, Some(67890));
+        assert_eq!(status.currency.as_deref(), Some("CHF"));
+        assert_eq!(status.reference_id.as_deref(), Some("connectify-xxx-123"));
+        // The payer's contact data is not passed on
+        assert!(!serde_json::to_string(&status)
+            .unwrap()
+            .contains("anna@example.com"));
+    }
+
+    #[test]
+    fn test_pre_authorization() {
+        let request: CreateGatewayRequest =
+            serde_json::from_value(json!({ "pre_authorization": true })).unwrap();
+        assert!(request.pre_authorization);
+        let request: CreateGatewayRequest = serde_json::from_value(json!({})).unwrap();
+        assert!(!request.pre_authorization);
+    }
+
+    #[test]
+    fn test_payment_method_params() {
+        let mut config: PayrexxConfig = serde_json::from_value(json!({
+            "instance_name": "demo",
+            "success_url": "https://example.com/success",
+            "failed_url": "https://example.com/failed",
+            "cancel_url": "https://example.com/cancel"
+        }))
+        .unwrap();
+        let request: CreateGatewayRequest = serde_json::from_value(json!({})).unwrap();
+        // The instance's defaults
+        assert_eq!(payment_methods(&config, &request), (vec![], vec![]));
+
+        config.payment_methods = vec![
+            "TWINT".to_string(),
+            " postfinance-efinance ".to_string(),
+            "twint".to_string(),
+        ];
+        config.psp = vec![44];
+        assert_eq!(
+            payment_methods(&config, &request),
+            (
+                vec!["twint".to_string(), "postfinance-efinance".to_string()],
+                vec![44]
+            )
+        );
+
+        let request: CreateGatewayRequest =
+            serde_json::from_value(json!({ "payment_methods": ["postfinance-card"], "psp": [] }))
+                .unwrap();
+        let price = PaymentPrice {
+            amount: 5000,
+            currency: "CHF".to_string(),
+            purpose: "Payment".to_string(),
+        };
+        let form = to_form(&gateway_params(
+            &config,
+            &request,
+            price,
+            "ord_1".to_string(),
+        ))
+        .unwrap();
+        assert_eq!(
+            serde_urlencoded::to_string(
+                form.iter()
+                    .filter(|(name, _)| name.starts_with("pm") || name.starts_with("psp"))
+                    .collect::<Vec<_>>()
+            )
+            .unwrap(),
+            "pm%5B0%5D=postfinance-card"
+        );
+    }
+
+    #[test]
+    fn test_payment_price() {
+        let config: PayrexxConfig = serde_json::from_value(json!({
+            "instance_name": "demo",
+            "success_url": "https://example.com/success",
+            "failed_url": "https://example.com/failed",
+            "cancel_url": "https://example.com/cancel",
+            "unit_amount": 2000
+        }))
+        .unwrap();
+        let pricing_config: PricingConfig = serde_json::from_value(json!({
+            "default_currency": "CHF",
+            "price_tiers": [
+                { "duration_minutes": 30, "unit_amount": 4000 },
+                { "duration_minutes": 60, "unit_amount": 7500, "product_name": "Beratung (60 Min)",
+                  "amounts": { "EUR": 8000 } }
+            ]
+        }))
+        .unwrap();
+        let booking = json!({
+            "start_time": "2025-05-15T10:00:00Z",
+            "end_time": "2025-05-15T11:00:00Z",
+            "summary": "Beratung"
+        });
+        let request = |currency: Option<&'static str>| PriceRequest {
+            fulfillment_type: Some("gcal_booking"),
+            fulfillment_data: Some(&booking),
+            // A client-side amount doesn't undercut the tier
+            amount_override: Some(1),
+            currency_override: currency,
+            purpose_override: None,
+        };
+
+        assert_eq!(
+            payment_price(&config, &pricing_config, request(None)).unwrap(),
+            PaymentPrice {
+                amount: 7500,
+                currency: "CHF".to_string(),
+                purpose: "Beratung (60 Min)".to_string(),
+            }
+        );
+        let eur = payment_price(&config, &pricing_config, request(Some("eur"))).unwrap();
+        assert_eq!((eur.amount, eur.currency.as_str()), (8000, "EUR"));
+        assert!(matches!(
+            payment_price(&config, &pricing_config, request(Some("USD"))),
+            Err(PayrexxError::PricingError(_))
+        ));
+
+        let short =
+            json!({ "start_time": "2025-05-15T10:00:00Z", "end_time": "2025-05-15T10:45:00Z" });
+        let unpriced = PriceRequest {
+            fulfillment_data: Some(&short),
+            ..request(None)
+        };
+        assert!(matches!(
+            payment_price(&config, &pricing_config, unpriced),
+            Err(PayrexxError::PricingError(msg)) if msg.contains("45 minutes")
+        ));
+
+        // Payments without a booking keep the static amount
+        let static_price = payment_price(
+            &config,
+            &pricing_config,
+            PriceRequest {
+                fulfillment_type: None,
+                fulfillment_data: None,
+                amount_override: None,
+                currency_override: None,
+                purpose_override: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(static_price.amount, 2000);
+        assert_eq!(static_price.purpose, "Payment");
+    }
+
+    #[test]
+    fn test_qr_invoice() {
+        let mut config: PayrexxConfig = serde_json::from_value(json!({
+            "instance_name": "demo",
+            "success_url": "https://example.com/success",
+            "failed_url": "https://example.com/failed",
+            "cancel_url": "https://example.com/cancel",
+            "currency": "CHF"
+        }))
+        .unwrap();
+        let request = CreateQrInvoiceRequest {
+            amount_override: Some(7500),
+            purpose_override: Some("Beratung".to_string()),
+            ..Default::default()
+        };
+        let price = PaymentPrice {
+            amount: 7500,
+            currency: "CHF".to_string(),
+            purpose: "Beratung".to_string(),
+        };
+        let form = to_form(&qr_invoice_params(&config, &request, price, "ord_1")).unwrap();
+        let field = |form: &[(String, String)], key: &str| {
+            form.iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(field(&form, "amount").as_deref(), Some("7500"));
+        assert_eq!(field(&form, "currency").as_deref(), Some("CHF"));
+        assert_eq!(field(&form, "referenceId").as_deref(), Some("ord_1"));
+        assert_eq!(
+            field(&form, "pm[0]").as_deref(),
+            Some(DEFAULT_QR_BILL_PAYMENT_METHOD)
+        );
+        config.qr_bill_payment_method = Some("qr-invoice".to_string());
+        let price = PaymentPrice {
+            amount: 7500,
+            currency: "CHF".to_string(),
+            purpose: "Beratung".to_string(),
+        };
+        let form = to_form(&qr_invoice_params(&config, &request, price, "ord_1")).unwrap();
+        assert_eq!(field(&form, "pm[0]").as_deref(), Some("qr-invoice"));
+
+        let body = json!({
+            "status": "success",
+            "data": [{ "id": 4711, "link": "https://demo.payrexx.com/?payment=a1b2c3" }]
+        })
+        .to_string();
+        let invoice: Invoice = api_object("Invoice", reqwest::StatusCode::OK, &body).unwrap();
+        let response = QrInvoiceResponse {
+            invoice_id: invoice.id,
+            url: invoice.link,
+            reference_id: "ord_1".to_string(),
+        };
+        let payload = invoice_fulfillment_payload(
+            Some(&json!({ "start_time": "2025-05-15T10:00:00Z" })),
+            &response,
+        )
+        .unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(payload["start_time"], "2025-05-15T10:00:00Z");
+        assert_eq!(payload["payment_method"], "payrexx");
+        assert_eq!(payload["payrexx_invoice_id"], 4711);
+        assert_eq!(
+            payload["invoice_url"],
+            "https://demo.payrexx.com/?payment=a1b2c3"
+        );
+        assert!(invoice_fulfillment_payload(Some(&json!([1])), &response).is_err());
+    }
+
+    #[test]
+    fn test_webhook_event_id() {
+        let payload = |transaction: serde_json::Value| -> PayrexxWebhookPayload {
+            serde_json::from_value(json!({ "transaction": transaction })).unwrap()
+        };
+        assert_eq!(
+            webhook_event_id(&payload(json!({ "id": 67890, "status": "confirmed" }))).as_deref(),
+            Some("67890:confirmed")
+        );
+        // A capture announces the same transaction again with another status
+        assert_eq!(
+            webhook_event_id(&payload(json!({ "id": 67890, "status": "authorized" }))).as_deref(),
+            Some("67890:authorized")
+        );
+        assert_eq!(webhook_event_id(&payload(json!({}))), None);
+        let empty: PayrexxWebhookPayload = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(webhook_event_id(&empty), None);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_fulfillment() {
+        let order = FulfillmentOrder::new(
+            "gcal_booking",
+            json!({ "summary": "Beratung", "invoice_url": "https://demo.payrexx.com/?payment=a1" })
+                .to_string(),
+            Utc::now(),
+        );
+        let order_id = order.order_id.clone();
+        save_fulfillment_order(order).await.unwrap();
+
+        // The gateway's reference is on the invoice
+        let transaction: PayrexxWebhookTransaction = serde_json::from_value(json!({
+            "id": 67890,
+            "status": "confirmed",
+            "amount": 7500,
+            "invoice": { "referenceId": order_id }
+        }))
+        .unwrap();
+        let (ff_type, ff_data) = transaction_fulfillment(&transaction)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(ff_type, "gcal_booking");
+        let payload = fulfillment_payload(&transaction, &ff_data).unwrap();
+        assert_eq!(payload["summary"], "Beratung");
+        assert_eq!(payload["payment_id"], "67890");
+        assert_eq!(payload["payment_method"], "payrexx");
+        assert_eq!(payload["payment_amount"], 7500);
+        assert_eq!(
+            payload["invoice_url"],
+            "https://demo.payrexx.com/?payment=a1"
+        );
+
+        // Custom fields, e.g. of a gateway created in the dashboard
+        let transaction: PayrexxWebhookTransaction = serde_json::from_value(json!({
+            "id": 67891,
+            "referenceId": "connectify-dashboard-1",
+            "invoice": { "custom_fields": [
+                { "name": "ff_type", "value": "adhoc_gcal_twilio" },
+                { "name": "ff_data_json", "value": "{\"duration_minutes\":30}" }
+            ] }
+        }))
+        .unwrap();
+        assert_eq!(
+            transaction_fulfillment(&transaction).await.unwrap(),
+            Some((
+                "adhoc_gcal_twilio".to_string(),
+                "{\"duration_minutes\":30}".to_string()
+            ))
+        );
+
+        let transaction: PayrexxWebhookTransaction =
+            serde_json::from_value(json!({ "id": 67892, "referenceId": "unknown" })).unwrap();
+        assert_eq!(transaction_fulfillment(&transaction).await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_list_transactions_query() {
+        let query: ListTransactionsAdminQuery = serde_json::from_value(json!({
+            "created_from": "2025-07-01",
+            "created_to": "2025-07-31",
+            "status": "confirmed",
+            "reference_id": "ord_1"
+        }))
+        .unwrap();
+        let page = CursorQuery {
+            limit: Some(20),
+            cursor: Some("40".to_string()),
+            order: None,
+        };
+        let params = query.payrexx_params(&page).unwrap();
+        assert_eq!(params.offset, Some(40));
+        // Filtering by status lists as many transactions as a page may hold
+        assert_eq!(params.limit, Some(MAX_PAGE_SIZE));
+        assert_eq!(params.order_by_time.as_deref(), Some("DESC"));
+        // Payrexx compares exclusively, the bounds include both days
+        assert_eq!(params.created_after.as_deref(), Some("2025-06-30 23:59:59"));
+        assert_eq!(
+            params.created_before.as_deref(),
+            Some("2025-08-01 00:00:00")
+        );
+
+        let transaction = |status: &str, reference: &str| -> PayrexxWebhookTransaction {
+            serde_json::from_value(json!({
+                "id": 1,
+                "status": status,
+                "invoice": { "referenceId": reference }
+            }))
+            .unwrap()
+        };
+        assert!(query.matches(&transaction("confirmed", "ord_1")));
+        assert!(!query.matches(&transaction("refunded", "ord_1")));
+        assert!(!query.matches(&transaction("confirmed", "ord_2")));
+        assert!(ListTransactionsAdminQuery::default().matches(&transaction("waiting", "ord_2")));
+
+        let oldest_first = CursorQuery {
+            order: Some(SortOrder::Asc),
+            ..Default::default()
+        };
+        let params = ListTransactionsAdminQuery::default()
+            .payrexx_params(&oldest_first)
+            .unwrap();
+        assert_eq!(params.offset, None);
+        assert_eq!(params.limit, Some(26));
+        assert_eq!(params.order_by_time.as_deref(), Some("ASC"));
+
+        for invalid in [
+            json!({ "created_from": "01.07.2025" }),
+            json!({ "created_from": "2025-07-31", "created_to": "2025-07-01" }),
+        ] {
+            let query: ListTransactionsAdminQuery = serde_json::from_value(invalid).unwrap();
+            assert!(matches!(
+                query.payrexx_params(&CursorQuery::default()),
+                Err(PayrexxError::InvalidTransactionFilter(_))
+            ));
+        }
+        let invalid_cursor = CursorQuery {
+            cursor: Some("ord_1".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ListTransactionsAdminQuery::default().payrexx_params(&invalid_cursor),
+            Err(PayrexxError::InvalidTransactionFilter(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_matching_transactions() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let transactions = |ids: std::ops::RangeInclusive<i64>, confirmed: &[i64]| {
+            let data: Vec<_> = ids
+                .map(|id| {
+                    let status = if confirmed.contains(&id) {
+                        "confirmed"
+                    } else {
+                        "waiting"
+                    };
+                    json!({ "id": id, "status": status })
+                })
+                .collect();
+            ResponseTemplate::new(200).set_body_json(json!({ "status": "success", "data": data }))
+        };
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/Transaction/"))
+            .and(query_param("offset", "100"))
+            .respond_with(transactions(101..=103, &[101]))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/Transaction/"))
+            .respond_with(transactions(1..=100, &[50, 100]))
+            .mount(&server)
+            .await;
+        let client = PayrexxClient::new("demo", "secret").with_base_url(server.uri());
+        let query = ListTransactionsAdminQuery {
+            status: Some("confirmed".to_string()),
+            ..Default::default()
+        };
+        let page = |limit: u32| CursorQuery {
+            limit: Some(limit),
+            ..Default::default()
+        };
+
+        // The first Payrexx page holds two matches, the third is on the next one
+        let full = list_matching_transactions(&client, &query, &page(2))
+            .await
+            .unwrap();
+        let ids: Vec<_> = full
+            .items
+            .iter()
+            .map(|transaction| transaction.id)
+            .collect();
+        assert_eq!(ids, vec![Some(50), Some(100)]);
+        assert!(full.has_more);
+        assert_eq!(full.next_cursor.as_deref(), Some("100"));
+
+        let last = list_matching_transactions(&client, &query, &page(5))
+            .await
+            .unwrap();
+        assert_eq!(last.items.len(), 3);
+        assert!(!last.has_more);
+        assert_eq!(last.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_booking() {
+        let order = FulfillmentOrder::new(
+            "gcal_booking",
+            json!({ "summary": "Beratung" }).to_string(),
+            Utc::now(),
+        );
+        let order_id = order.order_id.clone();
+        save_fulfillment_order(order).await.unwrap();
+        let transaction: PayrexxWebhookTransaction = serde_json::from_value(json!({
+            "id": 77001,
+            "status": "confirmed",
+            "invoice": { "referenceId": order_id }
+        }))
+        .unwrap();
+
+        // Not fulfilled before the confirmed webhook arrives
+        let booking = transaction_booking(&transaction).await.unwrap();
+        assert_eq!(booking.fulfillment_type, "gcal_booking");
+        assert_eq!(
+            booking.fulfillment_data,
+            Some(json!({ "summary": "Beratung" }))
+        );
+        assert_eq!(booking.fulfillment_status, None);
+
+        let store = webhook_event_store();
+        store
+            .record(WebhookEventRecord::received(
+                PAYREXX_PROVIDER,
+                "77001:confirmed",
+                "transaction",
+                "{}",
+                Utc::now(),
+            ))
+            .await
+            .unwrap();
+        store
+            .finish(
+                PAYREXX_PROVIDER,
+                "77001:confirmed",
+                WebhookEventStatus::Processed,
+                None,
+                Utc::now(),
+            )
+            .await
+            .unwrap();
+        let booking = transaction_booking(&transaction).await.unwrap();
+        assert_eq!(booking.fulfillment_status.as_deref(), Some("processed"));
+
+        // Transactions without a local order have no booking
+        let transaction: PayrexxWebhookTransaction =
+            serde_json::from_value(json!({ "id": 77002, "referenceId": "unknown" })).unwrap();
+        assert_eq!(transaction_booking(&transaction).await, None);
+    }
+}
@@ -4,15 +4,24 @@ use axum::{
     routing::{get, post},
     Router,
 };
+use connectify_common::api_key::ApiKeyAuthLayer;
+use connectify_common::idempotency::IdempotencyLayer;
+use connectify_common::runtime_flags::{feature_guard, CHECKOUT};
 use connectify_config::AppConfig;
 use std::sync::Arc; // Need AppConfig for state
-                    // Removed: use reqwest::Client; // No longer needed as parameter
-                    // Import the handler function and the specific state struct it needs
+use tracing::warn;
+// Removed: use reqwest::Client; // No longer needed as parameter
+// Import the handler function and the specific state struct it needs
 use crate::handlers::{
-    create_gateway_handler, payrexx_cancel_handler, payrexx_failure_handler,
-    payrexx_success_handler, payrexx_webhook_handler, PayrexxState,
+    admin_list_transactions_handler, capture_transaction_handler, create_gateway_handler,
+    create_qr_invoice_handler, get_gateway_handler, get_transaction_handler,
+    payrexx_cancel_handler, payrexx_failure_handler, payrexx_success_handler,
+    payrexx_webhook_handler, refund_transaction_handler, PayrexxState,
 };
 
+/// Scope an API key needs to list, capture and refund transactions.
+pub const ADMIN_SCOPE: &str = "admin";
+
 /// Creates a router containing all routes for the Payrexx feature.
 /// Initializes and applies the necessary PayrexxState.
 ///
@@ -28,6 +37,8 @@ pub fn routes(
 ) -> Router {
     // Return concrete Router, state applied internally
 
+    let admin_auth = ApiKeyAuthLayer::from_config(&config);
+
     // Create the specific state needed for Payrexx handlers
     // It only needs AppConfig now, as the client is static in logic.rs
     let payrexx_state = Arc::new(PayrexxState {
@@ -35,14 +46,60 @@ pub fn routes(
                 // Removed http_client field
     });
 
-    Router::new()
+    let mut router = Router::new()
         // API endpoint called by our frontend to create the payment link
-        .route("/payrexx/create-gateway", post(create_gateway_handler))
+        .route(
+            "/payrexx/create-gateway",
+            post(create_gateway_handler).layer((feature_guard(CHECKOUT), IdempotencyLayer::new())),
+        )
+        // QR-bill invoices for customers paying by bank transfer
+        .route(
+            "/payrexx/invoices",
+            post(create_qr_invoice_handler)
+                .layer((feature_guard(CHECKOUT), IdempotencyLayer::new())),
+        )
+        // Status lookups, polled by the frontend and reconciliation jobs
+        .route("/payrexx/gateways/{gateway_id}", get(get_gateway_handler))
+        .route(
+            "/payrexx/transactions/{transaction_id}",
+            get(get_transaction_handler),
+        )
         // API endpoint called by Payrexx SERVER for webhook notifications
         .route("/payrexx/webhook", post(payrexx_webhook_handler))
         // Routes for USER BROWSER redirects (typically GET)
         .route("/payrexx/webhook/success", get(payrexx_success_handler)) // <-- Use GET and correct handler
         .route("/payrexx/webhook/failure", get(payrexx_failure_handler)) // <-- Use GET and correct handler
-        .route("/payrexx/webhook/cancel", get(payrexx_cancel_handler)) // <-- Add route for cancel handler
-        .with_state(payrexx_state) // Apply the specific state to this router fragment
+        .route("/payrexx/webhook/cancel", get(payrexx_cancel_handler)); // <-- Add route for cancel handler
+
+    // Captures and refunds move money, and listings show all payments, so they are only
+    // exposed to admin API keys
+    match admin_auth {
+        Some(admin_auth) => {
+            let admin_auth = admin_auth.with_scope(ADMIN_SCOPE);
+            router = router
+                .route(
+                    "/payrexx/transactions/{transaction_id}/capture",
+                    post(capture_transaction_handler).layer((
+                        admin_auth.clone(),
+                        IdempotencyLayer::new(),
+                    )),
+                )
+                .route(
+                    "/payrexx/transactions/{transaction_id}/refund",
+                    post(refund_transaction_handler).layer((
+                        admin_auth.clone(),
+                        IdempotencyLayer::new(),
+                    )),
+                )
+                .route(
+                    "/admin/payrexx/transactions",
+                    get(admin_list_transactions_handler).layer(admin_auth.clone()),
+                );
+        }
+        None => warn!(
+            "No API keys configured, /payrexx/transactions/{{id}}/capture, /payrexx/transactions/{{id}}/refund and /admin/payrexx/transactions are disabled"
+        ),
+    }
+
+    router.with_state(payrexx_state) // Apply the specific state to this router fragment
 }
@@ -0,0 +1,691 @@
+// --- File: crates/connectify_stripe/src/client.rs ---
+//! A typed client for the Stripe REST API.
+//!
+//! Stripe expects request parameters form encoded, with nested objects and lists in bracket
+//! notation, e.g. `line_items[0][price_data][currency]=chf`. [`StripeClient`] serializes typed
+//! parameter structs into that format, sends the request with retries and metrics, and maps
+//! error responses to [`StripeError::ApiError`] with Stripe's error message.
+
+use crate::error::StripeError;
+use connectify_common::metrics::observe_external_call;
+use connectify_common::request_id::RequestIdExt;
+use connectify_common::retry::{send_with_retry, RetryPolicy};
+use connectify_common::HTTP_CLIENT;
+use once_cell::sync::Lazy;
+use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::sync::RwLock;
+use tracing::{error, info};
+
+/// Base URL of the Stripe API.
+const STRIPE_API_BASE: &str = "https://api.stripe.com/v1";
+
+/// The base URL new clients send their requests to.
+static API_BASE_URL: Lazy<RwLock<String>> = Lazy::new(|| RwLock::new(STRIPE_API_BASE.to_string()));
+
+/// Send the requests of new clients to another base URL, e.g. stripe-mock, or to the Stripe
+/// API again with `None`.
+pub fn configure_api_base_url(base_url: Option<&str>) {
+    let base_url = base_url.unwrap_or(STRIPE_API_BASE);
+    if base_url != STRIPE_API_BASE {
+        info!(
+            "[Stripe Client] Sending Stripe API requests to {}",
+            base_url
+        );
+    }
+    *API_BASE_URL.write().unwrap_or_else(|e| e.into_inner()) = base_url.to_string();
+}
+
+/// The base URL new clients send their requests to.
+fn api_base_url() -> String {
+    API_BASE_URL
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Parameters of a new Checkout Session.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CheckoutSessionParams {
+    /// Left out to offer the payment methods enabled in the dashboard
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub payment_method_types: Vec<String>,
+    pub mode: String,
+    pub success_url: String,
+    pub cancel_url: String,
+    pub line_items: Vec<CheckoutLineItem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_reference_id: Option<String>,
+    /// The existing customer (cus_...) paying, so their payments are linked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    /// Settings of the subscription created in `subscription` mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription_data: Option<SubscriptionData>,
+    /// Lets Stripe calculate taxes from the customer's address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub automatic_tax: Option<AutomaticTax>,
+    /// `auto` or `required`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billing_address_collection: Option<String>,
+    /// Which details entered in the checkout are saved to an existing `customer`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer_update: Option<CustomerUpdate>,
+    /// Unix timestamp at which the session expires unless paid, 30 minutes to 24 hours ahead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    /// Creates an invoice for the payment in `payment` mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invoice_creation: Option<InvoiceCreation>,
+}
+
+/// Invoicing of the payment of a Checkout Session.
+#[derive(Serialize, Debug, Clone)]
+pub struct InvoiceCreation {
+    pub enabled: bool,
+}
+
+/// Filters for listing Checkout Sessions.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CheckoutSessionListQuery {
+    /// `open`, `complete` or `expired`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<CreatedFilter>,
+    /// Only sessions whose customer entered this email address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer_details: Option<CustomerDetailsFilter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starting_after: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ending_before: Option<String>,
+}
+
+/// Filter on the creation time of listed objects.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CreatedFilter {
+    /// Created at or after this Unix timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gte: Option<i64>,
+    /// Created at or before this Unix timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lte: Option<i64>,
+}
+
+/// Filter on the customer details of listed Checkout Sessions.
+#[derive(Serialize, Debug, Clone)]
+pub struct CustomerDetailsFilter {
+    pub email: String,
+}
+
+/// Tax calculation of a Checkout Session.
+#[derive(Serialize, Debug, Clone)]
+pub struct AutomaticTax {
+    pub enabled: bool,
+}
+
+/// Details of an existing customer updated by a Checkout Session.
+#[derive(Serialize, Debug, Clone)]
+pub struct CustomerUpdate {
+    /// `auto` saves the address entered in the checkout, which tax calculation needs
+    pub address: String,
+}
+
+/// A line item of a Checkout Session, either with an existing price or an ad-hoc one.
+#[derive(Serialize, Debug, Clone)]
+pub struct CheckoutLineItem {
+    /// The id of an existing price (price_...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price_data: Option<PriceData>,
+    pub quantity: u32,
+}
+
+/// Settings of a subscription created by a Checkout Session.
+#[derive(Serialize, Debug, Clone)]
+pub struct SubscriptionData {
+    /// Metadata stored on the subscription, and sent with its events
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// An ad-hoc price of a line item.
+#[derive(Serialize, Debug, Clone)]
+pub struct PriceData {
+    pub currency: String,
+    pub product_data: ProductData,
+    /// Amount in the smallest currency unit
+    pub unit_amount: i64,
+    /// `inclusive` or `exclusive` of taxes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax_behavior: Option<String>,
+}
+
+/// An ad-hoc product of a line item.
+#[derive(Serialize, Debug, Clone)]
+pub struct ProductData {
+    pub name: String,
+}
+
+/// Parameters of a new refund.
+#[derive(Serialize, Debug, Clone)]
+pub struct RefundParams {
+    pub payment_intent: String,
+    /// Amount in the smallest currency unit; the whole remaining amount if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Parameters of a new customer.
+#[derive(Serialize, Debug, Clone)]
+pub struct CustomerParams {
+    pub email: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// Filter for listing customers.
+#[derive(Serialize, Debug, Clone)]
+pub struct CustomerListQuery {
+    /// Case-sensitive email address of the customers
+    pub email: String,
+    pub limit: u8,
+}
+
+/// A customer.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Customer {
+    pub id: String,
+    pub email: Option<String>,
+    pub name: Option<String>,
+}
+
+/// Parameters of a new SetupIntent, which saves a card of a customer for later payments.
+#[derive(Serialize, Debug, Clone)]
+pub struct SetupIntentParams {
+    pub customer: String,
+    pub payment_method_types: Vec<String>,
+    /// `off_session` to charge the card while the customer is not present
+    pub usage: String,
+}
+
+/// Parameters of a new Billing Portal session.
+#[derive(Serialize, Debug, Clone)]
+pub struct BillingPortalSessionParams {
+    pub customer: String,
+    /// Page the portal links back to; the portal's default in the dashboard if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_url: Option<String>,
+}
+
+/// Filter for listing the saved payment methods of a customer.
+#[derive(Serialize, Debug, Clone)]
+pub struct PaymentMethodListQuery {
+    pub customer: String,
+    #[serde(rename = "type")]
+    pub method_type: String,
+    pub limit: u8,
+}
+
+/// Parameters of a new PaymentIntent.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct PaymentIntentParams {
+    /// Amount in the smallest currency unit
+    pub amount: i64,
+    pub currency: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_method: Option<String>,
+    /// Lets the Payment Element offer the payment methods enabled in the dashboard
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub automatic_payment_methods: Option<AutomaticPaymentMethods>,
+    /// Whether the customer is not present, e.g. for a repeat booking with a saved card
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub off_session: Option<bool>,
+    /// Whether to charge right away
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirm: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Payment methods chosen by Stripe for a PaymentIntent.
+#[derive(Serialize, Debug, Clone)]
+pub struct AutomaticPaymentMethods {
+    pub enabled: bool,
+}
+
+/// A SetupIntent.
+#[derive(Deserialize, Debug)]
+pub struct SetupIntent {
+    pub id: String,
+    /// Secret for confirming the SetupIntent with Stripe.js in the frontend
+    pub client_secret: Option<String>,
+    pub status: String,
+}
+
+/// A saved payment method.
+#[derive(Deserialize, Debug, Clone)]
+pub struct PaymentMethod {
+    pub id: String,
+    pub card: Option<Card>,
+}
+
+/// The card of a payment method.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Card {
+    pub brand: String,
+    pub last4: String,
+    pub exp_month: u32,
+    pub exp_year: u32,
+}
+
+/// A PaymentIntent.
+#[derive(Deserialize, Debug)]
+pub struct PaymentIntent {
+    pub id: String,
+    /// e.g. "succeeded", "processing" or "requires_action"
+    pub status: String,
+    pub amount: i64,
+    pub currency: String,
+    pub client_secret: Option<String>,
+}
+
+/// A Billing Portal session.
+#[derive(Deserialize, Debug)]
+pub struct BillingPortalSession {
+    pub id: String,
+    /// Short-lived URL of the portal
+    pub url: String,
+}
+
+/// A newly created Checkout Session.
+#[derive(Deserialize, Debug)]
+pub struct CreatedCheckoutSession {
+    pub id: String,
+    pub url: Option<String>,
+}
+
+/// An invoice.
+#[derive(Deserialize, Debug)]
+pub struct Invoice {
+    pub id: String,
+    pub status: Option<String>,
+    /// Set once the invoice is finalized
+    pub number: Option<String>,
+    /// Page where the customer can view and download the invoice
+    pub hosted_invoice_url: Option<String>,
+    /// URL of the invoice PDF
+    pub invoice_pdf: Option<String>,
+}
+
+/// A refund.
+#[derive(Deserialize, Debug)]
+pub struct Refund {
+    pub id: String,
+    pub status: Option<String>,
+    pub amount: i64,
+    pub currency: String,
+}
+
+/// Client for the Stripe REST API.
+#[derive(Clone)]
+pub struct StripeClient {
+    secret_key: String,
+    base_url: String,
+    retry_policy: RetryPolicy,
+}
+
+impl StripeClient {
+    /// Create a client authenticating with the given secret key, sending its requests to the
+    /// configured base URL (see [`configure_api_base_url`]).
+    pub fn new(secret_key: impl Into<String>) -> Self {
+        Self {
+            secret_key: secret_key.into(),
+            base_url: api_base_url(),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Create a client with the secret key in the `STRIPE_SECRET_KEY` environment variable.
+    pub fn from_env() -> Result<Self, StripeError> {
+        env::var("STRIPE_SECRET_KEY")
+            .map(Self::new)
+            .map_err(|_| StripeError::ConfigError)
+    }
+
+    /// Send requests to another base URL, e.g. a mock server.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Retrieve an object, e.g. `checkout/sessions/cs_...`.
+    pub async fn get<R: DeserializeOwned>(
+        &self,
+        operation: &str,
+        path: &str,
+    ) -> Result<R, StripeError> {
+        self.send(operation, HTTP_CLIENT.get(self.url(path))).await
+    }
+
+    /// List objects, filtered by the given query parameters.
+    pub async fn list<Q: Serialize, R: DeserializeOwned>(
+        &self,
+        operation: &str,
+        path: &str,
+        query: &Q,
+    ) -> Result<R, StripeError> {
+        let query = to_form(query)?;
+        self.send(operation, HTTP_CLIENT.get(self.url(path)).query(&query))
+            .await
+    }
+
+    /// Create an object.
+    ///
+    /// Each call sends a new `Idempotency-Key`, so that Stripe returns the original object
+    /// instead of creating another one when a request is retried.
+    pub async fn post<P: Serialize, R: DeserializeOwned>(
+        &self,
+        operation: &str,
+        path: &str,
+        params: &P,
+    ) -> Result<R, StripeError> {
+        let form = to_form(params)?;
+        let request = HTTP_CLIENT
+            .post(self.url(path))
+            .header("Idempotency-Key", uuid::Uuid::new_v4().to_string())
+            .form(&form);
+        self.send(operation, request).await
+    }
+
+    async fn send<R: DeserializeOwned>(
+        &self,
+        operation: &str,
+        request: RequestBuilder,
+    ) -> Result<R, StripeError> {
+        let response = observe_external_call(
+            "stripe",
+            operation,
+            send_with_retry(
+                &self.retry_policy,
+                request
+                    .with_request_id()
+                    .basic_auth(&self.secret_key, None::<&str>),
+            ),
+        )
+        .await?;
+
+        let status = response.status();
+        let body_text = response.text().await?;
+        if status.is_success() {
+            return Ok(serde_json::from_str(&body_text)?);
+        }
+
+        let message = error_message(body_text);
+        error!(
+            "[Stripe Client] {} failed with HTTP status {}: {}",
+            operation, status, message
+        );
+        Err(StripeError::ApiError {
+            status_code: status.as_u16(),
+            message,
+        })
+    }
+}
+
+/// The message of a Stripe error response, or the whole body if it has none.
+fn error_message(body_text: String) -> String {
+    serde_json::from_str::<Value>(&body_text)
+        .ok()
+        .and_then(|json_body| {
+            json_body
+                .get("error")
+                .and_then(|e| e.get("message"))
+                .and_then(|m| m.as_str())
+                .map(String::from)
+        })
+        .unwrap_or(body_text)
+}
+
+/// Serialize parameters into form fields in Stripe's bracket notation.
+///
+/// Unset (`null`) values are left out.
+pub fn to_form<P: Serialize>(params: &P) -> Result<Vec<(String, String)>, StripeError> {
+    let mut fields = Vec::new();
+    match serde_json::to_value(params)? {
+        Value::Object(object) => {
+            for (key, value) in object {
+                flatten_field(key, value, &mut fields);
+            }
+        }
+        Value::Null => {}
+        other => {
+            return Err(StripeError::InternalError(format!(
+                "Stripe parameters must be an object, got {}",
+                other
+            )))
+        }
+    }
+    Ok(fields)
+}
+
+fn flatten_field(key: String, value: Value, fields: &mut Vec<(String, String)>) {
+    match value {
+        Value::Null => {}
+        Value::Object(object) => {
+            for (nested_key, nested_value) in object {
+                flatten_field(format!("{}[{}]", key, nested_key), nested_value, fields);
+            }
+        }
+        Value::Array(values) => {
+            for (index, nested_value) in values.into_iter().enumerate() {
+                flatten_field(format!("{}[{}]", key, index), nested_value, fields);
+            }
+        }
+        Value::String(value) => fields.push((key, value)),
+        other => fields.push((key, other.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_form() {
+        let params = CheckoutSessionParams {
+            payment_method_types: vec!["card".to_string()],
+            mode: "payment".to_string(),
+            success_url: "https://example.com/success".to_string(),
+            cancel_url: "https://example.com/cancel".to_string(),
+            line_items: vec![CheckoutLineItem {
+                price: None,
+                price_data: Some(PriceData {
+                    currency: "chf".to_string(),
+                    product_data: ProductData {
+                        name: "Consultation".to_string(),
+                    },
+                    unit_amount: 5000,
+                    tax_behavior: None,
+                }),
+                quantity: 1,
+            }],
+            client_reference_id: None,
+            customer: None,
+            metadata: BTreeMap::from([("ff_type".to_string(), "gcal_booking".to_string())]),
+            subscription_data: None,
+            automatic_tax: None,
+            billing_address_collection: None,
+            customer_update: None,
+            expires_at: None,
+            invoice_creation: None,
+        };
+
+        let form = to_form(&params).unwrap();
+        let field = |key: &str| {
+            form.iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(field("payment_method_types[0]"), Some("card"));
+        assert_eq!(field("line_items[0][price_data][currency]"), Some("chf"));
+        assert_eq!(
+            field("line_items[0][price_data][product_data][name]"),
+            Some("Consultation")
+        );
+        assert_eq!(
+            field("line_items[0][price_data][unit_amount]"),
+            Some("5000")
+        );
+        assert_eq!(field("line_items[0][quantity]"), Some("1"));
+        assert_eq!(field("metadata[ff_type]"), Some("gcal_booking"));
+        assert_eq!(field("client_reference_id"), None);
+        assert_eq!(form.len(), 9);
+
+        let subscription = CheckoutSessionParams {
+            mode: "subscription".to_string(),
+            line_items: vec![CheckoutLineItem {
+                price: Some("price_coaching".to_string()),
+                price_data: None,
+                quantity: 1,
+            }],
+            subscription_data: Some(SubscriptionData {
+                metadata: BTreeMap::from([("reference".to_string(), "order-1".to_string())]),
+            }),
+            ..params
+        };
+        let form = to_form(&subscription).unwrap();
+        assert!(form.contains(&(
+            "line_items[0][price]".to_string(),
+            "price_coaching".to_string()
+        )));
+        assert!(form.contains(&(
+            "subscription_data[metadata][reference]".to_string(),
+            "order-1".to_string()
+        )));
+        assert!(!form.iter().any(|(name, _)| name.contains("price_data")));
+
+        let returning = CheckoutSessionParams {
+            customer: Some("cus_123".to_string()),
+            invoice_creation: Some(InvoiceCreation { enabled: true }),
+            automatic_tax: Some(AutomaticTax { enabled: true }),
+            customer_update: Some(CustomerUpdate {
+                address: "auto".to_string(),
+            }),
+            ..subscription
+        };
+        let form = to_form(&returning).unwrap();
+        assert!(form.contains(&("customer".to_string(), "cus_123".to_string())));
+        assert!(form.contains(&("automatic_tax[enabled]".to_string(), "true".to_string())));
+        assert!(form.contains(&("customer_update[address]".to_string(), "auto".to_string())));
+        assert!(form.contains(&("invoice_creation[enabled]".to_string(), "true".to_string())));
+
+        let refund = RefundParams {
+            payment_intent: "pi_123".to_string(),
+            amount: None,
+            reason: Some("duplicate".to_string()),
+            metadata: BTreeMap::new(),
+        };
+        assert_eq!(
+            to_form(&refund).unwrap(),
+            vec![
+                ("payment_intent".to_string(), "pi_123".to_string()),
+                ("reason".to_string(), "duplicate".to_string())
+            ]
+        );
+
+        let expired = CheckoutSessionListQuery {
+            status: Some("expired".to_string()),
+            created: Some(CreatedFilter {
+                gte: Some(1700000000),
+                lte: None,
+            }),
+            limit: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(
+            to_form(&expired).unwrap(),
+            vec![
+                ("created[gte]".to_string(), "1700000000".to_string()),
+                ("limit".to_string(), "100".to_string()),
+                ("status".to_string(), "expired".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_off_session_payment_form() {
+        let params = PaymentIntentParams {
+            amount: 5000,
+            currency: "chf".to_string(),
+            customer: Some("cus_123".to_string()),
+            payment_method: Some("pm_123".to_string()),
+            off_session: Some(true),
+            confirm: Some(true),
+            ..Default::default()
+        };
+        let form = to_form(&params).unwrap();
+        assert!(form.contains(&("off_session".to_string(), "true".to_string())));
+        assert!(form.contains(&("confirm".to_string(), "true".to_string())));
+        assert!(form.contains(&("amount".to_string(), "5000".to_string())));
+        assert!(!form.iter().any(|(name, _)| name == "description"));
+
+        let embedded = PaymentIntentParams {
+            amount: 5000,
+            currency: "chf".to_string(),
+            automatic_payment_methods: Some(AutomaticPaymentMethods { enabled: true }),
+            ..Default::default()
+        };
+        assert_eq!(
+            to_form(&embedded).unwrap(),
+            vec![
+                ("amount".to_string(), "5000".to_string()),
+                (
+                    "automatic_payment_methods[enabled]".to_string(),
+                    "true".to_string()
+                ),
+                ("currency".to_string(), "chf".to_string())
+            ]
+        );
+
+        let query = PaymentMethodListQuery {
+            customer: "cus_123".to_string(),
+            method_type: "card".to_string(),
+            limit: 10,
+        };
+        assert!(to_form(&query)
+            .unwrap()
+            .contains(&("type".to_string(), "card".to_string())));
+    }
+
+    #[test]
+    fn test_error_message() {
+        let body = r#"{"error": {"message": "No such payment_intent: 'pi_123'"}}"#;
+        assert_eq!(
+            error_message(body.to_string()),
+            "No such payment_intent: 'pi_123'"
+        );
+        assert_eq!(error_message("Bad Gateway".to_string()), "Bad Gateway");
+    }
+}
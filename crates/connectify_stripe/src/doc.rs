@@ -1,13 +1,23 @@
 // --- File: crates/connectify_stripe/src/doc.rs ---
 #![allow(dead_code)]
 #![cfg(feature = "openapi")]
+use connectify_common::models::SortOrder;
 use utoipa::OpenApi;
 // Import all relevant schemas from logic.rs and handlers.rs
-use crate::handlers::{GetSessionDetailsQuery, StripeRedirectQuery};
+use crate::handlers::{
+    DeadLettersQuery, GetSessionDetailsQuery, StripeDeadLetterResponse, StripeRedirectQuery,
+    StripeWebhookEventResponse, WebhookEventsQuery,
+};
 use crate::logic::{
-    CreateCheckoutSessionRequest, CreateCheckoutSessionResponse, ListSessionsAdminQuery,
-    ListSessionsAdminResponse, StripeCheckoutSessionData, StripeCheckoutSessionObject,
-    StripeCustomerDetails, StripeEvent, StripeEventData, StripeListObject,
+    AdminCheckoutSession, BillingPortalSessionResponse, ChargeSavedPaymentMethodRequest,
+    ChargeSavedPaymentMethodResponse, CheckoutMode, CreateBillingPortalSessionRequest,
+    CreateCheckoutSessionRequest, CreateCheckoutSessionResponse, CreatePaymentIntentRequest,
+    CreateRefundRequest, CreateRefundResponse, CreateSetupIntentRequest, CreateSetupIntentResponse,
+    CustomerLookupQuery, CustomerRequest, ListSessionsAdminQuery, ListSessionsAdminResponse,
+    PaymentIntentResponse, RefundReason, SavedPaymentMethod, SessionBooking, StripeAddress,
+    StripeCheckoutSessionData, StripeCheckoutSessionObject, StripeCustomerDetails,
+    StripeCustomerResponse, StripeEvent, StripeEventData, StripeListObject,
+    StripePaymentIntentObject, StripeTotalDetails,
 };
 #[utoipa::path(
     post,
@@ -76,10 +86,14 @@ fn doc_stripe_checkout_cancel_handler() {}
 #[utoipa::path(
     get,
     path = "/stripe/order-confirmation-details", // Path relative to /api
-    params(("session_id" = String, Query, description = "The ID of the Stripe checkout")), // Use full path if ambiguous
+    params(
+        ("session_id" = String, Query, description = "The ID of the Stripe checkout"),
+        ("success_token" = String, Query, description = "The token the success page was redirected with")
+    ),
     // params(GetSessionDetailsQuery),
     responses(
         (status = 200, description = "Successfully retrieved checkout session details", body = StripeCheckoutSessionData),
+        (status = 401, description = "Missing, expired or foreign success token"),
         (status = 404, description = "Session not found or payment not completed"),
         (status = 500, description = "Internal Server Error")
     ),
@@ -92,22 +106,232 @@ fn doc_get_checkout_session_details_handler() {}
     get,
     path = "/admin/stripe/sessions", // Path relative to /api
     params(
-        ("limit" = Option<u8>, Query, description = "A limit on the number of objects to be returned. Limit can range between 1 and 100, and the default is 10.", example = 10),
-        ("starting_after" = Option<String>, Query, description = "A cursor for use in pagination. `starting_after` is an object ID that defines your place in the list.", example = "cs_test_a1b2c3..."),
-        ("ending_before" = Option<String>, Query, description = "A cursor for use in pagination. `ending_before` is an object ID that defines your place in the list.", example = "cs_test_z0y9x8...")
-        // Add other filter params from ListSessionsAdminQuery here if needed, e.g.:
-        // ("customer" = Option<String>, Query, description = "Only return sessions for the given customer ID."),
-        // ("payment_intent" = Option<String>, Query, description = "Only return sessions for the given PaymentIntent ID.")
+        ("limit" = Option<u32>, Query, description = "Number of sessions per page (1-100, default 25)", example = 25),
+        ("cursor" = Option<String>, Query, description = "The `next_cursor` of the previous page", example = "cs_test_a1b2c3..."),
+        ("order" = Option<SortOrder>, Query, description = "Only `desc`, sessions are listed newest first"),
+        ("created_from" = Option<String>, Query, description = "Only sessions created on or after this day (UTC)", example = "2025-07-01", format = "date"),
+        ("created_to" = Option<String>, Query, description = "Only sessions created on or before this day (UTC)", example = "2025-07-31", format = "date"),
+        ("customer_email" = Option<String>, Query, description = "Only sessions whose customer entered this email address", example = "client@example.com"),
+        ("payment_status" = Option<String>, Query, description = "Only sessions with this payment status: `paid`, `unpaid` or `no_payment_required`", example = "paid"),
+        ("client_reference_id" = Option<String>, Query, description = "Only sessions with this client reference ID", example = "order-1")
     ),
     responses(
-        (status = 200, description = "A list of Stripe Checkout Sessions", body = ListSessionsAdminResponse),
+        (status = 200, description = "A page of Stripe Checkout Sessions with their bookings", body = ListSessionsAdminResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 422, description = "Invalid filter"),
         (status = 500, description = "Internal server error or Stripe API error")
     ),
-    // TODO: Add security definition once admin auth is implemented
-    // security(("admin_auth" = [])), 
     tag = "Stripe Admin"
 )]
 fn doc_admin_list_checkout_sessions_handler() {}
+#[utoipa::path(
+    post,
+    path = "/stripe/refunds", // Path relative to /api
+    request_body(content = CreateRefundRequest, example = json!({
+        "session_id": "cs_test_a1b2c3...",
+        "amount": 2500, // Partial refund of 25.00; omit to refund the whole payment
+        "reason": "requested_by_customer"
+    })),
+    responses(
+        (status = 200, description = "Refund created", body = CreateRefundResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Session not found or not paid"),
+        (status = 422, description = "Validation failed"),
+        (status = 502, description = "Stripe API error")
+    ),
+    tag = "Stripe Admin"
+)]
+fn doc_create_refund_handler() {}
+#[utoipa::path(
+    get,
+    path = "/admin/stripe/events", // Path relative to /api
+    params(WebhookEventsQuery),
+    responses(
+        (status = 200, description = "Received Stripe webhook events, most recent first", body = [StripeWebhookEventResponse]),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 422, description = "Unknown status")
+    ),
+    tag = "Stripe Admin"
+)]
+fn doc_admin_list_webhook_events_handler() {}
+#[utoipa::path(
+    post,
+    path = "/admin/stripe/events/{event_id}/replay", // Path relative to /api
+    params(("event_id" = String, Path, description = "The Stripe event id", example = "evt_1N...")),
+    responses(
+        (status = 200, description = "Event processed", body = StripeWebhookEventResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Event not received"),
+        (status = 409, description = "Event already processed or being processed"),
+        (status = 502, description = "Processing failed again")
+    ),
+    tag = "Stripe Admin"
+)]
+fn doc_admin_replay_webhook_event_handler() {}
+#[utoipa::path(
+    get,
+    path = "/admin/stripe/dead-letters", // Path relative to /api
+    params(DeadLettersQuery),
+    responses(
+        (status = 200, description = "Failed fulfillments, most recent first", body = [StripeDeadLetterResponse]),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 422, description = "Unknown status")
+    ),
+    tag = "Stripe Admin"
+)]
+fn doc_admin_list_dead_letters_handler() {}
+#[utoipa::path(
+    post,
+    path = "/admin/stripe/dead-letters/{payment_id}/requeue", // Path relative to /api
+    params(("payment_id" = String, Path, description = "The Checkout Session or PaymentIntent id", example = "cs_test_a1...")),
+    responses(
+        (status = 200, description = "Fulfillment attempted; its status tells whether it was delivered", body = StripeDeadLetterResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "No failed fulfillment of the payment"),
+        (status = 409, description = "Fulfillment already delivered")
+    ),
+    tag = "Stripe Admin"
+)]
+fn doc_admin_requeue_dead_letter_handler() {}
+#[utoipa::path(
+    post,
+    path = "/admin/stripe/customers", // Path relative to /api
+    request_body(content = CustomerRequest, example = json!({
+        "email": "anna@example.com",
+        "name": "Anna Muster" // Only used if the customer is created
+    })),
+    responses(
+        (status = 200, description = "Existing or newly created customer", body = StripeCustomerResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 422, description = "Validation failed"),
+        (status = 502, description = "Stripe API error")
+    ),
+    tag = "Stripe Admin"
+)]
+fn doc_admin_find_or_create_customer_handler() {}
+#[utoipa::path(
+    get,
+    path = "/admin/stripe/customers", // Path relative to /api
+    params(CustomerLookupQuery),
+    responses(
+        (status = 200, description = "The customer of the email address", body = StripeCustomerResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "No customer with the email address"),
+        (status = 502, description = "Stripe API error")
+    ),
+    tag = "Stripe Admin"
+)]
+fn doc_admin_get_customer_handler() {}
+#[utoipa::path(
+    post,
+    path = "/stripe/setup-intents", // Path relative to /api
+    request_body(content = CreateSetupIntentRequest, example = json!({
+        "customer_email": "anna@example.com",
+        "customer_name": "Anna Muster"
+    })),
+    responses(
+        (status = 200, description = "SetupIntent to confirm with Stripe.js", body = CreateSetupIntentResponse),
+        (status = 422, description = "Validation failed"),
+        (status = 500, description = "Internal Server Error or Stripe API error")
+    ),
+    tag = "Stripe"
+)]
+fn doc_create_setup_intent_handler() {}
+#[utoipa::path(
+    post,
+    path = "/stripe/payment-intents", // Path relative to /api
+    request_body(content = CreatePaymentIntentRequest, example = json!({
+        "fulfillment_type": "gcal_booking",
+        "fulfillment_data": { // Priced by the duration, like Checkout Sessions
+            "start_time": "2025-08-01T14:00:00Z",
+            "end_time": "2025-08-01T15:00:00Z",
+            "summary": "Consultation (via Stripe)"
+        },
+        "client_reference_id": "my_order_ref_12345"
+    })),
+    responses(
+        (status = 200, description = "PaymentIntent with the client secret for the Payment Element", body = PaymentIntentResponse),
+        (status = 400, description = "No matching price tier or invalid fulfillment data"),
+        (status = 422, description = "Validation failed"),
+        (status = 500, description = "Internal Server Error or Stripe API error")
+    ),
+    tag = "Stripe"
+)]
+fn doc_create_payment_intent_handler() {}
+#[utoipa::path(
+    post,
+    path = "/stripe/payment-intents/{payment_intent_id}/confirm", // Path relative to /api
+    params(("payment_intent_id" = String, Path, description = "The PaymentIntent id", example = "pi_3N...")),
+    responses(
+        (status = 200, description = "PaymentIntent confirmed", body = PaymentIntentResponse),
+        (status = 400, description = "Invalid PaymentIntent id"),
+        (status = 402, description = "Payment declined"),
+        (status = 500, description = "Internal Server Error or Stripe API error")
+    ),
+    tag = "Stripe"
+)]
+fn doc_confirm_payment_intent_handler() {}
+#[utoipa::path(
+    post,
+    path = "/stripe/payment-intents/{payment_intent_id}/cancel", // Path relative to /api
+    params(("payment_intent_id" = String, Path, description = "The PaymentIntent id", example = "pi_3N...")),
+    responses(
+        (status = 200, description = "PaymentIntent canceled", body = PaymentIntentResponse),
+        (status = 400, description = "Invalid PaymentIntent id or already paid"),
+        (status = 500, description = "Internal Server Error or Stripe API error")
+    ),
+    tag = "Stripe"
+)]
+fn doc_cancel_payment_intent_handler() {}
+#[utoipa::path(
+    get,
+    path = "/admin/stripe/customers/{customer_id}/payment-methods", // Path relative to /api
+    params(("customer_id" = String, Path, description = "The Stripe customer id", example = "cus_N...")),
+    responses(
+        (status = 200, description = "Saved cards of the customer", body = [SavedPaymentMethod]),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 502, description = "Stripe API error")
+    ),
+    tag = "Stripe Admin"
+)]
+fn doc_admin_list_payment_methods_handler() {}
+#[utoipa::path(
+    post,
+    path = "/admin/stripe/payment-intents", // Path relative to /api
+    request_body(content = ChargeSavedPaymentMethodRequest, example = json!({
+        "customer_id": "cus_N...",
+        "payment_method_id": "pm_1N...",
+        "amount": 5000, // 50.00 in the default currency
+        "description": "Consultation 60 min",
+        "client_reference_id": "my_internal_order_123"
+    })),
+    responses(
+        (status = 200, description = "Saved card charged", body = ChargeSavedPaymentMethodResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 402, description = "Card declined or the customer has to authenticate"),
+        (status = 422, description = "Validation failed"),
+        (status = 502, description = "Stripe API error")
+    ),
+    tag = "Stripe Admin"
+)]
+fn doc_admin_charge_saved_payment_method_handler() {}
+#[utoipa::path(
+    post,
+    path = "/admin/stripe/billing-portal-sessions", // Path relative to /api
+    request_body(content = CreateBillingPortalSessionRequest, example = json!({
+        "customer_email": "anna@example.com",
+        "return_url": "https://example.com/account"
+    })),
+    responses(
+        (status = 200, description = "Portal URL to send to the customer", body = BillingPortalSessionResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "No customer with the email address"),
+        (status = 422, description = "Validation failed"),
+        (status = 502, description = "Stripe API error")
+    ),
+    tag = "Stripe Admin"
+)]
+fn doc_admin_create_billing_portal_session_handler() {}
 #[derive(OpenApi)]
 #[openapi(
     paths(
@@ -116,19 +340,43 @@ fn doc_admin_list_checkout_sessions_handler() {}
         doc_stripe_checkout_success_handler,
         doc_stripe_checkout_cancel_handler,
         doc_get_checkout_session_details_handler,
-        doc_admin_list_checkout_sessions_handler
+        doc_admin_list_checkout_sessions_handler,
+        doc_create_refund_handler,
+        doc_admin_list_webhook_events_handler,
+        doc_admin_replay_webhook_event_handler,
+        doc_admin_list_dead_letters_handler,
+        doc_admin_requeue_dead_letter_handler,
+        doc_admin_find_or_create_customer_handler,
+        doc_admin_get_customer_handler,
+        doc_create_setup_intent_handler,
+        doc_create_payment_intent_handler,
+        doc_confirm_payment_intent_handler,
+        doc_cancel_payment_intent_handler,
+        doc_admin_list_payment_methods_handler,
+        doc_admin_charge_saved_payment_method_handler,
+        doc_admin_create_billing_portal_session_handler
     ),
     components(
         schemas(
-            CreateCheckoutSessionRequest, CreateCheckoutSessionResponse,
+            CreateCheckoutSessionRequest, CreateCheckoutSessionResponse, CheckoutMode,
             StripeEvent, StripeEventData, StripeCheckoutSessionObject, StripeCustomerDetails,
+            StripeAddress, StripeTotalDetails,
             StripeRedirectQuery,
             crate::handlers::GetSessionDetailsQuery, // Use full path if ambiguous
             StripeCheckoutSessionData, // Response for single session details
             ListSessionsAdminQuery,    //  query schema for admin list
             ListSessionsAdminResponse, // response schema for admin list
             StripeListObject<StripeCheckoutSessionData>,// Ensure generic list object is in schema if used directly
-            GetSessionDetailsQuery
+            AdminCheckoutSession, SessionBooking, SortOrder,
+            GetSessionDetailsQuery,
+            CreateRefundRequest, CreateRefundResponse, RefundReason,
+            WebhookEventsQuery, StripeWebhookEventResponse,
+            DeadLettersQuery, StripeDeadLetterResponse,
+            CustomerRequest, CustomerLookupQuery, StripeCustomerResponse,
+            CreateSetupIntentRequest, CreateSetupIntentResponse, SavedPaymentMethod,
+            ChargeSavedPaymentMethodRequest, ChargeSavedPaymentMethodResponse,
+            CreateBillingPortalSessionRequest, BillingPortalSessionResponse,
+            CreatePaymentIntentRequest, PaymentIntentResponse, StripePaymentIntentObject
         )
     ),
     tags(
@@ -1,4 +1,6 @@
 // --- File: crates/connectify_stripe/src/error.rs ---
+use connectify_common::http::circuit_breaker::HttpClientError;
+use connectify_common::webhook::WebhookError;
 use connectify_common::{external_service_error, ConnectifyError, HttpStatusCode};
 use thiserror::Error;
 
@@ -9,6 +11,10 @@ pub enum StripeError {
     #[error("Stripe API request failed: {0}")]
     RequestError(#[from] reqwest::Error),
 
+    /// Stripe API is unavailable (circuit breaker open)
+    #[error("Stripe API unavailable: {0}")]
+    ServiceUnavailable(String),
+
     /// Error returned by the Stripe API
     #[error("Stripe API returned an error: {message} (Status: {status_code})")]
     ApiError { status_code: u16, message: String },
@@ -41,6 +47,10 @@ pub enum StripeError {
     #[error("Session not found or not paid")]
     SessionNotFoundOrNotPaid,
 
+    /// Missing, expired or forged success page token
+    #[error("Invalid success token: {0}")]
+    InvalidSuccessToken(String),
+
     /// Invalid fulfillment data for pricing
     #[error("Invalid fulfillment data for pricing: {0}")]
     InvalidFulfillmentDataForPricing(String),
@@ -49,11 +59,58 @@ pub enum StripeError {
     #[error("No matching price tier found for duration: {0} minutes")]
     NoMatchingPriceTier(i64),
 
+    /// The price tier has no price in the requested currency
+    #[error("No price in {currency} for {duration_minutes} minutes, available: {available}")]
+    UnsupportedCurrency {
+        currency: String,
+        duration_minutes: i64,
+        available: String,
+    },
+
+    /// The price of a subscription checkout is not a configured plan
+    #[error("Unknown subscription price: {0}")]
+    UnknownSubscriptionPrice(String),
+
+    /// The id of a PaymentIntent is malformed
+    #[error("Invalid PaymentIntent id: {0}")]
+    InvalidPaymentIntentId(String),
+
+    /// No customer with the email address exists
+    #[error("No Stripe customer found for {0}")]
+    CustomerNotFound(String),
+
+    /// A filter of the admin session listing is malformed
+    #[error("Invalid session filter: {0}")]
+    InvalidSessionFilter(String),
+
     /// Internal processing error
     #[error("Internal processing error: {0}")]
     InternalError(String),
 }
 
+/// Convert WebhookError to StripeError
+impl From<WebhookError> for StripeError {
+    fn from(err: WebhookError) -> Self {
+        match err {
+            WebhookError::Config(_) => StripeError::ConfigError,
+            other => StripeError::WebhookSignatureError(other.to_string()),
+        }
+    }
+}
+
+/// Convert errors from requests sent through the circuit breaker
+impl From<HttpClientError> for StripeError {
+    fn from(err: HttpClientError) -> Self {
+        match err {
+            HttpClientError::CircuitOpen { host } => StripeError::ServiceUnavailable(format!(
+                "Circuit open for {}, not sending request",
+                host
+            )),
+            HttpClientError::Request(e) => StripeError::RequestError(e),
+        }
+    }
+}
+
 /// Convert StripeError to ConnectifyError
 impl From<StripeError> for ConnectifyError {
     fn from(err: StripeError) -> Self {
@@ -61,6 +118,7 @@ impl From<StripeError> for ConnectifyError {
             StripeError::RequestError(e) => {
                 ConnectifyError::HttpError(format!("Stripe request error: {}", e))
             }
+            StripeError::ServiceUnavailable(msg) => external_service_error("Stripe API", msg),
             StripeError::ApiError {
                 status_code,
                 message,
@@ -89,6 +147,9 @@ impl From<StripeError> for ConnectifyError {
             StripeError::SessionNotFoundOrNotPaid => {
                 ConnectifyError::NotFoundError("Stripe session not found or not paid".to_string())
             }
+            StripeError::InvalidSuccessToken(msg) => {
+                ConnectifyError::AuthError(format!("Invalid success token: {}", msg))
+            }
             StripeError::InvalidFulfillmentDataForPricing(msg) => ConnectifyError::ValidationError(
                 format!("Invalid fulfillment data for pricing: {}", msg),
             ),
@@ -98,6 +159,26 @@ impl From<StripeError> for ConnectifyError {
                     duration
                 ))
             }
+            StripeError::UnsupportedCurrency {
+                currency,
+                duration_minutes,
+                available,
+            } => ConnectifyError::ValidationError(format!(
+                "No price in {} for {} minutes, available: {}",
+                currency, duration_minutes, available
+            )),
+            StripeError::UnknownSubscriptionPrice(price_id) => ConnectifyError::ValidationError(
+                format!("Unknown subscription price: {}", price_id),
+            ),
+            StripeError::InvalidPaymentIntentId(id) => {
+                ConnectifyError::ValidationError(format!("Invalid PaymentIntent id: {}", id))
+            }
+            StripeError::CustomerNotFound(email) => {
+                ConnectifyError::NotFoundError(format!("No Stripe customer found for {}", email))
+            }
+            StripeError::InvalidSessionFilter(msg) => {
+                ConnectifyError::ValidationError(format!("Invalid session filter: {}", msg))
+            }
             StripeError::InternalError(msg) => {
                 ConnectifyError::InternalError(format!("Stripe internal error: {}", msg))
             }
@@ -111,6 +192,7 @@ impl HttpStatusCode for StripeError {
     fn status_code(&self) -> u16 {
         match self {
             StripeError::RequestError(_) => 500,
+            StripeError::ServiceUnavailable(_) => 503,
             StripeError::ApiError { status_code, .. } => *status_code,
             StripeError::ParseError(_) => 400,
             StripeError::ConfigError => 500,
@@ -119,8 +201,14 @@ impl HttpStatusCode for StripeError {
             StripeError::FulfillmentError(_) => 502,
             StripeError::MissingFulfillmentData => 400,
             StripeError::SessionNotFoundOrNotPaid => 404,
+            StripeError::InvalidSuccessToken(_) => 401,
             StripeError::InvalidFulfillmentDataForPricing(_) => 400,
             StripeError::NoMatchingPriceTier(_) => 400,
+            StripeError::UnsupportedCurrency { .. } => 400,
+            StripeError::UnknownSubscriptionPrice(_) => 400,
+            StripeError::InvalidPaymentIntentId(_) => 400,
+            StripeError::CustomerNotFound(_) => 404,
+            StripeError::InvalidSessionFilter(_) => 400,
             StripeError::InternalError(_) => 500,
         }
     }
@@ -1,35 +1,59 @@
 // --- File: crates/connectify_stripe/src/handlers.rs ---
-// use crate::error::StripeError;
+use crate::client::StripeClient;
+use crate::error::StripeError;
 use crate::logic::{
-    create_checkout_session, get_checkout_session_details, list_checkout_sessions_admin,
-    process_stripe_webhook, verify_stripe_signature, CreateCheckoutSessionRequest,
-    CreateCheckoutSessionResponse, ListSessionsAdminQuery, ListSessionsAdminResponse,
-    StripeCheckoutSessionData, StripeEvent,
+    booking_payment, charge_saved_payment_method, create_billing_portal_session,
+    create_checkout_session, create_setup_intent, deliver_dead_letter, find_customer,
+    find_or_create_customer, get_owned_checkout_session_details, list_checkout_sessions_admin,
+    list_saved_payment_methods, process_stripe_webhook, refund_payment, refund_target,
+    save_fulfillment_order, webhook_tolerance_seconds, BillingPortalSessionResponse,
+    ChargeSavedPaymentMethodRequest, ChargeSavedPaymentMethodResponse,
+    CreateBillingPortalSessionRequest, CreateCheckoutSessionRequest, CreateCheckoutSessionResponse,
+    CreatePaymentIntentRequest, CreateRefundRequest, CreateRefundResponse,
+    CreateSetupIntentRequest, CreateSetupIntentResponse, CustomerLookupQuery, CustomerRequest,
+    ListSessionsAdminQuery, ListSessionsAdminResponse, PaymentIntentResponse, SavedPaymentMethod,
+    StripeCheckoutSessionData, StripeCustomerResponse, StripeEvent, StripeWebhookVerifier,
+    STRIPE_PROVIDER,
 };
+use crate::service::StripePaymentService;
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse, Json, Redirect, Response},
 };
+use chrono::Utc;
+use connectify_common::audit::{self, AuditActor, AuditEvent};
+use connectify_common::dead_letters::{dead_letter_store, DeadLetter, DeadLetterStatus};
+use connectify_common::lock::{distributed_lock, release_quietly, DistributedLock, LockLease};
+use connectify_common::models::CursorQuery;
+use connectify_common::services::PaymentService;
+use connectify_common::validation::ValidatedJson;
+use connectify_common::webhook::WebhookVerifier;
+use connectify_common::webhook_events::{
+    webhook_event_store, WebhookEventRecord, WebhookEventStatus,
+};
 use connectify_common::{
     config_error,
     // external_service_error,
     // validation_error,
     // not_found,
     // IntoHttpResponse,
-    // handle_json_result,
+    handle_json_result,
     // map_error,
     map_json_error,
     ConnectifyError,
 };
 use connectify_config::AppConfig;
 use connectify_config::StripeConfig;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 #[cfg(feature = "openapi")]
 use utoipa::ToSchema;
 
+/// How long processing a webhook event may hold its lock.
+const WEBHOOK_LOCK_TTL: std::time::Duration = std::time::Duration::from_secs(60);
+
 // --- State for Stripe Handlers ---
 // Only needs AppConfig as reqwest::Client is static in logic.rs
 #[derive(Clone)]
@@ -46,30 +70,126 @@ pub struct StripeState {
     responses(
         (status = 200, description = "Stripe Checkout Session created", body = CreateCheckoutSessionResponse),
         (status = 400, description = "Bad Request"),
+        (status = 422, description = "Validation failed"),
         (status = 500, description = "Internal Server Error or Stripe API error")
     ),
     tag = "Stripe"
 ))]
 pub async fn create_checkout_session_handler(
     State(state): State<Arc<StripeState>>,
-    Json(payload): Json<CreateCheckoutSessionRequest>,
-) -> Result<Json<CreateCheckoutSessionResponse>, Response> {
+    ValidatedJson(payload): ValidatedJson<CreateCheckoutSessionRequest>,
+) -> Result<Json<CreateCheckoutSessionResponse>, ConnectifyError> {
     if !state.config.use_stripe {
-        return Err(
-            ConnectifyError::ConfigError("Stripe service is disabled".to_string()).into_response(),
-        );
+        return Err(ConnectifyError::ConfigError(
+            "Stripe service is disabled".to_string(),
+        ));
     }
 
     if let Some(stripe_config) = state.config.stripe.as_ref() {
-        // Use map_json_error to convert StripeError to ConnectifyError and then to a Response
-        map_json_error(
-            create_checkout_session(stripe_config, payload).await,
-            |err| err.into(), // Convert StripeError to ConnectifyError using the From implementation
-        )
-        .map_err(|boxed| *boxed)
+        // Convert StripeError to ConnectifyError using the From implementation
+        let pricing_config = state.config.pricing.clone().unwrap_or_default();
+        handle_json_result(create_checkout_session(stripe_config, &pricing_config, payload).await)
     } else {
-        Err(config_error("Stripe configuration not loaded").into_response())
+        Err(config_error("Stripe configuration not loaded"))
+    }
+}
+
+/// Axum handler to create a PaymentIntent for the embedded Payment Element.
+///
+/// The booking is priced like a Checkout Session, and fulfilled by the
+/// `payment_intent.succeeded` webhook.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/stripe/payment-intents", // Path relative to /api
+    request_body = CreatePaymentIntentRequest,
+    responses(
+        (status = 200, description = "PaymentIntent created", body = PaymentIntentResponse),
+        (status = 400, description = "Bad Request"),
+        (status = 422, description = "Validation failed"),
+        (status = 500, description = "Internal Server Error or Stripe API error")
+    ),
+    tag = "Stripe"
+))]
+pub async fn create_payment_intent_handler(
+    State(state): State<Arc<StripeState>>,
+    ValidatedJson(payload): ValidatedJson<CreatePaymentIntentRequest>,
+) -> Result<Json<PaymentIntentResponse>, ConnectifyError> {
+    if state.config.stripe.is_none() || !state.config.use_stripe {
+        return Err(ConnectifyError::ConfigError(
+            "Stripe service not configured or disabled".to_string(),
+        ));
+    }
+
+    let pricing_config = state.config.pricing.clone().unwrap_or_default();
+    let payment = booking_payment(&pricing_config, &payload)?;
+    save_fulfillment_order(payment.order).await?;
+    let service = StripePaymentService::new(state.config.clone());
+    let result = service
+        .create_payment_intent(
+            payment.amount,
+            &payment.currency,
+            Some(&payment.description),
+            Some(payment.metadata),
+        )
+        .await;
+    handle_json_result(result.map(PaymentIntentResponse::from))
+}
+
+/// Axum handler to confirm a PaymentIntent with its attached payment method.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/stripe/payment-intents/{payment_intent_id}/confirm", // Path relative to /api
+    params(("payment_intent_id" = String, Path, description = "The PaymentIntent id")),
+    responses(
+        (status = 200, description = "PaymentIntent confirmed", body = PaymentIntentResponse),
+        (status = 400, description = "Invalid PaymentIntent id"),
+        (status = 402, description = "Payment declined"),
+        (status = 500, description = "Internal Server Error or Stripe API error")
+    ),
+    tag = "Stripe"
+))]
+pub async fn confirm_payment_intent_handler(
+    State(state): State<Arc<StripeState>>,
+    Path(payment_intent_id): Path<String>,
+) -> Result<Json<PaymentIntentResponse>, ConnectifyError> {
+    if !state.config.use_stripe || state.config.stripe.is_none() {
+        return Err(ConnectifyError::ConfigError(
+            "Stripe service not configured or disabled".to_string(),
+        ));
+    }
+    let service = StripePaymentService::new(state.config.clone());
+    let result = service.confirm_payment_intent(&payment_intent_id).await;
+    handle_json_result(result.map(PaymentIntentResponse::from))
+}
+
+/// Axum handler to cancel a PaymentIntent that was not paid, e.g. when the booking is
+/// abandoned.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/stripe/payment-intents/{payment_intent_id}/cancel", // Path relative to /api
+    params(("payment_intent_id" = String, Path, description = "The PaymentIntent id")),
+    responses(
+        (status = 200, description = "PaymentIntent canceled", body = PaymentIntentResponse),
+        (status = 400, description = "Invalid PaymentIntent id or already paid"),
+        (status = 500, description = "Internal Server Error or Stripe API error")
+    ),
+    tag = "Stripe"
+))]
+pub async fn cancel_payment_intent_handler(
+    State(state): State<Arc<StripeState>>,
+    Path(payment_intent_id): Path<String>,
+) -> Result<Json<PaymentIntentResponse>, ConnectifyError> {
+    if !state.config.use_stripe || state.config.stripe.is_none() {
+        return Err(ConnectifyError::ConfigError(
+            "Stripe service not configured or disabled".to_string(),
+        ));
     }
+    let service = StripePaymentService::new(state.config.clone());
+    let result = service.cancel_payment_intent(&payment_intent_id).await;
+    handle_json_result(result.map(PaymentIntentResponse::from))
 }
 
 // --- Placeholder for Stripe Webhook Handler ---
@@ -112,16 +232,13 @@ pub async fn stripe_webhook_handler(
         }
     };
 
-    // Get the 'Stripe-Signature' header from the request
-    let sig_header = headers
-        .get("Stripe-Signature")
-        .and_then(|h| h.to_str().ok());
-
-    // Call the verification function from logic.rs
-    if let Err(e) = verify_stripe_signature(body.as_bytes(), sig_header, &webhook_secret) {
+    // Verify the 'Stripe-Signature' header against the raw body
+    let verifier = StripeWebhookVerifier::new(webhook_secret)
+        .with_tolerance(webhook_tolerance_seconds(state.config.stripe.as_ref()));
+    if let Err(e) = verifier.verify(body.as_bytes(), &headers) {
         error!("Stripe webhook signature verification failed: {:?}", e);
-        // Return 400 Bad Request for signature errors
-        return ConnectifyError::from(e).into_response();
+        // Signature errors are rejected as unauthorized
+        return ConnectifyError::from(StripeError::from(e)).into_response();
     }
 
     info!("✅ Stripe webhook signature verified.");
@@ -137,9 +254,49 @@ pub async fn stripe_webhook_handler(
         }
     };
 
-    let app_config = state.config.clone(); // Clone the AppConfig for processing the webhook
+    // Stripe may deliver an event to several instances; only one of them processes it
+    let lock = distributed_lock();
+    let lease = match acquire_event_lock(&*lock, &event.id).await {
+        Ok(lease) => lease,
+        Err(e) => return e.into_response(),
+    };
+
+    // Store the event before processing it. Processed events are skipped, so neither a
+    // redelivery nor a request replayed within the signature tolerance is fulfilled twice.
+    let store = webhook_event_store();
+    let received = WebhookEventRecord::received(
+        STRIPE_PROVIDER,
+        &event.id,
+        &event.event_type,
+        &body,
+        Utc::now(),
+    );
+    match store.record(received).await {
+        Ok(None) => {}
+        Ok(Some(stored)) if stored.status == WebhookEventStatus::Processed => {
+            release_quietly(&*lock, &lease).await;
+            warn!(
+                "Stripe event {} was already processed, skipping duplicate.",
+                event.id
+            );
+            // Acknowledge it, so Stripe stops re-delivering it
+            return StatusCode::OK.into_response();
+        }
+        Ok(Some(stored)) => info!(
+            "Stripe event {} is delivered again after status '{}', processing it.",
+            event.id, stored.status
+        ),
+        Err(e) => {
+            release_quietly(&*lock, &lease).await;
+            error!("Failed to store Stripe event {}: {}", event.id, e);
+            return e.into_response();
+        }
+    }
+
     debug!("Webhook event: {:?}", event); // Call the processing logic from logic.rs
-    match process_stripe_webhook(event, app_config.clone()).await {
+    let result = process_and_record_event(event, state.config.clone()).await;
+    release_quietly(&*lock, &lease).await;
+    match result {
         Ok(()) => {
             info!("Stripe webhook processed successfully.");
             StatusCode::OK.into_response() // Return 200 OK to Stripe
@@ -152,6 +309,49 @@ pub async fn stripe_webhook_handler(
     }
 }
 
+/// Take the lock of an event, failing with a conflict if it is already being processed.
+async fn acquire_event_lock(
+    lock: &dyn DistributedLock,
+    event_id: &str,
+) -> Result<LockLease, ConnectifyError> {
+    match lock
+        .acquire(&format!("stripe:event:{}", event_id), WEBHOOK_LOCK_TTL)
+        .await?
+    {
+        Some(lease) => Ok(lease),
+        None => {
+            info!("Stripe event {} is already being processed.", event_id);
+            Err(ConnectifyError::ConflictError(format!(
+                "Event {} is already being processed",
+                event_id
+            )))
+        }
+    }
+}
+
+/// Process a stored event and record the outcome in the webhook event store.
+async fn process_and_record_event(
+    event: StripeEvent,
+    app_config: Arc<AppConfig>,
+) -> Result<(), StripeError> {
+    let event_id = event.id.clone();
+    let result = process_stripe_webhook(event, app_config).await;
+    let (status, error) = match &result {
+        Ok(()) => (WebhookEventStatus::Processed, None),
+        Err(e) => (WebhookEventStatus::Failed, Some(e.to_string())),
+    };
+    if let Err(e) = webhook_event_store()
+        .finish(STRIPE_PROVIDER, &event_id, status, error, Utc::now())
+        .await
+    {
+        error!(
+            "Failed to record status '{}' of Stripe event {}: {}",
+            status, event_id, e
+        );
+    }
+    result
+}
+
 // --- Redirect Handlers (Client-Side) ---
 // These are the success_url and cancel_url you provide to Stripe
 
@@ -162,6 +362,8 @@ pub struct StripeRedirectQuery {
     // e.g., ?session_id={CHECKOUT_SESSION_ID}
     #[cfg_attr(feature = "openapi", param(example = "cs_test_a1..."))]
     pub session_id: Option<String>,
+    /// Token proving the redirect came from the checkout, passed on to the success page
+    pub success_token: Option<String>,
 }
 
 #[axum::debug_handler]
@@ -191,13 +393,17 @@ pub async fn stripe_checkout_success_handler(
     // Construct the URL for your frontend success page
     // This page will be responsible for fetching and displaying details.
 
-    let frontend_success_url = format!(
+    let mut frontend_success_url = format!(
         "{}?session_id={}",
         <std::option::Option<StripeConfig> as Clone>::clone(&state.config.stripe)
             .unwrap()
             .payment_success_url,
         session_id
     );
+    // The success page needs the token to look the session up
+    if let Some(success_token) = &params.success_token {
+        frontend_success_url = format!("{}&success_token={}", frontend_success_url, success_token);
+    }
 
     // Perform a redirect
     Redirect::to(&frontend_success_url)
@@ -227,9 +433,12 @@ pub async fn stripe_checkout_cancel_handler(
 pub struct GetSessionDetailsQuery {
     #[cfg_attr(feature = "openapi", param(example = "cs_test_a1..."))]
     pub session_id: String, // Made session_id mandatory for this endpoint
+    /// The token of the session's success URL; required unless called by an admin
+    pub success_token: Option<String>,
 }
 /// Handler to retrieve details of a Stripe Checkout Session using its ID.
-/// This is called by the frontend success page.
+/// This is called by the frontend success page, with the success token of the redirect, so
+/// only the client who paid can look the session up.
 #[axum::debug_handler]
 #[cfg_attr(feature = "openapi", utoipa::path(
     get,
@@ -237,6 +446,7 @@ pub struct GetSessionDetailsQuery {
     params(GetSessionDetailsQuery),
     responses(
         (status = 200, description = "Checkout session details retrieved", body = StripeCheckoutSessionData),
+        (status = 401, description = "Missing, expired or foreign success token"),
         (status = 404, description = "Session not found or payment not completed"),
         (status = 500, description = "Internal server error")
     ),
@@ -245,52 +455,65 @@ pub struct GetSessionDetailsQuery {
 pub async fn get_checkout_session_details_handler(
     State(state): State<Arc<StripeState>>, // Needs state to check if Stripe is enabled/configured
     Query(query): Query<GetSessionDetailsQuery>,
-) -> Result<Json<StripeCheckoutSessionData>, Response> {
-    if !state.config.use_stripe || state.config.stripe.is_none() {
+) -> Result<Json<StripeCheckoutSessionData>, ConnectifyError> {
+    let Some(stripe_config) = state
+        .config
+        .stripe
+        .as_ref()
+        .filter(|_| state.config.use_stripe)
+    else {
         return Err(ConnectifyError::ConfigError(
             "Stripe service not configured or disabled".to_string(),
-        )
-        .into_response());
-    }
+        ));
+    };
+
+    let success_token = query
+        .success_token
+        .as_deref()
+        .ok_or_else(|| ConnectifyError::AuthError("Missing success token".to_string()))?;
 
-    // Use map_json_error to convert StripeError to ConnectifyError and then to a Response
+    // Use map_json_error to log and convert StripeError to ConnectifyError
     map_json_error(
-        get_checkout_session_details(&query.session_id).await,
+        get_owned_checkout_session_details(stripe_config, &query.session_id, success_token).await,
         |err| {
             info!("Error retrieving Stripe session details: {}", err);
             err.into() // Convert StripeError to ConnectifyError using the From implementation
         },
     )
-    .map_err(|boxed| *boxed)
 }
 #[axum::debug_handler]
 // Add OpenAPI docs if needed for admin routes
 pub async fn admin_get_checkout_session_details_handler(
     State(state): State<Arc<StripeState>>,
+    actor: AuditActor,
     Query(query): Query<GetSessionDetailsQuery>, // Assuming same query params
-) -> Result<Json<StripeCheckoutSessionData>, Response> {
+) -> Result<Json<StripeCheckoutSessionData>, ConnectifyError> {
     info!(
         "[ADMIN] Request to get Stripe session details: {:?}",
         query.session_id
     );
-    // TODO: Implement admin-specific authorization/logging if needed
-
     if !state.config.use_stripe || state.config.stripe.is_none() {
         return Err(ConnectifyError::ConfigError(
             "Stripe service not configured or disabled".to_string(),
-        )
-        .into_response());
+        ));
     }
 
-    // Use map_json_error to convert StripeError to ConnectifyError and then to a Response
-    map_json_error(
-        crate::logic::get_checkout_session_details(&query.session_id).await,
-        |err| {
-            info!("[ADMIN] Error retrieving Stripe session details: {}", err);
-            err.into() // Convert StripeError to ConnectifyError using the From implementation
-        },
+    let result = crate::logic::get_checkout_session_details(&query.session_id).await;
+    audit::record(
+        AuditEvent::new(
+            actor,
+            "payment.view",
+            format!("stripe_session:{}", query.session_id),
+        )
+        .with_result(&result),
     )
-    .map_err(|boxed| *boxed)
+    .await;
+
+    // Use map_json_error to log and convert StripeError to ConnectifyError
+    map_json_error(result, |err| {
+        info!("[ADMIN] Error retrieving Stripe session details: {}", err);
+        err.into() // Convert StripeError to ConnectifyError using the From implementation
+    })
 }
 
 // --- NEW: Admin Handler to list Checkout Sessions ---
@@ -298,37 +521,658 @@ pub async fn admin_get_checkout_session_details_handler(
 #[cfg_attr(feature = "openapi", utoipa::path(
     get,
     path = "/admin/stripe/sessions", // Path relative to /api
-    params(ListSessionsAdminQuery), // Use the query struct from logic.rs
+    params(ListSessionsAdminQuery, CursorQuery), // Use the query struct from logic.rs
     responses(
-        (status = 200, description = "List of Stripe Checkout Sessions", body = ListSessionsAdminResponse),
+        (status = 200, description = "Page of Stripe Checkout Sessions with their bookings", body = ListSessionsAdminResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 422, description = "Invalid filter"),
         (status = 500, description = "Internal server error or Stripe API error")
     ),
-    // TODO: Add security definition if admin routes are protected
-    // security(("admin_auth" = [])),
     tag = "Stripe Admin"
 ))]
 pub async fn admin_list_checkout_sessions_handler(
     State(state): State<Arc<StripeState>>,
+    actor: AuditActor,
     Query(query_params): Query<ListSessionsAdminQuery>,
-) -> Result<Json<ListSessionsAdminResponse>, Response> {
+    Query(page): Query<CursorQuery>,
+) -> Result<Json<ListSessionsAdminResponse>, ConnectifyError> {
     info!(
         "[ADMIN] Listing Stripe Checkout Sessions. Params: {:?}",
         query_params
     );
-    // TODO: Implement admin-specific authorization here
 
     if !state.config.use_stripe || state.config.stripe.is_none() {
         return Err(ConnectifyError::ConfigError(
             "Stripe service not configured or disabled".to_string(),
-        )
-        .into_response());
+        ));
     }
 
-    // Use map_json_error to convert StripeError to ConnectifyError and then to a Response
-    map_json_error(list_checkout_sessions_admin(query_params).await, |err| {
+    let metadata = serde_json::to_value(&query_params).unwrap_or_default();
+    let result = list_checkout_sessions_admin(query_params, page).await;
+    audit::record(
+        AuditEvent::new(actor, "payment.list", "stripe_sessions")
+            .with_metadata("query", metadata)
+            .with_result(&result),
+    )
+    .await;
+
+    // Use map_json_error to log and convert StripeError to ConnectifyError
+    map_json_error(result, |err| {
         info!("[ADMIN] Error listing Stripe sessions: {}", err);
         err.into() // Convert StripeError to ConnectifyError using the From implementation
     })
-    .map_err(|boxed| *boxed)
+}
+
+/// Admin handler to refund a payment in full or in part.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/stripe/refunds", // Path relative to /api
+    request_body = CreateRefundRequest,
+    responses(
+        (status = 200, description = "Refund created", body = CreateRefundResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Session not found or not paid"),
+        (status = 422, description = "Validation failed"),
+        (status = 502, description = "Stripe API error")
+    ),
+    tag = "Stripe Admin"
+))]
+pub async fn create_refund_handler(
+    State(state): State<Arc<StripeState>>,
+    actor: AuditActor,
+    ValidatedJson(payload): ValidatedJson<CreateRefundRequest>,
+) -> Result<Json<CreateRefundResponse>, ConnectifyError> {
+    info!("[ADMIN] Request to refund Stripe payment: {:?}", payload);
+
+    if !state.config.use_stripe || state.config.stripe.is_none() {
+        return Err(ConnectifyError::ConfigError(
+            "Stripe service not configured or disabled".to_string(),
+        ));
+    }
+
+    let target = match refund_target(&payload).await {
+        Ok(target) => target,
+        Err(e) => {
+            info!("[ADMIN] Error resolving payment to refund: {}", e);
+            return Err(e.into());
+        }
+    };
+
+    let result = refund_payment(&target, &payload).await;
+    // Links the refund to the booking, whose cancellation is audited under the same target
+    audit::record(
+        AuditEvent::new(
+            actor,
+            "payment.refund",
+            format!("stripe_payment:{}", target.payment_intent_id),
+        )
+        .with_metadata("amount", payload.amount)
+        .with_metadata("reason", payload.reason.map(|reason| reason.as_str()))
+        .with_metadata("session_id", payload.session_id.clone())
+        .with_metadata(
+            "booking",
+            payload
+                .booking_id
+                .as_ref()
+                .map(|booking_id| format!("gcal_event:{}", booking_id)),
+        )
+        .with_metadata(
+            "refund_id",
+            result.as_ref().ok().map(|refund| refund.id.clone()),
+        )
+        .with_result(&result),
+    )
+    .await;
+
+    map_json_error(
+        result.map(|refund| CreateRefundResponse {
+            refund_id: refund.id,
+            payment_intent_id: target.payment_intent_id,
+            status: refund.status,
+            amount: refund.amount,
+            currency: refund.currency,
+            reason: payload.reason,
+            booking_id: payload.booking_id.clone(),
+        }),
+        |err| {
+            info!("[ADMIN] Error creating Stripe refund: {}", err);
+            err.into()
+        },
+    )
+}
+
+#[derive(Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams, ToSchema))]
+pub struct WebhookEventsQuery {
+    /// Only list events with this status: `received`, `processed` or `failed`
+    #[cfg_attr(feature = "openapi", param(example = "failed"))]
+    pub status: Option<String>,
+    /// Maximum number of events (default: 50)
+    #[cfg_attr(feature = "openapi", param(example = 50))]
+    pub limit: Option<usize>,
+}
+
+/// A received Stripe webhook event, without its payload.
+#[derive(Serialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct StripeWebhookEventResponse {
+    pub event_id: String,
+    #[cfg_attr(feature = "openapi", schema(example = "checkout.session.completed"))]
+    pub event_type: String,
+    /// `received`, `processed` or `failed`
+    #[cfg_attr(feature = "openapi", schema(example = "failed"))]
+    pub status: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    /// Unix timestamp
+    pub received_at: i64,
+    /// Unix timestamp
+    pub updated_at: i64,
+}
+
+impl From<WebhookEventRecord> for StripeWebhookEventResponse {
+    fn from(event: WebhookEventRecord) -> Self {
+        Self {
+            event_id: event.event_id,
+            event_type: event.event_type,
+            status: event.status.to_string(),
+            attempts: event.attempts,
+            last_error: event.last_error,
+            received_at: event.received_at.timestamp(),
+            updated_at: event.updated_at.timestamp(),
+        }
+    }
+}
+
+/// Admin handler to list received Stripe webhook events, most recent first.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/admin/stripe/events", // Path relative to /api
+    params(WebhookEventsQuery),
+    responses(
+        (status = 200, description = "Received Stripe webhook events", body = [StripeWebhookEventResponse]),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 422, description = "Unknown status")
+    ),
+    tag = "Stripe Admin"
+))]
+pub async fn admin_list_webhook_events_handler(
+    Query(query): Query<WebhookEventsQuery>,
+) -> Result<Json<Vec<StripeWebhookEventResponse>>, ConnectifyError> {
+    let status = query
+        .status
+        .as_deref()
+        .map(str::parse::<WebhookEventStatus>)
+        .transpose()?;
+    let events = webhook_event_store()
+        .list(STRIPE_PROVIDER, status, query.limit.unwrap_or(50))
+        .await?;
+    Ok(Json(events.into_iter().map(Into::into).collect()))
+}
+
+/// Admin handler to process a stored Stripe webhook event again, e.g. after a failure.
+///
+/// Events that were processed successfully are not replayed, so payments are never
+/// fulfilled twice.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/admin/stripe/events/{event_id}/replay", // Path relative to /api
+    params(("event_id" = String, Path, description = "The Stripe event id")),
+    responses(
+        (status = 200, description = "Event processed", body = StripeWebhookEventResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Event not received"),
+        (status = 409, description = "Event already processed or being processed"),
+        (status = 502, description = "Processing failed again")
+    ),
+    tag = "Stripe Admin"
+))]
+pub async fn admin_replay_webhook_event_handler(
+    State(state): State<Arc<StripeState>>,
+    actor: AuditActor,
+    Path(event_id): Path<String>,
+) -> Result<Json<StripeWebhookEventResponse>, ConnectifyError> {
+    info!("[ADMIN] Request to replay Stripe event {}", event_id);
+
+    if !state.config.use_stripe || state.config.stripe.is_none() {
+        return Err(ConnectifyError::ConfigError(
+            "Stripe service not configured or disabled".to_string(),
+        ));
+    }
+
+    let store = webhook_event_store();
+    let stored = store
+        .get(STRIPE_PROVIDER, &event_id)
+        .await?
+        .ok_or_else(|| ConnectifyError::NotFoundError(format!("Event {} not found", event_id)))?;
+    if stored.status == WebhookEventStatus::Processed {
+        return Err(ConnectifyError::ConflictError(format!(
+            "Event {} was already processed",
+            event_id
+        )));
+    }
+    let event: StripeEvent = serde_json::from_str(&stored.payload).map_err(|e| {
+        ConnectifyError::ParseError(format!("Invalid stored payload of {}: {}", event_id, e))
+    })?;
+
+    let lock = distributed_lock();
+    let lease = acquire_event_lock(&*lock, &event_id).await?;
+    let result = process_and_record_event(event, state.config.clone()).await;
+    release_quietly(&*lock, &lease).await;
+    audit::record(
+        AuditEvent::new(
+            actor,
+            "webhook.replay",
+            format!("stripe_event:{}", event_id),
+        )
+        .with_metadata("previous_status", stored.status.as_str())
+        .with_result(&result),
+    )
+    .await;
+    result?;
+
+    let replayed = store
+        .get(STRIPE_PROVIDER, &event_id)
+        .await?
+        .unwrap_or(stored);
+    Ok(Json(replayed.into()))
+}
+
+#[derive(Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams, ToSchema))]
+pub struct DeadLettersQuery {
+    /// Only list failed fulfillments with this status: `pending`, `delivered` or `exhausted`
+    #[cfg_attr(feature = "openapi", param(example = "exhausted"))]
+    pub status: Option<String>,
+    /// Maximum number of failed fulfillments (default: 50)
+    #[cfg_attr(feature = "openapi", param(example = 50))]
+    pub limit: Option<usize>,
+}
+
+/// A fulfillment of a Stripe payment that failed and is retried.
+#[derive(Serialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct StripeDeadLetterResponse {
+    /// The Checkout Session or PaymentIntent id
+    pub payment_id: String,
+    #[cfg_attr(feature = "openapi", schema(example = "gcal_booking"))]
+    pub fulfillment_type: String,
+    /// The fulfillment request
+    pub payload: serde_json::Value,
+    /// `pending`, `delivered` or `exhausted`
+    #[cfg_attr(feature = "openapi", schema(example = "pending"))]
+    pub status: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    /// Unix timestamp of the next retry, if pending
+    pub next_attempt_at: i64,
+    /// Unix timestamp
+    pub created_at: i64,
+    /// Unix timestamp
+    pub updated_at: i64,
+}
+
+impl From<DeadLetter> for StripeDeadLetterResponse {
+    fn from(letter: DeadLetter) -> Self {
+        Self {
+            payload: serde_json::from_str(&letter.payload)
+                .unwrap_or(serde_json::Value::String(letter.payload)),
+            payment_id: letter.payment_id,
+            fulfillment_type: letter.fulfillment_type,
+            status: letter.status.to_string(),
+            attempts: letter.attempts,
+            last_error: letter.last_error,
+            next_attempt_at: letter.next_attempt_at.timestamp(),
+            created_at: letter.created_at.timestamp(),
+            updated_at: letter.updated_at.timestamp(),
+        }
+    }
+}
+
+/// Admin handler to list failed fulfillments of Stripe payments, most recent first.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/admin/stripe/dead-letters", // Path relative to /api
+    params(DeadLettersQuery),
+    responses(
+        (status = 200, description = "Failed fulfillments", body = [StripeDeadLetterResponse]),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 422, description = "Unknown status")
+    ),
+    tag = "Stripe Admin"
+))]
+pub async fn admin_list_dead_letters_handler(
+    Query(query): Query<DeadLettersQuery>,
+) -> Result<Json<Vec<StripeDeadLetterResponse>>, ConnectifyError> {
+    let status = query
+        .status
+        .as_deref()
+        .map(str::parse::<DeadLetterStatus>)
+        .transpose()?;
+    let letters = dead_letter_store()
+        .list(STRIPE_PROVIDER, status, query.limit.unwrap_or(50))
+        .await?;
+    Ok(Json(letters.into_iter().map(Into::into).collect()))
+}
+
+/// Admin handler to requeue a failed fulfillment, e.g. after fixing its cause.
+///
+/// The fulfillment is attempted right away; if that fails as well, its retries start over.
+/// Delivered fulfillments are not requeued, so bookings are never created twice.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/admin/stripe/dead-letters/{payment_id}/requeue", // Path relative to /api
+    params(("payment_id" = String, Path, description = "The Checkout Session or PaymentIntent id")),
+    responses(
+        (status = 200, description = "Fulfillment attempted; its status tells whether it was delivered", body = StripeDeadLetterResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "No failed fulfillment of the payment"),
+        (status = 409, description = "Fulfillment already delivered")
+    ),
+    tag = "Stripe Admin"
+))]
+pub async fn admin_requeue_dead_letter_handler(
+    State(state): State<Arc<StripeState>>,
+    actor: AuditActor,
+    Path(payment_id): Path<String>,
+) -> Result<Json<StripeDeadLetterResponse>, ConnectifyError> {
+    info!(
+        "[ADMIN] Request to requeue the fulfillment of Stripe payment {}",
+        payment_id
+    );
+
+    let stored = dead_letter_store()
+        .get(STRIPE_PROVIDER, &payment_id)
+        .await?
+        .ok_or_else(|| {
+            ConnectifyError::NotFoundError(format!(
+                "No failed fulfillment of payment {}",
+                payment_id
+            ))
+        })?;
+    if stored.status == DeadLetterStatus::Delivered {
+        return Err(ConnectifyError::ConflictError(format!(
+            "Fulfillment of payment {} was already delivered",
+            payment_id
+        )));
+    }
+    let previous_status = stored.status;
+
+    let now = Utc::now();
+    let requeued = DeadLetter {
+        status: DeadLetterStatus::Pending,
+        attempts: 0,
+        next_attempt_at: now,
+        ..stored
+    };
+    let result = deliver_dead_letter(&state.config, requeued, now).await;
+    audit::record(
+        AuditEvent::new(
+            actor,
+            "fulfillment.requeue",
+            format!("stripe_payment:{}", payment_id),
+        )
+        .with_metadata("previous_status", previous_status.as_str())
+        .with_result(&result),
+    )
+    .await;
+    Ok(Json(result?.into()))
+}
+
+/// Admin handler to look up the Stripe customer of an email address, creating one if the
+/// client is new.
+///
+/// The customer is stored in the payment repository, so later checkouts with the email address
+/// are attached to it.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/admin/stripe/customers", // Path relative to /api
+    request_body = CustomerRequest,
+    responses(
+        (status = 200, description = "Existing or newly created customer", body = StripeCustomerResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 422, description = "Validation failed"),
+        (status = 502, description = "Stripe API error")
+    ),
+    tag = "Stripe Admin"
+))]
+pub async fn admin_find_or_create_customer_handler(
+    State(state): State<Arc<StripeState>>,
+    actor: AuditActor,
+    ValidatedJson(payload): ValidatedJson<CustomerRequest>,
+) -> Result<Json<StripeCustomerResponse>, ConnectifyError> {
+    info!(
+        "[ADMIN] Request for the Stripe customer of {}",
+        payload.email
+    );
+
+    if !state.config.use_stripe || state.config.stripe.is_none() {
+        return Err(ConnectifyError::ConfigError(
+            "Stripe service not configured or disabled".to_string(),
+        ));
+    }
+
+    let client = StripeClient::from_env()?;
+    let result = find_or_create_customer(&client, &payload.email, payload.name.as_deref()).await;
+    if let Some(customer) = result.as_ref().ok().filter(|customer| customer.created) {
+        audit::record(
+            AuditEvent::new(
+                actor,
+                "customer.create",
+                format!("stripe_customer:{}", customer.customer_id),
+            )
+            .with_metadata("email", customer.email.clone()),
+        )
+        .await;
+    }
+    Ok(Json(result?))
+}
+
+/// Admin handler to look up the Stripe customer of an email address.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/admin/stripe/customers", // Path relative to /api
+    params(CustomerLookupQuery),
+    responses(
+        (status = 200, description = "The customer of the email address", body = StripeCustomerResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "No customer with the email address"),
+        (status = 502, description = "Stripe API error")
+    ),
+    tag = "Stripe Admin"
+))]
+pub async fn admin_get_customer_handler(
+    State(state): State<Arc<StripeState>>,
+    Query(query): Query<CustomerLookupQuery>,
+) -> Result<Json<StripeCustomerResponse>, ConnectifyError> {
+    if !state.config.use_stripe || state.config.stripe.is_none() {
+        return Err(ConnectifyError::ConfigError(
+            "Stripe service not configured or disabled".to_string(),
+        ));
+    }
+
+    let client = StripeClient::from_env()?;
+    find_customer(&client, &query.email)
+        .await?
+        .map(Json)
+        .ok_or_else(|| StripeError::CustomerNotFound(query.email).into())
+}
+
+/// Axum handler to start saving a card of a client for repeat bookings.
+///
+/// The frontend confirms the returned SetupIntent with Stripe.js, which saves the card to the
+/// client's Stripe customer.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/stripe/setup-intents", // Path relative to /api
+    request_body = CreateSetupIntentRequest,
+    responses(
+        (status = 200, description = "SetupIntent created", body = CreateSetupIntentResponse),
+        (status = 422, description = "Validation failed"),
+        (status = 500, description = "Internal Server Error or Stripe API error")
+    ),
+    tag = "Stripe"
+))]
+pub async fn create_setup_intent_handler(
+    State(state): State<Arc<StripeState>>,
+    ValidatedJson(payload): ValidatedJson<CreateSetupIntentRequest>,
+) -> Result<Json<CreateSetupIntentResponse>, ConnectifyError> {
+    if !state.config.use_stripe || state.config.stripe.is_none() {
+        return Err(ConnectifyError::ConfigError(
+            "Stripe service not configured or disabled".to_string(),
+        ));
+    }
+    handle_json_result(create_setup_intent(&payload).await)
+}
+
+/// Admin handler to list the cards saved for a customer.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/admin/stripe/customers/{customer_id}/payment-methods", // Path relative to /api
+    params(("customer_id" = String, Path, description = "The Stripe customer id")),
+    responses(
+        (status = 200, description = "Saved cards of the customer", body = [SavedPaymentMethod]),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 502, description = "Stripe API error")
+    ),
+    tag = "Stripe Admin"
+))]
+pub async fn admin_list_payment_methods_handler(
+    State(state): State<Arc<StripeState>>,
+    Path(customer_id): Path<String>,
+) -> Result<Json<Vec<SavedPaymentMethod>>, ConnectifyError> {
+    if !state.config.use_stripe || state.config.stripe.is_none() {
+        return Err(ConnectifyError::ConfigError(
+            "Stripe service not configured or disabled".to_string(),
+        ));
+    }
+    handle_json_result(list_saved_payment_methods(&customer_id).await)
+}
+
+/// Admin handler to charge a saved card while the customer is not present.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/admin/stripe/payment-intents", // Path relative to /api
+    request_body = ChargeSavedPaymentMethodRequest,
+    responses(
+        (status = 200, description = "Saved card charged", body = ChargeSavedPaymentMethodResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 402, description = "Card declined or the customer has to authenticate"),
+        (status = 422, description = "Validation failed"),
+        (status = 502, description = "Stripe API error")
+    ),
+    tag = "Stripe Admin"
+))]
+pub async fn admin_charge_saved_payment_method_handler(
+    State(state): State<Arc<StripeState>>,
+    actor: AuditActor,
+    ValidatedJson(payload): ValidatedJson<ChargeSavedPaymentMethodRequest>,
+) -> Result<Json<ChargeSavedPaymentMethodResponse>, ConnectifyError> {
+    info!(
+        "[ADMIN] Request to charge saved payment method of customer {}",
+        payload.customer_id
+    );
+
+    if state.config.stripe.is_none() || !state.config.use_stripe {
+        return Err(ConnectifyError::ConfigError(
+            "Stripe service not configured or disabled".to_string(),
+        ));
+    }
+
+    let pricing_config = state.config.pricing.clone().unwrap_or_default();
+    let result = charge_saved_payment_method(&pricing_config, &payload).await;
+    audit::record(
+        AuditEvent::new(
+            actor,
+            "payment.charge",
+            format!("stripe_customer:{}", payload.customer_id),
+        )
+        .with_metadata("payment_method_id", payload.payment_method_id.clone())
+        .with_metadata("amount", payload.amount)
+        .with_metadata("reference", payload.client_reference_id.clone())
+        .with_result(&result),
+    )
+    .await;
+
+    map_json_error(
+        result.map(|payment_intent| ChargeSavedPaymentMethodResponse {
+            payment_intent_id: payment_intent.id,
+            status: payment_intent.status,
+            amount: payment_intent.amount,
+            currency: payment_intent.currency,
+        }),
+        |err| {
+            info!("[ADMIN] Error charging saved payment method: {}", err);
+            err.into()
+        },
+    )
+}
+
+/// Admin handler to open the Stripe Billing Portal for a known customer, where they manage
+/// their payment methods, invoices and subscriptions themselves.
+///
+/// Returns the portal URL to send to the customer; it expires after a short time.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/admin/stripe/billing-portal-sessions", // Path relative to /api
+    request_body = CreateBillingPortalSessionRequest,
+    responses(
+        (status = 200, description = "Billing Portal session created", body = BillingPortalSessionResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "No customer with the email address"),
+        (status = 422, description = "Validation failed"),
+        (status = 502, description = "Stripe API error")
+    ),
+    tag = "Stripe Admin"
+))]
+pub async fn admin_create_billing_portal_session_handler(
+    State(state): State<Arc<StripeState>>,
+    actor: AuditActor,
+    ValidatedJson(payload): ValidatedJson<CreateBillingPortalSessionRequest>,
+) -> Result<Json<BillingPortalSessionResponse>, ConnectifyError> {
+    info!(
+        "[ADMIN] Request for a Billing Portal session of {}",
+        payload.customer_email
+    );
+
+    let Some(stripe_config) = state
+        .config
+        .stripe
+        .as_ref()
+        .filter(|_| state.config.use_stripe)
+    else {
+        return Err(ConnectifyError::ConfigError(
+            "Stripe service not configured or disabled".to_string(),
+        ));
+    };
+
+    let result = create_billing_portal_session(stripe_config, &payload).await;
+    if let Ok(session) = &result {
+        audit::record(
+            AuditEvent::new(
+                actor,
+                "customer.portal",
+                format!("stripe_customer:{}", session.customer_id),
+            )
+            .with_metadata("email", payload.customer_email.clone()),
+        )
+        .await;
+    }
+    handle_json_result(result)
 }
 /**/
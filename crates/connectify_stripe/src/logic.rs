@@ -1,45 +1,100 @@
 // --- File: crates/connectify_stripe/src/logic.rs ---
-use chrono::{DateTime, Utc};
-use connectify_config::{AppConfig, StripeConfig}; //, PriceTier};
-use hmac::{Hmac, Mac};
+use axum::http::HeaderMap;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use connectify_config::{AppConfig, PricingConfig, StripeConfig}; //, PriceTier};
 use serde::{Deserialize, Serialize};
 #[allow(unused_imports)]
 #[cfg(feature = "openapi")]
 use serde_json::json;
-use sha2::Sha256;
 use std::{
-    collections::HashMap,
-    env,
+    collections::{BTreeMap, HashMap},
     sync::Arc,
-    time::{SystemTime, UNIX_EPOCH},
+    time::Duration,
 };
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+use validator::{Validate, ValidationError};
 // Import the StripeError from the error module
+use crate::client::{
+    AutomaticPaymentMethods, AutomaticTax, BillingPortalSession, BillingPortalSessionParams,
+    CheckoutLineItem, CheckoutSessionListQuery, CheckoutSessionParams, CreatedCheckoutSession,
+    CreatedFilter, Customer, CustomerDetailsFilter, CustomerListQuery, CustomerParams,
+    CustomerUpdate, Invoice, InvoiceCreation, PaymentIntent, PaymentIntentParams, PaymentMethod,
+    PaymentMethodListQuery, PriceData, ProductData, Refund, RefundParams, SetupIntent,
+    SetupIntentParams, StripeClient, SubscriptionData,
+};
 use crate::error::StripeError;
 
 // Import the HTTP client from connectify_common
+use connectify_common::cache::{self, cache};
+use connectify_common::clock::{system_clock, Clock, SharedClock, SystemClock};
+use connectify_common::dead_letters::{dead_letter_store, DeadLetter, DeadLetterStatus};
+use connectify_common::events::{self, PaymentRefunded, PaymentSucceeded, SubscriptionChanged};
+use connectify_common::holds::slot_hold_store;
+use connectify_common::models::{CursorQuery, Page, SortOrder, MAX_PAGE_SIZE};
+use connectify_common::orders::{fulfillment_order_store, FulfillmentOrder};
+use connectify_common::payments::{
+    normalize_email, payment_customer_store, payment_record_store, PaymentCustomer, PaymentRecord,
+};
+use connectify_common::request_id::RequestIdExt;
+use connectify_common::retry::RetryPolicy;
+use connectify_common::services::{PaymentIntentResult, RefundResult};
+use connectify_common::webhook::{
+    constant_time_eq, hmac_sha256_hex, signature_header, verify_hmac_sha256_hex, VerifiedEvent,
+    WebhookError, WebhookVerifier,
+};
 use connectify_common::HTTP_CLIENT;
 
 // Conditionally import ToSchema if openapi feature is enabled
 #[cfg(feature = "openapi")]
 use utoipa::ToSchema;
 
+/// Provider name of Stripe in the shared stores.
+pub(crate) const STRIPE_PROVIDER: &str = "stripe";
+
 // --- Data Structures ---
 
+/// Whether a Checkout Session charges once or starts a subscription.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum CheckoutMode {
+    /// A one-time payment priced by the configured price tiers
+    #[default]
+    Payment,
+    /// A recurring payment of one of the configured subscription plans
+    Subscription,
+}
+
+impl CheckoutMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckoutMode::Payment => "payment",
+            CheckoutMode::Subscription => "subscription",
+        }
+    }
+}
+
 /// Request from our frontend to create a Stripe Checkout Session.
 // ** Added openapi derive **
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Validate)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[validate(schema(function = "validate_checkout_mode"))]
 pub struct CreateCheckoutSessionRequest {
     #[cfg_attr(feature = "openapi", schema(example = "Room Booking"))]
+    #[validate(length(min = 1))]
     pub product_name_override: Option<String>,
     #[cfg_attr(feature = "openapi", schema(example = 5000))]
+    #[validate(range(min = 1))]
     pub amount_override: Option<i64>,
+    /// Currency to pay in, which the booked price tier needs a price in (default: the tier's
+    /// currency)
     #[cfg_attr(feature = "openapi", schema(example = "CHF"))]
+    #[validate(length(equal = 3))]
     pub currency_override: Option<String>,
     // --- Fulfillment Information ---
     /// Type of fulfillment to trigger (e.g., "gcal_booking", "twilio_session_setup")
     #[cfg_attr(feature = "openapi", schema(example = "gcal_booking"))]
+    #[validate(length(min = 1))]
     pub fulfillment_type: String,
     /// JSON data specific to the fulfillment_type
     #[cfg_attr(feature = "openapi", schema(example = json!({
@@ -53,6 +108,37 @@ pub struct CreateCheckoutSessionRequest {
     // Stripe's client_reference_id can also be used to link to your internal order
     #[cfg_attr(feature = "openapi", schema(example = "my_internal_order_123"))]
     pub client_reference_id: Option<String>,
+
+    /// `payment` (default) or `subscription`
+    #[serde(default)]
+    pub mode: CheckoutMode,
+    /// The recurring price of a `subscription` checkout, one of the configured subscription plans
+    #[cfg_attr(feature = "openapi", schema(example = "price_1N..."))]
+    #[validate(length(min = 1))]
+    pub price_id: Option<String>,
+
+    /// Email address of the client; their Stripe customer is attached to the session, so a
+    /// returning client's payments are linked
+    #[cfg_attr(feature = "openapi", schema(example = "anna@example.com"))]
+    #[validate(email)]
+    pub customer_email: Option<String>,
+    /// Name of the customer, if one is created for `customer_email`
+    #[cfg_attr(feature = "openapi", schema(example = "Anna Muster"))]
+    #[validate(length(min = 1))]
+    pub customer_name: Option<String>,
+}
+
+/// Checks that subscription checkouts name their price, and payment checkouts don't.
+fn validate_checkout_mode(request: &CreateCheckoutSessionRequest) -> Result<(), ValidationError> {
+    match (request.mode, &request.price_id) {
+        (CheckoutMode::Subscription, None) => {
+            Err(ValidationError::new("subscription_requires_price_id"))
+        }
+        (CheckoutMode::Payment, Some(_)) => {
+            Err(ValidationError::new("price_id_requires_subscription"))
+        }
+        _ => Ok(()),
+    }
 }
 #[allow(dead_code)]
 #[derive(Deserialize, Debug)]
@@ -115,6 +201,12 @@ pub struct StripeCheckoutSessionObject {
     pub id: String,                // Checkout Session ID (cs_...)
     pub object: String,            // "checkout.session"
     pub amount_total: Option<i64>, // Total amount in cents
+    #[serde(default)]
+    pub amount_subtotal: Option<i64>, // Amount before taxes and discounts
+    #[serde(default)]
+    pub invoice: Option<String>, // Invoice ID (in_...) if invoice creation was enabled
+    #[serde(default)]
+    pub total_details: Option<StripeTotalDetails>, // Taxes, discounts and shipping
     pub currency: Option<String>,
     pub customer: Option<String>, // Customer ID (cus_...) if created
     pub customer_details: Option<StripeCustomerDetails>,
@@ -125,41 +217,198 @@ pub struct StripeCheckoutSessionObject {
     pub success_url: Option<String>,
     pub cancel_url: Option<String>,
     pub client_reference_id: Option<String>,
+    /// "payment" or "subscription"
+    #[serde(default)]
+    pub mode: Option<String>,
+    /// Subscription ID (sub_...) of a subscription checkout
+    #[serde(default)]
+    pub subscription: Option<String>,
+}
+
+/// Specific structure for the `data.object` of "customer.subscription.*" events.
+#[derive(Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct StripeSubscriptionObject {
+    pub id: String,               // Subscription ID (sub_...)
+    pub status: String,           // e.g., "active", "past_due", "canceled"
+    pub customer: Option<String>, // Customer ID (cus_...)
+    #[serde(default)]
+    pub cancel_at_period_end: bool,
+    pub current_period_end: Option<i64>,
+    #[serde(default)]
+    pub items: Option<StripeListObject<StripeSubscriptionItem>>,
+    pub metadata: Option<HashMap<String, String>>, // Metadata passed in subscription_data
+}
+
+/// An item of a subscription, with the subscribed price.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct StripeSubscriptionItem {
+    pub id: String,
+    pub price: Option<StripePrice>,
+    /// End of the current billing period; newer API versions only set it on the items
+    pub current_period_end: Option<i64>,
+}
+
+/// A Stripe price, as referenced by subscription items.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct StripePrice {
+    pub id: String,
 }
+
+impl StripeSubscriptionObject {
+    /// The subscribed price, for the single-item subscriptions created by checkout.
+    pub fn price_id(&self) -> Option<&str> {
+        self.first_item()?
+            .price
+            .as_ref()
+            .map(|price| price.id.as_str())
+    }
+
+    /// End of the current billing period.
+    pub fn current_period_end(&self) -> Option<i64> {
+        self.current_period_end
+            .or_else(|| self.first_item()?.current_period_end)
+    }
+
+    fn first_item(&self) -> Option<&StripeSubscriptionItem> {
+        self.items.as_ref()?.data.first()
+    }
+}
+
+/// Specific structure for the `data.object` of "payment_intent.*" events.
+#[derive(Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct StripePaymentIntentObject {
+    pub id: String,  // PaymentIntent ID (pi_...)
+    pub amount: i64, // Amount in cents
+    pub currency: String,
+    pub status: String, // e.g., "succeeded", "requires_payment_method"
+    pub metadata: Option<HashMap<String, String>>, // Metadata passed at creation
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct StripeCustomerDetails {
     pub email: Option<String>,
     pub name: Option<String>,
     pub phone: Option<String>,
-    // address: Option<StripeAddress>, // Define StripeAddress if needed
+    #[serde(default)]
+    pub address: Option<StripeAddress>, // Billing address, which taxes are calculated for
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct StripeAddress {
+    pub line1: Option<String>,
+    pub line2: Option<String>,
+    pub postal_code: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub country: Option<String>, // Two-letter country code, e.g. "CH"
+}
+
+/// Amounts added to or taken off the subtotal of a Checkout Session.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct StripeTotalDetails {
+    pub amount_tax: Option<i64>,
+    pub amount_discount: Option<i64>,
+    pub amount_shipping: Option<i64>,
 }
 
 // --- Webhook Processing Logic ---
 
-/// Verifies the signature of an incoming Stripe webhook request.
-///
-/// # Arguments
-/// * `payload_bytes` - The raw request body bytes.
-/// * `sig_header` - The value of the 'Stripe-Signature' header.
-/// * `secret` - Your Stripe webhook signing secret (whsec_...).
+/// Header carrying the Stripe webhook signature.
+pub const STRIPE_SIGNATURE_HEADER: &str = "Stripe-Signature";
+
+/// Seconds a webhook signature is accepted before or after its timestamp, like Stripe's
+/// libraries do.
+pub const DEFAULT_WEBHOOK_TOLERANCE_SECONDS: i64 = 300;
+
+/// The webhook timestamp tolerance configured in the Stripe config, or the default.
+pub fn webhook_tolerance_seconds(stripe_config: Option<&StripeConfig>) -> i64 {
+    stripe_config
+        .and_then(|stripe_config| stripe_config.webhook_tolerance_seconds)
+        .unwrap_or(DEFAULT_WEBHOOK_TOLERANCE_SECONDS)
+}
+
+/// Verifies Stripe webhook signatures (`Stripe-Signature: t=<timestamp>,v1=<hmac>`).
+pub struct StripeWebhookVerifier {
+    secret: String,
+    /// Clock the signature timestamp is compared against.
+    clock: SharedClock,
+    /// Seconds the signature timestamp may differ from the clock.
+    tolerance_seconds: i64,
+}
+
+impl StripeWebhookVerifier {
+    /// Create a verifier for the webhook signing secret (whsec_...).
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self {
+            secret: secret.into(),
+            clock: system_clock(),
+            tolerance_seconds: DEFAULT_WEBHOOK_TOLERANCE_SECONDS,
+        }
+    }
+
+    /// Accept signatures whose timestamp differs from the clock by up to `seconds`.
+    pub fn with_tolerance(mut self, seconds: i64) -> Self {
+        self.tolerance_seconds = seconds;
+        self
+    }
+
+    /// Use another clock for the timestamp tolerance check, e.g. in tests.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+impl WebhookVerifier for StripeWebhookVerifier {
+    fn verify(&self, payload: &[u8], headers: &HeaderMap) -> Result<VerifiedEvent, WebhookError> {
+        let sig_header = signature_header(headers, STRIPE_SIGNATURE_HEADER)?;
+        let timestamp = verify_signature_header(
+            payload,
+            sig_header,
+            &self.secret,
+            self.clock.timestamp(),
+            self.tolerance_seconds,
+        )?;
+        Ok(VerifiedEvent {
+            provider: "stripe",
+            payload: payload.to_vec(),
+            timestamp,
+        })
+    }
+}
+
+/// Check a `Stripe-Signature` header value against the payload.
 ///
-/// Returns Ok(()) if the signature is valid, otherwise StripeError::WebhookSignatureError.
-pub fn verify_stripe_signature(
+/// Signatures older or newer than `tolerance_seconds` are rejected, so captured requests can't
+/// be replayed later on. Returns the signing timestamp, or `None` if the shared fulfillment
+/// secret was used instead.
+fn verify_signature_header(
     payload_bytes: &[u8],
-    sig_header: Option<&str>,
+    sig_header_value: &str,
     secret: &str,
-) -> Result<(), StripeError> {
-    let sig_header_value = sig_header.ok_or_else(|| {
-        StripeError::WebhookSignatureError("Missing Stripe-Signature header".to_string())
-    })?;
+    current_timestamp: i64,
+    tolerance_seconds: i64,
+) -> Result<Option<i64>, WebhookError> {
     // Debug: Log the received signature header
     debug!("Stripe-Signature Header: {}", sig_header_value);
     if let Ok(shared_secret) = std::env::var("FULFIL_SHARED_SECRET") {
         if !shared_secret.is_empty() && sig_header_value == shared_secret {
-            return Ok(());
+            return Ok(None);
         }
     }
+    if secret.is_empty() {
+        return Err(WebhookError::Config(
+            "Stripe webhook secret is empty".to_string(),
+        ));
+    }
+
     let mut timestamp_str: Option<&str> = None;
     let mut v1_signatures_hex: Vec<&str> = Vec::new();
 
@@ -174,96 +423,82 @@ pub fn verify_stripe_signature(
         }
     }
 
-    let parsed_timestamp = timestamp_str
-        .ok_or_else(|| {
-            StripeError::WebhookSignatureError(
-                "Missing timestamp 't' in Stripe-Signature".to_string(),
-            )
-        })?
-        .parse::<i64>()
-        .map_err(|_| {
-            StripeError::WebhookSignatureError(
-                "Invalid timestamp format in Stripe-Signature".to_string(),
-            )
-        })?;
+    let timestamp_str = timestamp_str.ok_or_else(|| {
+        WebhookError::MissingSignature("Missing timestamp 't' in Stripe-Signature".to_string())
+    })?;
+    let parsed_timestamp = timestamp_str.parse::<i64>().map_err(|_| {
+        WebhookError::MissingSignature("Invalid timestamp format in Stripe-Signature".to_string())
+    })?;
 
     if v1_signatures_hex.is_empty() {
-        return Err(StripeError::WebhookSignatureError(
+        return Err(WebhookError::MissingSignature(
             "Missing v1 signature in Stripe-Signature".to_string(),
         ));
     }
     // Debug: Log parsed components
     debug!("[DEBUG] Parsed Timestamp (t): {}", parsed_timestamp);
-    info!(
-        "[DEBUG] Provided Signatures (v1 list): {:?}",
-        v1_signatures_hex
-    );
 
-    // Check timestamp tolerance (e.g., 10 minutes)
-    let current_timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_secs() as i64;
-    const TOLERANCE_SECONDS: i64 = 600; // 10 minutes
-    if (current_timestamp - parsed_timestamp).abs() > TOLERANCE_SECONDS {
-        info!(
-            "[WARN] Timestamp outside tolerance. Current: {}, Event: {}, Diff: {}",
-            current_timestamp,
-            parsed_timestamp,
-            (current_timestamp - parsed_timestamp).abs()
+    let age = (current_timestamp - parsed_timestamp).abs();
+    if age > tolerance_seconds {
+        warn!(
+            "Stripe signature timestamp outside tolerance. Current: {}, Event: {}, Diff: {}s, Tolerance: {}s",
+            current_timestamp, parsed_timestamp, age, tolerance_seconds
         );
-        // return Err(StripeError::WebhookSignatureError("Timestamp outside tolerance".to_string())); // Consider re-enabling for production
+        return Err(WebhookError::InvalidSignature(format!(
+            "Timestamp outside the tolerance of {} seconds",
+            tolerance_seconds
+        )));
     }
 
-    // Construct the signed payload string
-    let signed_payload_string = format!(
-        "{}.{}",
-        timestamp_str.unwrap(), // Use the original string timestamp from header
-        String::from_utf8_lossy(payload_bytes)
-    );
-    info!("[DEBUG] String to Sign: '{}'", signed_payload_string);
-
-    type HmacSha256 = Hmac<Sha256>;
-    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).map_err(|_| {
-        StripeError::WebhookSignatureError("Invalid webhook secret format for HMAC".to_string())
-    })?;
-    mac.update(signed_payload_string.as_bytes());
-    let expected_signature_bytes = mac.finalize().into_bytes();
-    let calculated_signature_hex = hex::encode(expected_signature_bytes);
-
-    // Debug: Log calculated signature
-    info!("[DEBUG] Calculated Signature: {}", calculated_signature_hex);
+    // The signed payload is "<timestamp>.<raw body>", using the original timestamp string
+    let mut signed_payload = Vec::with_capacity(timestamp_str.len() + 1 + payload_bytes.len());
+    signed_payload.extend_from_slice(timestamp_str.as_bytes());
+    signed_payload.push(b'.');
+    signed_payload.extend_from_slice(payload_bytes);
 
     // ** Iterate through all provided v1 signatures and check for a match **
-    for provided_sig_hex in v1_signatures_hex {
-        if constant_time_eq(
-            calculated_signature_hex.as_bytes(),
-            provided_sig_hex.as_bytes(),
-        ) {
-            return Ok(()); // Signature matches one of the v1 signatures
-        }
+    if v1_signatures_hex
+        .iter()
+        .any(|sig| verify_hmac_sha256_hex(secret.as_bytes(), &signed_payload, sig))
+    {
+        return Ok(Some(parsed_timestamp));
     }
     // If no match was found after checking all v1 signatures
-    info!(
-        "[ERROR] Stripe signature mismatch. Calculated: {}, Provided in header did not match.",
-        calculated_signature_hex
-    );
-    Err(StripeError::WebhookSignatureError(
+    info!("[ERROR] Stripe signature mismatch, no v1 signature in the header matched.");
+    Err(WebhookError::InvalidSignature(
         "Signature mismatch".to_string(),
     ))
 }
 
-/// Helper for constant-time string comparison.
-fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
-    if a.len() != b.len() {
-        return false;
-    }
-    let mut result = 0;
-    for (x, y) in a.iter().zip(b.iter()) {
-        result |= x ^ y;
-    }
-    result == 0
+/// Verifies the signature of an incoming Stripe webhook request.
+///
+/// # Arguments
+/// * `payload_bytes` - The raw request body bytes.
+/// * `sig_header` - The value of the 'Stripe-Signature' header.
+/// * `secret` - Your Stripe webhook signing secret (whsec_...).
+/// * `tolerance_seconds` - How far the signature timestamp may be off the current time.
+///
+/// Returns Ok(()) if the signature is valid, otherwise StripeError::WebhookSignatureError.
+/// Prefer [`StripeWebhookVerifier`], which reads the header itself.
+pub fn verify_stripe_signature(
+    payload_bytes: &[u8],
+    sig_header: Option<&str>,
+    secret: &str,
+    tolerance_seconds: i64,
+) -> Result<(), StripeError> {
+    let sig_header_value = sig_header.ok_or_else(|| {
+        StripeError::WebhookSignatureError("Missing Stripe-Signature header".to_string())
+    })?;
+    verify_signature_header(
+        payload_bytes,
+        sig_header_value,
+        secret,
+        SystemClock.timestamp(),
+        tolerance_seconds,
+    )?;
+    Ok(())
 }
+
 /// Processes a verified Stripe webhook event.
 pub async fn process_stripe_webhook(
     event: StripeEvent,
@@ -282,137 +517,98 @@ pub async fn process_stripe_webhook(
 
             info!("Checkout Session ID: {}", session.id);
             info!("Payment Status: {:?}", session.payment_status);
+            if let Err(e) = cache().invalidate(&session_cache_key(&session.id)).await {
+                error!("Failed to invalidate cached session {}: {}", session.id, e);
+            }
             info!("Metadata: {:?}", session.metadata);
             info!("Client Reference ID: {:?}", session.client_reference_id);
+            remember_session_customer(&session).await;
 
             if session.payment_status.as_deref() == Some("paid") {
                 info!(
                     "✅ Payment for Checkout Session {} was successful.",
                     session.id
                 );
-                // --- Trigger Fulfillment ---
-                let metadata = session.metadata.as_ref();
-                let fulfillment_type = metadata.and_then(|m| m.get("ff_type").cloned());
-                let fulfillment_data_json_str =
-                    metadata.and_then(|m| m.get("ff_data_json").cloned());
-
-                if let (Some(ff_type), Some(ff_data_str)) =
-                    (fulfillment_type, fulfillment_data_json_str)
+                events::publish(PaymentSucceeded {
+                    provider: "stripe".to_string(),
+                    payment_id: session.id.clone(),
+                    amount: session.amount_total,
+                    currency: session.currency.clone(),
+                    reference: session.client_reference_id.clone(),
+                });
+                if let Err(e) = payment_record_store()
+                    .record_payment(session_payment_record(&session))
+                    .await
                 {
-                    // Deserialize the ff_data_json string back into a serde_json::Value
-                    let fulfillment_payload_value: serde_json::Value = {
-                        // First parse the original JSON
-                        let mut base_value: serde_json::Value = serde_json::from_str(&ff_data_str)
-                            .map_err(|e| {
-                                StripeError::WebhookProcessingError(format!(
-                                    "Failed to parse ff_data_json: {}",
-                                    e
-                                ))
-                            })?;
-
-                        // Then add payment information to it
-                        if let serde_json::Value::Object(ref mut map) = base_value {
-                            map.insert(
-                                "payment_id".to_string(),
-                                serde_json::Value::String(session.id.clone()),
-                            );
-                            map.insert(
-                                "payment_method".to_string(),
-                                serde_json::Value::String("stripe".to_string()),
-                            );
-                            if let Some(amount) = session.amount_total {
-                                map.insert(
-                                    "payment_amount".to_string(),
-                                    serde_json::Value::Number(serde_json::Number::from(amount)),
-                                );
-                            }
-                        }
-
-                        base_value
-                    };
-                    debug!("fulfillment payload: {:?}", fulfillment_payload_value);
-
-                    if let Some(fulfillment_cfg) = app_config
-                        .fulfillment
-                        .as_ref()
-                        .and_then(|f| f.shared_secret.as_ref())
-                    {
-                        let fulfillment_base_url = format!(
-                            // Construct base URL
-                            "http://{}:{}",
-                            app_config.server.host, app_config.server.port
-                        );
-
-                        // Construct specific fulfillment endpoint URL based on ff_type
-                        let fulfillment_endpoint_path = match ff_type.as_str() {
-                            "gcal_booking" => "/api/fulfill/gcal-booking",
-                            // "twilio_session" => "/api/fulfill/twilio-session", // Example
-                            "adhoc_gcal_twilio" => "/api/fulfill/adhoc-gcal-twilio",
-                            // "twilio_session" => "/api/fulfill/twilio-session", // Example
-                            _ => {
-                                error!(
-                                    "[Stripe Webhook] Unknown fulfillment_type in metadata: {}",
-                                    ff_type
-                                );
-                                return Err(StripeError::WebhookProcessingError(format!(
-                                    "Unknown fulfillment type: {}",
-                                    ff_type
-                                )));
-                            }
-                        };
-                        let fulfillment_url =
-                            format!("{}{}", fulfillment_base_url, fulfillment_endpoint_path);
-
-                        info!("[Stripe Webhook] Calling fulfillment service at {} for type '{}', session {}", fulfillment_url, ff_type, session.id);
-
-                        let client = HTTP_CLIENT.clone(); // Use the static client
-                        match client
-                            .post(&fulfillment_url)
-                            .header("X-Internal-Auth-Secret", fulfillment_cfg) // Use the shared secret
-                            .json(&fulfillment_payload_value) // Send the original JSON Value
-                            .send()
-                            .await
-                        {
-                            Ok(resp) if resp.status().is_success() => {
-                                info!("[Stripe Webhook] Fulfillment for session {} (type: {}) triggered successfully.", session.id, ff_type);
-                            }
-                            Ok(resp) => {
-                                let status = resp.status(); // Store the status before consuming the response
-                                let err_text = resp.text().await.unwrap_or_else(|_| {
-                                    "Unknown error from fulfillment service".to_string()
-                                });
-                                error!("[Stripe Webhook] Fulfillment call for session {} (type: {}) failed: {} - {}", session.id, ff_type, status, err_text);
-                                return Err(StripeError::FulfillmentError(format!(
-                                    "Fulfillment service call failed: {} - {}",
-                                    status, err_text
-                                )));
-                            }
-                            Err(e) => {
-                                info!("[Stripe Webhook] Error calling fulfillment service for session {}: {}", session.id, e);
-                                return Err(StripeError::FulfillmentError(format!(
-                                    "Error calling fulfillment service: {}",
-                                    e
-                                )));
-                            }
-                        }
-                    } else {
-                        info!("[Stripe Webhook] Fulfillment shared secret not configured. Cannot call fulfillment service for session {}.", session.id);
-                        return Err(StripeError::ConfigError);
-                    }
-                } else {
-                    info!("[Stripe Webhook] Missing 'ff_type' or 'ff_data_json' in metadata for session {}. Cannot trigger fulfillment.", session.id);
-                    // Decide if this is an error or just no fulfillment needed
-                    return Err(StripeError::MissingFulfillmentData);
+                    error!("Failed to record payment of session {}: {}", session.id, e);
                 }
+                if session.mode.as_deref() == Some("subscription") {
+                    info!(
+                        "[Stripe Webhook] Checkout Session {} started subscription {:?}, handled by its customer.subscription events.",
+                        session.id, session.subscription
+                    );
+                    return Ok(());
+                }
+                let invoice_url = match &session.invoice {
+                    Some(invoice_id) => invoice_pdf_url(invoice_id).await,
+                    None => None,
+                };
+                trigger_fulfillment(
+                    &app_config,
+                    &session.id,
+                    session.amount_total,
+                    session.metadata.as_ref(),
+                    invoice_url.as_deref(),
+                )
+                .await?;
             } else {
                 info!("ℹ️ Checkout session {} completed, but payment status is: {:?}. No fulfillment action taken.", session.id, session.payment_status);
             }
         }
+        "checkout.session.expired" => {
+            let session: StripeCheckoutSessionObject = serde_json::from_value(event.data.object)
+                .map_err(|e| {
+                    StripeError::WebhookProcessingError(format!(
+                        "Failed to parse checkout session object: {}",
+                        e
+                    ))
+                })?;
+            info!(
+                "[Stripe Webhook] Checkout Session {} expired unpaid.",
+                session.id
+            );
+            release_session_hold(&session.id, session.metadata.as_ref()).await?;
+        }
         "payment_intent.succeeded" => {
-            let payment_intent_id: Option<&str> =
-                event.data.object.get("id").and_then(|v| v.as_str());
-            info!("PaymentIntent succeeded: {:?}", payment_intent_id);
-            // You might handle this if you're working directly with PaymentIntents
+            let payment_intent: StripePaymentIntentObject =
+                serde_json::from_value(event.data.object).map_err(|e| {
+                    StripeError::WebhookProcessingError(format!(
+                        "Failed to parse payment intent object: {}",
+                        e
+                    ))
+                })?;
+            info!("PaymentIntent succeeded: {}", payment_intent.id);
+            // Only PaymentIntents of the Payment Element carry fulfillment metadata; those of
+            // Checkout Sessions are fulfilled by checkout.session.completed
+            let metadata = payment_intent.metadata.as_ref();
+            if !metadata.is_some_and(|metadata| metadata.contains_key("ff_type")) {
+                return Ok(());
+            }
+            events::publish(PaymentSucceeded {
+                provider: STRIPE_PROVIDER.to_string(),
+                payment_id: payment_intent.id.clone(),
+                amount: Some(payment_intent.amount),
+                currency: Some(payment_intent.currency.clone()),
+                reference: metadata.and_then(|metadata| metadata.get("reference").cloned()),
+            });
+            trigger_fulfillment(
+                &app_config,
+                &payment_intent.id,
+                Some(payment_intent.amount),
+                metadata,
+                None,
+            )
+            .await?;
         }
         "payment_intent.payment_failed" => {
             let payment_intent_id: Option<&str> =
@@ -420,6 +616,37 @@ pub async fn process_stripe_webhook(
             info!("PaymentIntent failed: {:?}", payment_intent_id);
             // Handle failed payment attempts if necessary
         }
+        event_type if event_type.starts_with("customer.subscription.") => {
+            let change = event_type.trim_start_matches("customer.subscription.");
+            let subscription: StripeSubscriptionObject = serde_json::from_value(event.data.object)
+                .map_err(|e| {
+                    StripeError::WebhookProcessingError(format!(
+                        "Failed to parse subscription object: {}",
+                        e
+                    ))
+                })?;
+            info!(
+                "[Stripe Webhook] Subscription {} {}: status '{}', price {:?}, cancel at period end: {}",
+                subscription.id,
+                change,
+                subscription.status,
+                subscription.price_id(),
+                subscription.cancel_at_period_end
+            );
+            events::publish(SubscriptionChanged {
+                provider: "stripe".to_string(),
+                subscription_id: subscription.id.clone(),
+                change: change.to_string(),
+                status: subscription.status.clone(),
+                customer: subscription.customer.clone(),
+                price_id: subscription.price_id().map(String::from),
+                current_period_end: subscription.current_period_end(),
+                reference: subscription
+                    .metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.get("reference").cloned()),
+            });
+        }
         // Add more event types as needed
         _ => {
             info!("Received unhandled Stripe event type: {}", event.event_type);
@@ -428,127 +655,605 @@ pub async fn process_stripe_webhook(
     Ok(())
 }
 
-/// Creates a Stripe Checkout Session.
-/// Creates a Stripe Checkout Session.
-pub async fn create_checkout_session(
-    stripe_config: &StripeConfig,
-    request_data: CreateCheckoutSessionRequest,
-) -> Result<CreateCheckoutSessionResponse, StripeError> {
-    info!(
-        "[Stripe Logic] Creating Checkout Session for fulfillment type: {}",
-        request_data.fulfillment_type
-    );
+/// The invoice of a payment.
+async fn fetch_invoice(invoice_id: &str) -> Result<Invoice, StripeError> {
+    StripeClient::from_env()?
+        .get("get_invoice", &format!("invoices/{}", invoice_id))
+        .await
+}
 
-    let stripe_secret_key = env::var("STRIPE_SECRET_KEY").map_err(|_| StripeError::ConfigError)?;
+/// The PDF URL of an invoice, for the booking record; a missing invoice doesn't hold up the
+/// fulfillment.
+async fn invoice_pdf_url(invoice_id: &str) -> Option<String> {
+    match fetch_invoice(invoice_id).await {
+        Ok(invoice) => invoice.invoice_pdf,
+        Err(e) => {
+            error!("Failed to retrieve invoice {}: {}", invoice_id, e);
+            None
+        }
+    }
+}
 
-    // --- Determine Price and Product Name based on fulfillment_data and price_tiers ---
-    let unit_amount: i64;
-    let product_name: String;
-    let currency: String;
+/// Metadata key of the fulfillment order of a payment.
+const FULFILLMENT_ORDER_KEY: &str = "ff_order_id";
 
-    if request_data.fulfillment_type == "gcal_booking"
-        || request_data.fulfillment_type == "adhoc_gcal_twilio"
-    {
-        let start_time_str = request_data
-            .fulfillment_data
-            .get("start_time")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| {
-                StripeError::InvalidFulfillmentDataForPricing(
-                    "Missing start_time in fulfillment_data for GCal-based booking".to_string(),
-                )
-            })?;
-        let end_time_str = request_data
-            .fulfillment_data
-            .get("end_time")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| {
-                StripeError::InvalidFulfillmentDataForPricing(
-                    "Missing end_time in fulfillment_data for GCal-based booking".to_string(),
-                )
-            })?;
+/// Stores the fulfillment data of a payment as an order, whose ID is put into the payment's
+/// metadata instead of the data, as Stripe limits metadata values to 500 characters.
+fn fulfillment_order(
+    fulfillment_type: &str,
+    fulfillment_data: &serde_json::Value,
+) -> Result<FulfillmentOrder, StripeError> {
+    let payload = serde_json::to_string(fulfillment_data).map_err(|e| {
+        StripeError::InternalError(format!("Failed to serialize fulfillment_data: {}", e))
+    })?;
+    Ok(FulfillmentOrder::new(fulfillment_type, payload, Utc::now()))
+}
 
-        let start_dt = DateTime::parse_from_rfc3339(start_time_str)
-            .map_err(|e| {
-                StripeError::InvalidFulfillmentDataForPricing(format!(
-                    "Invalid start_time format: {}",
-                    e
-                ))
-            })?
-            .with_timezone(&Utc);
-        let end_dt = DateTime::parse_from_rfc3339(end_time_str)
-            .map_err(|e| {
-                StripeError::InvalidFulfillmentDataForPricing(format!(
-                    "Invalid end_time format: {}",
-                    e
-                ))
-            })?
-            .with_timezone(&Utc);
+/// Saves a fulfillment order before the payment referencing it is created.
+pub(crate) async fn save_fulfillment_order(order: FulfillmentOrder) -> Result<(), StripeError> {
+    let order_id = order.order_id.clone();
+    fulfillment_order_store().save(order).await.map_err(|e| {
+        StripeError::InternalError(format!(
+            "Failed to store fulfillment order {}: {}",
+            order_id, e
+        ))
+    })
+}
 
-        if end_dt <= start_dt {
-            return Err(StripeError::InvalidFulfillmentDataForPricing(
-                "End time must be after start time.".to_string(),
-            ));
-        }
-        let duration_minutes = (end_dt - start_dt).num_minutes();
+/// The JSON fulfillment data of a payment, loaded from its fulfillment order or, for payments
+/// created before orders were stored, from its `ff_data_json` metadata.
+async fn fulfillment_data_json(
+    metadata: Option<&HashMap<String, String>>,
+) -> Result<Option<String>, StripeError> {
+    let Some(metadata) = metadata else {
+        return Ok(None);
+    };
+    let Some(order_id) = metadata.get(FULFILLMENT_ORDER_KEY) else {
+        return Ok(metadata.get("ff_data_json").cloned());
+    };
+    let order = fulfillment_order_store().get(order_id).await.map_err(|e| {
+        StripeError::WebhookProcessingError(format!(
+            "Failed to load fulfillment order {}: {}",
+            order_id, e
+        ))
+    })?;
+    if order.is_none() {
+        error!("[Stripe Webhook] Fulfillment order {} not found.", order_id);
+    }
+    Ok(order.map(|order| order.payload))
+}
 
-        let tier = stripe_config
-            .price_tiers
-            .iter()
-            .find(|t| t.duration_minutes == duration_minutes)
-            .ok_or_else(|| StripeError::NoMatchingPriceTier(duration_minutes))?;
+/// Calls the fulfillment endpoint of a paid payment, as described by its `ff_type` metadata
+/// and its fulfillment order.
+async fn trigger_fulfillment(
+    app_config: &AppConfig,
+    payment_id: &str,
+    amount: Option<i64>,
+    metadata: Option<&HashMap<String, String>>,
+    invoice_url: Option<&str>,
+) -> Result<(), StripeError> {
+    let fulfillment_type = metadata.and_then(|m| m.get("ff_type").cloned());
+    let fulfillment_data_json_str = fulfillment_data_json(metadata).await?;
+
+    if let (Some(ff_type), Some(ff_data_str)) = (fulfillment_type, fulfillment_data_json_str) {
+        // Deserialize the ff_data_json string back into a serde_json::Value
+        let fulfillment_payload_value: serde_json::Value = {
+            // First parse the original JSON
+            let mut base_value: serde_json::Value =
+                serde_json::from_str(&ff_data_str).map_err(|e| {
+                    StripeError::WebhookProcessingError(format!(
+                        "Failed to parse ff_data_json: {}",
+                        e
+                    ))
+                })?;
 
-        unit_amount = tier.unit_amount;
-        // Use product_name from tier, fallback to summary from fulfillment_data, then a generic default
-        product_name = tier.product_name.clone().unwrap_or_else(|| {
-            request_data
-                .fulfillment_data
-                .get("summary")
-                .and_then(|v| v.as_str())
-                .map(String::from)
-                .unwrap_or_else(|| format!("Service - {} min", duration_minutes))
-        });
-        currency = tier
-            .currency
-            .clone()
-            .unwrap_or_else(|| {
-                stripe_config
-                    .default_currency
-                    .clone()
-                    .unwrap_or_else(|| "chf".to_string())
-            })
-            .to_lowercase();
+            // Then add payment information to it
+            if let serde_json::Value::Object(ref mut map) = base_value {
+                map.insert(
+                    "payment_id".to_string(),
+                    serde_json::Value::String(payment_id.to_string()),
+                );
+                map.insert(
+                    "payment_method".to_string(),
+                    serde_json::Value::String("stripe".to_string()),
+                );
+                if let Some(amount) = amount {
+                    map.insert(
+                        "payment_amount".to_string(),
+                        serde_json::Value::Number(serde_json::Number::from(amount)),
+                    );
+                }
+                if let Some(invoice_url) = invoice_url {
+                    map.insert(
+                        "invoice_url".to_string(),
+                        serde_json::Value::String(invoice_url.to_string()),
+                    );
+                }
+            }
 
-        info!("[Stripe Logic] Type: {}. Duration: {} mins. Tier: amount={}, product='{}', currency='{}'",
-                 request_data.fulfillment_type, duration_minutes, unit_amount, product_name, currency);
+            base_value
+        };
+        debug!("fulfillment payload: {:?}", fulfillment_payload_value);
+
+        let shared_secret = app_config
+            .fulfillment
+            .as_ref()
+            .and_then(|f| f.shared_secret.as_deref())
+            .ok_or_else(|| {
+                info!("[Stripe Webhook] Fulfillment shared secret not configured. Cannot call fulfillment service for payment {}.", payment_id);
+                StripeError::ConfigError
+            })?;
+        let endpoint_path = fulfillment_endpoint_path(&ff_type).ok_or_else(|| {
+            error!(
+                "[Stripe Webhook] Unknown fulfillment_type in metadata: {}",
+                ff_type
+            );
+            StripeError::WebhookProcessingError(format!("Unknown fulfillment type: {}", ff_type))
+        })?;
+
+        if let Err(e) = call_fulfillment(
+            app_config,
+            shared_secret,
+            endpoint_path,
+            payment_id,
+            &fulfillment_payload_value,
+        )
+        .await
+        {
+            // Stripe would redeliver the webhook for days, running into the same failure, so
+            // the fulfillment is retried from the dead letter store instead
+            dead_letter_fulfillment(
+                app_config,
+                payment_id,
+                &ff_type,
+                &fulfillment_payload_value,
+                e,
+            )
+            .await?;
+        }
     } else {
-        // Handle other fulfillment types or default pricing if necessary in the future
-        return Err(StripeError::InvalidFulfillmentDataForPricing(format!(
-            "Unsupported fulfillment_type for dynamic pricing: {}",
-            request_data.fulfillment_type
-        )));
+        info!("[Stripe Webhook] Missing 'ff_type' or fulfillment data for payment {}. Cannot trigger fulfillment.", payment_id);
+        // Decide if this is an error or just no fulfillment needed
+        return Err(StripeError::MissingFulfillmentData);
     }
-    // --- End Price and Product Name Determination ---
-    #[allow(clippy::vec_init_then_push)]
-    let mut form_body: Vec<(String, String)> = vec![
-        ("payment_method_types[]".to_string(), "card".to_string()),
-        ("mode".to_string(), "payment".to_string()),
-        ("success_url".to_string(), stripe_config.success_url.clone()),
-        ("cancel_url".to_string(), stripe_config.cancel_url.clone()),
-        ("line_items[0][price_data][currency]".to_string(), currency),
-        (
-            "line_items[0][price_data][product_data][name]".to_string(),
-            product_name,
-        ),
-        (
-            "line_items[0][price_data][unit_amount]".to_string(),
-            unit_amount.to_string(),
-        ),
-        ("line_items[0][quantity]".to_string(), "1".to_string()),
-    ];
-    if let Some(client_ref_id) = &request_data.client_reference_id {
-        form_body.push(("client_reference_id".to_string(), client_ref_id.clone()));
+    Ok(())
+}
+
+/// The path of the fulfillment endpoint of a fulfillment type.
+fn fulfillment_endpoint_path(ff_type: &str) -> Option<&'static str> {
+    match ff_type {
+        "gcal_booking" => Some("/api/fulfill/gcal-booking"),
+        // "twilio_session" => "/api/fulfill/twilio-session", // Example
+        "adhoc_gcal_twilio" => Some("/api/fulfill/adhoc-gcal-twilio"),
+        _ => None,
     }
+}
+
+/// Posts a fulfillment request to the fulfillment service of this backend.
+async fn call_fulfillment(
+    app_config: &AppConfig,
+    shared_secret: &str,
+    endpoint_path: &str,
+    payment_id: &str,
+    payload: &serde_json::Value,
+) -> Result<(), StripeError> {
+    let fulfillment_base_url = format!(
+        // Construct base URL
+        "http://{}:{}",
+        app_config.server.host, app_config.server.port
+    );
+    let fulfillment_url = format!("{}{}", fulfillment_base_url, endpoint_path);
+
+    info!(
+        "[Stripe Webhook] Calling fulfillment service at {} for payment {}",
+        fulfillment_url, payment_id
+    );
+
+    let client = HTTP_CLIENT.clone(); // Use the static client
+    match client
+        .post(&fulfillment_url)
+        .with_request_id() // Correlate the fulfillment logs with this webhook
+        .header("X-Internal-Auth-Secret", shared_secret) // Use the shared secret
+        .json(payload) // Send the original JSON Value
+        .send()
+        .await
+    {
+        Ok(resp) if resp.status().is_success() => {
+            info!(
+                "[Stripe Webhook] Fulfillment for payment {} triggered successfully.",
+                payment_id
+            );
+            Ok(())
+        }
+        Ok(resp) => {
+            let status = resp.status(); // Store the status before consuming the response
+            let err_text = resp
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error from fulfillment service".to_string());
+            error!(
+                "[Stripe Webhook] Fulfillment call for payment {} failed: {} - {}",
+                payment_id, status, err_text
+            );
+            Err(StripeError::FulfillmentError(format!(
+                "Fulfillment service call failed: {} - {}",
+                status, err_text
+            )))
+        }
+        Err(e) => {
+            info!(
+                "[Stripe Webhook] Error calling fulfillment service for payment {}: {}",
+                payment_id, e
+            );
+            Err(StripeError::FulfillmentError(format!(
+                "Error calling fulfillment service: {}",
+                e
+            )))
+        }
+    }
+}
+
+/// Number of dead letters retried per run of [`retry_failed_fulfillments`].
+const DEAD_LETTER_BATCH_SIZE: usize = 50;
+
+/// How often a failed fulfillment is attempted before it is left to an admin, if not configured.
+const DEFAULT_FULFILLMENT_MAX_ATTEMPTS: u32 = 8;
+
+/// Backoff between the retries of a failed fulfillment: 1 minute, doubling up to 6 hours.
+fn dead_letter_retry_policy(stripe_config: Option<&StripeConfig>) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: stripe_config
+            .and_then(|stripe| stripe.fulfillment_max_attempts)
+            .unwrap_or(DEFAULT_FULFILLMENT_MAX_ATTEMPTS),
+        initial_delay: Duration::from_secs(60),
+        max_delay: Duration::from_secs(6 * 60 * 60),
+        multiplier: 2.0,
+        jitter: false,
+    }
+}
+
+/// When the retry after the given number of failed attempts is due.
+fn next_retry_at(policy: &RetryPolicy, attempts: u32, now: DateTime<Utc>) -> DateTime<Utc> {
+    now + chrono::Duration::from_std(policy.delay_for(attempts))
+        .unwrap_or_else(|_| chrono::Duration::hours(6))
+}
+
+/// Stores a fulfillment whose call failed in the dead letter store, for
+/// [`retry_failed_fulfillments`].
+///
+/// # Returns
+///
+/// The original error if the dead letter could not be stored, so Stripe redelivers the webhook.
+async fn dead_letter_fulfillment(
+    app_config: &AppConfig,
+    payment_id: &str,
+    ff_type: &str,
+    payload: &serde_json::Value,
+    error: StripeError,
+) -> Result<(), StripeError> {
+    let now = Utc::now();
+    let letter = DeadLetter::failed(
+        STRIPE_PROVIDER,
+        payment_id,
+        ff_type,
+        payload.to_string(),
+        error.to_string(),
+        next_retry_at(
+            &dead_letter_retry_policy(app_config.stripe.as_ref()),
+            1,
+            now,
+        ),
+        now,
+    );
+    match dead_letter_store().save(letter).await {
+        Ok(()) => {
+            info!(
+                "[Stripe Webhook] Fulfillment of payment {} failed and will be retried: {}",
+                payment_id, error
+            );
+            Ok(())
+        }
+        Err(e) => {
+            error!(
+                "[Stripe Webhook] Failed to store failed fulfillment of payment {}: {}",
+                payment_id, e
+            );
+            Err(error)
+        }
+    }
+}
+
+/// Outcome of [`retry_failed_fulfillments`].
+#[derive(Debug, Default, PartialEq)]
+pub struct FulfillmentRetries {
+    /// Dead letters that were delivered
+    pub delivered: usize,
+    /// Dead letters that failed again and will be retried
+    pub failed: usize,
+    /// Dead letters that failed again and are out of retries
+    pub exhausted: usize,
+}
+
+/// Delivers a dead letter to the fulfillment service once, recording the outcome.
+///
+/// # Returns
+///
+/// The updated dead letter.
+pub async fn deliver_dead_letter(
+    app_config: &AppConfig,
+    mut letter: DeadLetter,
+    now: DateTime<Utc>,
+) -> Result<DeadLetter, StripeError> {
+    let shared_secret = app_config
+        .fulfillment
+        .as_ref()
+        .and_then(|f| f.shared_secret.as_deref())
+        .ok_or(StripeError::ConfigError)?;
+    let endpoint_path = fulfillment_endpoint_path(&letter.fulfillment_type).ok_or_else(|| {
+        StripeError::WebhookProcessingError(format!(
+            "Unknown fulfillment type: {}",
+            letter.fulfillment_type
+        ))
+    })?;
+    let payload: serde_json::Value = serde_json::from_str(&letter.payload).map_err(|e| {
+        StripeError::InternalError(format!(
+            "Invalid stored payload of {}: {}",
+            letter.payment_id, e
+        ))
+    })?;
+
+    let result = call_fulfillment(
+        app_config,
+        shared_secret,
+        endpoint_path,
+        &letter.payment_id,
+        &payload,
+    )
+    .await;
+    letter.attempts += 1;
+    letter.updated_at = now;
+    match result {
+        Ok(()) => {
+            letter.status = DeadLetterStatus::Delivered;
+            letter.last_error = None;
+        }
+        Err(e) => {
+            let policy = dead_letter_retry_policy(app_config.stripe.as_ref());
+            letter.status = if letter.attempts >= policy.max_attempts {
+                DeadLetterStatus::Exhausted
+            } else {
+                DeadLetterStatus::Pending
+            };
+            letter.last_error = Some(e.to_string());
+            letter.next_attempt_at = next_retry_at(&policy, letter.attempts, now);
+        }
+    }
+    dead_letter_store()
+        .save(letter.clone())
+        .await
+        .map_err(|e| {
+            StripeError::InternalError(format!(
+                "Failed to store dead letter of {}: {}",
+                letter.payment_id, e
+            ))
+        })?;
+    Ok(letter)
+}
+
+/// Retries the failed fulfillments whose retry is due.
+pub async fn retry_failed_fulfillments(
+    app_config: &AppConfig,
+    now: DateTime<Utc>,
+) -> Result<FulfillmentRetries, StripeError> {
+    let mut retries = FulfillmentRetries::default();
+    let due = dead_letter_store()
+        .due(STRIPE_PROVIDER, now, DEAD_LETTER_BATCH_SIZE)
+        .await
+        .map_err(|e| StripeError::InternalError(format!("Failed to load dead letters: {}", e)))?;
+    for letter in due {
+        let payment_id = letter.payment_id.clone();
+        match deliver_dead_letter(app_config, letter, now).await {
+            Ok(letter) => match letter.status {
+                DeadLetterStatus::Delivered => retries.delivered += 1,
+                DeadLetterStatus::Pending => retries.failed += 1,
+                DeadLetterStatus::Exhausted => {
+                    error!(
+                        "[Stripe Logic] Fulfillment of payment {} failed {} times, giving up: {:?}",
+                        payment_id, letter.attempts, letter.last_error
+                    );
+                    retries.exhausted += 1
+                }
+            },
+            Err(e) => error!(
+                "[Stripe Logic] Failed to retry fulfillment of payment {}: {}",
+                payment_id, e
+            ),
+        }
+    }
+    Ok(retries)
+}
+
+/// The price of a booking, from the configured price tiers.
+struct TierPrice {
+    /// Amount in the smallest currency unit
+    unit_amount: i64,
+    product_name: String,
+    currency: String,
+}
+
+/// Determines the price of a booking from its duration and the configured price tiers, in the
+/// requested currency or else the tier's own one.
+fn tier_price(
+    pricing_config: &PricingConfig,
+    fulfillment_type: &str,
+    fulfillment_data: &serde_json::Value,
+    requested_currency: Option<&str>,
+) -> Result<TierPrice, StripeError> {
+    let unit_amount: i64;
+    let product_name: String;
+    let currency: String;
+
+    if fulfillment_type == "gcal_booking" || fulfillment_type == "adhoc_gcal_twilio" {
+        let start_time_str = fulfillment_data
+            .get("start_time")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| {
+                StripeError::InvalidFulfillmentDataForPricing(
+                    "Missing start_time in fulfillment_data for GCal-based booking".to_string(),
+                )
+            })?;
+        let end_time_str = fulfillment_data
+            .get("end_time")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| {
+                StripeError::InvalidFulfillmentDataForPricing(
+                    "Missing end_time in fulfillment_data for GCal-based booking".to_string(),
+                )
+            })?;
+
+        let start_dt = DateTime::parse_from_rfc3339(start_time_str)
+            .map_err(|e| {
+                StripeError::InvalidFulfillmentDataForPricing(format!(
+                    "Invalid start_time format: {}",
+                    e
+                ))
+            })?
+            .with_timezone(&Utc);
+        let end_dt = DateTime::parse_from_rfc3339(end_time_str)
+            .map_err(|e| {
+                StripeError::InvalidFulfillmentDataForPricing(format!(
+                    "Invalid end_time format: {}",
+                    e
+                ))
+            })?
+            .with_timezone(&Utc);
+
+        if end_dt <= start_dt {
+            return Err(StripeError::InvalidFulfillmentDataForPricing(
+                "End time must be after start time.".to_string(),
+            ));
+        }
+        let duration_minutes = (end_dt - start_dt).num_minutes();
+
+        let tier = pricing_config
+            .tier_for_duration(duration_minutes)
+            .ok_or_else(|| StripeError::NoMatchingPriceTier(duration_minutes))?;
+
+        let default_currency = pricing_config.default_currency.as_deref();
+        match requested_currency {
+            Some(requested) => {
+                unit_amount = tier.amount_in(requested, default_currency).ok_or_else(|| {
+                    StripeError::UnsupportedCurrency {
+                        currency: requested.to_uppercase(),
+                        duration_minutes,
+                        available: tier.currencies(default_currency).join(", "),
+                    }
+                })?;
+                currency = requested.to_lowercase();
+            }
+            None => {
+                unit_amount = tier.unit_amount;
+                currency = tier
+                    .base_currency(default_currency)
+                    .unwrap_or_else(|| "chf".to_string())
+                    .to_lowercase();
+            }
+        }
+        // Use product_name from tier, fallback to summary from fulfillment_data, then a generic default
+        product_name = tier.product_name.clone().unwrap_or_else(|| {
+            fulfillment_data
+                .get("summary")
+                .and_then(|v| v.as_str())
+                .map(String::from)
+                .unwrap_or_else(|| format!("Service - {} min", duration_minutes))
+        });
+
+        info!("[Stripe Logic] Type: {}. Duration: {} mins. Tier: amount={}, product='{}', currency='{}'",
+                 fulfillment_type, duration_minutes, unit_amount, product_name, currency);
+    } else {
+        // Handle other fulfillment types or default pricing if necessary in the future
+        return Err(StripeError::InvalidFulfillmentDataForPricing(format!(
+            "Unsupported fulfillment_type for dynamic pricing: {}",
+            fulfillment_type
+        )));
+    }
+    Ok(TierPrice {
+        unit_amount,
+        product_name,
+        currency,
+    })
+}
+
+/// Creates a Stripe Checkout Session, priced by the shared price tiers.
+pub async fn create_checkout_session(
+    stripe_config: &StripeConfig,
+    pricing_config: &PricingConfig,
+    request_data: CreateCheckoutSessionRequest,
+) -> Result<CreateCheckoutSessionResponse, StripeError> {
+    info!(
+        "[Stripe Logic] Creating Checkout Session for fulfillment type: {}",
+        request_data.fulfillment_type
+    );
+
+    let client = StripeClient::from_env()?;
+    let customer = match &request_data.customer_email {
+        Some(email) => Some(
+            find_or_create_customer(&client, email, request_data.customer_name.as_deref())
+                .await?
+                .customer_id,
+        ),
+        None => None,
+    };
+    if request_data.mode == CheckoutMode::Subscription {
+        return create_subscription_checkout_session(
+            &client,
+            stripe_config,
+            request_data,
+            customer,
+        )
+        .await;
+    }
+
+    let TierPrice {
+        unit_amount,
+        product_name,
+        currency,
+    } = tier_price(
+        pricing_config,
+        &request_data.fulfillment_type,
+        &request_data.fulfillment_data,
+        request_data.currency_override.as_deref(),
+    )?;
+    let mut params = CheckoutSessionParams {
+        payment_method_types: checkout_payment_method_types(stripe_config),
+        mode: "payment".to_string(),
+        success_url: stripe_config.success_url.clone(),
+        cancel_url: stripe_config.cancel_url.clone(),
+        line_items: vec![CheckoutLineItem {
+            price: None,
+            price_data: Some(PriceData {
+                currency,
+                product_data: ProductData { name: product_name },
+                unit_amount,
+                tax_behavior: stripe_config
+                    .tax_behavior
+                    .map(|behavior| behavior.as_str().to_string()),
+            }),
+            quantity: 1,
+        }],
+        client_reference_id: request_data.client_reference_id.clone(),
+        customer,
+        metadata: BTreeMap::new(),
+        subscription_data: None,
+        automatic_tax: None,
+        billing_address_collection: None,
+        customer_update: None,
+        expires_at: Some(checkout_expires_at(stripe_config, Utc::now())),
+        invoice_creation: stripe_config
+            .create_invoices
+            .then_some(InvoiceCreation { enabled: true }),
+    };
+    apply_tax_settings(&mut params, stripe_config);
 
     // For gcal_booking, ensure we have a room_name in the fulfillment_data
     let mut fulfillment_data = request_data.fulfillment_data.clone();
@@ -560,19 +1265,7 @@ pub async fn create_checkout_session(
         fulfillment_data["room_name"] = serde_json::Value::String(room_name.clone());
 
         // Also update the success_url to include the room_name
-        let mut dynamic_success_url = stripe_config.success_url.clone();
-        if dynamic_success_url.contains('?') {
-            dynamic_success_url = format!("{}&room_name={}", dynamic_success_url, room_name);
-        } else {
-            dynamic_success_url = format!("{}?room_name={}", dynamic_success_url, room_name);
-        }
-        // Replace the success_url in the form_body
-        for item in &mut form_body {
-            if item.0 == "success_url" {
-                item.1 = dynamic_success_url;
-                break;
-            }
-        }
+        params.success_url = with_query_param(&params.success_url, "room_name", &room_name);
     }
 
     // Add payment information to the fulfillment data
@@ -586,81 +1279,163 @@ pub async fn create_checkout_session(
         .unwrap_or_else(|| format!("stripe-{}", uuid::Uuid::new_v4()));
     fulfillment_data["original_reference_id"] = serde_json::Value::String(payment_reference);
 
-    // Store fulfillment information in Stripe metadata
-    form_body.push((
-        "metadata[ff_type]".to_string(),
-        request_data.fulfillment_type.clone(),
-    ));
-    let fulfillment_data_str = serde_json::to_string(&fulfillment_data).map_err(|e| {
-        StripeError::InternalError(format!("Failed to serialize fulfillment_data: {}", e))
-    })?;
-    form_body.push(("metadata[ff_data_json]".to_string(), fulfillment_data_str));
-
-    let api_url = "https://api.stripe.com/v1/checkout/sessions";
-
-    info!("[Stripe Logic] Sending request to Stripe API: {}", api_url);
-    // For debugging form body:
-    // info!("[Stripe Logic] Form Body: {:?}", form_body);
+    // Store the fulfillment information as an order referenced by the Stripe metadata
+    let order = fulfillment_order(&request_data.fulfillment_type, &fulfillment_data)?;
+    params
+        .metadata
+        .insert("ff_type".to_string(), request_data.fulfillment_type.clone());
+    params
+        .metadata
+        .insert(FULFILLMENT_ORDER_KEY.to_string(), order.order_id.clone());
+    save_fulfillment_order(order).await?;
+    // Lets the session's expiry release the held slot
+    if let Some(hold_id) = fulfillment_data.get("hold_id").and_then(|v| v.as_str()) {
+        params
+            .metadata
+            .insert("hold_id".to_string(), hold_id.to_string());
+    }
+    add_success_token(&mut params, success_token_secret(stripe_config)?);
 
-    let response = HTTP_CLIENT
-        .post(api_url)
-        .basic_auth(stripe_secret_key, None::<&str>)
-        .form(&form_body)
-        .send()
+    info!("[Stripe Logic] Sending Checkout Session request to Stripe API");
+    let session: CreatedCheckoutSession = client
+        .post("create_checkout_session", "checkout/sessions", &params)
         .await?;
 
-    let status = response.status();
-    let body_text = response.text().await?;
-
-    info!("[Stripe Logic] Stripe API response status: {}", status);
-    if !status.is_success() {
+    if let Some(url) = session.url {
         info!(
-            "[Stripe Logic] Stripe API response body (raw): {}",
-            body_text
+            "[Stripe Logic] Stripe Checkout Session created successfully. URL: {}",
+            url
         );
-    }
-
-    if status.is_success() {
-        let stripe_response: StripeCheckoutSessionApiResponse = serde_json::from_str(&body_text)?;
-        if let Some(url) = stripe_response.url {
-            info!(
-                "[Stripe Logic] Stripe Checkout Session created successfully. URL: {}",
-                url
-            );
-            Ok(CreateCheckoutSessionResponse {
-                url,
-                session_id: stripe_response.id,
-            })
-        } else {
-            info!(
-                "[Stripe Logic] Stripe response missing checkout session URL: {}",
-                body_text
-            );
-            Err(StripeError::InternalError(
-                "Stripe response missing checkout URL".to_string(),
-            ))
-        }
+        Ok(CreateCheckoutSessionResponse {
+            url,
+            session_id: session.id,
+        })
     } else {
-        let error_message = match serde_json::from_str::<serde_json::Value>(&body_text) {
-            Ok(json_body) => json_body
-                .get("error")
-                .and_then(|e| e.get("message"))
-                .and_then(|m| m.as_str())
-                .unwrap_or(&body_text)
-                .to_string(),
-            Err(_) => body_text,
-        };
         info!(
-            "[Stripe Logic] Stripe API request failed with HTTP status: {}. Message: {}",
-            status, error_message
+            "[Stripe Logic] Stripe response missing checkout session URL for session {}",
+            session.id
         );
-        Err(StripeError::ApiError {
-            status_code: status.as_u16(),
-            message: error_message,
-        })
+        Err(StripeError::InternalError(
+            "Stripe response missing checkout URL".to_string(),
+        ))
     }
 }
 
+/// Applies the configured tax calculation and address collection to a Checkout Session.
+fn apply_tax_settings(params: &mut CheckoutSessionParams, stripe_config: &StripeConfig) {
+    params.billing_address_collection = stripe_config
+        .billing_address_collection
+        .map(|collection| collection.as_str().to_string());
+    if !stripe_config.automatic_tax {
+        return;
+    }
+    params.automatic_tax = Some(AutomaticTax { enabled: true });
+    // Taxes of an existing customer are calculated from their saved address, so save the one
+    // entered in the checkout
+    if params.customer.is_some() {
+        params.customer_update = Some(CustomerUpdate {
+            address: "auto".to_string(),
+        });
+    }
+}
+
+/// The payment record of a paid Checkout Session, with its tax amount.
+fn session_payment_record(session: &StripeCheckoutSessionObject) -> PaymentRecord {
+    PaymentRecord {
+        provider: STRIPE_PROVIDER.to_string(),
+        payment_id: session.id.clone(),
+        reference: session.client_reference_id.clone(),
+        customer_id: session.customer.clone(),
+        currency: session.currency.clone(),
+        amount_total: session.amount_total,
+        amount_subtotal: session.amount_subtotal,
+        amount_tax: session
+            .total_details
+            .as_ref()
+            .and_then(|details| details.amount_tax),
+        country: session
+            .customer_details
+            .as_ref()
+            .and_then(|details| details.address.as_ref())
+            .and_then(|address| address.country.clone()),
+        created_at: Utc::now(),
+    }
+}
+
+/// Creates a Stripe Checkout Session for a subscription to a configured plan.
+///
+/// The fulfillment information is stored on both the session and the subscription, so that
+/// the `customer.subscription.*` webhook events carry it as well.
+async fn create_subscription_checkout_session(
+    client: &StripeClient,
+    stripe_config: &StripeConfig,
+    request_data: CreateCheckoutSessionRequest,
+    customer: Option<String>,
+) -> Result<CreateCheckoutSessionResponse, StripeError> {
+    let price_id = request_data.price_id.clone().unwrap_or_default();
+    let plan = stripe_config
+        .subscription_plans
+        .iter()
+        .find(|plan| plan.price_id == price_id)
+        .ok_or_else(|| StripeError::UnknownSubscriptionPrice(price_id.clone()))?;
+    info!(
+        "[Stripe Logic] Type: {}. Subscription plan: price='{}', name={:?}",
+        request_data.fulfillment_type, plan.price_id, plan.name
+    );
+
+    let order = fulfillment_order(
+        &request_data.fulfillment_type,
+        &request_data.fulfillment_data,
+    )?;
+    let mut metadata = BTreeMap::from([
+        ("ff_type".to_string(), request_data.fulfillment_type.clone()),
+        (FULFILLMENT_ORDER_KEY.to_string(), order.order_id.clone()),
+    ]);
+    save_fulfillment_order(order).await?;
+    if let Some(reference) = &request_data.client_reference_id {
+        metadata.insert("reference".to_string(), reference.clone());
+    }
+
+    let mut params = CheckoutSessionParams {
+        payment_method_types: checkout_payment_method_types(stripe_config),
+        mode: CheckoutMode::Subscription.as_str().to_string(),
+        success_url: stripe_config.success_url.clone(),
+        cancel_url: stripe_config.cancel_url.clone(),
+        line_items: vec![CheckoutLineItem {
+            price: Some(plan.price_id.clone()),
+            price_data: None,
+            quantity: 1,
+        }],
+        client_reference_id: request_data.client_reference_id.clone(),
+        customer,
+        metadata: metadata.clone(),
+        subscription_data: Some(SubscriptionData { metadata }),
+        automatic_tax: None,
+        billing_address_collection: None,
+        customer_update: None,
+        expires_at: Some(checkout_expires_at(stripe_config, Utc::now())),
+        // Subscriptions are invoiced anyway
+        invoice_creation: None,
+    };
+    apply_tax_settings(&mut params, stripe_config);
+    add_success_token(&mut params, success_token_secret(stripe_config)?);
+
+    let session: CreatedCheckoutSession = client
+        .post("create_checkout_session", "checkout/sessions", &params)
+        .await?;
+    let url = session.url.ok_or_else(|| {
+        StripeError::InternalError("Stripe response missing checkout URL".to_string())
+    })?;
+    info!(
+        "[Stripe Logic] Stripe subscription Checkout Session created successfully. URL: {}",
+        url
+    );
+    Ok(CreateCheckoutSessionResponse {
+        url,
+        session_id: session.id,
+    })
+}
+
 // Response FROM Stripe API when retrieving a session
 // This is a more complete version of StripeCheckoutSessionObject
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -670,6 +1445,10 @@ pub struct StripeCheckoutSessionData {
     pub id: String,
     pub object: String, // "checkout.session"
     pub amount_total: Option<i64>,
+    #[serde(default)]
+    pub amount_subtotal: Option<i64>,
+    #[serde(default)]
+    pub total_details: Option<StripeTotalDetails>,
     pub currency: Option<String>,
     pub customer: Option<String>,
     pub customer_details: Option<StripeCustomerDetails>,
@@ -683,98 +1462,229 @@ pub struct StripeCheckoutSessionData {
     pub created: Option<i64>,
     pub expires_at: Option<i64>,
     pub room_name: Option<String>,
+    /// Invoice ID (in_...) if invoice creation was enabled
+    #[serde(default)]
+    pub invoice: Option<String>,
+    /// URL of the invoice PDF, once the invoice is finalized
+    #[serde(default)]
+    pub invoice_pdf: Option<String>,
+    /// Page where the customer can view and download the invoice
+    #[serde(default)]
+    pub hosted_invoice_url: Option<String>,
     // Add other fields you might want to display on the confirmation page
 }
 
-#[allow(dead_code)]
-#[derive(Deserialize, Debug)]
-struct StripeCheckoutSessionApiResponse {
-    // Used for session creation response
-    pub id: String,
-    pub url: Option<String>,
+/// How long completed Checkout Sessions are cached.
+const SESSION_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+fn session_cache_key(session_id: &str) -> String {
+    format!("stripe:session:{}", session_id)
+}
+
+/// Retrieves a Checkout Session for the success page, which has to present the success token
+/// the session's success URL was issued with.
+pub async fn get_owned_checkout_session_details(
+    stripe_config: &StripeConfig,
+    session_id: &str,
+    success_token: &str,
+) -> Result<StripeCheckoutSessionData, StripeError> {
+    let nonce = verify_success_token(
+        success_token_secret(stripe_config)?.as_bytes(),
+        success_token,
+        Utc::now(),
+    )?;
+    let session = get_checkout_session_details(session_id).await?;
+    let bound_nonce = session
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get(SUCCESS_NONCE_KEY));
+    match bound_nonce {
+        Some(bound) if constant_time_eq(bound.as_bytes(), nonce.as_bytes()) => Ok(session),
+        _ => Err(StripeError::InvalidSuccessToken(format!(
+            "not issued for session {}",
+            session_id
+        ))),
+    }
 }
 
 // --- NEW: Function to get Checkout Session Details ---
 /// Retrieves details of a Stripe Checkout Session.
+///
+/// Completed sessions are cached, since confirmation pages tend to be reloaded.
 pub async fn get_checkout_session_details(
     session_id: &str,
     // stripe_config: &StripeConfig, // Not strictly needed if secret key is from env
+) -> Result<StripeCheckoutSessionData, StripeError> {
+    let cache_key = session_cache_key(session_id);
+    if let Some(session) = cache::get_json(&*cache(), &cache_key).await {
+        return Ok(session);
+    }
+
+    let session = fetch_checkout_session_details(session_id).await?;
+    // Until its invoice is finalized, a session is fetched again to pick up the invoice URLs
+    let invoice_pending = session.invoice.is_some() && session.invoice_pdf.is_none();
+    if session.status.as_deref() == Some("complete") && !invoice_pending {
+        cache::set_json(&*cache(), &cache_key, &session, SESSION_CACHE_TTL).await;
+    }
+    Ok(session)
+}
+
+async fn fetch_checkout_session_details(
+    session_id: &str,
 ) -> Result<StripeCheckoutSessionData, StripeError> {
     info!(
         "[Stripe Logic] Retrieving Checkout Session details for ID: {}",
         session_id
     );
 
-    let stripe_secret_key = env::var("STRIPE_SECRET_KEY").map_err(|_| StripeError::ConfigError)?;
-
-    let api_url = format!("https://api.stripe.com/v1/checkout/sessions/{}", session_id);
-
-    let response = HTTP_CLIENT
-        .get(&api_url)
-        .basic_auth(stripe_secret_key, None::<&str>)
-        .send()
+    let mut session_data: StripeCheckoutSessionData = StripeClient::from_env()?
+        .get(
+            "get_checkout_session",
+            &format!("checkout/sessions/{}", session_id),
+        )
         .await?;
-
-    let status = response.status();
-    let body_text = response.text().await?;
-
-    if status.is_success() {
-        let session_data: StripeCheckoutSessionData = serde_json::from_str(&body_text)?;
-        // Optionally, verify payment_status here if needed for the confirmation page
-        if session_data.payment_status.as_deref() != Some("paid")
-            && session_data.status.as_deref() != Some("complete")
-        {
-            // This might happen if user hits success URL but payment is still processing or failed later
-            info!("[Stripe Logic] Warning: Checkout session {} status is {:?}, payment_status is {:?}.",
-                     session_id, session_data.status, session_data.payment_status);
-            // Depending on requirements, you might return an error or different data
+    if let Some(invoice_id) = &session_data.invoice {
+        match fetch_invoice(invoice_id).await {
+            Ok(invoice) => {
+                session_data.invoice_pdf = invoice.invoice_pdf;
+                session_data.hosted_invoice_url = invoice.hosted_invoice_url;
+            }
+            Err(e) => error!("Failed to retrieve invoice {}: {}", invoice_id, e),
         }
-        Ok(session_data)
-    } else {
-        let error_message = match serde_json::from_str::<serde_json::Value>(&body_text) {
-            Ok(json_body) => json_body
-                .get("error")
-                .and_then(|e| e.get("message"))
-                .and_then(|m| m.as_str())
-                .unwrap_or(&body_text)
-                .to_string(),
-            Err(_) => body_text,
-        };
-        error!(
-            "[Stripe Logic] Failed to retrieve session {}: {} - {}",
-            session_id, status, error_message
+    }
+    // Optionally, verify payment_status here if needed for the confirmation page
+    if session_data.payment_status.as_deref() != Some("paid")
+        && session_data.status.as_deref() != Some("complete")
+    {
+        // This might happen if user hits success URL but payment is still processing or failed later
+        info!(
+            "[Stripe Logic] Warning: Checkout session {} status is {:?}, payment_status is {:?}.",
+            session_id, session_data.status, session_data.payment_status
         );
-        Err(StripeError::ApiError {
-            status_code: status.as_u16(),
-            message: error_message,
-        })
+        // Depending on requirements, you might return an error or different data
     }
+    Ok(session_data)
 }
 
 // --- NEW: Structures for Listing Checkout Sessions (Admin) ---
-#[derive(Deserialize, Debug, PartialEq)]
+/// Filters of the admin Checkout Session listing; the page is selected with a [`CursorQuery`].
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 #[cfg_attr(feature = "openapi", derive(utoipa::IntoParams, ToSchema))] // For query parameters
 pub struct ListSessionsAdminQuery {
-    #[cfg_attr(feature = "openapi", param(example = 10, required = false))]
-    pub limit: Option<u8>, // Stripe limit is 1-100
+    /// Only sessions created on or after this day (UTC), as YYYY-MM-DD
+    #[cfg_attr(feature = "openapi", param(example = "2025-07-01", required = false))]
+    pub created_from: Option<String>,
+    /// Only sessions created on or before this day (UTC), as YYYY-MM-DD
+    #[cfg_attr(feature = "openapi", param(example = "2025-07-31", required = false))]
+    pub created_to: Option<String>,
+    /// Only sessions whose customer entered this email address
     #[cfg_attr(
         feature = "openapi",
-        param(example = "cs_test_a1...", required = false)
+        param(example = "client@example.com", required = false)
     )]
-    pub starting_after: Option<String>,
-    #[cfg_attr(
-        feature = "openapi",
-        param(example = "cs_test_z0...", required = false)
-    )]
-    pub ending_before: Option<String>,
-    // Add other Stripe list parameters as needed (e.g., created, customer, payment_intent)
-    // pub customer: Option<String>,
-    // pub payment_intent: Option<String>,
-    // pub created: Option<i64>, // Unix timestamp for filtering
+    pub customer_email: Option<String>,
+    /// Only sessions with this payment status: `paid`, `unpaid` or `no_payment_required`
+    #[cfg_attr(feature = "openapi", param(example = "paid", required = false))]
+    pub payment_status: Option<String>,
+    /// Only sessions with this client reference ID
+    #[cfg_attr(feature = "openapi", param(example = "order-1", required = false))]
+    pub client_reference_id: Option<String>,
+}
+
+/// Payment statuses of Checkout Sessions.
+const SESSION_PAYMENT_STATUSES: [&str; 3] = ["paid", "unpaid", "no_payment_required"];
+
+/// Maximum number of Stripe pages listed to fill one page of filtered sessions.
+const MAX_SESSION_LIST_REQUESTS: usize = 10;
+
+impl ListSessionsAdminQuery {
+    /// The query listing the sessions from Stripe, which filters by creation time and email.
+    ///
+    /// Stripe lists sessions newest first only, and the cursor is the ID of the session to
+    /// list after.
+    fn stripe_query(&self, page: &CursorQuery) -> Result<CheckoutSessionListQuery, StripeError> {
+        if page.order() == SortOrder::Asc {
+            return Err(StripeError::InvalidSessionFilter(
+                "Checkout Sessions can only be listed newest first".to_string(),
+            ));
+        }
+        let day = |name: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|value| {
+                    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+                        StripeError::InvalidSessionFilter(format!(
+                            "{} must be a date in YYYY-MM-DD format, got '{}'",
+                            name, value
+                        ))
+                    })
+                })
+                .transpose()
+        };
+        let from = day("created_from", &self.created_from)?;
+        let to = day("created_to", &self.created_to)?;
+        if let (Some(from), Some(to)) = (from, to) {
+            if to < from {
+                return Err(StripeError::InvalidSessionFilter(
+                    "created_to is before created_from".to_string(),
+                ));
+            }
+        }
+        if let Some(status) = self.payment_status.as_deref() {
+            if !SESSION_PAYMENT_STATUSES.contains(&status) {
+                return Err(StripeError::InvalidSessionFilter(format!(
+                    "unknown payment_status '{}', expected one of {}",
+                    status,
+                    SESSION_PAYMENT_STATUSES.join(", ")
+                )));
+            }
+        }
+
+        let created = (from.is_some() || to.is_some()).then(|| CreatedFilter {
+            gte: from.map(|day| day.and_time(NaiveTime::MIN).and_utc().timestamp()),
+            // The whole last day
+            lte: to
+                .map(|day| day.and_time(NaiveTime::MIN).and_utc().timestamp() + 24 * 60 * 60 - 1),
+        });
+        Ok(CheckoutSessionListQuery {
+            created,
+            customer_details: self
+                .customer_email
+                .as_ref()
+                .map(|email| CustomerDetailsFilter {
+                    email: email.trim().to_string(),
+                }),
+            // One more session than requested tells whether there is a next page; filtering
+            // locally drops sessions, so list as many as Stripe allows then
+            limit: Some(if self.filters_locally() {
+                MAX_PAGE_SIZE as u8
+            } else {
+                (page.limit() as u32 + 1).min(MAX_PAGE_SIZE) as u8
+            }),
+            starting_after: page.cursor.clone(),
+            ..Default::default()
+        })
+    }
+
+    /// Whether some of the filters are applied to the listed sessions, not by Stripe.
+    fn filters_locally(&self) -> bool {
+        self.payment_status.is_some() || self.client_reference_id.is_some()
+    }
+
+    /// Whether a listed session matches the filters Stripe can't apply.
+    fn matches(&self, session: &StripeCheckoutSessionData) -> bool {
+        self.payment_status
+            .as_deref()
+            .is_none_or(|status| session.payment_status.as_deref() == Some(status))
+            && self
+                .client_reference_id
+                .as_deref()
+                .is_none_or(|reference| session.client_reference_id.as_deref() == Some(reference))
+    }
 }
 
 /// Represents the list object returned by Stripe API.
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct StripeListObject<T> {
     pub object: String, // "list"
@@ -784,66 +1694,1906 @@ pub struct StripeListObject<T> {
                      // next_page: Option<String>, // Stripe uses starting_after/ending_before for pagination
 }
 
+/// The booking a Checkout Session pays for, from the data stored locally.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct SessionBooking {
+    /// The fulfillment type, e.g. "gcal_booking"
+    pub fulfillment_type: String,
+    /// The fulfillment data, e.g. the booked start and end time
+    pub fulfillment_data: Option<serde_json::Value>,
+    /// `pending`, `delivered` or `exhausted` if the fulfillment failed and is retried
+    pub fulfillment_retry_status: Option<String>,
+    /// Taxes of the payment, if it was recorded
+    pub amount_tax: Option<i64>,
+    /// Country of the billing address, if the payment was recorded
+    pub billing_country: Option<String>,
+}
+
+/// A Checkout Session listed for admins, with the booking it pays for.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct AdminCheckoutSession {
+    #[serde(flatten)]
+    pub session: StripeCheckoutSessionData,
+    pub booking: Option<SessionBooking>,
+}
+
 // Type alias for the specific list response
-pub type ListSessionsAdminResponse = StripeListObject<StripeCheckoutSessionData>;
+pub type ListSessionsAdminResponse = Page<AdminCheckoutSession>;
 
-// --- NEW: Function to list Checkout Sessions (Admin) ---
+/// Lists Checkout Sessions for admins, filtered and joined with the local booking data.
+///
+/// Stripe filters by creation time and email; the payment status and client reference ID
+/// are filtered here, listing further Stripe pages until the page is full. After
+/// `MAX_SESSION_LIST_REQUESTS` Stripe pages, a page may hold fewer sessions than `limit`,
+/// with `has_more` set and the cursor pointing after the last listed session.
 pub async fn list_checkout_sessions_admin(
     query_params: ListSessionsAdminQuery,
+    page: CursorQuery,
 ) -> Result<ListSessionsAdminResponse, StripeError> {
     info!(
-        "[Stripe Logic] Listing Checkout Sessions for admin. Params: {:?}",
-        query_params
+        "[Stripe Logic] Listing Checkout Sessions for admin. Params: {:?}, page: {:?}",
+        query_params, page
     );
 
-    let stripe_secret_key = env::var("STRIPE_SECRET_KEY").map_err(|_| StripeError::ConfigError)?;
-
-    let base_url = "https://api.stripe.com/v1/checkout/sessions";
-
-    // Build query parameters for the reqwest client
-    let mut request_query_params = Vec::new();
-    if let Some(limit) = query_params.limit {
-        request_query_params.push(("limit", limit.to_string()));
-    }
-    if let Some(starting_after) = query_params.starting_after {
-        request_query_params.push(("starting_after", starting_after));
+    let limit = page.limit();
+    let mut stripe_query = query_params.stripe_query(&page)?;
+    let client = StripeClient::from_env()?;
+    let mut matching = Vec::with_capacity(limit + 1);
+    let mut complete = false;
+    for _ in 0..MAX_SESSION_LIST_REQUESTS {
+        let sessions: StripeListObject<StripeCheckoutSessionData> = client
+            .list("list_checkout_sessions", "checkout/sessions", &stripe_query)
+            .await?;
+        let last_listed = sessions.data.last().map(|session| session.id.clone());
+        matching.extend(
+            sessions
+                .data
+                .into_iter()
+                .filter(|session| query_params.matches(session))
+                .take(limit + 1 - matching.len()),
+        );
+        if matching.len() > limit || !sessions.has_more {
+            complete = true;
+            break;
+        }
+        stripe_query.starting_after = last_listed;
     }
-    if let Some(ending_before) = query_params.ending_before {
-        request_query_params.push(("ending_before", ending_before));
+    let sessions_page = if complete {
+        Page::from_items(matching, limit, |session| session.id.clone())
+    } else {
+        // Stripe has more sessions, but the listed ones didn't fill the page
+        Page {
+            items: matching,
+            next_cursor: stripe_query.starting_after,
+            has_more: true,
+        }
+    };
+
+    let mut items = Vec::with_capacity(sessions_page.items.len());
+    for session in sessions_page.items {
+        let booking = session_booking(&session).await;
+        items.push(AdminCheckoutSession { session, booking });
     }
-    // Add other filters here, e.g.:
-    // request_query_params.push(("expand[]", "data.line_items".to_string())); // To expand line items
+    Ok(Page {
+        items,
+        next_cursor: sessions_page.next_cursor,
+        has_more: sessions_page.has_more,
+    })
+}
 
-    let response = HTTP_CLIENT
-        .get(base_url)
-        .basic_auth(stripe_secret_key, None::<&str>)
-        .query(&request_query_params) // reqwest can take Vec of tuples for query params
-        .send()
+/// The local booking data of a Checkout Session; lookups that fail are logged and left out,
+/// so the listing still shows the session.
+async fn session_booking(session: &StripeCheckoutSessionData) -> Option<SessionBooking> {
+    let metadata = session.metadata.as_ref();
+    let fulfillment_type = metadata?.get("ff_type")?.clone();
+    let fulfillment_data = match fulfillment_data_json(metadata).await {
+        Ok(data) => data.and_then(|data| serde_json::from_str(&data).ok()),
+        Err(e) => {
+            error!("[Stripe Logic] Session {}: {}", session.id, e);
+            None
+        }
+    };
+    let fulfillment_retry_status = match dead_letter_store().get(STRIPE_PROVIDER, &session.id).await
+    {
+        Ok(letter) => letter.map(|letter| letter.status.to_string()),
+        Err(e) => {
+            error!(
+                "[Stripe Logic] Failed to load the dead letter of session {}: {}",
+                session.id, e
+            );
+            None
+        }
+    };
+    let payment = match payment_record_store()
+        .get_payment(STRIPE_PROVIDER, &session.id)
+        .await
+    {
+        Ok(payment) => payment,
+        Err(e) => {
+            error!(
+                "[Stripe Logic] Failed to load the payment of session {}: {}",
+                session.id, e
+            );
+            None
+        }
+    };
+    Some(SessionBooking {
+        fulfillment_type,
+        fulfillment_data,
+        fulfillment_retry_status,
+        amount_tax: payment.as_ref().and_then(|payment| payment.amount_tax),
+        billing_country: payment.and_then(|payment| payment.country),
+    })
+}
+
+// --- Checkout expiration ---
+
+/// Minutes until an unpaid Checkout Session expires, if not configured.
+const DEFAULT_CHECKOUT_EXPIRY_MINUTES: i64 = 30;
+
+/// Stripe accepts expirations from 30 minutes to 24 hours after it receives the request, so
+/// a minute is kept off both bounds.
+const CHECKOUT_EXPIRY_BOUNDS_MINUTES: (i64, i64) = (31, 24 * 60 - 1);
+
+/// How far back expired sessions are checked for unreleased slot holds.
+const RECONCILE_LOOKBACK_HOURS: i64 = 48;
+
+/// How many sessions of each status a reconciliation looks at.
+const RECONCILE_PAGE_SIZE: u8 = 100;
+
+/// The `expires_at` of a Checkout Session created at `now`.
+fn checkout_expires_at(stripe_config: &StripeConfig, now: DateTime<Utc>) -> i64 {
+    let (min, max) = CHECKOUT_EXPIRY_BOUNDS_MINUTES;
+    let minutes = stripe_config
+        .checkout_expires_after_minutes
+        .unwrap_or(DEFAULT_CHECKOUT_EXPIRY_MINUTES)
+        .clamp(min, max);
+    (now + chrono::Duration::minutes(minutes)).timestamp()
+}
+
+/// Payment method offered by checkouts unless configured otherwise.
+const DEFAULT_PAYMENT_METHOD_TYPE: &str = "card";
+
+/// Configured payment method that lets Stripe offer the methods enabled in the dashboard.
+const AUTOMATIC_PAYMENT_METHODS: &str = "automatic";
+
+/// The `payment_method_types` of a Checkout Session; empty to let Stripe choose.
+fn checkout_payment_method_types(stripe_config: &StripeConfig) -> Vec<String> {
+    let configured: Vec<String> = stripe_config
+        .payment_method_types
+        .iter()
+        .map(|method| method.trim().to_lowercase())
+        .filter(|method| !method.is_empty())
+        .collect();
+    if configured.is_empty() {
+        return vec![DEFAULT_PAYMENT_METHOD_TYPE.to_string()];
+    }
+    if configured
+        .iter()
+        .any(|method| method == AUTOMATIC_PAYMENT_METHODS)
+    {
+        return Vec::new();
+    }
+    let mut methods: Vec<String> = Vec::with_capacity(configured.len());
+    for method in configured {
+        if !methods.contains(&method) {
+            methods.push(method);
+        }
+    }
+    methods
+}
+
+// --- Success page tokens ---
+
+/// Metadata key of the nonce a Checkout Session's success token is bound to.
+const SUCCESS_NONCE_KEY: &str = "success_nonce";
+
+/// How long a success token stays valid after its checkout expired, for late redirects.
+const SUCCESS_TOKEN_GRACE_SECONDS: i64 = 60 * 60;
+
+/// The key success tokens are signed with; only this backend issues and verifies them.
+///
+/// It is a dedicated secret, so the Stripe API key isn't used as a signing key as well.
+fn success_token_secret(stripe_config: &StripeConfig) -> Result<&str, StripeError> {
+    stripe_config
+        .success_token_secret
+        .as_deref()
+        // An unset environment variable leaves the marker in place
+        .filter(|secret| !secret.is_empty() && *secret != "secret_from_env")
+        .ok_or_else(|| {
+            error!("[Stripe Logic] stripe.success_token_secret is not configured");
+            StripeError::ConfigError
+        })
+}
+
+/// Appends a query parameter to a URL.
+fn with_query_param(url: &str, name: &str, value: &str) -> String {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}{}={}", url, separator, name, value)
+}
+
+/// Signs a success token for a nonce, as `<nonce>.<expires_at>.<signature>`.
+fn sign_success_token(secret: &[u8], nonce: &str, expires_at: i64) -> String {
+    let claims = format!("{}.{}", nonce, expires_at);
+    let signature = hmac_sha256_hex(secret, claims.as_bytes());
+    format!("{}.{}", claims, signature)
+}
+
+/// Verifies the signature and expiry of a success token.
+///
+/// # Returns
+///
+/// The nonce the token is bound to.
+fn verify_success_token(
+    secret: &[u8],
+    token: &str,
+    now: DateTime<Utc>,
+) -> Result<String, StripeError> {
+    let invalid = |reason: &str| StripeError::InvalidSuccessToken(reason.to_string());
+    let (claims, signature) = token.rsplit_once('.').ok_or_else(|| invalid("malformed"))?;
+    if !verify_hmac_sha256_hex(secret, claims.as_bytes(), signature) {
+        return Err(invalid("bad signature"));
+    }
+    let (nonce, expires_at) = claims.split_once('.').ok_or_else(|| invalid("malformed"))?;
+    let expires_at: i64 = expires_at.parse().map_err(|_| invalid("malformed"))?;
+    if expires_at < now.timestamp() {
+        return Err(invalid("expired"));
+    }
+    Ok(nonce.to_string())
+}
+
+/// Binds a Checkout Session to a fresh nonce and adds a success token for it to the success
+/// URL, so only the client redirected from the checkout can look the session up.
+///
+/// The session id is not known before Stripe creates the session, so the token signs the
+/// nonce, which is stored in the session metadata. It expires an hour after the checkout.
+fn add_success_token(params: &mut CheckoutSessionParams, secret: &str) {
+    let nonce = uuid::Uuid::new_v4().simple().to_string();
+    let expires_at = params
+        .expires_at
+        .unwrap_or_else(|| Utc::now().timestamp() + 24 * 60 * 60)
+        + SUCCESS_TOKEN_GRACE_SECONDS;
+    let token = sign_success_token(secret.as_bytes(), &nonce, expires_at);
+    params.success_url = with_query_param(&params.success_url, "success_token", &token);
+    params.metadata.insert(SUCCESS_NONCE_KEY.to_string(), nonce);
+}
+
+/// The slot hold of the booking paid with a Checkout Session, if any.
+fn session_hold_id(metadata: Option<&HashMap<String, String>>) -> Option<String> {
+    let metadata = metadata?;
+    if let Some(hold_id) = metadata.get("hold_id") {
+        return Some(hold_id.clone());
+    }
+    // Sessions created before the hold was stored in its own metadata key
+    let fulfillment_data: serde_json::Value =
+        serde_json::from_str(metadata.get("ff_data_json")?).ok()?;
+    fulfillment_data.get("hold_id")?.as_str().map(String::from)
+}
+
+/// Releases the slot hold of an expired Checkout Session.
+///
+/// # Returns
+///
+/// Whether a hold was released.
+async fn release_session_hold(
+    session_id: &str,
+    metadata: Option<&HashMap<String, String>>,
+) -> Result<bool, StripeError> {
+    let Some(hold_id) = session_hold_id(metadata) else {
+        return Ok(false);
+    };
+    let released = slot_hold_store().release(&hold_id).await.map_err(|e| {
+        StripeError::InternalError(format!("Failed to release slot hold {}: {}", hold_id, e))
+    })?;
+    if released {
+        info!(
+            "[Stripe Logic] Released slot hold {} of expired Checkout Session {}",
+            hold_id, session_id
+        );
+    }
+    Ok(released)
+}
+
+/// Outcome of [`reconcile_checkout_sessions`].
+#[derive(Debug, Default, PartialEq)]
+pub struct CheckoutReconciliation {
+    /// Open sessions past their `expires_at` that were expired
+    pub expired_sessions: usize,
+    /// Slot holds released for expired sessions
+    pub released_holds: usize,
+}
+
+/// Expires open Checkout Sessions past their `expires_at` and releases the slot holds of
+/// expired sessions, in case their `checkout.session.expired` webhook was missed.
+pub async fn reconcile_checkout_sessions(
+    client: &StripeClient,
+    now: DateTime<Utc>,
+) -> Result<CheckoutReconciliation, StripeError> {
+    let mut reconciliation = CheckoutReconciliation::default();
+
+    let open: StripeListObject<StripeCheckoutSessionData> = client
+        .list(
+            "list_checkout_sessions",
+            "checkout/sessions",
+            &CheckoutSessionListQuery {
+                status: Some("open".to_string()),
+                limit: Some(RECONCILE_PAGE_SIZE),
+                ..Default::default()
+            },
+        )
         .await?;
+    let stale = open
+        .data
+        .iter()
+        .filter(|session| session.expires_at.is_some_and(|at| at <= now.timestamp()));
+    for session in stale {
+        // Fails if the session was completed in the meantime
+        let expired: Result<StripeCheckoutSessionData, StripeError> = client
+            .post(
+                "expire_checkout_session",
+                &format!("checkout/sessions/{}/expire", session.id),
+                &BTreeMap::<String, String>::new(),
+            )
+            .await;
+        match expired {
+            Ok(_) => reconciliation.expired_sessions += 1,
+            Err(e) => error!(
+                "[Stripe Logic] Failed to expire stale Checkout Session {}: {}",
+                session.id, e
+            ),
+        }
+    }
+
+    let expired: StripeListObject<StripeCheckoutSessionData> = client
+        .list(
+            "list_checkout_sessions",
+            "checkout/sessions",
+            &CheckoutSessionListQuery {
+                status: Some("expired".to_string()),
+                created: Some(CreatedFilter {
+                    gte: Some(
+                        (now - chrono::Duration::hours(RECONCILE_LOOKBACK_HOURS)).timestamp(),
+                    ),
+                    lte: None,
+                }),
+                limit: Some(RECONCILE_PAGE_SIZE),
+                ..Default::default()
+            },
+        )
+        .await?;
+    for session in &expired.data {
+        if release_session_hold(&session.id, session.metadata.as_ref()).await? {
+            reconciliation.released_holds += 1;
+        }
+    }
+    Ok(reconciliation)
+}
 
-    let status = response.status();
-    let body_text = response.text().await?;
+// --- Refunds (Admin) ---
 
-    if status.is_success() {
-        let list_response: ListSessionsAdminResponse = serde_json::from_str(&body_text)?;
-        Ok(list_response)
-    } else {
-        let error_message = match serde_json::from_str::<serde_json::Value>(&body_text) {
-            Ok(json_body) => json_body
-                .get("error")
-                .and_then(|e| e.get("message"))
-                .and_then(|m| m.as_str())
-                .unwrap_or(&body_text)
-                .to_string(),
-            Err(_) => body_text,
-        };
+/// Why a payment is refunded.
+///
+/// Stripe only knows `duplicate`, `fraudulent` and `requested_by_customer`; the other codes are
+/// kept in the refund's `reason_code` metadata.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RefundReason {
+    Duplicate,
+    Fraudulent,
+    RequestedByCustomer,
+    /// The booking was cancelled, by the client or by us
+    BookingCancelled,
+    /// The session did not take place as booked, e.g. because of technical problems
+    ServiceNotProvided,
+}
+
+impl RefundReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RefundReason::Duplicate => "duplicate",
+            RefundReason::Fraudulent => "fraudulent",
+            RefundReason::RequestedByCustomer => "requested_by_customer",
+            RefundReason::BookingCancelled => "booking_cancelled",
+            RefundReason::ServiceNotProvided => "service_not_provided",
+        }
+    }
+
+    /// The reason as accepted by the Stripe API, if it has one.
+    pub fn stripe_reason(&self) -> Option<&'static str> {
+        match self {
+            RefundReason::Duplicate => Some("duplicate"),
+            RefundReason::Fraudulent => Some("fraudulent"),
+            RefundReason::RequestedByCustomer | RefundReason::BookingCancelled => {
+                Some("requested_by_customer")
+            }
+            RefundReason::ServiceNotProvided => None,
+        }
+    }
+}
+
+/// Request to refund a Stripe payment in full or in part.
+///
+/// The payment is identified either by its PaymentIntent or by the Checkout Session that
+/// created it.
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[validate(schema(function = "validate_refund_target"))]
+pub struct CreateRefundRequest {
+    #[cfg_attr(feature = "openapi", schema(example = "pi_3N..."))]
+    pub payment_intent_id: Option<String>,
+    #[cfg_attr(feature = "openapi", schema(example = "cs_test_a1..."))]
+    pub session_id: Option<String>,
+    /// Amount to refund in the smallest currency unit; the whole payment if not set
+    #[cfg_attr(feature = "openapi", schema(example = 2500))]
+    #[validate(range(min = 1))]
+    pub amount: Option<i64>,
+    pub reason: Option<RefundReason>,
+    /// The calendar event ID of the booking the refund is for, e.g. a cancelled one
+    #[cfg_attr(feature = "openapi", schema(example = "abc123xyz"))]
+    #[validate(length(min = 1))]
+    pub booking_id: Option<String>,
+    /// Phone number to notify by SMS; the checkout's phone number if not set
+    #[cfg_attr(feature = "openapi", schema(example = "+41791234567"))]
+    #[validate(length(min = 1))]
+    pub customer_phone: Option<String>,
+    /// User ID whose registered devices are notified by push notification
+    #[validate(length(min = 1))]
+    pub customer_user_id: Option<String>,
+}
+
+/// Checks that exactly one of the payment intent and the session is given.
+fn validate_refund_target(request: &CreateRefundRequest) -> Result<(), ValidationError> {
+    match (&request.payment_intent_id, &request.session_id) {
+        (Some(id), None) | (None, Some(id)) if !id.is_empty() => Ok(()),
+        _ => Err(ValidationError::new("payment_intent_id_or_session_id")),
+    }
+}
+
+/// Response after a refund was created.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct CreateRefundResponse {
+    pub refund_id: String,
+    pub payment_intent_id: String,
+    /// e.g. "succeeded" or "pending"
+    pub status: String,
+    pub amount: i64,
+    pub currency: String,
+    pub reason: Option<RefundReason>,
+    pub booking_id: Option<String>,
+}
+
+/// Creates a refund for a PaymentIntent via the Stripe API.
+///
+/// Refunds the whole remaining amount if `amount` is not set.
+pub async fn create_refund(
+    payment_intent_id: &str,
+    amount: Option<i64>,
+    reason: Option<&str>,
+) -> Result<RefundResult, StripeError> {
+    post_refund(RefundParams {
+        payment_intent: payment_intent_id.to_string(),
+        amount,
+        reason: reason.map(String::from),
+        metadata: BTreeMap::new(),
+    })
+    .await
+}
+
+/// Creates the refund of an admin's refund request, keeping its reason code and booking in
+/// the refund's metadata, and announces it with a [`PaymentRefunded`] event, which notifies
+/// the client.
+pub async fn refund_payment(
+    target: &RefundTarget,
+    request: &CreateRefundRequest,
+) -> Result<RefundResult, StripeError> {
+    let refund = post_refund(refund_params(&target.payment_intent_id, request)).await?;
+    events::publish(PaymentRefunded {
+        provider: STRIPE_PROVIDER.to_string(),
+        payment_id: target.payment_intent_id.clone(),
+        refund_id: refund.id.clone(),
+        amount: refund.amount,
+        currency: refund.currency.clone(),
+        reason: request.reason.map(|reason| reason.as_str().to_string()),
+        booking_id: request.booking_id.clone(),
+        customer_phone: request
+            .customer_phone
+            .clone()
+            .or_else(|| target.customer_phone.clone()),
+        customer_user_id: request.customer_user_id.clone(),
+    });
+    Ok(refund)
+}
+
+/// The Stripe parameters of an admin's refund request.
+fn refund_params(payment_intent_id: &str, request: &CreateRefundRequest) -> RefundParams {
+    let mut metadata = BTreeMap::new();
+    if let Some(reason) = request.reason {
+        metadata.insert("reason_code".to_string(), reason.as_str().to_string());
+    }
+    if let Some(booking_id) = &request.booking_id {
+        metadata.insert("booking_id".to_string(), booking_id.clone());
+    }
+    RefundParams {
+        payment_intent: payment_intent_id.to_string(),
+        amount: request.amount,
+        reason: request
+            .reason
+            .and_then(|reason| reason.stripe_reason())
+            .map(String::from),
+        metadata,
+    }
+}
+
+async fn post_refund(params: RefundParams) -> Result<RefundResult, StripeError> {
+    info!(
+        "[Stripe Logic] Creating refund for PaymentIntent {} (amount: {:?})",
+        params.payment_intent, params.amount
+    );
+    let refund: Refund = StripeClient::from_env()?
+        .post("create_refund", "refunds", &params)
+        .await?;
+    info!(
+        "[Stripe Logic] Refund {} created for PaymentIntent {}",
+        refund.id, params.payment_intent
+    );
+    Ok(RefundResult {
+        id: refund.id,
+        status: refund.status.unwrap_or_else(|| "pending".to_string()),
+        amount: refund.amount,
+        currency: refund.currency,
+    })
+}
+
+/// The payment a refund request is for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefundTarget {
+    pub payment_intent_id: String,
+    /// Phone number entered in the checkout, if the payment was identified by its session
+    pub customer_phone: Option<String>,
+}
+
+/// Resolves the PaymentIntent of a refund request, looking it up from the Checkout Session
+/// if necessary.
+pub async fn refund_target(request: &CreateRefundRequest) -> Result<RefundTarget, StripeError> {
+    if let Some(payment_intent_id) = &request.payment_intent_id {
+        return Ok(RefundTarget {
+            payment_intent_id: payment_intent_id.clone(),
+            customer_phone: None,
+        });
+    }
+    let session_id = request.session_id.as_deref().unwrap_or_default();
+    let session = get_checkout_session_details(session_id).await?;
+    Ok(RefundTarget {
+        payment_intent_id: session
+            .payment_intent
+            .ok_or(StripeError::SessionNotFoundOrNotPaid)?,
+        customer_phone: session.customer_details.and_then(|customer| customer.phone),
+    })
+}
+
+// --- Customers ---
+
+/// Request to look up or create the Stripe customer of an email address.
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct CustomerRequest {
+    #[cfg_attr(feature = "openapi", schema(example = "anna@example.com"))]
+    #[validate(email)]
+    pub email: String,
+    /// Name of the customer, if one is created
+    #[cfg_attr(feature = "openapi", schema(example = "Anna Muster"))]
+    #[validate(length(min = 1))]
+    pub name: Option<String>,
+}
+
+/// Query to look up the Stripe customer of an email address.
+#[derive(Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams, ToSchema))]
+pub struct CustomerLookupQuery {
+    #[cfg_attr(feature = "openapi", param(example = "anna@example.com"))]
+    pub email: String,
+}
+
+/// The Stripe customer of an email address.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct StripeCustomerResponse {
+    #[cfg_attr(feature = "openapi", schema(example = "cus_N..."))]
+    pub customer_id: String,
+    pub email: String,
+    pub name: Option<String>,
+    /// Whether the customer was newly created
+    pub created: bool,
+}
+
+/// Looks up the Stripe customer of an email address, first in the payment repository and then
+/// at Stripe.
+///
+/// Customers found at Stripe are stored in the payment repository, so that later lookups
+/// don't need the API.
+pub async fn find_customer(
+    client: &StripeClient,
+    email: &str,
+) -> Result<Option<StripeCustomerResponse>, StripeError> {
+    let email = normalize_email(email);
+    match payment_customer_store()
+        .find_by_email(STRIPE_PROVIDER, &email)
+        .await
+    {
+        Ok(Some(customer)) => {
+            return Ok(Some(StripeCustomerResponse {
+                customer_id: customer.customer_id,
+                email,
+                name: None,
+                created: false,
+            }))
+        }
+        Ok(None) => {}
+        Err(e) => error!("Failed to look up Stripe customer of {}: {}", email, e),
+    }
+
+    let query = CustomerListQuery {
+        email: email.clone(),
+        limit: 1,
+    };
+    let customers: StripeListObject<Customer> =
+        client.list("list_customers", "customers", &query).await?;
+    let Some(customer) = customers.data.into_iter().next() else {
+        return Ok(None);
+    };
+    info!(
+        "[Stripe Logic] Found existing Stripe customer {} for {}",
+        customer.id, email
+    );
+    remember_customer(&email, &customer.id).await;
+    Ok(Some(StripeCustomerResponse {
+        customer_id: customer.id,
+        email,
+        name: customer.name,
+        created: false,
+    }))
+}
+
+/// Returns the Stripe customer of an email address, creating one if the client is new.
+pub async fn find_or_create_customer(
+    client: &StripeClient,
+    email: &str,
+    name: Option<&str>,
+) -> Result<StripeCustomerResponse, StripeError> {
+    if let Some(customer) = find_customer(client, email).await? {
+        return Ok(customer);
+    }
+
+    let params = CustomerParams {
+        email: normalize_email(email),
+        name: name.map(String::from),
+    };
+    let customer: Customer = client.post("create_customer", "customers", &params).await?;
+    info!(
+        "[Stripe Logic] Created Stripe customer {} for {}",
+        customer.id, params.email
+    );
+    remember_customer(&params.email, &customer.id).await;
+    Ok(StripeCustomerResponse {
+        customer_id: customer.id,
+        email: params.email,
+        name: customer.name,
+        created: true,
+    })
+}
+
+/// Stores the customer of an email address in the payment repository.
+///
+/// Failures are only logged, the customer is found at Stripe again next time.
+async fn remember_customer(email: &str, customer_id: &str) {
+    let customer = PaymentCustomer {
+        provider: STRIPE_PROVIDER.to_string(),
+        email: normalize_email(email),
+        customer_id: customer_id.to_string(),
+        created_at: Utc::now(),
+    };
+    if let Err(e) = payment_customer_store().save(customer).await {
         error!(
-            "[Stripe Logic] Failed to list sessions: {} - {}",
-            status, error_message
+            "Failed to store Stripe customer {} of {}: {}",
+            customer_id, email, e
         );
-        Err(StripeError::ApiError {
-            status_code: status.as_u16(),
-            message: error_message,
+    }
+}
+
+/// Links the customer of a completed Checkout Session to the client's email address, unless
+/// the address already has a customer.
+async fn remember_session_customer(session: &StripeCheckoutSessionObject) {
+    let email = session
+        .customer_details
+        .as_ref()
+        .and_then(|details| details.email.as_deref());
+    let (Some(customer_id), Some(email)) = (session.customer.as_deref(), email) else {
+        return;
+    };
+    match payment_customer_store()
+        .find_by_email(STRIPE_PROVIDER, email)
+        .await
+    {
+        Ok(None) => remember_customer(email, customer_id).await,
+        Ok(Some(_)) => {}
+        Err(e) => error!("Failed to look up Stripe customer of {}: {}", email, e),
+    }
+}
+
+// --- Saved payment methods ---
+
+/// Request from our frontend to save a card of a client, so repeat bookings can skip entering
+/// the card details.
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct CreateSetupIntentRequest {
+    #[cfg_attr(feature = "openapi", schema(example = "anna@example.com"))]
+    #[validate(email)]
+    pub customer_email: String,
+    /// Name of the customer, if one is created for `customer_email`
+    #[cfg_attr(feature = "openapi", schema(example = "Anna Muster"))]
+    #[validate(length(min = 1))]
+    pub customer_name: Option<String>,
+}
+
+/// A SetupIntent for confirming the card with Stripe.js.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct CreateSetupIntentResponse {
+    #[cfg_attr(feature = "openapi", schema(example = "seti_1N..."))]
+    pub setup_intent_id: String,
+    /// Secret for `stripe.confirmCardSetup` in the frontend
+    pub client_secret: String,
+    #[cfg_attr(feature = "openapi", schema(example = "cus_N..."))]
+    pub customer_id: String,
+}
+
+/// A card saved for a customer.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct SavedPaymentMethod {
+    #[cfg_attr(feature = "openapi", schema(example = "pm_1N..."))]
+    pub payment_method_id: String,
+    #[cfg_attr(feature = "openapi", schema(example = "visa"))]
+    pub brand: String,
+    #[cfg_attr(feature = "openapi", schema(example = "4242"))]
+    pub last4: String,
+    pub exp_month: u32,
+    pub exp_year: u32,
+}
+
+impl SavedPaymentMethod {
+    /// The saved card of a payment method, `None` for other types of payment methods.
+    fn from_card(method: PaymentMethod) -> Option<Self> {
+        let card = method.card?;
+        Some(SavedPaymentMethod {
+            payment_method_id: method.id,
+            brand: card.brand,
+            last4: card.last4,
+            exp_month: card.exp_month,
+            exp_year: card.exp_year,
         })
     }
 }
+
+/// Request to charge a saved card while the customer is not present.
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ChargeSavedPaymentMethodRequest {
+    #[cfg_attr(feature = "openapi", schema(example = "cus_N..."))]
+    #[validate(length(min = 1))]
+    pub customer_id: String,
+    #[cfg_attr(feature = "openapi", schema(example = "pm_1N..."))]
+    #[validate(length(min = 1))]
+    pub payment_method_id: String,
+    /// Amount in the smallest currency unit
+    #[cfg_attr(feature = "openapi", schema(example = 5000))]
+    #[validate(range(min = 1))]
+    pub amount: i64,
+    /// The configured default currency if not set
+    #[cfg_attr(feature = "openapi", schema(example = "CHF"))]
+    #[validate(length(equal = 3))]
+    pub currency: Option<String>,
+    #[cfg_attr(feature = "openapi", schema(example = "Consultation 60 min"))]
+    pub description: Option<String>,
+    #[cfg_attr(feature = "openapi", schema(example = "my_internal_order_123"))]
+    pub client_reference_id: Option<String>,
+}
+
+/// Response after a saved card was charged.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ChargeSavedPaymentMethodResponse {
+    pub payment_intent_id: String,
+    /// e.g. "succeeded" or "processing"
+    pub status: String,
+    pub amount: i64,
+    pub currency: String,
+}
+
+/// Creates a SetupIntent saving a card of the client's Stripe customer for off-session
+/// payments.
+pub async fn create_setup_intent(
+    request: &CreateSetupIntentRequest,
+) -> Result<CreateSetupIntentResponse, StripeError> {
+    let client = StripeClient::from_env()?;
+    let customer = find_or_create_customer(
+        &client,
+        &request.customer_email,
+        request.customer_name.as_deref(),
+    )
+    .await?;
+
+    let params = SetupIntentParams {
+        customer: customer.customer_id.clone(),
+        payment_method_types: vec!["card".to_string()],
+        usage: "off_session".to_string(),
+    };
+    let setup_intent: SetupIntent = client
+        .post("create_setup_intent", "setup_intents", &params)
+        .await?;
+    info!(
+        "[Stripe Logic] SetupIntent {} created for customer {}",
+        setup_intent.id, customer.customer_id
+    );
+    let client_secret = setup_intent.client_secret.ok_or_else(|| {
+        StripeError::InternalError("Stripe response missing SetupIntent client secret".to_string())
+    })?;
+    Ok(CreateSetupIntentResponse {
+        setup_intent_id: setup_intent.id,
+        client_secret,
+        customer_id: customer.customer_id,
+    })
+}
+
+/// Lists the cards saved for a customer.
+pub async fn list_saved_payment_methods(
+    customer_id: &str,
+) -> Result<Vec<SavedPaymentMethod>, StripeError> {
+    let query = PaymentMethodListQuery {
+        customer: customer_id.to_string(),
+        method_type: "card".to_string(),
+        limit: 100,
+    };
+    let methods: StripeListObject<PaymentMethod> = StripeClient::from_env()?
+        .list("list_payment_methods", "payment_methods", &query)
+        .await?;
+    Ok(methods
+        .data
+        .into_iter()
+        .filter_map(SavedPaymentMethod::from_card)
+        .collect())
+}
+
+/// Charges a saved card of a customer who is not present, e.g. for a repeat booking.
+///
+/// Fails with Stripe's `402` error if the bank requires the customer to authenticate the
+/// payment; they then have to go through a regular checkout.
+pub async fn charge_saved_payment_method(
+    pricing_config: &PricingConfig,
+    request: &ChargeSavedPaymentMethodRequest,
+) -> Result<PaymentIntentResult, StripeError> {
+    info!(
+        "[Stripe Logic] Charging saved payment method {} of customer {} (amount: {})",
+        request.payment_method_id, request.customer_id, request.amount
+    );
+
+    let currency = request
+        .currency
+        .clone()
+        .or_else(|| pricing_config.default_currency.clone())
+        .unwrap_or_else(|| "chf".to_string())
+        .to_lowercase();
+    let mut metadata = BTreeMap::new();
+    if let Some(reference) = &request.client_reference_id {
+        metadata.insert("reference".to_string(), reference.clone());
+    }
+    let params = PaymentIntentParams {
+        amount: request.amount,
+        currency,
+        customer: Some(request.customer_id.clone()),
+        payment_method: Some(request.payment_method_id.clone()),
+        off_session: Some(true),
+        confirm: Some(true),
+        description: request.description.clone(),
+        metadata,
+        ..Default::default()
+    };
+    let payment_intent: PaymentIntent = StripeClient::from_env()?
+        .post("create_payment_intent", "payment_intents", &params)
+        .await?;
+    info!(
+        "[Stripe Logic] PaymentIntent {} for customer {} is {}",
+        payment_intent.id, request.customer_id, payment_intent.status
+    );
+
+    if payment_intent.status == "succeeded" {
+        events::publish(PaymentSucceeded {
+            provider: STRIPE_PROVIDER.to_string(),
+            payment_id: payment_intent.id.clone(),
+            amount: Some(payment_intent.amount),
+            currency: Some(payment_intent.currency.clone()),
+            reference: request.client_reference_id.clone(),
+        });
+    }
+    Ok(payment_intent.into())
+}
+
+// --- Billing portal ---
+
+/// Request to open the Stripe Billing Portal for a known customer.
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct CreateBillingPortalSessionRequest {
+    #[cfg_attr(feature = "openapi", schema(example = "anna@example.com"))]
+    #[validate(email)]
+    pub customer_email: String,
+    /// Page the portal links back to, `portal_return_url` of the Stripe config if not set
+    #[cfg_attr(feature = "openapi", schema(example = "https://example.com/account"))]
+    #[validate(url)]
+    pub return_url: Option<String>,
+}
+
+/// A Billing Portal session, where the customer manages their payment methods, invoices and
+/// subscriptions.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct BillingPortalSessionResponse {
+    #[cfg_attr(feature = "openapi", schema(example = "bps_1N..."))]
+    pub session_id: String,
+    /// Short-lived URL to redirect the customer to
+    pub url: String,
+    #[cfg_attr(feature = "openapi", schema(example = "cus_N..."))]
+    pub customer_id: String,
+}
+
+/// The parameters of a Billing Portal session for a customer.
+fn billing_portal_params(
+    stripe_config: &StripeConfig,
+    customer_id: &str,
+    request: &CreateBillingPortalSessionRequest,
+) -> BillingPortalSessionParams {
+    BillingPortalSessionParams {
+        customer: customer_id.to_string(),
+        return_url: request
+            .return_url
+            .clone()
+            .or_else(|| stripe_config.portal_return_url.clone()),
+    }
+}
+
+/// Creates a Billing Portal session for the Stripe customer of an email address.
+///
+/// Customers are not created here, an email address without a customer fails with
+/// [`StripeError::CustomerNotFound`].
+pub async fn create_billing_portal_session(
+    stripe_config: &StripeConfig,
+    request: &CreateBillingPortalSessionRequest,
+) -> Result<BillingPortalSessionResponse, StripeError> {
+    let client = StripeClient::from_env()?;
+    let customer = find_customer(&client, &request.customer_email)
+        .await?
+        .ok_or_else(|| StripeError::CustomerNotFound(request.customer_email.clone()))?;
+
+    let params = billing_portal_params(stripe_config, &customer.customer_id, request);
+    let session: BillingPortalSession = client
+        .post(
+            "create_billing_portal_session",
+            "billing_portal/sessions",
+            &params,
+        )
+        .await?;
+    info!(
+        "[Stripe Logic] Billing Portal session {} created for customer {}",
+        session.id, customer.customer_id
+    );
+    Ok(BillingPortalSessionResponse {
+        session_id: session.id,
+        url: session.url,
+        customer_id: customer.customer_id,
+    })
+}
+
+// --- PaymentIntents (embedded Payment Element) ---
+
+/// Request from our frontend to create a PaymentIntent for the embedded Payment Element.
+///
+/// Like for Checkout Sessions, the amount is determined by the configured price tiers.
+#[derive(Deserialize, Serialize, Debug, Validate)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct CreatePaymentIntentRequest {
+    /// Type of fulfillment to trigger (e.g., "gcal_booking")
+    #[cfg_attr(feature = "openapi", schema(example = "gcal_booking"))]
+    #[validate(length(min = 1))]
+    pub fulfillment_type: String,
+    /// JSON data specific to the fulfillment_type
+    #[cfg_attr(feature = "openapi", schema(example = json!({
+        "start_time": "2025-07-15T10:00:00Z",
+        "end_time": "2025-07-15T11:00:00Z",
+        "summary": "Consultation via Stripe"
+    })))]
+    pub fulfillment_data: serde_json::Value,
+    #[cfg_attr(feature = "openapi", schema(example = "my_internal_order_123"))]
+    pub client_reference_id: Option<String>,
+    /// Currency to pay in, which the booked price tier needs a price in (default: the tier's
+    /// currency)
+    #[cfg_attr(feature = "openapi", schema(example = "EUR"))]
+    #[validate(length(equal = 3))]
+    pub currency: Option<String>,
+}
+
+/// A PaymentIntent, with the secret the Payment Element needs to confirm it.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct PaymentIntentResponse {
+    #[cfg_attr(feature = "openapi", schema(example = "pi_3N..."))]
+    pub payment_intent_id: String,
+    /// e.g. "requires_payment_method", "succeeded" or "canceled"
+    pub status: String,
+    pub amount: i64,
+    pub currency: String,
+    /// Secret for `stripe.elements` and `stripe.confirmPayment` in the frontend
+    pub client_secret: Option<String>,
+}
+
+impl From<PaymentIntentResult> for PaymentIntentResponse {
+    fn from(result: PaymentIntentResult) -> Self {
+        Self {
+            payment_intent_id: result.id,
+            status: result.status,
+            amount: result.amount,
+            currency: result.currency,
+            client_secret: result.client_secret,
+        }
+    }
+}
+
+/// Amount, description and fulfillment metadata of a booking paid with a PaymentIntent.
+pub(crate) struct BookingPayment {
+    pub amount: i64,
+    pub currency: String,
+    pub description: String,
+    pub metadata: serde_json::Value,
+    /// The order to save before creating the PaymentIntent
+    pub order: FulfillmentOrder,
+}
+
+/// Prices a booking for the Payment Element and prepares its fulfillment metadata, which the
+/// `payment_intent.succeeded` webhook uses to fulfill it.
+pub(crate) fn booking_payment(
+    pricing_config: &PricingConfig,
+    request: &CreatePaymentIntentRequest,
+) -> Result<BookingPayment, StripeError> {
+    let price = tier_price(
+        pricing_config,
+        &request.fulfillment_type,
+        &request.fulfillment_data,
+        request.currency.as_deref(),
+    )?;
+    let reference = request
+        .client_reference_id
+        .clone()
+        .unwrap_or_else(|| format!("stripe-{}", uuid::Uuid::new_v4()));
+
+    let mut fulfillment_data = request.fulfillment_data.clone();
+    fulfillment_data["payment_method"] = serde_json::Value::String("stripe".to_string());
+    fulfillment_data["payment_amount"] =
+        serde_json::Value::Number(serde_json::Number::from(price.unit_amount));
+    fulfillment_data["original_reference_id"] = serde_json::Value::String(reference.clone());
+    let order = fulfillment_order(&request.fulfillment_type, &fulfillment_data)?;
+
+    Ok(BookingPayment {
+        amount: price.unit_amount,
+        currency: price.currency,
+        description: price.product_name,
+        metadata: serde_json::json!({
+            "ff_type": request.fulfillment_type,
+            FULFILLMENT_ORDER_KEY: order.order_id,
+            "reference": reference,
+        }),
+        order,
+    })
+}
+
+/// Converts JSON metadata to Stripe's string metadata values.
+fn metadata_params(metadata: Option<&serde_json::Value>) -> BTreeMap<String, String> {
+    let Some(serde_json::Value::Object(object)) = metadata else {
+        return BTreeMap::new();
+    };
+    object
+        .iter()
+        .filter(|(_, value)| !value.is_null())
+        .map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(value) => value.clone(),
+                other => other.to_string(),
+            };
+            (key.clone(), value)
+        })
+        .collect()
+}
+
+/// Checks that a PaymentIntent id from a request path can be put into a Stripe API path.
+fn validate_payment_intent_id(payment_intent_id: &str) -> Result<(), StripeError> {
+    let valid = payment_intent_id.starts_with("pi_")
+        && payment_intent_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(StripeError::InvalidPaymentIntentId(
+            payment_intent_id.to_string(),
+        ))
+    }
+}
+
+impl From<PaymentIntent> for PaymentIntentResult {
+    fn from(payment_intent: PaymentIntent) -> Self {
+        Self {
+            id: payment_intent.id,
+            status: payment_intent.status,
+            amount: payment_intent.amount,
+            currency: payment_intent.currency,
+            client_secret: payment_intent.client_secret,
+        }
+    }
+}
+
+/// Creates a PaymentIntent for the payment methods enabled in the Stripe dashboard.
+pub async fn create_payment_intent(
+    amount: i64,
+    currency: &str,
+    description: Option<&str>,
+    metadata: Option<&serde_json::Value>,
+) -> Result<PaymentIntentResult, StripeError> {
+    info!(
+        "[Stripe Logic] Creating PaymentIntent (amount: {} {})",
+        amount, currency
+    );
+    let params = PaymentIntentParams {
+        amount,
+        currency: currency.to_lowercase(),
+        automatic_payment_methods: Some(AutomaticPaymentMethods { enabled: true }),
+        description: description.map(String::from),
+        metadata: metadata_params(metadata),
+        ..Default::default()
+    };
+    let payment_intent: PaymentIntent = StripeClient::from_env()?
+        .post("create_payment_intent", "payment_intents", &params)
+        .await?;
+    info!("[Stripe Logic] PaymentIntent {} created", payment_intent.id);
+    Ok(payment_intent.into())
+}
+
+/// Confirms a PaymentIntent with the payment method attached to it.
+pub async fn confirm_payment_intent(
+    payment_intent_id: &str,
+) -> Result<PaymentIntentResult, StripeError> {
+    validate_payment_intent_id(payment_intent_id)?;
+    let payment_intent: PaymentIntent = StripeClient::from_env()?
+        .post(
+            "confirm_payment_intent",
+            &format!("payment_intents/{}/confirm", payment_intent_id),
+            &BTreeMap::<String, String>::new(),
+        )
+        .await?;
+    info!(
+        "[Stripe Logic] PaymentIntent {} confirmed, status: {}",
+        payment_intent.id, payment_intent.status
+    );
+    Ok(payment_intent.into())
+}
+
+/// Cancels a PaymentIntent that was not paid yet.
+pub async fn cancel_payment_intent(
+    payment_intent_id: &str,
+) -> Result<PaymentIntentResult, StripeError> {
+    validate_payment_intent_id(payment_intent_id)?;
+    let payment_intent: PaymentIntent = StripeClient::from_env()?
+        .post(
+            "cancel_payment_intent",
+            &format!("payment_intents/{}/cancel", payment_intent_id),
+            &BTreeMap::<String, String>::new(),
+        )
+        .await?;
+    info!(
+        "[Stripe Logic] PaymentIntent {} canceled",
+        payment_intent.id
+    );
+    Ok(payment_intent.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::to_form;
+    use connectify_common::holds::SlotHold;
+    use serde_json::json;
+
+    #[test]
+    fn test_checkout_mode_validation() {
+        let request = |body: serde_json::Value| -> CreateCheckoutSessionRequest {
+            serde_json::from_value(body).unwrap()
+        };
+        let payment = request(json!({
+            "fulfillment_type": "gcal_booking",
+            "fulfillment_data": {}
+        }));
+        assert_eq!(payment.mode, CheckoutMode::Payment);
+        assert!(payment.validate().is_ok());
+
+        let subscription = request(json!({
+            "fulfillment_type": "coaching_package",
+            "fulfillment_data": {},
+            "mode": "subscription",
+            "price_id": "price_coaching"
+        }));
+        assert_eq!(subscription.mode, CheckoutMode::Subscription);
+        assert!(subscription.validate().is_ok());
+
+        let without_price = request(json!({
+            "fulfillment_type": "coaching_package",
+            "fulfillment_data": {},
+            "mode": "subscription"
+        }));
+        assert!(without_price.validate().is_err());
+
+        let invalid_email = request(json!({
+            "fulfillment_type": "gcal_booking",
+            "fulfillment_data": {},
+            "customer_email": "not an email"
+        }));
+        assert!(invalid_email.validate().is_err());
+    }
+
+    #[test]
+    fn test_saved_payment_method() {
+        let methods: StripeListObject<PaymentMethod> = serde_json::from_value(json!({
+            "object": "list",
+            "data": [
+                {
+                    "id": "pm_card",
+                    "object": "payment_method",
+                    "type": "card",
+                    "card": { "brand": "visa", "last4": "4242", "exp_month": 12, "exp_year": 2030 }
+                },
+                { "id": "pm_twint", "object": "payment_method", "type": "twint" }
+            ],
+            "has_more": false,
+            "url": "/v1/payment_methods"
+        }))
+        .unwrap();
+        let saved: Vec<SavedPaymentMethod> = methods
+            .data
+            .into_iter()
+            .filter_map(SavedPaymentMethod::from_card)
+            .collect();
+        assert_eq!(
+            saved,
+            vec![SavedPaymentMethod {
+                payment_method_id: "pm_card".to_string(),
+                brand: "visa".to_string(),
+                last4: "4242".to_string(),
+                exp_month: 12,
+                exp_year: 2030,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_booking_payment() {
+        let pricing_config: PricingConfig = serde_json::from_value(json!({
+            "price_tiers": [
+                {
+                    "duration_minutes": 60,
+                    "unit_amount": 12000,
+                    "product_name": "Consultation 60 min",
+                    "amounts": { "EUR": 12500 }
+                }
+            ],
+            "default_currency": "CHF"
+        }))
+        .unwrap();
+        let request = CreatePaymentIntentRequest {
+            fulfillment_type: "gcal_booking".to_string(),
+            fulfillment_data: json!({
+                "start_time": "2025-07-15T10:00:00Z",
+                "end_time": "2025-07-15T11:00:00Z"
+            }),
+            client_reference_id: Some("order-1".to_string()),
+            currency: None,
+        };
+
+        let payment = booking_payment(&pricing_config, &request).unwrap();
+        assert_eq!(payment.amount, 12000);
+        assert_eq!(payment.currency, "chf");
+        assert_eq!(payment.description, "Consultation 60 min");
+        let metadata = metadata_params(Some(&payment.metadata));
+        assert_eq!(metadata["ff_type"], "gcal_booking");
+        assert_eq!(metadata["reference"], "order-1");
+        assert_eq!(metadata["ff_order_id"], payment.order.order_id);
+        assert_eq!(payment.order.fulfillment_type, "gcal_booking");
+        let fulfillment_data: serde_json::Value =
+            serde_json::from_str(&payment.order.payload).unwrap();
+        assert_eq!(fulfillment_data["payment_amount"], 12000);
+        assert_eq!(fulfillment_data["original_reference_id"], "order-1");
+
+        // The tier's price in a requested currency, matched case-insensitively
+        let in_euros = CreatePaymentIntentRequest {
+            currency: Some("eur".to_string()),
+            ..request
+        };
+        let payment = booking_payment(&pricing_config, &in_euros).unwrap();
+        assert_eq!(payment.amount, 12500);
+        assert_eq!(payment.currency, "eur");
+        let in_francs = CreatePaymentIntentRequest {
+            currency: Some("CHF".to_string()),
+            ..in_euros
+        };
+        assert_eq!(
+            booking_payment(&pricing_config, &in_francs).unwrap().amount,
+            12000
+        );
+        let in_dollars = CreatePaymentIntentRequest {
+            currency: Some("USD".to_string()),
+            ..in_francs
+        };
+        match booking_payment(&pricing_config, &in_dollars) {
+            Err(StripeError::UnsupportedCurrency {
+                currency,
+                available,
+                ..
+            }) => {
+                assert_eq!(currency, "USD");
+                assert_eq!(available, "CHF, EUR");
+            }
+            other => panic!("expected UnsupportedCurrency, got {:?}", other.err()),
+        }
+        let request = CreatePaymentIntentRequest {
+            currency: None,
+            ..in_dollars
+        };
+
+        let unpriced = CreatePaymentIntentRequest {
+            fulfillment_data: json!({
+                "start_time": "2025-07-15T10:00:00Z",
+                "end_time": "2025-07-15T10:45:00Z"
+            }),
+            ..request
+        };
+        assert!(matches!(
+            booking_payment(&pricing_config, &unpriced),
+            Err(StripeError::NoMatchingPriceTier(45))
+        ));
+    }
+
+    #[test]
+    fn test_checkout_expires_at() {
+        let mut stripe_config: StripeConfig = serde_json::from_value(json!({
+            "success_url": "https://example.com/success",
+            "cancel_url": "https://example.com/cancel",
+            "payment_success_url": "https://example.com/paid"
+        }))
+        .unwrap();
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let minutes_ahead = |stripe_config: &StripeConfig| {
+            (checkout_expires_at(stripe_config, now) - now.timestamp()) / 60
+        };
+
+        assert_eq!(minutes_ahead(&stripe_config), 31);
+        stripe_config.checkout_expires_after_minutes = Some(120);
+        assert_eq!(minutes_ahead(&stripe_config), 120);
+        // Kept within the bounds Stripe accepts
+        stripe_config.checkout_expires_after_minutes = Some(5);
+        assert_eq!(minutes_ahead(&stripe_config), 31);
+        stripe_config.checkout_expires_after_minutes = Some(7 * 24 * 60);
+        assert_eq!(minutes_ahead(&stripe_config), 24 * 60 - 1);
+    }
+
+    #[test]
+    fn test_checkout_payment_method_types() {
+        let mut stripe_config: StripeConfig = serde_json::from_value(json!({
+            "success_url": "https://example.com/success",
+            "cancel_url": "https://example.com/cancel",
+            "payment_success_url": "https://example.com/paid"
+        }))
+        .unwrap();
+        assert_eq!(checkout_payment_method_types(&stripe_config), vec!["card"]);
+
+        stripe_config.payment_method_types = vec![
+            "card".to_string(),
+            " TWINT ".to_string(),
+            "sepa_debit".to_string(),
+            "card".to_string(),
+        ];
+        assert_eq!(
+            checkout_payment_method_types(&stripe_config),
+            vec!["card", "twint", "sepa_debit"]
+        );
+
+        // Stripe offers the methods enabled in the dashboard, e.g. Apple Pay and Google Pay
+        stripe_config.payment_method_types = vec!["automatic".to_string()];
+        assert!(checkout_payment_method_types(&stripe_config).is_empty());
+        let params = CheckoutSessionParams {
+            payment_method_types: checkout_payment_method_types(&stripe_config),
+            mode: "payment".to_string(),
+            ..Default::default()
+        };
+        assert!(!to_form(&params)
+            .unwrap()
+            .iter()
+            .any(|(key, _)| key.starts_with("payment_method_types")));
+    }
+
+    #[test]
+    fn test_success_token() {
+        let secret = b"success_token_secret";
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let token = sign_success_token(secret, "nonce1", now.timestamp() + 60);
+        assert_eq!(verify_success_token(secret, &token, now).unwrap(), "nonce1");
+
+        // Expired, forged or signed with another key
+        assert!(matches!(
+            verify_success_token(secret, &token, now + chrono::Duration::minutes(2)),
+            Err(StripeError::InvalidSuccessToken(_))
+        ));
+        let forged = token.replacen("nonce1", "nonce2", 1);
+        assert!(verify_success_token(secret, &forged, now).is_err());
+        let extended = sign_success_token(b"other", "nonce1", now.timestamp() + 3600);
+        assert!(verify_success_token(secret, &extended, now).is_err());
+        assert!(verify_success_token(secret, "cs_test_a1", now).is_err());
+    }
+
+    #[test]
+    fn test_success_token_secret() {
+        let mut stripe_config: StripeConfig = serde_json::from_value(json!({
+            "success_url": "https://example.com/success",
+            "cancel_url": "https://example.com/cancel",
+            "payment_success_url": "https://example.com/paid"
+        }))
+        .unwrap();
+        assert!(matches!(
+            success_token_secret(&stripe_config),
+            Err(StripeError::ConfigError)
+        ));
+        // The environment variable of the marker is not set
+        stripe_config.success_token_secret = Some("secret_from_env".to_string());
+        assert!(success_token_secret(&stripe_config).is_err());
+        stripe_config.success_token_secret = Some("0123abcd".to_string());
+        assert_eq!(success_token_secret(&stripe_config).unwrap(), "0123abcd");
+    }
+
+    #[test]
+    fn test_add_success_token() {
+        let mut params = CheckoutSessionParams {
+            success_url: "https://example.com/success?session_id={CHECKOUT_SESSION_ID}".to_string(),
+            expires_at: Some(1_700_000_000),
+            ..Default::default()
+        };
+        add_success_token(&mut params, "success_token_secret");
+
+        let nonce = params.metadata.get(SUCCESS_NONCE_KEY).unwrap().clone();
+        let (url, token) = params.success_url.split_once("&success_token=").unwrap();
+        assert_eq!(
+            url,
+            "https://example.com/success?session_id={CHECKOUT_SESSION_ID}"
+        );
+        let now = DateTime::from_timestamp(1_700_000_000 + SUCCESS_TOKEN_GRACE_SECONDS, 0).unwrap();
+        assert_eq!(
+            verify_success_token(b"success_token_secret", token, now).unwrap(),
+            nonce
+        );
+    }
+
+    #[test]
+    fn test_dead_letter_retries() {
+        let mut stripe_config: StripeConfig = serde_json::from_value(json!({
+            "success_url": "https://example.com/success",
+            "cancel_url": "https://example.com/cancel",
+            "payment_success_url": "https://example.com/paid"
+        }))
+        .unwrap();
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let policy = dead_letter_retry_policy(Some(&stripe_config));
+        assert_eq!(policy.max_attempts, DEFAULT_FULFILLMENT_MAX_ATTEMPTS);
+        let minutes_ahead = |attempts| (next_retry_at(&policy, attempts, now) - now).num_minutes();
+
+        // The wait doubles after each failed attempt, up to 6 hours
+        assert_eq!(minutes_ahead(1), 1);
+        assert_eq!(minutes_ahead(2), 2);
+        assert_eq!(minutes_ahead(5), 16);
+        assert_eq!(minutes_ahead(20), 6 * 60);
+
+        stripe_config.fulfillment_max_attempts = Some(3);
+        assert_eq!(
+            dead_letter_retry_policy(Some(&stripe_config)).max_attempts,
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_release_session_hold() {
+        let start_time = Utc::now() + chrono::Duration::days(1);
+        let hold = |id: &str| SlotHold {
+            id: id.to_string(),
+            calendar_id: "primary".to_string(),
+            start_time,
+            end_time: start_time + chrono::Duration::hours(1),
+            expires_at: Utc::now() + chrono::Duration::minutes(15),
+            reference: None,
+        };
+        slot_hold_store()
+            .create(hold("hold-expired-session"))
+            .await
+            .unwrap();
+        slot_hold_store()
+            .create(hold("hold-legacy-session"))
+            .await
+            .unwrap();
+
+        let metadata = HashMap::from([("hold_id".to_string(), "hold-expired-session".to_string())]);
+        assert!(release_session_hold("cs_1", Some(&metadata)).await.unwrap());
+        assert!(slot_hold_store()
+            .get("hold-expired-session")
+            .await
+            .unwrap()
+            .is_none());
+        // Releasing again, e.g. for a redelivered webhook, is fine
+        assert!(!release_session_hold("cs_1", Some(&metadata)).await.unwrap());
+
+        // Sessions that only have the hold in their fulfillment data
+        let legacy = HashMap::from([(
+            "ff_data_json".to_string(),
+            json!({ "hold_id": "hold-legacy-session" }).to_string(),
+        )]);
+        assert!(release_session_hold("cs_2", Some(&legacy)).await.unwrap());
+        assert!(!release_session_hold("cs_3", None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_fulfillment_data_json() {
+        let order = fulfillment_order(
+            "gcal_booking",
+            &json!({ "summary": "x".repeat(1000), "hold_id": "hold-1" }),
+        )
+        .unwrap();
+        save_fulfillment_order(order.clone()).await.unwrap();
+
+        let metadata = HashMap::from([
+            ("ff_type".to_string(), "gcal_booking".to_string()),
+            (FULFILLMENT_ORDER_KEY.to_string(), order.order_id.clone()),
+        ]);
+        let loaded = fulfillment_data_json(Some(&metadata))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded, order.payload);
+        let fulfillment_data: serde_json::Value = serde_json::from_str(&loaded).unwrap();
+        assert_eq!(fulfillment_data["hold_id"], "hold-1");
+
+        // Payments created before the data was stored as an order
+        let legacy = HashMap::from([("ff_data_json".to_string(), "{}".to_string())]);
+        assert_eq!(
+            fulfillment_data_json(Some(&legacy))
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("{}")
+        );
+        let unknown =
+            HashMap::from([(FULFILLMENT_ORDER_KEY.to_string(), "ord_unknown".to_string())]);
+        assert_eq!(fulfillment_data_json(Some(&unknown)).await.unwrap(), None);
+        assert_eq!(fulfillment_data_json(None).await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_list_sessions_admin_query() {
+        let query: ListSessionsAdminQuery = serde_json::from_value(json!({
+            "created_from": "2025-07-01",
+            "created_to": "2025-07-31",
+            "customer_email": " client@example.com ",
+            "payment_status": "paid",
+            "client_reference_id": "order-1"
+        }))
+        .unwrap();
+        let page = CursorQuery {
+            limit: Some(20),
+            cursor: Some("cs_0".to_string()),
+            order: None,
+        };
+        assert_eq!(
+            to_form(&query.stripe_query(&page).unwrap()).unwrap(),
+            vec![
+                ("created[gte]".to_string(), "1751328000".to_string()),
+                ("created[lte]".to_string(), "1754006399".to_string()),
+                (
+                    "customer_details[email]".to_string(),
+                    "client@example.com".to_string()
+                ),
+                // Filtering by payment status lists as many sessions as possible
+                ("limit".to_string(), "100".to_string()),
+                ("starting_after".to_string(), "cs_0".to_string())
+            ]
+        );
+        let unfiltered: ListSessionsAdminQuery = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(
+            to_form(&unfiltered.stripe_query(&page).unwrap()).unwrap(),
+            vec![
+                ("limit".to_string(), "21".to_string()),
+                ("starting_after".to_string(), "cs_0".to_string())
+            ]
+        );
+        let oldest_first = CursorQuery {
+            order: Some(SortOrder::Asc),
+            ..Default::default()
+        };
+        assert!(matches!(
+            unfiltered.stripe_query(&oldest_first),
+            Err(StripeError::InvalidSessionFilter(_))
+        ));
+
+        let session = |payment_status: &str, reference: Option<&str>| {
+            serde_json::from_value::<StripeCheckoutSessionData>(json!({
+                "id": "cs_1",
+                "object": "checkout.session",
+                "amount_total": 12000,
+                "currency": "chf",
+                "customer": null,
+                "customer_details": null,
+                "metadata": null,
+                "payment_intent": null,
+                "payment_status": payment_status,
+                "status": "complete",
+                "success_url": null,
+                "cancel_url": null,
+                "client_reference_id": reference,
+                "created": 1751328000,
+                "expires_at": null,
+                "room_name": null
+            }))
+            .unwrap()
+        };
+        assert!(query.matches(&session("paid", Some("order-1"))));
+        assert!(!query.matches(&session("unpaid", Some("order-1"))));
+        assert!(!query.matches(&session("paid", Some("order-2"))));
+        assert!(!query.matches(&session("paid", None)));
+
+        let invalid = |filters: serde_json::Value| {
+            let query: ListSessionsAdminQuery = serde_json::from_value(filters).unwrap();
+            matches!(
+                query.stripe_query(&CursorQuery::default()),
+                Err(StripeError::InvalidSessionFilter(_))
+            )
+        };
+        assert!(invalid(json!({ "created_from": "01.07.2025" })));
+        assert!(invalid(
+            json!({ "created_from": "2025-07-31", "created_to": "2025-07-01" })
+        ));
+        assert!(invalid(json!({ "payment_status": "refunded" })));
+    }
+
+    #[tokio::test]
+    async fn test_session_booking() {
+        let order = fulfillment_order(
+            "gcal_booking",
+            &json!({ "start_time": "2025-07-15T10:00:00Z", "end_time": "2025-07-15T11:00:00Z" }),
+        )
+        .unwrap();
+        save_fulfillment_order(order.clone()).await.unwrap();
+        let session: StripeCheckoutSessionData = serde_json::from_value(json!({
+            "id": "cs_session_booking",
+            "object": "checkout.session",
+            "amount_total": 12000,
+            "currency": "chf",
+            "customer": null,
+            "customer_details": null,
+            "metadata": { "ff_type": "gcal_booking", "ff_order_id": order.order_id },
+            "payment_intent": null,
+            "payment_status": "paid",
+            "status": "complete",
+            "success_url": null,
+            "cancel_url": null,
+            "client_reference_id": null,
+            "created": 1751328000,
+            "expires_at": null,
+            "room_name": null
+        }))
+        .unwrap();
+
+        let booking = session_booking(&session).await.unwrap();
+        assert_eq!(booking.fulfillment_type, "gcal_booking");
+        assert_eq!(
+            booking.fulfillment_data.unwrap()["start_time"],
+            "2025-07-15T10:00:00Z"
+        );
+        assert_eq!(booking.fulfillment_retry_status, None);
+
+        // Sessions that don't pay for a fulfillment have no booking
+        let unrelated = StripeCheckoutSessionData {
+            metadata: None,
+            ..session
+        };
+        assert_eq!(session_booking(&unrelated).await, None);
+    }
+
+    #[test]
+    fn test_validate_payment_intent_id() {
+        assert!(validate_payment_intent_id("pi_3N1abc").is_ok());
+        assert!(validate_payment_intent_id("cus_123").is_err());
+        assert!(validate_payment_intent_id("pi_1/../../customers").is_err());
+    }
+
+    #[test]
+    fn test_session_payment_record() {
+        let session: StripeCheckoutSessionObject = serde_json::from_value(json!({
+            "id": "cs_test_tax",
+            "object": "checkout.session",
+            "amount_subtotal": 10000,
+            "amount_total": 10810,
+            "currency": "chf",
+            "customer": "cus_123",
+            "customer_details": {
+                "email": "anna@example.com",
+                "address": { "city": "Zürich", "country": "CH", "postal_code": "8001" }
+            },
+            "total_details": { "amount_discount": 0, "amount_shipping": 0, "amount_tax": 810 },
+            "payment_status": "paid",
+            "client_reference_id": "order-1"
+        }))
+        .unwrap();
+
+        let record = session_payment_record(&session);
+        assert_eq!(record.payment_id, "cs_test_tax");
+        assert_eq!(record.amount_total, Some(10810));
+        assert_eq!(record.amount_subtotal, Some(10000));
+        assert_eq!(record.amount_tax, Some(810));
+        assert_eq!(record.country.as_deref(), Some("CH"));
+        assert_eq!(record.customer_id.as_deref(), Some("cus_123"));
+        assert_eq!(record.reference.as_deref(), Some("order-1"));
+    }
+
+    #[test]
+    fn test_webhook_signature_tolerance() {
+        use connectify_common::clock::MockClock;
+
+        let secret = "whsec_test_secret";
+        let payload = br#"{"id":"evt_123","type":"payment_intent.succeeded"}"#;
+        let signed_at = 1_700_000_000;
+        let mut signed_payload = format!("{}.", signed_at).into_bytes();
+        signed_payload.extend_from_slice(payload);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            STRIPE_SIGNATURE_HEADER,
+            format!(
+                "t={},v1={}",
+                signed_at,
+                hmac_sha256_hex(secret.as_bytes(), &signed_payload)
+            )
+            .parse()
+            .unwrap(),
+        );
+        let clock = Arc::new(MockClock::new(
+            DateTime::from_timestamp(signed_at + DEFAULT_WEBHOOK_TOLERANCE_SECONDS, 0).unwrap(),
+        ));
+        let verifier = StripeWebhookVerifier::new(secret).with_clock(clock.clone());
+
+        let verified = verifier.verify(payload, &headers).unwrap();
+        assert_eq!(verified.timestamp, Some(signed_at));
+
+        // Replaying the request after the tolerance fails
+        clock.advance(chrono::Duration::seconds(1));
+        assert!(matches!(
+            verifier.verify(payload, &headers),
+            Err(WebhookError::InvalidSignature(_))
+        ));
+        let lenient = StripeWebhookVerifier::new(secret)
+            .with_clock(clock.clone())
+            .with_tolerance(600);
+        assert!(lenient.verify(payload, &headers).is_ok());
+
+        // Timestamps in the future are limited as well
+        clock.set(DateTime::from_timestamp(signed_at - 301, 0).unwrap());
+        assert!(verifier.verify(payload, &headers).is_err());
+
+        let stripe_config: StripeConfig = serde_json::from_value(json!({
+            "success_url": "https://example.com/success",
+            "cancel_url": "https://example.com/cancel",
+            "payment_success_url": "https://example.com/paid",
+            "webhook_tolerance_seconds": 60
+        }))
+        .unwrap();
+        assert_eq!(webhook_tolerance_seconds(Some(&stripe_config)), 60);
+        assert_eq!(webhook_tolerance_seconds(None), 300);
+    }
+
+    #[test]
+    fn test_billing_portal_params() {
+        let mut stripe_config: StripeConfig = serde_json::from_value(json!({
+            "success_url": "https://example.com/success",
+            "cancel_url": "https://example.com/cancel",
+            "payment_success_url": "https://example.com/paid"
+        }))
+        .unwrap();
+        let mut request = CreateBillingPortalSessionRequest {
+            customer_email: "anna@example.com".to_string(),
+            return_url: None,
+        };
+        assert!(request.validate().is_ok());
+
+        // Without a return URL the portal's default is used
+        assert_eq!(
+            to_form(&billing_portal_params(&stripe_config, "cus_123", &request)).unwrap(),
+            vec![("customer".to_string(), "cus_123".to_string())]
+        );
+
+        stripe_config.portal_return_url = Some("https://example.com/account".to_string());
+        let params = billing_portal_params(&stripe_config, "cus_123", &request);
+        assert_eq!(
+            params.return_url.as_deref(),
+            Some("https://example.com/account")
+        );
+        request.return_url = Some("https://example.com/bookings".to_string());
+        let params = billing_portal_params(&stripe_config, "cus_123", &request);
+        assert_eq!(
+            params.return_url.as_deref(),
+            Some("https://example.com/bookings")
+        );
+
+        request.return_url = Some("not a url".to_string());
+        assert!(request.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_remember_session_customer() {
+        let session = |customer: &str| -> StripeCheckoutSessionObject {
+            serde_json::from_value(json!({
+                "id": "cs_test_123",
+                "object": "checkout.session",
+                "customer": customer,
+                "customer_details": { "email": "Returning@Example.com" }
+            }))
+            .unwrap()
+        };
+        remember_session_customer(&session("cus_first")).await;
+        // A later session's customer doesn't replace the linked one
+        remember_session_customer(&session("cus_second")).await;
+
+        let linked = payment_customer_store()
+            .find_by_email(STRIPE_PROVIDER, "returning@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(linked.customer_id, "cus_first");
+    }
+
+    #[test]
+    fn test_subscription_object() {
+        let subscription: StripeSubscriptionObject = serde_json::from_value(json!({
+            "id": "sub_123",
+            "object": "subscription",
+            "status": "active",
+            "customer": "cus_123",
+            "items": {
+                "object": "list",
+                "data": [{
+                    "id": "si_123",
+                    "price": { "id": "price_coaching", "object": "price" },
+                    "current_period_end": 1750000000
+                }],
+                "has_more": false,
+                "url": "/v1/subscription_items?subscription=sub_123"
+            },
+            "metadata": { "reference": "order-1" }
+        }))
+        .unwrap();
+        assert_eq!(subscription.price_id(), Some("price_coaching"));
+        // Newer API versions only set the billing period on the items
+        assert_eq!(subscription.current_period_end(), Some(1750000000));
+        assert!(!subscription.cancel_at_period_end);
+    }
+}
@@ -1,8 +1,15 @@
 // --- File: crates/connectify_stripe/src/routes.rs ---
 
+use crate::client::configure_api_base_url;
 use crate::handlers::{
-    admin_get_checkout_session_details_handler, admin_list_checkout_sessions_handler,
-    create_checkout_session_handler, get_checkout_session_details_handler,
+    admin_charge_saved_payment_method_handler, admin_create_billing_portal_session_handler,
+    admin_find_or_create_customer_handler, admin_get_checkout_session_details_handler,
+    admin_get_customer_handler, admin_list_checkout_sessions_handler,
+    admin_list_dead_letters_handler, admin_list_payment_methods_handler,
+    admin_list_webhook_events_handler, admin_replay_webhook_event_handler,
+    admin_requeue_dead_letter_handler, cancel_payment_intent_handler,
+    confirm_payment_intent_handler, create_checkout_session_handler, create_payment_intent_handler,
+    create_refund_handler, create_setup_intent_handler, get_checkout_session_details_handler,
     stripe_checkout_cancel_handler, stripe_checkout_success_handler, stripe_webhook_handler,
     StripeState,
 };
@@ -10,19 +17,58 @@ use axum::{
     routing::{get, post},
     Router,
 };
+use connectify_common::api_key::ApiKeyAuthLayer;
+use connectify_common::idempotency::IdempotencyLayer;
+use connectify_common::rate_limit::RateLimitLayer;
+use connectify_common::runtime_flags::{feature_guard, CHECKOUT};
 use connectify_config::AppConfig;
 use std::sync::Arc;
+use tracing::warn;
+
+/// Scope an API key needs to create refunds and charges and manage webhook events and
+/// customers.
+pub const ADMIN_SCOPE: &str = "admin";
 
 /// Creates a router containing all routes for the Stripe feature.
 pub fn routes(config: Arc<AppConfig>) -> Router {
+    let webhook_rate_limit = RateLimitLayer::for_group(&config, "stripe_webhook");
+    let admin_auth = ApiKeyAuthLayer::from_config(&config);
+    configure_api_base_url(
+        config
+            .stripe
+            .as_ref()
+            .and_then(|stripe| stripe.api_base_url.as_deref()),
+    );
     let stripe_state = Arc::new(StripeState { config });
 
-    Router::new()
+    let mut router = Router::new()
         .route(
             "/stripe/create-checkout-session",
-            post(create_checkout_session_handler),
+            post(create_checkout_session_handler)
+                .layer((feature_guard(CHECKOUT), IdempotencyLayer::new())),
+        )
+        .route(
+            "/stripe/payment-intents",
+            post(create_payment_intent_handler)
+                .layer((feature_guard(CHECKOUT), IdempotencyLayer::new())),
+        )
+        .route(
+            "/stripe/payment-intents/{payment_intent_id}/confirm",
+            post(confirm_payment_intent_handler).layer(IdempotencyLayer::new()),
+        )
+        .route(
+            "/stripe/payment-intents/{payment_intent_id}/cancel",
+            post(cancel_payment_intent_handler),
+        )
+        .route(
+            "/stripe/setup-intents",
+            post(create_setup_intent_handler)
+                .layer((feature_guard(CHECKOUT), IdempotencyLayer::new())),
+        )
+        .route(
+            "/stripe/webhook",
+            post(stripe_webhook_handler).layer(webhook_rate_limit),
         )
-        .route("/stripe/webhook", post(stripe_webhook_handler))
         .route(
             "/stripe/checkout-success",
             get(stripe_checkout_success_handler),
@@ -34,14 +80,72 @@ pub fn routes(config: Arc<AppConfig>) -> Router {
         .route(
             "/stripe/order-confirmation-details",
             get(get_checkout_session_details_handler),
-        )
-        .route(
-            "/admin/stripe/order-details",
-            get(admin_get_checkout_session_details_handler),
-        )
-        .route(
-            "/admin/stripe/sessions",
-            get(admin_list_checkout_sessions_handler),
-        )
-        .with_state(stripe_state)
+        );
+
+    // Refunds, charges and replays move money and customers hold personal data, so they are
+    // only exposed to admin API keys
+    match admin_auth {
+        Some(admin_auth) => {
+            let admin_auth = admin_auth.with_scope(ADMIN_SCOPE);
+            router = router
+                .route(
+                    "/stripe/refunds",
+                    post(create_refund_handler)
+                        .layer((admin_auth.clone(), IdempotencyLayer::new())),
+                )
+                .route(
+                    "/admin/stripe/sessions",
+                    get(admin_list_checkout_sessions_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/stripe/order-details",
+                    get(admin_get_checkout_session_details_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/stripe/events",
+                    get(admin_list_webhook_events_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/stripe/events/{event_id}/replay",
+                    post(admin_replay_webhook_event_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/stripe/dead-letters",
+                    get(admin_list_dead_letters_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/stripe/dead-letters/{payment_id}/requeue",
+                    post(admin_requeue_dead_letter_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/stripe/customers",
+                    get(admin_get_customer_handler)
+                        .post(admin_find_or_create_customer_handler)
+                        .layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/stripe/customers/{customer_id}/payment-methods",
+                    get(admin_list_payment_methods_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/stripe/billing-portal-sessions",
+                    post(admin_create_billing_portal_session_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/stripe/payment-intents",
+                    post(admin_charge_saved_payment_method_handler)
+                        .layer((admin_auth, IdempotencyLayer::new())),
+                );
+        }
+        None => {
+            warn!(
+                "No API keys configured, /stripe/refunds, /admin/stripe/sessions, \
+                 /admin/stripe/order-details, /admin/stripe/events, /admin/stripe/dead-letters, \
+                 /admin/stripe/customers, /admin/stripe/billing-portal-sessions and \
+                 /admin/stripe/payment-intents are disabled"
+            )
+        }
+    }
+
+    router.with_state(stripe_state)
 }
@@ -1,5 +1,7 @@
 use crate::error::StripeError;
-use crate::logic::{create_checkout_session, CreateCheckoutSessionRequest};
+use crate::logic::{
+    cancel_payment_intent, confirm_payment_intent, create_payment_intent, create_refund,
+};
 use connectify_common::services::{PaymentIntentResult, PaymentService, RefundResult};
 use connectify_config::AppConfig;
 use serde_json::Value;
@@ -17,6 +19,15 @@ impl StripePaymentService {
     pub fn new(config: Arc<AppConfig>) -> Self {
         Self { config }
     }
+
+    /// Fails if Stripe is disabled or not configured.
+    fn ensure_configured(&self) -> Result<(), StripeError> {
+        if self.config.use_stripe && self.config.stripe.is_some() {
+            Ok(())
+        } else {
+            Err(StripeError::ConfigError)
+        }
+    }
 }
 
 impl PaymentService for StripePaymentService {
@@ -29,40 +40,12 @@ impl PaymentService for StripePaymentService {
         description: Option<&str>,
         metadata: Option<Value>,
     ) -> Pin<Box<dyn Future<Output = Result<PaymentIntentResult, Self::Error>> + Send + '_>> {
-        // Clone the values to avoid lifetime issues
         let currency = currency.to_string();
         let description = description.map(|s| s.to_string());
-        let metadata = metadata.clone();
-        let _config = self.config.clone();
-
         Box::pin(async move {
-            // Create a checkout session request
-            let request = CreateCheckoutSessionRequest {
-                product_name_override: description.map(|s| s.to_string()),
-                amount_override: Some(amount),
-                currency_override: Some(currency.to_string()),
-                fulfillment_type: "payment".to_string(),
-                fulfillment_data: metadata.unwrap_or(Value::Null),
-                client_reference_id: None,
-            };
-
-            // Use the existing create_checkout_session function
-            let stripe_config = self
-                .config
-                .stripe
-                .as_ref()
-                .ok_or_else(|| StripeError::ConfigError)?;
-
-            let checkout_result = create_checkout_session(stripe_config, request).await?;
-
-            // Convert to PaymentIntentResult
-            Ok(PaymentIntentResult {
-                id: checkout_result.session_id,
-                status: "created".to_string(),
-                amount,
-                currency: currency.to_string(),
-                client_secret: None,
-            })
+            self.ensure_configured()?;
+            create_payment_intent(amount, &currency, description.as_deref(), metadata.as_ref())
+                .await
         })
     }
 
@@ -72,15 +55,8 @@ impl PaymentService for StripePaymentService {
     ) -> Pin<Box<dyn Future<Output = Result<PaymentIntentResult, Self::Error>> + Send + '_>> {
         let payment_intent_id = payment_intent_id.to_string();
         Box::pin(async move {
-            // This would need to be implemented with Stripe API
-            // For now, return a placeholder
-            Err(StripeError::ApiError {
-                status_code: 501,
-                message: format!(
-                    "Not implemented: confirm_payment_intent for {}",
-                    payment_intent_id
-                ),
-            })
+            self.ensure_configured()?;
+            confirm_payment_intent(&payment_intent_id).await
         })
     }
 
@@ -90,15 +66,8 @@ impl PaymentService for StripePaymentService {
     ) -> Pin<Box<dyn Future<Output = Result<PaymentIntentResult, Self::Error>> + Send + '_>> {
         let payment_intent_id = payment_intent_id.to_string();
         Box::pin(async move {
-            // This would need to be implemented with Stripe API
-            // For now, return a placeholder
-            Err(StripeError::ApiError {
-                status_code: 501,
-                message: format!(
-                    "Not implemented: cancel_payment_intent for {}",
-                    payment_intent_id
-                ),
-            })
+            self.ensure_configured()?;
+            cancel_payment_intent(&payment_intent_id).await
         })
     }
 
@@ -110,16 +79,6 @@ impl PaymentService for StripePaymentService {
     ) -> Pin<Box<dyn Future<Output = Result<RefundResult, Self::Error>> + Send + '_>> {
         let payment_intent_id = payment_intent_id.to_string();
         let reason = reason.map(|s| s.to_string());
-        Box::pin(async move {
-            // This would need to be implemented with Stripe API
-            // For now, return a placeholder
-            Err(StripeError::ApiError {
-                status_code: 501,
-                message: format!(
-                    "Not implemented: create_refund for {} with amount {:?} and reason {:?}",
-                    payment_intent_id, amount, reason
-                ),
-            })
-        })
+        Box::pin(async move { create_refund(&payment_intent_id, amount, reason.as_deref()).await })
     }
 }
@@ -0,0 +1,198 @@
+//! Tests against stripe-mock (https://github.com/stripe/stripe-mock), which answers the Stripe
+//! API with fixture objects, so requests and responses are checked without live keys.
+//!
+//! Start it with `docker run --rm -p 12111:12111 stripe/stripe-mock` and point
+//! `STRIPE_MOCK_URL` at it, e.g. `STRIPE_MOCK_URL=http://localhost:12111/v1 cargo test`. Tests
+//! needing stripe-mock are skipped when `STRIPE_MOCK_URL` is not set.
+
+use axum::http::HeaderMap;
+use connectify_common::models::CursorQuery;
+use connectify_common::webhook::{hmac_sha256_hex, WebhookVerifier};
+use connectify_config::{AppConfig, PricingConfig, StripeConfig};
+use connectify_stripe::client::configure_api_base_url;
+use connectify_stripe::logic::{
+    create_billing_portal_session, create_checkout_session, list_checkout_sessions_admin,
+    process_stripe_webhook, refund_payment, refund_target, CheckoutMode,
+    CreateBillingPortalSessionRequest, CreateCheckoutSessionRequest, CreateRefundRequest,
+    ListSessionsAdminQuery, RefundReason, StripeEvent, StripeWebhookVerifier,
+};
+use serde_json::json;
+use std::sync::Arc;
+
+/// Any key works with stripe-mock, as long as it looks like a test key.
+const SECRET_KEY: &str = "sk_test_123";
+
+const WEBHOOK_SECRET: &str = "whsec_test_secret";
+
+/// A Stripe config sending requests to stripe-mock, or `None` to skip the test.
+fn stripe_mock_config() -> Option<StripeConfig> {
+    let Some(base_url) = std::env::var("STRIPE_MOCK_URL")
+        .ok()
+        .filter(|url| !url.is_empty())
+    else {
+        eprintln!("STRIPE_MOCK_URL not set, skipping stripe-mock test");
+        return None;
+    };
+    std::env::set_var("STRIPE_SECRET_KEY", SECRET_KEY);
+    let config: StripeConfig = serde_json::from_value(json!({
+        "success_url": "https://example.com/success",
+        "cancel_url": "https://example.com/cancel",
+        "payment_success_url": "https://example.com/paid",
+        "api_base_url": base_url,
+        "success_token_secret": "success_token_test_secret"
+    }))
+    .unwrap();
+    configure_api_base_url(config.api_base_url.as_deref());
+    Some(config)
+}
+
+fn pricing_config() -> PricingConfig {
+    serde_json::from_value(json!({
+        "default_currency": "CHF",
+        "price_tiers": [
+            {
+                "duration_minutes": 60,
+                "unit_amount": 12000,
+                "product_name": "Consultation 60 min"
+            }
+        ]
+    }))
+    .unwrap()
+}
+
+/// A `Stripe-Signature` header signing the payload the way Stripe does.
+fn signed_headers(payload: &[u8], timestamp: i64) -> HeaderMap {
+    let mut signed_payload = format!("{}.", timestamp).into_bytes();
+    signed_payload.extend_from_slice(payload);
+    let signature = hmac_sha256_hex(WEBHOOK_SECRET.as_bytes(), &signed_payload);
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "Stripe-Signature",
+        format!("t={},v1={}", timestamp, signature).parse().unwrap(),
+    );
+    headers
+}
+
+#[tokio::test]
+async fn test_create_checkout_session() {
+    let Some(stripe_config) = stripe_mock_config() else {
+        return;
+    };
+    let request = CreateCheckoutSessionRequest {
+        product_name_override: None,
+        amount_override: None,
+        currency_override: None,
+        fulfillment_type: "gcal_booking".to_string(),
+        fulfillment_data: json!({
+            "start_time": "2025-07-15T10:00:00Z",
+            "end_time": "2025-07-15T11:00:00Z",
+            "summary": "Consultation"
+        }),
+        client_reference_id: Some("order-1".to_string()),
+        mode: CheckoutMode::Payment,
+        price_id: None,
+        customer_email: None,
+        customer_name: None,
+    };
+
+    let session = create_checkout_session(&stripe_config, &pricing_config(), request)
+        .await
+        .unwrap();
+    assert!(session.session_id.starts_with("cs_"));
+    assert!(session.url.starts_with("https://"));
+}
+
+#[tokio::test]
+async fn test_refund_payment() {
+    if stripe_mock_config().is_none() {
+        return;
+    }
+    let request = CreateRefundRequest {
+        payment_intent_id: Some("pi_123".to_string()),
+        session_id: None,
+        amount: Some(2500),
+        reason: Some(RefundReason::BookingCancelled),
+        booking_id: Some("abc123xyz".to_string()),
+        customer_phone: None,
+        customer_user_id: None,
+    };
+
+    let target = refund_target(&request).await.unwrap();
+    assert_eq!(target.payment_intent_id, "pi_123");
+    let refund = refund_payment(&target, &request).await.unwrap();
+    assert!(refund.id.starts_with("re_"));
+}
+
+#[tokio::test]
+async fn test_list_checkout_sessions_admin() {
+    if stripe_mock_config().is_none() {
+        return;
+    }
+    let filters: ListSessionsAdminQuery = serde_json::from_value(json!({})).unwrap();
+    let page = CursorQuery {
+        limit: Some(1),
+        ..Default::default()
+    };
+
+    let sessions = list_checkout_sessions_admin(filters, page).await.unwrap();
+    assert!(sessions.items.len() <= 1);
+    assert_eq!(sessions.has_more, sessions.next_cursor.is_some());
+}
+
+#[tokio::test]
+async fn test_create_billing_portal_session() {
+    let Some(stripe_config) = stripe_mock_config() else {
+        return;
+    };
+    let request = CreateBillingPortalSessionRequest {
+        customer_email: "anna@example.com".to_string(),
+        return_url: Some("https://example.com/account".to_string()),
+    };
+
+    let session = create_billing_portal_session(&stripe_config, &request)
+        .await
+        .unwrap();
+    assert!(session.session_id.starts_with("bps_"));
+    assert!(session.customer_id.starts_with("cus_"));
+    assert!(session.url.starts_with("https://"));
+}
+
+#[tokio::test]
+async fn test_verify_and_process_webhook() {
+    let payload = json!({
+        "id": "evt_123",
+        "object": "event",
+        "api_version": "2024-06-20",
+        "created": 1_700_000_000,
+        "livemode": false,
+        "type": "payment_intent.payment_failed",
+        "data": { "object": { "id": "pi_123", "object": "payment_intent" } },
+        "request": null
+    })
+    .to_string();
+    let verifier = StripeWebhookVerifier::new(WEBHOOK_SECRET);
+    let now = chrono::Utc::now().timestamp();
+
+    let verified = verifier
+        .verify(payload.as_bytes(), &signed_headers(payload.as_bytes(), now))
+        .unwrap();
+    let event: StripeEvent = verified.json().unwrap();
+    assert_eq!(event.id, "evt_123");
+    let app_config: AppConfig =
+        serde_json::from_value(json!({ "server": { "host": "127.0.0.1", "port": 8080 } })).unwrap();
+    process_stripe_webhook(event, Arc::new(app_config))
+        .await
+        .unwrap();
+
+    // A payload changed after signing is rejected
+    let tampered = payload.replace("pi_123", "pi_456");
+    assert!(verifier
+        .verify(
+            tampered.as_bytes(),
+            &signed_headers(payload.as_bytes(), now)
+        )
+        .is_err());
+    let mut forged = signed_headers(payload.as_bytes(), now);
+    forged.insert("Stripe-Signature", "t=1,v1=00".parse().unwrap());
+    assert!(verifier.verify(payload.as_bytes(), &forged).is_err());
+}
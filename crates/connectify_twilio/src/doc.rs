@@ -8,7 +8,20 @@
 use utoipa::OpenApi;
 // Import the request query and response structs from the twilio_token module
 // Ensure these structs derive utoipa::ToSchema (needs to be added there)
+use crate::twilio_conversations::{
+    AddParticipantRequest, Conversation, ConversationMessage, CreateConversationRequest,
+    ParticipantResponse, PostMessageRequest,
+};
+use crate::twilio_recordings::{
+    ListRoomsQuery, MediaLink, RoomComposition, RoomMedia, RoomRecording, VideoRoom,
+};
+use crate::twilio_sms::{ListMessagesQuery, MessageStatusCallback, MessageStatusResponse};
+use crate::twilio_sms_inbound::{
+    InboundSmsResponse, InboundSmsWebhook, ListRepliesQuery, SmsConsentQuery, SmsConsentResponse,
+};
 use crate::twilio_token::{TokenRequestQuery, TokenResponse};
+use crate::twilio_usage::{CategoryUsage, FeatureUsage, UsageQuery, UsageReport};
+use crate::twilio_voice::{CallStatusCallback, VoiceCallRequest, VoiceCallResponse};
 
 // Define a dummy function with the utoipa::path macro to document the endpoint
 #[utoipa::path(
@@ -18,13 +31,17 @@ use crate::twilio_token::{TokenRequestQuery, TokenResponse};
     // Note: TokenRequestQuery needs #[derive(utoipa::IntoParams)] for this to work directly,
     // or list params manually as below.
     params(
-        ("identity" = String, Query, description = "User identity for the token", example = "User_12345"),
-        ("roomName" = String, Query, description = "Room name for the token", example = "MyCoolRoom")
+        ("identity" = Option<String>, Query, description = "Identity of an anonymous caller; signed-in callers get their user id", example = "User_12345"),
+        ("roomName" = Option<String>, Query, description = "Room of the video grant", example = "MyCoolRoom"),
+        ("grants" = Option<String>, Query, description = "Comma-separated grants: video, chat and voice (default: video)", example = "video,chat"),
+        ("chatServiceSid" = Option<String>, Query, description = "Conversations service of the chat grant, must be the configured one"),
+        ("ttl" = Option<u32>, Query, description = "Seconds the token is valid, up to token_max_ttl_seconds", example = 3600)
     ),
     responses(
         (status = 200, description = "Successfully generated Twilio Access Token", body = TokenResponse),
-        (status = 500, description = "Token generation failed due to server error", body = String, example = json!("Failed to generate token")),
-        (status = 503, description = "Twilio service disabled by configuration", body = String, example = json!("Twilio service is disabled by configuration."))
+        (status = 400, description = "Invalid grants or TTL, e.g. a grant that isn't configured"),
+        (status = 401, description = "Not signed in though required, or the identity is not the caller's"),
+        (status = 500, description = "Twilio not configured or disabled")
     ),
     tag = "Twilio" // Group this endpoint under the "Twilio" tag in Swagger UI
 )]
@@ -32,19 +49,273 @@ fn doc_generate_token() {
     // This function body is never executed, it's just an anchor for the macro.
 }
 
+#[utoipa::path(
+    post,
+    path = "/twilio/calls",
+    request_body(content = VoiceCallRequest, example = json!({
+        "to": "+41791234567",
+        "message": "Erinnerung: Ihre Beratung beginnt morgen um 10 Uhr.",
+        "language": "de-DE"
+    })),
+    responses(
+        (status = 200, description = "Call queued", body = VoiceCallResponse),
+        (status = 400, description = "Invalid number, or neither or both of message and twiml"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 502, description = "Twilio API error"),
+        (status = 503, description = "Outgoing calls are disabled")
+    ),
+    tag = "Twilio"
+)]
+fn doc_place_call() {}
+
+#[utoipa::path(
+    post,
+    path = "/twilio/calls/status",
+    request_body(content = CallStatusCallback, description = "Sent by Twilio while a call progresses", content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 204, description = "Status received"),
+        (status = 401, description = "Missing or invalid X-Twilio-Signature")
+    ),
+    tag = "Twilio"
+)]
+fn doc_call_status_callback() {}
+
+#[utoipa::path(
+    post,
+    path = "/twilio/conversations",
+    request_body(content = CreateConversationRequest, example = json!({
+        "booking_id": "evt_0123456789",
+        "friendly_name": "Beratung am 1. Juli"
+    })),
+    responses(
+        (status = 200, description = "The booking's conversation, created unless it existed", body = Conversation),
+        (status = 400, description = "Missing booking_id"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 502, description = "Twilio API error")
+    ),
+    tag = "Twilio"
+)]
+fn doc_create_conversation() {}
+
+#[utoipa::path(
+    post,
+    path = "/twilio/conversations/{conversation_sid}/participants",
+    params(("conversation_sid" = String, Path, description = "The Twilio conversation SID", example = "CH0123456789abcdef0123456789abcdef")),
+    request_body(content = AddParticipantRequest, example = json!({ "phone": "+41791234567" })),
+    responses(
+        (status = 200, description = "Participant added", body = ParticipantResponse),
+        (status = 400, description = "Neither or both of phone and identity, or an invalid number"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Conversation not found"),
+        (status = 409, description = "Already a participant"),
+        (status = 502, description = "Twilio API error")
+    ),
+    tag = "Twilio"
+)]
+fn doc_add_participant() {}
+
+#[utoipa::path(
+    post,
+    path = "/twilio/conversations/{conversation_sid}/messages",
+    params(("conversation_sid" = String, Path, description = "The Twilio conversation SID", example = "CH0123456789abcdef0123456789abcdef")),
+    request_body = PostMessageRequest,
+    responses(
+        (status = 200, description = "Message posted to all participants", body = ConversationMessage),
+        (status = 400, description = "Empty body"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Conversation not found"),
+        (status = 502, description = "Twilio API error")
+    ),
+    tag = "Twilio"
+)]
+fn doc_post_message() {}
+
+#[utoipa::path(
+    get,
+    path = "/admin/twilio/rooms",
+    params(ListRoomsQuery),
+    responses(
+        (status = 200, description = "Completed rooms", body = Vec<VideoRoom>),
+        (status = 400, description = "Invalid filter, e.g. a malformed date"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 502, description = "Twilio API error")
+    ),
+    tag = "Twilio"
+)]
+fn doc_list_rooms() {}
+
+#[utoipa::path(
+    get,
+    path = "/admin/twilio/rooms/{room_sid}/recordings",
+    params(("room_sid" = String, Path, description = "The Twilio room SID", example = "RM0123456789abcdef0123456789abcdef")),
+    responses(
+        (status = 200, description = "Recordings and compositions of the room", body = RoomMedia),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Room not found"),
+        (status = 502, description = "Twilio API error")
+    ),
+    tag = "Twilio"
+)]
+fn doc_room_recordings() {}
+
+#[utoipa::path(
+    get,
+    path = "/admin/twilio/recordings/{sid}/media",
+    params(("sid" = String, Path, description = "A recording (RT…) or composition (CJ…) SID", example = "CJ0123456789abcdef0123456789abcdef")),
+    responses(
+        (status = 200, description = "Link to the media, valid for 10 minutes", body = MediaLink),
+        (status = 400, description = "Neither a recording nor a composition SID"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Recording not found"),
+        (status = 502, description = "Twilio API error")
+    ),
+    tag = "Twilio"
+)]
+fn doc_media_link() {}
+
+#[utoipa::path(
+    post,
+    path = "/twilio/messages/status",
+    request_body(content = MessageStatusCallback, description = "Sent by Twilio while a message is delivered", content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 204, description = "Status recorded"),
+        (status = 401, description = "Missing or invalid X-Twilio-Signature"),
+        (status = 500, description = "The status could not be stored")
+    ),
+    tag = "Twilio"
+)]
+fn doc_message_status_callback() {}
+
+#[utoipa::path(
+    get,
+    path = "/admin/twilio/messages",
+    params(ListMessagesQuery),
+    responses(
+        (status = 200, description = "Delivery statuses, newest first", body = Vec<MessageStatusResponse>),
+        (status = 401, description = "Missing or invalid admin API key")
+    ),
+    tag = "Twilio"
+)]
+fn doc_list_message_statuses() {}
+
+#[utoipa::path(
+    get,
+    path = "/admin/twilio/messages/{message_sid}",
+    params(("message_sid" = String, Path, description = "The Twilio message SID", example = "SM0123456789abcdef0123456789abcdef")),
+    responses(
+        (status = 200, description = "Delivery status of the message", body = MessageStatusResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "No status recorded for the message")
+    ),
+    tag = "Twilio"
+)]
+fn doc_message_status() {}
+
+#[utoipa::path(
+    post,
+    path = "/twilio/messages/inbound",
+    request_body(content = InboundSmsWebhook, description = "Sent by Twilio when an SMS is received", content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "TwiML, replying to HELP", content_type = "text/xml"),
+        (status = 401, description = "Missing or invalid X-Twilio-Signature"),
+        (status = 500, description = "The SMS could not be stored")
+    ),
+    tag = "Twilio"
+)]
+fn doc_inbound_sms() {}
+
+#[utoipa::path(
+    get,
+    path = "/admin/twilio/replies",
+    params(ListRepliesQuery),
+    responses(
+        (status = 200, description = "Received SMS, newest first", body = Vec<InboundSmsResponse>),
+        (status = 401, description = "Missing or invalid admin API key")
+    ),
+    tag = "Twilio"
+)]
+fn doc_list_replies() {}
+
+#[utoipa::path(
+    get,
+    path = "/admin/twilio/consent",
+    params(SmsConsentQuery),
+    responses(
+        (status = 200, description = "Consent of the number", body = SmsConsentResponse),
+        (status = 401, description = "Missing or invalid admin API key")
+    ),
+    tag = "Twilio"
+)]
+fn doc_sms_consent() {}
+
+#[utoipa::path(
+    get,
+    path = "/admin/twilio/usage",
+    params(UsageQuery),
+    responses(
+        (status = 200, description = "Usage and cost per feature", body = UsageReport),
+        (status = 400, description = "Invalid date range"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 502, description = "Twilio API error")
+    ),
+    tag = "Twilio"
+)]
+fn doc_usage_report() {}
+
 // Define the main OpenAPI documentation structure for this crate/feature
 #[derive(OpenApi)]
 #[openapi(
     paths(
-        doc_generate_token // List the path-documenting functions
+        doc_generate_token, // List the path-documenting functions
+        doc_place_call,
+        doc_call_status_callback,
+        doc_create_conversation,
+        doc_add_participant,
+        doc_post_message,
+        doc_list_rooms,
+        doc_room_recordings,
+        doc_media_link,
+        doc_message_status_callback,
+        doc_list_message_statuses,
+        doc_message_status,
+        doc_inbound_sms,
+        doc_list_replies,
+        doc_sms_consent,
+        doc_usage_report
     ),
     components(
         // List all schemas used in the paths (request/response bodies, parameters)
-        schemas(TokenRequestQuery, TokenResponse)
+        schemas(
+            TokenRequestQuery,
+            TokenResponse,
+            VoiceCallRequest,
+            VoiceCallResponse,
+            CallStatusCallback,
+            CreateConversationRequest,
+            Conversation,
+            AddParticipantRequest,
+            ParticipantResponse,
+            PostMessageRequest,
+            ConversationMessage,
+            ListRoomsQuery,
+            VideoRoom,
+            RoomMedia,
+            RoomRecording,
+            RoomComposition,
+            MediaLink,
+            MessageStatusCallback,
+            MessageStatusResponse,
+            InboundSmsWebhook,
+            InboundSmsResponse,
+            SmsConsentResponse,
+            UsageReport,
+            FeatureUsage,
+            CategoryUsage
+        )
     ),
     tags(
         // Define the tag used above for grouping endpoints
-        (name = "Twilio", description = "Twilio Token Generation API")
+        (name = "Twilio", description = "Twilio Token Generation, Voice Call, Conversation, Recording, SMS and Usage API")
     )
     // No servers needed here, defined in the main backend doc
 )]
@@ -2,11 +2,42 @@ pub mod doc;
 pub mod routes;
 /// This module provides the Twilio notification service implementation.
 pub mod service;
+mod service_test;
+#[cfg(test)]
+mod test_support;
+/// This module creates conversations per booking, bridging SMS and chat.
+pub mod twilio_conversations;
+mod twilio_conversations_test;
+/// This module sends emails through SendGrid.
+pub mod twilio_email;
+mod twilio_email_test;
+/// This module retrieves and expires video room recordings.
+pub mod twilio_recordings;
+mod twilio_recordings_test;
 pub mod twilio_sms;
+/// This module applies the compliance rules of the recipient's country to SMS.
+pub mod twilio_sms_country;
+mod twilio_sms_country_test;
+/// This module receives SMS and handles opt-out keywords.
+pub mod twilio_sms_inbound;
+mod twilio_sms_inbound_test;
+/// This module sends queued SMS at the sender's throughput.
+pub mod twilio_sms_queue;
+mod twilio_sms_queue_test;
+/// This module renders SMS bodies from templates.
+pub mod twilio_sms_templates;
+mod twilio_sms_templates_test;
 mod twilio_sms_test;
 /// This module provides functionality related to Twilio tokens.
 pub mod twilio_token;
-// mod twilio_token_test;
-
-// mod twilio_token_test;
+mod twilio_token_test;
+/// This module reports Twilio usage and cost per feature.
+pub mod twilio_usage;
+mod twilio_usage_test;
+/// This module places outbound voice calls.
+pub mod twilio_voice;
+mod twilio_voice_test;
+/// This module validates the signatures of inbound Twilio webhooks.
+pub mod twilio_webhook;
+mod twilio_webhook_test;
 // use twilio_token::{generate_token, TokenResponse};
@@ -1,14 +1,121 @@
 // --- File: crates/connectify_twilio/src/routes.rs ---
-use axum::{routing::get, Router};
+use axum::{
+    middleware::from_fn_with_state,
+    routing::{get, post},
+    Router,
+};
+use connectify_common::api_key::ApiKeyAuthLayer;
+use connectify_common::idempotency::IdempotencyLayer;
+use connectify_common::runtime_flags::{feature_guard, CALLS};
 use std::sync::Arc;
+use tracing::warn;
 // Import the handler function from the sibling module
+use crate::twilio_conversations::{
+    add_participant_handler, create_conversation_handler, post_message_handler,
+};
+use crate::twilio_recordings::{list_rooms_handler, media_link_handler, room_recordings_handler};
+use crate::twilio_sms::{
+    list_message_statuses_handler, message_status_callback_handler, message_status_handler,
+};
+use crate::twilio_sms_inbound::{inbound_sms_handler, list_replies_handler, sms_consent_handler};
 use crate::twilio_token::generate_token;
+use crate::twilio_usage::usage_report_handler;
+use crate::twilio_voice::{call_status_callback_handler, place_call_handler};
+use crate::twilio_webhook::validate_twilio_signature;
 use connectify_config::AppConfig;
 
+/// Scope an API key needs to place calls, manage conversations and access recordings and
+/// message statuses.
+pub const ADMIN_SCOPE: &str = "admin";
+
 /// Creates a router containing all routes for the Twilio feature.
 pub fn routes(config: Arc<AppConfig>) -> Router {
-    Router::new()
+    let admin_auth = ApiKeyAuthLayer::from_config(&config);
+    // Webhooks are only accepted with a valid X-Twilio-Signature
+    let twilio_signature = from_fn_with_state(config.clone(), validate_twilio_signature);
+
+    let mut router = Router::new()
         .route("/generate-token", get(generate_token))
-        .with_state(config)
-    // Add any other Twilio-specific routes here later
+        // Called by Twilio while a call progresses
+        .route(
+            "/twilio/calls/status",
+            post(call_status_callback_handler).layer(twilio_signature.clone()),
+        )
+        // Called by Twilio while a message is delivered
+        .route(
+            "/twilio/messages/status",
+            post(message_status_callback_handler).layer(twilio_signature.clone()),
+        )
+        // Called by Twilio when an SMS is received
+        .route(
+            "/twilio/messages/inbound",
+            post(inbound_sms_handler).layer(twilio_signature),
+        );
+
+    // Calls and conversations cost money and reach arbitrary numbers, and recordings, message
+    // statuses, replies and usage are private, so they are only exposed to admin API keys
+    match admin_auth {
+        Some(admin_auth) => {
+            let admin_auth = admin_auth.with_scope(ADMIN_SCOPE);
+            router = router
+                .route(
+                    "/twilio/calls",
+                    post(place_call_handler).layer((
+                        feature_guard(CALLS),
+                        admin_auth.clone(),
+                        IdempotencyLayer::new(),
+                    )),
+                )
+                .route(
+                    "/twilio/conversations",
+                    post(create_conversation_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/twilio/conversations/{conversation_sid}/participants",
+                    post(add_participant_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/twilio/conversations/{conversation_sid}/messages",
+                    post(post_message_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/twilio/rooms",
+                    get(list_rooms_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/twilio/rooms/{room_sid}/recordings",
+                    get(room_recordings_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/twilio/recordings/{sid}/media",
+                    get(media_link_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/twilio/messages",
+                    get(list_message_statuses_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/twilio/messages/{message_sid}",
+                    get(message_status_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/twilio/replies",
+                    get(list_replies_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/twilio/consent",
+                    get(sms_consent_handler).layer(admin_auth.clone()),
+                )
+                .route(
+                    "/admin/twilio/usage",
+                    get(usage_report_handler).layer(admin_auth),
+                );
+        }
+        None => warn!(
+            "No API keys configured, /twilio/calls, /twilio/conversations and /admin/twilio/* \
+             are disabled"
+        ),
+    }
+
+    router.with_state(config)
 }
@@ -1,10 +1,21 @@
+use connectify_common::http::circuit_breaker::HttpClientError;
+use connectify_common::metrics::observe_external_call;
+use connectify_common::retry::{send_non_idempotent_with_retry, send_with_retry, RetryPolicy};
+use connectify_common::runtime_flags::{runtime_flags, SMS};
 use connectify_common::services::{NotificationResult, NotificationService};
-use connectify_config::AppConfig;
+use connectify_common::{external_service_error, ConnectifyError};
+use connectify_config::{AppConfig, TwilioConfig};
+use reqwest::{Method, RequestBuilder, Response};
+use serde::Deserialize;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 use thiserror::Error;
 
+use crate::twilio_email::{deliver_email, sendgrid_mail};
+use crate::twilio_sms::SmsRequest;
+use crate::twilio_sms_queue::queue_sms;
+
 /// Twilio-specific error types.
 #[derive(Error, Debug)]
 pub enum TwilioError {
@@ -20,27 +31,298 @@ pub enum TwilioError {
     #[error("Twilio configuration missing or incomplete")]
     ConfigError,
 
+    /// The request is invalid, e.g. a call without message
+    #[error("Invalid request: {0}")]
+    ValidationError(String),
+
+    /// Twilio can't reach the recipient, e.g. an invalid or landline number
+    #[error("Invalid recipient: {message}")]
+    InvalidRecipient { code: u32, message: String },
+
+    /// The account may not send to the recipient, e.g. an unverified number of a trial account
+    #[error("Not permitted by Twilio: {message}")]
+    NotPermitted { code: u32, message: String },
+
+    /// The sender can't send the message, e.g. a number not owned by the account
+    #[error("Invalid sender: {message}")]
+    InvalidSender { code: u32, message: String },
+
+    /// Twilio rejected the account's credentials
+    #[error("Twilio authentication failed: {0}")]
+    AuthenticationFailed(String),
+
+    /// The recipient opted out of SMS
+    #[error("Recipient opted out of SMS: {0}")]
+    OptedOut(String),
+
+    /// The recipient's country allows no SMS at this time
+    #[error("Outside the SMS sending hours of {0}")]
+    OutsideSendingHours(String),
+
+    /// An SMS template is missing or can't be rendered
+    #[error("SMS template error: {0}")]
+    TemplateError(String),
+
+    /// A request that must not be sent twice failed after it may have reached Twilio, e.g. on a
+    /// timeout, so whether Twilio accepted it is unknown
+    #[error("Twilio may have accepted the request: {0}")]
+    Unconfirmed(String),
+
     /// Internal processing error
     #[error("Internal processing error: {0}")]
     InternalError(String),
 }
 
+/// Error body of the Twilio API.
+#[derive(Deserialize, Debug)]
+struct TwilioApiErrorBody {
+    code: Option<i64>,
+    message: String,
+}
+
+/// What a Twilio error code is about, see [`TWILIO_ERROR_CODES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TwilioErrorClass {
+    InvalidRecipient,
+    NotPermitted,
+    InvalidSender,
+    InvalidRequest,
+    Authentication,
+}
+
+/// Twilio error codes with their class and what to do about them, see
+/// <https://www.twilio.com/docs/api/errors>. Other codes are reported as [`TwilioError::ApiError`].
+pub const TWILIO_ERROR_CODES: [(u32, TwilioErrorClass, &str); 13] = [
+    (
+        20003,
+        TwilioErrorClass::Authentication,
+        "Check twilio.account_sid and twilio.auth_token",
+    ),
+    (
+        21211,
+        TwilioErrorClass::InvalidRecipient,
+        "The number is not valid, send it in E.164 format, e.g. +41791234567",
+    ),
+    (
+        21214,
+        TwilioErrorClass::InvalidRecipient,
+        "The number cannot be reached, check it with the recipient",
+    ),
+    (
+        21614,
+        TwilioErrorClass::InvalidRecipient,
+        "The number is not a mobile number and cannot receive SMS",
+    ),
+    (
+        21408,
+        TwilioErrorClass::NotPermitted,
+        "Enable the recipient's country in the geo permissions of the Twilio console",
+    ),
+    (
+        21608,
+        TwilioErrorClass::NotPermitted,
+        "Trial accounts only send to verified numbers, verify the number or upgrade the account",
+    ),
+    (
+        21610,
+        TwilioErrorClass::NotPermitted,
+        "The recipient replied STOP and must reply START to receive SMS again",
+    ),
+    (
+        21212,
+        TwilioErrorClass::InvalidSender,
+        "The sender is not a valid number or sender ID, check the configured sender",
+    ),
+    (
+        21606,
+        TwilioErrorClass::InvalidSender,
+        "The sender cannot send SMS, use an SMS-capable number of the account",
+    ),
+    (
+        21659,
+        TwilioErrorClass::InvalidSender,
+        "The sender is not a number of the account, check the configured sender",
+    ),
+    (
+        21703,
+        TwilioErrorClass::InvalidSender,
+        "The Messaging Service has no sender, add one to its sender pool",
+    ),
+    (
+        21602,
+        TwilioErrorClass::InvalidRequest,
+        "The message has no body",
+    ),
+    (
+        21617,
+        TwilioErrorClass::InvalidRequest,
+        "The body exceeds 1600 characters, shorten it or split it into several messages",
+    ),
+];
+
+/// The class and hint of a Twilio error code, if it is one of [`TWILIO_ERROR_CODES`].
+pub fn classify_error_code(code: u32) -> Option<(TwilioErrorClass, &'static str)> {
+    TWILIO_ERROR_CODES
+        .iter()
+        .find(|(known, _, _)| *known == code)
+        .map(|(_, class, hint)| (*class, *hint))
+}
+
+impl TwilioError {
+    /// The error of a failed Twilio API response: a typed error with a hint for a known Twilio
+    /// error code, otherwise an API error with Twilio's error code if the body has one.
+    pub(crate) fn from_response(status: reqwest::StatusCode, body: String) -> Self {
+        let Ok(error) = serde_json::from_str::<TwilioApiErrorBody>(&body) else {
+            return TwilioError::ApiError {
+                status_code: status.as_u16(),
+                message: body,
+            };
+        };
+        let Some(code) = error.code else {
+            return TwilioError::ApiError {
+                status_code: status.as_u16(),
+                message: error.message,
+            };
+        };
+        let classified = u32::try_from(code)
+            .ok()
+            .and_then(|code| classify_error_code(code).map(|(class, hint)| (code, class, hint)));
+        let Some((code, class, hint)) = classified else {
+            return TwilioError::ApiError {
+                status_code: status.as_u16(),
+                message: format!("{} (code {})", error.message, code),
+            };
+        };
+        let message = format!("{} (code {}). {}", error.message, code, hint);
+        match class {
+            TwilioErrorClass::InvalidRecipient => TwilioError::InvalidRecipient { code, message },
+            TwilioErrorClass::NotPermitted => TwilioError::NotPermitted { code, message },
+            TwilioErrorClass::InvalidSender => TwilioError::InvalidSender { code, message },
+            TwilioErrorClass::InvalidRequest => TwilioError::ValidationError(message),
+            TwilioErrorClass::Authentication => TwilioError::AuthenticationFailed(message),
+        }
+    }
+}
+
+/// The Twilio config, or a config error if Twilio is not configured or disabled.
+pub(crate) fn twilio_config(config: &AppConfig) -> Result<&TwilioConfig, ConnectifyError> {
+    config
+        .twilio
+        .as_ref()
+        .filter(|_| config.use_twilio)
+        .ok_or_else(|| {
+            ConnectifyError::ConfigError("Twilio service not configured or disabled".to_string())
+        })
+}
+
+/// Whether a request may be sent twice without effect, i.e. is a GET, HEAD, PUT or DELETE.
+pub(crate) fn is_idempotent(request: &RequestBuilder) -> bool {
+    request
+        .try_clone()
+        .and_then(|request| request.build().ok())
+        .is_some_and(|request| {
+            matches!(
+                *request.method(),
+                Method::GET | Method::HEAD | Method::PUT | Method::DELETE
+            )
+        })
+}
+
+/// Sends a Twilio API request through the circuit breaker with the default [`RetryPolicy`].
+///
+/// Idempotent requests are retried on transport errors, 429s and 5xx responses. Other requests,
+/// e.g. POSTs sending an SMS or placing a call, are only retried on connect errors and 429s, as
+/// Twilio may have accepted them before a timeout or 5xx. Their other transport errors are
+/// returned as [`TwilioError::Unconfirmed`], so callers don't send them again.
+///
+/// # Arguments
+///
+/// * `request` - The authorized request
+/// * `action` - What the request does, for the error message and the `operation` label of the
+///   external call metrics, e.g. `sending SMS`
+pub(crate) async fn send_twilio_request(
+    request: RequestBuilder,
+    action: &str,
+) -> Result<Response, TwilioError> {
+    let policy = RetryPolicy::default();
+    let idempotent = is_idempotent(&request);
+    let result = if idempotent {
+        observe_external_call("twilio", action, send_with_retry(&policy, request)).await
+    } else {
+        observe_external_call(
+            "twilio",
+            action,
+            send_non_idempotent_with_retry(&policy, request),
+        )
+        .await
+    };
+    result.map_err(|e| match e {
+        // Requests failing before they were sent surely didn't reach Twilio
+        HttpClientError::Request(e) if !idempotent && !e.is_connect() && !e.is_builder() => {
+            TwilioError::Unconfirmed(format!("HTTP error {}: {}", action, e))
+        }
+        e => TwilioError::InternalError(format!("HTTP error {}: {}", action, e)),
+    })
+}
+
+/// Convert TwilioError to ConnectifyError
+impl From<TwilioError> for ConnectifyError {
+    fn from(err: TwilioError) -> Self {
+        match err {
+            TwilioError::RequestError(e) => {
+                ConnectifyError::HttpError(format!("Twilio request error: {}", e))
+            }
+            TwilioError::ApiError {
+                status_code: 404,
+                message,
+            } => ConnectifyError::NotFoundError(format!("Twilio resource not found: {}", message)),
+            TwilioError::ApiError {
+                status_code: 409,
+                message,
+            } => ConnectifyError::ConflictError(format!("Twilio resource exists: {}", message)),
+            TwilioError::ApiError {
+                status_code,
+                message,
+            } => external_service_error(
+                "Twilio API",
+                format!("Status: {}, Message: {}", status_code, message),
+            ),
+            TwilioError::ConfigError => {
+                ConnectifyError::ConfigError("Twilio configuration missing or incomplete".into())
+            }
+            TwilioError::ValidationError(msg) => ConnectifyError::ValidationError(msg),
+            TwilioError::InvalidRecipient { message, .. }
+            | TwilioError::NotPermitted { message, .. } => {
+                ConnectifyError::ValidationError(message)
+            }
+            TwilioError::InvalidSender { message, .. }
+            | TwilioError::AuthenticationFailed(message) => {
+                ConnectifyError::ConfigError(format!("Twilio configuration error: {}", message))
+            }
+            TwilioError::OptedOut(to) => {
+                ConnectifyError::ValidationError(format!("{} opted out of SMS", to))
+            }
+            TwilioError::OutsideSendingHours(to) => ConnectifyError::ConflictError(format!(
+                "SMS to {} are not allowed at this time of day",
+                to
+            )),
+            TwilioError::TemplateError(msg) => {
+                ConnectifyError::ConfigError(format!("SMS template error: {}", msg))
+            }
+            TwilioError::Unconfirmed(msg) => external_service_error("Twilio API", msg),
+            TwilioError::InternalError(msg) => {
+                ConnectifyError::InternalError(format!("Twilio internal error: {}", msg))
+            }
+        }
+    }
+}
+
 /// Twilio notification service implementation
 pub struct TwilioNotificationService {
     /// Configuration for the Twilio service.
     ///
-    /// This field stores the application configuration that contains Twilio API credentials
-    /// and settings. While it's not used in the current placeholder implementation (which
-    /// returns "Not implemented" errors), it's kept for several reasons:
-    ///
-    /// 1. It will be needed when implementing the actual Twilio API integration
-    /// 2. It follows the pattern used by other service implementations
-    /// 3. It maintains consistency with the dependency injection architecture
-    /// 4. It makes the service's dependencies explicit
-    ///
-    /// When the actual implementation is completed, this configuration will be used to
-    /// authenticate with the Twilio API and configure the service behavior.
-    #[allow(dead_code)]
+    /// This field stores the application configuration that contains the Twilio and SendGrid
+    /// credentials and settings emails are sent with.
     config: Arc<AppConfig>,
 }
 
@@ -59,22 +341,26 @@ impl NotificationService for TwilioNotificationService {
         to: &str,
         subject: &str,
         body: &str,
-        _is_html: bool,
+        is_html: bool,
     ) -> Pin<Box<dyn Future<Output = Result<NotificationResult, Self::Error>> + Send + '_>> {
-        // Clone the values to avoid lifetime issues
-        let to = to.to_string();
-        let subject = subject.to_string();
-        let _body = body.to_string();
+        // Built before the future to avoid lifetime issues
+        let mail = self
+            .config
+            .twilio
+            .as_ref()
+            .ok_or(TwilioError::ConfigError)
+            .and_then(|twilio_config| sendgrid_mail(twilio_config, to, subject, body, is_html));
 
         Box::pin(async move {
-            // This would need to be implemented with Twilio API
-            // For now, return a placeholder
-            Err(TwilioError::ApiError {
-                status_code: 501,
-                message: format!(
-                    "Not implemented: send_email to {} with subject {}",
-                    to, subject
-                ),
+            let twilio_config = self
+                .config
+                .twilio
+                .as_ref()
+                .ok_or(TwilioError::ConfigError)?;
+            let id = deliver_email(twilio_config, &mail?).await?;
+            Ok(NotificationResult {
+                id,
+                status: "accepted".to_string(),
             })
         })
     }
@@ -89,11 +375,20 @@ impl NotificationService for TwilioNotificationService {
         let body = body.to_string();
 
         Box::pin(async move {
-            // This would need to be implemented with Twilio API
-            // For now, return a placeholder
-            Err(TwilioError::ApiError {
-                status_code: 501,
-                message: format!("Not implemented: send_sms to {} with body {}", to, body),
+            if !runtime_flags().is_enabled(SMS) {
+                return Err(TwilioError::ApiError {
+                    status_code: 503,
+                    message: "Outgoing SMS are temporarily disabled".to_string(),
+                });
+            }
+
+            // Sent by the queue worker at the sender's throughput
+            let id = queue_sms(&SmsRequest { to, message: body })
+                .await
+                .map_err(|e| TwilioError::InternalError(format!("Failed to queue SMS: {}", e)))?;
+            Ok(NotificationResult {
+                id,
+                status: "queued".to_string(),
             })
         })
     }
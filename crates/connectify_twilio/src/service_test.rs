@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod tests {
+    use crate::service::{
+        classify_error_code, is_idempotent, TwilioError, TwilioErrorClass, TWILIO_ERROR_CODES,
+    };
+    use crate::twilio_sms_queue::SmsOutcome;
+    use connectify_common::ConnectifyError;
+    use reqwest::StatusCode;
+
+    fn error_body(code: u32, message: &str) -> String {
+        format!(
+            r#"{{"code": {}, "message": "{}", "more_info": "https://www.twilio.com/docs/errors/{}", "status": 400}}"#,
+            code, message, code
+        )
+    }
+
+    #[test]
+    fn test_error_codes_are_unique() {
+        for (index, (code, _, _)) in TWILIO_ERROR_CODES.iter().enumerate() {
+            assert!(
+                !TWILIO_ERROR_CODES[index + 1..]
+                    .iter()
+                    .any(|(other, _, _)| other == code),
+                "{} is listed twice",
+                code
+            );
+        }
+        assert_eq!(
+            classify_error_code(21608).map(|(class, _)| class),
+            Some(TwilioErrorClass::NotPermitted)
+        );
+        assert_eq!(classify_error_code(30003), None);
+    }
+
+    #[test]
+    fn test_from_response_classifies_codes() {
+        let error = TwilioError::from_response(
+            StatusCode::BAD_REQUEST,
+            error_body(21211, "Invalid 'To' Phone Number: +4179"),
+        );
+        match &error {
+            TwilioError::InvalidRecipient { code, message } => {
+                assert_eq!(*code, 21211);
+                assert!(message.starts_with("Invalid 'To' Phone Number: +4179 (code 21211). "));
+                assert!(message.contains("E.164"));
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+        assert_eq!(SmsOutcome::of(&Err::<(), _>(error)), SmsOutcome::Rejected);
+
+        let error = TwilioError::from_response(
+            StatusCode::BAD_REQUEST,
+            error_body(21608, "The number +41791234567 is unverified"),
+        );
+        assert!(matches!(
+            error,
+            TwilioError::NotPermitted { code: 21608, .. }
+        ));
+        assert!(matches!(
+            ConnectifyError::from(error),
+            ConnectifyError::ValidationError(message) if message.contains("verify the number")
+        ));
+
+        let error =
+            TwilioError::from_response(StatusCode::UNAUTHORIZED, error_body(20003, "Authenticate"));
+        assert!(matches!(error, TwilioError::AuthenticationFailed(_)));
+        // A wrong config fails every SMS, so they stay queued and the worker pauses
+        assert_eq!(SmsOutcome::of(&Err::<(), _>(error)), SmsOutcome::Suspended);
+
+        let error = TwilioError::from_response(
+            StatusCode::BAD_REQUEST,
+            error_body(
+                21617,
+                "The concatenated message body exceeds the 1600 character limit",
+            ),
+        );
+        assert!(matches!(error, TwilioError::ValidationError(_)));
+    }
+
+    #[test]
+    fn test_is_idempotent() {
+        let client = reqwest::Client::new();
+        let url = "https://api.twilio.com/2010-04-01/Accounts/AC123/Messages.json";
+        assert!(is_idempotent(&client.get(url)));
+        assert!(is_idempotent(&client.delete(url)));
+        // Sending an SMS twice sends two SMS
+        assert!(!is_idempotent(&client.post(url).form(&[("Body", "Hi")])));
+    }
+
+    #[test]
+    fn test_from_response_unknown_codes() {
+        let error = TwilioError::from_response(
+            StatusCode::CONFLICT,
+            error_body(
+                50353,
+                "Conversation with provided unique name already exists",
+            ),
+        );
+        match error {
+            TwilioError::ApiError {
+                status_code,
+                message,
+            } => {
+                assert_eq!(status_code, 409);
+                assert_eq!(
+                    message,
+                    "Conversation with provided unique name already exists (code 50353)"
+                );
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        let error = TwilioError::from_response(StatusCode::BAD_GATEWAY, "Bad gateway".to_string());
+        assert!(matches!(
+            error,
+            TwilioError::ApiError { status_code: 502, ref message } if message == "Bad gateway"
+        ));
+        assert_eq!(
+            SmsOutcome::of(&Err::<(), _>(error)),
+            SmsOutcome::Unconfirmed
+        );
+    }
+}
@@ -0,0 +1,24 @@
+//! Fixtures shared by the tests of this crate.
+
+use connectify_config::TwilioConfig;
+
+/// A Twilio config with test credentials and everything else unset.
+///
+/// Tests override the fields they need with struct update syntax:
+///
+/// ```ignore
+/// let config = TwilioConfig {
+///     caller_id: Some("+41440000000".to_string()),
+///     ..test_support::twilio_config()
+/// };
+/// ```
+pub(crate) fn twilio_config() -> TwilioConfig {
+    TwilioConfig {
+        account_sid: "AC123".to_string(),
+        api_key_sid: "SK123".to_string(),
+        api_key_secret: "secret".to_string(),
+        auth_token: "token".to_string(),
+        phone_number: "+41790000000".to_string(),
+        ..Default::default()
+    }
+}
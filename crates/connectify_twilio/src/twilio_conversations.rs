@@ -0,0 +1,417 @@
+// --- File: crates/connectify_twilio/src/twilio_conversations.rs ---
+//! Twilio Conversations per booking: a persistent channel for questions before the appointment,
+//! bridging participants writing by SMS and in the app's chat.
+//!
+//! A booking's conversation has the unique name `booking-{booking_id}`, so creating it again
+//! returns the existing one instead of a second conversation.
+
+use axum::{
+    extract::{Path, State},
+    response::Json,
+};
+use connectify_common::audit::{self, AuditActor, AuditEvent};
+use connectify_common::request_id::RequestIdExt;
+use connectify_common::{ConnectifyError, HTTP_CLIENT};
+use connectify_config::{AppConfig, TwilioConfig};
+use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::service::{send_twilio_request, twilio_config, TwilioError};
+use crate::twilio_voice::is_e164;
+
+/// Base URL of the Twilio Conversations REST API.
+const TWILIO_CONVERSATIONS_API_BASE_URL: &str = "https://conversations.twilio.com/v1";
+
+/// Twilio error code of a conversation whose unique name is taken.
+const CONVERSATION_EXISTS_CODE: &str = "50353";
+
+#[derive(Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateConversationRequest {
+    /// The booking the conversation is about, e.g. its calendar event ID
+    #[cfg_attr(feature = "openapi", schema(example = "evt_0123456789"))]
+    pub booking_id: String,
+    /// Name shown in the chat (default: `Booking {booking_id}`)
+    #[cfg_attr(feature = "openapi", schema(example = "Consultation on 1 July"))]
+    pub friendly_name: Option<String>,
+}
+
+/// A conversation as returned by the Conversations API.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Conversation {
+    #[cfg_attr(
+        feature = "openapi",
+        schema(example = "CH0123456789abcdef0123456789abcdef")
+    )]
+    pub sid: String,
+    #[cfg_attr(feature = "openapi", schema(example = "booking-evt_0123456789"))]
+    pub unique_name: Option<String>,
+    pub friendly_name: Option<String>,
+    /// `active`, `inactive` or `closed`
+    pub state: Option<String>,
+    pub date_created: Option<String>,
+}
+
+/// A participant to add to a conversation: either a phone number writing by SMS or an identity
+/// of the app's chat.
+#[derive(Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AddParticipantRequest {
+    /// Number of an SMS participant, in E.164 format
+    #[cfg_attr(feature = "openapi", schema(example = "+41791234567"))]
+    pub phone: Option<String>,
+    /// Identity of a chat participant, as in their access token
+    #[cfg_attr(feature = "openapi", schema(example = "User_12345"))]
+    pub identity: Option<String>,
+}
+
+/// A participant as returned by the Conversations API.
+#[derive(Deserialize, Debug)]
+struct TwilioParticipant {
+    sid: String,
+    identity: Option<String>,
+    messaging_binding: Option<MessagingBinding>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MessagingBinding {
+    address: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ParticipantResponse {
+    #[cfg_attr(
+        feature = "openapi",
+        schema(example = "MB0123456789abcdef0123456789abcdef")
+    )]
+    pub participant_sid: String,
+    /// Identity of a chat participant
+    pub identity: Option<String>,
+    /// Number of an SMS participant
+    pub phone: Option<String>,
+}
+
+impl From<TwilioParticipant> for ParticipantResponse {
+    fn from(participant: TwilioParticipant) -> Self {
+        Self {
+            participant_sid: participant.sid,
+            identity: participant.identity,
+            phone: participant
+                .messaging_binding
+                .and_then(|binding| binding.address),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PostMessageRequest {
+    /// Text of the message
+    #[cfg_attr(
+        feature = "openapi",
+        schema(example = "Please bring your documents to the appointment.")
+    )]
+    pub body: String,
+    /// Author shown with the message (default: Twilio's `system`)
+    #[cfg_attr(feature = "openapi", schema(example = "Connectify"))]
+    pub author: Option<String>,
+}
+
+/// A message as returned by the Conversations API.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ConversationMessage {
+    #[cfg_attr(
+        feature = "openapi",
+        schema(example = "IM0123456789abcdef0123456789abcdef")
+    )]
+    pub sid: String,
+    /// Position of the message in the conversation
+    pub index: i64,
+    pub author: Option<String>,
+    pub body: Option<String>,
+    pub date_created: Option<String>,
+}
+
+/// The unique name of a booking's conversation.
+pub fn booking_unique_name(booking_id: &str) -> String {
+    format!("booking-{}", booking_id)
+}
+
+/// The form parameters creating a booking's conversation.
+pub fn conversation_params(
+    request: &CreateConversationRequest,
+) -> Result<Vec<(&'static str, String)>, TwilioError> {
+    let booking_id = request.booking_id.trim();
+    if booking_id.is_empty() {
+        return Err(TwilioError::ValidationError(
+            "booking_id is required".to_string(),
+        ));
+    }
+    let friendly_name = request
+        .friendly_name
+        .clone()
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| format!("Booking {}", booking_id));
+    Ok(vec![
+        ("UniqueName", booking_unique_name(booking_id)),
+        ("FriendlyName", friendly_name),
+        (
+            "Attributes",
+            serde_json::json!({ "booking_id": booking_id }).to_string(),
+        ),
+    ])
+}
+
+/// The form parameters adding a participant. SMS participants write from their number to the
+/// configured `phone_number`.
+pub fn participant_params(
+    config: &TwilioConfig,
+    request: &AddParticipantRequest,
+) -> Result<Vec<(&'static str, String)>, TwilioError> {
+    let phone = request.phone.as_deref().map(str::trim);
+    let identity = request.identity.as_deref().map(str::trim);
+    match (phone, identity) {
+        (Some(phone), None) if is_e164(phone) => Ok(vec![
+            ("MessagingBinding.Address", phone.to_string()),
+            ("MessagingBinding.ProxyAddress", config.phone_number.clone()),
+        ]),
+        (Some(phone), None) => Err(TwilioError::ValidationError(format!(
+            "phone must be a phone number in E.164 format, got '{}'",
+            phone
+        ))),
+        (None, Some(identity)) if !identity.is_empty() => {
+            Ok(vec![("Identity", identity.to_string())])
+        }
+        _ => Err(TwilioError::ValidationError(
+            "set either phone or identity".to_string(),
+        )),
+    }
+}
+
+/// The form parameters posting a message.
+pub fn message_params(
+    request: &PostMessageRequest,
+) -> Result<Vec<(&'static str, String)>, TwilioError> {
+    if request.body.trim().is_empty() {
+        return Err(TwilioError::ValidationError("body is required".to_string()));
+    }
+    let mut params = vec![("Body", request.body.clone())];
+    if let Some(author) = request.author.as_deref().filter(|a| !a.trim().is_empty()) {
+        params.push(("Author", author.to_string()));
+    }
+    Ok(params)
+}
+
+fn authorized(config: &TwilioConfig, request: RequestBuilder) -> RequestBuilder {
+    request
+        .with_request_id()
+        .basic_auth(&config.account_sid, Some(&config.auth_token))
+}
+
+/// Sends a request to the Conversations API and parses its response.
+async fn send<T: DeserializeOwned>(
+    config: &TwilioConfig,
+    request: RequestBuilder,
+) -> Result<T, TwilioError> {
+    let response =
+        send_twilio_request(authorized(config, request), "calling Twilio Conversations").await?;
+    let status = response.status();
+    let body = response.text().await?;
+    if !status.is_success() {
+        error!(
+            "[Twilio Conversations] Twilio returned {}: {}",
+            status, body
+        );
+        return Err(TwilioError::from_response(status, body));
+    }
+    serde_json::from_str(&body).map_err(|e| {
+        TwilioError::InternalError(format!("Failed to parse Twilio Conversations: {}", e))
+    })
+}
+
+/// Creates the conversation of a booking, or returns the existing one.
+///
+/// # Returns
+///
+/// The conversation, and whether it was created.
+pub async fn create_booking_conversation(
+    config: &TwilioConfig,
+    request: &CreateConversationRequest,
+) -> Result<(Conversation, bool), TwilioError> {
+    let params = conversation_params(request)?;
+    let url = format!("{}/Conversations", TWILIO_CONVERSATIONS_API_BASE_URL);
+    match send(config, HTTP_CLIENT.post(&url).form(&params)).await {
+        Ok(conversation) => Ok((conversation, true)),
+        Err(TwilioError::ApiError {
+            status_code: 409,
+            message,
+        }) if message.contains(CONVERSATION_EXISTS_CODE) => {
+            let unique_name = booking_unique_name(request.booking_id.trim());
+            info!(
+                "[Twilio Conversations] Conversation {} exists already",
+                unique_name
+            );
+            let conversation =
+                send(config, HTTP_CLIENT.get(format!("{}/{}", url, unique_name))).await?;
+            Ok((conversation, false))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Adds a participant to a conversation.
+pub async fn add_participant(
+    config: &TwilioConfig,
+    conversation_sid: &str,
+    request: &AddParticipantRequest,
+) -> Result<ParticipantResponse, TwilioError> {
+    let params = participant_params(config, request)?;
+    let url = format!(
+        "{}/Conversations/{}/Participants",
+        TWILIO_CONVERSATIONS_API_BASE_URL, conversation_sid
+    );
+    let participant: TwilioParticipant = send(config, HTTP_CLIENT.post(&url).form(&params)).await?;
+    Ok(participant.into())
+}
+
+/// Posts a message to a conversation, delivered to its SMS and chat participants.
+pub async fn post_message(
+    config: &TwilioConfig,
+    conversation_sid: &str,
+    request: &PostMessageRequest,
+) -> Result<ConversationMessage, TwilioError> {
+    let params = message_params(request)?;
+    let url = format!(
+        "{}/Conversations/{}/Messages",
+        TWILIO_CONVERSATIONS_API_BASE_URL, conversation_sid
+    );
+    send(config, HTTP_CLIENT.post(&url).form(&params)).await
+}
+
+/// Admin handler creating the conversation of a booking, or returning the existing one.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/twilio/conversations", // Path relative to /api
+    request_body = CreateConversationRequest,
+    responses(
+        (status = 200, description = "The booking's conversation", body = Conversation),
+        (status = 400, description = "Missing booking_id"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 502, description = "Twilio API error")
+    ),
+    tag = "Twilio"
+))]
+pub async fn create_conversation_handler(
+    State(config): State<Arc<AppConfig>>,
+    actor: AuditActor,
+    Json(request): Json<CreateConversationRequest>,
+) -> Result<Json<Conversation>, ConnectifyError> {
+    let twilio_config = twilio_config(&config)?;
+
+    let result = create_booking_conversation(twilio_config, &request).await;
+    if !matches!(result, Ok((_, false))) {
+        audit::record(
+            AuditEvent::new(
+                actor,
+                "conversation.create",
+                format!("booking:{}", request.booking_id),
+            )
+            .with_metadata(
+                "conversation_sid",
+                result
+                    .as_ref()
+                    .ok()
+                    .map(|(conversation, _)| conversation.sid.clone()),
+            )
+            .with_result(&result),
+        )
+        .await;
+    }
+    Ok(Json(result?.0))
+}
+
+/// Admin handler adding an SMS or chat participant to a conversation.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/twilio/conversations/{conversation_sid}/participants", // Path relative to /api
+    params(("conversation_sid" = String, Path, description = "The Twilio conversation SID")),
+    request_body = AddParticipantRequest,
+    responses(
+        (status = 200, description = "Participant added", body = ParticipantResponse),
+        (status = 400, description = "Neither or both of phone and identity, or an invalid number"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Conversation not found"),
+        (status = 409, description = "Already a participant"),
+        (status = 502, description = "Twilio API error")
+    ),
+    tag = "Twilio"
+))]
+pub async fn add_participant_handler(
+    State(config): State<Arc<AppConfig>>,
+    actor: AuditActor,
+    Path(conversation_sid): Path<String>,
+    Json(request): Json<AddParticipantRequest>,
+) -> Result<Json<ParticipantResponse>, ConnectifyError> {
+    let twilio_config = twilio_config(&config)?;
+
+    let result = add_participant(twilio_config, &conversation_sid, &request).await;
+    audit::record(
+        AuditEvent::new(
+            actor,
+            "conversation.participant_add",
+            format!("twilio_conversation:{}", conversation_sid),
+        )
+        .with_metadata(
+            "participant",
+            request.phone.clone().or_else(|| request.identity.clone()),
+        )
+        .with_result(&result),
+    )
+    .await;
+    Ok(Json(result?))
+}
+
+/// Admin handler posting a message to a conversation.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/twilio/conversations/{conversation_sid}/messages", // Path relative to /api
+    params(("conversation_sid" = String, Path, description = "The Twilio conversation SID")),
+    request_body = PostMessageRequest,
+    responses(
+        (status = 200, description = "Message posted", body = ConversationMessage),
+        (status = 400, description = "Empty body"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Conversation not found"),
+        (status = 502, description = "Twilio API error")
+    ),
+    tag = "Twilio"
+))]
+pub async fn post_message_handler(
+    State(config): State<Arc<AppConfig>>,
+    Path(conversation_sid): Path<String>,
+    Json(request): Json<PostMessageRequest>,
+) -> Result<Json<ConversationMessage>, ConnectifyError> {
+    let twilio_config = twilio_config(&config)?;
+    info!(
+        "[Twilio Conversations] Posting message to conversation {}",
+        conversation_sid
+    );
+    Ok(Json(
+        post_message(twilio_config, &conversation_sid, &request).await?,
+    ))
+}
@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use crate::service::TwilioError;
+    use crate::test_support::twilio_config;
+    use crate::twilio_conversations::{
+        conversation_params, message_params, participant_params, AddParticipantRequest,
+        CreateConversationRequest, PostMessageRequest,
+    };
+
+    fn participant(phone: Option<&str>, identity: Option<&str>) -> AddParticipantRequest {
+        AddParticipantRequest {
+            phone: phone.map(str::to_string),
+            identity: identity.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_conversation_params() {
+        let params = conversation_params(&CreateConversationRequest {
+            booking_id: " evt_1 ".to_string(),
+            friendly_name: None,
+        })
+        .unwrap();
+        assert_eq!(
+            params,
+            vec![
+                ("UniqueName", "booking-evt_1".to_string()),
+                ("FriendlyName", "Booking evt_1".to_string()),
+                ("Attributes", r#"{"booking_id":"evt_1"}"#.to_string()),
+            ]
+        );
+        assert!(matches!(
+            conversation_params(&CreateConversationRequest {
+                booking_id: " ".to_string(),
+                friendly_name: None,
+            }),
+            Err(TwilioError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_participant_params() {
+        let config = twilio_config();
+        assert_eq!(
+            participant_params(&config, &participant(Some("+41791234567"), None)).unwrap(),
+            vec![
+                ("MessagingBinding.Address", "+41791234567".to_string()),
+                ("MessagingBinding.ProxyAddress", "+41790000000".to_string()),
+            ]
+        );
+        assert_eq!(
+            participant_params(&config, &participant(None, Some("User_12345"))).unwrap(),
+            vec![("Identity", "User_12345".to_string())]
+        );
+        for invalid in [
+            participant(Some("079 123 45 67"), None),
+            participant(Some("+41791234567"), Some("User_12345")),
+            participant(None, Some(" ")),
+            participant(None, None),
+        ] {
+            assert!(matches!(
+                participant_params(&config, &invalid),
+                Err(TwilioError::ValidationError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_message_params() {
+        let params = message_params(&PostMessageRequest {
+            body: "See you tomorrow".to_string(),
+            author: Some("Connectify".to_string()),
+        })
+        .unwrap();
+        assert_eq!(
+            params,
+            vec![
+                ("Body", "See you tomorrow".to_string()),
+                ("Author", "Connectify".to_string()),
+            ]
+        );
+        assert!(matches!(
+            message_params(&PostMessageRequest {
+                body: "".to_string(),
+                author: None,
+            }),
+            Err(TwilioError::ValidationError(_))
+        ));
+    }
+}
@@ -0,0 +1,229 @@
+// --- File: crates/connectify_twilio/src/twilio_email.rs ---
+//! Emails sent through Twilio SendGrid's v3 Mail Send API.
+//!
+//! Each email has a plain text body, and HTML emails also the HTML body, so clients not showing
+//! HTML get readable text derived from it. The emails are sent from `email_from` with the API key
+//! `sendgrid_api_key` and tagged with `email_categories` for SendGrid's statistics, see
+//! <https://www.twilio.com/docs/sendgrid/api-reference/mail-send/mail-send>.
+use connectify_common::request_id::RequestIdExt;
+use connectify_common::HTTP_CLIENT;
+use connectify_config::TwilioConfig;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::service::{send_twilio_request, TwilioError};
+
+/// Endpoint of the Mail Send API.
+const SENDGRID_MAIL_SEND_URL: &str = "https://api.sendgrid.com/v3/mail/send";
+
+/// Header of SendGrid's ID of an accepted email.
+const MESSAGE_ID_HEADER: &str = "X-Message-Id";
+
+/// Most categories SendGrid accepts per email.
+const MAX_CATEGORIES: usize = 10;
+
+/// An address of an email.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EmailAddress {
+    pub email: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// The recipients of an email.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Personalization {
+    pub to: Vec<EmailAddress>,
+}
+
+/// A body of an email, `text/plain` or `text/html`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EmailContent {
+    #[serde(rename = "type")]
+    pub content_type: String,
+    pub value: String,
+}
+
+/// The request body of the Mail Send API.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SendGridMail {
+    pub personalizations: Vec<Personalization>,
+    pub from: EmailAddress,
+    pub subject: String,
+    /// The bodies, plain text first as SendGrid requires.
+    pub content: Vec<EmailContent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+}
+
+/// Error body of the SendGrid API.
+#[derive(Deserialize, Debug)]
+struct SendGridErrorBody {
+    errors: Vec<SendGridErrorItem>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SendGridErrorItem {
+    message: String,
+    field: Option<String>,
+}
+
+/// Readable text of an HTML body: line breaks for `<br>` and block ends, tags removed, common
+/// entities decoded.
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 1..start + end]
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        if matches!(
+            tag.as_str(),
+            "br" | "p" | "div" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+        ) && !text.ends_with('\n')
+        {
+            text.push('\n');
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The Mail Send request of an email from the configured sender.
+pub fn sendgrid_mail(
+    config: &TwilioConfig,
+    to: &str,
+    subject: &str,
+    body: &str,
+    is_html: bool,
+) -> Result<SendGridMail, TwilioError> {
+    let from = config
+        .email_from
+        .clone()
+        .filter(|from| !from.is_empty())
+        .ok_or(TwilioError::ConfigError)?;
+    if to.trim().is_empty() || !to.contains('@') {
+        return Err(TwilioError::ValidationError(format!(
+            "Invalid email recipient '{}'",
+            to
+        )));
+    }
+    let mut content = vec![EmailContent {
+        content_type: "text/plain".to_string(),
+        value: if is_html {
+            html_to_text(body)
+        } else {
+            body.to_string()
+        },
+    }];
+    if is_html {
+        content.push(EmailContent {
+            content_type: "text/html".to_string(),
+            value: body.to_string(),
+        });
+    }
+    Ok(SendGridMail {
+        personalizations: vec![Personalization {
+            to: vec![EmailAddress {
+                email: to.trim().to_string(),
+                name: None,
+            }],
+        }],
+        from: EmailAddress {
+            email: from,
+            name: config.email_from_name.clone(),
+        },
+        subject: subject.to_string(),
+        content,
+        categories: config
+            .email_categories
+            .iter()
+            .take(MAX_CATEGORIES)
+            .cloned()
+            .collect(),
+    })
+}
+
+/// The error of a failed SendGrid response, with the messages of its error body.
+fn sendgrid_error(status: reqwest::StatusCode, body: String) -> TwilioError {
+    let message = serde_json::from_str::<SendGridErrorBody>(&body)
+        .map(|error| {
+            error
+                .errors
+                .into_iter()
+                .map(|item| match item.field {
+                    Some(field) => format!("{} ({})", item.message, field),
+                    None => item.message,
+                })
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
+        .unwrap_or(body);
+    TwilioError::ApiError {
+        status_code: status.as_u16(),
+        message,
+    }
+}
+
+/// Sends an email through SendGrid.
+///
+/// # Returns
+///
+/// SendGrid's ID of the accepted email, empty if SendGrid sent none.
+pub async fn deliver_email(
+    config: &TwilioConfig,
+    mail: &SendGridMail,
+) -> Result<String, TwilioError> {
+    let api_key = config
+        .sendgrid_api_key
+        .as_deref()
+        .filter(|key| !key.is_empty())
+        .ok_or(TwilioError::ConfigError)?;
+    let response = send_twilio_request(
+        HTTP_CLIENT
+            .post(SENDGRID_MAIL_SEND_URL)
+            .with_request_id()
+            .bearer_auth(api_key)
+            .json(mail),
+        "sending email",
+    )
+    .await?;
+
+    let status = response.status();
+    let message_id = response
+        .headers()
+        .get(MESSAGE_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        error!("[SendGrid] Mail Send returned {}: {}", status, body);
+        return Err(sendgrid_error(status, body));
+    }
+    info!(
+        "[SendGrid] Email '{}' accepted as {}",
+        mail.subject, message_id
+    );
+    Ok(message_id)
+}
@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use crate::service::TwilioError;
+    use crate::test_support;
+    use crate::twilio_email::{html_to_text, sendgrid_mail, SendGridMail};
+    use connectify_config::TwilioConfig;
+    use serde_json::json;
+
+    fn twilio_config() -> TwilioConfig {
+        TwilioConfig {
+            sendgrid_api_key: Some("SG.key".to_string()),
+            email_from: Some("noreply@connectify.example.com".to_string()),
+            email_from_name: Some("Connectify".to_string()),
+            email_categories: vec!["notifications".to_string()],
+            ..test_support::twilio_config()
+        }
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        assert_eq!(
+            html_to_text(
+                "<html><body><h1>Booking confirmed</h1><p>Monday,&nbsp;10:00 &amp; 11:00<br/>Room <b>A</b></p></body></html>"
+            ),
+            "Booking confirmed\nMonday, 10:00 & 11:00\nRoom A"
+        );
+        assert_eq!(html_to_text("1 < 2"), "1 < 2");
+    }
+
+    #[test]
+    fn test_sendgrid_mail_text() {
+        let mail = sendgrid_mail(
+            &twilio_config(),
+            "anna@example.com",
+            "Reminder",
+            "See you tomorrow",
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(&mail).unwrap(),
+            json!({
+                "personalizations": [{"to": [{"email": "anna@example.com"}]}],
+                "from": {"email": "noreply@connectify.example.com", "name": "Connectify"},
+                "subject": "Reminder",
+                "content": [{"type": "text/plain", "value": "See you tomorrow"}],
+                "categories": ["notifications"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_sendgrid_mail_html() {
+        let mail: SendGridMail = sendgrid_mail(
+            &twilio_config(),
+            "anna@example.com",
+            "Reminder",
+            "<p>See you <b>tomorrow</b></p>",
+            true,
+        )
+        .unwrap();
+        let types: Vec<&str> = mail
+            .content
+            .iter()
+            .map(|content| content.content_type.as_str())
+            .collect();
+        assert_eq!(types, vec!["text/plain", "text/html"]);
+        assert_eq!(mail.content[0].value, "See you tomorrow");
+        assert_eq!(mail.content[1].value, "<p>See you <b>tomorrow</b></p>");
+    }
+
+    #[test]
+    fn test_sendgrid_mail_errors() {
+        let config = twilio_config();
+        assert!(matches!(
+            sendgrid_mail(&config, "not-an-address", "Reminder", "Hi", false),
+            Err(TwilioError::ValidationError(_))
+        ));
+        let config = TwilioConfig {
+            email_from: None,
+            ..config
+        };
+        assert!(matches!(
+            sendgrid_mail(&config, "anna@example.com", "Reminder", "Hi", false),
+            Err(TwilioError::ConfigError)
+        ));
+    }
+}
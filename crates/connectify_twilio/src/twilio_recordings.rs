@@ -0,0 +1,476 @@
+// --- File: crates/connectify_twilio/src/twilio_recordings.rs ---
+//! Recordings and compositions of completed Twilio Video rooms: listing them, short-lived links
+//! to their media, and the retention policy deleting them after `recording_retention_days`.
+
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use chrono::{DateTime, Duration, NaiveDate, SecondsFormat, Utc};
+use connectify_common::audit::{self, AuditActor, AuditEvent};
+use connectify_common::http::client::create_client;
+use connectify_common::request_id::RequestIdExt;
+use connectify_common::{ConnectifyError, HTTP_CLIENT};
+use connectify_config::{AppConfig, TwilioConfig};
+use once_cell::sync::Lazy;
+use reqwest::header::{HeaderMap, LOCATION};
+use reqwest::{Client, RequestBuilder, Response, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::service::{send_twilio_request, twilio_config, TwilioError};
+
+/// Base URL of the Twilio Video REST API.
+const TWILIO_VIDEO_API_BASE_URL: &str = "https://video.twilio.com/v1";
+
+/// Seconds a media link stays valid.
+pub const MEDIA_LINK_TTL_SECONDS: u32 = 600;
+
+/// Items requested per page of a listing.
+const PAGE_SIZE: &str = "100";
+
+/// Pages followed by a listing before it stops, bounding the requests of a single call.
+const MAX_PAGES: usize = 50;
+
+/// Client for the media endpoints, which answer with a redirect to the signed media URL; the
+/// redirect is returned, not followed, as the media itself is not downloaded.
+static MEDIA_CLIENT: Lazy<Client> =
+    Lazy::new(|| create_client(30, false).expect("Failed to create Twilio media client"));
+
+/// A Twilio Video room.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct VideoRoom {
+    #[cfg_attr(
+        feature = "openapi",
+        schema(example = "RM0123456789abcdef0123456789abcdef")
+    )]
+    pub sid: String,
+    #[cfg_attr(feature = "openapi", schema(example = "MyCoolRoom"))]
+    pub unique_name: Option<String>,
+    /// `in-progress` or `completed`
+    #[cfg_attr(feature = "openapi", schema(example = "completed"))]
+    pub status: String,
+    /// e.g. "2025-05-15T10:00:00Z"
+    pub date_created: Option<String>,
+    pub end_time: Option<String>,
+    /// Seconds the room was open
+    pub duration: Option<i64>,
+}
+
+/// A recording of one track of a room.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RoomRecording {
+    #[cfg_attr(
+        feature = "openapi",
+        schema(example = "RT0123456789abcdef0123456789abcdef")
+    )]
+    pub sid: String,
+    /// `processing`, `completed`, `deleted` or `failed`
+    #[cfg_attr(feature = "openapi", schema(example = "completed"))]
+    pub status: String,
+    /// `audio`, `video` or `data`
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub track_name: Option<String>,
+    /// Seconds of media
+    pub duration: Option<i64>,
+    /// Bytes of media
+    pub size: Option<i64>,
+    /// e.g. `mka` or `mkv`
+    pub container: Option<String>,
+    pub date_created: Option<String>,
+}
+
+/// A composition, the recordings of a room mixed into one playable file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RoomComposition {
+    #[cfg_attr(
+        feature = "openapi",
+        schema(example = "CJ0123456789abcdef0123456789abcdef")
+    )]
+    pub sid: String,
+    /// `enqueued`, `processing`, `completed`, `deleted` or `failed`
+    #[cfg_attr(feature = "openapi", schema(example = "completed"))]
+    pub status: String,
+    /// e.g. `mp4` or `webm`
+    pub format: Option<String>,
+    /// Seconds of media
+    pub duration: Option<i64>,
+    /// Bytes of media
+    pub size: Option<i64>,
+    pub date_created: Option<String>,
+}
+
+/// The recordings and compositions of a room.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RoomMedia {
+    pub room_sid: String,
+    pub recordings: Vec<RoomRecording>,
+    pub compositions: Vec<RoomComposition>,
+}
+
+/// A short-lived link to the media of a recording or composition.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MediaLink {
+    pub sid: String,
+    pub url: String,
+    #[cfg_attr(feature = "openapi", schema(example = 600))]
+    pub expires_in_seconds: u32,
+}
+
+/// Query of the completed rooms listing.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams, utoipa::ToSchema))]
+pub struct ListRoomsQuery {
+    /// Only rooms created on or after this day (UTC), as YYYY-MM-DD
+    #[cfg_attr(feature = "openapi", param(example = "2025-07-01", required = false))]
+    pub created_from: Option<String>,
+    /// Only rooms created on or before this day (UTC), as YYYY-MM-DD
+    #[cfg_attr(feature = "openapi", param(example = "2025-07-31", required = false))]
+    pub created_to: Option<String>,
+}
+
+impl ListRoomsQuery {
+    /// The query parameters listing completed rooms from Twilio.
+    fn twilio_params(&self) -> Result<Vec<(&'static str, String)>, TwilioError> {
+        let day = |name: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|value| {
+                    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+                        TwilioError::ValidationError(format!(
+                            "{} must be a date in YYYY-MM-DD format, got '{}'",
+                            name, value
+                        ))
+                    })
+                })
+                .transpose()
+        };
+        let from = day("created_from", &self.created_from)?;
+        let to = day("created_to", &self.created_to)?;
+        let mut params = vec![
+            ("Status", "completed".to_string()),
+            ("PageSize", PAGE_SIZE.to_string()),
+        ];
+        if let Some(from) = from {
+            params.push(("DateCreatedAfter", format!("{}T00:00:00Z", from)));
+        }
+        if let Some(to) = to {
+            params.push(("DateCreatedBefore", format!("{}T23:59:59Z", to)));
+        }
+        Ok(params)
+    }
+}
+
+/// What the retention job deleted.
+#[derive(Debug, Default, PartialEq)]
+pub struct PurgedRecordings {
+    pub recordings: usize,
+    pub compositions: usize,
+}
+
+/// The creation time before which recordings are deleted.
+pub fn retention_cutoff(now: DateTime<Utc>, retention_days: u32) -> String {
+    (now - Duration::days(i64::from(retention_days))).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// The items of a list page under `key`, and the URL of the next page.
+pub fn page_items<T: DeserializeOwned>(
+    mut page: Value,
+    key: &str,
+) -> Result<(Vec<T>, Option<String>), TwilioError> {
+    let items = match page.get_mut(key).map(Value::take) {
+        Some(items) => serde_json::from_value(items).map_err(|e| {
+            TwilioError::InternalError(format!("Failed to parse Twilio {}: {}", key, e))
+        })?,
+        None => Vec::new(),
+    };
+    let next_page_url = page
+        .pointer("/meta/next_page_url")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    Ok((items, next_page_url))
+}
+
+/// The signed media URL of a media endpoint's response: its `redirect_to`, or else the
+/// `Location` of its redirect.
+pub fn media_url(headers: &HeaderMap, body: &str) -> Option<String> {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|body| body.get("redirect_to")?.as_str().map(str::to_string))
+        .or_else(|| {
+            headers
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .map(str::to_string)
+        })
+}
+
+fn authorized(config: &TwilioConfig, request: RequestBuilder) -> RequestBuilder {
+    request
+        .with_request_id()
+        .basic_auth(&config.account_sid, Some(&config.auth_token))
+}
+
+async fn send(request: RequestBuilder) -> Result<Response, TwilioError> {
+    send_twilio_request(request, "calling Twilio Video").await
+}
+
+/// Lists all items of a Twilio Video listing, following its pages.
+async fn list_all<T: DeserializeOwned>(
+    config: &TwilioConfig,
+    path: &str,
+    params: &[(&str, String)],
+    key: &str,
+) -> Result<Vec<T>, TwilioError> {
+    let url = Url::parse_with_params(&format!("{}/{}", TWILIO_VIDEO_API_BASE_URL, path), params)
+        .map_err(|e| TwilioError::InternalError(format!("Invalid Twilio Video URL: {}", e)))?;
+    let mut items = Vec::new();
+    let mut next_page_url = Some(url.to_string());
+    for _ in 0..MAX_PAGES {
+        let Some(url) = next_page_url.take() else {
+            return Ok(items);
+        };
+        let response = send(authorized(config, HTTP_CLIENT.get(&url))).await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            error!(
+                "[Twilio Video] Listing {} returned {}: {}",
+                key, status, body
+            );
+            return Err(TwilioError::from_response(status, body));
+        }
+        let page = serde_json::from_str(&body).map_err(|e| {
+            TwilioError::InternalError(format!("Failed to parse Twilio {} page: {}", key, e))
+        })?;
+        let (page_items, next) = page_items(page, key)?;
+        items.extend(page_items);
+        next_page_url = next;
+    }
+    if next_page_url.is_some() {
+        warn!(
+            "[Twilio Video] Stopped listing {} after {} pages",
+            key, MAX_PAGES
+        );
+    }
+    Ok(items)
+}
+
+/// Lists the completed rooms.
+pub async fn list_completed_rooms(
+    config: &TwilioConfig,
+    query: &ListRoomsQuery,
+) -> Result<Vec<VideoRoom>, TwilioError> {
+    list_all(config, "Rooms", &query.twilio_params()?, "rooms").await
+}
+
+/// Lists the recordings and compositions of a room.
+pub async fn room_media(config: &TwilioConfig, room_sid: &str) -> Result<RoomMedia, TwilioError> {
+    let page_size = [("PageSize", PAGE_SIZE.to_string())];
+    let recordings = list_all(
+        config,
+        &format!("Rooms/{}/Recordings", room_sid),
+        &page_size,
+        "recordings",
+    )
+    .await?;
+    let compositions = list_all(
+        config,
+        "Compositions",
+        &[
+            ("RoomSid", room_sid.to_string()),
+            ("PageSize", PAGE_SIZE.to_string()),
+        ],
+        "compositions",
+    )
+    .await?;
+    Ok(RoomMedia {
+        room_sid: room_sid.to_string(),
+        recordings,
+        compositions,
+    })
+}
+
+/// A link to the media of a recording (`RT…`) or composition (`CJ…`), valid for
+/// [`MEDIA_LINK_TTL_SECONDS`].
+pub async fn media_link(config: &TwilioConfig, sid: &str) -> Result<MediaLink, TwilioError> {
+    let resource = match sid.get(..2) {
+        Some("RT") => "Recordings",
+        Some("CJ") => "Compositions",
+        _ => {
+            return Err(TwilioError::ValidationError(format!(
+                "'{}' is neither a recording (RT…) nor a composition (CJ…) SID",
+                sid
+            )))
+        }
+    };
+    let url = format!("{}/{}/{}/Media", TWILIO_VIDEO_API_BASE_URL, resource, sid);
+    let response = send(
+        authorized(config, MEDIA_CLIENT.get(&url))
+            .query(&[("Ttl", MEDIA_LINK_TTL_SECONDS.to_string())]),
+    )
+    .await?;
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.text().await?;
+    if !status.is_success() && !status.is_redirection() {
+        error!(
+            "[Twilio Video] Media of {} returned {}: {}",
+            sid, status, body
+        );
+        return Err(TwilioError::from_response(status, body));
+    }
+    let url = media_url(&headers, &body).ok_or_else(|| {
+        TwilioError::InternalError(format!("Twilio returned no media URL for {}", sid))
+    })?;
+    Ok(MediaLink {
+        sid: sid.to_string(),
+        url,
+        expires_in_seconds: MEDIA_LINK_TTL_SECONDS,
+    })
+}
+
+async fn delete(config: &TwilioConfig, resource: &str, sid: &str) -> Result<(), TwilioError> {
+    let url = format!("{}/{}/{}", TWILIO_VIDEO_API_BASE_URL, resource, sid);
+    let response = send(authorized(config, HTTP_CLIENT.delete(&url))).await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await?;
+        return Err(TwilioError::from_response(status, body));
+    }
+    Ok(())
+}
+
+/// Deletes the recordings and compositions created more than `retention_days` ago.
+///
+/// Failed deletions are logged and retried by the next run.
+pub async fn purge_expired_recordings(
+    config: &TwilioConfig,
+    retention_days: u32,
+    now: DateTime<Utc>,
+) -> Result<PurgedRecordings, TwilioError> {
+    let params = [
+        ("DateCreatedBefore", retention_cutoff(now, retention_days)),
+        ("PageSize", PAGE_SIZE.to_string()),
+    ];
+    let mut purged = PurgedRecordings::default();
+    // Compositions first, as Twilio keeps recordings that a pending composition still uses
+    let compositions: Vec<RoomComposition> =
+        list_all(config, "Compositions", &params, "compositions").await?;
+    for composition in compositions.iter().filter(|c| c.status != "deleted") {
+        match delete(config, "Compositions", &composition.sid).await {
+            Ok(()) => purged.compositions += 1,
+            Err(e) => warn!(
+                "[Twilio Video] Failed to delete composition {}: {}",
+                composition.sid, e
+            ),
+        }
+    }
+    let recordings: Vec<RoomRecording> =
+        list_all(config, "Recordings", &params, "recordings").await?;
+    for recording in recordings.iter().filter(|r| r.status != "deleted") {
+        match delete(config, "Recordings", &recording.sid).await {
+            Ok(()) => purged.recordings += 1,
+            Err(e) => warn!(
+                "[Twilio Video] Failed to delete recording {}: {}",
+                recording.sid, e
+            ),
+        }
+    }
+    Ok(purged)
+}
+
+/// Admin handler listing the completed video rooms.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/admin/twilio/rooms", // Path relative to /api
+    params(ListRoomsQuery),
+    responses(
+        (status = 200, description = "Completed rooms", body = Vec<VideoRoom>),
+        (status = 400, description = "Invalid filter"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 502, description = "Twilio API error")
+    ),
+    tag = "Twilio"
+))]
+pub async fn list_rooms_handler(
+    State(config): State<Arc<AppConfig>>,
+    Query(query): Query<ListRoomsQuery>,
+) -> Result<Json<Vec<VideoRoom>>, ConnectifyError> {
+    info!(
+        "[ADMIN] Listing completed Twilio rooms. Params: {:?}",
+        query
+    );
+    let twilio_config = twilio_config(&config)?;
+    Ok(Json(list_completed_rooms(twilio_config, &query).await?))
+}
+
+/// Admin handler listing the recordings and compositions of a room.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/admin/twilio/rooms/{room_sid}/recordings", // Path relative to /api
+    params(("room_sid" = String, Path, description = "The Twilio room SID")),
+    responses(
+        (status = 200, description = "Recordings and compositions of the room", body = RoomMedia),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Room not found"),
+        (status = 502, description = "Twilio API error")
+    ),
+    tag = "Twilio"
+))]
+pub async fn room_recordings_handler(
+    State(config): State<Arc<AppConfig>>,
+    Path(room_sid): Path<String>,
+) -> Result<Json<RoomMedia>, ConnectifyError> {
+    info!("[ADMIN] Listing recordings of Twilio room {}", room_sid);
+    let twilio_config = twilio_config(&config)?;
+    Ok(Json(room_media(twilio_config, &room_sid).await?))
+}
+
+/// Admin handler returning a short-lived link to the media of a recording or composition.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/admin/twilio/recordings/{sid}/media", // Path relative to /api
+    params(("sid" = String, Path, description = "A recording (RT…) or composition (CJ…) SID")),
+    responses(
+        (status = 200, description = "Link to the media, valid for 10 minutes", body = MediaLink),
+        (status = 400, description = "Neither a recording nor a composition SID"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "Recording not found"),
+        (status = 502, description = "Twilio API error")
+    ),
+    tag = "Twilio"
+))]
+pub async fn media_link_handler(
+    State(config): State<Arc<AppConfig>>,
+    actor: AuditActor,
+    Path(sid): Path<String>,
+) -> Result<Json<MediaLink>, ConnectifyError> {
+    let twilio_config = twilio_config(&config)?;
+
+    let result = media_link(twilio_config, &sid).await;
+    audit::record(
+        AuditEvent::new(actor, "recording.view", format!("twilio_recording:{}", sid))
+            .with_result(&result),
+    )
+    .await;
+    Ok(Json(result?))
+}
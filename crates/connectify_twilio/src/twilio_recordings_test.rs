@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use crate::twilio_recordings::{
+        media_url, page_items, retention_cutoff, RoomComposition, RoomRecording,
+    };
+    use chrono::{TimeZone, Utc};
+    use reqwest::header::{HeaderMap, HeaderValue, LOCATION};
+    use serde_json::json;
+
+    #[test]
+    fn test_page_items() {
+        let page = json!({
+            "recordings": [{
+                "sid": "RT123",
+                "status": "completed",
+                "type": "audio",
+                "track_name": "mic",
+                "duration": 1800,
+                "size": 4096,
+                "container": "mka",
+                "date_created": "2025-05-15T10:00:00Z",
+                "grouping_sids": { "room_sid": "RM123" }
+            }],
+            "meta": {
+                "page": 0,
+                "next_page_url": "https://video.twilio.com/v1/Recordings?PageToken=PT1"
+            }
+        });
+        let (recordings, next): (Vec<RoomRecording>, _) = page_items(page, "recordings").unwrap();
+        assert_eq!(recordings.len(), 1);
+        assert_eq!(recordings[0].kind.as_deref(), Some("audio"));
+        assert_eq!(recordings[0].duration, Some(1800));
+        assert_eq!(
+            next.as_deref(),
+            Some("https://video.twilio.com/v1/Recordings?PageToken=PT1")
+        );
+
+        // The last page has no next page
+        let page = json!({ "compositions": [], "meta": { "next_page_url": null } });
+        let (compositions, next): (Vec<RoomComposition>, _) =
+            page_items(page, "compositions").unwrap();
+        assert!(compositions.is_empty());
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn test_media_url() {
+        let body =
+            json!({ "redirect_to": "https://media.twilio.com/signed?Expires=1" }).to_string();
+        assert_eq!(
+            media_url(&HeaderMap::new(), &body).as_deref(),
+            Some("https://media.twilio.com/signed?Expires=1")
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            LOCATION,
+            HeaderValue::from_static("https://media.twilio.com/signed?Expires=2"),
+        );
+        assert_eq!(
+            media_url(&headers, "").as_deref(),
+            Some("https://media.twilio.com/signed?Expires=2")
+        );
+        assert_eq!(media_url(&HeaderMap::new(), ""), None);
+    }
+
+    #[test]
+    fn test_retention_cutoff() {
+        let now = Utc.with_ymd_and_hms(2025, 7, 31, 3, 30, 0).unwrap();
+        assert_eq!(retention_cutoff(now, 30), "2025-07-01T03:30:00Z");
+    }
+}
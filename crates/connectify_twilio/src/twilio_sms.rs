@@ -1,13 +1,36 @@
 // --- File: crates/connectify_twilio/src/twilio_sms.rs ---
-use axum::{extract::State, http::StatusCode, response::Json};
-use reqwest::Client;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json},
+    Form,
+};
+use chrono::{DateTime, Utc};
+use connectify_common::message_status::{
+    message_status_store, MessageStatusRecord, MessageStatusStore,
+};
+use connectify_common::request_id::RequestIdExt;
+use connectify_common::sms_consent::{is_opted_out, sms_consent_store};
+use connectify_common::{ConnectifyError, HTTP_CLIENT};
 use std::sync::Arc;
 
-use connectify_config::AppConfig;
+use connectify_config::{AppConfig, TwilioConfig};
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tracing::{info, warn};
 
-#[derive(Deserialize, Debug)]
+use crate::service::{send_twilio_request, TwilioError};
+use crate::twilio_sms_country::{check_sending_hours, sms_country, with_opt_out_text};
+
+/// Path of the message status callback, relative to `status_callback_base_url`.
+pub const SMS_STATUS_CALLBACK_PATH: &str = "/twilio/messages/status";
+
+/// Sender of the messages if no Messaging Service is configured.
+const SMS_FROM: &str = "whatsapp:+14155238886";
+
+/// Most messages listed per recipient if the query sets no limit.
+const DEFAULT_MESSAGE_LIMIT: usize = 20;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "openapi", derive(utoipa::IntoParams, utoipa::ToSchema))]
 pub struct SmsRequest {
     pub to: String,
@@ -19,6 +42,138 @@ pub struct SmsRequest {
 pub struct SmsResponse {
     pub success: bool,
     pub message: String,
+    /// The Twilio message SID, to look up the delivery status
+    pub message_sid: Option<String>,
+}
+
+/// A message as returned by the Messages API.
+#[derive(Deserialize, Debug)]
+struct TwilioMessage {
+    sid: String,
+    status: String,
+}
+
+/// Form fields of a message status callback, see
+/// <https://www.twilio.com/docs/messaging/guides/track-outbound-message-status>.
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "PascalCase")]
+pub struct MessageStatusCallback {
+    pub message_sid: String,
+    /// e.g. `sent`, `delivered`, `undelivered` or `failed`
+    pub message_status: String,
+    pub to: Option<String>,
+    pub from: Option<String>,
+    /// Twilio error code of undelivered and failed messages, e.g. `30003`
+    pub error_code: Option<String>,
+}
+
+/// The delivery status of a sent message.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MessageStatusResponse {
+    #[cfg_attr(
+        feature = "openapi",
+        schema(example = "SM0123456789abcdef0123456789abcdef")
+    )]
+    pub message_sid: String,
+    #[cfg_attr(feature = "openapi", schema(example = "whatsapp:+41791234567"))]
+    pub to: String,
+    #[cfg_attr(feature = "openapi", schema(example = "undelivered"))]
+    pub status: String,
+    /// See <https://www.twilio.com/docs/api/errors>
+    #[cfg_attr(feature = "openapi", schema(example = "30003"))]
+    pub error_code: Option<String>,
+    /// When the message was sent, in RFC 3339 format
+    pub created_at: String,
+    /// When the status was last reported, in RFC 3339 format
+    pub updated_at: String,
+}
+
+impl From<MessageStatusRecord> for MessageStatusResponse {
+    fn from(record: MessageStatusRecord) -> Self {
+        Self {
+            message_sid: record.message_sid,
+            to: record.to,
+            status: record.status,
+            error_code: record.error_code,
+            created_at: record.created_at.to_rfc3339(),
+            updated_at: record.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Query of the messages sent to a number.
+#[derive(Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+pub struct ListMessagesQuery {
+    /// The recipient, as the message was sent to, e.g. `whatsapp:+41791234567`
+    pub to: String,
+    /// Most messages returned, newest first (default 20)
+    pub limit: Option<usize>,
+}
+
+/// The form parameters sending a message, from the sender of the recipient's country or
+/// through the configured Messaging Service if any, with the country's opt-out text.
+pub fn sms_params(config: &TwilioConfig, request: &SmsRequest) -> Vec<(&'static str, String)> {
+    let country = sms_country(config, &request.to);
+    let sender = match (
+        country.and_then(|country| country.from.as_deref()),
+        config.messaging_service_sid.as_deref(),
+    ) {
+        (Some(from), _) => ("From", from.to_string()),
+        (None, Some(service_sid)) => ("MessagingServiceSid", service_sid.to_string()),
+        (None, None) => ("From", SMS_FROM.to_string()),
+    };
+    let body = match country {
+        Some(country) => with_opt_out_text(&request.message, country),
+        None => request.message.clone(),
+    };
+    let mut params = vec![("Body", body), sender, ("To", request.to.clone())];
+    if let Some(base_url) = config.status_callback_base_url.as_deref() {
+        params.push((
+            "StatusCallback",
+            format!(
+                "{}{}",
+                base_url.trim_end_matches('/'),
+                SMS_STATUS_CALLBACK_PATH
+            ),
+        ));
+    }
+    params
+}
+
+/// Records a reported message status. Messages sent before statuses were recorded get a record
+/// on their first callback.
+///
+/// # Returns
+///
+/// Whether the status was applied, see [`MessageStatusRecord::apply_status`].
+pub async fn record_message_status(
+    store: &dyn MessageStatusStore,
+    callback: &MessageStatusCallback,
+    now: DateTime<Utc>,
+) -> Result<bool, ConnectifyError> {
+    let error_code = callback.error_code.clone().filter(|code| !code.is_empty());
+    let record = match store.get(&callback.message_sid).await? {
+        Some(mut record) => {
+            if !record.apply_status(&callback.message_status, error_code, now) {
+                return Ok(false);
+            }
+            record
+        }
+        None => MessageStatusRecord {
+            error_code,
+            ..MessageStatusRecord::sent(
+                &callback.message_sid,
+                callback.to.as_deref().unwrap_or_default(),
+                &callback.message_status,
+                now,
+            )
+        },
+    };
+    store.save(record).await?;
+    Ok(true)
 }
 
 pub async fn send_sms(
@@ -30,30 +185,42 @@ pub async fn send_sms(
         return Err((StatusCode::SERVICE_UNAVAILABLE, "Twilio disabled".into()));
     }
 
+    deliver_sms(twilio_config, &request)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Sends an SMS through the Twilio Messages API and records its initial status. Numbers that
+/// opted out of SMS are not sent any, nor are numbers outside their country's sending hours.
+pub async fn deliver_sms(
+    twilio_config: &TwilioConfig,
+    request: &SmsRequest,
+) -> Result<SmsResponse, TwilioError> {
+    let opted_out = is_opted_out(&*sms_consent_store(), &request.to)
+        .await
+        .map_err(|e| TwilioError::InternalError(format!("Cannot check SMS consent: {}", e)))?;
+    if opted_out {
+        info!("Not sending SMS to {}, who opted out", request.to);
+        return Err(TwilioError::OptedOut(request.to.clone()));
+    }
+    check_sending_hours(twilio_config, &request.to, Utc::now())?;
     let url = format!(
         "https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json",
         twilio_config.account_sid
     );
-    let params = [
-        ("Body", request.message.as_str()),
-        ("From", "whatsapp:+14155238886"),
-        ("To", request.to.as_str()),
-    ];
-    // Use lowercase keys
+    let params = sms_params(twilio_config, request);
     info!("Sending SMS to {}: {}", &request.to, &request.message);
-    let resp = Client::new()
-        // 👇 **account_sid** + **auth_token** here
-        .post(&url)
-        .basic_auth(&twilio_config.account_sid, Some(&twilio_config.auth_token))
-        .form(&params)
-        .send()
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("HTTP error sending SMS: {}", e),
-            )
-        })?;
+    let resp = send_twilio_request(
+        HTTP_CLIENT
+            // 👇 **account_sid** + **auth_token** here
+            .post(&url)
+            .with_request_id()
+            .basic_auth(&twilio_config.account_sid, Some(&twilio_config.auth_token))
+            .form(&params),
+        "sending SMS",
+    )
+    .await?;
 
     let status = resp.status();
     let body = resp.text().await.unwrap_or_default();
@@ -61,15 +228,120 @@ pub async fn send_sms(
     if !status.is_success() {
         // Bubble up the Twilio JSON error so you can debug
         tracing::error!("Twilio returned {}: {}", status, body);
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Twilio error {}: {}", status, body),
-        ));
+        return Err(TwilioError::from_response(status, body));
     }
 
     tracing::info!("SMS sent to {}: {}", request.to, request.message);
-    Ok(Json(SmsResponse {
+    let message_sid = match serde_json::from_str::<TwilioMessage>(&body) {
+        Ok(message) => {
+            let record =
+                MessageStatusRecord::sent(&message.sid, &request.to, message.status, Utc::now());
+            if let Err(e) = message_status_store().save(record).await {
+                warn!("Failed to record status of message {}: {}", message.sid, e);
+            }
+            Some(message.sid)
+        }
+        Err(e) => {
+            warn!("Failed to parse message sent to {}: {}", request.to, e);
+            None
+        }
+    };
+    Ok(SmsResponse {
         success: true,
         message: "SMS sent successfully".into(),
-    }))
+        message_sid,
+    })
+}
+
+/// Handler of the status callbacks Twilio sends while a message is delivered.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/twilio/messages/status", // Path relative to /api
+    request_body(content = MessageStatusCallback, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 204, description = "Status recorded"),
+        (status = 401, description = "Missing or invalid X-Twilio-Signature"),
+        (status = 500, description = "The status could not be stored")
+    ),
+    tag = "Twilio"
+))]
+pub async fn message_status_callback_handler(
+    Form(callback): Form<MessageStatusCallback>,
+) -> Result<impl IntoResponse, ConnectifyError> {
+    match callback.message_status.as_str() {
+        "undelivered" | "failed" => warn!(
+            "[Twilio SMS] Message {} to {} is {} (error {})",
+            callback.message_sid,
+            callback.to.as_deref().unwrap_or("unknown"),
+            callback.message_status,
+            callback.error_code.as_deref().unwrap_or("unknown")
+        ),
+        status => info!(
+            "[Twilio SMS] Message {} is {}",
+            callback.message_sid, status
+        ),
+    }
+    if !record_message_status(message_status_store().as_ref(), &callback, Utc::now()).await? {
+        info!(
+            "[Twilio SMS] Ignored late status {} of message {}",
+            callback.message_status, callback.message_sid
+        );
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Admin handler returning the delivery status of a message.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/admin/twilio/messages/{message_sid}", // Path relative to /api
+    params(("message_sid" = String, Path, description = "The Twilio message SID")),
+    responses(
+        (status = 200, description = "Delivery status of the message", body = MessageStatusResponse),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 404, description = "No status recorded for the message")
+    ),
+    tag = "Twilio"
+))]
+pub async fn message_status_handler(
+    Path(message_sid): Path<String>,
+) -> Result<Json<MessageStatusResponse>, ConnectifyError> {
+    message_status_store()
+        .get(&message_sid)
+        .await?
+        .map(|record| Json(record.into()))
+        .ok_or_else(|| {
+            ConnectifyError::NotFoundError(format!(
+                "No status recorded for message {}",
+                message_sid
+            ))
+        })
+}
+
+/// Admin handler listing the delivery statuses of the latest messages to a number, e.g. to
+/// troubleshoot a client not receiving their SMS.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/admin/twilio/messages", // Path relative to /api
+    params(ListMessagesQuery),
+    responses(
+        (status = 200, description = "Delivery statuses, newest first", body = [MessageStatusResponse]),
+        (status = 401, description = "Missing or invalid admin API key")
+    ),
+    tag = "Twilio"
+))]
+pub async fn list_message_statuses_handler(
+    Query(query): Query<ListMessagesQuery>,
+) -> Result<Json<Vec<MessageStatusResponse>>, ConnectifyError> {
+    let limit = query.limit.unwrap_or(DEFAULT_MESSAGE_LIMIT);
+    let records = message_status_store()
+        .list_for_recipient(&query.to, limit)
+        .await?;
+    Ok(Json(records.into_iter().map(Into::into).collect()))
 }
@@ -0,0 +1,101 @@
+// --- File: crates/connectify_twilio/src/twilio_sms_country.rs ---
+//! Per-country compliance rules of outgoing SMS.
+//!
+//! Countries regulate SMS differently: some require a registered alphanumeric sender ID or a
+//! local long code, some forbid marketing SMS at night, some require opt-out instructions in
+//! every message. `sms_countries` configures these per calling code, and [`deliver_sms`] applies
+//! the rules of the recipient's country to each SMS: its sender overrides the default one, its
+//! opt-out text is appended to the body, and SMS outside its sending hours fail with
+//! [`TwilioError::OutsideSendingHours`], so the queue worker delivers them again later.
+//!
+//! Recipients addressed through a channel, e.g. `whatsapp:+41791234567`, aren't sent SMS, so
+//! no rules apply to them.
+//!
+//! [`deliver_sms`]: crate::twilio_sms::deliver_sms
+use chrono::{DateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use connectify_config::{SmsCountryConfig, TwilioConfig};
+
+use crate::service::TwilioError;
+
+/// The rules of the country an SMS recipient's number belongs to, by the longest configured
+/// calling code the number starts with.
+pub fn sms_country<'a>(config: &'a TwilioConfig, to: &str) -> Option<&'a SmsCountryConfig> {
+    if to.contains(':') {
+        return None;
+    }
+    config
+        .sms_countries
+        .iter()
+        .filter(|(calling_code, _)| to.starts_with(calling_code.as_str()))
+        .max_by_key(|(calling_code, _)| calling_code.len())
+        .map(|(_, country)| country)
+}
+
+/// The message with the country's opt-out text appended, unless it already contains it,
+/// ignoring case.
+pub fn with_opt_out_text(message: &str, country: &SmsCountryConfig) -> String {
+    match country.opt_out_text.as_deref().map(str::trim) {
+        Some(text)
+            if !text.is_empty() && !message.to_lowercase().contains(&text.to_lowercase()) =>
+        {
+            format!("{}\n{}", message.trim_end(), text)
+        }
+        _ => message.to_string(),
+    }
+}
+
+fn parse_time(name: &str, value: &str) -> Result<NaiveTime, TwilioError> {
+    NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| {
+        TwilioError::ValidationError(format!(
+            "sms_countries {} must be a time in HH:MM format, got '{}'",
+            name, value
+        ))
+    })
+}
+
+/// Whether SMS may be sent to the country at a time. Without both `send_from` and
+/// `send_until`, they may be sent at any time.
+pub fn within_sending_hours(
+    country: &SmsCountryConfig,
+    now: DateTime<Utc>,
+) -> Result<bool, TwilioError> {
+    let (Some(send_from), Some(send_until)) = (&country.send_from, &country.send_until) else {
+        return Ok(true);
+    };
+    let from = parse_time("send_from", send_from)?;
+    let until = parse_time("send_until", send_until)?;
+    let local = match country.time_zone.as_deref() {
+        Some(time_zone) => {
+            let tz: Tz = time_zone.parse().map_err(|_| {
+                TwilioError::ValidationError(format!(
+                    "sms_countries time_zone '{}' is not a known time zone",
+                    time_zone
+                ))
+            })?;
+            now.with_timezone(&tz).time()
+        }
+        None => now.time(),
+    };
+    Ok(if from <= until {
+        from <= local && local < until
+    } else {
+        // The hours span midnight, e.g. 20:00 to 02:00
+        local >= from || local < until
+    })
+}
+
+/// Fails with [`TwilioError::OutsideSendingHours`] if SMS may not be sent to the recipient's
+/// country at a time.
+pub fn check_sending_hours(
+    config: &TwilioConfig,
+    to: &str,
+    now: DateTime<Utc>,
+) -> Result<(), TwilioError> {
+    match sms_country(config, to) {
+        Some(country) if !within_sending_hours(country, now)? => {
+            Err(TwilioError::OutsideSendingHours(to.to_string()))
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,157 @@
+#[cfg(test)]
+mod tests {
+    use crate::service::TwilioError;
+    use crate::test_support;
+    use crate::twilio_sms::{sms_params, SmsRequest};
+    use crate::twilio_sms_country::{
+        check_sending_hours, sms_country, with_opt_out_text, within_sending_hours,
+    };
+    use crate::twilio_sms_queue::SmsOutcome;
+    use chrono::{DateTime, Utc};
+    use connectify_config::{SmsCountryConfig, TwilioConfig};
+
+    fn swiss() -> SmsCountryConfig {
+        SmsCountryConfig {
+            from: Some("Connectify".to_string()),
+            time_zone: Some("Europe/Zurich".to_string()),
+            send_from: Some("08:00".to_string()),
+            send_until: Some("21:00".to_string()),
+            opt_out_text: None,
+        }
+    }
+
+    fn us() -> SmsCountryConfig {
+        SmsCountryConfig {
+            from: Some("+12025550123".to_string()),
+            opt_out_text: Some("Reply STOP to opt out".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn twilio_config() -> TwilioConfig {
+        TwilioConfig {
+            messaging_service_sid: Some("MG123".to_string()),
+            sms_countries: [
+                ("+1".to_string(), us()),
+                ("+41".to_string(), swiss()),
+                (
+                    "+4179".to_string(),
+                    SmsCountryConfig {
+                        from: Some("+41790000000".to_string()),
+                        ..swiss()
+                    },
+                ),
+            ]
+            .into_iter()
+            .collect(),
+            ..test_support::twilio_config()
+        }
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_sms_country_longest_prefix() {
+        let config = twilio_config();
+        assert_eq!(sms_country(&config, "+12125550199"), Some(&us()));
+        assert_eq!(
+            sms_country(&config, "+41441234567").and_then(|c| c.from.as_deref()),
+            Some("Connectify")
+        );
+        assert_eq!(
+            sms_country(&config, "+41791234567").and_then(|c| c.from.as_deref()),
+            Some("+41790000000")
+        );
+        assert_eq!(sms_country(&config, "+49301234567"), None);
+        // WhatsApp messages aren't SMS
+        assert_eq!(sms_country(&config, "whatsapp:+12125550199"), None);
+    }
+
+    #[test]
+    fn test_with_opt_out_text() {
+        let country = us();
+        assert_eq!(
+            with_opt_out_text("Your session starts at 10:00. ", &country),
+            "Your session starts at 10:00.\nReply STOP to opt out"
+        );
+        assert_eq!(
+            with_opt_out_text("Reminder. reply stop to opt out", &country),
+            "Reminder. reply stop to opt out"
+        );
+        assert_eq!(with_opt_out_text("Reminder", &swiss()), "Reminder");
+    }
+
+    #[test]
+    fn test_within_sending_hours() {
+        let country = swiss();
+        // 07:30 UTC is 09:30 in Zurich in summer
+        assert!(within_sending_hours(&country, at("2025-07-01T07:30:00Z")).unwrap());
+        // 05:30 UTC is 07:30 in Zurich
+        assert!(!within_sending_hours(&country, at("2025-07-01T05:30:00Z")).unwrap());
+        // 19:00 UTC is 21:00 in Zurich, the end is exclusive
+        assert!(!within_sending_hours(&country, at("2025-07-01T19:00:00Z")).unwrap());
+        // Without hours, SMS may always be sent
+        assert!(within_sending_hours(&us(), at("2025-07-01T03:00:00Z")).unwrap());
+
+        let night = SmsCountryConfig {
+            time_zone: None,
+            send_from: Some("20:00".to_string()),
+            send_until: Some("02:00".to_string()),
+            ..Default::default()
+        };
+        assert!(within_sending_hours(&night, at("2025-07-01T23:00:00Z")).unwrap());
+        assert!(within_sending_hours(&night, at("2025-07-01T01:00:00Z")).unwrap());
+        assert!(!within_sending_hours(&night, at("2025-07-01T12:00:00Z")).unwrap());
+
+        let invalid = SmsCountryConfig {
+            time_zone: Some("Mars/Olympus".to_string()),
+            ..swiss()
+        };
+        assert!(matches!(
+            within_sending_hours(&invalid, at("2025-07-01T07:30:00Z")),
+            Err(TwilioError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_sending_hours() {
+        let config = twilio_config();
+        let night = at("2025-07-01T23:00:00Z");
+        let result = check_sending_hours(&config, "+41791234567", night);
+        assert!(matches!(result, Err(TwilioError::OutsideSendingHours(_))));
+        // The queue delivers it again later
+        assert_eq!(SmsOutcome::of(&result), SmsOutcome::Retry);
+        assert!(check_sending_hours(&config, "+12125550199", night).is_ok());
+        assert!(check_sending_hours(&config, "+49301234567", night).is_ok());
+    }
+
+    #[test]
+    fn test_sms_params_per_country() {
+        let config = twilio_config();
+        let request = |to: &str| SmsRequest {
+            to: to.to_string(),
+            message: "Your session starts at 10:00.".to_string(),
+        };
+
+        let params = sms_params(&config, &request("+12125550199"));
+        assert!(params.contains(&("From", "+12025550123".to_string())));
+        assert!(!params
+            .iter()
+            .any(|(name, _)| *name == "MessagingServiceSid"));
+        assert!(params.contains(&(
+            "Body",
+            "Your session starts at 10:00.\nReply STOP to opt out".to_string()
+        )));
+
+        let params = sms_params(&config, &request("+41441234567"));
+        assert!(params.contains(&("From", "Connectify".to_string())));
+        assert!(params.contains(&("Body", "Your session starts at 10:00.".to_string())));
+
+        // Countries without rules are sent through the Messaging Service
+        let params = sms_params(&config, &request("+49301234567"));
+        assert!(params.contains(&("MessagingServiceSid", "MG123".to_string())));
+        assert!(!params.iter().any(|(name, _)| *name == "From"));
+    }
+}
@@ -0,0 +1,324 @@
+// --- File: crates/connectify_twilio/src/twilio_sms_inbound.rs ---
+//! SMS received on our numbers, and the opt-out keywords of their senders.
+//!
+//! Twilio posts each SMS received to the incoming message webhook of the number or Messaging
+//! Service, `/twilio/messages/inbound`. The SMS is stored, and a body that is just a keyword
+//! changes the sender's consent: `STOP` and its synonyms opt them out of further SMS, `START`
+//! and its synonyms opt them in again, and `HELP` is answered with the `sms_help` template.
+//! With Twilio's Advanced Opt-Out, the keyword Twilio matched is reported as `OptOutType` and
+//! takes precedence.
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Json},
+    Form,
+};
+use chrono::{DateTime, Utc};
+use connectify_common::sms_consent::{
+    sms_consent_store, InboundSmsRecord, SmsConsentRecord, SmsConsentStore,
+};
+use connectify_common::ConnectifyError;
+use connectify_config::AppConfig;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::twilio_sms_templates::{SmsTemplates, SMS_HELP};
+use crate::twilio_voice::escape_xml;
+
+/// Keywords opting the sender out of SMS, as recognized by Twilio.
+pub const OPT_OUT_KEYWORDS: [&str; 8] = [
+    "STOP",
+    "STOPALL",
+    "UNSUBSCRIBE",
+    "CANCEL",
+    "END",
+    "QUIT",
+    "OPTOUT",
+    "REVOKE",
+];
+
+/// Keywords opting the sender in to SMS again.
+pub const OPT_IN_KEYWORDS: [&str; 4] = ["START", "YES", "UNSTOP", "OPTIN"];
+
+/// Keywords asking for help.
+pub const HELP_KEYWORDS: [&str; 2] = ["HELP", "INFO"];
+
+/// Most replies listed per sender if the query sets no limit.
+const DEFAULT_REPLY_LIMIT: usize = 20;
+
+/// A keyword of a received SMS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmsKeyword {
+    OptOut,
+    OptIn,
+    Help,
+}
+
+/// A body as a keyword: in upper case, without surrounding whitespace and punctuation.
+fn keyword_word(body: &str) -> String {
+    body.trim()
+        .trim_end_matches(['.', '!'])
+        .trim()
+        .to_uppercase()
+}
+
+impl SmsKeyword {
+    /// The keyword a body consists of, ignoring case, surrounding whitespace and punctuation.
+    pub fn parse(body: &str) -> Option<Self> {
+        let word = keyword_word(body);
+        if OPT_OUT_KEYWORDS.contains(&word.as_str()) {
+            Some(SmsKeyword::OptOut)
+        } else if OPT_IN_KEYWORDS.contains(&word.as_str()) {
+            Some(SmsKeyword::OptIn)
+        } else if HELP_KEYWORDS.contains(&word.as_str()) {
+            Some(SmsKeyword::Help)
+        } else {
+            None
+        }
+    }
+
+    /// The keyword of Twilio's `OptOutType`: `STOP`, `START` or `HELP`.
+    pub fn from_opt_out_type(opt_out_type: &str) -> Option<Self> {
+        match opt_out_type.to_uppercase().as_str() {
+            "STOP" => Some(SmsKeyword::OptOut),
+            "START" => Some(SmsKeyword::OptIn),
+            "HELP" => Some(SmsKeyword::Help),
+            _ => None,
+        }
+    }
+}
+
+/// Form fields of an incoming message webhook, see
+/// <https://www.twilio.com/docs/messaging/guides/webhook-request>.
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "PascalCase")]
+pub struct InboundSmsWebhook {
+    pub message_sid: String,
+    /// The sender's number
+    pub from: String,
+    /// Our number the SMS was sent to
+    pub to: String,
+    #[serde(default)]
+    pub body: String,
+    /// The keyword matched by Twilio's Advanced Opt-Out: `STOP`, `START` or `HELP`
+    pub opt_out_type: Option<String>,
+}
+
+/// An SMS received from a number.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct InboundSmsResponse {
+    #[cfg_attr(
+        feature = "openapi",
+        schema(example = "SM0123456789abcdef0123456789abcdef")
+    )]
+    pub message_sid: String,
+    #[cfg_attr(feature = "openapi", schema(example = "+41791234567"))]
+    pub from: String,
+    pub to: String,
+    #[cfg_attr(feature = "openapi", schema(example = "Can we move it to 11:00?"))]
+    pub body: String,
+    /// When the SMS was received, in RFC 3339 format
+    pub received_at: String,
+}
+
+impl From<InboundSmsRecord> for InboundSmsResponse {
+    fn from(record: InboundSmsRecord) -> Self {
+        Self {
+            message_sid: record.message_sid,
+            from: record.from,
+            to: record.to,
+            body: record.body,
+            received_at: record.received_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Whether a number may be sent SMS.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SmsConsentResponse {
+    #[cfg_attr(feature = "openapi", schema(example = "+41791234567"))]
+    pub phone: String,
+    pub opted_out: bool,
+    /// The keyword the consent was last changed with, none if it never was
+    #[cfg_attr(feature = "openapi", schema(example = "STOP"))]
+    pub keyword: Option<String>,
+    /// When the consent was last changed, in RFC 3339 format
+    pub updated_at: Option<String>,
+}
+
+/// Query of the SMS received from a number.
+#[derive(Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+pub struct ListRepliesQuery {
+    /// The sender, e.g. `+41791234567`
+    pub from: String,
+    /// Most SMS returned, newest first (default 20)
+    pub limit: Option<usize>,
+}
+
+/// Query of the consent of a number.
+#[derive(Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+pub struct SmsConsentQuery {
+    /// The number, as SMS are sent to it, e.g. `+41791234567`
+    pub phone: String,
+}
+
+/// Stores a received SMS and applies its keyword to the sender's consent.
+///
+/// # Returns
+///
+/// The keyword of the SMS, if it is one.
+pub async fn receive_sms(
+    store: &dyn SmsConsentStore,
+    webhook: &InboundSmsWebhook,
+    now: DateTime<Utc>,
+) -> Result<Option<SmsKeyword>, ConnectifyError> {
+    store
+        .save_message(InboundSmsRecord {
+            message_sid: webhook.message_sid.clone(),
+            from: webhook.from.clone(),
+            to: webhook.to.clone(),
+            body: webhook.body.clone(),
+            received_at: now,
+        })
+        .await?;
+
+    let keyword = match webhook.opt_out_type.as_deref() {
+        Some(opt_out_type) => SmsKeyword::from_opt_out_type(opt_out_type),
+        None => SmsKeyword::parse(&webhook.body),
+    };
+    let opted_out = match keyword {
+        Some(SmsKeyword::OptOut) => true,
+        Some(SmsKeyword::OptIn) => false,
+        _ => return Ok(keyword),
+    };
+    store
+        .save_consent(SmsConsentRecord {
+            phone: webhook.from.clone(),
+            opted_out,
+            keyword: keyword_word(webhook.opt_out_type.as_deref().unwrap_or(&webhook.body)),
+            updated_at: now,
+        })
+        .await?;
+    if opted_out {
+        info!("[Twilio SMS] {} opted out of SMS", webhook.from);
+    } else {
+        info!("[Twilio SMS] {} opted in to SMS again", webhook.from);
+    }
+    Ok(keyword)
+}
+
+/// TwiML answering an incoming SMS, with a reply if any.
+pub fn reply_twiml(reply: Option<&str>) -> String {
+    match reply {
+        Some(reply) => format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><Response><Message>{}</Message></Response>"#,
+            escape_xml(reply)
+        ),
+        None => r#"<?xml version="1.0" encoding="UTF-8"?><Response/>"#.to_string(),
+    }
+}
+
+/// Handler of the SMS Twilio receives on our numbers.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/twilio/messages/inbound", // Path relative to /api
+    request_body(content = InboundSmsWebhook, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "TwiML, replying to HELP", content_type = "text/xml"),
+        (status = 401, description = "Missing or invalid X-Twilio-Signature"),
+        (status = 500, description = "The SMS could not be stored")
+    ),
+    tag = "Twilio"
+))]
+pub async fn inbound_sms_handler(
+    State(config): State<Arc<AppConfig>>,
+    Form(webhook): Form<InboundSmsWebhook>,
+) -> Result<impl IntoResponse, ConnectifyError> {
+    info!(
+        "[Twilio SMS] Received SMS {} from {}",
+        webhook.message_sid, webhook.from
+    );
+    let keyword = receive_sms(&*sms_consent_store(), &webhook, Utc::now()).await?;
+
+    let reply = match (keyword, config.twilio.as_ref()) {
+        (Some(SmsKeyword::Help), Some(twilio_config)) => {
+            match SmsTemplates::from_config(twilio_config).render(SMS_HELP, &[]) {
+                Ok(sms) => Some(sms.body),
+                Err(e) => {
+                    warn!("[Twilio SMS] Cannot reply to HELP: {}", e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    Ok((
+        [(header::CONTENT_TYPE, "text/xml")],
+        reply_twiml(reply.as_deref()),
+    ))
+}
+
+/// Admin handler listing the latest SMS received from a number, e.g. replies to reminders.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/admin/twilio/replies", // Path relative to /api
+    params(ListRepliesQuery),
+    responses(
+        (status = 200, description = "Received SMS, newest first", body = [InboundSmsResponse]),
+        (status = 401, description = "Missing or invalid admin API key")
+    ),
+    tag = "Twilio"
+))]
+pub async fn list_replies_handler(
+    Query(query): Query<ListRepliesQuery>,
+) -> Result<Json<Vec<InboundSmsResponse>>, ConnectifyError> {
+    let limit = query.limit.unwrap_or(DEFAULT_REPLY_LIMIT);
+    let records = sms_consent_store()
+        .list_messages(&query.from, limit)
+        .await?;
+    Ok(Json(records.into_iter().map(Into::into).collect()))
+}
+
+/// Admin handler returning whether a number opted out of SMS.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/admin/twilio/consent", // Path relative to /api
+    params(SmsConsentQuery),
+    responses(
+        (status = 200, description = "Consent of the number", body = SmsConsentResponse),
+        (status = 401, description = "Missing or invalid admin API key")
+    ),
+    tag = "Twilio"
+))]
+pub async fn sms_consent_handler(
+    Query(query): Query<SmsConsentQuery>,
+) -> Result<Json<SmsConsentResponse>, ConnectifyError> {
+    let consent = sms_consent_store().get_consent(&query.phone).await?;
+    Ok(Json(match consent {
+        Some(consent) => SmsConsentResponse {
+            phone: consent.phone,
+            opted_out: consent.opted_out,
+            keyword: Some(consent.keyword),
+            updated_at: Some(consent.updated_at.to_rfc3339()),
+        },
+        None => SmsConsentResponse {
+            phone: query.phone,
+            opted_out: false,
+            keyword: None,
+            updated_at: None,
+        },
+    }))
+}
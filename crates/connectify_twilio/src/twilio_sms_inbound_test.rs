@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use crate::service::TwilioError;
+    use crate::test_support;
+    use crate::twilio_sms::{deliver_sms, SmsRequest};
+    use crate::twilio_sms_inbound::{receive_sms, reply_twiml, InboundSmsWebhook, SmsKeyword};
+    use chrono::DateTime;
+    use connectify_common::sms_consent::{
+        is_opted_out, sms_consent_store, InMemorySmsConsentStore, SmsConsentRecord, SmsConsentStore,
+    };
+
+    fn webhook(sid: &str, body: &str, opt_out_type: Option<&str>) -> InboundSmsWebhook {
+        InboundSmsWebhook {
+            message_sid: sid.to_string(),
+            from: "+41791234567".to_string(),
+            to: "+41790000000".to_string(),
+            body: body.to_string(),
+            opt_out_type: opt_out_type.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_sms_keyword() {
+        assert_eq!(SmsKeyword::parse(" stop "), Some(SmsKeyword::OptOut));
+        assert_eq!(SmsKeyword::parse("Unsubscribe."), Some(SmsKeyword::OptOut));
+        assert_eq!(SmsKeyword::parse("START!"), Some(SmsKeyword::OptIn));
+        assert_eq!(SmsKeyword::parse("help"), Some(SmsKeyword::Help));
+        // Only a body consisting of the keyword counts
+        assert_eq!(SmsKeyword::parse("Please stop the reminders"), None);
+        assert_eq!(
+            SmsKeyword::from_opt_out_type("START"),
+            Some(SmsKeyword::OptIn)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_receive_sms() {
+        let store = InMemorySmsConsentStore::default();
+        let at = |second| DateTime::from_timestamp(second, 0).unwrap();
+
+        let keyword = receive_sms(&store, &webhook("SM1", "Can we move it?", None), at(0))
+            .await
+            .unwrap();
+        assert_eq!(keyword, None);
+        assert!(!is_opted_out(&store, "+41791234567").await.unwrap());
+
+        receive_sms(&store, &webhook("SM2", "Stop", None), at(1))
+            .await
+            .unwrap();
+        assert!(is_opted_out(&store, "+41791234567").await.unwrap());
+        let consent = store.get_consent("+41791234567").await.unwrap().unwrap();
+        assert_eq!(consent.keyword, "STOP");
+
+        // HELP doesn't change the consent
+        let keyword = receive_sms(&store, &webhook("SM3", "HELP", None), at(2))
+            .await
+            .unwrap();
+        assert_eq!(keyword, Some(SmsKeyword::Help));
+        assert!(is_opted_out(&store, "+41791234567").await.unwrap());
+
+        // Twilio's Advanced Opt-Out matched a custom keyword
+        receive_sms(&store, &webhook("SM4", "Weiter", Some("START")), at(3))
+            .await
+            .unwrap();
+        assert!(!is_opted_out(&store, "+41791234567").await.unwrap());
+
+        let replies = store.list_messages("+41791234567", 10).await.unwrap();
+        assert_eq!(replies.len(), 4);
+        assert_eq!(replies[3].body, "Can we move it?");
+    }
+
+    #[test]
+    fn test_reply_twiml() {
+        assert_eq!(
+            reply_twiml(Some("Reply STOP <to> stop")),
+            r#"<?xml version="1.0" encoding="UTF-8"?><Response><Message>Reply STOP &lt;to&gt; stop</Message></Response>"#
+        );
+        assert_eq!(
+            reply_twiml(None),
+            r#"<?xml version="1.0" encoding="UTF-8"?><Response/>"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_sms_to_opted_out_numbers() {
+        sms_consent_store()
+            .save_consent(SmsConsentRecord {
+                phone: "+41790001111".to_string(),
+                opted_out: true,
+                keyword: "STOP".to_string(),
+                updated_at: DateTime::from_timestamp(0, 0).unwrap(),
+            })
+            .await
+            .unwrap();
+        let config = test_support::twilio_config();
+        let request = SmsRequest {
+            to: "+41790001111".to_string(),
+            message: "Reminder".to_string(),
+        };
+        assert!(matches!(
+            deliver_sms(&config, &request).await,
+            Err(TwilioError::OptedOut(_))
+        ));
+    }
+}
@@ -0,0 +1,233 @@
+// --- File: crates/connectify_twilio/src/twilio_sms_queue.rs ---
+//! Outgoing SMS sent by a queued worker at the sender's throughput.
+//!
+//! Twilio accepts only so many messages per second from a sender and answers further ones with
+//! 429, so SMS are published to the [`SMS`] queue and sent by [`spawn_sms_worker`], which takes
+//! a token of a bucket refilled `sms_per_second` times a second before each send. The bucket
+//! holds `sms_burst` tokens, 1 by default, so bulk sends are spread evenly. The limit applies per
+//! backend instance.
+//!
+//! Messages stay queued while the `sms` runtime flag is off, outside the sending hours of the
+//! recipient's country and after failures Twilio surely didn't send them in, including 429s,
+//! and are delivered again after the queue's visibility timeout. Messages Twilio rejects, e.g.
+//! to invalid numbers, are dropped and published as [`NotificationFailed`], and so are messages
+//! Twilio may have sent before a timeout or 5xx, since sending them again could duplicate them.
+//! Sender and credential errors fail every message, so the worker then pauses for
+//! [`SUSPEND_PAUSE`] instead of retrying each one.
+
+use connectify_common::events::{self, NotificationFailed};
+use connectify_common::queue::{message_queue, publish_json, MessageQueue, QueueMessage, SMS};
+use connectify_common::rate_limit::RateLimiter;
+use connectify_common::runtime_flags::{runtime_flags, SMS as SMS_FLAG};
+use connectify_common::ConnectifyError;
+use connectify_config::{AppConfig, RateLimitRule, TwilioConfig};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::service::TwilioError;
+use crate::twilio_sms::{deliver_sms, SmsRequest};
+
+/// SMS sent per second if the config sets no `sms_per_second`, the throughput of a Twilio long
+/// code.
+const DEFAULT_SMS_PER_SECOND: u32 = 1;
+
+/// Messages taken from the queue at once.
+const BATCH_SIZE: usize = 10;
+
+/// Time the worker waits for messages before asking the queue again.
+const CONSUME_WAIT: Duration = Duration::from_secs(20);
+
+/// Pause of the worker after Twilio answered 429, or while the `sms` flag is off.
+const PAUSE: Duration = Duration::from_secs(5);
+
+/// Pause of the worker after an error of the account or sender, which fails every SMS until the
+/// configuration is fixed.
+const SUSPEND_PAUSE: Duration = Duration::from_secs(300);
+
+/// Key of the worker's token bucket.
+const BUCKET: &str = "twilio_sms";
+
+/// What became of a queued SMS.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SmsOutcome {
+    /// Sent, so the message is acknowledged.
+    Sent,
+    /// Failed for good, e.g. an invalid number, so the message is acknowledged and reported.
+    Rejected,
+    /// Failed for now, so the message is delivered again.
+    Retry,
+    /// Failed after Twilio may have sent it, e.g. on a timeout or 5xx, so the message is
+    /// acknowledged and reported rather than risking a duplicate SMS.
+    Unconfirmed,
+    /// Twilio's rate limit was hit, so the message is delivered again after a pause.
+    RateLimited,
+    /// The account or sender is misconfigured, so every SMS fails until it is fixed: the message
+    /// is delivered again, and the worker reports the error and pauses.
+    Suspended,
+}
+
+impl SmsOutcome {
+    /// The outcome of a send.
+    pub fn of(result: &Result<impl Sized, TwilioError>) -> Self {
+        match result {
+            Ok(_) => SmsOutcome::Sent,
+            Err(TwilioError::ApiError {
+                status_code: 429, ..
+            }) => SmsOutcome::RateLimited,
+            Err(TwilioError::ApiError { status_code, .. }) if (400..500).contains(status_code) => {
+                SmsOutcome::Rejected
+            }
+            Err(TwilioError::ApiError { status_code, .. }) if *status_code >= 500 => {
+                SmsOutcome::Unconfirmed
+            }
+            Err(TwilioError::Unconfirmed(_)) => SmsOutcome::Unconfirmed,
+            Err(
+                TwilioError::ValidationError(_)
+                | TwilioError::OptedOut(_)
+                | TwilioError::InvalidRecipient { .. }
+                | TwilioError::NotPermitted { .. },
+            ) => SmsOutcome::Rejected,
+            // Errors of the account or sender apply to every SMS, so they wait for a fix
+            Err(TwilioError::InvalidSender { .. } | TwilioError::AuthenticationFailed(_)) => {
+                SmsOutcome::Suspended
+            }
+            Err(_) => SmsOutcome::Retry,
+        }
+    }
+}
+
+/// The token bucket pacing the sends of a config.
+pub fn sms_throttle(config: &TwilioConfig) -> RateLimiter {
+    let per_second = config
+        .sms_per_second
+        .unwrap_or(DEFAULT_SMS_PER_SECOND)
+        .max(1);
+    RateLimiter::new(&RateLimitRule {
+        requests_per_minute: per_second * 60,
+        burst: Some(config.sms_burst.unwrap_or(1).max(1)),
+    })
+}
+
+/// Queues an SMS to be sent by the worker.
+///
+/// # Returns
+///
+/// The ID of the queued message.
+pub async fn queue_sms(request: &SmsRequest) -> Result<String, ConnectifyError> {
+    let id = publish_json(&*message_queue(), SMS, request).await?;
+    info!("[Twilio SMS] Queued SMS {} to {}", id, request.to);
+    Ok(id)
+}
+
+/// Waits until the throttle allows the next send.
+async fn acquire(throttle: &RateLimiter) {
+    while let Err(retry_after) = throttle.check(BUCKET) {
+        tokio::time::sleep(retry_after).await;
+    }
+}
+
+/// Sends a queued SMS, acknowledging it unless it is to be delivered again.
+async fn process(
+    config: &TwilioConfig,
+    queue: &dyn MessageQueue,
+    message: &QueueMessage,
+) -> Result<SmsOutcome, ConnectifyError> {
+    let request: SmsRequest = match serde_json::from_str(&message.payload) {
+        Ok(request) => request,
+        Err(e) => {
+            error!("[Twilio SMS] Dropping malformed SMS {}: {}", message.id, e);
+            queue.ack(SMS, message).await?;
+            return Ok(SmsOutcome::Rejected);
+        }
+    };
+    let result = deliver_sms(config, &request).await;
+    let outcome = SmsOutcome::of(&result);
+    match (&outcome, result) {
+        (SmsOutcome::Sent, _) => queue.ack(SMS, message).await?,
+        (SmsOutcome::Rejected, Err(e)) => {
+            warn!("[Twilio SMS] Dropping SMS to {}: {}", request.to, e);
+            fail(queue, message, request, e).await?;
+        }
+        (SmsOutcome::Unconfirmed, Err(e)) => {
+            warn!(
+                "[Twilio SMS] SMS to {} may have been sent, not sending it again: {}",
+                request.to, e
+            );
+            fail(queue, message, request, e).await?;
+        }
+        (SmsOutcome::Suspended, Err(e)) => error!(
+            "[Twilio SMS] Cannot send SMS, fix the Twilio configuration: {}",
+            e
+        ),
+        (_, Err(e)) => warn!(
+            "[Twilio SMS] Sending SMS to {} failed, retrying later: {}",
+            request.to, e
+        ),
+        _ => {}
+    }
+    Ok(outcome)
+}
+
+/// Acknowledges an SMS that is not sent again and reports it as failed.
+async fn fail(
+    queue: &dyn MessageQueue,
+    message: &QueueMessage,
+    request: SmsRequest,
+    error: TwilioError,
+) -> Result<(), ConnectifyError> {
+    queue.ack(SMS, message).await?;
+    events::publish(NotificationFailed {
+        channel: "twilio_sms".to_string(),
+        recipient: request.to,
+        error: error.to_string(),
+    });
+    Ok(())
+}
+
+/// Starts the worker sending the queued SMS.
+pub fn spawn_sms_worker(config: Arc<AppConfig>) -> Option<JoinHandle<()>> {
+    let twilio_config = config.twilio.clone().filter(|_| config.use_twilio)?;
+    let throttle = sms_throttle(&twilio_config);
+    info!(
+        "[Twilio SMS] Sending queued SMS at {} per second",
+        twilio_config
+            .sms_per_second
+            .unwrap_or(DEFAULT_SMS_PER_SECOND)
+    );
+    Some(tokio::spawn(async move {
+        let queue = message_queue();
+        loop {
+            if !runtime_flags().is_enabled(SMS_FLAG) {
+                tokio::time::sleep(PAUSE).await;
+                continue;
+            }
+            let messages = match queue.consume(SMS, BATCH_SIZE, CONSUME_WAIT).await {
+                Ok(messages) => messages,
+                Err(e) => {
+                    error!("[Twilio SMS] Failed to consume queued SMS: {}", e);
+                    tokio::time::sleep(PAUSE).await;
+                    continue;
+                }
+            };
+            for message in &messages {
+                acquire(&throttle).await;
+                match process(&twilio_config, &*queue, message).await {
+                    Ok(SmsOutcome::RateLimited) => {
+                        warn!("[Twilio SMS] Rate limited by Twilio, pausing");
+                        tokio::time::sleep(PAUSE).await;
+                    }
+                    Ok(SmsOutcome::Suspended) => {
+                        // The rest of the batch would fail the same way
+                        warn!("[Twilio SMS] Pausing sending for {:?}", SUSPEND_PAUSE);
+                        tokio::time::sleep(SUSPEND_PAUSE).await;
+                        break;
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("[Twilio SMS] Failed to process SMS {}: {}", message.id, e),
+                }
+            }
+        }
+    }))
+}
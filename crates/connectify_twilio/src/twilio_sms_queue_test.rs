@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+    use crate::service::TwilioError;
+    use crate::test_support;
+    use crate::twilio_sms::SmsRequest;
+    use crate::twilio_sms_queue::{queue_sms, sms_throttle, SmsOutcome};
+    use connectify_common::queue::{message_queue, SMS};
+    use connectify_config::TwilioConfig;
+    use std::time::Duration;
+
+    fn twilio_config() -> TwilioConfig {
+        TwilioConfig {
+            sms_per_second: Some(10),
+            sms_burst: Some(2),
+            ..test_support::twilio_config()
+        }
+    }
+
+    fn api_error(status_code: u16) -> Result<(), TwilioError> {
+        Err(TwilioError::ApiError {
+            status_code,
+            message: "error".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_sms_outcome() {
+        assert_eq!(SmsOutcome::of(&Ok::<(), TwilioError>(())), SmsOutcome::Sent);
+        assert_eq!(SmsOutcome::of(&api_error(429)), SmsOutcome::RateLimited);
+        assert_eq!(SmsOutcome::of(&api_error(400)), SmsOutcome::Rejected);
+        // Twilio may have sent the SMS before a 5xx or timeout, so it is not sent again
+        assert_eq!(SmsOutcome::of(&api_error(503)), SmsOutcome::Unconfirmed);
+        assert_eq!(
+            SmsOutcome::of(&Err::<(), _>(TwilioError::Unconfirmed(
+                "operation timed out".to_string()
+            ))),
+            SmsOutcome::Unconfirmed
+        );
+        assert_eq!(
+            SmsOutcome::of(&Err::<(), _>(TwilioError::InternalError(
+                "connection refused".to_string()
+            ))),
+            SmsOutcome::Retry
+        );
+    }
+
+    #[test]
+    fn test_sms_throttle() {
+        let throttle = sms_throttle(&twilio_config());
+        // The burst is sent at once, the next SMS waits for the refill of 1/10 s
+        assert!(throttle.check("twilio_sms").is_ok());
+        assert!(throttle.check("twilio_sms").is_ok());
+        let retry_after = throttle.check("twilio_sms").unwrap_err();
+        assert!(retry_after <= Duration::from_millis(100));
+        assert!(retry_after > Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_queue_sms() {
+        let request = SmsRequest {
+            to: "+41791234567".to_string(),
+            message: "Reminder".to_string(),
+        };
+        let id = queue_sms(&request).await.unwrap();
+
+        let messages = message_queue()
+            .consume(SMS, 10, Duration::ZERO)
+            .await
+            .unwrap();
+        let message = messages.iter().find(|message| message.id == id).unwrap();
+        let queued: SmsRequest = serde_json::from_str(&message.payload).unwrap();
+        assert_eq!(queued, request);
+    }
+}
@@ -0,0 +1,220 @@
+// --- File: crates/connectify_twilio/src/twilio_sms_templates.rs ---
+//! SMS bodies rendered from templates, e.g. booking confirmations, in the configured locale.
+//!
+//! Templates contain `{placeholder}` variables substituted when rendered; `{{` and `}}` stand
+//! for literal braces. The built-in templates are English, `sms_templates` of the Twilio config
+//! adds other locales or overrides them. A rendered SMS comes with an estimate of the segments
+//! it is billed as.
+
+use connectify_config::TwilioConfig;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::service::TwilioError;
+
+/// Template of the SMS confirming a paid booking.
+pub const BOOKING_CONFIRMED: &str = "booking_confirmed";
+
+/// Template of the SMS announcing an adhoc session.
+pub const ADHOC_BOOKED: &str = "adhoc_booked";
+
+/// Template of the SMS reminding of an upcoming booking.
+pub const BOOKING_REMINDER: &str = "booking_reminder";
+
+/// Template of the reply to an SMS asking for `HELP`.
+pub const SMS_HELP: &str = "sms_help";
+
+/// Locale of the built-in templates, used if the config sets none.
+pub const DEFAULT_SMS_LOCALE: &str = "en";
+
+/// The built-in templates, in [`DEFAULT_SMS_LOCALE`].
+const BUILT_IN_TEMPLATES: [(&str, &str); 4] = [
+    (
+        BOOKING_CONFIRMED,
+        "Appointment confirmed: start_time: {start_time}, end_time: {end_time}, summary: {summary}",
+    ),
+    (
+        ADHOC_BOOKED,
+        "Adhoc session booked: room: {room_name}, start: {start_time}, end: {end_time}, summary: {summary}",
+    ),
+    (
+        BOOKING_REMINDER,
+        "Reminder: your appointment starts at {start_time}. {summary}",
+    ),
+    (
+        SMS_HELP,
+        "Appointment notifications. Reply STOP to stop receiving SMS, START to receive them again.",
+    ),
+];
+
+/// Characters of the GSM 03.38 basic character set.
+const GSM7_BASIC: &str = "@£$¥èéùìòÇ\nØø\rÅåΔ_ΦΓΛΩΠΨΣΘΞÆæßÉ !\"#¤%&'()*+,-./0123456789:;<=>?\
+                          ¡ABCDEFGHIJKLMNOPQRSTUVWXYZÄÖÑÜ§¿abcdefghijklmnopqrstuvwxyzäöñüà";
+
+/// Characters of the GSM 03.38 extension table, taking two septets each.
+const GSM7_EXTENSION: &str = "^{}\\[~]|€\u{0c}";
+
+/// How an SMS body is encoded, which decides how many characters fit into a segment.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmsEncoding {
+    /// The GSM 7-bit alphabet: 160 characters in a single segment, 153 per segment otherwise
+    Gsm7,
+    /// UCS-2, needed by any other character: 70 in a single segment, 67 per segment otherwise
+    Ucs2,
+}
+
+/// The estimated size of an SMS body.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SmsSegments {
+    pub encoding: SmsEncoding,
+    /// Length in septets (GSM-7) or UTF-16 code units (UCS-2)
+    pub length: usize,
+    /// Segments the SMS is sent and billed as
+    pub segments: usize,
+}
+
+/// An SMS body rendered from a template.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RenderedSms {
+    pub body: String,
+    pub segments: SmsSegments,
+}
+
+/// Estimates the encoding and number of segments of an SMS body.
+pub fn estimate_segments(body: &str) -> SmsSegments {
+    let septets = body.chars().try_fold(0, |length, c| {
+        if GSM7_BASIC.contains(c) {
+            Some(length + 1)
+        } else if GSM7_EXTENSION.contains(c) {
+            Some(length + 2)
+        } else {
+            None
+        }
+    });
+    let (encoding, length, single, multi) = match septets {
+        Some(septets) => (SmsEncoding::Gsm7, septets, 160, 153),
+        None => (SmsEncoding::Ucs2, body.encode_utf16().count(), 70, 67),
+    };
+    SmsSegments {
+        encoding,
+        length,
+        segments: if length <= single {
+            1
+        } else {
+            length.div_ceil(multi)
+        },
+    }
+}
+
+/// Substitutes the `{placeholder}` variables of a template.
+pub fn render_template(template: &str, variables: &[(&str, &str)]) -> Result<String, TwilioError> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                rendered.push('{');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => {
+                            return Err(TwilioError::TemplateError(format!(
+                                "unterminated placeholder {{{} in '{}'",
+                                name, template
+                            )))
+                        }
+                    }
+                }
+                let value = variables
+                    .iter()
+                    .find(|(variable, _)| *variable == name.trim())
+                    .map(|(_, value)| *value)
+                    .ok_or_else(|| {
+                        TwilioError::TemplateError(format!(
+                            "no value for placeholder {{{}}} in '{}'",
+                            name, template
+                        ))
+                    })?;
+                rendered.push_str(value);
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                rendered.push('}');
+            }
+            c => rendered.push(c),
+        }
+    }
+    Ok(rendered)
+}
+
+/// The SMS templates of a locale: the configured ones, falling back to the built-in ones.
+#[derive(Debug, Clone)]
+pub struct SmsTemplates {
+    locale: String,
+    templates: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl SmsTemplates {
+    /// The templates of the Twilio config, in its `sms_locale`.
+    pub fn from_config(config: &TwilioConfig) -> Self {
+        Self {
+            locale: config
+                .sms_locale
+                .clone()
+                .unwrap_or_else(|| DEFAULT_SMS_LOCALE.to_string()),
+            templates: config.sms_templates.clone(),
+        }
+    }
+
+    /// The templates in another locale, e.g. the one of the recipient.
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        self.locale = locale.into();
+        self
+    }
+
+    /// The locale templates are looked up in.
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// A template, in the locale (`de-CH`), its language (`de`), the default locale or built in,
+    /// whichever has it first.
+    pub fn template(&self, name: &str) -> Option<&str> {
+        let language = self.locale.split(['-', '_']).next().unwrap_or_default();
+        [self.locale.as_str(), language, DEFAULT_SMS_LOCALE]
+            .into_iter()
+            .find_map(|locale| self.templates.get(locale)?.get(name))
+            .map(String::as_str)
+            .or_else(|| {
+                BUILT_IN_TEMPLATES
+                    .iter()
+                    .find(|(template, _)| *template == name)
+                    .map(|(_, body)| *body)
+            })
+    }
+
+    /// Renders a template with its variables.
+    pub fn render(
+        &self,
+        name: &str,
+        variables: &[(&str, &str)],
+    ) -> Result<RenderedSms, TwilioError> {
+        let template = self.template(name).ok_or_else(|| {
+            TwilioError::TemplateError(format!(
+                "no SMS template {} in locale {}",
+                name, self.locale
+            ))
+        })?;
+        let body = render_template(template, variables)?;
+        Ok(RenderedSms {
+            segments: estimate_segments(&body),
+            body,
+        })
+    }
+}
@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod tests {
+    use crate::service::TwilioError;
+    use crate::test_support;
+    use crate::twilio_sms_templates::{
+        estimate_segments, render_template, SmsEncoding, SmsTemplates, BOOKING_CONFIRMED,
+        BOOKING_REMINDER,
+    };
+    use connectify_config::TwilioConfig;
+    use std::collections::BTreeMap;
+
+    fn twilio_config(locale: Option<&str>) -> TwilioConfig {
+        TwilioConfig {
+            sms_locale: locale.map(str::to_string),
+            sms_templates: BTreeMap::from([
+                (
+                    "de".to_string(),
+                    BTreeMap::from([(
+                        BOOKING_CONFIRMED.to_string(),
+                        "Termin bestätigt: {start_time}".to_string(),
+                    )]),
+                ),
+                (
+                    "de-CH".to_string(),
+                    BTreeMap::from([(
+                        BOOKING_REMINDER.to_string(),
+                        "Erinnerig: {start_time}".to_string(),
+                    )]),
+                ),
+            ]),
+            ..test_support::twilio_config()
+        }
+    }
+
+    #[test]
+    fn test_render_template() {
+        assert_eq!(
+            render_template(
+                "{{literal}} {name} at { time }",
+                &[("name", "Anna"), ("time", "10:00")]
+            )
+            .unwrap(),
+            "{literal} Anna at 10:00"
+        );
+        assert!(matches!(
+            render_template("Hello {name}", &[]),
+            Err(TwilioError::TemplateError(_))
+        ));
+        assert!(matches!(
+            render_template("Hello {name", &[("name", "Anna")]),
+            Err(TwilioError::TemplateError(_))
+        ));
+    }
+
+    #[test]
+    fn test_template_locale_fallback() {
+        let templates = SmsTemplates::from_config(&twilio_config(Some("de-CH")));
+        assert_eq!(
+            templates.template(BOOKING_REMINDER),
+            Some("Erinnerig: {start_time}")
+        );
+        assert_eq!(
+            templates.template(BOOKING_CONFIRMED),
+            Some("Termin bestätigt: {start_time}")
+        );
+
+        // Built in if no locale has it
+        let templates = templates.with_locale("fr");
+        assert!(templates
+            .template(BOOKING_CONFIRMED)
+            .unwrap()
+            .starts_with("Appointment confirmed"));
+        assert_eq!(templates.template("unknown"), None);
+
+        let templates = SmsTemplates::from_config(&twilio_config(None));
+        assert_eq!(templates.locale(), "en");
+        let sms = templates
+            .render(
+                BOOKING_CONFIRMED,
+                &[
+                    ("start_time", "2025-07-01T10:00"),
+                    ("end_time", "2025-07-01T11:00"),
+                    ("summary", "Consultation"),
+                ],
+            )
+            .unwrap();
+        assert_eq!(
+            sms.body,
+            "Appointment confirmed: start_time: 2025-07-01T10:00, end_time: 2025-07-01T11:00, summary: Consultation"
+        );
+        assert!(matches!(
+            templates.render("unknown", &[]),
+            Err(TwilioError::TemplateError(_))
+        ));
+    }
+
+    #[test]
+    fn test_estimate_segments() {
+        let short = estimate_segments("Termin am Montag, 10:00 Uhr. Grüße!");
+        assert_eq!(short.encoding, SmsEncoding::Gsm7);
+        assert_eq!(short.segments, 1);
+
+        // Extension characters take two septets
+        assert_eq!(estimate_segments("€10 [x]").length, 10);
+        assert_eq!(estimate_segments(&"a".repeat(160)).segments, 1);
+        assert_eq!(estimate_segments(&"a".repeat(161)).segments, 2);
+        assert_eq!(estimate_segments(&"a".repeat(307)).segments, 3);
+
+        // A single character outside GSM-7 switches the whole body to UCS-2
+        let unicode = estimate_segments(&format!("{}✓", "a".repeat(69)));
+        assert_eq!(unicode.encoding, SmsEncoding::Ucs2);
+        assert_eq!(unicode.segments, 1);
+        let unicode = estimate_segments(&format!("{}✓", "a".repeat(70)));
+        assert_eq!(unicode.segments, 2);
+        assert_eq!(estimate_segments("👋").length, 2);
+    }
+}
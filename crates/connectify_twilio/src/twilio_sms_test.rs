@@ -1,11 +1,103 @@
 #[cfg(test)]
 mod tests {
-    use crate::twilio_sms::{send_sms, SmsRequest};
+    use crate::test_support;
+    use crate::twilio_sms::{
+        record_message_status, send_sms, sms_params, MessageStatusCallback, SmsRequest,
+    };
     use axum::extract::State;
     use axum::Json;
-    use connectify_config::load_config;
+    use chrono::DateTime;
+    use connectify_common::message_status::{InMemoryMessageStatusStore, MessageStatusStore};
+    use connectify_config::{load_config, TwilioConfig};
     use std::sync::Arc;
 
+    fn twilio_config() -> TwilioConfig {
+        TwilioConfig {
+            status_callback_base_url: Some("https://connectify.example.com/api/".to_string()),
+            ..test_support::twilio_config()
+        }
+    }
+
+    fn callback(status: &str, error_code: Option<&str>) -> MessageStatusCallback {
+        MessageStatusCallback {
+            message_sid: "SM123".to_string(),
+            message_status: status.to_string(),
+            to: Some("whatsapp:+41791234567".to_string()),
+            from: None,
+            error_code: error_code.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_sms_params() {
+        let request = SmsRequest {
+            to: "whatsapp:+41791234567".to_string(),
+            message: "Hallo".to_string(),
+        };
+        let params = sms_params(&twilio_config(), &request);
+        assert!(params.contains(&(
+            "StatusCallback",
+            "https://connectify.example.com/api/twilio/messages/status".to_string()
+        )));
+
+        let config = TwilioConfig {
+            status_callback_base_url: None,
+            ..twilio_config()
+        };
+        let params = sms_params(&config, &request);
+        assert!(!params.iter().any(|(name, _)| *name == "StatusCallback"));
+    }
+
+    #[test]
+    fn test_sms_params_messaging_service() {
+        let request = SmsRequest {
+            to: "+41791234567".to_string(),
+            message: "Hallo".to_string(),
+        };
+        let params = sms_params(&twilio_config(), &request);
+        assert!(params.iter().any(|(name, _)| *name == "From"));
+
+        // The Messaging Service picks the sender instead
+        let config = TwilioConfig {
+            messaging_service_sid: Some("MG123".to_string()),
+            ..twilio_config()
+        };
+        let params = sms_params(&config, &request);
+        assert!(params.contains(&("MessagingServiceSid", "MG123".to_string())));
+        assert!(!params.iter().any(|(name, _)| *name == "From"));
+    }
+
+    #[tokio::test]
+    async fn test_record_message_status() {
+        let store = InMemoryMessageStatusStore::default();
+        let at = |second| DateTime::from_timestamp(second, 0).unwrap();
+
+        // The first callback of an unknown message creates its record
+        assert!(
+            record_message_status(&store, &callback("sent", None), at(0))
+                .await
+                .unwrap()
+        );
+        assert!(
+            record_message_status(&store, &callback("undelivered", Some("30003")), at(1))
+                .await
+                .unwrap()
+        );
+        // A late intermediate status is ignored
+        assert!(
+            !record_message_status(&store, &callback("sent", Some("")), at(2))
+                .await
+                .unwrap()
+        );
+
+        let record = store.get("SM123").await.unwrap().unwrap();
+        assert_eq!(record.to, "whatsapp:+41791234567");
+        assert_eq!(record.status, "undelivered");
+        assert_eq!(record.error_code.as_deref(), Some("30003"));
+        assert_eq!(record.created_at, at(0));
+        assert_eq!(record.updated_at, at(1));
+    }
+
     #[tokio::test]
     async fn test_send_sms() {
         // Skip this test if not in production environment
@@ -1,47 +1,111 @@
 // --- File: crates/connectify_twilio/src/twilio_token.rs ---
+//! Twilio access tokens for the app's video rooms, chat and voice.
+//!
+//! The caller requests the grants it needs, each checked against the config: a video room,
+//! the Conversations service of `chat_service_sid`, or voice through `voice_twiml_app_sid`.
+//! The identity of a signed-in caller is their user id; anonymous callers name their own,
+//! unless `token_require_auth` is set.
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
     response::Json,
 };
 use std::sync::Arc;
 
-use chrono::{Duration, Utc};
-use connectify_config::AppConfig;
+use chrono::Duration;
+use connectify_common::clock::{Clock, SystemClock};
+use connectify_common::jwt::AuthClaims;
+use connectify_common::ConnectifyError;
+use connectify_config::{AppConfig, TwilioConfig};
 use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
 use serde::{Deserialize, Serialize};
-use std::{
-    env,
-    time::{SystemTime, UNIX_EPOCH},
-};
-use tracing::info;
+use std::env;
+use tracing::{error, info};
+
+use crate::service::{twilio_config, TwilioError};
+
+/// Seconds a token is valid if neither the request nor the config sets a TTL.
+const DEFAULT_TOKEN_TTL_SECONDS: u32 = 3600;
+
+/// Longest TTL a request can ask for if the config sets no `token_max_ttl_seconds`.
+const DEFAULT_MAX_TOKEN_TTL_SECONDS: u32 = 4 * 3600;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct VideoGrant {
-    room: Option<String>,
+/// Longest TTL Twilio accepts.
+const TWILIO_MAX_TOKEN_TTL_SECONDS: u32 = 24 * 3600;
+
+/// Grants issued if the request names none.
+const DEFAULT_GRANTS: &str = "video";
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct VideoGrant {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub room: Option<String>,
 }
-#[derive(Debug, Serialize, Deserialize)]
-struct Grants {
-    identity: String,
-    video: VideoGrant,
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ChatGrant {
+    pub service_sid: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct VoiceGrant {
+    pub incoming: VoiceIncoming,
+    pub outgoing: VoiceOutgoing,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct VoiceIncoming {
+    pub allow: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct VoiceOutgoing {
+    pub application_sid: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Grants {
+    pub identity: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video: Option<VideoGrant>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat: Option<ChatGrant>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice: Option<VoiceGrant>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    sub: String, // Twilio Account SID
-    iss: String, // Twilio API Key SID
-    exp: usize,  // Expiration timestamp (Unix epoch seconds)
-    jti: String, // Unique identifier for the token
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Claims {
+    pub sub: String, // Twilio Account SID
+    pub iss: String, // Twilio API Key SID
+    pub exp: usize,  // Expiration timestamp (Unix epoch seconds)
+    pub jti: String, // Unique identifier for the token
     // aud: String, // Usually not needed for Video Grant
-    grants: Grants,
+    pub grants: Grants,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default)]
 #[cfg_attr(feature = "openapi", derive(utoipa::IntoParams, utoipa::ToSchema))]
 pub struct TokenRequestQuery {
-    pub identity: String,
+    /// Identity of an anonymous caller; signed-in callers get their user id
+    #[cfg_attr(feature = "openapi", param(example = "User_12345"))]
+    pub identity: Option<String>,
+    /// Room of the video grant
     #[serde(rename = "roomName")]
-    pub room_name: String,
+    #[cfg_attr(feature = "openapi", param(example = "MyCoolRoom"))]
+    pub room_name: Option<String>,
+    /// Comma-separated grants: `video`, `chat` and `voice` (default: `video`)
+    #[cfg_attr(feature = "openapi", param(example = "video,chat"))]
+    pub grants: Option<String>,
+    /// Conversations service of the chat grant, must be the configured one
+    #[serde(rename = "chatServiceSid")]
+    #[cfg_attr(
+        feature = "openapi",
+        param(example = "IS0123456789abcdef0123456789abcdef")
+    )]
+    pub chat_service_sid: Option<String>,
+    /// Seconds the token is valid, up to `token_max_ttl_seconds`
+    #[cfg_attr(feature = "openapi", param(example = 3600))]
+    pub ttl: Option<u32>,
 }
 
 #[derive(Serialize, Debug)]
@@ -49,109 +113,211 @@ pub struct TokenRequestQuery {
 // Added Debug
 pub struct TokenResponse {
     pub token: String,
+    /// The identity the token was issued to
+    pub identity: String,
+    /// Seconds the token is valid
+    pub ttl: u32,
 }
-// --- Axum Handler Function ---
 
-/// Generates a Twilio access token for video services using Axum.
-///
-/// Expects shared AppConfig state and query parameters.
-/// Loads the API secret directly from environment variables.
-/// Returns a JSON token on success or a status code + error message on failure.
-/// Generates a Twilio access token for video services using Axum.
-///
-/// Expects shared AppConfig state and query parameters.
-/// Loads the API secret directly from environment variables.
-/// Returns a JSON token on success or a status code + error message on failure.
-#[axum::debug_handler] // Useful Axum macro for debugging handler type issues
-pub async fn generate_token(
-    State(config): State<Arc<AppConfig>>, // Axum state extractor (Arc<AppConfig>)
-    Query(query): Query<TokenRequestQuery>, // Axum query extractor
-) -> Result<Json<TokenResponse>, (StatusCode, String)> {
-    // Axum Result type
+/// The identity of a token: the user id of a signed-in caller, else the requested one.
+pub fn token_identity(
+    twilio_conf: &TwilioConfig,
+    query: &TokenRequestQuery,
+    claims: Option<&AuthClaims>,
+) -> Result<String, ConnectifyError> {
+    let requested = query
+        .identity
+        .as_deref()
+        .map(str::trim)
+        .filter(|identity| !identity.is_empty());
+    match (claims, requested) {
+        (Some(claims), Some(requested)) if requested != claims.sub => {
+            Err(ConnectifyError::AuthError(format!(
+                "User '{}' cannot get a token for identity '{}'",
+                claims.sub, requested
+            )))
+        }
+        (Some(claims), _) => Ok(claims.sub.clone()),
+        (None, _) if twilio_conf.token_require_auth => Err(ConnectifyError::AuthError(
+            "Sign in to get a Twilio token".to_string(),
+        )),
+        (None, Some(requested)) => Ok(requested.to_string()),
+        (None, None) => {
+            Err(TwilioError::ValidationError("identity is required".to_string()).into())
+        }
+    }
+}
 
-    // --- Access Config & Check Runtime Flag ---
-    // Check if the optional twilio config section exists
-    let Some(twilio_conf) = config.twilio.as_ref() else {
-        let err_msg = "Twilio configuration section missing in server config.".to_string();
-        info!("{}", err_msg);
-        // Return Axum error tuple
-        return Err((StatusCode::INTERNAL_SERVER_ERROR, err_msg));
+/// The TTL of a token: the requested one, or `token_ttl_seconds`, or the `TOKEN_EXPIRY`
+/// environment variable, or an hour.
+pub fn token_ttl(
+    twilio_conf: &TwilioConfig,
+    query: &TokenRequestQuery,
+) -> Result<u32, TwilioError> {
+    let max_ttl = twilio_conf
+        .token_max_ttl_seconds
+        .unwrap_or(DEFAULT_MAX_TOKEN_TTL_SECONDS)
+        .min(TWILIO_MAX_TOKEN_TTL_SECONDS);
+    let ttl = match query.ttl {
+        Some(ttl) => ttl,
+        None => twilio_conf
+            .token_ttl_seconds
+            .or_else(|| env::var("TOKEN_EXPIRY").ok()?.parse().ok())
+            .unwrap_or(DEFAULT_TOKEN_TTL_SECONDS)
+            .min(max_ttl),
     };
-
-    // Check the runtime flag
-    if !config.use_twilio {
-        let err_msg = "Twilio service is disabled by configuration.".to_string();
-        info!("{}", err_msg);
-        return Err((StatusCode::SERVICE_UNAVAILABLE, err_msg));
+    if ttl == 0 || ttl > max_ttl {
+        return Err(TwilioError::ValidationError(format!(
+            "ttl must be between 1 and {} seconds, got {}",
+            max_ttl, ttl
+        )));
     }
+    Ok(ttl)
+}
 
-    let api_key_secret = match &config.twilio {
-        Some(twilio_conf) => twilio_conf.api_key_secret.clone(),
-        None => {
-            let err_msg = "Missing TWILIO Config.".to_string();
-            info!("{}", err_msg);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, err_msg));
-        }
+/// The requested grants of an identity, checked against the config.
+pub fn token_grants(
+    twilio_conf: &TwilioConfig,
+    query: &TokenRequestQuery,
+    identity: String,
+) -> Result<Grants, TwilioError> {
+    let mut grants = Grants {
+        identity,
+        video: None,
+        chat: None,
+        voice: None,
     };
+    let requested = query.grants.as_deref().unwrap_or(DEFAULT_GRANTS);
+    for grant in requested.split(',').map(str::trim) {
+        match grant {
+            "video" => {
+                let room = query
+                    .room_name
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|room| !room.is_empty())
+                    .ok_or_else(|| {
+                        TwilioError::ValidationError(
+                            "roomName is required for the video grant".to_string(),
+                        )
+                    })?;
+                grants.video = Some(VideoGrant {
+                    room: Some(room.to_string()),
+                });
+            }
+            "chat" => {
+                let service_sid = twilio_conf.chat_service_sid.as_deref().ok_or_else(|| {
+                    TwilioError::ValidationError("chat grants are not configured".to_string())
+                })?;
+                if let Some(requested) = query.chat_service_sid.as_deref() {
+                    if requested != service_sid {
+                        return Err(TwilioError::ValidationError(format!(
+                            "chat service {} is not the configured one",
+                            requested
+                        )));
+                    }
+                }
+                grants.chat = Some(ChatGrant {
+                    service_sid: service_sid.to_string(),
+                });
+            }
+            "voice" => {
+                let application_sid =
+                    twilio_conf.voice_twiml_app_sid.as_deref().ok_or_else(|| {
+                        TwilioError::ValidationError("voice grants are not configured".to_string())
+                    })?;
+                grants.voice = Some(VoiceGrant {
+                    incoming: VoiceIncoming { allow: true },
+                    outgoing: VoiceOutgoing {
+                        application_sid: application_sid.to_string(),
+                    },
+                });
+            }
+            other => {
+                return Err(TwilioError::ValidationError(format!(
+                    "unknown grant '{}', expected video, chat or voice",
+                    other
+                )))
+            }
+        }
+    }
+    Ok(grants)
+}
 
-    // --- Token Generation Logic ---
-    let now_secs = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_secs();
-
-    // Use fields from twilio_conf loaded via AppConfig state
-    let jti = format!("{}-{}", twilio_conf.api_key_sid, now_secs);
+/// Construct the claims of a token issued at the clock's current time.
+pub fn token_claims(
+    twilio_conf: &TwilioConfig,
+    grants: Grants,
+    ttl_seconds: u32,
+    clock: &dyn Clock,
+) -> Claims {
+    let now = clock.now();
+    Claims {
+        sub: twilio_conf.account_sid.clone(),
+        iss: twilio_conf.api_key_sid.clone(),
+        exp: (now + Duration::seconds(i64::from(ttl_seconds))).timestamp() as usize,
+        jti: format!("{}-{}", twilio_conf.api_key_sid, now.timestamp()),
+        grants,
+    }
+}
 
-    let expiry_seconds: i64 = env::var("TOKEN_EXPIRY") // Reading helper env var is fine
-        .ok()
-        .and_then(|s| s.parse::<i64>().ok())
-        .unwrap_or(3600); // Default to 1 hour
+// --- Axum Handler Function ---
 
-    let expiration = (Utc::now() + Duration::seconds(expiry_seconds)).timestamp() as usize;
+/// Generates a Twilio access token with the requested grants.
+///
+/// Signed-in callers get a token for their user id; anonymous callers name their identity,
+/// unless `token_require_auth` is set.
+#[axum::debug_handler] // Useful Axum macro for debugging handler type issues
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/generate-token", // Path relative to /api
+    params(TokenRequestQuery),
+    responses(
+        (status = 200, description = "Successfully generated Twilio Access Token", body = TokenResponse),
+        (status = 400, description = "Invalid grants or TTL"),
+        (status = 401, description = "Not signed in, or the identity is not the caller's"),
+        (status = 500, description = "Twilio not configured or disabled")
+    ),
+    tag = "Twilio"
+))]
+pub async fn generate_token(
+    State(config): State<Arc<AppConfig>>, // Axum state extractor (Arc<AppConfig>)
+    claims: Option<AuthClaims>,
+    Query(query): Query<TokenRequestQuery>, // Axum query extractor
+) -> Result<Json<TokenResponse>, ConnectifyError> {
+    // --- Access Config & Check Runtime Flag ---
+    let twilio_conf = twilio_config(&config)?;
 
-    // Construct claims using data from config and query
-    let claims = Claims {
-        sub: twilio_conf.account_sid.clone(),
-        iss: twilio_conf.api_key_sid.clone(),
-        exp: expiration,
-        jti,
-        grants: Grants {
-            identity: query.identity.clone(),
-            video: VideoGrant {
-                room: Some(query.room_name.clone()),
-            },
-        },
-    };
+    // --- Token Generation Logic ---
+    let identity = token_identity(twilio_conf, &query, claims.as_ref())?;
+    let ttl = token_ttl(twilio_conf, &query)?;
+    let grants = token_grants(twilio_conf, &query, identity.clone())?;
+    let claims = token_claims(twilio_conf, grants, ttl, &SystemClock);
 
     info!(
-        "DEBUG: Generating token with iss(SK): {}, sub(AC): {}",
-        claims.iss, claims.sub
+        "Generating Twilio token for {} with grants {} valid for {}s",
+        identity,
+        query.grants.as_deref().unwrap_or(DEFAULT_GRANTS),
+        ttl
     );
 
-    // Standard Twilio JWT headers for Video Grant tokens
+    // Standard Twilio JWT headers for access tokens
     let mut header = Header::new(Algorithm::HS256);
     header.cty = Some("twilio-fpa;v=1".to_string());
     header.typ = Some("JWT".to_string());
 
-    // Encode the token using the secret loaded from the environment
-    match encode(
+    let token = encode(
         &header,
         &claims,
-        &EncodingKey::from_secret(api_key_secret.as_ref()),
-    ) {
-        Ok(token) => {
-            // On success, return JSON response
-            Ok(Json(TokenResponse { token }))
-        }
-        Err(e) => {
-            // On failure, log error and return Axum error tuple
-            let err_msg = format!("Error generating token: {}", e);
-            info!("{}", err_msg);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to generate token".to_string(),
-            ))
-        }
-    }
+        &EncodingKey::from_secret(twilio_conf.api_key_secret.as_ref()),
+    )
+    .map_err(|e| {
+        error!("Error generating token: {}", e);
+        ConnectifyError::InternalError("Failed to generate token".to_string())
+    })?;
+    Ok(Json(TokenResponse {
+        token,
+        identity,
+        ttl,
+    }))
 }
@@ -0,0 +1,169 @@
+#[cfg(test)]
+mod tests {
+    use crate::service::TwilioError;
+    use crate::test_support;
+    use crate::twilio_token::{
+        token_claims, token_grants, token_identity, token_ttl, ChatGrant, TokenRequestQuery,
+        VideoGrant,
+    };
+    use chrono::TimeZone;
+    use connectify_common::clock::MockClock;
+    use connectify_common::jwt::AuthClaims;
+    use connectify_common::ConnectifyError;
+    use connectify_config::TwilioConfig;
+
+    fn twilio_config() -> TwilioConfig {
+        TwilioConfig {
+            token_ttl_seconds: Some(1800),
+            token_max_ttl_seconds: Some(7200),
+            chat_service_sid: Some("IS123".to_string()),
+            ..test_support::twilio_config()
+        }
+    }
+
+    fn user(sub: &str) -> AuthClaims {
+        AuthClaims {
+            sub: sub.to_string(),
+            exp: 0,
+            iss: None,
+            email: None,
+            roles: Vec::new(),
+            scope: None,
+            extra: Default::default(),
+        }
+    }
+
+    fn query(identity: Option<&str>) -> TokenRequestQuery {
+        TokenRequestQuery {
+            identity: identity.map(str::to_string),
+            room_name: Some("MyCoolRoom".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_token_identity() {
+        let config = twilio_config();
+        let alice = user("alice");
+        assert_eq!(
+            token_identity(&config, &query(None), Some(&alice)).unwrap(),
+            "alice"
+        );
+        assert_eq!(
+            token_identity(&config, &query(Some("alice")), Some(&alice)).unwrap(),
+            "alice"
+        );
+        assert!(matches!(
+            token_identity(&config, &query(Some("bob")), Some(&alice)),
+            Err(ConnectifyError::AuthError(_))
+        ));
+        assert_eq!(
+            token_identity(&config, &query(Some("guest_1")), None).unwrap(),
+            "guest_1"
+        );
+        assert!(matches!(
+            token_identity(&config, &query(None), None),
+            Err(ConnectifyError::ValidationError(_))
+        ));
+
+        let config = TwilioConfig {
+            token_require_auth: true,
+            ..twilio_config()
+        };
+        assert!(matches!(
+            token_identity(&config, &query(Some("guest_1")), None),
+            Err(ConnectifyError::AuthError(_))
+        ));
+    }
+
+    #[test]
+    fn test_token_ttl() {
+        let config = twilio_config();
+        assert_eq!(token_ttl(&config, &query(None)).unwrap(), 1800);
+        let requested = |ttl| TokenRequestQuery {
+            ttl: Some(ttl),
+            ..query(None)
+        };
+        assert_eq!(token_ttl(&config, &requested(600)).unwrap(), 600);
+        assert!(matches!(
+            token_ttl(&config, &requested(7201)),
+            Err(TwilioError::ValidationError(_))
+        ));
+        assert!(matches!(
+            token_ttl(&config, &requested(0)),
+            Err(TwilioError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_token_grants() {
+        let config = twilio_config();
+        let grants = token_grants(&config, &query(None), "alice".to_string()).unwrap();
+        assert_eq!(
+            grants.video,
+            Some(VideoGrant {
+                room: Some("MyCoolRoom".to_string())
+            })
+        );
+        assert_eq!(grants.chat, None);
+
+        let chat = TokenRequestQuery {
+            grants: Some("chat".to_string()),
+            room_name: None,
+            ..query(None)
+        };
+        let grants = token_grants(&config, &chat, "alice".to_string()).unwrap();
+        assert_eq!(grants.video, None);
+        assert_eq!(
+            grants.chat,
+            Some(ChatGrant {
+                service_sid: "IS123".to_string()
+            })
+        );
+
+        for invalid in [
+            // Chat service other than the configured one
+            TokenRequestQuery {
+                chat_service_sid: Some("IS999".to_string()),
+                ..chat
+            },
+            // Voice grants are not configured
+            TokenRequestQuery {
+                grants: Some("video,voice".to_string()),
+                ..query(None)
+            },
+            // Video grant without room
+            TokenRequestQuery {
+                room_name: None,
+                ..query(None)
+            },
+            TokenRequestQuery {
+                grants: Some("fax".to_string()),
+                ..query(None)
+            },
+        ] {
+            assert!(matches!(
+                token_grants(&config, &invalid, "alice".to_string()),
+                Err(TwilioError::ValidationError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_token_claims() {
+        let config = twilio_config();
+        let clock = MockClock::new(chrono::Utc.with_ymd_and_hms(2025, 5, 5, 9, 0, 0).unwrap());
+        let grants = token_grants(&config, &query(None), "alice".to_string()).unwrap();
+        let claims = token_claims(&config, grants, 600, &clock);
+        assert_eq!(claims.sub, "AC123");
+        assert_eq!(claims.iss, "SK123");
+        assert_eq!(claims.exp, 1_746_435_600 + 600);
+
+        // Grants that aren't issued are left out of the token
+        let json = serde_json::to_value(&claims.grants).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "identity": "alice", "video": { "room": "MyCoolRoom" } })
+        );
+    }
+}
@@ -0,0 +1,287 @@
+// --- File: crates/connectify_twilio/src/twilio_usage.rs ---
+//! Twilio usage and cost of a date range, per feature of Connectify.
+//!
+//! The usage records of the account are fetched from Twilio's Usage API and those of the
+//! categories each feature bills under, e.g. `sms` and `mms` for SMS, are summed up. Only
+//! top-level categories are counted, as Twilio also reports their subcategories, e.g.
+//! `sms-outbound`. The total is Twilio's `totalprice`, so it includes the cost of features
+//! Connectify doesn't use, like phone numbers.
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use chrono::NaiveDate;
+use connectify_common::request_id::RequestIdExt;
+use connectify_common::{ConnectifyError, HTTP_CLIENT};
+use connectify_config::{AppConfig, TwilioConfig};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::service::{send_twilio_request, twilio_config, TwilioError};
+
+/// Base URL of the Twilio REST API, against which `next_page_uri` is resolved.
+const TWILIO_API_BASE_URL: &str = "https://api.twilio.com";
+
+/// Items requested per page of usage records.
+const PAGE_SIZE: &str = "1000";
+
+/// Pages followed before the listing stops, bounding the requests of a single report.
+const MAX_PAGES: usize = 10;
+
+/// Category of the account's total cost.
+const TOTAL_PRICE_CATEGORY: &str = "totalprice";
+
+/// Features and the usage categories they bill under, see
+/// <https://www.twilio.com/docs/usage/api/usage-record#usage-categories>.
+pub const USAGE_FEATURES: [(&str, &[&str]); 4] = [
+    ("sms", &["sms", "mms"]),
+    ("voice", &["calls"]),
+    (
+        "video",
+        &["group-rooms", "peer-to-peer-rooms-participant-minutes"],
+    ),
+    ("conversations", &["conversations"]),
+];
+
+/// A usage record of the Usage API. Amounts are sent as decimal strings.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct UsageRecord {
+    pub category: String,
+    pub description: Option<String>,
+    #[serde(default, deserialize_with = "decimal")]
+    pub count: f64,
+    pub count_unit: Option<String>,
+    #[serde(default, deserialize_with = "decimal")]
+    pub usage: f64,
+    pub usage_unit: Option<String>,
+    #[serde(default, deserialize_with = "decimal")]
+    pub price: f64,
+    pub price_unit: Option<String>,
+}
+
+/// Reads a decimal sent as string or number, null as 0.
+fn decimal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Null => Ok(0.0),
+        Value::Number(number) => Ok(number.as_f64().unwrap_or_default()),
+        Value::String(text) if text.is_empty() => Ok(0.0),
+        Value::String(text) => text.parse().map_err(serde::de::Error::custom),
+        other => Err(serde::de::Error::custom(format!(
+            "expected a decimal, got {}",
+            other
+        ))),
+    }
+}
+
+/// Query of the usage report.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams, utoipa::ToSchema))]
+pub struct UsageQuery {
+    /// First day of the range, as YYYY-MM-DD
+    #[cfg_attr(feature = "openapi", param(example = "2025-07-01"))]
+    pub start_date: String,
+    /// Last day of the range, as YYYY-MM-DD
+    #[cfg_attr(feature = "openapi", param(example = "2025-07-31"))]
+    pub end_date: String,
+}
+
+impl UsageQuery {
+    /// The query parameters listing the usage records of the range.
+    pub fn twilio_params(&self) -> Result<Vec<(&'static str, String)>, TwilioError> {
+        let day = |name: &str, value: &str| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+                TwilioError::ValidationError(format!(
+                    "{} must be a date in YYYY-MM-DD format, got '{}'",
+                    name, value
+                ))
+            })
+        };
+        let start = day("start_date", &self.start_date)?;
+        let end = day("end_date", &self.end_date)?;
+        if start > end {
+            return Err(TwilioError::ValidationError(format!(
+                "start_date {} is after end_date {}",
+                start, end
+            )));
+        }
+        Ok(vec![
+            ("StartDate", start.to_string()),
+            ("EndDate", end.to_string()),
+            ("PageSize", PAGE_SIZE.to_string()),
+        ])
+    }
+}
+
+/// The usage of a category.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CategoryUsage {
+    #[cfg_attr(feature = "openapi", schema(example = "sms"))]
+    pub category: String,
+    pub description: Option<String>,
+    /// e.g. the segments of SMS or the minutes of calls
+    #[cfg_attr(feature = "openapi", schema(example = 1240.0))]
+    pub usage: f64,
+    #[cfg_attr(feature = "openapi", schema(example = "segments"))]
+    pub usage_unit: Option<String>,
+    pub count: f64,
+    pub count_unit: Option<String>,
+    #[cfg_attr(feature = "openapi", schema(example = 9.67))]
+    pub price: f64,
+}
+
+/// The usage and cost of a feature.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FeatureUsage {
+    /// `sms`, `voice`, `video` or `conversations`
+    #[cfg_attr(feature = "openapi", schema(example = "sms"))]
+    pub feature: String,
+    pub categories: Vec<CategoryUsage>,
+    /// Sum of the categories' prices
+    #[cfg_attr(feature = "openapi", schema(example = 9.67))]
+    pub price: f64,
+}
+
+/// The usage and cost of a date range.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UsageReport {
+    pub start_date: String,
+    pub end_date: String,
+    /// Currency of the prices, e.g. `usd`
+    #[cfg_attr(feature = "openapi", schema(example = "usd"))]
+    pub currency: Option<String>,
+    pub features: Vec<FeatureUsage>,
+    /// Total cost of the account in the range, including features not listed
+    #[cfg_attr(feature = "openapi", schema(example = 42.5))]
+    pub total_price: f64,
+}
+
+/// Rounds an amount to the 4 decimals Twilio prices have, dropping float noise of sums.
+fn round_price(price: f64) -> f64 {
+    (price * 10_000.0).round() / 10_000.0
+}
+
+/// Sums the usage records of a range up per feature.
+pub fn aggregate_usage(records: &[UsageRecord], query: &UsageQuery) -> UsageReport {
+    let features = USAGE_FEATURES
+        .iter()
+        .map(|(feature, categories)| {
+            let categories: Vec<CategoryUsage> = records
+                .iter()
+                .filter(|record| categories.contains(&record.category.as_str()))
+                .map(|record| CategoryUsage {
+                    category: record.category.clone(),
+                    description: record.description.clone(),
+                    usage: record.usage,
+                    usage_unit: record.usage_unit.clone(),
+                    count: record.count,
+                    count_unit: record.count_unit.clone(),
+                    price: record.price,
+                })
+                .collect();
+            FeatureUsage {
+                feature: feature.to_string(),
+                price: round_price(categories.iter().map(|category| category.price).sum()),
+                categories,
+            }
+        })
+        .collect();
+    let total = records
+        .iter()
+        .find(|record| record.category == TOTAL_PRICE_CATEGORY);
+    UsageReport {
+        start_date: query.start_date.clone(),
+        end_date: query.end_date.clone(),
+        currency: total
+            .or_else(|| records.first())
+            .and_then(|record| record.price_unit.clone()),
+        features,
+        total_price: round_price(total.map(|record| record.price).unwrap_or_default()),
+    }
+}
+
+/// Lists the usage records of a range, following their pages.
+pub async fn usage_records(
+    config: &TwilioConfig,
+    query: &UsageQuery,
+) -> Result<Vec<UsageRecord>, TwilioError> {
+    let mut request = HTTP_CLIENT
+        .get(format!(
+            "{}/2010-04-01/Accounts/{}/Usage/Records.json",
+            TWILIO_API_BASE_URL, config.account_sid
+        ))
+        .query(&query.twilio_params()?);
+    let mut records = Vec::new();
+    for _ in 0..MAX_PAGES {
+        let response = send_twilio_request(
+            request
+                .with_request_id()
+                .basic_auth(&config.account_sid, Some(&config.auth_token)),
+            "listing usage",
+        )
+        .await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            error!("[Twilio Usage] Listing usage returned {}: {}", status, body);
+            return Err(TwilioError::from_response(status, body));
+        }
+        let mut page: Value = serde_json::from_str(&body).map_err(|e| {
+            TwilioError::InternalError(format!("Failed to parse Twilio usage page: {}", e))
+        })?;
+        if let Some(items) = page.get_mut("usage_records").map(Value::take) {
+            let items: Vec<UsageRecord> = serde_json::from_value(items).map_err(|e| {
+                TwilioError::InternalError(format!("Failed to parse Twilio usage records: {}", e))
+            })?;
+            records.extend(items);
+        }
+        match page.get("next_page_uri").and_then(Value::as_str) {
+            Some(next_page_uri) => {
+                request = HTTP_CLIENT.get(format!("{}{}", TWILIO_API_BASE_URL, next_page_uri));
+            }
+            None => return Ok(records),
+        }
+    }
+    warn!(
+        "[Twilio Usage] Stopped listing usage after {} pages",
+        MAX_PAGES
+    );
+    Ok(records)
+}
+
+/// Admin handler reporting the Twilio usage and cost of a date range per feature.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/admin/twilio/usage", // Path relative to /api
+    params(UsageQuery),
+    responses(
+        (status = 200, description = "Usage and cost per feature", body = UsageReport),
+        (status = 400, description = "Invalid date range"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 502, description = "Twilio API error")
+    ),
+    tag = "Twilio"
+))]
+pub async fn usage_report_handler(
+    State(config): State<Arc<AppConfig>>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<UsageReport>, ConnectifyError> {
+    let twilio_config = twilio_config(&config)?;
+    let records = usage_records(twilio_config, &query).await?;
+    let report = aggregate_usage(&records, &query);
+    info!(
+        "[Twilio Usage] {} to {} cost {} {}",
+        report.start_date,
+        report.end_date,
+        report.total_price,
+        report.currency.as_deref().unwrap_or_default()
+    );
+    Ok(Json(report))
+}
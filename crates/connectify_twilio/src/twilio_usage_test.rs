@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use crate::service::TwilioError;
+    use crate::twilio_usage::{aggregate_usage, UsageQuery, UsageRecord};
+    use serde_json::json;
+
+    fn query(start_date: &str, end_date: &str) -> UsageQuery {
+        UsageQuery {
+            start_date: start_date.to_string(),
+            end_date: end_date.to_string(),
+        }
+    }
+
+    fn records() -> Vec<UsageRecord> {
+        let record = |category: &str, usage: &str, usage_unit: &str, price: &str| {
+            json!({
+                "category": category,
+                "description": category,
+                "count": usage,
+                "count_unit": usage_unit,
+                "usage": usage,
+                "usage_unit": usage_unit,
+                "price": price,
+                "price_unit": "usd",
+            })
+        };
+        serde_json::from_value(json!([
+            record("sms", "1240", "segments", "9.672"),
+            // Subcategories are part of their category's price
+            record("sms-outbound", "1200", "segments", "9.36"),
+            record("mms", "3", "messages", "0.06"),
+            record("calls", "42", "minutes", "0.588"),
+            record("group-rooms", "300", "participant-minutes", "1.2"),
+            record("phonenumbers", "1", "numbers", "1.15"),
+            record("totalprice", "0", "", "12.67"),
+            { "category": "conversations", "count": null, "usage": 0, "price": null },
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_usage_params() {
+        assert_eq!(
+            query("2025-07-01", "2025-07-31").twilio_params().unwrap(),
+            vec![
+                ("StartDate", "2025-07-01".to_string()),
+                ("EndDate", "2025-07-31".to_string()),
+                ("PageSize", "1000".to_string()),
+            ]
+        );
+        for invalid in [
+            query("2025-07-31", "2025-07-01"),
+            query("July", "2025-07-31"),
+        ] {
+            assert!(matches!(
+                invalid.twilio_params(),
+                Err(TwilioError::ValidationError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_aggregate_usage() {
+        let report = aggregate_usage(&records(), &query("2025-07-01", "2025-07-31"));
+        assert_eq!(report.currency.as_deref(), Some("usd"));
+        assert_eq!(report.total_price, 12.67);
+
+        let features: Vec<(&str, f64)> = report
+            .features
+            .iter()
+            .map(|feature| (feature.feature.as_str(), feature.price))
+            .collect();
+        assert_eq!(
+            features,
+            vec![
+                ("sms", 9.732),
+                ("voice", 0.588),
+                ("video", 1.2),
+                ("conversations", 0.0)
+            ]
+        );
+        let sms = &report.features[0];
+        let categories: Vec<&str> = sms.categories.iter().map(|c| c.category.as_str()).collect();
+        assert_eq!(categories, vec!["sms", "mms"]);
+        assert_eq!(sms.categories[0].usage, 1240.0);
+        assert_eq!(sms.categories[0].usage_unit.as_deref(), Some("segments"));
+    }
+}
@@ -0,0 +1,298 @@
+// --- File: crates/connectify_twilio/src/twilio_voice.rs ---
+//! Outbound voice calls through the Twilio Calls API, e.g. an appointment reminder read to the
+//! client by text-to-speech, and the status callbacks Twilio sends while a call progresses.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Json},
+    Form,
+};
+use connectify_common::audit::{self, AuditActor, AuditEvent};
+use connectify_common::request_id::RequestIdExt;
+use connectify_common::{ConnectifyError, HTTP_CLIENT};
+use connectify_config::{AppConfig, TwilioConfig};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::service::{send_twilio_request, twilio_config, TwilioError};
+
+/// Base URL of the Twilio REST API.
+const TWILIO_API_BASE_URL: &str = "https://api.twilio.com/2010-04-01";
+
+/// Path of the call status callback, relative to `status_callback_base_url`.
+pub const CALL_STATUS_CALLBACK_PATH: &str = "/twilio/calls/status";
+
+/// Call progress events reported to the status callback.
+const CALL_STATUS_EVENTS: [&str; 4] = ["initiated", "ringing", "answered", "completed"];
+
+/// Language of the text read in calls if neither the request nor the config sets one.
+const DEFAULT_VOICE_LANGUAGE: &str = "en-US";
+
+/// Statuses of calls that did not reach the callee.
+const UNSUCCESSFUL_CALL_STATUSES: [&str; 4] = ["busy", "failed", "no-answer", "canceled"];
+
+#[derive(Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct VoiceCallRequest {
+    /// Number to call, in E.164 format
+    #[cfg_attr(feature = "openapi", schema(example = "+41791234567"))]
+    pub to: String,
+    /// Text read to the callee by text-to-speech
+    #[cfg_attr(
+        feature = "openapi",
+        schema(example = "Reminder: your consultation starts tomorrow at 10:00.")
+    )]
+    pub message: Option<String>,
+    /// TwiML instructions of the call, instead of `message`
+    pub twiml: Option<String>,
+    /// Text-to-speech voice, overriding `voice` of the config
+    #[cfg_attr(feature = "openapi", schema(example = "Polly.Marlene"))]
+    pub voice: Option<String>,
+    /// Language of the message, overriding `voice_language` of the config
+    #[cfg_attr(feature = "openapi", schema(example = "de-DE"))]
+    pub language: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct VoiceCallResponse {
+    /// The Twilio call SID
+    #[cfg_attr(
+        feature = "openapi",
+        schema(example = "CA0123456789abcdef0123456789abcdef")
+    )]
+    pub call_sid: String,
+    /// `queued` until Twilio dials the number
+    #[cfg_attr(feature = "openapi", schema(example = "queued"))]
+    pub status: String,
+}
+
+/// A call as returned by the Calls API.
+#[derive(Deserialize, Debug)]
+struct TwilioCall {
+    sid: String,
+    status: String,
+}
+
+/// Form fields of a call status callback, see
+/// <https://www.twilio.com/docs/voice/api/call-resource#statuscallback>.
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "PascalCase")]
+pub struct CallStatusCallback {
+    pub call_sid: String,
+    /// e.g. `ringing`, `in-progress`, `completed`, `busy` or `no-answer`
+    pub call_status: String,
+    pub to: Option<String>,
+    pub from: Option<String>,
+    /// Seconds the call lasted, once completed
+    pub call_duration: Option<String>,
+    /// Order of the callbacks of a call, as they may arrive out of order
+    pub sequence_number: Option<String>,
+}
+
+/// Whether a number is in E.164 format, e.g. `+41791234567`.
+pub fn is_e164(number: &str) -> bool {
+    number.starts_with('+') && number.len() >= 8 && number[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+/// Escapes text for use in TwiML.
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// TwiML reading a message by text-to-speech.
+pub fn say_twiml(message: &str, voice: Option<&str>, language: &str) -> String {
+    let voice = voice
+        .map(|voice| format!(" voice=\"{}\"", escape_xml(voice)))
+        .unwrap_or_default();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Response><Say{} language=\"{}\">{}</Say></Response>",
+        voice,
+        escape_xml(language),
+        escape_xml(message)
+    )
+}
+
+/// The TwiML of a call: the request's own, or its message read by text-to-speech.
+fn call_twiml(config: &TwilioConfig, request: &VoiceCallRequest) -> Result<String, TwilioError> {
+    match (request.twiml.as_deref(), request.message.as_deref()) {
+        (Some(_), Some(_)) => Err(TwilioError::ValidationError(
+            "set either message or twiml, not both".to_string(),
+        )),
+        (Some(twiml), None) if !twiml.trim().is_empty() => Ok(twiml.to_string()),
+        (None, Some(message)) if !message.trim().is_empty() => Ok(say_twiml(
+            message,
+            request.voice.as_deref().or(config.voice.as_deref()),
+            request
+                .language
+                .as_deref()
+                .or(config.voice_language.as_deref())
+                .unwrap_or(DEFAULT_VOICE_LANGUAGE),
+        )),
+        _ => Err(TwilioError::ValidationError(
+            "message or twiml is required".to_string(),
+        )),
+    }
+}
+
+/// The form parameters creating a call.
+pub fn call_params(
+    config: &TwilioConfig,
+    request: &VoiceCallRequest,
+) -> Result<Vec<(&'static str, String)>, TwilioError> {
+    let to = request.to.trim();
+    if !is_e164(to) {
+        return Err(TwilioError::ValidationError(format!(
+            "to must be a phone number in E.164 format, got '{}'",
+            request.to
+        )));
+    }
+    let caller_id = config.caller_id.as_deref().ok_or_else(|| {
+        error!("[Twilio Voice] No caller_id configured, cannot place calls.");
+        TwilioError::ConfigError
+    })?;
+    let mut params = vec![
+        ("To", to.to_string()),
+        ("From", caller_id.to_string()),
+        ("Twiml", call_twiml(config, request)?),
+    ];
+    if let Some(base_url) = config.status_callback_base_url.as_deref() {
+        params.push((
+            "StatusCallback",
+            format!(
+                "{}{}",
+                base_url.trim_end_matches('/'),
+                CALL_STATUS_CALLBACK_PATH
+            ),
+        ));
+        params.extend(
+            CALL_STATUS_EVENTS
+                .iter()
+                .map(|event| ("StatusCallbackEvent", event.to_string())),
+        );
+    }
+    Ok(params)
+}
+
+/// Places a call through the Twilio Calls API.
+pub async fn place_call(
+    config: &TwilioConfig,
+    request: &VoiceCallRequest,
+) -> Result<VoiceCallResponse, TwilioError> {
+    let params = call_params(config, request)?;
+    let url = format!(
+        "{}/Accounts/{}/Calls.json",
+        TWILIO_API_BASE_URL, config.account_sid
+    );
+    info!("[Twilio Voice] Placing call to {}", request.to);
+    let response = send_twilio_request(
+        HTTP_CLIENT
+            .post(&url)
+            .with_request_id()
+            .basic_auth(&config.account_sid, Some(&config.auth_token))
+            .form(&params),
+        "placing call",
+    )
+    .await?;
+
+    let status = response.status();
+    let body = response.text().await?;
+    if !status.is_success() {
+        error!("[Twilio Voice] Twilio returned {}: {}", status, body);
+        return Err(TwilioError::from_response(status, body));
+    }
+    let call: TwilioCall = serde_json::from_str(&body)
+        .map_err(|e| TwilioError::InternalError(format!("Failed to parse call: {}", e)))?;
+    info!(
+        "[Twilio Voice] Call {} to {} is {}",
+        call.sid, request.to, call.status
+    );
+    Ok(VoiceCallResponse {
+        call_sid: call.sid,
+        status: call.status,
+    })
+}
+
+/// Admin handler placing a voice call, e.g. an appointment reminder.
+///
+/// Requires an API key with the `admin` scope, see `routes`.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/twilio/calls", // Path relative to /api
+    request_body = VoiceCallRequest,
+    responses(
+        (status = 200, description = "Call queued", body = VoiceCallResponse),
+        (status = 400, description = "Invalid number, or neither or both of message and twiml"),
+        (status = 401, description = "Missing or invalid admin API key"),
+        (status = 502, description = "Twilio API error"),
+        (status = 503, description = "Outgoing calls are disabled")
+    ),
+    tag = "Twilio"
+))]
+pub async fn place_call_handler(
+    State(config): State<Arc<AppConfig>>,
+    actor: AuditActor,
+    Json(request): Json<VoiceCallRequest>,
+) -> Result<Json<VoiceCallResponse>, ConnectifyError> {
+    let twilio_config = twilio_config(&config)?;
+
+    let result = place_call(twilio_config, &request).await;
+    audit::record(
+        AuditEvent::new(actor, "call.place", format!("phone:{}", request.to))
+            .with_metadata(
+                "call_sid",
+                result.as_ref().ok().map(|call| call.call_sid.clone()),
+            )
+            .with_result(&result),
+    )
+    .await;
+    Ok(Json(result?))
+}
+
+/// Handler of the status callbacks Twilio sends while a call progresses.
+#[axum::debug_handler]
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/twilio/calls/status", // Path relative to /api
+    request_body(content = CallStatusCallback, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 204, description = "Status received"),
+        (status = 401, description = "Missing or invalid X-Twilio-Signature")
+    ),
+    tag = "Twilio"
+))]
+pub async fn call_status_callback_handler(
+    Form(callback): Form<CallStatusCallback>,
+) -> impl IntoResponse {
+    if UNSUCCESSFUL_CALL_STATUSES.contains(&callback.call_status.as_str()) {
+        warn!(
+            "[Twilio Voice] Call {} to {} did not go through: {}",
+            callback.call_sid,
+            callback.to.as_deref().unwrap_or("unknown"),
+            callback.call_status
+        );
+    } else {
+        info!(
+            "[Twilio Voice] Call {} is {} (duration: {}s)",
+            callback.call_sid,
+            callback.call_status,
+            callback.call_duration.as_deref().unwrap_or("0")
+        );
+    }
+    StatusCode::NO_CONTENT
+}
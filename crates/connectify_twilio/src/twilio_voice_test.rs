@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use crate::service::TwilioError;
+    use crate::test_support;
+    use crate::twilio_voice::{call_params, say_twiml, VoiceCallRequest};
+    use connectify_config::TwilioConfig;
+
+    fn twilio_config() -> TwilioConfig {
+        TwilioConfig {
+            caller_id: Some("+41440000000".to_string()),
+            status_callback_base_url: Some("https://connectify.example.com/api/".to_string()),
+            voice: Some("Polly.Marlene".to_string()),
+            voice_language: Some("de-DE".to_string()),
+            ..test_support::twilio_config()
+        }
+    }
+
+    fn reminder(to: &str) -> VoiceCallRequest {
+        VoiceCallRequest {
+            to: to.to_string(),
+            message: Some("Beratung morgen um 10:00 <Raum 3> & Kaffee".to_string()),
+            twiml: None,
+            voice: None,
+            language: None,
+        }
+    }
+
+    #[test]
+    fn test_say_twiml_escapes_message() {
+        let twiml = say_twiml("Tom & \"Jerry\" <3", None, "en-US");
+        assert!(twiml.contains(
+            "<Say language=\"en-US\">Tom &amp; &quot;Jerry&quot; &lt;3</Say></Response>"
+        ));
+    }
+
+    #[test]
+    fn test_call_params() {
+        let params = call_params(&twilio_config(), &reminder("+41791234567")).unwrap();
+        let value = |key: &str| {
+            params
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(value("To"), Some("+41791234567"));
+        assert_eq!(value("From"), Some("+41440000000"));
+        assert!(value("Twiml").unwrap().contains(
+            "<Say voice=\"Polly.Marlene\" language=\"de-DE\">Beratung morgen um 10:00 &lt;Raum 3&gt; &amp; Kaffee</Say>"
+        ));
+        assert_eq!(
+            value("StatusCallback"),
+            Some("https://connectify.example.com/api/twilio/calls/status")
+        );
+        assert_eq!(
+            params
+                .iter()
+                .filter(|(name, _)| *name == "StatusCallbackEvent")
+                .count(),
+            4
+        );
+
+        // No status callbacks without a public base URL
+        let config = TwilioConfig {
+            status_callback_base_url: None,
+            ..twilio_config()
+        };
+        let params = call_params(&config, &reminder("+41791234567")).unwrap();
+        assert!(!params
+            .iter()
+            .any(|(name, _)| name.starts_with("StatusCallback")));
+    }
+
+    #[test]
+    fn test_call_params_validation() {
+        let config = twilio_config();
+        assert!(matches!(
+            call_params(&config, &reminder("079 123 45 67")),
+            Err(TwilioError::ValidationError(_))
+        ));
+        let both = VoiceCallRequest {
+            twiml: Some("<Response><Hangup/></Response>".to_string()),
+            ..reminder("+41791234567")
+        };
+        assert!(matches!(
+            call_params(&config, &both),
+            Err(TwilioError::ValidationError(_))
+        ));
+        let neither = VoiceCallRequest {
+            message: Some("  ".to_string()),
+            ..reminder("+41791234567")
+        };
+        assert!(matches!(
+            call_params(&config, &neither),
+            Err(TwilioError::ValidationError(_))
+        ));
+        let config = TwilioConfig {
+            caller_id: None,
+            ..twilio_config()
+        };
+        assert!(matches!(
+            call_params(&config, &reminder("+41791234567")),
+            Err(TwilioError::ConfigError)
+        ));
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests {
+    use crate::test_support;
+    use crate::twilio_webhook::{
+        twilio_signature, webhook_url, TwilioWebhookVerifier, TWILIO_SIGNATURE_HEADER,
+    };
+    use axum::http::{HeaderMap, Uri};
+    use connectify_common::webhook::{WebhookError, WebhookVerifier};
+    use connectify_config::TwilioConfig;
+
+    const URL: &str = "https://mycompany.com/myapp.php?foo=1&bar=2";
+    const BODY: &str =
+        "To=%2B18005551212&From=%2B14158675309&Caller=%2B14158675309&Digits=1234&CallSid=CA1234567890ABCDE";
+
+    fn twilio_config(base_url: Option<&str>) -> TwilioConfig {
+        TwilioConfig {
+            auth_token: "12345".to_string(),
+            status_callback_base_url: base_url.map(str::to_string),
+            ..test_support::twilio_config()
+        }
+    }
+
+    fn signed(signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(TWILIO_SIGNATURE_HEADER, signature.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_twilio_signature() {
+        // Example of https://www.twilio.com/docs/usage/security#validating-requests
+        let params: Vec<(String, String)> = serde_urlencoded::from_str(BODY).unwrap();
+        assert_eq!(
+            twilio_signature("12345", URL, &params),
+            "RSOYDt4T1cUTdK1PDd93/VVr8B8="
+        );
+    }
+
+    #[test]
+    fn test_verifier() {
+        let verifier = TwilioWebhookVerifier::new("12345", URL);
+        let event = verifier
+            .verify(BODY.as_bytes(), &signed("RSOYDt4T1cUTdK1PDd93/VVr8B8="))
+            .unwrap();
+        assert_eq!(event.provider, "twilio");
+
+        // A changed parameter, a different URL or token, or no signature is rejected
+        let tampered = BODY.replace("Digits=1234", "Digits=1235");
+        assert!(matches!(
+            verifier.verify(tampered.as_bytes(), &signed("RSOYDt4T1cUTdK1PDd93/VVr8B8=")),
+            Err(WebhookError::InvalidSignature(_))
+        ));
+        assert!(matches!(
+            TwilioWebhookVerifier::new("12345", "https://mycompany.com/myapp.php")
+                .verify(BODY.as_bytes(), &signed("RSOYDt4T1cUTdK1PDd93/VVr8B8=")),
+            Err(WebhookError::InvalidSignature(_))
+        ));
+        assert!(matches!(
+            verifier.verify(BODY.as_bytes(), &HeaderMap::new()),
+            Err(WebhookError::MissingSignature(_))
+        ));
+        assert!(matches!(
+            TwilioWebhookVerifier::new("", URL).verify(BODY.as_bytes(), &HeaderMap::new()),
+            Err(WebhookError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_webhook_url() {
+        let config = twilio_config(Some("https://connectify.example.com/api/"));
+        let uri: Uri = "/twilio/messages/status?attempt=2".parse().unwrap();
+        assert_eq!(
+            webhook_url(&config, &uri).unwrap(),
+            "https://connectify.example.com/api/twilio/messages/status?attempt=2"
+        );
+        assert!(matches!(
+            webhook_url(&twilio_config(None), &uri),
+            Err(WebhookError::Config(_))
+        ));
+    }
+}
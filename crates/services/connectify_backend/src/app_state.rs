@@ -1,4 +1,5 @@
 // --- File: crates/services/connectify_backend/src/app_state.rs ---
+use connectify_common::clock::{system_clock, SharedClock};
 use connectify_common::services::ServiceFactory;
 use connectify_config::AppConfig;
 use std::sync::Arc;
@@ -58,6 +59,9 @@ pub struct AppState {
     #[allow(dead_code)]
     pub service_factory: Arc<dyn ServiceFactory>,
 
+    /// Clock the handlers and scheduled jobs read the current time from.
+    pub clock: SharedClock,
+
     /// Google Calendar state, available when the "gcal" feature is enabled.
     /// This is kept for backward compatibility during transition to the new architecture.
     #[cfg(feature = "gcal")]
@@ -86,6 +90,7 @@ pub struct AppState {
 pub struct AppStateBuilder {
     config: Arc<AppConfig>,
     service_factory: Option<Arc<dyn ServiceFactory>>,
+    clock: SharedClock,
 
     #[cfg(feature = "gcal")]
     gcal_state: Option<Arc<GcalState>>,
@@ -98,6 +103,7 @@ impl AppStateBuilder {
         Self {
             config,
             service_factory: None,
+            clock: system_clock(),
             #[cfg(feature = "gcal")]
             gcal_state: None,
         }
@@ -110,6 +116,13 @@ impl AppStateBuilder {
         self
     }
 
+    /// Set the clock, e.g. a mock clock in tests.
+    #[allow(dead_code)]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Set the Google Calendar state.
     #[cfg(feature = "gcal")]
     #[allow(dead_code)]
@@ -129,6 +142,7 @@ impl AppStateBuilder {
         AppState {
             config: self.config,
             service_factory: self.service_factory.unwrap(),
+            clock: self.clock,
             #[cfg(feature = "gcal")]
             gcal_state: self.gcal_state,
         }
@@ -162,19 +176,23 @@ impl AppState {
     /// This is a convenience method that creates a service factory and builds the AppState.
     pub async fn new(config: Arc<AppConfig>) -> Self {
         let service_factory = Arc::new(ConnectifyServiceFactory::new(config.clone()).await);
+        let clock = system_clock();
 
         #[cfg(feature = "gcal")]
-        let gcal_state = if config.use_gcal && config.gcal.is_some() {
+        let gcal_state = if let Some(gcal_config) = config.gcal.as_ref().filter(|_| config.use_gcal)
+        {
             // For backward compatibility, create GcalState if needed
             if let Some(_calendar_service) = service_factory.calendar_service() {
                 // We don't have direct access to the hub, but we can create it again
                 // This is not ideal but necessary during the transition
-                match connectify_gcal::auth::create_calendar_hub(config.gcal.as_ref().unwrap())
-                    .await
-                {
+                match connectify_gcal::auth::create_calendar_hub(gcal_config).await {
                     Ok(hub) => Some(Arc::new(GcalState {
                         config: config.clone(),
                         calendar_hub: Arc::new(hub),
+                        clock: clock.clone(),
+                        blackouts: Arc::new(connectify_gcal::blackout::BlackoutStore::from_config(
+                            gcal_config,
+                        )),
                     })),
                     Err(_) => None,
                 }
@@ -188,6 +206,7 @@ impl AppState {
         Self {
             config,
             service_factory,
+            clock,
             #[cfg(feature = "gcal")]
             gcal_state,
         }
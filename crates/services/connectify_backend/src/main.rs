@@ -1,8 +1,16 @@
 // File: services/connectify_backend/src/main.rs
-use axum::{routing::get, Router};
+use axum::{routing::get, Extension, Router};
+use connectify_common::holds::slot_hold_store;
+use connectify_common::http::circuit_breaker::configure_circuit_breakers;
+use connectify_common::idempotency::idempotency_store;
+use connectify_common::runtime_flags::{runtime_flags, ConfigFlagSource, RuntimeFlagSource};
+use connectify_common::scheduler::Scheduler;
 #[allow(unused_imports)]
-use connectify_common::{is_feature_enabled, logging};
+use connectify_common::{
+    is_feature_enabled, jwt::JwtVerifier, logging, metrics, rate_limit::RateLimitLayer, request_id,
+};
 use connectify_config::load_config;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tower_http::services::ServeDir;
@@ -26,11 +34,253 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let config = Arc::new(load_config().expect("Failed to load config"));
     info!("✅ Configuration loaded.");
 
+    // Apply the configured thresholds to the outbound HTTP circuit breakers
+    if let Some(circuit_breaker) = config.circuit_breaker.as_ref() {
+        configure_circuit_breakers(circuit_breaker);
+    }
+
+    // Cache in memory, or in Redis if configured and compiled in
+    if let Some(cache_config) = config.cache.as_ref() {
+        use connectify_common::cache::{configure_cache, MemoryCache, DEFAULT_MAX_ENTRIES};
+
+        configure_cache(Arc::new(MemoryCache::new(
+            cache_config.max_entries.unwrap_or(DEFAULT_MAX_ENTRIES),
+        )));
+        if let Some(redis_url) = cache_config.redis_url.as_deref() {
+            #[cfg(feature = "redis")]
+            match connectify_common::cache::RedisCache::connect(redis_url).await {
+                Ok(redis_cache) => {
+                    info!("✅ Cache stored in Redis.");
+                    configure_cache(Arc::new(redis_cache));
+                }
+                Err(e) => warn!("⚠️ Cache kept in memory: {}", e),
+            }
+            #[cfg(not(feature = "redis"))]
+            {
+                let _ = redis_url;
+                warn!("⚠️ cache.redis_url is set, but Redis support is not compiled in. Cache kept in memory.");
+            }
+        }
+    }
+
+    // Share locks between instances through Redis or Postgres if configured
+    let lock_backend = config
+        .lock
+        .as_ref()
+        .and_then(|lock| lock.backend.as_deref())
+        .unwrap_or("memory");
+    if lock_backend == "redis" {
+        let redis_url = config
+            .lock
+            .as_ref()
+            .and_then(|lock| lock.redis_url.as_deref())
+            .or_else(|| config.cache.as_ref()?.redis_url.as_deref());
+        #[cfg(feature = "redis")]
+        match redis_url {
+            Some(redis_url) => match connectify_common::lock::RedisLock::connect(redis_url).await {
+                Ok(lock) => {
+                    info!("✅ Locks shared through Redis.");
+                    connectify_common::lock::configure_distributed_lock(Arc::new(lock));
+                }
+                Err(e) => warn!("⚠️ Locks kept in memory: {}", e),
+            },
+            None => warn!(
+                "⚠️ lock.backend is redis, but no Redis URL is configured. Locks kept in memory."
+            ),
+        }
+        #[cfg(not(feature = "redis"))]
+        {
+            let _ = redis_url;
+            warn!("⚠️ lock.backend is redis, but Redis support is not compiled in. Locks kept in memory.");
+        }
+    }
+
+    // Queue background work in Redis Streams or SQS if configured
+    if let Some(queue_config) = config.queue.as_ref() {
+        use connectify_common::queue::{configure_message_queue, DEFAULT_VISIBILITY_TIMEOUT};
+
+        let visibility_timeout = queue_config
+            .visibility_timeout_seconds
+            .map(std::time::Duration::from_secs)
+            .unwrap_or(DEFAULT_VISIBILITY_TIMEOUT);
+        match queue_config.backend.as_deref().unwrap_or("memory") {
+            "redis" => {
+                let redis_url = queue_config
+                    .redis_url
+                    .as_deref()
+                    .or_else(|| config.cache.as_ref()?.redis_url.as_deref());
+                #[cfg(feature = "redis")]
+                match redis_url {
+                    Some(redis_url) => {
+                        match connectify_common::queue::RedisQueue::connect(
+                            redis_url,
+                            visibility_timeout,
+                        )
+                        .await
+                        {
+                            Ok(queue) => {
+                                info!("✅ Message queue in Redis Streams.");
+                                configure_message_queue(Arc::new(queue));
+                            }
+                            Err(e) => warn!("⚠️ Message queue kept in memory: {}", e),
+                        }
+                    }
+                    None => warn!("⚠️ queue.backend is redis, but no Redis URL is configured. Message queue kept in memory."),
+                }
+                #[cfg(not(feature = "redis"))]
+                {
+                    let _ = redis_url;
+                    warn!("⚠️ queue.backend is redis, but Redis support is not compiled in. Message queue kept in memory.");
+                }
+            }
+            "sqs" => {
+                use connectify_common::queue::{AwsCredentials, SqsQueue};
+
+                match (
+                    queue_config.sqs_region.as_deref(),
+                    queue_config.sqs_queue_url_prefix.as_deref(),
+                    AwsCredentials::from_env(),
+                ) {
+                    (Some(region), Some(prefix), Ok(credentials)) => {
+                        let mut queue =
+                            SqsQueue::new(region, prefix, credentials, visibility_timeout);
+                        if let Some(endpoint) = queue_config.sqs_endpoint.as_deref() {
+                            queue = queue.with_endpoint(endpoint);
+                        }
+                        info!("✅ Message queue in SQS ({}).", region);
+                        configure_message_queue(Arc::new(queue));
+                    }
+                    (_, _, Err(e)) => warn!("⚠️ Message queue kept in memory: {}", e),
+                    _ => warn!("⚠️ queue.backend is sqs, but sqs_region or sqs_queue_url_prefix is missing. Message queue kept in memory."),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Runtime kill switches are read from the config and, if available, the database
+    if let Some(flags) = config.runtime_flags.as_ref() {
+        runtime_flags().replace(flags.flags.clone());
+    }
+    #[allow(unused_mut)]
+    let mut flag_sources: Vec<Arc<dyn RuntimeFlagSource>> = vec![Arc::new(ConfigFlagSource)];
+
+    // Persist idempotency keys and audit events in the database, so they survive restarts
+    #[cfg(feature = "database")]
+    if config.database.is_some() {
+        use connectify_common::audit::add_audit_sink;
+        use connectify_common::booking_ledger::configure_booking_ledger;
+        use connectify_common::dead_letters::configure_dead_letter_store;
+        use connectify_common::event_mirror::configure_event_mirror;
+        use connectify_common::holds::configure_slot_hold_store;
+        use connectify_common::idempotency::configure_idempotency_store;
+        use connectify_common::message_status::configure_message_status_store;
+        use connectify_common::oauth_tokens::configure_oauth_token_store;
+        use connectify_common::orders::configure_fulfillment_order_store;
+        use connectify_common::payments::{
+            configure_payment_customer_store, configure_payment_record_store,
+        };
+        use connectify_common::schedule_exceptions::configure_schedule_exception_store;
+        use connectify_common::sms_consent::configure_sms_consent_store;
+        use connectify_common::webhook_events::configure_webhook_event_store;
+        use connectify_db::{
+            DbClient, SqlAdvisoryLock, SqlAuditRepository, SqlBookingRepository,
+            SqlDeadLetterRepository, SqlEventMirrorRepository, SqlFulfillmentOrderRepository,
+            SqlIdempotencyRepository, SqlMessageStatusRepository, SqlOAuthTokenRepository,
+            SqlPaymentRepository, SqlRuntimeFlagRepository, SqlScheduleExceptionRepository,
+            SqlSlotHoldRepository, SqlSmsConsentRepository, SqlWebhookEventRepository,
+        };
+
+        let db_client = DbClient::new(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to the database: {}", e))?;
+
+        let repository = SqlIdempotencyRepository::new(db_client.clone());
+        init_store("Idempotency keys", repository.init_schema()).await?;
+        configure_idempotency_store(Arc::new(repository));
+
+        let hold_repository = SqlSlotHoldRepository::new(db_client.clone());
+        init_store("Slot holds", hold_repository.init_schema()).await?;
+        configure_slot_hold_store(Arc::new(hold_repository));
+
+        let booking_repository = SqlBookingRepository::new(db_client.clone());
+        init_store("Seats of group slots", booking_repository.init_schema()).await?;
+        configure_booking_ledger(Arc::new(booking_repository));
+
+        let token_repository = SqlOAuthTokenRepository::new(db_client.clone());
+        init_store("OAuth refresh tokens", token_repository.init_schema()).await?;
+        configure_oauth_token_store(Arc::new(token_repository));
+
+        let mirror_repository = SqlEventMirrorRepository::new(db_client.clone());
+        init_store("Mirrored calendar events", mirror_repository.init_schema()).await?;
+        configure_event_mirror(Arc::new(mirror_repository));
+
+        let exception_repository = SqlScheduleExceptionRepository::new(db_client.clone());
+        init_store("Schedule exceptions", exception_repository.init_schema()).await?;
+        configure_schedule_exception_store(Arc::new(exception_repository));
+
+        let webhook_repository = SqlWebhookEventRepository::new(db_client.clone());
+        init_store("Webhook events", webhook_repository.init_schema()).await?;
+        configure_webhook_event_store(Arc::new(webhook_repository));
+
+        let dead_letter_repository = SqlDeadLetterRepository::new(db_client.clone());
+        init_store("Failed fulfillments", dead_letter_repository.init_schema()).await?;
+        configure_dead_letter_store(Arc::new(dead_letter_repository));
+
+        let order_repository = SqlFulfillmentOrderRepository::new(db_client.clone());
+        init_store("Fulfillment orders", order_repository.init_schema()).await?;
+        configure_fulfillment_order_store(Arc::new(order_repository));
+
+        let payment_repository = Arc::new(SqlPaymentRepository::new(db_client.clone()));
+        init_store(
+            "Payment customers and payments",
+            payment_repository.init_schema(),
+        )
+        .await?;
+        configure_payment_customer_store(payment_repository.clone());
+        configure_payment_record_store(payment_repository);
+
+        let message_status_repository = SqlMessageStatusRepository::new(db_client.clone());
+        init_store(
+            "SMS delivery statuses",
+            message_status_repository.init_schema(),
+        )
+        .await?;
+        configure_message_status_store(Arc::new(message_status_repository));
+
+        let sms_consent_repository = SqlSmsConsentRepository::new(db_client.clone());
+        init_store(
+            "Received SMS and opt-outs",
+            sms_consent_repository.init_schema(),
+        )
+        .await?;
+        configure_sms_consent_store(Arc::new(sms_consent_repository));
+
+        if lock_backend == "postgres" {
+            info!("✅ Locks shared through Postgres advisory locks.");
+            connectify_common::lock::configure_distributed_lock(Arc::new(SqlAdvisoryLock::new(
+                db_client.clone(),
+            )));
+        }
+
+        let flag_repository = SqlRuntimeFlagRepository::new(db_client.clone());
+        init_store("Runtime flags", flag_repository.init_schema()).await?;
+        flag_sources.push(Arc::new(flag_repository));
+
+        let audit_repository = SqlAuditRepository::new(db_client);
+        init_store("Audit events", audit_repository.init_schema()).await?;
+        add_audit_sink(Arc::new(audit_repository));
+    }
+
     // Create the AppState with the config
     // This will initialize all services based on the configuration
     #[allow(unused_variables)]
     let app_state = AppState::new(config.clone()).await;
-    #[allow(unused_mut)]
+    let _refund_notifications =
+        connectify_backend::notifications::notify_refunds(app_state.service_factory.clone());
+    // Send queued SMS at the sender's throughput
+    #[cfg(feature = "twilio")]
+    let _sms_worker = connectify_twilio::twilio_sms_queue::spawn_sms_worker(config.clone());
     let mut api_router =
         Router::new().route("/api", get(|| async { "Welcome to Connectify-Rs API!" }));
 
@@ -134,6 +384,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    // Apply the global per-client rate limit to all API routes
+    api_router = api_router.layer(RateLimitLayer::from_config(&config));
+
+    // Make the JWT verifier available to the AuthClaims extractor
+    if let Some(jwt_config) = config.jwt.as_ref() {
+        match JwtVerifier::from_config(jwt_config) {
+            Ok(verifier) => {
+                info!("🔐 JWT authentication enabled.");
+                api_router = api_router.layer(Extension(Arc::new(verifier)));
+            }
+            Err(e) => warn!("⚠️ JWT authentication disabled: {}", e),
+        }
+    }
+
     // --- Create Main App Router ---
     // Nest all API routes under /api
     let mut app = Router::new().nest("/api", api_router);
@@ -211,16 +475,218 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         app = app.fallback_service(ServeDir::new("../dist"));
     }
 
+    // Expose Prometheus metrics and record every request handled by the app
+    app = app
+        .merge(metrics::metrics_router())
+        .layer(axum::middleware::from_fn(metrics::track_http_metrics));
+
+    // Assign an X-Request-Id to every request and attach it to its logs and outgoing calls
+    app = app.layer(axum::middleware::from_fn(request_id::propagate_request_id));
+
+    // Run periodic maintenance jobs in the background, reading the time from the shared clock
+    let clock = app_state.clock.clone();
+    let hold_clock = clock.clone();
+    let scheduler = Scheduler::from_config(&config.scheduler.clone().unwrap_or_default())?
+        .every("0 3 * * *", "idempotency_cleanup", || async {
+            let purged = idempotency_store().purge_expired().await?;
+            info!("Purged {} expired idempotency keys", purged);
+            Ok(())
+        })?
+        .every("*/10 * * * *", "slot_hold_cleanup", move || {
+            let now = hold_clock.now();
+            async move {
+                let purged = slot_hold_store().purge_expired(now).await?;
+                info!("Purged {} expired slot holds", purged);
+                Ok(())
+            }
+        })?
+        .every("* * * * *", "runtime_flags_refresh", move || {
+            let flag_sources = flag_sources.clone();
+            async move { runtime_flags().refresh(&flag_sources).await }
+        })?;
+
+    // Watch the calendars so push notifications keep cached availability fresh
+    #[cfg(feature = "gcal")]
+    let scheduler = match (
+        app_state.gcal_state.clone(),
+        std::env::var(connectify_gcal::handlers::PUSH_CHANNEL_TOKEN_ENV),
+    ) {
+        (Some(gcal_state), Ok(token))
+            if gcal_state
+                .config
+                .gcal
+                .as_ref()
+                .is_some_and(|gcal| gcal.push_notification_url.is_some()) =>
+        {
+            let watch_calendars = move || {
+                let gcal_state = gcal_state.clone();
+                let token = token.clone();
+                async move {
+                    if let Some(gcal_config) = gcal_state.config.gcal.as_ref() {
+                        let watched = connectify_gcal::logic::watch_configured_calendars(
+                            &gcal_state.calendar_hub,
+                            gcal_config,
+                            &token,
+                        )
+                        .await?;
+                        info!("Watching {} calendars for changes", watched);
+                    }
+                    Ok::<(), connectify_common::ConnectifyError>(())
+                }
+            };
+            let initial_watch = watch_calendars();
+            tokio::spawn(async move {
+                if let Err(e) = initial_watch.await {
+                    warn!("Failed to watch calendars for changes: {}", e);
+                }
+            });
+            scheduler.every("0 4 * * *", "gcal_watch_renewal", watch_calendars)?
+        }
+        _ => scheduler,
+    };
+
+    // Mirror the calendars' events, so conflict checks keep working while Google is down
+    #[cfg(feature = "gcal")]
+    let scheduler = match app_state.gcal_state.clone() {
+        Some(gcal_state)
+            if gcal_state
+                .config
+                .gcal
+                .as_ref()
+                .is_some_and(|gcal| gcal.mirror_events) =>
+        {
+            let sync_calendars = move || {
+                let gcal_state = gcal_state.clone();
+                async move {
+                    if let Some(gcal_config) = gcal_state.config.gcal.as_ref() {
+                        let changed = connectify_gcal::mirror::sync_configured_calendars(
+                            &gcal_state.calendar_hub,
+                            gcal_config,
+                            gcal_state.clock.now(),
+                        )
+                        .await?;
+                        info!("Mirrored {} changed calendar events", changed);
+                    }
+                    Ok::<(), connectify_common::ConnectifyError>(())
+                }
+            };
+            let initial_sync = sync_calendars();
+            tokio::spawn(async move {
+                if let Err(e) = initial_sync.await {
+                    warn!("Failed to mirror calendar events: {}", e);
+                }
+            });
+            scheduler.every(
+                connectify_gcal::mirror::MIRROR_SYNC_SCHEDULE,
+                "gcal_event_mirror_sync",
+                sync_calendars,
+            )?
+        }
+        _ => scheduler,
+    };
+    // Expire stale checkouts and release their slot holds if the webhook was missed
+    #[cfg(feature = "stripe")]
+    let scheduler = if is_feature_enabled(&config, config.use_stripe, config.stripe.as_ref()) {
+        let reconcile_clock = clock.clone();
+        scheduler.every("*/15 * * * *", "checkout_session_reconcile", move || {
+            let now = reconcile_clock.now();
+            async move {
+                let client = connectify_stripe::client::StripeClient::from_env()?;
+                let reconciled =
+                    connectify_stripe::logic::reconcile_checkout_sessions(&client, now).await?;
+                info!(
+                    "Expired {} stale checkout sessions, released {} slot holds",
+                    reconciled.expired_sessions, reconciled.released_holds
+                );
+                Ok(())
+            }
+        })?
+    } else {
+        scheduler
+    };
+    // Retry fulfillments whose call failed when their webhook arrived
+    #[cfg(feature = "stripe")]
+    let scheduler = if is_feature_enabled(&config, config.use_stripe, config.stripe.as_ref()) {
+        let retry_config = config.clone();
+        let retry_clock = clock.clone();
+        scheduler.every("*/5 * * * *", "fulfillment_dead_letter_retry", move || {
+            let retry_config = retry_config.clone();
+            let now = retry_clock.now();
+            async move {
+                let retries =
+                    connectify_stripe::logic::retry_failed_fulfillments(&retry_config, now).await?;
+                info!(
+                    "Retried failed fulfillments: {} delivered, {} failed, {} exhausted",
+                    retries.delivered, retries.failed, retries.exhausted
+                );
+                Ok(())
+            }
+        })?
+    } else {
+        scheduler
+    };
+    // Delete video recordings once their retention period is over
+    #[cfg(feature = "twilio")]
+    let scheduler = match config
+        .twilio
+        .clone()
+        .filter(|_| config.use_twilio)
+        .and_then(|twilio_config| Some((twilio_config.recording_retention_days?, twilio_config)))
+    {
+        Some((retention_days, twilio_config)) => {
+            let retention_clock = clock.clone();
+            scheduler.every("30 3 * * *", "twilio_recording_retention", move || {
+                let twilio_config = twilio_config.clone();
+                let now = retention_clock.now();
+                async move {
+                    let purged = connectify_twilio::twilio_recordings::purge_expired_recordings(
+                        &twilio_config,
+                        retention_days,
+                        now,
+                    )
+                    .await?;
+                    info!(
+                        "Deleted {} expired recordings and {} compositions",
+                        purged.recordings, purged.compositions
+                    );
+                    Ok(())
+                }
+            })?
+        }
+        None => scheduler,
+    };
+    let _scheduler = scheduler.start();
+
     // 6. Bind and serve
     let addr = format!("{}:{}", config.server.host, config.server.port);
     let listener = TcpListener::bind(&addr).await.unwrap();
     info!("Starting server at http://{}", addr);
     info!("API endpoints available at http://{}/api", addr);
+    info!("Prometheus metrics available at http://{}/metrics", addr);
     #[cfg(feature = "openapi")]
     info!("Swagger UI available at http://{}/admin/api/docs", addr);
     #[cfg(feature = "database")]
     info!("Database feature available");
 
-    axum::serve(listener, app.into_make_service()).await?;
+    // Client addresses are needed to rate limit requests per IP
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Creates the tables of a database-backed store. A configured database that cannot hold the
+/// store fails startup, rather than silently keeping the data in memory.
+#[cfg(feature = "database")]
+async fn init_store(
+    store: &str,
+    schema: impl std::future::Future<Output = Result<(), connectify_db::error::DbError>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    schema
+        .await
+        .map_err(|e| format!("Failed to set up the database for {}: {}", store, e))?;
+    info!("✅ {} stored in the database.", store);
     Ok(())
 }
@@ -0,0 +1,148 @@
+// --- File: crates/services/connectify_backend/src/notifications.rs ---
+//! Notifications of clients about their payments.
+//!
+//! Subscribes to the event bus and informs clients through the registered services: by SMS
+//! through the notification service (Twilio) and by push notification on the devices they
+//! registered with Firebase.
+use connectify_common::events::{NotificationFailed, PaymentRefunded, EVENT_BUS};
+use connectify_common::services::ServiceFactory;
+use connectify_common::{external_service_error, ConnectifyError};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+#[allow(unused_imports)]
+use tracing::{info, warn};
+
+#[cfg(feature = "firebase")]
+use {
+    connectify_firebase::client::{AndroidOptions, ApnsOptions, Notification},
+    connectify_firebase::FirebaseServiceFactory,
+};
+
+/// Notify clients when one of their payments is refunded.
+pub fn notify_refunds(services: Arc<dyn ServiceFactory>) -> JoinHandle<()> {
+    EVENT_BUS.on::<PaymentRefunded, _, _>("refund_notification", move |refund| {
+        let services = services.clone();
+        async move { send_refund_notifications(&*services, &refund).await }
+    })
+}
+
+/// The text telling a client about a refund, e.g. "CHF 25.00 of your payment were refunded."
+pub fn refund_message(refund: &PaymentRefunded) -> String {
+    let amount = format!(
+        "{} {}.{:02}",
+        refund.currency.to_uppercase(),
+        refund.amount / 100,
+        refund.amount % 100
+    );
+    match refund.reason.as_deref() {
+        Some("booking_cancelled") => format!(
+            "Your booking was cancelled and {} of your payment were refunded.",
+            amount
+        ),
+        _ => format!("{} of your payment were refunded.", amount),
+    }
+}
+
+async fn send_refund_notifications(
+    services: &dyn ServiceFactory,
+    refund: &PaymentRefunded,
+) -> Result<(), ConnectifyError> {
+    let message = refund_message(refund);
+    let mut failed = false;
+
+    match (&refund.customer_phone, services.notification_service()) {
+        (Some(phone), Some(sms)) => {
+            if let Err(e) = sms.send_sms(phone, &message).await {
+                connectify_common::events::publish(NotificationFailed {
+                    channel: "twilio_sms".to_string(),
+                    recipient: phone.clone(),
+                    error: e.to_string(),
+                });
+                failed = true;
+            } else {
+                info!("Sent refund SMS for refund {}", refund.refund_id);
+            }
+        }
+        (Some(_), None) => warn!(
+            "No notification service to send the SMS of refund {}",
+            refund.refund_id
+        ),
+        (None, _) => {}
+    }
+
+    #[cfg(feature = "firebase")]
+    if let (Some(user_id), Some(firebase)) = (
+        &refund.customer_user_id,
+        services.registry().get::<FirebaseServiceFactory>(),
+    ) {
+        let notification = Notification {
+            title: "Refund".to_string(),
+            body: message.clone(),
+        };
+        let data = std::collections::HashMap::from([
+            ("type".to_string(), "payment_refunded".to_string()),
+            ("refund_id".to_string(), refund.refund_id.clone()),
+            ("payment_id".to_string(), refund.payment_id.clone()),
+        ]);
+        let apns = ApnsOptions {
+            sound: Some("default".to_string()),
+            ..Default::default()
+        };
+        // A later notification of the same refund replaces an undelivered one
+        let android = AndroidOptions {
+            collapse_key: Some(format!("refund_{}", refund.refund_id)),
+            ..Default::default()
+        };
+        // Failures of single devices are published by the client
+        if let Err(e) = firebase
+            .client()
+            .send_notification_to_user(user_id, notification, Some(data), Some(apns), Some(android))
+            .await
+        {
+            warn!(
+                "Failed to send the push notification of refund {}: {}",
+                refund.refund_id, e
+            );
+            failed = true;
+        }
+    }
+
+    if failed {
+        return Err(external_service_error(
+            "Notifications",
+            format!("Client not notified of refund {}", refund.refund_id),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn refund(amount: i64, reason: Option<&str>) -> PaymentRefunded {
+        PaymentRefunded {
+            provider: "stripe".to_string(),
+            payment_id: "pi_1".to_string(),
+            refund_id: "re_1".to_string(),
+            amount,
+            currency: "chf".to_string(),
+            reason: reason.map(String::from),
+            booking_id: None,
+            customer_phone: None,
+            customer_user_id: None,
+        }
+    }
+
+    #[test]
+    fn test_refund_message() {
+        assert_eq!(
+            refund_message(&refund(2505, None)),
+            "CHF 25.05 of your payment were refunded."
+        );
+        assert_eq!(
+            refund_message(&refund(10000, Some("booking_cancelled"))),
+            "Your booking was cancelled and CHF 100.00 of your payment were refunded."
+        );
+    }
+}